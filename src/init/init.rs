@@ -6,6 +6,7 @@ use qos_core::{
     EPHEMERAL_KEY_FILE,
     MANIFEST_FILE,
     PIVOT_FILE,
+    PIVOT_INFO_FILE,
     QUORUM_FILE,
     SEC_APP_SOCK,
 };
@@ -70,11 +71,12 @@ fn main() {
 	     QUORUM_FILE.to_string(),
 	     MANIFEST_FILE.to_string(),
 	     PIVOT_FILE.to_string(),
+	     PIVOT_INFO_FILE.to_string(),
 	);
 	Reaper::execute(
 	     &handles,
 	     Box::new(Nsm),
-	     SocketAddress::new_vsock(cid, 3, VMADDR_NO_FLAGS),
+	     vec![SocketAddress::new_vsock(cid, 3, VMADDR_NO_FLAGS)],
 	     SocketAddress::new_unix(SEC_APP_SOCK),
 	     None,
 	);
@@ -5,7 +5,10 @@
 #![warn(missing_docs)]
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use qos_core::parser::{GetParserForOptions, OptionsParser, Parser, Token};
+use qos_core::{
+	handles::PivotInfoHandle,
+	parser::{GetParserForOptions, OptionsParser, Parser, Token},
+};
 
 /// Path to the file `pivot_ok` writes on success for tests.
 pub const PIVOT_OK_SUCCESS_FILE: &str = "./pivot_ok_works";
@@ -101,5 +104,13 @@ impl Cli {
 		let msg = opts.single(MSG).expect("required argument.");
 
 		std::fs::write(path, msg).expect("Failed to write to pivot success");
+
+		// Record the namespace from the pivot info the reaper points us to
+		if std::env::var(qos_core::PIVOT_INFO_FILE_ENV).is_ok() {
+			if let Ok(info) = PivotInfoHandle::from_env().get_pivot_info() {
+				std::fs::write(format!("{path}.namespace"), info.namespace)
+					.expect("Failed to write pivot info namespace");
+			}
+		}
 	}
 }
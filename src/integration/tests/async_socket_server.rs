@@ -0,0 +1,227 @@
+use std::time::{Duration, Instant};
+
+use borsh::BorshDeserialize;
+use qos_core::{
+	async_client::AsyncClient,
+	async_server::{AsyncRequestProcessor, AsyncSocketServer, SharedProcessor},
+	client::Client,
+	handles::Handles,
+	io::{SocketAddress, TimeVal, TimeValLike},
+	protocol::{
+		msg::ProtocolMsg, AsyncProcessor, Processor, ProtocolPhase,
+		ENCLAVE_APP_SOCKET_CLIENT_BACKOFF,
+	},
+};
+use qos_nsm::mock::MockNsm;
+use qos_test_primitives::PathWrapper;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_socket_server_handles_sync_and_async_clients() {
+	let usock: PathWrapper = "./async_socket_server.sock".into();
+	let handles = Handles::new(
+		"./async_socket_server.eph".to_string(),
+		"./async_socket_server.secret".to_string(),
+		"./async_socket_server.manifest".to_string(),
+		"./async_socket_server.pivot".to_string(),
+		"./async_socket_server.pivot_info".to_string(),
+	);
+
+	let processor = Processor::new(
+		Box::new(MockNsm),
+		handles,
+		SocketAddress::new_unix("./never.sock"),
+		None,
+	);
+	let server = tokio::spawn(AsyncSocketServer::listen(
+		SocketAddress::new_unix(&usock),
+		SharedProcessor::new(processor),
+	));
+
+	let request = borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap();
+	let timeout = TimeVal::seconds(5);
+
+	let async_client =
+		AsyncClient::new(SocketAddress::new_unix(&usock), timeout);
+	let response = async_client.send(&request).await.unwrap();
+	assert_eq!(
+		ProtocolMsg::try_from_slice(&response).unwrap(),
+		ProtocolMsg::StatusResponse(ProtocolPhase::WaitingForBootInstruction)
+	);
+
+	// The blocking client speaks the same wire format
+	let sync_request = request.clone();
+	let sync_usock = (*usock).to_string();
+	let response = tokio::task::spawn_blocking(move || {
+		Client::new(SocketAddress::new_unix(&sync_usock), timeout)
+			.send(&sync_request)
+	})
+	.await
+	.unwrap()
+	.unwrap();
+	assert_eq!(
+		ProtocolMsg::try_from_slice(&response).unwrap(),
+		ProtocolMsg::StatusResponse(ProtocolPhase::WaitingForBootInstruction)
+	);
+
+	server.abort();
+}
+
+const SLOW_APP_DELAY: Duration = Duration::from_millis(500);
+
+struct SlowEchoApp;
+impl AsyncRequestProcessor for SlowEchoApp {
+	async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+		tokio::time::sleep(SLOW_APP_DELAY).await;
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_processor_proxies_requests_concurrently() {
+	let usock: PathWrapper = "./async_processor_proxies.sock".into();
+	let app_usock: PathWrapper = "./async_processor_proxies.app.sock".into();
+	let handles = Handles::new(
+		"./async_processor_proxies.eph".to_string(),
+		"./async_processor_proxies.secret".to_string(),
+		"./async_processor_proxies.manifest".to_string(),
+		"./async_processor_proxies.pivot".to_string(),
+		"./async_processor_proxies.pivot_info".to_string(),
+	);
+	let timeout = TimeVal::seconds(5);
+
+	let app = tokio::spawn(AsyncSocketServer::listen(
+		SocketAddress::new_unix(&app_usock),
+		SlowEchoApp,
+	));
+	let processor = AsyncProcessor::new(
+		Processor::new(
+			Box::new(MockNsm),
+			handles,
+			SocketAddress::new_unix(&app_usock),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		),
+		AsyncClient::new(SocketAddress::new_unix(&app_usock), timeout),
+	);
+	let server = tokio::spawn(AsyncSocketServer::listen(
+		SocketAddress::new_unix(&usock),
+		processor,
+	));
+	let client = AsyncClient::new(SocketAddress::new_unix(&usock), timeout);
+	// Make sure both servers are up
+	client
+		.send(&borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap())
+		.await
+		.unwrap();
+
+	let start = Instant::now();
+	let proxies: Vec<_> = (0..4u8)
+		.map(|i| {
+			let client = client.clone();
+			tokio::spawn(async move {
+				let request =
+					borsh::to_vec(&ProtocolMsg::ProxyRequest { data: vec![i] })
+						.unwrap();
+				let response = client.send(&request).await.unwrap();
+				assert_eq!(
+					ProtocolMsg::try_from_slice(&response).unwrap(),
+					ProtocolMsg::ProxyResponse { data: vec![i] }
+				);
+			})
+		})
+		.collect();
+
+	// Other requests are not stuck behind the slow app
+	let response = client
+		.send(&borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap())
+		.await
+		.unwrap();
+	assert_eq!(
+		ProtocolMsg::try_from_slice(&response).unwrap(),
+		ProtocolMsg::StatusResponse(ProtocolPhase::QuorumKeyProvisioned)
+	);
+	assert!(start.elapsed() < SLOW_APP_DELAY);
+
+	for proxy in proxies {
+		proxy.await.unwrap();
+	}
+	// The proxy requests were sent to the app at the same time
+	assert!(start.elapsed() < SLOW_APP_DELAY * 2);
+
+	server.abort();
+	app.abort();
+}
+
+struct EchoApp;
+impl AsyncRequestProcessor for EchoApp {
+	async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_processor_reconnects_to_app_without_blocking_requests() {
+	let usock: PathWrapper = "./async_processor_reconnects.sock".into();
+	let app_usock: PathWrapper = "./async_processor_reconnects.app.sock".into();
+	let handles = Handles::new(
+		"./async_processor_reconnects.eph".to_string(),
+		"./async_processor_reconnects.secret".to_string(),
+		"./async_processor_reconnects.manifest".to_string(),
+		"./async_processor_reconnects.pivot".to_string(),
+		"./async_processor_reconnects.pivot_info".to_string(),
+	);
+	let timeout = TimeVal::seconds(5);
+
+	// The app is not listening yet, like while the reaper restarts it
+	let processor = AsyncProcessor::new(
+		Processor::new(
+			Box::new(MockNsm),
+			handles,
+			SocketAddress::new_unix(&app_usock),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		),
+		AsyncClient::new(SocketAddress::new_unix(&app_usock), timeout)
+			.with_backoff(ENCLAVE_APP_SOCKET_CLIENT_BACKOFF),
+	);
+	let server = tokio::spawn(AsyncSocketServer::listen(
+		SocketAddress::new_unix(&usock),
+		processor,
+	));
+	let client = AsyncClient::new(SocketAddress::new_unix(&usock), timeout);
+	client
+		.send(&borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap())
+		.await
+		.unwrap();
+
+	let proxy = {
+		let client = client.clone();
+		tokio::spawn(async move {
+			let request =
+				borsh::to_vec(&ProtocolMsg::ProxyRequest { data: vec![7] })
+					.unwrap();
+			client.send(&request).await.unwrap()
+		})
+	};
+
+	// Other requests are answered while the proxy request is reconnecting
+	let start = Instant::now();
+	client
+		.send(&borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap())
+		.await
+		.unwrap();
+	assert!(start.elapsed() < Duration::from_millis(100));
+
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	let app = tokio::spawn(AsyncSocketServer::listen(
+		SocketAddress::new_unix(&app_usock),
+		EchoApp,
+	));
+
+	let response = proxy.await.unwrap();
+	assert_eq!(
+		ProtocolMsg::try_from_slice(&response).unwrap(),
+		ProtocolMsg::ProxyResponse { data: vec![7] }
+	);
+
+	server.abort();
+	app.abort();
+}
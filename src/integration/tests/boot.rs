@@ -18,12 +18,13 @@ use qos_core::protocol::{
 		},
 		genesis::{GenesisMemberOutput, GenesisOutput},
 	},
+	status::{EnclaveStatus, PivotStatus},
 	ProtocolPhase, QosHash,
 };
 use qos_crypto::sha_256;
 use qos_host::EnclaveInfo;
 use qos_p256::P256Pair;
-use qos_test_primitives::{ChildWrapper, PathWrapper};
+use qos_test_primitives::{ChildWrapper, PathWrapper, Ready};
 
 const PIVOT_HASH_PATH: &str = "/tmp/standard_boot_e2e-pivot-hash.txt";
 
@@ -32,6 +33,8 @@ async fn standard_boot_e2e() {
 	let host_port = qos_test_primitives::find_free_port().unwrap();
 	let tmp: PathWrapper = "/tmp/boot-e2e".into();
 	let _: PathWrapper = PIVOT_OK2_SUCCESS_FILE.into();
+	let _namespace_path: PathWrapper =
+		format!("{PIVOT_OK2_SUCCESS_FILE}.namespace").into();
 	let _: PathWrapper = PIVOT_HASH_PATH.into();
 	fs::create_dir_all(&*tmp).unwrap();
 
@@ -40,6 +43,8 @@ async fn standard_boot_e2e() {
 	let pivot_path: PathWrapper = "/tmp/boot-e2e/boot_e2e.pivot".into();
 	let manifest_path: PathWrapper = "/tmp/boot-e2e/boot_e2e.manifest".into();
 	let eph_path: PathWrapper = "/tmp/boot-e2e/ephemeral_key.secret".into();
+	let pivot_info_path: PathWrapper =
+		"/tmp/boot-e2e/boot_e2e.pivot_info".into();
 
 	let boot_dir: PathWrapper = "/tmp/boot-e2e/boot-dir".into();
 	fs::create_dir_all(&*boot_dir).unwrap();
@@ -63,6 +68,19 @@ async fn standard_boot_e2e() {
 	let pivot_hash = qos_hex::encode_to_vec(&mock_pivot_hash);
 	std::fs::write(PIVOT_HASH_PATH, pivot_hash).unwrap();
 
+	// -- Create a manifest set with a different threshold than the share set,
+	// so the two can't be mixed up
+	let manifest_set_dir: PathWrapper = "/tmp/boot-e2e/manifest-set".into();
+	fs::create_dir_all(&*manifest_set_dir).unwrap();
+	for user in [user1, user2, user3] {
+		fs::copy(
+			format!("./mock/keys/manifest-set/{user}.pub"),
+			format!("{}/{user}.pub", &*manifest_set_dir),
+		)
+		.unwrap();
+	}
+	fs::write(format!("{}/quorum_threshold", &*manifest_set_dir), "3").unwrap();
+
 	// -- CLIENT create manifest.
 	let msg = "testing420";
 	let pivot_args = format!("[--msg,{msg}]");
@@ -88,7 +106,7 @@ async fn standard_boot_e2e() {
 			"--pivot-args",
 			&pivot_args,
 			"--manifest-set-dir",
-			"./mock/keys/manifest-set",
+			&manifest_set_dir,
 			"--share-set-dir",
 			"./mock/keys/share-set",
 			"--patch-set-dir",
@@ -132,11 +150,14 @@ async fn standard_boot_e2e() {
 		hash: mock_pivot_hash,
 		restart: RestartPolicy::Never,
 		args: vec!["--msg".to_string(), msg.to_string()],
+		app_config: vec![],
+		egress: vec![],
 	};
 	assert_eq!(manifest.pivot, pivot);
-	let manifest_set = ManifestSet { threshold: 2, members: members.clone() };
+	let manifest_set = ManifestSet { threshold: 3, members: members.clone() };
 	assert_eq!(manifest.manifest_set, manifest_set);
-	let share_set = ShareSet { threshold: 2, members };
+	let share_set =
+		ShareSet { threshold: 2, members, share_commitments: vec![] };
 	assert_eq!(manifest.share_set, share_set);
 
 	// -- CLIENT make sure each user can run `approve-manifest`
@@ -164,7 +185,7 @@ async fn standard_boot_e2e() {
 				"--qos-release-dir",
 				QOS_DIST_DIR,
 				"--manifest-set-dir",
-				"./mock/keys/manifest-set",
+				&manifest_set_dir,
 				"--share-set-dir",
 				"./mock/keys/share-set",
 				"--patch-set-dir",
@@ -187,6 +208,16 @@ async fn standard_boot_e2e() {
 			stdout_reader.lines()
 		};
 
+		// Skip over the manifest review, which ends by saying there is no
+		// earlier manifest to compare against
+		assert_eq!(&stdout.next().unwrap().unwrap(), "Manifest to approve:");
+		let no_previous = "No manifest for an earlier nonce of this namespace \
+			found to compare against.";
+		assert!(stdout
+			.by_ref()
+			.map(Result::unwrap)
+			.any(|line| line == no_previous));
+
 		assert_eq!(
 			&stdout.next().unwrap().unwrap(),
 			"Is this the correct namespace name: quit-coding-to-vape? (yes/no)"
@@ -216,6 +247,14 @@ async fn standard_boot_e2e() {
 		assert_eq!(&stdout.next().unwrap().unwrap(), "(yes/no)");
 		stdin.write_all("yes\n".as_bytes()).expect("Failed to write to stdin");
 
+		assert_eq!(
+			&stdout.next().unwrap().unwrap(),
+			"Type `quit-coding-to-vape 2` to approve this manifest:"
+		);
+		stdin
+			.write_all("quit-coding-to-vape 2\n".as_bytes())
+			.expect("Failed to write to stdin");
+
 		// Wait for the command to write the approval and exit
 		assert!(child.wait().unwrap().success());
 
@@ -241,7 +280,7 @@ async fn standard_boot_e2e() {
 	}
 
 	// -- ENCLAVE start enclave
-	let mut _enclave_child_process: ChildWrapper =
+	let mut enclave_child_process: ChildWrapper =
 		Command::new("../target/debug/qos_core")
 			.args([
 				"--usock",
@@ -255,6 +294,8 @@ async fn standard_boot_e2e() {
 				"--mock",
 				"--manifest-file",
 				&*manifest_path,
+				"--pivot-info-file",
+				&*pivot_info_path,
 			])
 			.spawn()
 			.unwrap()
@@ -316,6 +357,33 @@ async fn standard_boot_e2e() {
 		.unwrap()
 		.success());
 
+	// -- CLIENT export the boot attestation doc for an auditor
+	let boot_attestation_doc_path =
+		format!("{}/boot_attestation_doc", &*attestation_dir);
+	let boot_attestation_json_path =
+		format!("{}/boot_attestation_doc.json", &*attestation_dir);
+	assert!(Command::new("../target/debug/qos_client")
+		.args([
+			"export-attestation",
+			"--host-port",
+			&host_port.to_string(),
+			"--host-ip",
+			LOCAL_HOST,
+			"--attestation-doc-path",
+			&boot_attestation_doc_path,
+			"--json-path",
+			&boot_attestation_json_path,
+			"--boot",
+		])
+		.spawn()
+		.unwrap()
+		.wait()
+		.unwrap()
+		.success());
+	assert!(fs::read_to_string(&boot_attestation_json_path)
+		.unwrap()
+		.contains("\"pcrs\""));
+
 	// For each user, post a share,
 	// and sanity check the pivot has not yet executed.
 	assert!(!Path::new(PIVOT_OK2_SUCCESS_FILE).exists());
@@ -364,7 +432,7 @@ async fn standard_boot_e2e() {
 				"--pcr3-preimage-path",
 				PCR3_PRE_IMAGE_PATH,
 				"--manifest-set-dir",
-				"./mock/keys/manifest-set",
+				&manifest_set_dir,
 				"--alias",
 				user,
 				"--unsafe-skip-attestation",
@@ -435,8 +503,9 @@ async fn standard_boot_e2e() {
 			.success());
 	}
 
-	// Give the enclave time to start the pivot
-	std::thread::sleep(std::time::Duration::from_secs(2));
+	// Wait for the enclave to start the pivot
+	enclave_child_process
+		.wait_until_ready(Ready::FileExists(PIVOT_OK2_SUCCESS_FILE));
 
 	// Check that the pivot executed
 	let contents = std::fs::read(PIVOT_OK2_SUCCESS_FILE).unwrap();
@@ -448,5 +517,25 @@ async fn standard_boot_e2e() {
 		ureq::get(&enclave_info_url).call().unwrap().into_json().unwrap();
 	assert_eq!(enclave_info.phase, ProtocolPhase::QuorumKeyProvisioned);
 
+	let enclave_status_url =
+		format!("http://{LOCAL_HOST}:{}/qos/enclave-status", host_port);
+	let enclave_status: EnclaveStatus =
+		ureq::get(&enclave_status_url).call().unwrap().into_json().unwrap();
+	assert_eq!(enclave_status.phase, ProtocolPhase::QuorumKeyProvisioned);
+	let manifest_status = enclave_status.manifest.unwrap();
+	assert_eq!(manifest_status.hash, manifest.qos_hash());
+	// The share set threshold, not the manifest set one
+	assert_eq!(manifest_status.reconstruction.threshold, 2);
+	assert_eq!(
+		enclave_status.pivot,
+		PivotStatus::Exited { code: Some(0), restarts: 0 }
+	);
+	let timeline = enclave_status.timeline;
+	assert!(timeline.boot_standard_accepted_ms.is_some());
+	assert_eq!(timeline.shares_received_ms.len(), 2);
+	assert!(timeline.quorum_reconstructed_ms.is_some());
+	assert!(timeline.ceremony_ms().is_some());
+	assert!(timeline.pivot_spawned_ms >= timeline.quorum_reconstructed_ms);
+
 	fs::remove_file(PIVOT_OK2_SUCCESS_FILE).unwrap();
 }
@@ -0,0 +1,96 @@
+use std::process::Command;
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+const QOS_CLIENT: &str = "../target/debug/qos_client";
+
+/// Stands in for the enclave and a pivot, answering a proxy request with its
+/// name followed by the request data.
+struct EchoProcessor(&'static str);
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let ProtocolMsg::ProxyRequest { data } =
+			ProtocolMsg::try_from_slice(&request).unwrap()
+		else {
+			panic!("expected a proxy request")
+		};
+
+		let data = [self.0.as_bytes(), &data].concat();
+		borsh::to_vec(&ProtocolMsg::ProxyResponse { data }).unwrap()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_proxies_raw_bytes_to_the_app() {
+	let default_usock: PathWrapper = "./client_app_proxy_default.sock".into();
+	let echo_usock: PathWrapper = "./client_app_proxy_echo.sock".into();
+	let data_path: PathWrapper = "./client_app_proxy.data".into();
+	std::fs::write(&*data_path, [0xca, 0xfe]).unwrap();
+
+	let enclaves = [("default", &default_usock), ("echo", &echo_usock)].map(
+		|(name, usock)| {
+			SocketServer::spawn(
+				vec![SocketAddress::new_unix(usock)],
+				EchoProcessor(name),
+			)
+			.unwrap()
+		},
+	);
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&default_usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_namespace("echo".to_string(), SocketAddress::new_unix(&echo_usock));
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let app_proxy = move |args: &[&str]| {
+		let output = Command::new(QOS_CLIENT)
+			.args([
+				"app-proxy",
+				"--host-port",
+				&host_port.to_string(),
+				"--host-ip",
+				LOCAL_HOST,
+			])
+			.args(args)
+			.output()
+			.unwrap();
+		(output.status, String::from_utf8(output.stdout).unwrap())
+	};
+
+	let result = tokio::task::spawn_blocking(move || {
+		let (status, stdout) = app_proxy(&["--data-hex", "0102"]);
+		assert!(status.success());
+		assert_eq!(stdout.trim(), qos_hex::encode(b"default\x01\x02"));
+
+		let (status, stdout) =
+			app_proxy(&["--data-path", &data_path, "--app", "echo"]);
+		assert!(status.success());
+		assert_eq!(stdout.trim(), qos_hex::encode(b"echo\xca\xfe"));
+
+		// No data to send
+		let (status, _) = app_proxy(&[]);
+		assert_eq!(
+			status.code(),
+			Some(qos_client::cli::ExitCode::InvalidInput as i32)
+		);
+	})
+	.await;
+
+	for enclave in enclaves {
+		enclave.shutdown();
+	}
+	result.unwrap();
+}
@@ -0,0 +1,70 @@
+use std::{fs, process::Command};
+
+use qos_test_primitives::PathWrapper;
+
+const QOS_CLIENT: &str = "../target/debug/qos_client";
+
+#[test]
+fn client_signs_with_a_file_key_and_requires_a_key_source() {
+	let master_seed: PathWrapper = "./client_p256.secret".into();
+	let public: PathWrapper = "./client_p256.pub".into();
+	let payload: PathWrapper = "./client_p256.payload".into();
+	let signature: PathWrapper = "./client_p256.signature".into();
+	fs::write(&*payload, b"sign me").unwrap();
+
+	assert!(Command::new(QOS_CLIENT)
+		.args([
+			"generate-file-key",
+			"--master-seed-path",
+			&master_seed,
+			"--pub-path",
+			&public,
+		])
+		.status()
+		.unwrap()
+		.success());
+
+	assert!(Command::new(QOS_CLIENT)
+		.args([
+			"p256-sign",
+			"--payload-path",
+			&payload,
+			"--signature-path",
+			&signature,
+			"--master-seed-path",
+			&master_seed,
+		])
+		.status()
+		.unwrap()
+		.success());
+
+	assert!(Command::new(QOS_CLIENT)
+		.args([
+			"p256-verify",
+			"--payload-path",
+			&payload,
+			"--signature-path",
+			&signature,
+			"--pub-path",
+			&public,
+		])
+		.status()
+		.unwrap()
+		.success());
+
+	// Neither a master seed nor a yubikey is given
+	let status = Command::new(QOS_CLIENT)
+		.args([
+			"p256-sign",
+			"--payload-path",
+			&payload,
+			"--signature-path",
+			&signature,
+		])
+		.status()
+		.unwrap();
+	assert_eq!(
+		status.code(),
+		Some(qos_client::cli::ExitCode::InvalidInput as i32)
+	);
+}
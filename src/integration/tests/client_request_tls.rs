@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use qos_client::request::{self, Config};
+use qos_core::io::SocketAddress;
+use qos_host::{tls::HostTls, HostServer};
+
+const FIXTURES: &str = "./fixtures/host_tls";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_verifies_https_hosts_against_the_pinned_ca() {
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let tls = HostTls::load(
+		format!("{FIXTURES}/server.pem"),
+		format!("{FIXTURES}/server.key"),
+		None::<&str>,
+	)
+	.unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix("./client_request_tls.sock"),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_tls(tls);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("https://localhost:{host_port}/qos/host-health");
+	let config = |ca: &str| Config {
+		retries: 0,
+		backoff: Duration::from_millis(1),
+		ca_cert: Some(format!("{FIXTURES}/{ca}").into()),
+		..Config::default()
+	};
+
+	let pinned_url = url.clone();
+	tokio::task::spawn_blocking(move || {
+		request::get_with_config(&pinned_url, &config("ca.pem"))
+	})
+	.await
+	.unwrap()
+	.unwrap();
+
+	// The host certificate is not issued by any other CA
+	let untrusted_url = url.clone();
+	let response = tokio::task::spawn_blocking(move || {
+		request::get_with_config(&untrusted_url, &config("untrusted_ca.pem"))
+	})
+	.await
+	.unwrap();
+	assert!(matches!(response, Err(request::Error::Transport { .. })));
+
+	// Nor by the web PKI roots used without a pinned CA
+	let response = tokio::task::spawn_blocking(move || {
+		request::get_with_config(
+			&url,
+			&Config { ca_cert: None, ..config("ca.pem") },
+		)
+	})
+	.await
+	.unwrap();
+	assert!(matches!(response, Err(request::Error::Transport { .. })));
+}
@@ -1,24 +1,29 @@
 use std::{fs, path::Path, process::Command};
 
 use integration::{LOCAL_HOST, PIVOT_OK3_PATH, PIVOT_OK3_SUCCESS_FILE};
-use qos_test_primitives::{ChildWrapper, PathWrapper};
+use qos_test_primitives::{ChildWrapper, PathWrapper, Ready};
 
 #[tokio::test]
 async fn dev_boot_e2e() {
 	let tmp: PathWrapper = "/tmp/dev-boot-e2e-tmp".into();
 	drop(fs::create_dir_all(&*tmp));
 	let _: PathWrapper = PIVOT_OK3_SUCCESS_FILE.into();
+	// Written by the pivot once it found its pivot info
+	let _namespace_path: PathWrapper =
+		format!("{PIVOT_OK3_SUCCESS_FILE}.namespace").into();
 	let usock: PathWrapper = "/tmp/dev-boot-e2e-tmp/sock.sock".into();
 	let secret_path: PathWrapper = "/tmp/dev-boot-e2e-tmp/quorum.secret".into();
 	let pivot_path: PathWrapper = "/tmp/dev-boot-e2e-tmp/pivot.pivot".into();
 	let manifest_path: PathWrapper =
 		"/tmp/dev-boot-e2e-tmp/manifest.manifest".into();
 	let eph_path: PathWrapper = "/tmp/dev-boot-e2e-tmp/eph.secret".into();
+	let pivot_info_path: PathWrapper =
+		"/tmp/dev-boot-e2e-tmp/pivot.pivot_info".into();
 
 	let host_port = qos_test_primitives::find_free_port().unwrap();
 
 	// Start Enclave
-	let mut _enclave_child_process: ChildWrapper =
+	let mut enclave_child_process: ChildWrapper =
 		Command::new("../target/debug/qos_core")
 			.args([
 				"--usock",
@@ -32,6 +37,8 @@ async fn dev_boot_e2e() {
 				"--mock",
 				"--manifest-file",
 				&*manifest_path,
+				"--pivot-info-file",
+				&*pivot_info_path,
 			])
 			.spawn()
 			.unwrap()
@@ -54,7 +61,8 @@ async fn dev_boot_e2e() {
 
 	qos_test_primitives::wait_until_port_is_bound(host_port);
 
-	// Run `dangerous-dev-boot`
+	// Run `dangerous-dev-boot`. The mock enclave gives up its ephemeral key,
+	// so no `--unsafe-eph-path-override` is needed.
 	let res = Command::new("../target/debug/qos_client")
 		.args([
 			"dangerous-dev-boot",
@@ -68,16 +76,15 @@ async fn dev_boot_e2e() {
 			"never",
 			"--pivot-args",
 			"[--msg,vapers-only]",
-			"--unsafe-eph-path-override",
-			&*eph_path,
 		])
 		.spawn()
 		.unwrap()
 		.wait()
 		.unwrap();
 
-	// Give the coordinator time to pivot
-	std::thread::sleep(std::time::Duration::from_secs(2));
+	// Wait for the coordinator to pivot
+	enclave_child_process
+		.wait_until_ready(Ready::FileExists(PIVOT_OK3_SUCCESS_FILE));
 
 	// Make sure pivot ran
 	assert!(Path::new(PIVOT_OK3_SUCCESS_FILE).exists());
@@ -37,9 +37,15 @@ fn enclave_app_client_socket_stress() {
 			hash: [1; 32],
 			restart: RestartPolicy::Always,
 			args: vec![APP_SOCK.to_string()],
+			app_config: vec![],
+			egress: vec![],
 		},
 		manifest_set: ManifestSet { threshold: 0, members: vec![] },
-		share_set: ShareSet { threshold: 0, members: vec![] },
+		share_set: ShareSet {
+			threshold: 0,
+			members: vec![],
+			share_commitments: vec![],
+		},
 		enclave: NitroConfig {
 			pcr0: vec![1; 32],
 			pcr1: vec![1; 32],
@@ -59,12 +65,14 @@ fn enclave_app_client_socket_stress() {
 	let manifest_path = "/tmp/enclave_app_client_socket_stress/manifest";
 	let quorum_key_path =
 		"/tmp/enclave_app_client_socket_stress/quorum_key.secret";
+	let pivot_info_path = "/tmp/enclave_app_client_socket_stress/pivot_info";
 
 	let handles = Handles::new(
 		"secret_path_never".to_string(),
 		quorum_key_path.to_string(),
 		manifest_path.to_string(),
 		PIVOT_SOCKET_STRESS_PATH.to_string(),
+		pivot_info_path.to_string(),
 	);
 
 	let p256_pair = P256Pair::generate().unwrap();
@@ -77,7 +85,7 @@ fn enclave_app_client_socket_stress() {
 		Reaper::execute(
 			&handles,
 			Box::new(MockNsm),
-			SocketAddress::new_unix(ENCLAVE_SOCK),
+			vec![SocketAddress::new_unix(ENCLAVE_SOCK)],
 			SocketAddress::new_unix(APP_SOCK),
 			// Force the phase to quorum key provisioned so message proxy-ing
 			// works
@@ -0,0 +1,56 @@
+use borsh::BorshDeserialize;
+use qos_core::{
+	diagnostics::Diagnostics,
+	handles::Handles,
+	io::SocketAddress,
+	protocol::{msg::ProtocolMsg, Processor, ProtocolPhase},
+	server::RequestProcessor,
+};
+use qos_nsm::mock::MockNsm;
+use qos_test_primitives::PathWrapper;
+
+// The panic hook is global to the process, so this is the only test here.
+#[test]
+fn enclave_status_reports_the_latest_panic() {
+	let pivot_info: PathWrapper = "/tmp/enclave_panic_report.pivot_info".into();
+	let handles = Handles::new(
+		"eph".to_string(),
+		"quorum".to_string(),
+		"manifest".to_string(),
+		"pivot".to_string(),
+		pivot_info.to_string(),
+	);
+	let _report: PathWrapper = handles.panic_report_path().into();
+	Diagnostics::new(handles.panic_report_path()).install_panic_hook();
+
+	let panicked = std::thread::Builder::new()
+		.name("worker".to_string())
+		.spawn(|| panic!("lost the quorum key"))
+		.unwrap()
+		.join();
+	assert!(panicked.is_err());
+
+	// A restarted enclave reads the report from the same file
+	let mut processor = Processor::new(
+		Box::new(MockNsm),
+		handles,
+		SocketAddress::new_unix("./never.sock"),
+		Some(ProtocolPhase::WaitingForBootInstruction),
+	);
+	let response = processor
+		.process(borsh::to_vec(&ProtocolMsg::EnclaveStatusRequest).unwrap());
+	let ProtocolMsg::EnclaveStatusResponse(status) =
+		ProtocolMsg::try_from_slice(&response).unwrap()
+	else {
+		panic!("expected an enclave status response")
+	};
+
+	let report = status.last_panic.unwrap();
+	assert_eq!(report.message, "lost the quorum key");
+	assert_eq!(report.thread.as_deref(), Some("worker"));
+	assert!(report
+		.location
+		.unwrap()
+		.starts_with("integration/tests/enclave_panic_report.rs:"));
+	assert!(report.timestamp_ms > 0);
+}
@@ -7,7 +7,9 @@ use std::{
 
 use borsh::de::BorshDeserialize;
 use integration::{LOCAL_HOST, PCR3_PRE_IMAGE_PATH, QOS_DIST_DIR};
-use qos_core::protocol::services::genesis::GenesisOutput;
+use qos_core::protocol::services::{
+	genesis::GenesisOutput, provision::open_share,
+};
 use qos_crypto::{sha_512, shamir::shares_reconstruct};
 use qos_nsm::nitro::unsafe_attestation_doc_from_der;
 use qos_p256::{P256Pair, P256Public};
@@ -187,8 +189,14 @@ async fn genesis_e2e() {
 			let share_pair = P256Pair::from_hex_file(share_key_path).unwrap();
 
 			// Decrypt the share with the personal key
-			let plain_text_share =
-				share_pair.decrypt(&member.encrypted_quorum_key_share).unwrap();
+			let plain_text_share = open_share(
+				&share_pair
+					.decrypt(&member.encrypted_quorum_key_share)
+					.unwrap(),
+				&genesis_output.quorum_key,
+				genesis_output.threshold,
+			)
+			.unwrap();
 
 			assert_eq!(sha_512(&plain_text_share), member.share_hash);
 
@@ -201,6 +209,7 @@ async fn genesis_e2e() {
 	let master_secret: [u8; qos_p256::MASTER_SEED_LEN] =
 		shares_reconstruct(&decrypted_shares[0..threshold])
 			.unwrap()
+			.as_slice()
 			.try_into()
 			.unwrap();
 	let reconstructed = P256Pair::from_master_seed(&master_secret).unwrap();
@@ -244,8 +253,12 @@ async fn genesis_e2e() {
 		let share_key_pair = P256Pair::from_hex_file(share_key_path).unwrap();
 
 		// Check the share is encrypted to personal key
-		let share =
-			share_key_pair.decrypt(&fs::read(share_path).unwrap()).unwrap();
+		let share = open_share(
+			&share_key_pair.decrypt(&fs::read(share_path).unwrap()).unwrap(),
+			&genesis_output.quorum_key,
+			genesis_output.threshold,
+		)
+		.unwrap();
 		// Cross check that the share belongs `decrypted_shares`, which we
 		// created out of band in this test.
 		assert!(decrypted_shares.contains(&share));
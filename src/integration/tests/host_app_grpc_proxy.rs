@@ -0,0 +1,107 @@
+use std::io::Read;
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_app::{GrpcCode, GrpcRouter, GrpcStatus};
+use qos_core::{
+	io::SocketAddress,
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+/// Stands in for the enclave, passing proxy requests to a pivot serving gRPC
+/// methods with a [`GrpcRouter`].
+struct GrpcAppProcessor(GrpcRouter);
+impl RequestProcessor for GrpcAppProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let ProtocolMsg::ProxyRequest { data } =
+			ProtocolMsg::try_from_slice(&request).unwrap()
+		else {
+			panic!("expected a proxy request")
+		};
+		let data = self.0.process(data);
+		borsh::to_vec(&ProtocolMsg::ProxyResponse { data }).unwrap()
+	}
+}
+
+/// Frame `message` as a gRPC-Web data frame.
+fn data_frame(message: &[u8]) -> Vec<u8> {
+	let mut frame = vec![0];
+	frame.extend_from_slice(
+		&u32::try_from(message.len()).unwrap().to_be_bytes(),
+	);
+	frame.extend_from_slice(message);
+	frame
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_bridges_grpc_web_calls_to_the_app() {
+	let usock: PathWrapper = "./host_app_grpc_proxy.sock".into();
+	let router = GrpcRouter::new()
+		.unary("/echo.Echo/Say", |message| Ok(message.to_vec()))
+		.unary("/echo.Echo/Fail", |_| {
+			Err(GrpcStatus::new(GrpcCode::PermissionDenied, "no"))
+		});
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		GrpcAppProcessor(router),
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_app_grpc_proxy();
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let call = |method: &str| {
+			let response =
+				ureq::post(&format!("{url}/grpc/echo.Echo/{method}"))
+					.set("content-type", "application/grpc-web+proto")
+					.send_bytes(&data_frame(b"hello"))
+					.unwrap();
+			assert_eq!(response.status(), 200);
+			assert_eq!(
+				response.header("content-type"),
+				Some("application/grpc-web+proto")
+			);
+			let mut body = vec![];
+			response.into_reader().read_to_end(&mut body).unwrap();
+			body
+		};
+
+		let mut expected = data_frame(b"hello");
+		expected.extend_from_slice(b"\x80\x00\x00\x00\x10grpc-status: 0\r\n");
+		assert_eq!(call("Say"), expected);
+
+		let body = call("Fail");
+		assert!(body.starts_with(b"\x80"));
+		assert!(body.ends_with(b"grpc-status: 7\r\ngrpc-message: no\r\n"));
+
+		let body = call("Shout");
+		assert!(body
+			.ends_with(b"grpc-message: unknown method /echo.Echo/Shout\r\n"));
+
+		// Only gRPC-Web requests are bridged
+		let Err(ureq::Error::Status(code, _)) =
+			ureq::post(&format!("{url}/grpc/echo.Echo/Say"))
+				.set("content-type", "application/json")
+				.send_bytes(b"{}")
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 415);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
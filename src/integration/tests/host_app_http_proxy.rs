@@ -0,0 +1,81 @@
+use std::io::Read;
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+/// Stands in for the enclave and a pivot speaking HTTP, answering with the
+/// request line and body it got.
+struct HttpAppProcessor;
+impl RequestProcessor for HttpAppProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let ProtocolMsg::ProxyRequest { data } =
+			ProtocolMsg::try_from_slice(&request).unwrap()
+		else {
+			panic!("expected a proxy request")
+		};
+		let request = String::from_utf8(data).unwrap();
+		let (head, body) = request.split_once("\r\n\r\n").unwrap();
+		let request_line = head.lines().next().unwrap();
+		let content =
+			format!("{request_line}\n{}\n{body}", head.contains("x-app: 1"));
+
+		let data = format!(
+			"HTTP/1.1 201 Created\r\nx-pivot: yes\r\ncontent-length: {}\r\n\r\n{content}",
+			content.len()
+		)
+		.into_bytes();
+		borsh::to_vec(&ProtocolMsg::ProxyResponse { data }).unwrap()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_proxies_raw_http_to_the_app() {
+	let usock: PathWrapper = "./host_app_http_proxy.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		HttpAppProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_app_http_proxy();
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let response = ureq::post(&format!("{url}/app/sign/tx?key=1"))
+			.set("x-app", "1")
+			.send_bytes(b"payload")
+			.unwrap();
+		assert_eq!(response.status(), 201);
+		assert_eq!(response.header("x-pivot"), Some("yes"));
+		let mut body = String::new();
+		response.into_reader().read_to_string(&mut body).unwrap();
+		assert_eq!(body, "POST /sign/tx?key=1 HTTP/1.1\ntrue\npayload");
+
+		// The websocket route is still there
+		let Err(ureq::Error::Status(code, _)) =
+			ureq::get(&format!("{url}/app/ws")).call()
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 400);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
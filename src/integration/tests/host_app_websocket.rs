@@ -0,0 +1,133 @@
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::TcpStream,
+};
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+/// Stands in for the enclave, answering proxy requests with the reversed
+/// data.
+struct ReverseProcessor;
+impl RequestProcessor for ReverseProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let ProtocolMsg::ProxyRequest { mut data } =
+			ProtocolMsg::try_from_slice(&request).unwrap()
+		else {
+			panic!("expected a proxy request")
+		};
+		data.reverse();
+		borsh::to_vec(&ProtocolMsg::ProxyResponse { data }).unwrap()
+	}
+}
+
+/// A small, masked binary frame as a client would send it.
+fn client_frame(payload: &[u8]) -> Vec<u8> {
+	let mask = [1, 2, 3, 4];
+	let mut frame = vec![0x82, 0x80 | u8::try_from(payload.len()).unwrap()];
+	frame.extend_from_slice(&mask);
+	frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+	frame
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_bridges_websocket_messages_to_the_app() {
+	let usock: PathWrapper = "./host_app_websocket.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		ReverseProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let result = tokio::task::spawn_blocking(move || {
+		let mut stream =
+			TcpStream::connect(format!("{LOCAL_HOST}:{host_port}")).unwrap();
+		write!(
+			stream,
+			"GET /qos/app/ws HTTP/1.1\r\n\
+			Host: {LOCAL_HOST}\r\n\
+			Upgrade: websocket\r\n\
+			Connection: Upgrade\r\n\
+			Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+			Sec-WebSocket-Version: 13\r\n\r\n"
+		)
+		.unwrap();
+
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		let mut head = vec![];
+		loop {
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			if line == "\r\n" {
+				break;
+			}
+			head.push(line.trim_end().to_lowercase());
+		}
+		assert!(head[0].starts_with("http/1.1 101"), "{head:?}");
+		assert!(head.contains(
+			&"sec-websocket-accept: s3pplmbitxaq9kygzzhzrbk+xoo=".to_string()
+		));
+
+		for message in [&b"hello"[..], b"interactive"] {
+			stream.write_all(&client_frame(message)).unwrap();
+
+			let mut frame = vec![0; 2 + message.len()];
+			reader.read_exact(&mut frame).unwrap();
+			let mut expected = message.to_vec();
+			expected.reverse();
+			assert_eq!(
+				frame[..2],
+				[0x82, u8::try_from(message.len()).unwrap()]
+			);
+			assert_eq!(frame[2..], expected);
+		}
+
+		// Close the connection and expect the host to confirm
+		stream.write_all(&[0x88, 0x82, 0, 0, 0, 0, 0x03, 0xE8]).unwrap();
+		let mut close = vec![];
+		reader.read_to_end(&mut close).unwrap();
+		assert_eq!(close, [0x88, 2, 0x03, 0xE8]);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_rejects_plain_requests_to_the_websocket_route() {
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix("./host_app_websocket_plain.sock"),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos/app/ws");
+	let code =
+		tokio::task::spawn_blocking(move || match ureq::get(&url).call() {
+			Err(ureq::Error::Status(code, _)) => code,
+			other => panic!("expected an error status, got {other:?}"),
+		})
+		.await
+		.unwrap();
+	assert_eq!(code, 400);
+}
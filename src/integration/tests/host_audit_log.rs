@@ -0,0 +1,75 @@
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{audit::AuditEntry, HostServer};
+use qos_test_primitives::PathWrapper;
+
+struct EchoProcessor;
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_exports_a_hash_chained_audit_log() {
+	let usock: PathWrapper = "./host_audit_log.sock".into();
+	let alpha_usock: PathWrapper = "./host_audit_log_alpha.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		EchoProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_namespace("alpha".to_string(), SocketAddress::new_unix(&alpha_usock))
+	.with_audit_log_capacity(2);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let entries = tokio::task::spawn_blocking(move || {
+		for message in [&b"first"[..], b"second"] {
+			let response = ureq::post(&format!("{url}/message"))
+				.send_bytes(message)
+				.unwrap();
+			assert_eq!(response.status(), 200);
+		}
+		// Nothing serves the namespace, so the host fails to forward it
+		let Err(ureq::Error::Status(500, _)) =
+			ureq::post(&format!("{url}/ns/alpha/message")).send_bytes(b"third")
+		else {
+			panic!("expected the message to fail")
+		};
+
+		ureq::get(&format!("{url}/audit-log"))
+			.call()
+			.unwrap()
+			.into_json::<Vec<AuditEntry>>()
+			.unwrap()
+	})
+	.await
+	.unwrap();
+	enclave.shutdown();
+
+	// Only the latest two entries are kept
+	assert_eq!(entries.len(), 2);
+	assert!(AuditEntry::verify_chain(&entries));
+
+	assert_eq!(entries[0].index, 1);
+	assert_eq!(entries[0].route, "/qos/message");
+	assert_eq!(entries[0].payload_hash, qos_crypto::sha_256(b"second"));
+	assert_eq!(entries[0].status, 200);
+
+	assert_eq!(entries[1].index, 2);
+	assert_eq!(entries[1].route, "/qos/ns/alpha/message");
+	assert_eq!(entries[1].payload_hash, qos_crypto::sha_256(b"third"));
+	assert_eq!(entries[1].status, 500);
+}
@@ -0,0 +1,119 @@
+use std::{io::Read, time::Duration};
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::{Backoff, SocketAddress},
+	protocol::{msg::ProtocolMsg, ProtocolError},
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{
+	circuit::{CircuitBreaker, CircuitState},
+	HostHealth, HostServer,
+};
+use qos_test_primitives::PathWrapper;
+
+struct EchoProcessor;
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+fn host_health(url: &str) -> HostHealth {
+	ureq::get(&format!("{url}/host-health"))
+		.call()
+		.unwrap()
+		.into_json()
+		.unwrap()
+}
+
+/// Post a status request to the message route, returning the status code and
+/// response.
+fn post_message(url: &str) -> (u16, ureq::Response) {
+	let request = borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap();
+	match ureq::post(&format!("{url}/message")).send_bytes(&request) {
+		Ok(response) => (response.status(), response),
+		Err(ureq::Error::Status(code, response)) => (code, response),
+		Err(e) => panic!("unexpected error: {e:?}"),
+	}
+}
+
+fn decode(response: ureq::Response) -> ProtocolMsg {
+	let mut body = vec![];
+	response.into_reader().read_to_end(&mut body).unwrap();
+	ProtocolMsg::try_from_slice(&body).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_fails_fast_while_the_enclave_is_unavailable() {
+	let usock: PathWrapper = "./host_circuit_breaker.sock".into();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_enclave_backoff(Backoff {
+		initial: Duration::from_millis(10),
+		max: Duration::from_millis(50),
+		max_elapsed: Duration::from_millis(200),
+	})
+	.with_circuit_breaker(CircuitBreaker::new(2, Duration::from_secs(2)));
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let enclave = tokio::task::spawn_blocking(move || {
+		// Nothing is listening on the enclave socket yet
+		for _ in 0..2 {
+			let (code, _) = post_message(&url);
+			assert_eq!(code, 500);
+		}
+
+		let health = host_health(&url);
+		assert_eq!(health.enclave_circuit, CircuitState::Open);
+		assert_eq!(health.consecutive_failures, 2);
+
+		let (code, response) = post_message(&url);
+		assert_eq!(code, 503);
+		assert_eq!(response.header("retry-after"), Some("2"));
+		assert_eq!(
+			decode(response),
+			ProtocolMsg::ProtocolErrorResponse(
+				ProtocolError::EnclaveUnavailable
+			)
+		);
+
+		let Err(ureq::Error::Status(code, _)) =
+			ureq::get(&format!("{url}/enclave-health")).call()
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 503);
+
+		// Once the enclave is up and the cooldown has passed, the trial
+		// request closes the circuit again
+		let enclave = SocketServer::spawn(
+			vec![SocketAddress::new_unix("./host_circuit_breaker.sock")],
+			EchoProcessor,
+		)
+		.unwrap();
+		std::thread::sleep(Duration::from_secs(2));
+		assert_eq!(host_health(&url).enclave_circuit, CircuitState::HalfOpen);
+
+		let (code, response) = post_message(&url);
+		assert_eq!(code, 200);
+		assert_eq!(decode(response), ProtocolMsg::StatusRequest);
+		let health = host_health(&url);
+		assert_eq!(health.enclave_circuit, CircuitState::Closed);
+		assert_eq!(health.consecutive_failures, 0);
+
+		enclave
+	})
+	.await
+	.unwrap();
+
+	enclave.shutdown();
+}
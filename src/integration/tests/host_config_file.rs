@@ -0,0 +1,49 @@
+use std::process::Command;
+
+use integration::LOCAL_HOST;
+use qos_test_primitives::{ChildWrapper, PathWrapper};
+
+#[test]
+fn host_reads_options_from_a_config_file() {
+	let config_path: PathWrapper = "./host_config_file.toml".into();
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	std::fs::write(
+		&*config_path,
+		format!(
+			r#"
+			host-ip = "{LOCAL_HOST}"
+			host-port = 1
+			usock = "./host_config_file.sock"
+			max-message-size = 16
+			"#
+		),
+	)
+	.unwrap();
+
+	// The port on the command line wins over the one in the file
+	let _host: ChildWrapper = Command::new("../target/debug/qos_host")
+		.args([
+			"--config",
+			&*config_path,
+			"--host-port",
+			&host_port.to_string(),
+		])
+		.spawn()
+		.unwrap()
+		.into();
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	assert_eq!(
+		ureq::get(&format!("{url}/host-health")).call().unwrap().status(),
+		200
+	);
+
+	// The size limit from the file applies
+	let Err(ureq::Error::Status(code, _)) =
+		ureq::post(&format!("{url}/message")).send_bytes(&[0; 17])
+	else {
+		panic!("expected an error status")
+	};
+	assert_eq!(code, 400);
+}
@@ -0,0 +1,60 @@
+use integration::LOCAL_HOST;
+use qos_core::io::SocketAddress;
+use qos_host::HostServer;
+
+const ALLOWED: &str = "https://approve.example.com";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_sends_cors_headers_to_allowed_origins() {
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix("./host_cors.sock"),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_cors_origins(vec![ALLOWED.to_string()]);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let preflight = ureq::request("OPTIONS", &format!("{url}/message"))
+			.set("Origin", ALLOWED)
+			.set("Access-Control-Request-Method", "POST")
+			.set("Access-Control-Request-Headers", "content-type")
+			.call()
+			.unwrap();
+		assert_eq!(preflight.status(), 204);
+		assert_eq!(
+			preflight.header("access-control-allow-origin"),
+			Some(ALLOWED)
+		);
+		assert_eq!(
+			preflight.header("access-control-allow-methods"),
+			Some("GET, POST")
+		);
+		assert_eq!(
+			preflight.header("access-control-allow-headers"),
+			Some("content-type")
+		);
+
+		let response = ureq::get(&format!("{url}/host-health"))
+			.set("Origin", ALLOWED)
+			.call()
+			.unwrap();
+		assert_eq!(response.status(), 200);
+		assert_eq!(
+			response.header("access-control-allow-origin"),
+			Some(ALLOWED)
+		);
+
+		let response = ureq::get(&format!("{url}/host-health"))
+			.set("Origin", "https://evil.example.com")
+			.call()
+			.unwrap();
+		assert_eq!(response.header("access-control-allow-origin"), None);
+	})
+	.await;
+
+	result.unwrap();
+}
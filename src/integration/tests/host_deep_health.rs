@@ -0,0 +1,113 @@
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	protocol::{app_health::AppHealth, msg::ProtocolMsg, ProtocolPhase},
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{DeepHealth, HealthState, HostServer};
+use qos_test_primitives::PathWrapper;
+
+/// Stands in for an enclave in `phase` whose pivot reports `app` health.
+struct PhaseProcessor(ProtocolPhase, AppHealth);
+impl RequestProcessor for PhaseProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let response = match borsh::from_slice(&request).unwrap() {
+			ProtocolMsg::AppHealthRequest => {
+				ProtocolMsg::AppHealthResponse(self.1.clone())
+			}
+			_ => ProtocolMsg::StatusResponse(self.0),
+		};
+		borsh::to_vec(&response).unwrap()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deep_health_tells_apart_unreachable_and_unready_enclaves() {
+	let ready_usock: PathWrapper = "./host_deep_health_ready.sock".into();
+	let booting_usock: PathWrapper = "./host_deep_health_booting.sock".into();
+	let loading_usock: PathWrapper = "./host_deep_health_loading.sock".into();
+	let loading = AppHealth::NotReady { reason: "loading".to_string() };
+	let enclaves = [
+		(&ready_usock, ProtocolPhase::QuorumKeyProvisioned, AppHealth::Ready),
+		(
+			&booting_usock,
+			ProtocolPhase::WaitingForBootInstruction,
+			AppHealth::Ready,
+		),
+		(&loading_usock, ProtocolPhase::QuorumKeyProvisioned, loading.clone()),
+	]
+	.map(|(usock, phase, app)| {
+		SocketServer::spawn(
+			vec![SocketAddress::new_unix(usock)],
+			PhaseProcessor(phase, app),
+		)
+		.unwrap()
+	});
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	// Nothing listens on the default enclave socket
+	let host = HostServer::new(
+		SocketAddress::new_unix("./host_deep_health_missing.sock"),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_namespace("ready".to_string(), SocketAddress::new_unix(&ready_usock))
+	.with_namespace(
+		"booting".to_string(),
+		SocketAddress::new_unix(&booting_usock),
+	)
+	.with_namespace(
+		"loading".to_string(),
+		SocketAddress::new_unix(&loading_usock),
+	);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let deep_health = |prefix: &str| {
+			let response =
+				match ureq::get(&format!("{url}{prefix}/health/deep")).call() {
+					Ok(response) | Err(ureq::Error::Status(_, response)) => {
+						response
+					}
+					Err(e) => panic!("unexpected error: {e:?}"),
+				};
+			let status = response.status();
+			(status, response.into_json::<DeepHealth>().unwrap())
+		};
+
+		let (status, health) = deep_health("/ns/ready");
+		assert_eq!(status, 200);
+		assert_eq!(health.state, HealthState::Healthy);
+		assert_eq!(health.phase, Some(ProtocolPhase::QuorumKeyProvisioned));
+		assert!(health.round_trip_ms.is_some());
+		assert_eq!(health.app, Some(AppHealth::Ready));
+
+		let (status, health) = deep_health("/ns/booting");
+		assert_eq!(status, 503);
+		assert_eq!(health.state, HealthState::EnclaveNotReady);
+		assert_eq!(
+			health.phase,
+			Some(ProtocolPhase::WaitingForBootInstruction)
+		);
+		assert_eq!(health.app, None);
+
+		let (status, health) = deep_health("/ns/loading");
+		assert_eq!(status, 503);
+		assert_eq!(health.state, HealthState::AppNotReady);
+		assert_eq!(health.app, Some(loading));
+
+		let (status, health) = deep_health("");
+		assert_eq!(status, 503);
+		assert_eq!(health.state, HealthState::EnclaveUnreachable);
+		assert_eq!(health.phase, None);
+		assert!(health.error.is_some());
+	})
+	.await;
+
+	for enclave in enclaves {
+		enclave.shutdown();
+	}
+	result.unwrap();
+}
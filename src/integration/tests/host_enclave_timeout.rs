@@ -0,0 +1,64 @@
+use std::{io::Read, time::Duration};
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::{SocketAddress, TimeVal, TimeValLike},
+	protocol::{msg::ProtocolMsg, ProtocolError},
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+/// Responds after longer than the host is willing to wait.
+struct SlowProcessor;
+impl RequestProcessor for SlowProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		std::thread::sleep(Duration::from_secs(3));
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_responds_with_a_timeout_error_when_the_enclave_stalls() {
+	let usock: PathWrapper = "./host_enclave_timeout.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		SlowProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_enclave_timeout(TimeVal::seconds(1));
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos/message");
+	let result = tokio::task::spawn_blocking(move || {
+		let request = borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap();
+		let Err(ureq::Error::Status(code, response)) =
+			ureq::post(&url).send_bytes(&request)
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 504);
+
+		let mut body = vec![];
+		response.into_reader().read_to_end(&mut body).unwrap();
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&body).unwrap(),
+			ProtocolMsg::ProtocolErrorResponse(
+				ProtocolError::EnclaveClientTimeout
+			)
+		);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
@@ -0,0 +1,145 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{ingress::IngressMap, HostServer};
+use qos_test_primitives::PathWrapper;
+use serde::Deserialize;
+
+const INGRESS_MAP: &str = r#"
+[[route]]
+method = "GET"
+path = "/keys/:name"
+variant = 0
+fields = [{ name = "name", type = "string" }]
+
+[[route]]
+method = "POST"
+path = "/keys/:name/sign"
+variant = 1
+fields = [
+	{ name = "name", type = "string" },
+	{ name = "payload", type = "bytes" },
+]
+
+[[response]]
+variant = 0
+status = 200
+fields = [{ name = "signature", type = "bytes" }]
+
+[[response]]
+variant = 1
+status = 404
+fields = [{ name = "error", type = "string" }]
+"#;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+enum AppRequest {
+	Key { name: String },
+	Sign { name: String, payload: Vec<u8> },
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+enum AppResponse {
+	Signature { signature: Vec<u8> },
+	Error { error: String },
+}
+
+#[derive(Deserialize)]
+struct SignatureBody {
+	signature: String,
+}
+
+/// Stands in for the enclave and a pivot speaking borsh, "signing" with the
+/// key `k1` by reversing the payload.
+struct BorshAppProcessor;
+impl RequestProcessor for BorshAppProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let ProtocolMsg::ProxyRequest { data } =
+			ProtocolMsg::try_from_slice(&request).unwrap()
+		else {
+			panic!("expected a proxy request")
+		};
+		let response = match AppRequest::try_from_slice(&data).unwrap() {
+			AppRequest::Sign { name, mut payload } if name == "k1" => {
+				payload.reverse();
+				AppResponse::Signature { signature: payload }
+			}
+			AppRequest::Key { name } | AppRequest::Sign { name, .. } => {
+				AppResponse::Error { error: format!("no key {name}") }
+			}
+		};
+
+		let data = borsh::to_vec(&response).unwrap();
+		borsh::to_vec(&ProtocolMsg::ProxyResponse { data }).unwrap()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_maps_json_routes_to_app_messages() {
+	let usock: PathWrapper = "./host_ingress.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		BorshAppProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_ingress_map(IngressMap::from_toml(INGRESS_MAP).unwrap());
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let response = ureq::post(&format!("{url}/api/keys/k1/sign"))
+			.send_string(r#"{"payload": "010203"}"#)
+			.unwrap();
+		assert_eq!(response.status(), 200);
+		let body: SignatureBody = response.into_json().unwrap();
+		assert_eq!(body.signature, "030201");
+
+		let status = |request: ureq::Request, body: &str| match request
+			.send_string(body)
+		{
+			Ok(response) => {
+				(response.status(), response.into_string().unwrap())
+			}
+			Err(ureq::Error::Status(code, response)) => {
+				(code, response.into_string().unwrap())
+			}
+			Err(e) => panic!("{e}"),
+		};
+
+		// App responses are mapped to their status
+		assert_eq!(
+			status(ureq::get(&format!("{url}/api/keys/k2")), ""),
+			(404, r#"{"error":"no key k2"}"#.to_string())
+		);
+		// Bodies that do not match the route are refused by the host
+		assert_eq!(
+			status(
+				ureq::post(&format!("{url}/api/keys/k1/sign")),
+				r#"{"payload": "zz"}"#
+			)
+			.0,
+			400
+		);
+		// As are routes that are not in the map
+		assert_eq!(
+			status(ureq::delete(&format!("{url}/api/keys/k1")), "").0,
+			404
+		);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
@@ -0,0 +1,73 @@
+use std::{sync::Arc, time::Duration};
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	log::{LogBatch, LogLevel, Logger},
+	protocol::msg::ProtocolMsg,
+	server::{RequestProcessor, SocketServer},
+	time::MockClock,
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+/// Answers log requests from its own logger, like the enclave does.
+struct LogsProcessor(Arc<Logger>);
+impl RequestProcessor for LogsProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let Ok(ProtocolMsg::LogsRequest { since, max }) =
+			ProtocolMsg::try_from_slice(&request)
+		else {
+			panic!("expected a logs request")
+		};
+		let batch = self.0.read(since, max as usize);
+		borsh::to_vec(&ProtocolMsg::LogsResponse(batch)).unwrap()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_pulls_the_enclave_logs() {
+	let usock: PathWrapper = "./host_logs.sock".into();
+	let logger =
+		Arc::new(Logger::new(2, Arc::new(MockClock::new(Duration::ZERO))));
+	for message in ["booting", "waiting for shares", "provisioned"] {
+		logger.log(LogLevel::Info, "test", message);
+	}
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		LogsProcessor(logger),
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos/logs");
+	let (all, latest) = tokio::task::spawn_blocking(move || {
+		let get = |url: &str| {
+			ureq::get(url).call().unwrap().into_json::<LogBatch>().unwrap()
+		};
+		(get(&url), get(&format!("{url}?since=2&max=1")))
+	})
+	.await
+	.unwrap();
+	enclave.shutdown();
+
+	// Only the latest two entries are kept
+	assert_eq!(all.dropped, 1);
+	assert_eq!(all.next_seq, 3);
+	let messages: Vec<_> =
+		all.entries.iter().map(|entry| entry.message.as_str()).collect();
+	assert_eq!(messages, ["waiting for shares", "provisioned"]);
+
+	assert_eq!(latest.dropped, 0);
+	assert_eq!(latest.entries.len(), 1);
+	assert_eq!(latest.entries[0].message, "provisioned");
+}
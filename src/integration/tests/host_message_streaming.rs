@@ -0,0 +1,132 @@
+use std::{
+	io::{Cursor, Read, Write},
+	net::TcpStream,
+	time::{Duration, Instant},
+};
+
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	server::{RequestProcessor, SocketServer, SOCKET_SERVER_TIMEOUT_SECS},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+const MEGABYTE: usize = 1024 * 1024;
+
+struct EchoProcessor;
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_streams_messages_up_to_the_size_limit() {
+	let usock: PathWrapper = "./host_message_streaming.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		EchoProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_max_message_size(4 * MEGABYTE);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos/message");
+	let result = tokio::task::spawn_blocking(move || {
+		let message: Vec<u8> = (0..3 * MEGABYTE + 1).map(|i| i as u8).collect();
+
+		// Spans several chunks, with a known length
+		let response = ureq::post(&url).send_bytes(&message).unwrap();
+		let mut echoed = vec![];
+		response.into_reader().read_to_end(&mut echoed).unwrap();
+		assert_eq!(echoed, message);
+
+		// Too large, rejected based on the content length before the body
+		// is sent
+		let mut stream = TcpStream::connect((LOCAL_HOST, host_port)).unwrap();
+		write!(
+			stream,
+			"POST /qos/message HTTP/1.1\r\nHost: {LOCAL_HOST}\r\nContent-Length: {}\r\n\r\n",
+			4 * MEGABYTE + 1
+		)
+		.unwrap();
+		let mut status_line = [0u8; 12];
+		stream.read_exact(&mut status_line).unwrap();
+		assert_eq!(&status_line, b"HTTP/1.1 400");
+
+		// Too large, rejected while streaming a chunked body
+		let too_large = vec![0; 4 * MEGABYTE + 1];
+		let Err(ureq::Error::Status(code, _)) =
+			ureq::post(&url).send(Cursor::new(too_large))
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 400);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_does_not_hold_the_enclave_while_a_body_stalls() {
+	let usock: PathWrapper = "./host_message_stalled_body.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		EchoProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos/message");
+	let result = tokio::task::spawn_blocking(move || {
+		// Send half of a body, then stall for longer than the enclave is
+		// willing to wait on a connection
+		let mut stalled = TcpStream::connect((LOCAL_HOST, host_port)).unwrap();
+		write!(
+			stalled,
+			"POST /qos/message HTTP/1.1\r\nHost: {LOCAL_HOST}\r\nConnection: close\r\nContent-Length: 8\r\n\r\nstal"
+		)
+		.unwrap();
+
+		// Other messages are forwarded in the meantime
+		std::thread::sleep(Duration::from_millis(100));
+		let start = Instant::now();
+		let response = ureq::post(&url).send_bytes(b"ping").unwrap();
+		let mut echoed = vec![];
+		response.into_reader().read_to_end(&mut echoed).unwrap();
+		assert_eq!(echoed, b"ping".to_vec());
+		assert!(start.elapsed() < Duration::from_secs(1));
+
+		std::thread::sleep(Duration::from_secs(
+			SOCKET_SERVER_TIMEOUT_SECS.unsigned_abs() + 1,
+		));
+		stalled.write_all(b"led!").unwrap();
+		let mut response = String::new();
+		stalled.read_to_string(&mut response).unwrap();
+		assert!(response.starts_with("HTTP/1.1 200"));
+		assert!(response.ends_with("stalled!"));
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
@@ -0,0 +1,161 @@
+use std::{
+	fs::File,
+	io::{BufReader, Read, Write},
+	net::TcpStream,
+	sync::Arc,
+};
+
+use qos_core::{
+	io::SocketAddress,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{tls::HostTls, HostServer};
+use qos_test_primitives::PathWrapper;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+const FIXTURES: &str = "./fixtures/host_tls";
+
+struct EchoProcessor;
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn message_route_requires_client_certificate() {
+	let usock: PathWrapper = "./host_mtls.sock".into();
+	let enclave = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&usock)],
+		EchoProcessor,
+	)
+	.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let tls = HostTls::load(
+		format!("{FIXTURES}/server.pem"),
+		format!("{FIXTURES}/server.key"),
+		Some(format!("{FIXTURES}/ca.pem")),
+	)
+	.unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_tls(tls);
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	// Health checks don't need a client certificate
+	let response = tokio::task::spawn_blocking(move || {
+		post(host_port, None, "GET", "/qos/host-health", b"")
+	})
+	.await
+	.unwrap()
+	.unwrap();
+	assert!(response.starts_with(b"HTTP/1.1 200"));
+
+	// Messages without a client certificate are rejected
+	let response = tokio::task::spawn_blocking(move || {
+		post(host_port, None, "POST", "/qos/message", b"ping")
+	})
+	.await
+	.unwrap()
+	.unwrap();
+	assert!(response.starts_with(b"HTTP/1.1 403"));
+
+	// Messages with a trusted client certificate are proxied to the enclave
+	let response = tokio::task::spawn_blocking(move || {
+		post(host_port, Some("client"), "POST", "/qos/message", b"ping")
+	})
+	.await
+	.unwrap()
+	.unwrap();
+	assert!(response.starts_with(b"HTTP/1.1 200"));
+	assert!(response.ends_with(b"\r\n\r\nping"));
+
+	// Certificates from any other CA fail the handshake
+	let response = tokio::task::spawn_blocking(move || {
+		post(
+			host_port,
+			Some("untrusted_client"),
+			"POST",
+			"/qos/message",
+			b"ping",
+		)
+	})
+	.await
+	.unwrap();
+	assert!(response.is_err());
+
+	enclave.shutdown();
+}
+
+/// Make a single HTTP request over TLS, optionally presenting the fixture
+/// client certificate `client`. Returns the raw response.
+fn post(
+	port: u16,
+	client: Option<&str>,
+	method: &str,
+	path: &str,
+	body: &[u8],
+) -> std::io::Result<Vec<u8>> {
+	let mut roots = RootCertStore::empty();
+	for cert in certs(&format!("{FIXTURES}/ca.pem")) {
+		roots.add(cert).unwrap();
+	}
+	let builder = ClientConfig::builder().with_root_certificates(roots);
+	let config = match client {
+		Some(client) => {
+			let key = rustls_pemfile::private_key(&mut BufReader::new(
+				File::open(format!("{FIXTURES}/{client}.key")).unwrap(),
+			))
+			.unwrap()
+			.unwrap();
+			builder
+				.with_client_auth_cert(
+					certs(&format!("{FIXTURES}/{client}.pem")),
+					key,
+				)
+				.unwrap()
+		}
+		None => builder.with_no_client_auth(),
+	};
+
+	let connection = ClientConnection::new(
+		Arc::new(config),
+		"localhost".try_into().unwrap(),
+	)
+	.unwrap();
+	let tcp = TcpStream::connect(("127.0.0.1", port))?;
+	let mut stream = StreamOwned::new(connection, tcp);
+
+	stream.write_all(
+		format!(
+			"{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+			body.len()
+		)
+		.as_bytes(),
+	)?;
+	stream.write_all(body)?;
+
+	let mut response = vec![];
+	match stream.read_to_end(&mut response) {
+		Ok(_) => Ok(response),
+		// The server may close the connection without a TLS close_notify
+		Err(e)
+			if e.kind() == std::io::ErrorKind::UnexpectedEof
+				&& !response.is_empty() =>
+		{
+			Ok(response)
+		}
+		Err(e) => Err(e),
+	}
+}
+
+fn certs(path: &str) -> Vec<rustls::pki_types::CertificateDer<'static>> {
+	rustls_pemfile::certs(&mut BufReader::new(File::open(path).unwrap()))
+		.collect::<Result<_, _>>()
+		.unwrap()
+}
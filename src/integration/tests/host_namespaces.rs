@@ -0,0 +1,83 @@
+use std::io::Read;
+
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::SocketAddress,
+	server::{RequestProcessor, SocketServer},
+};
+use qos_host::{circuit::CircuitState, HostHealth, HostServer};
+use qos_test_primitives::PathWrapper;
+
+/// Stands in for an enclave, answering every request with its name.
+struct NameProcessor(&'static str);
+impl RequestProcessor for NameProcessor {
+	fn process(&mut self, _request: Vec<u8>) -> Vec<u8> {
+		self.0.as_bytes().to_vec()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_routes_messages_by_namespace() {
+	let alpha_usock: PathWrapper = "./host_namespaces_alpha.sock".into();
+	let beta_usock: PathWrapper = "./host_namespaces_beta.sock".into();
+	let default_usock: PathWrapper = "./host_namespaces_default.sock".into();
+	let enclaves = [
+		("alpha", &alpha_usock),
+		("beta", &beta_usock),
+		("default", &default_usock),
+	]
+	.map(|(name, usock)| {
+		SocketServer::spawn(
+			vec![SocketAddress::new_unix(usock)],
+			NameProcessor(name),
+		)
+		.unwrap()
+	});
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&default_usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_namespace("alpha".to_string(), SocketAddress::new_unix(&alpha_usock))
+	.with_namespace("beta".to_string(), SocketAddress::new_unix(&beta_usock));
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let result = tokio::task::spawn_blocking(move || {
+		let post = |path: &str| {
+			let response = ureq::post(&format!("{url}{path}"))
+				.send_bytes(b"hello")
+				.unwrap();
+			let mut body = String::new();
+			response.into_reader().read_to_string(&mut body).unwrap();
+			body
+		};
+		assert_eq!(post("/ns/alpha/message"), "alpha");
+		assert_eq!(post("/ns/beta/message"), "beta");
+		assert_eq!(post("/message"), "default");
+
+		let Err(ureq::Error::Status(code, _)) =
+			ureq::post(&format!("{url}/ns/gamma/message")).send_bytes(b"hello")
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 404);
+
+		let health: HostHealth =
+			ureq::get(&format!("{url}/ns/alpha/host-health"))
+				.call()
+				.unwrap()
+				.into_json()
+				.unwrap();
+		assert_eq!(health.enclave_circuit, CircuitState::Closed);
+	})
+	.await;
+
+	for enclave in enclaves {
+		enclave.shutdown();
+	}
+	result.unwrap();
+}
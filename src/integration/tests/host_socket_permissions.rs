@@ -0,0 +1,78 @@
+use std::io::Read;
+
+use borsh::BorshDeserialize;
+use integration::LOCAL_HOST;
+use qos_core::{
+	io::{SocketAddress, SocketPermissions},
+	protocol::{msg::ProtocolMsg, ProtocolError},
+	server::{RequestProcessor, ServerOptions, SocketServer},
+};
+use qos_host::HostServer;
+use qos_test_primitives::PathWrapper;
+
+struct EchoProcessor;
+impl RequestProcessor for EchoProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		request
+	}
+}
+
+/// Start a host for the enclave at `usock` that expects its socket to have
+/// `mode` and return the url of its message endpoint.
+async fn spawn_host(usock: &str, mode: u32) -> String {
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_socket_permissions(SocketPermissions { mode, uid: None, gid: None });
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	format!("http://{LOCAL_HOST}:{host_port}/qos/message")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_only_talks_to_enclave_socket_with_expected_permissions() {
+	let usock: PathWrapper = "./host_socket_permissions.sock".into();
+	let enclave = SocketServer::spawn_with_options(
+		vec![SocketAddress::new_unix(&usock)],
+		EchoProcessor,
+		ServerOptions {
+			permissions: Some(SocketPermissions {
+				mode: 0o600,
+				uid: None,
+				gid: None,
+			}),
+			..Default::default()
+		},
+	)
+	.unwrap();
+
+	let expected = spawn_host(&usock, 0o600).await;
+	let unexpected = spawn_host(&usock, 0o660).await;
+	let result = tokio::task::spawn_blocking(move || {
+		let response = ureq::post(&expected).send_bytes(b"ping").unwrap();
+		let mut body = vec![];
+		response.into_reader().read_to_end(&mut body).unwrap();
+		assert_eq!(body, b"ping".to_vec());
+
+		let Err(ureq::Error::Status(code, response)) =
+			ureq::post(&unexpected).send_bytes(b"ping")
+		else {
+			panic!("expected an error status")
+		};
+		assert_eq!(code, 500);
+		let mut body = vec![];
+		response.into_reader().read_to_end(&mut body).unwrap();
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&body).unwrap(),
+			ProtocolMsg::ProtocolErrorResponse(ProtocolError::EnclaveClient)
+		);
+	})
+	.await;
+
+	enclave.shutdown();
+	result.unwrap();
+}
@@ -0,0 +1,113 @@
+use std::{sync::Arc, time::Duration};
+
+use integration::LOCAL_HOST;
+use qos_core::{
+	client::Client,
+	handles::Handles,
+	io::{SocketAddress, TimeVal, TimeValLike},
+	log::{LogLevel, Logger},
+	protocol::{Processor, ProtocolPhase},
+	server::{RequestProcessor, SocketServer},
+	time::MockClock,
+};
+use qos_host::HostServer;
+use qos_nsm::mock::MockNsm;
+use qos_test_primitives::PathWrapper;
+
+/// Pivot speaking HTTP, answering with the trace ID header it got.
+struct TraceApp;
+impl RequestProcessor for TraceApp {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let request = String::from_utf8(request).unwrap();
+		let trace_id = request
+			.lines()
+			.find_map(|line| line.strip_prefix("x-qos-trace-id: "))
+			.unwrap_or("none");
+
+		format!(
+			"HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{trace_id}",
+			trace_id.len()
+		)
+		.into_bytes()
+	}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_ids_reach_the_enclave_and_the_app() {
+	let usock: PathWrapper = "./host_trace_ids.sock".into();
+	let app_usock: PathWrapper = "./host_trace_ids_app.sock".into();
+	let app = SocketServer::spawn(
+		vec![SocketAddress::new_unix(&app_usock)],
+		TraceApp,
+	)
+	.unwrap();
+
+	let logger =
+		Arc::new(Logger::new(8, Arc::new(MockClock::new(Duration::ZERO))));
+	let processor = Processor::new(
+		Box::new(MockNsm),
+		Handles::new(
+			"eph".to_string(),
+			"quorum".to_string(),
+			"manifest".to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		),
+		SocketAddress::new_unix(&app_usock),
+		Some(ProtocolPhase::QuorumKeyProvisioned),
+	)
+	.with_app_client(Client::new(
+		SocketAddress::new_unix(&app_usock),
+		TimeVal::seconds(5),
+	))
+	.with_logger(logger.clone());
+	let enclave =
+		SocketServer::spawn(vec![SocketAddress::new_unix(&usock)], processor)
+			.unwrap();
+
+	let host_port = qos_test_primitives::find_free_port().unwrap();
+	let host = HostServer::new(
+		SocketAddress::new_unix(&usock),
+		([127, 0, 0, 1], host_port).into(),
+		None,
+	)
+	.with_app_http_proxy()
+	.with_trace_ids();
+	tokio::spawn(async move { host.serve().await });
+	qos_test_primitives::wait_until_port_is_bound(host_port);
+
+	let url = format!("http://{LOCAL_HOST}:{host_port}/qos");
+	let [traced, generated] = tokio::task::spawn_blocking(move || {
+		let traced = ureq::get(&format!("{url}/app/status"))
+			.set("x-qos-trace-id", "req-42")
+			.call()
+			.unwrap();
+		// Invalid trace IDs are replaced
+		let generated = ureq::get(&format!("{url}/app/status"))
+			.set("x-qos-trace-id", "not valid")
+			.call()
+			.unwrap();
+
+		[traced, generated].map(|response| {
+			let header = response.header("x-qos-trace-id").unwrap().to_string();
+			(header, response.into_string().unwrap())
+		})
+	})
+	.await
+	.unwrap();
+	enclave.shutdown();
+	app.shutdown();
+
+	assert_eq!(traced, ("req-42".to_string(), "req-42".to_string()));
+	assert_eq!(generated.0.len(), 32);
+	assert_eq!(generated.0, generated.1);
+
+	let entries = logger.read(0, 8).entries;
+	assert_eq!(entries.len(), 2);
+	assert_eq!(entries[0].level, LogLevel::Debug);
+	assert_eq!(entries[0].message, "trace_id=req-42 request succeeded");
+	assert_eq!(
+		entries[1].message,
+		format!("trace_id={} request succeeded", generated.0)
+	);
+}
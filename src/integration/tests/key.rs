@@ -41,6 +41,7 @@ async fn key_fwd_e2e() {
 	let new_secret_path = "/tmp/key-fwd-e2e/new_secret.secret";
 	let new_pivot_path = "/tmp/key-fwd-e2e/new_pivot.pivot";
 	let new_manifest_path = "/tmp/key-fwd-e2e/new_manifest.manifest";
+	let new_pivot_info_path = "/tmp/key-fwd-e2e/new_pivot.pivot_info";
 	let new_usock = "/tmp/key-fwd-e2e/new_usock.sock";
 
 	// -- ENCLAVE start new enclave
@@ -60,6 +61,8 @@ async fn key_fwd_e2e() {
 				"--mock",
 				"--manifest-file",
 				new_manifest_path,
+				"--pivot-info-file",
+				new_pivot_info_path,
 			])
 			.spawn()
 			.unwrap()
@@ -227,6 +230,7 @@ fn boot_old_enclave(old_host_port: u16) -> (ChildWrapper, ChildWrapper) {
 	let old_secret_path = "/tmp/key-fwd-e2e/old_secret.secret";
 	let old_pivot_path = "/tmp/key-fwd-e2e/old_pivot.pivot";
 	let old_manifest_path = "/tmp/key-fwd-e2e/old_manifest.manifest";
+	let old_pivot_info_path = "/tmp/key-fwd-e2e/old_pivot.pivot_info";
 	let old_usock = "/tmp/key-fwd-e2e/old_usock.sock";
 
 	// -- ENCLAVE start old enclave
@@ -244,6 +248,8 @@ fn boot_old_enclave(old_host_port: u16) -> (ChildWrapper, ChildWrapper) {
 				"--mock",
 				"--manifest-file",
 				old_manifest_path,
+				"--pivot-info-file",
+				old_pivot_info_path,
 			])
 			.spawn()
 			.unwrap()
@@ -122,7 +122,7 @@ fn preprod_reshard_ceremony() {
 
 		// Now we have the proper quorum key, we're ready to shard it in two
 		// pieces, to our two new users.
-		let new_shares = shares_generate(&decrypted_dev_share, 2, 2).unwrap(); // (threshold, total)
+		let (new_shares, _) = shares_generate(&decrypted_dev_share, 2, 2).unwrap(); // (threshold, total)
 		assert_eq!(new_shares.len(), 2);
 
 		for (user, share) in
@@ -1,10 +1,16 @@
-use std::fs;
+use std::{fs, path::Path};
 
-use integration::{PIVOT_ABORT_PATH, PIVOT_OK_PATH, PIVOT_PANIC_PATH};
+use borsh::BorshDeserialize;
+use integration::{
+	PIVOT_ABORT_PATH, PIVOT_LOOP_PATH, PIVOT_OK_PATH, PIVOT_PANIC_PATH,
+};
 use qos_core::{
+	client::Client,
 	handles::Handles,
-	io::SocketAddress,
-	protocol::services::boot::ManifestEnvelope,
+	io::{SocketAddress, TimeVal, TimeValLike},
+	protocol::{
+		msg::ProtocolMsg, services::boot::ManifestEnvelope, ProtocolPhase,
+	},
 	reaper::{Reaper, REAPER_EXIT_DELAY_IN_SECONDS},
 };
 use qos_nsm::mock::MockNsm;
@@ -16,6 +22,7 @@ fn reaper_works() {
 	// let eph_path = "reaper_works.eph.key";
 	let usock: PathWrapper = "./reaper_works/reaper_works.sock".into();
 	let manifest_path: PathWrapper = "reaper_works.manifest".into();
+	let pivot_info_path: PathWrapper = "reaper_works.pivot_info".into();
 	let msg = "durp-a-durp";
 
 	// For our sanity, ensure the secret does not yet exist
@@ -26,22 +33,26 @@ fn reaper_works() {
 		(*secret_path).to_string(),
 		(*manifest_path).to_string(),
 		PIVOT_OK_PATH.to_string(),
+		(*pivot_info_path).to_string(),
 	);
 
 	// Make sure we have written everything necessary to pivot, except the
 	// quorum key
 	let mut manifest_envelope = ManifestEnvelope::default();
+	manifest_envelope.manifest.namespace.name = "reaper-works".to_string();
 	manifest_envelope.manifest.pivot.args =
 		vec!["--msg".to_string(), msg.to_string()];
 
 	handles.put_manifest_envelope(&manifest_envelope).unwrap();
 	assert!(handles.pivot_exists());
+	// Pivot info left over from an earlier boot does not stop the pivot
+	handles.put_pivot_info(&manifest_envelope.manifest).unwrap();
 
 	let reaper_handle = std::thread::spawn(move || {
 		Reaper::execute(
 			&handles,
 			Box::new(MockNsm),
-			SocketAddress::new_unix(&usock),
+			vec![SocketAddress::new_unix(&usock)],
 			SocketAddress::new_unix("./never.sock"),
 			None,
 		)
@@ -63,6 +74,11 @@ fn reaper_works() {
 	let contents = fs::read(integration::PIVOT_OK_SUCCESS_FILE).unwrap();
 	assert_eq!(std::str::from_utf8(&contents).unwrap(), msg);
 	assert!(fs::remove_file(integration::PIVOT_OK_SUCCESS_FILE).is_ok());
+
+	// The pivot found the pivot info through the environment
+	let namespace_path: PathWrapper =
+		format!("{}.namespace", integration::PIVOT_OK_SUCCESS_FILE).into();
+	assert_eq!(fs::read_to_string(&*namespace_path).unwrap(), "reaper-works");
 }
 
 #[test]
@@ -72,6 +88,8 @@ fn reaper_handles_non_zero_exits() {
 	let usock: PathWrapper = "./reaper_handles_non_zero_exits.sock".into();
 	let manifest_path: PathWrapper =
 		"./reaper_handles_non_zero_exits.manifest".into();
+	let pivot_info_path: PathWrapper =
+		"./reaper_handles_non_zero_exits.pivot_info".into();
 
 	// For our sanity, ensure the secret does not yet exist
 	drop(fs::remove_file(&*secret_path));
@@ -81,6 +99,7 @@ fn reaper_handles_non_zero_exits() {
 		(*secret_path).to_string(),
 		(*manifest_path).to_string(),
 		PIVOT_ABORT_PATH.to_string(),
+		(*pivot_info_path).to_string(),
 	);
 
 	// Make sure we have written everything necessary to pivot, except the
@@ -88,11 +107,12 @@ fn reaper_handles_non_zero_exits() {
 	handles.put_manifest_envelope(&Default::default()).unwrap();
 	assert!(handles.pivot_exists());
 
+	let enclave_addr = SocketAddress::new_unix(&usock);
 	let reaper_handle = std::thread::spawn(move || {
 		Reaper::execute(
 			&handles,
 			Box::new(MockNsm),
-			SocketAddress::new_unix(&usock),
+			vec![enclave_addr],
 			SocketAddress::new_unix("./never.sock"),
 			None,
 		)
@@ -116,6 +136,8 @@ fn reaper_handles_non_zero_exits() {
 	));
 
 	assert!(reaper_handle.is_finished());
+	// The enclave server was shut down and cleaned up its socket.
+	assert!(!Path::new(&*usock).exists());
 }
 
 #[test]
@@ -123,6 +145,8 @@ fn reaper_handles_panic() {
 	let secret_path: PathWrapper = "./reaper_handles_panics.secret".into();
 	let usock: PathWrapper = "./reaper_handles_panics.sock".into();
 	let manifest_path: PathWrapper = "./reaper_handles_panics.manifest".into();
+	let pivot_info_path: PathWrapper =
+		"./reaper_handles_panics.pivot_info".into();
 
 	// For our sanity, ensure the secret does not yet exist
 	drop(fs::remove_file(&*secret_path));
@@ -132,6 +156,7 @@ fn reaper_handles_panic() {
 		secret_path.to_string(),
 		manifest_path.to_string(),
 		PIVOT_PANIC_PATH.to_string(),
+		pivot_info_path.to_string(),
 	);
 
 	// Make sure we have written everything necessary to pivot, except the
@@ -143,7 +168,7 @@ fn reaper_handles_panic() {
 		Reaper::execute(
 			&handles,
 			Box::new(MockNsm),
-			SocketAddress::new_unix(&usock),
+			vec![SocketAddress::new_unix(&usock)],
 			SocketAddress::new_unix("./never.sock"),
 			None,
 		)
@@ -181,3 +206,101 @@ fn can_restart_panicking_pivot() {
 
 	// Write the secret
 }
+
+#[test]
+fn reaper_execute_async_serves_and_shuts_down() {
+	let secret_path: PathWrapper = "./reaper_execute_async.secret".into();
+	let usock: PathWrapper = "./reaper_execute_async.sock".into();
+	let manifest_path: PathWrapper = "./reaper_execute_async.manifest".into();
+	let pivot_info_path: PathWrapper =
+		"./reaper_execute_async.pivot_info".into();
+
+	// For our sanity, ensure the secret does not yet exist
+	drop(fs::remove_file(&*secret_path));
+
+	let handles = Handles::new(
+		"eph_path".to_string(),
+		(*secret_path).to_string(),
+		(*manifest_path).to_string(),
+		PIVOT_ABORT_PATH.to_string(),
+		(*pivot_info_path).to_string(),
+	);
+	handles.put_manifest_envelope(&Default::default()).unwrap();
+
+	let enclave_addr = SocketAddress::new_unix(&usock);
+	let reaper_handle = std::thread::spawn(move || {
+		Reaper::execute_async(
+			&handles,
+			Box::new(MockNsm),
+			vec![enclave_addr],
+			SocketAddress::new_unix("./never.sock"),
+			None,
+			TimeVal::seconds(5),
+		)
+	});
+
+	// Give the enclave server time to bind to the socket
+	std::thread::sleep(std::time::Duration::from_secs(1));
+	let client =
+		Client::new(SocketAddress::new_unix(&usock), TimeVal::seconds(1));
+	let response = client
+		.send(&borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap())
+		.unwrap();
+	assert_eq!(
+		ProtocolMsg::try_from_slice(&response).unwrap(),
+		ProtocolMsg::StatusResponse(ProtocolPhase::WaitingForBootInstruction)
+	);
+
+	// Let the pivot run and exit
+	fs::write(&*secret_path, b"super dank tank secret tech").unwrap();
+	std::thread::sleep(std::time::Duration::from_secs(
+		REAPER_EXIT_DELAY_IN_SECONDS * 2,
+	));
+
+	assert!(reaper_handle.is_finished());
+	// The enclave server was shut down and cleaned up its socket.
+	assert!(!Path::new(&*usock).exists());
+}
+
+#[test]
+fn reaper_refuses_stale_pivot_info() {
+	let secret_path: PathWrapper = "./reaper_refuses_stale.secret".into();
+	let usock: PathWrapper = "./reaper_refuses_stale.sock".into();
+	let manifest_path: PathWrapper = "./reaper_refuses_stale.manifest".into();
+	let pivot_info_path: PathWrapper =
+		"./reaper_refuses_stale.pivot_info".into();
+
+	let handles = Handles::new(
+		"eph_path".to_string(),
+		(*secret_path).to_string(),
+		(*manifest_path).to_string(),
+		PIVOT_LOOP_PATH.to_string(),
+		(*pivot_info_path).to_string(),
+	);
+
+	// Pivot info left over from an earlier boot with another manifest
+	let mut stale = ManifestEnvelope::default();
+	stale.manifest.namespace.name = "stale".to_string();
+	handles.put_pivot_info(&stale.manifest).unwrap();
+
+	let mut manifest_envelope = ManifestEnvelope::default();
+	manifest_envelope.manifest.namespace.name = "current".to_string();
+	handles.put_manifest_envelope(&manifest_envelope).unwrap();
+	fs::write(&*secret_path, b"super dank tank secret tech").unwrap();
+
+	let reaper_handle = std::thread::spawn(move || {
+		Reaper::execute(
+			&handles,
+			Box::new(MockNsm),
+			vec![SocketAddress::new_unix(&usock)],
+			SocketAddress::new_unix("./never.sock"),
+			None,
+		)
+	});
+
+	// The looping pivot would keep the reaper running if it was spawned
+	std::thread::sleep(std::time::Duration::from_secs(
+		REAPER_EXIT_DELAY_IN_SECONDS,
+	));
+	assert!(reaper_handle.is_finished());
+}
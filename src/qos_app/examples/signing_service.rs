@@ -0,0 +1,246 @@
+//! Reference pivot signing payloads with a key derived from the Quorum Key,
+//! and the checks its clients make before trusting the signatures.
+//!
+//! The pivot gets its signing key from [`Executor::app_secret`], so the key
+//! stays the same across restarts and re-provisioning for as long as the
+//! Quorum Key and the namespace do. At startup it requests an attestation
+//! document embedding the public key, which it hands out with the key.
+//!
+//! A client checks once, with [`verify_signing_key`], that the document was
+//! produced by a genuine Nitro enclave running the approved manifest and that
+//! it embeds the key. From then on it checks signatures with
+//! [`verify_signature`] alone.
+//!
+//! Run it as the pivot of a manifest, or test it with:
+//!
+//! ```sh
+//! cargo test -p qos_app --features mock --example signing_service
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_app::{AppError, AppServer, Executor, ExecutorError, Handler};
+use qos_core::{
+	executor::AppAttestationData,
+	protocol::{services::boot::Manifest, QosHash},
+};
+use qos_nsm::nitro::{
+	attestation_doc_from_der, verify_attestation_doc_against_user_input,
+	AttestError,
+};
+use qos_p256::{P256Error, P256Pair, P256Public};
+
+/// Name of the app secret the signing key is derived from. Renaming it
+/// changes the key.
+const SIGNING_KEY_SECRET: &str = "signing-service";
+
+/// Requests of the signing service.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub enum SigningRequest {
+	/// Get the signing key, answered with [`SigningResponse::Key`].
+	Key,
+	/// Sign `payload`, answered with [`SigningResponse::Signature`].
+	Sign {
+		/// Bytes to sign.
+		payload: Vec<u8>,
+	},
+}
+
+/// Responses of the signing service.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub enum SigningResponse {
+	/// The signing key.
+	Key(SigningKey),
+	/// Signature over the payload of a [`SigningRequest::Sign`].
+	Signature(Vec<u8>),
+}
+
+/// The public signing key of the service, with the attestation document
+/// proving it comes from the enclave.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SigningKey {
+	/// Encoded [`P256Public`] key.
+	pub public_key: Vec<u8>,
+	/// COSE signed attestation document whose `user_data` is the
+	/// [`AppAttestationData`] for `public_key`.
+	pub attestation_doc: Vec<u8>,
+}
+
+/// The pivot.
+pub struct SigningService {
+	pair: P256Pair,
+	key: SigningKey,
+}
+
+impl SigningService {
+	/// Derive the signing key from the Quorum Key and attest to it.
+	pub fn from_executor(executor: &Executor) -> Result<Self, ExecutorError> {
+		let secret = executor.app_secret(SIGNING_KEY_SECRET)?;
+		let pair = P256Pair::from_master_seed(&secret)
+			.expect("any 32 bytes are a valid master seed. qed.");
+		let attestation_doc =
+			executor.attestation_doc(pair.public_key().to_bytes(), None)?;
+
+		Ok(Self::new(pair, attestation_doc))
+	}
+
+	/// Create a new instance of [`Self`] signing with `pair`.
+	pub fn new(pair: P256Pair, attestation_doc: Vec<u8>) -> Self {
+		let public_key = pair.public_key().to_bytes();
+		Self { pair, key: SigningKey { public_key, attestation_doc } }
+	}
+}
+
+impl Handler for SigningService {
+	type Request = SigningRequest;
+	type Response = SigningResponse;
+
+	fn handle(
+		&mut self,
+		request: SigningRequest,
+	) -> Result<SigningResponse, AppError> {
+		match request {
+			SigningRequest::Key => Ok(SigningResponse::Key(self.key.clone())),
+			SigningRequest::Sign { payload } => self
+				.pair
+				.sign(&payload)
+				.map(SigningResponse::Signature)
+				.map_err(|e| AppError::Handler(format!("{e:?}"))),
+		}
+	}
+}
+
+/// Why a client does not trust a signing key or signature.
+#[derive(Debug)]
+pub enum VerifyError {
+	/// The attestation document is not from an enclave running the
+	/// manifest, or does not embed the key.
+	Attestation(AttestError),
+	/// The public key or signature is invalid.
+	P256(P256Error),
+}
+
+/// Client side: check that `key` belongs to a pivot running under
+/// `manifest`, which the client approved or got from a source it trusts, in a
+/// genuine Nitro enclave. `validation_time` is the current time in seconds
+/// since the unix epoch, to check the certificates of the document against.
+pub fn verify_signing_key(
+	manifest: &Manifest,
+	key: &SigningKey,
+	validation_time: u64,
+) -> Result<P256Public, VerifyError> {
+	let attestation_doc = attestation_doc_from_der(
+		&key.attestation_doc,
+		&manifest.enclave.aws_root_certificate,
+		validation_time,
+	)
+	.map_err(VerifyError::Attestation)?;
+
+	let user_data = borsh::to_vec(&AppAttestationData {
+		manifest_hash: manifest.qos_hash(),
+		app_data: key.public_key.clone(),
+	})
+	.expect("AppAttestationData can always be serialized. qed.");
+	verify_attestation_doc_against_user_input(
+		&attestation_doc,
+		&user_data,
+		&manifest.enclave.pcr0,
+		&manifest.enclave.pcr1,
+		&manifest.enclave.pcr2,
+		&manifest.enclave.pcr3,
+	)
+	.map_err(VerifyError::Attestation)?;
+
+	P256Public::from_bytes(&key.public_key).map_err(VerifyError::P256)
+}
+
+/// Client side: check `signature` over `payload` with a key checked by
+/// [`verify_signing_key`].
+pub fn verify_signature(
+	public_key: &P256Public,
+	payload: &[u8],
+	signature: &[u8],
+) -> Result<(), VerifyError> {
+	public_key.verify(payload, signature).map_err(VerifyError::P256)
+}
+
+fn main() {
+	let executor =
+		Executor::from_env().expect("the pivot must be started by QOS");
+	let service = SigningService::from_executor(&executor)
+		.expect("failed to set up the signing key");
+
+	AppServer::run(service).expect("the signing service stopped");
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+	use qos_app::TestHarness;
+	use qos_nsm::{
+		mock::{
+			MOCK_NSM_ATTESTATION_DOCUMENT, MOCK_PCR0, MOCK_PCR1, MOCK_PCR2,
+			MOCK_PCR3, MOCK_SECONDS_SINCE_EPOCH,
+		},
+		nitro::aws_root_cert,
+	};
+
+	use super::*;
+
+	fn harness() -> TestHarness<SigningRequest, SigningResponse> {
+		let service = SigningService::new(
+			P256Pair::generate().unwrap(),
+			MOCK_NSM_ATTESTATION_DOCUMENT.to_vec(),
+		);
+		TestHarness::new(service)
+	}
+
+	fn signing_key(
+		harness: &TestHarness<SigningRequest, SigningResponse>,
+	) -> SigningKey {
+		match harness.send(&SigningRequest::Key).unwrap() {
+			SigningResponse::Key(key) => key,
+			SigningResponse::Signature(_) => panic!("expected the key"),
+		}
+	}
+
+	#[test]
+	fn signatures_verify_with_the_served_key() {
+		let harness = harness();
+		let public_key =
+			P256Public::from_bytes(&signing_key(&harness).public_key).unwrap();
+
+		let payload = b"transfer 10 to alice".to_vec();
+		let SigningResponse::Signature(signature) = harness
+			.send(&SigningRequest::Sign { payload: payload.clone() })
+			.unwrap()
+		else {
+			panic!("expected a signature")
+		};
+
+		assert!(verify_signature(&public_key, &payload, &signature).is_ok());
+		assert!(
+			verify_signature(&public_key, b"transfer 99", &signature).is_err()
+		);
+	}
+
+	#[test]
+	fn keys_are_only_trusted_if_the_attestation_doc_embeds_them() {
+		let harness = harness();
+		let mut manifest = Manifest::default();
+		manifest.enclave.pcr0 = qos_hex::decode(MOCK_PCR0).unwrap();
+		manifest.enclave.pcr1 = qos_hex::decode(MOCK_PCR1).unwrap();
+		manifest.enclave.pcr2 = qos_hex::decode(MOCK_PCR2).unwrap();
+		manifest.enclave.pcr3 = qos_hex::decode(MOCK_PCR3).unwrap();
+		manifest.enclave.aws_root_certificate = aws_root_cert().unwrap();
+
+		// The mock document is genuine, but was not requested for this key
+		// and manifest
+		assert!(matches!(
+			verify_signing_key(
+				&manifest,
+				&signing_key(&harness),
+				MOCK_SECONDS_SINCE_EPOCH
+			),
+			Err(VerifyError::Attestation(AttestError::DifferentUserData))
+		));
+	}
+}
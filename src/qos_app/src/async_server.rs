@@ -0,0 +1,227 @@
+//! Async server listening on the app socket and routing requests to an
+//! [`AsyncHandler`].
+//!
+//! Unlike [`crate::AppServer`], requests are handled concurrently, each in
+//! its own task.
+
+use std::{
+	future::Future,
+	time::{Duration, Instant},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_core::{
+	async_server::{AsyncRequestProcessor, AsyncSocketServer},
+	io::SocketAddress,
+	protocol::app_health::{AppHealth, APP_HEALTH_REQUEST},
+	server::{SocketServerError, SOCKET_SERVER_TIMEOUT_SECS},
+};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{
+	middleware::{Chain, Middleware},
+	server::{app_socket, respond},
+	AppError, AppResponse,
+};
+
+/// Handles the requests of a pivot, many at a time.
+pub trait AsyncHandler: Send + Sync + 'static {
+	/// Requests the pivot accepts.
+	type Request: BorshDeserialize + Send;
+	/// Responses the pivot sends.
+	type Response: BorshSerialize + Send;
+
+	/// Handle a single request. May be called again before an earlier call
+	/// has completed. An error is sent back to the client as
+	/// [`AppResponse::Err`].
+	fn handle(
+		&self,
+		request: Self::Request,
+	) -> impl Future<Output = Result<Self::Response, AppError>> + Send;
+
+	/// Health of the pivot, reported to health probes. Defaults to
+	/// [`AppHealth::Ready`], since a pivot that can answer is running.
+	fn health(&self) -> impl Future<Output = AppHealth> + Send {
+		std::future::ready(AppHealth::Ready)
+	}
+}
+
+/// [`AsyncRequestProcessor`] decoding requests for an [`AsyncHandler`] and
+/// encoding its responses.
+pub struct AsyncAppProcessor<H> {
+	handler: H,
+	middleware: Chain,
+}
+
+impl<H: AsyncHandler> AsyncAppProcessor<H> {
+	/// Create a new instance of [`Self`].
+	pub fn new(handler: H) -> Self {
+		Self { handler, middleware: Chain::default() }
+	}
+
+	/// Run `middleware` for every request, after any middleware already
+	/// added.
+	#[must_use]
+	pub fn with_middleware(
+		mut self,
+		middleware: impl Middleware + 'static,
+	) -> Self {
+		self.middleware.push(middleware);
+		self
+	}
+}
+
+impl<H: AsyncHandler> AsyncRequestProcessor for AsyncAppProcessor<H> {
+	async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+		if request == APP_HEALTH_REQUEST {
+			return self.handler.health().await.to_response();
+		}
+
+		let received = Instant::now();
+		let response = match self.middleware.on_request(&request) {
+			Ok(()) => match borsh::from_slice(&request) {
+				Ok(request) => self.handler.handle(request).await.into(),
+				Err(_) => AppResponse::Err(AppError::InvalidRequest),
+			},
+			Err(err) => AppResponse::Err(err),
+		};
+
+		respond(&self.middleware, &request, &response, received)
+	}
+}
+
+/// Async server for a pivot.
+pub struct AsyncAppServer;
+
+impl AsyncAppServer {
+	/// Listen on the socket given by [`crate::USOCK`] in the arguments of
+	/// the process, or [`qos_core::SEC_APP_SOCK`] if there is none, and serve
+	/// requests with `handler` until the process receives `SIGTERM`.
+	///
+	/// Must be called from within a tokio runtime.
+	pub async fn run<H: AsyncHandler>(
+		handler: H,
+	) -> Result<(), SocketServerError> {
+		let args: Vec<String> = std::env::args().collect();
+		Self::listen(app_socket(&args), handler, terminated()).await
+	}
+
+	/// Listen on `addr` and serve requests with `handler` until `shutdown`
+	/// resolves. Returns once the requests already accepted have been
+	/// responded to.
+	///
+	/// Must be called from within a tokio runtime.
+	pub async fn listen<H: AsyncHandler>(
+		addr: SocketAddress,
+		handler: H,
+		shutdown: impl Future<Output = ()>,
+	) -> Result<(), SocketServerError> {
+		AsyncSocketServer::listen_with_shutdown(
+			addr,
+			AsyncAppProcessor::new(handler),
+			Duration::from_secs(SOCKET_SERVER_TIMEOUT_SECS.unsigned_abs()),
+			shutdown,
+		)
+		.await
+	}
+}
+
+/// Resolves once the process receives `SIGTERM`, or never if the signal
+/// cannot be listened for.
+async fn terminated() {
+	match signal(SignalKind::terminate()) {
+		Ok(mut sigterm) => {
+			sigterm.recv().await;
+		}
+		Err(err) => {
+			eprintln!("AsyncAppServer: cannot listen for SIGTERM: {err:?}");
+			std::future::pending::<()>().await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+
+	use qos_core::{
+		async_client::AsyncClient,
+		io::{TimeVal, TimeValLike},
+	};
+	use tokio::sync::Barrier;
+
+	use super::*;
+
+	/// Responds to each request once as many requests as `barrier` waits for
+	/// are in flight at the same time.
+	struct Rendezvous {
+		barrier: Barrier,
+		handled: Arc<AtomicUsize>,
+	}
+
+	impl AsyncHandler for Rendezvous {
+		type Request = u32;
+		type Response = u32;
+
+		async fn handle(&self, request: u32) -> Result<u32, AppError> {
+			self.barrier.wait().await;
+			self.handled.fetch_add(1, Ordering::SeqCst);
+			Ok(request * 2)
+		}
+	}
+
+	#[tokio::test]
+	async fn handles_requests_concurrently_and_shuts_down_gracefully() {
+		let path = "./handles_requests_concurrently_and_shuts_down.sock";
+		let addr = SocketAddress::new_unix(path);
+		let handled = Arc::new(AtomicUsize::new(0));
+		let rendezvous =
+			Rendezvous { barrier: Barrier::new(3), handled: handled.clone() };
+		let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+		let server = tokio::spawn(AsyncAppServer::listen(
+			addr.clone(),
+			rendezvous,
+			async {
+				let _ = shutdown_rx.await;
+			},
+		));
+		// Wait for the server to bind
+		while !std::path::Path::new(path).exists() {
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		// None of the requests is answered until all three are in flight
+		let requests: Vec<_> = (1..=3u32)
+			.map(|i| {
+				let client =
+					AsyncClient::new(addr.clone(), TimeVal::seconds(5));
+				tokio::spawn(async move {
+					client.send(&borsh::to_vec(&i).unwrap()).await.unwrap()
+				})
+			})
+			.collect();
+		for (i, request) in (1..=3u32).zip(requests) {
+			let response = request.await.unwrap();
+			assert_eq!(
+				borsh::from_slice::<AppResponse<u32>>(&response).unwrap(),
+				AppResponse::Ok(i * 2)
+			);
+		}
+
+		// Health probes are answered without reaching the handler
+		let client = AsyncClient::new(addr.clone(), TimeVal::seconds(5));
+		let response = client.send(APP_HEALTH_REQUEST).await.unwrap();
+		assert_eq!(
+			AppHealth::from_response(&response).unwrap(),
+			AppHealth::Ready
+		);
+
+		shutdown_tx.send(()).unwrap();
+		server.await.unwrap().unwrap();
+		assert_eq!(handled.load(Ordering::SeqCst), 3);
+		assert!(!std::path::Path::new(path).exists());
+	}
+}
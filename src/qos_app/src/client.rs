@@ -0,0 +1,79 @@
+//! Typed client for a pivot served by [`crate::AppServer`].
+
+use std::marker::PhantomData;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_core::{
+	client::{Client, ClientError},
+	io::{SocketAddress, TimeVal, TimeValLike},
+	protocol::{ProtocolError, ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS},
+};
+
+use crate::{AppError, AppResponse};
+
+/// Error variants for [`AppClient`].
+#[derive(Debug)]
+pub enum AppClientError {
+	/// [`ClientError`] wrapper.
+	Client(ClientError),
+	/// The response could not be decoded.
+	InvalidResponse,
+	/// The pivot responded with an error.
+	App(AppError),
+	/// QOS could not forward the request to the pivot.
+	Protocol(ProtocolError),
+}
+
+impl From<ClientError> for AppClientError {
+	fn from(err: ClientError) -> Self {
+		Self::Client(err)
+	}
+}
+
+/// Client sending `Req`s to a pivot and decoding its `Resp`s.
+pub struct AppClient<Req, Resp> {
+	client: Client,
+	_phantom: PhantomData<(Req, Resp)>,
+}
+
+impl<Req, Resp> AppClient<Req, Resp>
+where
+	Req: BorshSerialize,
+	Resp: BorshDeserialize,
+{
+	/// Create a new instance of [`Self`], with the same timeout QOS uses
+	/// for the app socket.
+	#[must_use]
+	pub fn new(addr: SocketAddress) -> Self {
+		Self::with_timeout(
+			addr,
+			TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS),
+		)
+	}
+
+	/// Create a new instance of [`Self`] with a custom `timeout` for each
+	/// send and receive.
+	#[must_use]
+	pub fn with_timeout(addr: SocketAddress, timeout: TimeVal) -> Self {
+		Self { client: Client::new(addr, timeout), _phantom: PhantomData }
+	}
+
+	/// Send `request` and wait for the response.
+	pub fn send(&self, request: &Req) -> Result<Resp, AppClientError> {
+		let request = borsh::to_vec(request)
+			.map_err(|e| AppClientError::Client(ClientError::BorshError(e)))?;
+		let response = self.client.send(&request)?;
+
+		Self::decode(&response)
+	}
+
+	/// Decode a response of the pivot, for example one forwarded by the
+	/// host rather than received by [`Self::send`].
+	pub fn decode(response: &[u8]) -> Result<Resp, AppClientError> {
+		match AppResponse::<Resp>::try_from_slice(response) {
+			Ok(AppResponse::Ok(response)) => Ok(response),
+			Ok(AppResponse::Err(err)) => Err(AppClientError::App(err)),
+			Err(_) => Err(AppClientError::InvalidResponse),
+		}
+	}
+}
@@ -0,0 +1,16 @@
+//! Errors a pivot sends back to its clients.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Error handling a request, sent back to the client in place of a response.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum AppError {
+	/// The request could not be decoded as a request of the pivot.
+	InvalidRequest,
+	/// The response of the handler could not be encoded.
+	InvalidResponse,
+	/// The request is not supported by the pivot.
+	Unsupported,
+	/// The handler failed, with a message for the client.
+	Handler(String),
+}
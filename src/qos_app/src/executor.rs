@@ -0,0 +1,262 @@
+//! Client for the executor that started the pivot, for requests only the
+//! pivot can make: attestation documents embedding app data, so the pivot can
+//! prove to its own clients that some data, such as a public key it just
+//! generated, comes from the enclave, and secrets derived from the Quorum Key
+//! that stay the same across restarts and re-provisioning.
+
+use borsh::BorshDeserialize;
+use qos_core::{
+	client::{Client, ClientError},
+	executor::ExecutorMsg,
+	io::{SocketAddress, TimeVal, TimeValLike},
+	protocol::{ProtocolError, ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS},
+	EXECUTOR_SOCKET_ENV,
+};
+use qos_nsm::types::{NsmErrorCode, NsmResponse};
+use zeroize::Zeroizing;
+
+pub use qos_core::executor::{AppAttestationData, MAX_APP_DATA_LEN};
+pub use qos_p256::P256_SECRET_LEN;
+
+/// Error variants for [`Executor`].
+#[derive(Debug)]
+pub enum ExecutorError {
+	/// [`EXECUTOR_SOCKET_ENV`] is not set, so the pivot was not started by
+	/// QOS.
+	MissingExecutorSocket,
+	/// [`ClientError`] wrapper.
+	Client(ClientError),
+	/// The response could not be decoded.
+	InvalidResponse,
+	/// The executor could not handle the request.
+	Protocol(ProtocolError),
+	/// The NSM could not produce an attestation document.
+	Nsm(NsmErrorCode),
+}
+
+impl From<ClientError> for ExecutorError {
+	fn from(err: ClientError) -> Self {
+		Self::Client(err)
+	}
+}
+
+/// Client for the executor that started the pivot.
+pub struct Executor {
+	client: Client,
+}
+
+impl Executor {
+	/// Create a new instance of [`Self`] for the socket the executor passes
+	/// to the pivot in [`EXECUTOR_SOCKET_ENV`].
+	pub fn from_env() -> Result<Self, ExecutorError> {
+		let path = std::env::var(EXECUTOR_SOCKET_ENV)
+			.map_err(|_| ExecutorError::MissingExecutorSocket)?;
+
+		Ok(Self::new(SocketAddress::new_unix(&path)))
+	}
+
+	/// Create a new instance of [`Self`] for the executor listening on
+	/// `addr`.
+	#[must_use]
+	pub fn new(addr: SocketAddress) -> Self {
+		let timeout = TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS);
+		Self { client: Client::new(addr, timeout) }
+	}
+
+	/// Request a fresh attestation document whose `user_data` is the borsh
+	/// encoded [`AppAttestationData`] for `app_data`, which must be at most
+	/// [`MAX_APP_DATA_LEN`] bytes. Returns the COSE signed document.
+	pub fn attestation_doc(
+		&self,
+		app_data: Vec<u8>,
+		nonce: Option<Vec<u8>>,
+	) -> Result<Vec<u8>, ExecutorError> {
+		let request = borsh::to_vec(&ExecutorMsg::AppAttestationRequest {
+			app_data,
+			nonce,
+		})
+		.map_err(|e| ExecutorError::Client(ClientError::BorshError(e)))?;
+		let response = self.client.send(&request)?;
+
+		match ExecutorMsg::try_from_slice(&response) {
+			Ok(ExecutorMsg::AppAttestationResponse {
+				nsm_response: NsmResponse::Attestation { document },
+			}) => Ok(document),
+			Ok(ExecutorMsg::AppAttestationResponse {
+				nsm_response: NsmResponse::Error(code),
+			}) => Err(ExecutorError::Nsm(code)),
+			Ok(ExecutorMsg::ErrorResponse(err)) => {
+				Err(ExecutorError::Protocol(err))
+			}
+			_ => Err(ExecutorError::InvalidResponse),
+		}
+	}
+
+	/// Get the secret named `name`, derived from the Quorum Key for the
+	/// namespace of the manifest. The same name always gives the same secret
+	/// for a given Quorum Key and namespace, and different names give
+	/// independent secrets.
+	pub fn app_secret(
+		&self,
+		name: &str,
+	) -> Result<Zeroizing<[u8; P256_SECRET_LEN]>, ExecutorError> {
+		let request = borsh::to_vec(&ExecutorMsg::AppSecretRequest {
+			name: name.to_string(),
+		})
+		.map_err(|e| ExecutorError::Client(ClientError::BorshError(e)))?;
+		let response = Zeroizing::new(self.client.send(&request)?);
+
+		match ExecutorMsg::try_from_slice(&response) {
+			Ok(ExecutorMsg::AppSecretResponse { secret }) => {
+				Ok(Zeroizing::new(secret))
+			}
+			Ok(ExecutorMsg::ErrorResponse(err)) => {
+				Err(ExecutorError::Protocol(err))
+			}
+			_ => Err(ExecutorError::InvalidResponse),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::{
+		executor::ExecutorProcessor, handles::Handles,
+		protocol::services::boot::ManifestEnvelope, server::SocketServer,
+	};
+	use qos_nsm::{
+		mock::{MockNsm, ScriptedNsm, MOCK_NSM_ATTESTATION_DOCUMENT},
+		types::{NsmErrorCode, NsmRequest},
+	};
+	use qos_p256::P256Pair;
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+
+	#[test]
+	fn attestation_doc_is_requested_from_the_executor() {
+		let manifest_file: PathWrapper =
+			"./attestation_doc_is_requested.manifest".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			"quorum".to_string(),
+			(*manifest_file).to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		let addr =
+			SocketAddress::new_unix("./attestation_doc_is_requested.sock");
+		let server = SocketServer::spawn(
+			vec![addr.clone()],
+			ExecutorProcessor::new(Box::new(MockNsm), handles.clone()),
+		)
+		.unwrap();
+		let executor = Executor::new(addr);
+
+		assert!(matches!(
+			executor.attestation_doc(vec![1; 33], None),
+			Err(ExecutorError::Protocol(
+				ProtocolError::FailedToGetManifestEnvelope
+			))
+		));
+
+		handles.put_manifest_envelope(&ManifestEnvelope::default()).unwrap();
+		assert_eq!(
+			executor.attestation_doc(vec![1; 33], Some(vec![2])).unwrap(),
+			MOCK_NSM_ATTESTATION_DOCUMENT.to_vec()
+		);
+		assert!(matches!(
+			executor.attestation_doc(vec![1; MAX_APP_DATA_LEN + 1], None),
+			Err(ExecutorError::Protocol(ProtocolError::OversizedAppData))
+		));
+
+		server.shutdown();
+	}
+
+	#[test]
+	fn attestation_failures_are_returned_to_the_app() {
+		let manifest_file: PathWrapper =
+			"./attestation_failures_are_returned.manifest".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			"quorum".to_string(),
+			(*manifest_file).to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		handles.put_manifest_envelope(&ManifestEnvelope::default()).unwrap();
+		let nsm = ScriptedNsm::new()
+			.then_fail(NsmErrorCode::InternalError)
+			.then_fail(NsmErrorCode::InputTooLarge);
+		let addr =
+			SocketAddress::new_unix("./attestation_failures_are_returned.sock");
+		let server = SocketServer::spawn(
+			vec![addr.clone()],
+			ExecutorProcessor::new(Box::new(nsm.clone()), handles),
+		)
+		.unwrap();
+		let executor = Executor::new(addr);
+
+		assert!(matches!(
+			executor.attestation_doc(vec![1], None),
+			Err(ExecutorError::Nsm(NsmErrorCode::InternalError))
+		));
+		assert!(matches!(
+			executor.attestation_doc(vec![1], None),
+			Err(ExecutorError::Nsm(NsmErrorCode::InputTooLarge))
+		));
+		// The NSM recovers, so a retry succeeds
+		assert_eq!(
+			executor.attestation_doc(vec![1], Some(vec![2])).unwrap(),
+			MOCK_NSM_ATTESTATION_DOCUMENT.to_vec()
+		);
+		assert!(nsm
+			.requests()
+			.iter()
+			.all(|request| matches!(request, NsmRequest::Attestation { .. })));
+		assert_eq!(nsm.requests().len(), 3);
+
+		server.shutdown();
+	}
+
+	#[test]
+	fn app_secret_is_requested_from_the_executor() {
+		let quorum_file: PathWrapper =
+			"./app_secret_is_requested.quorum".into();
+		let manifest_file: PathWrapper =
+			"./app_secret_is_requested.manifest".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			(*quorum_file).to_string(),
+			(*manifest_file).to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		handles.put_manifest_envelope(&ManifestEnvelope::default()).unwrap();
+		let addr = SocketAddress::new_unix("./app_secret_is_requested.sock");
+		let server = SocketServer::spawn(
+			vec![addr.clone()],
+			ExecutorProcessor::new(Box::new(MockNsm), handles.clone()),
+		)
+		.unwrap();
+		let executor = Executor::new(addr);
+
+		assert!(matches!(
+			executor.app_secret("tls"),
+			Err(ExecutorError::Protocol(ProtocolError::FailedToGetQuorumKey(
+				_
+			)))
+		));
+
+		handles.put_quorum_key(&P256Pair::generate().unwrap()).unwrap();
+		let secret = executor.app_secret("tls").unwrap();
+		assert_eq!(secret, executor.app_secret("tls").unwrap());
+		assert_ne!(secret, executor.app_secret("db").unwrap());
+		assert!(matches!(
+			executor.app_secret(""),
+			Err(ExecutorError::Protocol(ProtocolError::InvalidAppSecretName))
+		));
+
+		server.shutdown();
+	}
+}
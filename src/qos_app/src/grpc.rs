@@ -0,0 +1,179 @@
+//! Serve unary gRPC methods to clients of the host's gRPC-Web bridge, see
+//! [`qos_core::protocol::app_grpc`].
+//!
+//! Methods receive and return encoded protobuf messages, so services can use
+//! the message types generated by `prost` or `tonic-build` as they are:
+//!
+//! ```ignore
+//! let router = GrpcRouter::new().unary("/echo.Echo/Say", |message| {
+//!     let request = SayRequest::decode(message)
+//!         .map_err(|e| GrpcStatus::new(GrpcCode::InvalidArgument, &e.to_string()))?;
+//!     Ok(SayResponse { text: request.text }.encode_to_vec())
+//! });
+//! GrpcServer::run(router).unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+
+use qos_core::{
+	io::SocketAddress,
+	protocol::{
+		app_grpc::{AppGrpcRequest, AppGrpcResponse, GrpcCode, GrpcStatus},
+		app_health::{AppHealth, APP_HEALTH_REQUEST},
+	},
+	server::{
+		RequestProcessor, SocketServer, SocketServerError, SocketServerHandle,
+	},
+};
+
+use crate::server::app_socket;
+
+/// A unary gRPC method: takes the encoded request message and returns the
+/// encoded response message.
+type UnaryMethod =
+	Box<dyn FnMut(&[u8]) -> Result<Vec<u8>, GrpcStatus> + Send + 'static>;
+
+/// [`RequestProcessor`] routing [`AppGrpcRequest`]s to unary methods by path.
+/// Calls to unknown paths fail with [`GrpcCode::Unimplemented`].
+#[derive(Default)]
+pub struct GrpcRouter {
+	methods: BTreeMap<String, UnaryMethod>,
+}
+
+impl GrpcRouter {
+	/// Create a new instance of [`Self`] without any methods.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Serve `method` at `path`, e.g. `/echo.Echo/Say`. Replaces any method
+	/// already served at `path`.
+	#[must_use]
+	pub fn unary<F>(mut self, path: &str, method: F) -> Self
+	where
+		F: FnMut(&[u8]) -> Result<Vec<u8>, GrpcStatus> + Send + 'static,
+	{
+		self.methods.insert(path.to_string(), Box::new(method));
+		self
+	}
+
+	fn call(&mut self, request: &AppGrpcRequest) -> AppGrpcResponse {
+		let Some(method) = self.methods.get_mut(&request.path) else {
+			return AppGrpcResponse::error(GrpcStatus::new(
+				GrpcCode::Unimplemented,
+				&format!("unknown method {}", request.path),
+			));
+		};
+
+		match method(&request.message) {
+			Ok(message) => AppGrpcResponse::ok(message),
+			Err(status) => AppGrpcResponse::error(status),
+		}
+	}
+}
+
+impl RequestProcessor for GrpcRouter {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		if request == APP_HEALTH_REQUEST {
+			return AppHealth::Ready.to_response();
+		}
+
+		let response = match borsh::from_slice::<AppGrpcRequest>(&request) {
+			Ok(request) => self.call(&request),
+			Err(_) => AppGrpcResponse::error(GrpcStatus::new(
+				GrpcCode::Internal,
+				"invalid gRPC request",
+			)),
+		};
+
+		borsh::to_vec(&response)
+			.expect("AppGrpcResponse can always be serialized. qed.")
+	}
+}
+
+/// Server for a pivot serving gRPC methods.
+pub struct GrpcServer;
+
+impl GrpcServer {
+	/// Listen on the socket given by [`crate::USOCK`] in the arguments of
+	/// the process, or [`qos_core::SEC_APP_SOCK`] if there is none, and serve
+	/// calls with `router`. Only returns on error.
+	pub fn run(router: GrpcRouter) -> Result<(), SocketServerError> {
+		let args: Vec<String> = std::env::args().collect();
+		Self::listen(app_socket(&args), router)
+	}
+
+	/// Listen on `addr` and serve calls with `router`. Only returns on
+	/// error.
+	pub fn listen(
+		addr: SocketAddress,
+		router: GrpcRouter,
+	) -> Result<(), SocketServerError> {
+		SocketServer::listen(addr, router)
+	}
+
+	/// Like [`Self::listen`], but serve calls from a background thread. See
+	/// [`SocketServer::spawn`].
+	pub fn spawn(
+		addr: SocketAddress,
+		router: GrpcRouter,
+	) -> Result<SocketServerHandle, SocketServerError> {
+		SocketServer::spawn(vec![addr], router)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn call(
+		router: &mut GrpcRouter,
+		path: &str,
+		message: &[u8],
+	) -> AppGrpcResponse {
+		let request = AppGrpcRequest {
+			path: path.to_string(),
+			metadata: vec![],
+			message: message.to_vec(),
+		};
+		let response = router.process(borsh::to_vec(&request).unwrap());
+		borsh::from_slice(&response).unwrap()
+	}
+
+	#[test]
+	fn routes_calls_by_path() {
+		let mut router = GrpcRouter::new()
+			.unary("/echo.Echo/Say", |message| Ok(message.to_vec()))
+			.unary("/echo.Echo/Fail", |_| {
+				Err(GrpcStatus::new(GrpcCode::InvalidArgument, "bad"))
+			});
+
+		assert_eq!(
+			call(&mut router, "/echo.Echo/Say", b"hi"),
+			AppGrpcResponse::ok(b"hi".to_vec())
+		);
+		assert_eq!(
+			call(&mut router, "/echo.Echo/Fail", b"hi"),
+			AppGrpcResponse::error(GrpcStatus::new(
+				GrpcCode::InvalidArgument,
+				"bad"
+			))
+		);
+		assert_eq!(
+			call(&mut router, "/echo.Echo/Shout", b"hi").status.code,
+			GrpcCode::Unimplemented
+		);
+
+		let response: AppGrpcResponse =
+			borsh::from_slice(&router.process(vec![9, 9])).unwrap();
+		assert_eq!(response.status.code, GrpcCode::Internal);
+		assert_eq!(
+			AppHealth::from_response(
+				&router.process(APP_HEALTH_REQUEST.to_vec())
+			)
+			.unwrap(),
+			AppHealth::Ready
+		);
+	}
+}
@@ -0,0 +1,250 @@
+//! Harness for testing a [`Handler`] end to end without an enclave.
+//!
+//! The harness runs the QOS protocol executor with a mock NSM, already
+//! provisioned, in front of the handler. Both run on background threads of
+//! the test and talk over in memory connections, so tests need no child
+//! processes, sockets or sleeps.
+
+use std::{
+	marker::PhantomData,
+	path::PathBuf,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_core::{
+	client::{Client, ClientError},
+	handles::Handles,
+	io::{memory, SocketAddress, TimeVal, TimeValLike},
+	protocol::{
+		app_health::AppHealth, msg::ProtocolMsg,
+		services::boot::ManifestEnvelope, Processor, ProtocolPhase,
+		ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
+	},
+	server::SocketServer,
+};
+use qos_nsm::mock::MockNsm;
+use qos_p256::P256Pair;
+
+use crate::{AppClient, AppClientError, AppProcessor, Handler};
+
+/// Distinguishes the state directories of harnesses in the same process.
+static HARNESS_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// A provisioned enclave running a [`Handler`] as its pivot, for tests.
+///
+/// The enclave state, such as the quorum key and manifest, is kept in a
+/// temporary directory that is removed when the harness is dropped.
+pub struct TestHarness<Req, Resp> {
+	client: Client,
+	handles: Handles,
+	state_dir: PathBuf,
+	_phantom: PhantomData<(Req, Resp)>,
+}
+
+impl<Req, Resp> TestHarness<Req, Resp>
+where
+	Req: BorshSerialize,
+	Resp: BorshDeserialize,
+{
+	/// Provision an enclave with a fresh quorum key and a default manifest,
+	/// and pivot to `handler`.
+	///
+	/// # Panics
+	///
+	/// If the enclave state cannot be written to the temporary directory.
+	pub fn new<H>(handler: H) -> Self
+	where
+		H: Handler<Request = Req, Response = Resp> + Send + 'static,
+	{
+		Self::with_manifest(handler, &ManifestEnvelope::default())
+	}
+
+	/// Like [`Self::new`], but boot the enclave with `manifest_envelope` and
+	/// pivot to `app`, for handlers that depend on it, for example through
+	/// [`qos_core::handles::PivotInfoHandle`], which also reads its app
+	/// config.
+	///
+	/// # Panics
+	///
+	/// If the enclave state cannot be written to the temporary directory.
+	pub fn with_manifest<H>(
+		app: H,
+		manifest_envelope: &ManifestEnvelope,
+	) -> Self
+	where
+		H: Handler<Request = Req, Response = Resp> + Send + 'static,
+	{
+		let state_dir = std::env::temp_dir().join(format!(
+			"qos_app_harness_{}_{}",
+			std::process::id(),
+			HARNESS_COUNT.fetch_add(1, Ordering::SeqCst)
+		));
+		std::fs::create_dir_all(&state_dir)
+			.expect("Failed to create the harness state directory");
+		let path = |file: &str| state_dir.join(file).display().to_string();
+		let handles = Handles::new(
+			path("qos.ephemeral.key"),
+			path("qos.quorum.key"),
+			path("qos.manifest"),
+			path("qos.pivot.bin"),
+			path("qos.pivot.info"),
+		);
+		handles
+			.put_quorum_key(
+				&P256Pair::generate().expect("Failed to generate quorum key"),
+			)
+			.expect("Failed to put the quorum key");
+		handles
+			.put_manifest_envelope(manifest_envelope)
+			.expect("Failed to put the manifest envelope");
+		handles
+			.put_pivot_info(&manifest_envelope.manifest)
+			.expect("Failed to put the pivot info");
+		handles
+			.put_app_config(&manifest_envelope.manifest)
+			.expect("Failed to put the app config");
+
+		let timeout = TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS);
+		let (app_listener, app_connector) = memory::listener();
+		let (enclave_listener, enclave_connector) = memory::listener();
+		let processor = Processor::new(
+			Box::new(MockNsm),
+			handles.clone(),
+			// Unused, requests reach the app through `app_connector`
+			SocketAddress::new_unix(&path("app.sock")),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		)
+		.with_app_client(Client::in_memory(app_connector, timeout));
+
+		// Each server returns once the last connector to it is dropped: the
+		// enclave when the harness is, and then the app with the enclave.
+		let app = AppProcessor::new(app);
+		std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&app_listener, app);
+		});
+		std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&enclave_listener, processor);
+		});
+
+		Self {
+			client: Client::in_memory(enclave_connector, timeout),
+			handles,
+			state_dir,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Send `request` to the handler in a [`ProtocolMsg::ProxyRequest`],
+	/// like the host does, and decode its response.
+	pub fn send(&self, request: &Req) -> Result<Resp, AppClientError> {
+		let request = borsh::to_vec(request)
+			.map_err(|e| AppClientError::Client(ClientError::BorshError(e)))?;
+
+		match self.request(&ProtocolMsg::ProxyRequest { data: request })? {
+			ProtocolMsg::ProxyResponse { data } => {
+				AppClient::<Req, Resp>::decode(&data)
+			}
+			ProtocolMsg::ProtocolErrorResponse(err) => {
+				Err(AppClientError::Protocol(err))
+			}
+			_ => Err(AppClientError::InvalidResponse),
+		}
+	}
+
+	/// Probe the health of the handler through the enclave, like the host's
+	/// deep health check does.
+	pub fn app_health(&self) -> Result<AppHealth, AppClientError> {
+		match self.request(&ProtocolMsg::AppHealthRequest)? {
+			ProtocolMsg::AppHealthResponse(health) => Ok(health),
+			ProtocolMsg::ProtocolErrorResponse(err) => {
+				Err(AppClientError::Protocol(err))
+			}
+			_ => Err(AppClientError::InvalidResponse),
+		}
+	}
+
+	/// Send any `request` to the enclave.
+	pub fn request(
+		&self,
+		request: &ProtocolMsg,
+	) -> Result<ProtocolMsg, AppClientError> {
+		let request = borsh::to_vec(request)
+			.map_err(|e| AppClientError::Client(ClientError::BorshError(e)))?;
+		let response = self.client.send(&request)?;
+
+		ProtocolMsg::try_from_slice(&response)
+			.map_err(|_| AppClientError::InvalidResponse)
+	}
+
+	/// Handles to the state of the enclave.
+	#[must_use]
+	pub fn handles(&self) -> &Handles {
+		&self.handles
+	}
+}
+
+impl<Req, Resp> Drop for TestHarness<Req, Resp> {
+	fn drop(&mut self) {
+		drop(std::fs::remove_dir_all(&self.state_dir));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::protocol::ProtocolError;
+
+	use super::*;
+	use crate::AppError;
+
+	struct Counter {
+		count: u32,
+	}
+
+	impl Handler for Counter {
+		type Request = u32;
+		type Response = u32;
+
+		fn handle(&mut self, request: u32) -> Result<u32, AppError> {
+			if request == 0 {
+				return Err(AppError::InvalidRequest);
+			}
+			self.count += request;
+			Ok(self.count)
+		}
+	}
+
+	#[test]
+	fn proxies_requests_to_the_handler() {
+		let harness = TestHarness::new(Counter { count: 0 });
+
+		assert_eq!(harness.send(&2).unwrap(), 2);
+		assert_eq!(harness.send(&3).unwrap(), 5);
+		assert!(matches!(
+			harness.send(&0),
+			Err(AppClientError::App(AppError::InvalidRequest))
+		));
+		assert_eq!(harness.app_health().unwrap(), AppHealth::Ready);
+		assert!(harness.handles().quorum_key_exists());
+
+		// The enclave only serves what a provisioned enclave would
+		assert!(matches!(
+			harness.request(&ProtocolMsg::MockEphemeralKeyRequest),
+			Ok(ProtocolMsg::ProtocolErrorResponse(
+				ProtocolError::NoMatchingRoute(
+					ProtocolPhase::QuorumKeyProvisioned
+				)
+			))
+		));
+	}
+
+	#[test]
+	fn state_is_removed_on_drop() {
+		let harness = TestHarness::new(Counter { count: 0 });
+		let state_dir = harness.state_dir.clone();
+		assert!(state_dir.exists());
+
+		drop(harness);
+		assert!(!state_dir.exists());
+	}
+}
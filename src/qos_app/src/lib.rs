@@ -0,0 +1,143 @@
+//! SDK for writing pivots, the secure apps QOS runs in the enclave.
+//!
+//! A pivot is a server listening on the app socket, which QOS forwards
+//! requests from the host to. This crate takes care of the socket, framing
+//! and encoding, so a pivot only implements [`Handler`] for its own request
+//! and response types:
+//!
+//! ```no_run
+//! use qos_app::{AppError, AppServer, Handler};
+//!
+//! #[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+//! enum Request {
+//!     Echo(String),
+//! }
+//!
+//! #[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+//! enum Response {
+//!     Echo(String),
+//! }
+//!
+//! struct App;
+//!
+//! impl Handler for App {
+//!     type Request = Request;
+//!     type Response = Response;
+//!
+//!     fn handle(&mut self, request: Request) -> Result<Response, AppError> {
+//!         match request {
+//!             Request::Echo(msg) => Ok(Response::Echo(msg)),
+//!         }
+//!     }
+//! }
+//!
+//! AppServer::run(App).unwrap();
+//! ```
+//!
+//! Requests are borsh encoded `Handler::Request`s and responses are borsh
+//! encoded [`AppResponse`]s, which clients can decode with [`AppClient`].
+//!
+//! To prove to its own clients that data, such as a public key, comes from
+//! the enclave, a pivot can request an attestation document embedding the
+//! data from the [`Executor`]. It can also get secrets derived from the
+//! Quorum Key, for example to encrypt its own state, which stay the same as
+//! long as the Quorum Key and the namespace do. `examples/signing_service.rs`
+//! is a complete pivot signing payloads with such a key, along with the
+//! checks its clients make.
+//!
+//! Configuration approved with the manifest, such as endpoints or feature
+//! switches, can be read with
+//! [`qos_core::handles::PivotInfoHandle::get_app_config`].
+//!
+//! Every pivot served by this crate answers the health probes of the enclave
+//! and of `qos_host`'s deep health check, see
+//! [`qos_core::protocol::app_health`]. A pivot that is sometimes unable to
+//! serve requests reports it by overriding [`Handler::health`].
+//!
+//! Logging, metrics and other concerns shared by every request are added to
+//! an [`AppProcessor`] as [`Middleware`], see [`LogMiddleware`] and
+//! [`MetricsMiddleware`].
+//!
+//! Pivots written as gRPC services can serve their methods with a
+//! [`GrpcRouter`] instead, for clients of the host's gRPC-Web bridge.
+//!
+//! With the `mock` feature enabled, [`TestHarness`] runs a handler behind a
+//! mock enclave in the test process, so pivots can be tested end to end.
+//!
+//! With the `async` feature enabled, pivots that handle many requests at once
+//! can implement `AsyncHandler` and serve with `AsyncAppServer` instead.
+
+#![deny(clippy::all, unsafe_code)]
+#![warn(missing_docs, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
+
+#[cfg(feature = "async")]
+mod async_server;
+mod client;
+mod error;
+mod executor;
+mod grpc;
+#[cfg(any(feature = "mock", test))]
+mod harness;
+mod middleware;
+mod server;
+
+#[cfg(feature = "async")]
+pub use async_server::{AsyncAppProcessor, AsyncAppServer, AsyncHandler};
+use borsh::{BorshDeserialize, BorshSerialize};
+pub use client::{AppClient, AppClientError};
+pub use error::AppError;
+pub use executor::{
+	AppAttestationData, Executor, ExecutorError, MAX_APP_DATA_LEN,
+	P256_SECRET_LEN,
+};
+pub use grpc::{GrpcRouter, GrpcServer};
+#[cfg(any(feature = "mock", test))]
+pub use harness::TestHarness;
+pub use middleware::{
+	LogMiddleware, MetricsMiddleware, Middleware, ResponseEvent,
+};
+pub use qos_core::protocol::{
+	app_grpc::{AppGrpcRequest, AppGrpcResponse, GrpcCode, GrpcStatus},
+	app_health::{AppHealth, APP_HEALTH_REQUEST},
+};
+pub use server::{AppProcessor, AppServer, USOCK};
+
+/// Handles the requests of a pivot.
+pub trait Handler {
+	/// Requests the pivot accepts.
+	type Request: BorshDeserialize;
+	/// Responses the pivot sends.
+	type Response: BorshSerialize;
+
+	/// Handle a single request. An error is sent back to the client as
+	/// [`AppResponse::Err`].
+	fn handle(
+		&mut self,
+		request: Self::Request,
+	) -> Result<Self::Response, AppError>;
+
+	/// Health of the pivot, reported to health probes. Defaults to
+	/// [`AppHealth::Ready`], since a pivot that can answer is running.
+	fn health(&mut self) -> AppHealth {
+		AppHealth::Ready
+	}
+}
+
+/// Response of a pivot, as sent over the app socket.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum AppResponse<T> {
+	/// The request was handled.
+	Ok(T),
+	/// The request could not be handled.
+	Err(AppError),
+}
+
+impl<T> From<Result<T, AppError>> for AppResponse<T> {
+	fn from(result: Result<T, AppError>) -> Self {
+		match result {
+			Ok(response) => Self::Ok(response),
+			Err(err) => Self::Err(err),
+		}
+	}
+}
@@ -0,0 +1,249 @@
+//! Middleware observing, and optionally rejecting, the requests of a pivot.
+//!
+//! Middleware is added to an [`crate::AppProcessor`], or an
+//! `AsyncAppProcessor`, and sees every request before it is decoded and
+//! every response once it is encoded. [`LogMiddleware`] and
+//! [`MetricsMiddleware`] cover the common needs:
+//!
+//! ```no_run
+//! # use qos_app::{AppError, Handler};
+//! # struct App;
+//! # impl Handler for App {
+//! #     type Request = u32;
+//! #     type Response = u32;
+//! #     fn handle(&mut self, request: u32) -> Result<u32, AppError> {
+//! #         Ok(request)
+//! #     }
+//! # }
+//! use std::sync::Arc;
+//!
+//! use qos_app::{AppProcessor, LogMiddleware, MetricsMiddleware};
+//! use qos_core::{
+//!     io::{metrics::Counters, SocketAddress},
+//!     server::SocketServer,
+//! };
+//!
+//! let counters = Arc::new(Counters::default());
+//! let processor = AppProcessor::new(App)
+//!     .with_middleware(LogMiddleware::new())
+//!     .with_middleware(MetricsMiddleware::new(counters.clone()));
+//! SocketServer::listen(SocketAddress::new_unix("./app.sock"), processor)
+//!     .unwrap();
+//! ```
+//!
+//! Health probes are answered before any middleware runs.
+
+use std::{
+	io::Write,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use qos_core::io::metrics::Metrics;
+
+use crate::AppError;
+
+/// Intercepts the requests and responses of a pivot. Both methods default to
+/// doing nothing, so implementors only override what they need.
+pub trait Middleware: Send + Sync {
+	/// Called with each encoded request, before it is decoded. Returning an
+	/// error rejects the request: the error is sent back to the client, the
+	/// handler is not called and later middleware does not see the request.
+	fn on_request(&self, _request: &[u8]) -> Result<(), AppError> {
+		Ok(())
+	}
+
+	/// Called once the response to a request is encoded, including for
+	/// requests rejected by middleware.
+	fn on_response(&self, _event: &ResponseEvent<'_>) {}
+}
+
+/// What happened to a request, for [`Middleware::on_response`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseEvent<'a> {
+	/// Length of the encoded request.
+	pub request_len: usize,
+	/// Length of the encoded response.
+	pub response_len: usize,
+	/// Time from receiving the request to encoding its response.
+	pub latency: Duration,
+	/// The error sent back to the client, if the request failed.
+	pub error: Option<&'a AppError>,
+}
+
+/// The middleware of a processor, run in the order it was added.
+#[derive(Default)]
+pub(crate) struct Chain {
+	middleware: Vec<Box<dyn Middleware>>,
+}
+
+impl Chain {
+	pub(crate) fn push(&mut self, middleware: impl Middleware + 'static) {
+		self.middleware.push(Box::new(middleware));
+	}
+
+	pub(crate) fn on_request(&self, request: &[u8]) -> Result<(), AppError> {
+		self.middleware.iter().try_for_each(|m| m.on_request(request))
+	}
+
+	pub(crate) fn on_response(&self, event: &ResponseEvent<'_>) {
+		for middleware in &self.middleware {
+			middleware.on_response(event);
+		}
+	}
+}
+
+/// [`Middleware`] writing a line in logfmt for every request, by default to
+/// stderr, which QOS forwards to the enclave log.
+///
+/// Lines only name the kind of error, never its message, since a handler
+/// message may carry data the pivot should not log.
+pub struct LogMiddleware {
+	writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl LogMiddleware {
+	/// Create a new instance of [`Self`] writing to stderr.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::with_writer(std::io::stderr())
+	}
+
+	/// Create a new instance of [`Self`] writing to `writer`.
+	#[must_use]
+	pub fn with_writer(writer: impl Write + Send + 'static) -> Self {
+		Self { writer: Mutex::new(Box::new(writer)) }
+	}
+}
+
+impl Default for LogMiddleware {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Middleware for LogMiddleware {
+	fn on_response(&self, event: &ResponseEvent<'_>) {
+		let outcome = match event.error {
+			None => "ok",
+			Some(AppError::InvalidRequest) => "invalid_request",
+			Some(AppError::InvalidResponse) => "invalid_response",
+			Some(AppError::Unsupported) => "unsupported",
+			Some(AppError::Handler(_)) => "handler_error",
+		};
+		let line = format!(
+			"component=qos_app event=request outcome={outcome} \
+			request_bytes={} response_bytes={} latency_us={}\n",
+			event.request_len,
+			event.response_len,
+			event.latency.as_micros()
+		);
+
+		// A poisoned lock only means another thread panicked mid write
+		let mut writer = match self.writer.lock() {
+			Ok(writer) => writer,
+			Err(poisoned) => poisoned.into_inner(),
+		};
+		drop(writer.write_all(line.as_bytes()));
+	}
+}
+
+/// [`Middleware`] reporting every request to a [`Metrics`] implementation,
+/// such as [`qos_core::io::metrics::Counters`].
+pub struct MetricsMiddleware {
+	metrics: Arc<dyn Metrics>,
+}
+
+impl MetricsMiddleware {
+	/// Create a new instance of [`Self`] reporting to `metrics`.
+	#[must_use]
+	pub fn new(metrics: Arc<dyn Metrics>) -> Self {
+		Self { metrics }
+	}
+}
+
+impl Middleware for MetricsMiddleware {
+	fn on_response(&self, event: &ResponseEvent<'_>) {
+		self.metrics.request_received(event.request_len);
+		self.metrics.response_sent(event.response_len, event.latency);
+		if event.error.is_some() {
+			self.metrics.request_failed();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::io::metrics::Counters;
+
+	use super::*;
+
+	/// Writer shared with the test, to read back what was logged.
+	#[derive(Clone, Default)]
+	struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+	impl Write for SharedBuf {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn log_middleware_writes_a_line_per_request() {
+		let buf = SharedBuf::default();
+		let log = LogMiddleware::with_writer(buf.clone());
+		let error = AppError::Handler("secret detail".to_string());
+
+		log.on_response(&ResponseEvent {
+			request_len: 3,
+			response_len: 5,
+			latency: Duration::from_micros(42),
+			error: None,
+		});
+		log.on_response(&ResponseEvent {
+			request_len: 1,
+			response_len: 2,
+			latency: Duration::from_millis(1),
+			error: Some(&error),
+		});
+
+		let logged = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+		assert_eq!(
+			logged,
+			"component=qos_app event=request outcome=ok request_bytes=3 \
+			response_bytes=5 latency_us=42\n\
+			component=qos_app event=request outcome=handler_error \
+			request_bytes=1 response_bytes=2 latency_us=1000\n"
+		);
+	}
+
+	#[test]
+	fn metrics_middleware_counts_requests_and_failures() {
+		let counters = Arc::new(Counters::default());
+		let metrics = MetricsMiddleware::new(counters.clone());
+
+		metrics.on_response(&ResponseEvent {
+			request_len: 3,
+			response_len: 5,
+			latency: Duration::from_millis(2),
+			error: None,
+		});
+		metrics.on_response(&ResponseEvent {
+			request_len: 1,
+			response_len: 2,
+			latency: Duration::from_millis(2),
+			error: Some(&AppError::InvalidRequest),
+		});
+
+		let snapshot = counters.snapshot();
+		assert_eq!(snapshot.requests, 2);
+		assert_eq!(snapshot.failed_requests, 1);
+		assert_eq!(snapshot.bytes_received, 4);
+		assert_eq!(snapshot.bytes_sent, 7);
+		assert_eq!(snapshot.latency[1], 2);
+	}
+}
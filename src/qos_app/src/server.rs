@@ -0,0 +1,318 @@
+//! Server listening on the app socket and routing requests to a
+//! [`Handler`].
+
+use std::time::Instant;
+
+use borsh::BorshSerialize;
+use qos_core::{
+	io::SocketAddress,
+	protocol::app_health::APP_HEALTH_REQUEST,
+	server::{
+		RequestProcessor, SocketServer, SocketServerError, SocketServerHandle,
+	},
+	SEC_APP_SOCK,
+};
+
+use crate::{
+	middleware::{Chain, Middleware, ResponseEvent},
+	AppError, AppResponse, Handler,
+};
+
+/// Pivot argument with the socket to listen on: `--usock <path>`. The
+/// manifest declares it in the pivot args, and it must match the app socket
+/// QOS forwards requests to.
+pub const USOCK: &str = "--usock";
+
+/// [`RequestProcessor`] decoding requests for a [`Handler`] and encoding its
+/// responses. Use it directly to serve with custom
+/// [`qos_core::server::ServerOptions`] or [`Middleware`].
+pub struct AppProcessor<H> {
+	handler: H,
+	middleware: Chain,
+}
+
+impl<H: Handler> AppProcessor<H> {
+	/// Create a new instance of [`Self`].
+	pub fn new(handler: H) -> Self {
+		Self { handler, middleware: Chain::default() }
+	}
+
+	/// Run `middleware` for every request, after any middleware already
+	/// added.
+	#[must_use]
+	pub fn with_middleware(
+		mut self,
+		middleware: impl Middleware + 'static,
+	) -> Self {
+		self.middleware.push(middleware);
+		self
+	}
+}
+
+impl<H: Handler> RequestProcessor for AppProcessor<H> {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		if request == APP_HEALTH_REQUEST {
+			return self.handler.health().to_response();
+		}
+
+		let received = Instant::now();
+		let response = match self.middleware.on_request(&request) {
+			Ok(()) => match borsh::from_slice(&request) {
+				Ok(request) => self.handler.handle(request).into(),
+				Err(_) => AppResponse::Err(AppError::InvalidRequest),
+			},
+			Err(err) => AppResponse::Err(err),
+		};
+
+		respond(&self.middleware, &request, &response, received)
+	}
+}
+
+/// Encode `response` to `request` and report it to `middleware`.
+pub(crate) fn respond<T: BorshSerialize>(
+	middleware: &Chain,
+	request: &[u8],
+	response: &AppResponse<T>,
+	received: Instant,
+) -> Vec<u8> {
+	let encoded = encode_response(response);
+	middleware.on_response(&ResponseEvent {
+		request_len: request.len(),
+		response_len: encoded.len(),
+		latency: received.elapsed(),
+		error: match response {
+			AppResponse::Ok(_) => None,
+			AppResponse::Err(err) => Some(err),
+		},
+	});
+
+	encoded
+}
+
+/// Encode `response`, falling back to [`AppError::InvalidResponse`] if it
+/// cannot be encoded.
+fn encode_response<T: BorshSerialize>(response: &AppResponse<T>) -> Vec<u8> {
+	borsh::to_vec(response).unwrap_or_else(|_| {
+		borsh::to_vec(&AppResponse::<T>::Err(AppError::InvalidResponse))
+			.expect("AppError is valid borsh. qed.")
+	})
+}
+
+/// Server for a pivot.
+pub struct AppServer;
+
+impl AppServer {
+	/// Listen on the socket given by [`USOCK`] in the arguments of the
+	/// process, or [`SEC_APP_SOCK`] if there is none, and serve requests with
+	/// `handler`. Only returns on error.
+	pub fn run<H>(handler: H) -> Result<(), SocketServerError>
+	where
+		H: Handler,
+	{
+		let args: Vec<String> = std::env::args().collect();
+		Self::listen(app_socket(&args), handler)
+	}
+
+	/// Listen on `addr` and serve requests with `handler`. Only returns on
+	/// error.
+	pub fn listen<H>(
+		addr: SocketAddress,
+		handler: H,
+	) -> Result<(), SocketServerError>
+	where
+		H: Handler,
+	{
+		SocketServer::listen(addr, AppProcessor::new(handler))
+	}
+
+	/// Like [`Self::listen`], but serve requests from a background thread.
+	/// See [`SocketServer::spawn`].
+	pub fn spawn<H>(
+		addr: SocketAddress,
+		handler: H,
+	) -> Result<SocketServerHandle, SocketServerError>
+	where
+		H: Handler + Send + 'static,
+	{
+		SocketServer::spawn(vec![addr], AppProcessor::new(handler))
+	}
+}
+
+/// The socket given by [`USOCK`] in `args`, or [`SEC_APP_SOCK`]. Other
+/// arguments are left for the pivot.
+pub(crate) fn app_socket(args: &[String]) -> SocketAddress {
+	let path = args
+		.iter()
+		.position(|arg| arg == USOCK)
+		.and_then(|i| args.get(i + 1))
+		.map_or(SEC_APP_SOCK, String::as_str);
+
+	SocketAddress::new_unix(path)
+}
+
+#[cfg(test)]
+mod test {
+	use borsh::{BorshDeserialize, BorshSerialize};
+	use qos_core::io::SocketAddress;
+
+	use super::*;
+	use crate::{AppClient, AppHealth};
+
+	#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+	enum Request {
+		Add(u32, u32),
+		Fail,
+	}
+
+	struct Adder {
+		requests: usize,
+	}
+
+	impl Handler for Adder {
+		type Request = Request;
+		type Response = u32;
+
+		fn handle(&mut self, request: Request) -> Result<u32, AppError> {
+			self.requests += 1;
+			match request {
+				Request::Add(a, b) => Ok(a + b),
+				Request::Fail => Err(AppError::Handler(format!(
+					"failed request {}",
+					self.requests
+				))),
+			}
+		}
+	}
+
+	#[test]
+	fn processor_routes_requests_to_the_handler() {
+		let mut processor = AppProcessor::new(Adder { requests: 0 });
+		let decode = |response: Vec<u8>| {
+			AppResponse::<u32>::try_from_slice(&response).unwrap()
+		};
+
+		let request = borsh::to_vec(&Request::Add(2, 3)).unwrap();
+		assert_eq!(decode(processor.process(request)), AppResponse::Ok(5));
+
+		let request = borsh::to_vec(&Request::Fail).unwrap();
+		assert_eq!(
+			decode(processor.process(request)),
+			AppResponse::Err(AppError::Handler("failed request 2".to_string()))
+		);
+
+		// Requests that cannot be decoded never reach the handler
+		assert_eq!(
+			decode(processor.process(vec![9, 9, 9])),
+			AppResponse::Err(AppError::InvalidRequest)
+		);
+		assert_eq!(processor.handler.requests, 2);
+	}
+
+	#[test]
+	fn processor_runs_middleware() {
+		use std::sync::Arc;
+
+		use qos_core::io::metrics::Counters;
+
+		use crate::MetricsMiddleware;
+
+		/// Rejects requests longer than 4 bytes.
+		struct MaxLen;
+		impl Middleware for MaxLen {
+			fn on_request(&self, request: &[u8]) -> Result<(), AppError> {
+				if request.len() > 4 {
+					return Err(AppError::Unsupported);
+				}
+				Ok(())
+			}
+		}
+
+		let counters = Arc::new(Counters::default());
+		let mut processor = AppProcessor::new(Adder { requests: 0 })
+			.with_middleware(MaxLen)
+			.with_middleware(MetricsMiddleware::new(counters.clone()));
+		let decode = |response: Vec<u8>| {
+			AppResponse::<u32>::try_from_slice(&response).unwrap()
+		};
+
+		let request = borsh::to_vec(&Request::Fail).unwrap();
+		assert!(matches!(
+			decode(processor.process(request)),
+			AppResponse::Err(AppError::Handler(_))
+		));
+		let request = borsh::to_vec(&Request::Add(2, 3)).unwrap();
+		assert_eq!(
+			decode(processor.process(request)),
+			AppResponse::Err(AppError::Unsupported)
+		);
+		processor.process(APP_HEALTH_REQUEST.to_vec());
+
+		// Rejected requests never reach the handler, and health probes never
+		// reach the middleware
+		assert_eq!(processor.handler.requests, 1);
+		let snapshot = counters.snapshot();
+		assert_eq!(snapshot.requests, 2);
+		assert_eq!(snapshot.failed_requests, 2);
+	}
+
+	#[test]
+	fn processor_answers_health_probes() {
+		struct Loading;
+		impl Handler for Loading {
+			type Request = u32;
+			type Response = u32;
+
+			fn handle(&mut self, request: u32) -> Result<u32, AppError> {
+				Ok(request)
+			}
+
+			fn health(&mut self) -> AppHealth {
+				AppHealth::NotReady { reason: "loading".to_string() }
+			}
+		}
+
+		let mut processor = AppProcessor::new(Adder { requests: 0 });
+		let response = processor.process(APP_HEALTH_REQUEST.to_vec());
+		assert_eq!(
+			AppHealth::from_response(&response).unwrap(),
+			AppHealth::Ready
+		);
+		// Health probes never reach the handler
+		assert_eq!(processor.handler.requests, 0);
+
+		let mut processor = AppProcessor::new(Loading);
+		let response = processor.process(APP_HEALTH_REQUEST.to_vec());
+		assert_eq!(
+			AppHealth::from_response(&response).unwrap(),
+			AppHealth::NotReady { reason: "loading".to_string() }
+		);
+	}
+
+	#[test]
+	fn spawn_serves_clients() {
+		let addr = SocketAddress::new_unix("./spawn_serves_clients.sock");
+		let handle =
+			AppServer::spawn(addr.clone(), Adder { requests: 0 }).unwrap();
+
+		let client = AppClient::<Request, u32>::new(addr);
+		assert_eq!(client.send(&Request::Add(40, 2)).unwrap(), 42);
+		assert!(matches!(
+			client.send(&Request::Fail),
+			Err(crate::AppClientError::App(AppError::Handler(_)))
+		));
+
+		handle.shutdown();
+	}
+
+	#[test]
+	fn app_socket_is_taken_from_args() {
+		let args: Vec<String> = ["pivot", "--other", "x", USOCK, "./app.sock"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		assert_eq!(app_socket(&args), SocketAddress::new_unix("./app.sock"));
+
+		let args = vec!["pivot".to_string(), USOCK.to_string()];
+		assert_eq!(app_socket(&args), SocketAddress::new_unix(SEC_APP_SOCK));
+	}
+}
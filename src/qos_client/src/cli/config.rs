@@ -0,0 +1,184 @@
+//! Defaults for command options from environment variables and a config file,
+//! so options shared by every command of a ceremony, like `--host-ip`, need
+//! not be typed each time.
+//!
+//! An option is set by the environment variable named after it, e.g.
+//! `QOS_HOST_IP` for `--host-ip`, or by the key named after it in the config
+//! file, e.g. `host-ip = "127.0.0.1"`. Options given on the command line take
+//! precedence over environment variables, which take precedence over the
+//! config file. Options the command does not accept are ignored.
+
+use std::path::PathBuf;
+
+use qos_core::parser::Parser;
+
+/// Command line option with the path of the config file.
+const CONFIG: &str = "config";
+/// Environment variable with the path of the config file.
+const CONFIG_ENV: &str = "QOS_CONFIG";
+/// Prefix of the environment variables that set options.
+const ENV_PREFIX: &str = "QOS_";
+/// Config file used when no other is given, relative to the home directory.
+const DEFAULT_CONFIG: &str = ".qos/config.toml";
+
+/// Remove `--config <path>` from `args` and return the config file to use:
+/// the one given with `--config`, else the one in [`CONFIG_ENV`], else the
+/// default one if it exists.
+pub(super) fn take_config_path(args: &mut Vec<String>) -> Option<PathBuf> {
+	let inline = format!("--{CONFIG}=");
+	if let Some(i) = args.iter().position(|arg| arg.starts_with(&inline)) {
+		return Some(args.remove(i)[inline.len()..].into());
+	}
+	if let Some(i) = args.iter().position(|arg| *arg == format!("--{CONFIG}")) {
+		let path = args.get(i + 1).cloned().map(PathBuf::from);
+		args.drain(i..(i + 2).min(args.len()));
+		return path;
+	}
+
+	if let Some(path) = std::env::var_os(CONFIG_ENV) {
+		return Some(path.into());
+	}
+
+	std::env::var_os("HOME")
+		.map(|home| PathBuf::from(home).join(DEFAULT_CONFIG))
+		.filter(|path| path.exists())
+}
+
+/// Arguments for the options of `parser` set in `env` or `config`, leaving
+/// out the ones already given in `cli_args`.
+pub(super) fn default_args(
+	parser: &Parser,
+	cli_args: &[String],
+	env: impl IntoIterator<Item = (String, String)>,
+	config: Option<&str>,
+) -> Result<Vec<String>, String> {
+	// Options given as `--name=value` count as `--name`
+	let mut given: Vec<String> = cli_args
+		.iter()
+		.map(|arg| arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag))
+		.map(String::from)
+		.collect();
+	let mut args = vec![];
+	let mut push = |name: &str, values: Vec<String>, args: &mut Vec<_>| {
+		let flag = format!("--{name}");
+		match parser.takes_value(name) {
+			Some(true) if !given.contains(&flag) => {
+				for value in values {
+					args.push(flag.clone());
+					args.push(value);
+				}
+				given.push(flag);
+			}
+			Some(false) if !given.contains(&flag) => {
+				if values.iter().any(|value| value == "true") {
+					args.push(flag.clone());
+				}
+				given.push(flag);
+			}
+			_ => {}
+		}
+	};
+
+	for (key, value) in env {
+		let Some(name) = key.strip_prefix(ENV_PREFIX) else { continue };
+		if key == CONFIG_ENV {
+			continue;
+		}
+		push(&name.to_lowercase().replace('_', "-"), vec![value], &mut args);
+	}
+
+	if let Some(config) = config {
+		let config: toml_edit::Document = config
+			.parse()
+			.map_err(|e| format!("config file is not valid TOML: {e}"))?;
+		for (key, item) in config.iter() {
+			let values = match item.as_value() {
+				Some(toml_edit::Value::Array(values)) => values
+					.iter()
+					.map(|value| config_value(key, value))
+					.collect::<Result<_, _>>()?,
+				Some(value) => vec![config_value(key, value)?],
+				None => {
+					return Err(format!(
+						"invalid value for `{key}` in config file"
+					))
+				}
+			};
+			push(key, values, &mut args);
+		}
+	}
+
+	Ok(args)
+}
+
+fn config_value(key: &str, value: &toml_edit::Value) -> Result<String, String> {
+	match value {
+		toml_edit::Value::String(s) => Ok(s.value().clone()),
+		toml_edit::Value::Integer(i) => Ok(i.value().to_string()),
+		toml_edit::Value::Boolean(b) => Ok(b.value().to_string()),
+		_ => Err(format!("invalid value for `{key}` in config file")),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::parser::Token;
+
+	use super::*;
+
+	#[test]
+	fn cli_args_take_precedence_over_env_and_config() {
+		let parser = Parser::new()
+			.token(Token::new("host-ip", "").takes_value(true))
+			.token(Token::new("host-port", "").takes_value(true))
+			.token(Token::new("namespace-dir", "").takes_value(true))
+			.token(Token::new("yubikey", ""));
+		let cli_args = vec!["--host-port".to_string(), "3001".to_string()];
+		let env = [
+			("QOS_HOST_IP".to_string(), "10.0.0.1".to_string()),
+			("QOS_HOST_PORT".to_string(), "3002".to_string()),
+			("QOS_CONFIG".to_string(), "./config.toml".to_string()),
+			("HOME".to_string(), "/root".to_string()),
+		];
+		let config = r#"
+			host-ip = "127.0.0.1"
+			host-port = 3003
+			namespace-dir = "./namespace"
+			yubikey = true
+			threshold = 2
+		"#;
+
+		assert_eq!(
+			default_args(&parser, &cli_args, env, Some(config)).unwrap(),
+			[
+				"--host-ip",
+				"10.0.0.1",
+				"--namespace-dir",
+				"./namespace",
+				"--yubikey"
+			]
+		);
+		assert!(default_args(&parser, &[], [], Some("host-ip = [")).is_err());
+
+		let cli_args = vec!["--host-ip=10.0.0.2".to_string()];
+		assert_eq!(
+			default_args(&parser, &cli_args, [], Some("host-ip = \"a\""))
+				.unwrap(),
+			Vec::<String>::new()
+		);
+	}
+
+	#[test]
+	fn takes_the_config_path_out_of_the_args() {
+		let mut args: Vec<String> =
+			["qos_client", "host-health", "--config", "./qos.toml"]
+				.map(String::from)
+				.to_vec();
+		assert_eq!(take_config_path(&mut args), Some("./qos.toml".into()));
+		assert_eq!(args, ["qos_client", "host-health"]);
+
+		args.push("--config=./other.toml".to_string());
+		assert_eq!(take_config_path(&mut args), Some("./other.toml".into()));
+		assert_eq!(args, ["qos_client", "host-health"]);
+	}
+}
@@ -7,17 +7,30 @@
 //! ```shell
 //! cargo run --bin qos_client <command-name> --help
 //! ```
+//!
+//! Options can also be set with environment variables, e.g. `QOS_HOST_IP`
+//! for `--host-ip`, or in a TOML config file, e.g. `host-ip = "127.0.0.1"`.
+//! The config file is `~/.qos/config.toml` unless another one is given with
+//! `--config` or `QOS_CONFIG`. Options given on the command line take
+//! precedence.
 
 use std::env;
 
 use qos_core::{
+	hex, log,
 	parser::{CommandParser, GetParserForCommand, Parser, Token},
-	protocol::{msg::ProtocolMsg, services::boot},
+	protocol::{msg::ProtocolMsg, services::boot, Hash256},
 };
 
-mod services;
+mod config;
 
-pub use services::PairOrYubi;
+pub use crate::services::{Error, ExitCode, PairOrYubi};
+use crate::{
+	qr::QrArtifact,
+	services::{
+		AppProxyData, AttestationDocSource, DisplayType, PivotHashSource,
+	},
+};
 
 const HOST_IP: &str = "host-ip";
 const HOST_PORT: &str = "host-port";
@@ -27,8 +40,12 @@ const NONCE: &str = "nonce";
 const RESTART_POLICY: &str = "restart-policy";
 const PIVOT_PATH: &str = "pivot-path";
 const PIVOT_ARGS: &str = "pivot-args";
+const PIVOT_ARG: &str = "pivot-arg";
+const APP_CONFIG_PATH: &str = "app-config-path";
+const EGRESS: &str = "egress";
 const UNSAFE_SKIP_ATTESTATION: &str = "unsafe-skip-attestation";
 const UNSAFE_EPH_PATH_OVERRIDE: &str = "unsafe-eph-path-override";
+const APP_ECHO_HEX: &str = "app-echo-hex";
 const ENDPOINT_BASE_PATH: &str = "endpoint-base-path";
 const QOS_REALEASE_DIR: &str = "qos-release-dir";
 const PCR3_PREIMAGE_PATH: &str = "pcr3-preimage-path";
@@ -41,11 +58,15 @@ const UNSAFE_AUTO_CONFIRM: &str = "unsafe-auto-confirm";
 const PUB_PATH: &str = "pub-path";
 const YUBIKEY: &str = "yubikey";
 const SECRET_PATH: &str = "secret-path";
+const ENCRYPT: &str = "encrypt";
+const MNEMONIC: &str = "mnemonic";
+const APPROVED_ONLY: &str = "approved-only";
 const SHARE_PATH: &str = "share-path";
 const OUTPUT_PATH: &str = "output-path";
 const QUORUM_KEY_PATH: &str = "quorum-key-path";
 const MANIFEST_APPROVALS_DIR: &str = "manifest-approvals-dir";
 const MANIFEST_PATH: &str = "manifest-path";
+const MANIFEST_HASH: &str = "manifest-hash";
 const MANIFEST_ENVELOPE_PATH: &str = "manifest-envelope-path";
 const APPROVAL_PATH: &str = "approval-path";
 const EPH_WRAPPED_SHARE_PATH: &str = "eph-wrapped-share-path";
@@ -69,23 +90,36 @@ const PLAINTEXT_PATH: &str = "plaintext-path";
 const OUTPUT_HEX: &str = "output-hex";
 const VALIDATION_TIME_OVERRIDE: &str = "validation-time-override";
 const JSON: &str = "json";
-
-pub(crate) enum DisplayType {
-	Manifest,
-	ManifestEnvelope,
-	GenesisOutput,
-}
-
-impl From<&str> for DisplayType {
-	fn from(ty: &str) -> Self {
-		match ty {
-			"manifest" => Self::Manifest,
-			"manifest-envelope" => Self::ManifestEnvelope,
-			"genesis-output" => Self::GenesisOutput,
-			unknown => panic!("unrecognized display type: {unknown}"),
-		}
-	}
-}
+const QR_ARTIFACT: &str = "qr-artifact";
+const QR_PAYLOAD: &str = "qr-payload";
+const SVG_PATH: &str = "svg-path";
+const SPEC: &str = "spec";
+const ROTATION_DIR: &str = "rotation-dir";
+const JSON_PATH: &str = "json-path";
+const ATTESTATION_NONCE: &str = "attestation-nonce";
+const BOOT: &str = "boot";
+const DATA_HEX: &str = "data-hex";
+const DATA_PATH: &str = "data-path";
+const APP: &str = "app";
+const PERSONAL_DIR: &str = "personal-dir";
+const NONCE_LEDGER_PATH: &str = "nonce-ledger-path";
+const KMS_KEY_ID: &str = "kms-key-id";
+const PLAN: &str = "plan";
+const REPORT_PATH: &str = "report-path";
+
+/// Flags of `generate-manifest` that a `--spec` file replaces. One of
+/// `--pivot-hash-path` or `--pivot-path` is also needed.
+const MANIFEST_SPEC_FLAGS: [&str; 9] = [
+	NONCE,
+	NAMESPACE,
+	RESTART_POLICY,
+	QOS_REALEASE_DIR,
+	PCR3_PREIMAGE_PATH,
+	MANIFEST_SET_DIR,
+	SHARE_SET_DIR,
+	PATCH_SET_DIR,
+	QUORUM_KEY_PATH,
+];
 
 /// Commands for the Client CLI.
 ///
@@ -103,8 +137,14 @@ pub enum Command {
 	HostHealth,
 	/// Query the status of the enclave.
 	EnclaveStatus,
+	/// Query the progress of provisioning the quorum key: how many shares are
+	/// posted, the threshold, and which share set members have yet to post.
+	ProvisionStatus,
 	/// Generate a Setup Key for use in the Genesis ceremony.
 	GenerateFileKey,
+	/// Recreate a Setup Key made with `generate-file-key --mnemonic` from its
+	/// recovery phrase, which is prompted for on the terminal.
+	RecoverFileKey,
 	/// Run the the Boot Genesis logic to generate and shard a Quorum Key
 	/// across the given Setup Keys. Each setup key will correspond to a Quorum
 	/// Set Member, so N will equal the number of Setup Keys.
@@ -129,6 +169,9 @@ pub enum Command {
 	VerifyGenesis,
 	/// Using the given Personal Keys as the Manifest Set, generate a manifest.
 	GenerateManifest,
+	/// Print the next manifest nonce to use for a namespace, according to a
+	/// nonce ledger kept by `generate-manifest`.
+	NextNonce,
 	/// Sign a trusted Manifest.
 	///
 	/// This will output a manifest `Approval`.
@@ -147,6 +190,25 @@ pub enum Command {
 	/// Get the attestation document from an enclave. Will also get the
 	/// manifest envelope if it exists.
 	GetAttestationDoc,
+	/// Export the live attestation doc of an enclave, optionally with a
+	/// nonce, or the one it returned when it booted. Writes the COSE Sign1
+	/// structure and a JSON rendering, for customers or auditors to verify
+	/// independently.
+	ExportAttestation,
+	/// Export the enclave's hash chained audit log of the requests that
+	/// changed its state or used its keys, and check its chain and Quorum Key
+	/// signatures locally. Writes the entries and a JSON verification report,
+	/// and fails if either check does.
+	ExportAuditLog,
+	/// Check that a live enclave is running the given manifest: the
+	/// attestation doc cert chain, the PCRs, the manifest hash and the AWS
+	/// root certificate. Prints whether each check passed.
+	VerifyEnclave,
+	/// Run the crypto self test of a booted enclave: known-answer tests of
+	/// AES-GCM, ECDH, ECDSA and SHA-2, and a Shamir round trip. Prints
+	/// whether each test passed, after checking the report is signed by the
+	/// Ephemeral Key from the live attestation doc.
+	SelfTest,
 	/// Given an attestation document from an enclave waiting for shares,
 	/// re-encrypt the local share to the Ephemeral Key from the attestation
 	/// doc.
@@ -161,6 +223,32 @@ pub enum Command {
 	ProxyReEncryptShare,
 	/// Submit an encrypted share to an enclave.
 	PostShare,
+	/// Run a boot ceremony from a TOML plan: validate the manifest, collect
+	/// the manifest set approvals, boot the enclave, verify its attestation
+	/// and post the shares as members provide them. Completed steps are
+	/// checkpointed, so running it again resumes an interrupted ceremony.
+	RunBootCeremony,
+	/// Wrap an encrypted share with an AWS KMS key, so recovering it also
+	/// needs the organization's KMS key. Unwrap it at ceremony time by giving
+	/// the same key to `proxy-re-encrypt-share`.
+	KmsWrapShare,
+	/// Send raw bytes to the app running in the enclave and print the hex
+	/// encoded response.
+	AppProxy,
+	/// Replace a lost or compromised personal key.
+	///
+	/// The first run generates the new personal key and writes a rotation
+	/// request signed by it. Once a threshold of the share set has approved
+	/// the request with `approve-share-rotation`, the second run has the
+	/// enclave encrypt the member's share to the new key and writes the new
+	/// encrypted share.
+	RotatePersonalKey,
+	/// Approve another share set member's request to rotate their personal
+	/// key.
+	///
+	/// Careful - only approve a rotation the member has confirmed to you in
+	/// person or over a trusted channel.
+	ApproveShareRotation,
 	/// Given a directory containing a manifest and threshold approvals for it,
 	/// generate a manifest envelope and write it back to the same directory.
 	GenerateManifestEnvelope,
@@ -172,6 +260,10 @@ pub enum Command {
 	/// sharding it (N=1), creating/signing/posting a Manifest, and
 	/// provisioning the quorum key.
 	DangerousDevBoot,
+	/// Run a whole ceremony in process with simulated members and a mock
+	/// enclave: genesis, manifest generation, approvals, boot and
+	/// provisioning, checking every artifact. Needs the "simulate" feature.
+	SimulateCeremony,
 	/// Provision a yubikey with a singing and encryption key.
 	ProvisionYubiKey,
 	/// Provision a yubikey by generating a secret and importing it onto the
@@ -190,6 +282,31 @@ pub enum Command {
 	YubiKeyPublic,
 	/// Display some borsh encoded type in an easy to read format.
 	Display,
+	/// List the public keys, master seeds and shares in a personal dir with
+	/// their aliases, namespaces and public key fingerprints, warning about
+	/// secrets other users can read.
+	ListKeys,
+	/// Verify the approvals in a manifest approvals directory against the
+	/// manifest and its manifest set, reporting which members have signed and
+	/// whether the threshold is met. Nothing is sent to the enclave.
+	VerifyApprovals,
+	/// Display a borsh encoded manifest or manifest envelope as JSON, along
+	/// with the manifest's qos hash, which is what members sign to approve
+	/// it, and whether each approval in the envelope verifies.
+	DisplayManifest,
+	/// Show a QR code with a compact artifact, so it can be moved from an air
+	/// gapped machine by scanning it: the qos hash of a manifest, an approval,
+	/// or the digest of an attestation doc.
+	QrExport,
+	/// Write the artifact from the scanned text of a QR code made with
+	/// `qr-export` to a file.
+	QrImport,
+	/// Write a printable, checksummed paper backup of an encrypted share or
+	/// personal key, with instructions for recovering it.
+	PaperBackupExport,
+	/// Restore an encrypted share or personal key from a paper backup made
+	/// with `paper-backup-export`.
+	PaperBackupImport,
 	/// Reset the PIV app. WARNING: this is a destructive operation that will
 	/// destroy all PIV keys!
 	YubiKeyPivReset,
@@ -218,18 +335,31 @@ impl From<&str> for Command {
 		match s {
 			"host-health" => Self::HostHealth,
 			"enclave-status" => Self::EnclaveStatus,
+			"provision-status" => Self::ProvisionStatus,
 			"generate-file-key" => Self::GenerateFileKey,
+			"recover-file-key" => Self::RecoverFileKey,
 			"generate-manifest-envelope" => Self::GenerateManifestEnvelope,
 			"boot-genesis" => Self::BootGenesis,
 			"after-genesis" => Self::AfterGenesis,
 			"verify-genesis" => Self::VerifyGenesis,
 			"generate-manifest" => Self::GenerateManifest,
+			"next-nonce" => Self::NextNonce,
 			"approve-manifest" => Self::ApproveManifest,
 			"boot-standard" => Self::BootStandard,
 			"get-attestation-doc" => Self::GetAttestationDoc,
+			"verify-enclave" => Self::VerifyEnclave,
+			"self-test" => Self::SelfTest,
+			"export-attestation" => Self::ExportAttestation,
+			"export-audit-log" => Self::ExportAuditLog,
 			"proxy-re-encrypt-share" => Self::ProxyReEncryptShare,
+			"kms-wrap-share" => Self::KmsWrapShare,
 			"post-share" => Self::PostShare,
+			"run-boot-ceremony" => Self::RunBootCeremony,
+			"app-proxy" => Self::AppProxy,
+			"rotate-personal-key" => Self::RotatePersonalKey,
+			"approve-share-rotation" => Self::ApproveShareRotation,
 			"dangerous-dev-boot" => Self::DangerousDevBoot,
+			"simulate-ceremony" => Self::SimulateCeremony,
 			"provision-yubikey" => Self::ProvisionYubiKey,
 			"advanced-provision-yubikey" => Self::AdvancedProvisionYubiKey,
 			"pivot-hash" => Self::PivotHash,
@@ -240,6 +370,13 @@ impl From<&str> for Command {
 			"yubikey-piv-reset" => Self::YubiKeyPivReset,
 			"yubikey-change-pin" => Self::YubiKeyChangePin,
 			"display" => Self::Display,
+			"list-keys" => Self::ListKeys,
+			"display-manifest" => Self::DisplayManifest,
+			"qr-export" => Self::QrExport,
+			"qr-import" => Self::QrImport,
+			"paper-backup-export" => Self::PaperBackupExport,
+			"paper-backup-import" => Self::PaperBackupImport,
+			"verify-approvals" => Self::VerifyApprovals,
 			"boot-key-fwd" => Self::BootKeyFwd,
 			"export-key" => Self::ExportKey,
 			"inject-key" => Self::InjectKey,
@@ -284,6 +421,31 @@ impl Command {
 		.takes_value(true)
 		.default_value("[]")
 	}
+	fn pivot_arg_token() -> Token {
+		Token::new(
+			PIVOT_ARG,
+			"A CLI arg for the pivot; repeat for each arg, in order. Use `--pivot-arg=<arg>` for args starting with `--`.",
+		)
+		.takes_value(true)
+		.allow_multiple(true)
+		.forbids(vec![PIVOT_ARGS])
+	}
+	fn app_config_path_token() -> Token {
+		Token::new(
+			APP_CONFIG_PATH,
+			"Path to a file with an opaque config for the app. It is approved \
+			with the manifest and given to the pivot as is.",
+		)
+		.takes_value(true)
+	}
+	fn egress_token() -> Token {
+		Token::new(
+			EGRESS,
+			"Comma separated `host:port` endpoints the pivot may connect to \
+			through the egress proxy, e.g. `api.example.com:443,*.example.org:443`.",
+		)
+		.takes_value(true)
+	}
 	fn unsafe_skip_attestation_token() -> Token {
 		Token::new(
 			UNSAFE_SKIP_ATTESTATION,
@@ -387,6 +549,26 @@ impl Command {
 			.required(false)
 			.forbids(vec![SECRET_PATH])
 	}
+	fn encrypt_token() -> Token {
+		Token::new(
+			ENCRYPT,
+			"Flag to encrypt the master seed with a passphrase. Commands that \
+			read the master seed prompt for the passphrase.",
+		)
+		.takes_value(false)
+		.required(false)
+		.forbids(vec![YUBIKEY])
+	}
+	fn mnemonic_token() -> Token {
+		Token::new(
+			MNEMONIC,
+			"Flag to print a recovery phrase for the master seed, which \
+			`recover-file-key` can recreate the key from.",
+		)
+		.takes_value(false)
+		.required(false)
+		.forbids(vec![YUBIKEY])
+	}
 	fn secret_path_token() -> Token {
 		Token::new(
 			SECRET_PATH,
@@ -416,6 +598,14 @@ impl Command {
 			.takes_value(true)
 			.required(true)
 	}
+	fn manifest_hash_token() -> Token {
+		Token::new(
+			MANIFEST_HASH,
+			"Hex encoded qos hash the manifest is expected to have",
+		)
+		.takes_value(true)
+		.required(true)
+	}
 	fn manifest_envelope_path_token() -> Token {
 		Token::new(MANIFEST_ENVELOPE_PATH, "Path to a manifest envelope")
 			.takes_value(true)
@@ -444,6 +634,15 @@ impl Command {
 			.takes_value(true)
 			.required(true)
 	}
+	fn master_seed_path_or_yubikey_token() -> Token {
+		Token::new(
+			MASTER_SEED_PATH,
+			"Path to a master seed. Required unless `--yubikey` is given.",
+		)
+		.takes_value(true)
+		.required(false)
+		.forbids(vec![YUBIKEY])
+	}
 	fn share_token() -> Token {
 		Token::new(
 			SHARE,
@@ -497,6 +696,140 @@ impl Command {
 			.takes_value(true)
 			.required(true)
 	}
+	fn qr_artifact_token() -> Token {
+		Token::new(
+			QR_ARTIFACT,
+			"The artifact in the file (manifest-hash, approval, attestation-digest). For manifest-hash the file is a manifest; for attestation-digest it is an attestation doc.",
+		)
+		.takes_value(true)
+		.required(true)
+	}
+	fn qr_payload_token() -> Token {
+		Token::new(QR_PAYLOAD, "The text scanned from a QR code.")
+			.takes_value(true)
+			.required(true)
+	}
+	fn svg_path_token() -> Token {
+		Token::new(SVG_PATH, "Path to also write the QR code to as an SVG.")
+			.takes_value(true)
+	}
+	fn rotation_dir_token() -> Token {
+		Token::new(
+			ROTATION_DIR,
+			"Directory with the share rotation request and its approvals.",
+		)
+		.takes_value(true)
+		.required(true)
+	}
+	fn data_hex_token() -> Token {
+		Token::new(DATA_HEX, "Hex encoded bytes to send to the app.")
+			.takes_value(true)
+			.forbids(vec![DATA_PATH])
+	}
+	fn data_path_token() -> Token {
+		Token::new(
+			DATA_PATH,
+			"Path to a file with the bytes to send to the app.",
+		)
+		.takes_value(true)
+	}
+	fn app_token() -> Token {
+		Token::new(
+			APP,
+			"Namespace of the app's enclave on a host serving several.",
+		)
+		.takes_value(true)
+	}
+	fn approved_only_token() -> Token {
+		Token::new(
+			APPROVED_ONLY,
+			"Flag to require the enclave to be built with only approved \
+			algorithms. The manifest lists the algorithms the enclave uses.",
+		)
+		.takes_value(false)
+		.required(false)
+	}
+	fn nonce_ledger_path_token() -> Token {
+		Token::new(
+			NONCE_LEDGER_PATH,
+			"Path to a ledger of the highest manifest nonce used for each \
+			namespace. `generate-manifest` refuses a nonce that is not greater \
+			and records the new one.",
+		)
+		.takes_value(true)
+	}
+	fn app_echo_hex_token() -> Token {
+		Token::new(
+			APP_ECHO_HEX,
+			"Hex encoded data to send to the app once the pivot is ready. The \
+			app must echo it back.",
+		)
+		.takes_value(true)
+	}
+	fn plan_token() -> Token {
+		Token::new(PLAN, "Path to the TOML plan of the boot ceremony.")
+			.takes_value(true)
+			.required(true)
+	}
+	fn kms_key_id_token() -> Token {
+		Token::new(
+			KMS_KEY_ID,
+			"ID, ARN or alias of the AWS KMS key the share is wrapped with. \
+			Calls KMS with the `aws` CLI.",
+		)
+		.takes_value(true)
+	}
+	fn personal_dir_token() -> Token {
+		Token::new(
+			PERSONAL_DIR,
+			"Directory with personal or setup keys and shares.",
+		)
+		.takes_value(true)
+		.required(true)
+	}
+	fn json_path_token() -> Token {
+		Token::new(JSON_PATH, "Path to write the JSON rendering to.")
+			.takes_value(true)
+			.required(true)
+	}
+	fn attestation_nonce_token() -> Token {
+		Token::new(
+			ATTESTATION_NONCE,
+			"Hex encoded nonce to include in the attestation doc.",
+		)
+		.takes_value(true)
+		.forbids(vec![BOOT])
+	}
+	fn boot_token() -> Token {
+		Token::new(
+			BOOT,
+			"Export the attestation doc the enclave returned when it booted.",
+		)
+		.takes_value(false)
+	}
+	fn report_path_token() -> Token {
+		Token::new(REPORT_PATH, "Path to write the verification report to.")
+			.takes_value(true)
+			.required(true)
+	}
+	fn spec_token() -> Token {
+		let mut forbidden = MANIFEST_SPEC_FLAGS.to_vec();
+		forbidden.extend([
+			PIVOT_HASH_PATH,
+			PIVOT_PATH,
+			PIVOT_ARGS,
+			PIVOT_ARG,
+			APP_CONFIG_PATH,
+			EGRESS,
+			APPROVED_ONLY,
+		]);
+		Token::new(
+			SPEC,
+			"Path to a TOML spec of the manifest, used instead of the flags for the namespace, nonce, pivot, PCRs, sets and quorum key.",
+		)
+		.takes_value(true)
+		.forbids(forbidden)
+	}
 	fn display_type_token() -> Token {
 		Token::new(
 			DISPLAY_TYPE,
@@ -585,9 +918,19 @@ impl Command {
 	}
 
 	fn generate_file_key() -> Parser {
+		Parser::new()
+			.token(Self::master_seed_path_or_yubikey_token())
+			.token(Self::pub_path_token())
+			.token(Self::yubikey_token())
+			.token(Self::encrypt_token())
+			.token(Self::mnemonic_token())
+	}
+
+	fn recover_file_key() -> Parser {
 		Parser::new()
 			.token(Self::master_seed_path_token())
 			.token(Self::pub_path_token())
+			.token(Self::encrypt_token())
 	}
 
 	fn boot_genesis() -> Parser {
@@ -621,26 +964,41 @@ impl Command {
 	}
 
 	fn generate_manifest() -> Parser {
+		// Without `--spec` the handler checks that these are all given.
 		Parser::new()
+			.token(Self::spec_token())
 			.token(
 				Token::new(
 					NONCE,
 					"Nonce of the manifest relative to the namespace.",
 				)
-				.takes_value(true)
-				.required(true),
+				.takes_value(true),
 			)
-			.token(Self::namespace_token())
-			.token(Self::pivot_hash_path_token())
-			.token(Self::restart_policy_token())
-			.token(Self::qos_release_dir_token())
-			.token(Self::pcr3_preimage_path_token())
+			.token(Self::namespace_token().required(false))
+			.token(Self::pivot_hash_path_token().required(false))
+			.token(
+				Self::pivot_path_token()
+					.required(false)
+					.forbids(vec![PIVOT_HASH_PATH]),
+			)
+			.token(Self::restart_policy_token().required(false))
+			.token(Self::qos_release_dir_token().required(false))
+			.token(Self::pcr3_preimage_path_token().required(false))
 			.token(Self::manifest_path_token())
-			.token(Self::manifest_set_dir_token())
-			.token(Self::share_set_dir_token())
-			.token(Self::patch_set_dir_token())
-			.token(Self::quorum_key_path_token())
+			.token(Self::nonce_ledger_path_token())
+			.token(Self::manifest_set_dir_token().required(false))
+			.token(Self::share_set_dir_token().required(false))
+			.token(Self::patch_set_dir_token().required(false))
+			.token(Self::quorum_key_path_token().required(false))
 			.token(Self::pivot_args_token())
+			.token(Self::pivot_arg_token())
+			.token(Self::app_config_path_token())
+			.token(Self::egress_token())
+			.token(Self::approved_only_token())
+			.example(
+				"qos_client generate-manifest --spec ./manifest-spec.toml \
+				--manifest-path ./manifest --nonce-ledger-path ./nonces.toml",
+			)
 	}
 
 	fn approve_manifest() -> Parser {
@@ -674,6 +1032,29 @@ impl Command {
 			.token(Self::manifest_envelope_path_token())
 	}
 
+	fn export_attestation() -> Parser {
+		Self::base()
+			.token(Self::attestation_doc_path_token())
+			.token(Self::json_path_token())
+			.token(Self::attestation_nonce_token())
+			.token(Self::boot_token())
+	}
+
+	fn export_audit_log() -> Parser {
+		Self::base()
+			.token(Self::quorum_key_path_token())
+			.token(Self::output_path_token())
+			.token(Self::report_path_token())
+	}
+
+	fn verify_enclave() -> Parser {
+		Self::base().token(Self::manifest_path_token())
+	}
+
+	fn self_test() -> Parser {
+		Self::base().token(Self::unsafe_skip_attestation_token())
+	}
+
 	fn proxy_re_encrypt_share() -> Parser {
 		Parser::new()
 			.token(Self::yubikey_token())
@@ -689,6 +1070,18 @@ impl Command {
 			.token(Self::unsafe_skip_attestation_token())
 			.token(Self::unsafe_eph_path_override_token())
 			.token(Self::unsafe_auto_confirm_token())
+			.token(Self::kms_key_id_token())
+	}
+
+	fn kms_wrap_share() -> Parser {
+		Parser::new()
+			.token(Self::share_path_token())
+			.token(Self::kms_key_id_token().required(true))
+			.token(Self::output_path_token())
+	}
+
+	fn run_boot_ceremony() -> Parser {
+		Self::base().token(Self::plan_token())
 	}
 
 	fn post_share() -> Parser {
@@ -697,6 +1090,46 @@ impl Command {
 			.token(Self::eph_wrapped_share_path_token())
 	}
 
+	fn next_nonce() -> Parser {
+		Parser::new()
+			.token(Self::nonce_ledger_path_token().required(true))
+			.token(Self::namespace_token())
+	}
+
+	fn list_keys() -> Parser {
+		Parser::new().token(Self::personal_dir_token())
+	}
+
+	fn app_proxy() -> Parser {
+		Self::base()
+			.token(Self::data_hex_token())
+			.token(Self::data_path_token())
+			.token(Self::app_token())
+	}
+
+	fn rotate_personal_key() -> Parser {
+		Self::base()
+			.token(Self::alias_token())
+			.token(Self::namespace_dir_token())
+			.token(Self::manifest_envelope_path_token())
+			.token(Self::master_seed_path_token())
+			.token(Self::pub_path_token())
+			.token(Self::encrypt_token())
+			.token(Self::rotation_dir_token())
+			.token(Self::share_path_token())
+	}
+
+	fn approve_share_rotation() -> Parser {
+		Parser::new()
+			.token(Self::yubikey_token())
+			.token(Self::secret_path_token())
+			.token(Self::alias_token())
+			.token(Self::namespace_dir_token())
+			.token(Self::manifest_envelope_path_token())
+			.token(Self::rotation_dir_token())
+			.token(Self::unsafe_auto_confirm_token())
+	}
+
 	fn generate_manifest_envelope() -> Parser {
 		Parser::new()
 			.token(Self::manifest_approvals_dir_token())
@@ -704,12 +1137,32 @@ impl Command {
 			.token(Self::manifest_envelope_path_token())
 	}
 
+	fn verify_approvals() -> Parser {
+		Parser::new()
+			.token(Self::manifest_approvals_dir_token())
+			.token(Self::manifest_path_token())
+			.token(Self::manifest_hash_token())
+	}
+
 	fn dangerous_dev_boot() -> Parser {
 		Self::base()
 			.token(Self::pivot_path_token())
 			.token(Self::restart_policy_token())
 			.token(Self::pivot_args_token())
+			.token(Self::pivot_arg_token())
 			.token(Self::unsafe_eph_path_override_token())
+			.token(Self::app_echo_hex_token())
+			.example(
+				"qos_client dangerous-dev-boot --host-ip 127.0.0.1 \
+				--host-port 3000 --pivot-path ./pivot --restart-policy never \
+				--pivot-arg=--usock --pivot-arg ./pivot.sock",
+			)
+	}
+
+	fn simulate_ceremony() -> Parser {
+		Parser::new()
+			.token(Self::total_shares_token())
+			.token(Self::threshold_token())
 	}
 
 	fn provision_yubikey() -> Parser {
@@ -757,6 +1210,29 @@ impl Command {
 			.token(Self::json_token())
 	}
 
+	fn display_manifest() -> Parser {
+		Parser::new().token(Self::file_path_token())
+	}
+
+	fn qr_export() -> Parser {
+		Parser::new()
+			.token(Self::qr_artifact_token())
+			.token(Self::file_path_token())
+			.token(Self::svg_path_token())
+	}
+
+	fn qr_import() -> Parser {
+		Parser::new()
+			.token(Self::qr_payload_token())
+			.token(Self::output_path_token())
+	}
+
+	fn paper_backup() -> Parser {
+		Parser::new()
+			.token(Self::file_path_token())
+			.token(Self::output_path_token())
+	}
+
 	fn boot_key_fwd() -> Parser {
 		Self::base()
 			.token(Self::manifest_envelope_path_token())
@@ -786,7 +1262,9 @@ impl Command {
 		Parser::new()
 			.token(Self::payload_path_token())
 			.token(Self::signature_path_token())
-			.token(Self::master_seed_path_token())
+			.token(Self::master_seed_path_or_yubikey_token())
+			.token(Self::yubikey_token())
+			.token(Self::current_pin_path_token())
 	}
 
 	fn p256_asymmetric_encrypt() -> Parser {
@@ -800,7 +1278,9 @@ impl Command {
 		Parser::new()
 			.token(Self::plaintext_path_token())
 			.token(Self::ciphertext_path_token())
-			.token(Self::master_seed_path_token())
+			.token(Self::master_seed_path_or_yubikey_token())
+			.token(Self::yubikey_token())
+			.token(Self::current_pin_path_token())
 			.token(Self::output_hex_token())
 	}
 }
@@ -808,18 +1288,32 @@ impl Command {
 impl GetParserForCommand for Command {
 	fn parser(&self) -> Parser {
 		match self {
-			Self::HostHealth | Self::EnclaveStatus => Self::base(),
+			Self::HostHealth | Self::EnclaveStatus | Self::ProvisionStatus => {
+				Self::base()
+			}
 			Self::GenerateFileKey => Self::generate_file_key(),
+			Self::RecoverFileKey => Self::recover_file_key(),
 			Self::BootGenesis => Self::boot_genesis(),
 			Self::AfterGenesis => Self::after_genesis(),
 			Self::VerifyGenesis => Self::verify_genesis(),
 			Self::GenerateManifest => Self::generate_manifest(),
+			Self::NextNonce => Self::next_nonce(),
 			Self::ApproveManifest => Self::approve_manifest(),
 			Self::BootStandard => Self::boot_standard(),
 			Self::GetAttestationDoc => Self::get_attestation_doc(),
+			Self::VerifyEnclave => Self::verify_enclave(),
+			Self::SelfTest => Self::self_test(),
+			Self::ExportAttestation => Self::export_attestation(),
+			Self::ExportAuditLog => Self::export_audit_log(),
 			Self::ProxyReEncryptShare => Self::proxy_re_encrypt_share(),
+			Self::KmsWrapShare => Self::kms_wrap_share(),
 			Self::PostShare => Self::post_share(),
+			Self::RunBootCeremony => Self::run_boot_ceremony(),
+			Self::AppProxy => Self::app_proxy(),
+			Self::RotatePersonalKey => Self::rotate_personal_key(),
+			Self::ApproveShareRotation => Self::approve_share_rotation(),
 			Self::DangerousDevBoot => Self::dangerous_dev_boot(),
+			Self::SimulateCeremony => Self::simulate_ceremony(),
 			Self::GenerateManifestEnvelope => {
 				Self::generate_manifest_envelope()
 			}
@@ -835,6 +1329,14 @@ impl GetParserForCommand for Command {
 			Self::YubiKeyPivReset => Parser::new(),
 			Self::YubiKeyChangePin => Self::yubikey_change_pin(),
 			Self::Display => Self::display(),
+			Self::ListKeys => Self::list_keys(),
+			Self::DisplayManifest => Self::display_manifest(),
+			Self::QrExport => Self::qr_export(),
+			Self::QrImport => Self::qr_import(),
+			Self::PaperBackupExport | Self::PaperBackupImport => {
+				Self::paper_backup()
+			}
+			Self::VerifyApprovals => Self::verify_approvals(),
 			Self::BootKeyFwd => Self::boot_key_fwd(),
 			Self::ExportKey => Self::export_key(),
 			Self::InjectKey => Self::inject_key(),
@@ -867,6 +1369,14 @@ impl ClientOpts {
 		self.path("message")
 	}
 
+	/// The message endpoint of the enclave named by `--app`, if given.
+	fn path_app_message(&self) -> String {
+		match self.parsed.single(APP) {
+			Some(app) => self.path(&format!("ns/{app}/message")),
+			None => self.path_message(),
+		}
+	}
+
 	fn alias(&self) -> String {
 		self.parsed.single(ALIAS).expect("required arg").to_string()
 	}
@@ -953,6 +1463,10 @@ impl ClientOpts {
 	}
 
 	fn pivot_args(&self) -> Vec<String> {
+		if let Some(args) = self.parsed.multiple(PIVOT_ARG) {
+			return args.to_vec();
+		}
+
 		let v = self.parsed.single(PIVOT_ARGS).expect("required arg");
 		let mut chars = v.chars();
 
@@ -1010,6 +1524,23 @@ impl ClientOpts {
 			.to_string()
 	}
 
+	fn manifest_hash(&self) -> Result<Hash256, Error> {
+		let hash = self
+			.parsed
+			.single(MANIFEST_HASH)
+			.expect("Missing `--manifest-hash`");
+
+		hex::decode_hash256(hash.trim())
+			.map_err(|error| Error::InvalidHexArg { arg: MANIFEST_HASH, error })
+	}
+
+	fn rotation_dir(&self) -> String {
+		self.parsed
+			.single(ROTATION_DIR)
+			.expect("Missing `--rotation-dir`")
+			.to_string()
+	}
+
 	fn manifest_envelope_path(&self) -> String {
 		self.parsed
 			.single(MANIFEST_ENVELOPE_PATH)
@@ -1049,6 +1580,10 @@ impl ClientOpts {
 			.to_string()
 	}
 
+	fn maybe_master_seed_path(&self) -> Option<String> {
+		self.parsed.single(MASTER_SEED_PATH).cloned()
+	}
+
 	fn output_dir(&self) -> String {
 		self.parsed
 			.single(OUTPUT_DIR)
@@ -1101,12 +1636,121 @@ impl ClientOpts {
 			.to_string()
 	}
 
-	fn display_type(&self) -> DisplayType {
+	fn display_type(&self) -> Result<DisplayType, Error> {
 		self.parsed
 			.single(DISPLAY_TYPE)
 			.expect("Missing `--display-type`")
-			.as_str()
-			.into()
+			.parse()
+	}
+
+	fn qr_artifact(&self) -> Result<QrArtifact, Error> {
+		Ok(self
+			.parsed
+			.single(QR_ARTIFACT)
+			.expect("Missing `--qr-artifact`")
+			.parse()?)
+	}
+
+	fn qr_payload(&self) -> String {
+		self.parsed
+			.single(QR_PAYLOAD)
+			.expect("Missing `--qr-payload`")
+			.to_string()
+	}
+
+	fn app_proxy_data(&self) -> Option<AppProxyData<String>> {
+		self.parsed
+			.single(DATA_HEX)
+			.map(|hex| AppProxyData::Hex(hex.clone()))
+			.or_else(|| {
+				self.parsed
+					.single(DATA_PATH)
+					.map(|path| AppProxyData::File(path.clone()))
+			})
+	}
+
+	fn plan(&self) -> String {
+		self.parsed.single(PLAN).expect("Missing `--plan`").to_string()
+	}
+
+	fn kms_key_id(&self) -> Option<String> {
+		self.parsed.single(KMS_KEY_ID).cloned()
+	}
+
+	fn app_config_path(&self) -> Option<String> {
+		self.parsed.single(APP_CONFIG_PATH).cloned()
+	}
+
+	fn egress(&self) -> Vec<boot::EgressEndpoint> {
+		self.parsed.single(EGRESS).map_or_else(Vec::new, |endpoints| {
+			endpoints
+				.split(',')
+				.map(|endpoint| {
+					endpoint
+						.trim()
+						.to_string()
+						.try_into()
+						.expect("Could not parse `--egress`, use `host:port`")
+				})
+				.collect()
+		})
+	}
+
+	fn nonce_ledger_path(&self) -> Option<String> {
+		self.parsed.single(NONCE_LEDGER_PATH).cloned()
+	}
+
+	fn personal_dir(&self) -> String {
+		self.parsed
+			.single(PERSONAL_DIR)
+			.expect("Missing `--personal-dir`")
+			.to_string()
+	}
+
+	fn json_path(&self) -> String {
+		self.parsed
+			.single(JSON_PATH)
+			.expect("Missing `--json-path`")
+			.to_string()
+	}
+
+	fn report_path(&self) -> String {
+		self.parsed
+			.single(REPORT_PATH)
+			.expect("Missing `--report-path`")
+			.to_string()
+	}
+
+	fn attestation_doc_source(&self) -> Result<AttestationDocSource, Error> {
+		if self.parsed.flag(BOOT).unwrap_or(false) {
+			return Ok(AttestationDocSource::Boot);
+		}
+
+		let nonce = self
+			.parsed
+			.single(ATTESTATION_NONCE)
+			.map(|nonce| qos_hex::decode(nonce))
+			.transpose()?;
+		Ok(AttestationDocSource::Live(nonce))
+	}
+
+	fn svg_path(&self) -> Option<String> {
+		self.parsed.single(SVG_PATH).map(Into::into)
+	}
+
+	fn spec_path(&self) -> Option<String> {
+		self.parsed.single(SPEC).map(Into::into)
+	}
+
+	fn pivot_hash_source(&self) -> Option<PivotHashSource<String>> {
+		self.parsed
+			.single(PIVOT_HASH_PATH)
+			.map(|path| PivotHashSource::HashFile(path.clone()))
+			.or_else(|| {
+				self.parsed
+					.single(PIVOT_PATH)
+					.map(|path| PivotHashSource::Binary(path.clone()))
+			})
 	}
 
 	fn dr_key_path(&self) -> Option<String> {
@@ -1155,6 +1799,18 @@ impl ClientOpts {
 		self.parsed.flag(YUBIKEY).unwrap_or(false)
 	}
 
+	fn encrypt(&self) -> bool {
+		self.parsed.flag(ENCRYPT).unwrap_or(false)
+	}
+
+	fn mnemonic(&self) -> bool {
+		self.parsed.flag(MNEMONIC).unwrap_or(false)
+	}
+
+	fn approved_only(&self) -> bool {
+		self.parsed.flag(APPROVED_ONLY).unwrap_or(false)
+	}
+
 	fn unsafe_skip_attestation(&self) -> bool {
 		self.parsed.flag(UNSAFE_SKIP_ATTESTATION).unwrap_or(false)
 	}
@@ -1163,6 +1819,10 @@ impl ClientOpts {
 		self.parsed.single(UNSAFE_EPH_PATH_OVERRIDE).map(String::from)
 	}
 
+	fn app_echo_hex(&self) -> Option<&str> {
+		self.parsed.single(APP_ECHO_HEX).map(String::as_str)
+	}
+
 	fn unsafe_auto_confirm(&self) -> bool {
 		self.parsed.flag(UNSAFE_AUTO_CONFIRM).unwrap_or(false)
 	}
@@ -1180,78 +1840,151 @@ impl ClientOpts {
 struct ClientRunner {
 	cmd: Command,
 	opts: ClientOpts,
+	/// The binary and command as typed, for the usage line of the help.
+	usage_name: String,
 }
 impl ClientRunner {
-	/// Create [`Self`] from the command line arguments.
+	/// Create [`Self`] from the command line arguments, filling in options
+	/// not given from the environment and config file. See [`config`].
 	pub fn new(args: &mut Vec<String>) -> Self {
+		let config_path = config::take_config_path(args);
+		// The first arg is the binary name and the second the command
+		if let Some(command) = args.get(1) {
+			let parser = Command::from(command.as_str()).parser();
+			let config = match config_path.map(std::fs::read_to_string) {
+				Some(Ok(config)) => Some(config),
+				Some(Err(e)) => {
+					eprintln!("Error: failed to read the config file: {e}");
+					std::process::exit(ExitCode::Io as i32);
+				}
+				None => None,
+			};
+			match config::default_args(
+				&parser,
+				&args[2..],
+				env::vars(),
+				config.as_deref(),
+			) {
+				Ok(default_args) => args.extend(default_args),
+				Err(e) => {
+					eprintln!("Error: {e}");
+					std::process::exit(ExitCode::InvalidInput as i32);
+				}
+			}
+		}
+
+		let usage_name =
+			format!("qos_client {}", args.get(1).map_or("", String::as_str));
 		let (cmd, parsed) =
 			CommandParser::<Command>::parse(args).expect("Invalid CLI args");
 
-		Self { cmd, opts: ClientOpts { parsed } }
+		Self { cmd, opts: ClientOpts { parsed }, usage_name }
 	}
 
 	/// Run the given command.
+	#[allow(clippy::too_many_lines)]
 	pub fn run(self) {
+		// Status and progress go through the log, so `--quiet` and `--verbose`
+		// apply to them. Prompts and the output a command is run for, like a
+		// signature or a report, are always printed.
+		log::global().set_print_level(self.opts.parsed.log_level());
+
 		if self.opts.parsed.version() {
 			println!("version: {}", env!("CARGO_PKG_VERSION"));
 		} else if self.opts.parsed.help() {
-			println!("Command: {:?}", self.cmd);
+			println!("{}", self.opts.parsed.usage(&self.usage_name));
 			println!("{}", self.opts.parsed.info());
 		} else {
-			match self.cmd {
+			let result = match self.cmd {
 				Command::HostHealth => handlers::host_health(&self.opts),
 				Command::EnclaveStatus => handlers::enclave_status(&self.opts),
+				Command::ProvisionStatus => {
+					handlers::provision_status(&self.opts)
+				}
 				Command::GenerateFileKey => {
-					handlers::generate_file_key(&self.opts);
+					handlers::generate_file_key(&self.opts)
+				}
+				Command::RecoverFileKey => {
+					handlers::recover_file_key(&self.opts)
 				}
 				Command::ProvisionYubiKey => {
-					handlers::provision_yubikey(&self.opts);
+					handlers::provision_yubikey(&self.opts)
 				}
 				Command::AdvancedProvisionYubiKey => {
-					handlers::advanced_provision_yubikey(&self.opts);
+					handlers::advanced_provision_yubikey(&self.opts)
 				}
 				Command::BootGenesis => handlers::boot_genesis(&self.opts),
 				Command::AfterGenesis => handlers::after_genesis(&self.opts),
-				Command::VerifyGenesis => {
-					handlers::verify_genesis(&self.opts);
-				}
+				Command::VerifyGenesis => handlers::verify_genesis(&self.opts),
 				Command::GenerateManifest => {
-					handlers::generate_manifest(&self.opts);
+					handlers::generate_manifest(&self.opts)
 				}
+				Command::NextNonce => handlers::next_nonce(&self.opts),
 				Command::ApproveManifest => {
-					handlers::approve_manifest(&self.opts);
+					handlers::approve_manifest(&self.opts)
 				}
 				Command::BootStandard => handlers::boot_standard(&self.opts),
 				Command::GetAttestationDoc => {
-					handlers::get_attestation_doc(&self.opts);
+					handlers::get_attestation_doc(&self.opts)
+				}
+				Command::VerifyEnclave => handlers::verify_enclave(&self.opts),
+				Command::SelfTest => handlers::self_test(&self.opts),
+				Command::ExportAttestation => {
+					handlers::export_attestation(&self.opts)
+				}
+				Command::ExportAuditLog => {
+					handlers::export_audit_log(&self.opts)
 				}
 				Command::ProxyReEncryptShare => {
-					handlers::proxy_re_encrypt_share(&self.opts);
+					handlers::proxy_re_encrypt_share(&self.opts)
 				}
+				Command::KmsWrapShare => handlers::kms_wrap_share(&self.opts),
 				Command::PostShare => handlers::post_share(&self.opts),
-				Command::DangerousDevBoot => {
-					handlers::dangerous_dev_boot(&self.opts);
+				Command::RunBootCeremony => {
+					handlers::run_boot_ceremony(&self.opts)
 				}
-				Command::GenerateManifestEnvelope => {
-					handlers::generate_manifest_envelope(&self.opts);
+				Command::AppProxy => handlers::app_proxy(&self.opts),
+				Command::RotatePersonalKey => {
+					handlers::rotate_personal_key(&self.opts)
 				}
-				Command::PivotHash => {
-					handlers::pivot_hash(&self.opts);
+				Command::ApproveShareRotation => {
+					handlers::approve_share_rotation(&self.opts)
+				}
+				Command::DangerousDevBoot => {
+					handlers::dangerous_dev_boot(&self.opts)
 				}
-				Command::ShamirSplit => {
-					handlers::shamir_split(&self.opts);
+				Command::SimulateCeremony => {
+					handlers::simulate_ceremony(&self.opts)
 				}
+				Command::GenerateManifestEnvelope => {
+					handlers::generate_manifest_envelope(&self.opts)
+				}
+				Command::PivotHash => handlers::pivot_hash(&self.opts),
+				Command::ShamirSplit => handlers::shamir_split(&self.opts),
 				Command::ShamirReconstruct => {
-					handlers::shamir_reconstruct(&self.opts);
+					handlers::shamir_reconstruct(&self.opts)
 				}
 				Command::YubiKeySign => handlers::yubikey_sign(&self.opts),
 				Command::YubiKeyPublic => handlers::yubikey_public(&self.opts),
 				Command::YubiKeyPivReset => handlers::yubikey_piv_reset(),
 				Command::YubiKeyChangePin => {
-					handlers::yubikey_change_pin(&self.opts);
+					handlers::yubikey_change_pin(&self.opts)
+				}
+				Command::Display => handlers::display(&self.opts),
+				Command::ListKeys => handlers::list_keys(&self.opts),
+				Command::DisplayManifest => {
+					handlers::display_manifest(&self.opts)
+				}
+				Command::QrExport => handlers::qr_export(&self.opts),
+				Command::QrImport => handlers::qr_import(&self.opts),
+				Command::PaperBackupExport => {
+					handlers::paper_backup_export(&self.opts)
 				}
-				Command::Display => {
-					handlers::display(&self.opts);
+				Command::PaperBackupImport => {
+					handlers::paper_backup_import(&self.opts)
+				}
+				Command::VerifyApprovals => {
+					handlers::verify_approvals(&self.opts)
 				}
 				Command::BootKeyFwd => handlers::boot_key_fwd(&self.opts),
 				Command::ExportKey => handlers::export_key(&self.opts),
@@ -1259,11 +1992,16 @@ impl ClientRunner {
 				Command::P256Verify => handlers::p256_verify(&self.opts),
 				Command::P256Sign => handlers::p256_sign(&self.opts),
 				Command::P256AsymmetricEncrypt => {
-					handlers::p256_asymmetric_encrypt(&self.opts);
+					handlers::p256_asymmetric_encrypt(&self.opts)
 				}
 				Command::P256AsymmetricDecrypt => {
-					handlers::p256_asymmetric_decrypt(&self.opts);
+					handlers::p256_asymmetric_decrypt(&self.opts)
 				}
+			};
+
+			if let Err(e) = result {
+				eprintln!("Error: {e}");
+				std::process::exit(e.exit_code() as i32);
 			}
 		}
 	}
@@ -1283,136 +2021,154 @@ impl CLI {
 }
 
 mod handlers {
-	use super::services::{ApproveManifestArgs, ProxyReEncryptShareArgs};
 	use crate::{
-		cli::{
-			services::{self, GenerateManifestArgs, PairOrYubi},
-			ClientOpts, ProtocolMsg,
-		},
+		cli::{ClientOpts, ProtocolMsg, MANIFEST_SPEC_FLAGS},
 		request,
+		services::{
+			self, ApproveManifestArgs, ApproveShareRotationArgs, Error,
+			GenerateManifestArgs, PairOrYubi, ProxyReEncryptShareArgs,
+			RotatePersonalKeyArgs,
+		},
 	};
 
-	pub(super) fn pivot_hash(opts: &ClientOpts) {
+	pub(super) fn pivot_hash(opts: &ClientOpts) -> Result<(), Error> {
 		let pivot = std::fs::read(opts.pivot_path())
-			.expect("Failed to read pivot file");
+			.map_err(Error::FailedToReadPivot)?;
 
 		let hash = qos_crypto::sha_256(&pivot);
 		let hex_hash = qos_hex::encode(&hash);
 
-		std::fs::write(opts.output_path(), hex_hash.as_bytes())
-			.expect("Failed to write pivot hash to specified path");
+		std::fs::write(opts.output_path(), hex_hash.as_bytes()).map_err(|e| {
+			Error::FailedToWrite {
+				path: opts.output_path(),
+				error: e.to_string(),
+			}
+		})
 	}
 
-	pub(super) fn host_health(opts: &ClientOpts) {
+	pub(super) fn host_health(opts: &ClientOpts) -> Result<(), Error> {
 		let path = &opts.path("host-health");
-		if let Ok(response) = request::get(path) {
-			println!("{response}");
-		} else {
-			panic!("Error...")
-		}
+		println!("{}", request::get(path)?);
+
+		Ok(())
 	}
 
-	pub(super) fn enclave_status(opts: &ClientOpts) {
-		let path = &opts.path_message();
+	pub(super) fn provision_status(opts: &ClientOpts) -> Result<(), Error> {
+		services::provision_status(&opts.path_message())
+	}
 
-		let response = request::post(path, &ProtocolMsg::StatusRequest)
-			.map_err(|e| println!("{e:?}"))
-			.expect("Enclave request failed");
+	pub(super) fn enclave_status(opts: &ClientOpts) -> Result<(), Error> {
+		let path = &opts.path_message();
 
-		match response {
+		match request::post(path, &ProtocolMsg::StatusRequest)? {
 			ProtocolMsg::StatusResponse(phase) => {
 				println!("Enclave phase: {phase:?}");
+				Ok(())
+			}
+			other => {
+				Err(Error::UnexpectedProtocolMsgResponse(format!("{other:?}")))
 			}
-			other => panic!("Unexpected response {other:?}"),
 		}
 	}
 
-	pub(super) fn generate_file_key(opts: &ClientOpts) {
-		services::generate_file_key(&opts.master_seed_path(), &opts.pub_path());
+	pub(super) fn generate_file_key(opts: &ClientOpts) -> Result<(), Error> {
+		if opts.yubikey() {
+			// Generate the key on the yubikey instead of writing it to a file
+			return provision_yubikey(opts);
+		}
+
+		let master_seed_path =
+			opts.maybe_master_seed_path().ok_or(Error::InvalidArgs(
+				"need either the yubikey flag or a master seed path",
+			))?;
+		services::generate_file_key(
+			&master_seed_path,
+			&opts.pub_path(),
+			opts.encrypt(),
+			opts.mnemonic(),
+		)
+	}
+
+	pub(super) fn recover_file_key(opts: &ClientOpts) -> Result<(), Error> {
+		let phrase = rpassword::prompt_password("Enter the recovery phrase: ")
+			.map_err(Error::PassphraseEntry)?;
+		services::recover_file_key(
+			&phrase,
+			opts.master_seed_path(),
+			opts.pub_path(),
+			opts.encrypt(),
+		)
 	}
 
-	pub(super) fn provision_yubikey(opts: &ClientOpts) {
+	pub(super) fn provision_yubikey(opts: &ClientOpts) -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
 		{
-			if let Err(e) = services::provision_yubikey(opts.pub_path()) {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			services::provision_yubikey(opts.pub_path())
 		}
 	}
 
-	pub(super) fn advanced_provision_yubikey(opts: &ClientOpts) {
+	pub(super) fn advanced_provision_yubikey(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
 		{
-			if let Err(e) = services::advanced_provision_yubikey(
+			services::advanced_provision_yubikey(
 				opts.master_seed_path(),
 				opts.current_pin_path(),
-			) {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			)
 		}
 	}
 
-	pub(super) fn yubikey_sign(opts: &ClientOpts) {
+	pub(super) fn yubikey_sign(opts: &ClientOpts) -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
 		{
-			if let Err(e) = services::yubikey_sign(&opts.payload()) {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			services::yubikey_sign(&opts.payload())
 		}
 	}
 
-	pub(super) fn yubikey_public(_opts: &ClientOpts) {
+	pub(super) fn yubikey_public(_opts: &ClientOpts) -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
 		{
-			if let Err(e) = services::yubikey_public() {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			services::yubikey_public()
 		}
 	}
 
-	pub(super) fn yubikey_piv_reset() {
+	pub(super) fn yubikey_piv_reset() -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
 		{
-			if let Err(e) = crate::yubikey::yubikey_piv_reset() {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			crate::yubikey::yubikey_piv_reset().map_err(Into::into)
 		}
 	}
 
-	pub(super) fn yubikey_change_pin(opts: &ClientOpts) {
+	pub(super) fn yubikey_change_pin(opts: &ClientOpts) -> Result<(), Error> {
 		#[cfg(not(feature = "smartcard"))]
 		{
-			panic!("{}", services::SMARTCARD_FEAT_DISABLED_MSG)
+			Err(Error::InvalidArgs(services::SMARTCARD_FEAT_DISABLED_MSG))
 		}
 
 		#[cfg(feature = "smartcard")]
@@ -1420,21 +2176,16 @@ mod handlers {
 			let current_pin = services::pin_from_path(
 				opts.current_pin_path()
 					.expect("Missing `--current-pin-path` arg"),
-			);
-			let new_pin = services::pin_from_path(opts.new_pin_path());
+			)?;
+			let new_pin = services::pin_from_path(opts.new_pin_path())?;
 
-			if let Err(e) = crate::yubikey::yubikey_change_pin(
-				&current_pin[..],
-				&new_pin[..],
-			) {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
+			crate::yubikey::yubikey_change_pin(&current_pin[..], &new_pin[..])
+				.map_err(Into::into)
 		}
 	}
 
-	pub(super) fn boot_genesis(opts: &ClientOpts) {
-		if let Err(e) = services::boot_genesis(services::BootGenesisArgs {
+	pub(super) fn boot_genesis(opts: &ClientOpts) -> Result<(), Error> {
+		services::boot_genesis(services::BootGenesisArgs {
 			uri: &opts.path_message(),
 			namespace_dir: opts.namespace_dir(),
 			share_set_dir: opts.share_set_dir(),
@@ -1442,15 +2193,12 @@ mod handlers {
 			pcr3_preimage_path: opts.pcr3_preimage_path(),
 			dr_key_path: opts.dr_key_path(),
 			unsafe_skip_attestation: opts.unsafe_skip_attestation(),
-		}) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		})
 	}
 
-	pub(super) fn after_genesis(opts: &ClientOpts) {
-		let pair = get_pair_or_yubi(opts);
-		if let Err(e) = services::after_genesis(services::AfterGenesisArgs {
+	pub(super) fn after_genesis(opts: &ClientOpts) -> Result<(), Error> {
+		let pair = get_pair_or_yubi(opts)?;
+		services::after_genesis(services::AfterGenesisArgs {
 			pair,
 			share_path: opts.share_path(),
 			alias: opts.alias(),
@@ -1459,46 +2207,66 @@ mod handlers {
 			pcr3_preimage_path: opts.pcr3_preimage_path(),
 			unsafe_skip_attestation: opts.unsafe_skip_attestation(),
 			validation_time_override: opts.validation_time_override(),
-		}) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		})
 	}
 
-	pub(super) fn verify_genesis(opts: &ClientOpts) {
-		if let Err(e) = services::verify_genesis(
-			opts.namespace_dir(),
-			opts.master_seed_path(),
-		) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+	pub(super) fn verify_genesis(opts: &ClientOpts) -> Result<(), Error> {
+		services::verify_genesis(opts.namespace_dir(), opts.master_seed_path())
 	}
 
-	pub(super) fn generate_manifest(opts: &ClientOpts) {
-		if let Err(e) = services::generate_manifest(GenerateManifestArgs {
+	pub(super) fn generate_manifest(opts: &ClientOpts) -> Result<(), Error> {
+		if let Some(spec_path) = opts.spec_path() {
+			return services::generate_manifest_from_spec(
+				spec_path,
+				opts.manifest_path(),
+				opts.nonce_ledger_path(),
+			);
+		}
+		let Some(pivot) = opts.pivot_hash_source().filter(|_| {
+			MANIFEST_SPEC_FLAGS
+				.iter()
+				.all(|flag| opts.parsed.single(flag).is_some())
+		}) else {
+			return Err(Error::InvalidArgs(
+				"either `--spec` or all of `--nonce`, `--namespace`, \
+				`--restart-policy`, `--qos-release-dir`, \
+				`--pcr3-preimage-path`, `--manifest-set-dir`, \
+				`--share-set-dir`, `--patch-set-dir`, `--quorum-key-path` and \
+				one of `--pivot-hash-path` or `--pivot-path` are required",
+			));
+		};
+
+		services::generate_manifest(GenerateManifestArgs {
 			nonce: opts.nonce(),
 			namespace: opts.namespace(),
 			restart_policy: opts.restart_policy(),
-			pivot_hash_path: opts.pivot_hash_path(),
+			pivot,
 			qos_release_dir_path: opts.qos_release_dir(),
 			pcr3_preimage_path: opts.pcr3_preimage_path(),
 			manifest_path: opts.manifest_path(),
 			pivot_args: opts.pivot_args(),
+			app_config_path: opts.app_config_path(),
+			egress: opts.egress(),
 			share_set_dir: opts.share_set_dir(),
 			manifest_set_dir: opts.manifest_set_dir(),
 			patch_set_dir: opts.patch_set_dir(),
 			quorum_key_path: opts.quorum_key_path(),
-		}) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+			nonce_ledger_path: opts.nonce_ledger_path(),
+			approved_only: opts.approved_only(),
+		})
 	}
 
-	pub(super) fn approve_manifest(opts: &ClientOpts) {
-		let pair = get_pair_or_yubi(opts);
+	pub(super) fn next_nonce(opts: &ClientOpts) -> Result<(), Error> {
+		services::next_nonce(
+			opts.nonce_ledger_path().expect("required arg"),
+			&opts.namespace(),
+		)
+	}
+
+	pub(super) fn approve_manifest(opts: &ClientOpts) -> Result<(), Error> {
+		let pair = get_pair_or_yubi(opts)?;
 
-		if let Err(e) = services::approve_manifest(ApproveManifestArgs {
+		services::approve_manifest(ApproveManifestArgs {
 			pair,
 			manifest_path: opts.manifest_path(),
 			manifest_approvals_dir: opts.manifest_approvals_dir(),
@@ -1511,210 +2279,301 @@ mod handlers {
 			patch_set_dir: opts.patch_set_dir(),
 			alias: opts.alias(),
 			unsafe_auto_confirm: opts.unsafe_auto_confirm(),
-		}) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		})
 	}
 
-	pub(super) fn boot_standard(opts: &ClientOpts) {
-		if let Err(e) = services::boot_standard(services::BootStandardArgs {
+	pub(super) fn boot_standard(opts: &ClientOpts) -> Result<(), Error> {
+		services::boot_standard(services::BootStandardArgs {
 			uri: opts.path_message(),
 			pivot_path: opts.pivot_path(),
 			manifest_envelope_path: opts.manifest_envelope_path(),
 			pcr3_preimage_path: opts.pcr3_preimage_path(),
 			unsafe_skip_attestation: opts.unsafe_skip_attestation(),
-		}) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		})
 	}
 
-	pub(super) fn get_attestation_doc(opts: &ClientOpts) {
+	pub(super) fn get_attestation_doc(opts: &ClientOpts) -> Result<(), Error> {
 		services::get_attestation_doc(
 			&opts.path_message(),
 			opts.attestation_doc_path(),
 			opts.manifest_envelope_path(),
-		);
+		)
 	}
 
-	pub(super) fn proxy_re_encrypt_share(opts: &ClientOpts) {
-		let pair = get_pair_or_yubi(opts);
-
-		if let Err(e) =
-			services::proxy_re_encrypt_share(ProxyReEncryptShareArgs {
-				pair,
-				share_path: opts.share_path(),
-				manifest_envelope_path: opts.manifest_envelope_path(),
-				approval_path: opts.approval_path(),
-				eph_wrapped_share_path: opts.eph_wrapped_share_path(),
-				attestation_doc_path: opts.attestation_doc_path(),
-				pcr3_preimage_path: opts.pcr3_preimage_path(),
-				alias: opts.alias(),
-				manifest_set_dir: opts.manifest_set_dir(),
-				unsafe_skip_attestation: opts.unsafe_skip_attestation(),
-				unsafe_eph_path_override: opts.unsafe_eph_path_override(),
-				unsafe_auto_confirm: opts.unsafe_auto_confirm(),
-			}) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+	pub(super) fn export_attestation(opts: &ClientOpts) -> Result<(), Error> {
+		services::export_attestation(services::ExportAttestationArgs {
+			uri: opts.path_message(),
+			source: opts.attestation_doc_source()?,
+			attestation_doc_path: opts.attestation_doc_path(),
+			json_path: opts.json_path(),
+		})
+	}
+
+	pub(super) fn export_audit_log(opts: &ClientOpts) -> Result<(), Error> {
+		services::export_audit_log(services::ExportAuditLogArgs {
+			uri: opts.path_message(),
+			quorum_key_path: opts.quorum_key_path(),
+			output_path: opts.output_path(),
+			report_path: opts.report_path(),
+		})
+	}
+
+	pub(super) fn verify_enclave(opts: &ClientOpts) -> Result<(), Error> {
+		services::verify_enclave(&opts.path_message(), opts.manifest_path())
+	}
+
+	pub(super) fn self_test(opts: &ClientOpts) -> Result<(), Error> {
+		services::self_test(
+			&opts.path_message(),
+			opts.unsafe_skip_attestation(),
+		)
+	}
+
+	pub(super) fn proxy_re_encrypt_share(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
+		let pair = get_pair_or_yubi(opts)?;
+
+		services::proxy_re_encrypt_share(ProxyReEncryptShareArgs {
+			pair,
+			share_path: opts.share_path(),
+			manifest_envelope_path: opts.manifest_envelope_path(),
+			approval_path: opts.approval_path(),
+			eph_wrapped_share_path: opts.eph_wrapped_share_path(),
+			attestation_doc_path: opts.attestation_doc_path(),
+			pcr3_preimage_path: opts.pcr3_preimage_path(),
+			alias: opts.alias(),
+			manifest_set_dir: opts.manifest_set_dir(),
+			unsafe_skip_attestation: opts.unsafe_skip_attestation(),
+			unsafe_eph_path_override: opts.unsafe_eph_path_override(),
+			unsafe_auto_confirm: opts.unsafe_auto_confirm(),
+			kms_key_id: opts.kms_key_id(),
+		})
+	}
+
+	pub(super) fn kms_wrap_share(opts: &ClientOpts) -> Result<(), Error> {
+		services::kms_wrap_share(
+			opts.share_path(),
+			opts.kms_key_id().expect("required arg"),
+			opts.output_path(),
+		)
+	}
+
+	pub(super) fn run_boot_ceremony(opts: &ClientOpts) -> Result<(), Error> {
+		services::run_boot_ceremony(&opts.path_message(), opts.plan())
 	}
 
-	pub(super) fn post_share(opts: &ClientOpts) {
-		if let Err(e) = services::post_share(
+	pub(super) fn post_share(opts: &ClientOpts) -> Result<(), Error> {
+		services::post_share(
 			&opts.path_message(),
 			opts.eph_wrapped_share_path(),
 			opts.approval_path(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
+	}
+
+	pub(super) fn app_proxy(opts: &ClientOpts) -> Result<(), Error> {
+		let Some(data) = opts.app_proxy_data() else {
+			return Err(Error::InvalidArgs(
+				"need either `--data-hex` or `--data-path`",
+			));
+		};
+
+		services::app_proxy(&opts.path_app_message(), &data)
+	}
+
+	pub(super) fn rotate_personal_key(opts: &ClientOpts) -> Result<(), Error> {
+		services::rotate_personal_key(RotatePersonalKeyArgs {
+			uri: opts.path_message(),
+			alias: opts.alias(),
+			namespace_dir: opts.namespace_dir(),
+			manifest_envelope_path: opts.manifest_envelope_path(),
+			master_seed_path: opts.master_seed_path(),
+			pub_path: opts.pub_path(),
+			encrypt: opts.encrypt(),
+			rotation_dir: opts.rotation_dir(),
+			share_path: opts.share_path(),
+		})
+	}
+
+	pub(super) fn approve_share_rotation(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
+		let pair = get_pair_or_yubi(opts)?;
+
+		services::approve_share_rotation(ApproveShareRotationArgs {
+			pair,
+			alias: opts.alias(),
+			namespace_dir: opts.namespace_dir(),
+			manifest_envelope_path: opts.manifest_envelope_path(),
+			rotation_dir: opts.rotation_dir(),
+			unsafe_auto_confirm: opts.unsafe_auto_confirm(),
+		})
 	}
 
-	pub(super) fn display(opts: &ClientOpts) {
-		if let Err(e) = services::display(
-			&opts.display_type(),
+	pub(super) fn list_keys(opts: &ClientOpts) -> Result<(), Error> {
+		services::list_keys(opts.personal_dir())
+	}
+
+	pub(super) fn display(opts: &ClientOpts) -> Result<(), Error> {
+		services::display(&opts.display_type()?, opts.file_path(), opts.json())
+	}
+
+	pub(super) fn display_manifest(opts: &ClientOpts) -> Result<(), Error> {
+		services::display_manifest(opts.file_path())
+	}
+
+	pub(super) fn qr_export(opts: &ClientOpts) -> Result<(), Error> {
+		services::qr_export(
+			opts.qr_artifact()?,
 			opts.file_path(),
-			opts.json(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+			opts.svg_path(),
+		)
 	}
 
-	pub(super) fn dangerous_dev_boot(opts: &ClientOpts) {
+	pub(super) fn qr_import(opts: &ClientOpts) -> Result<(), Error> {
+		services::qr_import(&opts.qr_payload(), opts.output_path())
+	}
+
+	pub(super) fn paper_backup_export(opts: &ClientOpts) -> Result<(), Error> {
+		services::paper_backup_export(opts.file_path(), opts.output_path())
+	}
+
+	pub(super) fn paper_backup_import(opts: &ClientOpts) -> Result<(), Error> {
+		services::paper_backup_import(opts.file_path(), opts.output_path())
+	}
+
+	pub(super) fn dangerous_dev_boot(opts: &ClientOpts) -> Result<(), Error> {
 		services::dangerous_dev_boot(
 			&opts.path_message(),
 			opts.pivot_path(),
 			opts.restart_policy(),
 			opts.pivot_args(),
 			opts.unsafe_eph_path_override(),
-		);
+			opts.app_echo_hex(),
+		)
+	}
+
+	pub(super) fn simulate_ceremony(opts: &ClientOpts) -> Result<(), Error> {
+		#[cfg(not(feature = "simulate"))]
+		{
+			let _ = opts;
+			Err(Error::InvalidArgs(services::SIMULATE_FEAT_DISABLED_MSG))
+		}
+
+		#[cfg(feature = "simulate")]
+		{
+			services::simulate_ceremony(opts.total_shares(), opts.threshold())
+		}
 	}
 
-	pub(super) fn generate_manifest_envelope(opts: &ClientOpts) {
-		if let Err(e) = services::generate_manifest_envelope(
+	pub(super) fn verify_approvals(opts: &ClientOpts) -> Result<(), Error> {
+		services::verify_approvals(
+			opts.manifest_approvals_dir(),
+			opts.manifest_path(),
+			&opts.manifest_hash()?,
+		)
+	}
+
+	pub(super) fn generate_manifest_envelope(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
+		services::generate_manifest_envelope(
 			opts.manifest_approvals_dir(),
 			opts.manifest_path(),
 			opts.maybe_manifest_envelope_path(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn shamir_split(opts: &ClientOpts) {
-		if let Err(e) = services::shamir_split(
+	pub(super) fn shamir_split(opts: &ClientOpts) -> Result<(), Error> {
+		services::shamir_split(
 			opts.secret_path().expect("Missing `--secret-path`"),
 			opts.total_shares(),
 			opts.threshold(),
 			&opts.output_dir(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn shamir_reconstruct(opts: &ClientOpts) {
-		if let Err(e) =
-			services::shamir_reconstruct(opts.shares(), &opts.output_path())
-		{
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+	pub(super) fn shamir_reconstruct(opts: &ClientOpts) -> Result<(), Error> {
+		services::shamir_reconstruct(opts.shares(), &opts.output_path())
 	}
 
-	fn get_pair_or_yubi(opts: &ClientOpts) -> PairOrYubi {
-		match PairOrYubi::from_inputs(
+	/// Like [`get_pair_or_yubi`], but with the pair read from
+	/// `--master-seed-path`.
+	fn get_signer(opts: &ClientOpts) -> Result<PairOrYubi, Error> {
+		PairOrYubi::from_inputs(
+			opts.yubikey(),
+			opts.maybe_master_seed_path(),
+			opts.current_pin_path(),
+		)
+	}
+
+	fn get_pair_or_yubi(opts: &ClientOpts) -> Result<PairOrYubi, Error> {
+		PairOrYubi::from_inputs(
 			opts.yubikey(),
 			opts.secret_path(),
 			opts.current_pin_path(),
-		) {
-			Err(e) => {
-				eprintln!("Error: {e:?}");
-				std::process::exit(1);
-			}
-			Ok(p) => p,
-		}
+		)
 	}
 
-	pub(super) fn boot_key_fwd(opts: &ClientOpts) {
-		if let Err(e) = services::boot_key_fwd(
+	pub(super) fn boot_key_fwd(opts: &ClientOpts) -> Result<(), Error> {
+		services::boot_key_fwd(
 			&opts.path_message(),
 			opts.manifest_envelope_path(),
 			opts.pivot_path(),
 			opts.attestation_doc_path(),
-		) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn export_key(opts: &ClientOpts) {
-		if let Err(e) = services::export_key(
+	pub(super) fn export_key(opts: &ClientOpts) -> Result<(), Error> {
+		services::export_key(
 			&opts.path_message(),
 			opts.manifest_envelope_path(),
 			opts.attestation_doc_path(),
 			opts.encrypted_quorum_key_path(),
-		) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn inject_key(opts: &ClientOpts) {
-		if let Err(e) = services::inject_key(
+	pub(super) fn inject_key(opts: &ClientOpts) -> Result<(), Error> {
+		services::inject_key(
 			&opts.path_message(),
 			opts.encrypted_quorum_key_path(),
-		) {
-			println!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn p256_verify(opts: &ClientOpts) {
-		if let Err(e) = services::p256_verify(
+	pub(super) fn p256_verify(opts: &ClientOpts) -> Result<(), Error> {
+		services::p256_verify(
 			opts.payload_path(),
 			opts.signature_path(),
 			opts.pub_path(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn p256_sign(opts: &ClientOpts) {
-		if let Err(e) = services::p256_sign(
+	pub(super) fn p256_sign(opts: &ClientOpts) -> Result<(), Error> {
+		services::p256_sign(
 			&opts.payload_path(),
 			opts.signature_path(),
-			opts.master_seed_path(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+			get_signer(opts)?,
+		)
 	}
 
-	pub(super) fn p256_asymmetric_encrypt(opts: &ClientOpts) {
-		if let Err(e) = services::p256_asymmetric_encrypt(
+	pub(super) fn p256_asymmetric_encrypt(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
+		services::p256_asymmetric_encrypt(
 			opts.plaintext_path(),
 			opts.ciphertext_path(),
 			opts.pub_path(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 
-	pub(super) fn p256_asymmetric_decrypt(opts: &ClientOpts) {
-		if let Err(e) = services::p256_asymmetric_decrypt(
+	pub(super) fn p256_asymmetric_decrypt(
+		opts: &ClientOpts,
+	) -> Result<(), Error> {
+		services::p256_asymmetric_decrypt(
 			opts.plaintext_path(),
 			opts.ciphertext_path(),
-			opts.master_seed_path(),
+			get_signer(opts)?,
 			opts.output_hex(),
-		) {
-			eprintln!("Error: {e:?}");
-			std::process::exit(1);
-		}
+		)
 	}
 }
@@ -0,0 +1,157 @@
+//! Wrapping personal shares with an AWS KMS key, so organizational key
+//! custody can be layered on top of personal keys.
+//!
+//! A wrapped share is the KMS ciphertext blob of the share as it was
+//! encrypted to the member's personal key. Both keys are needed to recover
+//! the share: KMS to unwrap it and the personal key to decrypt it.
+//!
+//! KMS is called through the `aws` CLI, so its usual credentials, profile and
+//! region configuration applies.
+
+use std::{
+	io::{self, Write},
+	path::PathBuf,
+	process::{Command, Stdio},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Errors calling KMS.
+#[derive(Debug)]
+pub enum KmsError {
+	/// The `aws` CLI could not be run.
+	Spawn(io::Error),
+	/// The `aws` CLI exited with an error.
+	Failed {
+		/// Exit code of the `aws` CLI, if it was not terminated by a signal.
+		code: Option<i32>,
+		/// What the `aws` CLI wrote to stderr.
+		stderr: String,
+	},
+	/// The `aws` CLI did not output base64.
+	InvalidOutput,
+}
+
+impl From<io::Error> for KmsError {
+	fn from(err: io::Error) -> Self {
+		Self::Spawn(err)
+	}
+}
+
+/// A KMS key used to wrap shares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kms {
+	program: PathBuf,
+	key_id: String,
+}
+
+impl Kms {
+	/// The KMS key with the given id, ARN or alias, called with the `aws`
+	/// CLI on the `PATH`.
+	#[must_use]
+	pub fn new(key_id: String) -> Self {
+		Self::with_program("aws", key_id)
+	}
+
+	/// Like [`Self::new`], but with the given `aws` CLI.
+	#[must_use]
+	pub fn with_program(program: impl Into<PathBuf>, key_id: String) -> Self {
+		Self { program: program.into(), key_id }
+	}
+
+	/// Encrypt `plaintext` with the KMS key.
+	pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, KmsError> {
+		self.call(&["encrypt", "--plaintext"], "CiphertextBlob", plaintext)
+	}
+
+	/// Decrypt `ciphertext` made with [`Self::encrypt`].
+	pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, KmsError> {
+		self.call(&["decrypt", "--ciphertext-blob"], "Plaintext", ciphertext)
+	}
+
+	/// Run `aws kms <args> fileb:///dev/stdin` with `input` on stdin and
+	/// decode the base64 `field` of the response.
+	fn call(
+		&self,
+		args: &[&str],
+		field: &str,
+		input: &[u8],
+	) -> Result<Vec<u8>, KmsError> {
+		let mut child = Command::new(&self.program)
+			.arg("kms")
+			.args(args)
+			.arg("fileb:///dev/stdin")
+			.args(["--key-id", &self.key_id])
+			.args(["--query", field, "--output", "text"])
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()?;
+		if let Some(mut stdin) = child.stdin.take() {
+			// If the CLI exits without reading its input, report how it exited
+			match stdin.write_all(input) {
+				Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+					return Err(e.into())
+				}
+				_ => {}
+			}
+		}
+		let output = child.wait_with_output()?;
+
+		if !output.status.success() {
+			return Err(KmsError::Failed {
+				code: output.status.code(),
+				stderr: String::from_utf8_lossy(&output.stderr)
+					.trim()
+					.to_string(),
+			});
+		}
+
+		let stdout = String::from_utf8(output.stdout)
+			.map_err(|_| KmsError::InvalidOutput)?;
+		STANDARD
+			.decode(stdout.split_whitespace().collect::<String>())
+			.map_err(|_| KmsError::InvalidOutput)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, os::unix::fs::PermissionsExt};
+
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+
+	/// Stands in for the `aws` CLI, prefixing the plaintext with "wrapped"
+	/// and only knowing the key `my-key`.
+	const FAKE_AWS: &str = r#"#!/bin/sh
+[ "$5" = "--key-id" ] && [ "$6" = "my-key" ] || { echo "no such key" >&2; exit 254; }
+case "$2" in
+encrypt) { printf wrapped; cat; } | base64 ;;
+decrypt) tail -c +8 | base64 ;;
+esac
+"#;
+
+	#[test]
+	fn wrap_unwrap_round_trip() {
+		let program: PathWrapper = "/tmp/qos_client_fake_aws".into();
+		fs::write(&*program, FAKE_AWS).unwrap();
+		fs::set_permissions(&*program, fs::Permissions::from_mode(0o700))
+			.unwrap();
+
+		let kms = Kms::with_program(&*program, "my-key".to_string());
+		let share = [1; 200];
+		let wrapped = kms.encrypt(&share).unwrap();
+		assert_eq!(wrapped, [b"wrapped".as_slice(), &share].concat());
+		assert_eq!(kms.decrypt(&wrapped).unwrap(), share);
+
+		let other = Kms::with_program(&*program, "other-key".to_string());
+		match other.decrypt(&wrapped) {
+			Err(KmsError::Failed { code: Some(254), stderr }) => {
+				assert_eq!(stderr, "no such key");
+			}
+			r => panic!("unexpected result: {r:?}"),
+		}
+	}
+}
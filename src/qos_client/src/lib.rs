@@ -6,68 +6,543 @@
 #![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
 
 pub mod cli;
+pub mod kms;
+pub mod paper;
+pub mod qr;
+pub mod services;
 #[cfg(feature = "smartcard")]
 pub mod yubikey;
 
 /// Host HTTP request helpers.
+///
+/// Requests to `https://` hosts are verified against the web PKI roots, or only
+/// against [`Config::ca_cert`] if it is set. Requests go through the proxy in
+/// the `HTTPS_PROXY` or `HTTP_PROXY` environment variable, depending on the
+/// scheme of the url, unless the host is listed in `NO_PROXY`.
 pub mod request {
-	use std::io::Read;
+	use std::{
+		fs::File,
+		io::{self, BufReader, Read, Write},
+		net::TcpStream,
+		path::PathBuf,
+		sync::Arc,
+		time::Duration,
+	};
 
 	use borsh::BorshDeserialize;
-	use qos_core::protocol::msg::ProtocolMsg;
+	use qos_core::{log, protocol::msg::ProtocolMsg};
+	use rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
 
 	const MAX_SIZE: u64 = u32::MAX as u64;
 
+	/// Timeouts and retries for requests to the host.
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Config {
+		/// How long to wait for a connection to the host.
+		pub connect_timeout: Duration,
+		/// How long to wait for each read of the response.
+		pub read_timeout: Duration,
+		/// How many times to retry a request that failed because the host
+		/// could not be reached or was temporarily unavailable.
+		pub retries: u32,
+		/// How long to wait before the first retry. The wait doubles with
+		/// each retry.
+		pub backoff: Duration,
+		/// PEM file with the CA certificate of `https://` hosts. If set, only
+		/// host certificates issued by this CA are trusted.
+		pub ca_cert: Option<PathBuf>,
+	}
+
+	impl Default for Config {
+		fn default() -> Self {
+			Self {
+				connect_timeout: Duration::from_secs(10),
+				// Booting and attesting can take a while in the enclave.
+				read_timeout: Duration::from_secs(120),
+				retries: 3,
+				backoff: Duration::from_millis(500),
+				ca_cert: None,
+			}
+		}
+	}
+
+	impl Config {
+		fn agent(&self, url: &str) -> Result<ureq::Agent, Error> {
+			let mut builder = ureq::AgentBuilder::new()
+				.timeout_connect(self.connect_timeout)
+				.timeout_read(self.read_timeout)
+				.tls_connector(Arc::new(TlsConnector(self.tls_config()?)));
+
+			if let Some(proxy) =
+				proxy_from_env(url, |var| std::env::var(var).ok())
+			{
+				let proxy = ureq::Proxy::new(&proxy).map_err(|e| {
+					Error::Proxy { proxy, error: e.to_string() }
+				})?;
+				builder = builder.proxy(proxy);
+			}
+
+			Ok(builder.build())
+		}
+
+		fn tls_config(&self) -> Result<Arc<ClientConfig>, Error> {
+			let roots = match &self.ca_cert {
+				Some(path) => {
+					let ca_cert_error = |error: String| Error::CaCert {
+						path: path.display().to_string(),
+						error,
+					};
+					let file = File::open(path)
+						.map_err(|e| ca_cert_error(e.to_string()))?;
+					let mut roots = RootCertStore::empty();
+					for cert in rustls_pemfile::certs(&mut BufReader::new(file))
+					{
+						roots
+							.add(
+								cert.map_err(|e| ca_cert_error(e.to_string()))?,
+							)
+							.map_err(|e| ca_cert_error(e.to_string()))?;
+					}
+					if roots.is_empty() {
+						return Err(ca_cert_error(
+							"no certificates in file".to_string(),
+						));
+					}
+					roots
+				}
+				None => RootCertStore {
+					roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+				},
+			};
+
+			Ok(Arc::new(
+				ClientConfig::builder()
+					.with_root_certificates(roots)
+					.with_no_client_auth(),
+			))
+		}
+
+		/// Call `send` until it succeeds, fails with an error that is not
+		/// transient, or runs out of retries.
+		fn with_retries(
+			&self,
+			url: &str,
+			mut send: impl FnMut() -> Result<ureq::Response, ureq::Error>,
+		) -> Result<ureq::Response, Error> {
+			let mut backoff = self.backoff;
+			let mut attempt = 0;
+			loop {
+				match send() {
+					Err(e) if attempt < self.retries && is_transient(&e) => {
+						attempt += 1;
+						log::warn(
+							"client",
+							format_args!(
+								"Request to {url} failed ({e}), retrying in \
+								{}ms ({attempt} of {})",
+								backoff.as_millis(),
+								self.retries
+							),
+						);
+						std::thread::sleep(backoff);
+						backoff = backoff.saturating_mul(2);
+					}
+					result => {
+						return result.map_err(|e| map_ureq_error(url, e))
+					}
+				}
+			}
+		}
+	}
+
+	/// Errors talking to the host.
+	#[derive(Debug)]
+	pub enum Error {
+		/// The host could not be reached.
+		Transport {
+			/// Url of the request.
+			url: String,
+			/// Why the request failed.
+			error: String,
+		},
+		/// The host responded with an error status.
+		Status {
+			/// Url of the request.
+			url: String,
+			/// HTTP status code of the response.
+			code: u16,
+			/// Body of the response, if it could be read.
+			body: Option<String>,
+		},
+		/// Reading the response body failed.
+		Read(std::io::Error),
+		/// The response body is not a [`ProtocolMsg`].
+		Decode(std::io::Error),
+		/// The CA certificate in [`Config::ca_cert`] could not be loaded.
+		CaCert {
+			/// Path of the CA certificate.
+			path: String,
+			/// Why it could not be loaded.
+			error: String,
+		},
+		/// The proxy from the environment is not a valid proxy url.
+		Proxy {
+			/// The proxy url.
+			proxy: String,
+			/// Why it is not valid.
+			error: String,
+		},
+	}
+
+	impl std::fmt::Display for Error {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				Self::Transport { url, error } => write!(
+					f,
+					"could not reach the host at {url}: {error}. Check the \
+					`--host-ip` and `--host-port` args and that the host is \
+					running"
+				),
+				Self::Status { url, code, body } => write!(
+					f,
+					"the host responded to {url} with status {code}: {}",
+					body.as_deref().unwrap_or("<unreadable body>")
+				),
+				Self::Read(e) => {
+					write!(f, "failed to read the response from the host: {e}")
+				}
+				Self::Decode(e) => write!(
+					f,
+					"could not decode the response from the host: {e}. Make \
+					sure the qos_client version matches qos_host"
+				),
+				Self::CaCert { path, error } => {
+					write!(
+						f,
+						"could not load the CA certificate {path}: {error}"
+					)
+				}
+				Self::Proxy { proxy, error } => {
+					write!(f, "invalid proxy {proxy}: {error}")
+				}
+			}
+		}
+	}
+
+	/// Post a [`qos_core::protocol::msg::ProtocolMsg`] to the given host `url`
+	/// with the default [`Config`].
+	pub fn post(url: &str, msg: &ProtocolMsg) -> Result<ProtocolMsg, Error> {
+		post_with_config(url, msg, &Config::default())
+	}
+
 	/// Post a [`qos_core::protocol::msg::ProtocolMsg`] to the given host `url`.
 	///
 	/// # Panics
 	/// Panics if the `msg` cannot be Borsh serialized.
 	/// Should never happen in practice because all protocol messages are
 	/// Borsh-serializable.
-	pub fn post(url: &str, msg: &ProtocolMsg) -> Result<ProtocolMsg, String> {
+	pub fn post_with_config(
+		url: &str,
+		msg: &ProtocolMsg,
+		config: &Config,
+	) -> Result<ProtocolMsg, Error> {
 		let mut buf: Vec<u8> = vec![];
+		let body = borsh::to_vec(msg)
+			.expect("ProtocolMsg can always be serialized. qed.");
 
-		let response = ureq::post(url)
-			.send_bytes(
-				&borsh::to_vec(msg)
-					.expect("ProtocolMsg can always be serialized. qed."),
-			)
-			.map_err(|e| match e {
-				ureq::Error::Status(code, r) => {
-					let body = r.into_string();
-					format!("http_post error: [url: {url}, status: {code}, body: {body:?}]")
-				}
-				ureq::Error::Transport(e) => {
-					format!("http_post error: transport error: {e}")
-				}
-			})?;
+		let agent = config.agent(url)?;
+		let response =
+			config.with_retries(url, || agent.post(url).send_bytes(&body))?;
 
-		response.into_reader().take(MAX_SIZE).read_to_end(&mut buf).map_err(
-			|e| {
-				format!(
-					"http_post error: failed to read response to buffer {e:?}"
-				)
-			},
-		)?;
+		response
+			.into_reader()
+			.take(MAX_SIZE)
+			.read_to_end(&mut buf)
+			.map_err(Error::Read)?;
 
-		let decoded_response =
-			ProtocolMsg::try_from_slice(&buf).map_err(|e| {
-				format!("http_post error: deserialization error: {e:?}")
-			})?;
+		ProtocolMsg::try_from_slice(&buf).map_err(Error::Decode)
+	}
 
-		Ok(decoded_response)
+	/// Get the resource at the given host `url` with the default [`Config`].
+	pub fn get(url: &str) -> Result<String, Error> {
+		get_with_config(url, &Config::default())
 	}
 
 	/// Get the resource at the given host `url`.
-	///
-	/// # Panics
-	///
-	/// Panics if the http request fails.
-	pub fn get(url: &str) -> Result<String, String> {
-		ureq::get(url)
-			.call()
-			.unwrap()
+	pub fn get_with_config(
+		url: &str,
+		config: &Config,
+	) -> Result<String, Error> {
+		let agent = config.agent(url)?;
+		config
+			.with_retries(url, || agent.get(url).call())?
 			.into_string()
-			.map_err(|_| format!("GET `{url:?}` failed"))
+			.map_err(Error::Read)
+	}
+
+	/// The proxy to use for `url`, from the environment variables looked up
+	/// with `var`.
+	fn proxy_from_env(
+		url: &str,
+		var: impl Fn(&str) -> Option<String>,
+	) -> Option<String> {
+		let var = |name: &str| {
+			var(name)
+				.or_else(|| var(&name.to_lowercase()))
+				.filter(|value| !value.is_empty())
+		};
+		let (scheme, rest) = url.split_once("://")?;
+		let authority = rest.split(['/', '?', '#']).next()?;
+		let host_and_port = authority.rsplit('@').next()?;
+		let host = match host_and_port.strip_prefix('[') {
+			Some(ipv6) => ipv6.split(']').next()?,
+			None => host_and_port.split(':').next()?,
+		};
+
+		let bypass = var("NO_PROXY").is_some_and(|no_proxy| {
+			no_proxy.split(',').map(str::trim).any(|entry| {
+				let entry = entry.trim_start_matches('.');
+				entry == "*"
+					|| host.eq_ignore_ascii_case(entry)
+					|| host
+						.to_lowercase()
+						.ends_with(&format!(".{}", entry.to_lowercase()))
+			})
+		});
+		if bypass {
+			return None;
+		}
+
+		match scheme {
+			"https" => var("HTTPS_PROXY"),
+			"http" => var("HTTP_PROXY"),
+			_ => None,
+		}
+	}
+
+	/// [`ureq::TlsConnector`] for rustls, so `https://` hosts are verified
+	/// with the same rustls as the rest of the workspace.
+	struct TlsConnector(Arc<ClientConfig>);
+
+	impl ureq::TlsConnector for TlsConnector {
+		fn connect(
+			&self,
+			dns_name: &str,
+			mut io: Box<dyn ureq::ReadWrite>,
+		) -> Result<Box<dyn ureq::ReadWrite>, ureq::Error> {
+			// rustls does not take ipv6 addresses in brackets
+			let dns_name =
+				dns_name.trim_start_matches('[').trim_end_matches(']');
+			let server_name = ServerName::try_from(dns_name.to_string())
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+			let mut connection =
+				rustls::ClientConnection::new(self.0.clone(), server_name)
+					.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+			connection.complete_io(&mut io)?;
+
+			Ok(Box::new(TlsStream(rustls::StreamOwned::new(connection, io))))
+		}
+	}
+
+	#[derive(Debug)]
+	struct TlsStream(
+		rustls::StreamOwned<rustls::ClientConnection, Box<dyn ureq::ReadWrite>>,
+	);
+
+	impl ureq::ReadWrite for TlsStream {
+		fn socket(&self) -> Option<&TcpStream> {
+			self.0.get_ref().socket()
+		}
+	}
+
+	impl Read for TlsStream {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			self.0.read(buf)
+		}
+	}
+
+	impl Write for TlsStream {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.write(buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			self.0.flush()
+		}
+	}
+
+	/// Whether a request that failed with `err` might succeed if retried.
+	fn is_transient(err: &ureq::Error) -> bool {
+		match err {
+			ureq::Error::Status(code, _) => matches!(code, 502..=504),
+			ureq::Error::Transport(e) => match e.kind() {
+				ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed => {
+					true
+				}
+				// Failed TLS handshakes surface as invalid data and will fail
+				// the same way again.
+				ureq::ErrorKind::Io => !std::error::Error::source(e)
+					.and_then(|e| e.downcast_ref::<io::Error>())
+					.is_some_and(|e| e.kind() == io::ErrorKind::InvalidData),
+				_ => false,
+			},
+		}
+	}
+
+	fn map_ureq_error(url: &str, err: ureq::Error) -> Error {
+		match err {
+			ureq::Error::Status(code, response) => Error::Status {
+				url: url.to_string(),
+				code,
+				body: response.into_string().ok(),
+			},
+			ureq::Error::Transport(e) => {
+				Error::Transport { url: url.to_string(), error: e.to_string() }
+			}
+		}
+	}
+	#[cfg(test)]
+	mod tests {
+		use std::{
+			io::{Read, Write},
+			net::TcpListener,
+			thread,
+		};
+
+		use super::*;
+
+		/// Serve one response per item of `responses`, in order, reading each
+		/// request fully first.
+		fn serve(responses: Vec<Vec<u8>>) -> String {
+			let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+			let url = format!(
+				"http://{}/qos/message",
+				listener.local_addr().unwrap()
+			);
+			thread::spawn(move || {
+				for response in responses {
+					let (mut stream, _) = listener.accept().unwrap();
+					let mut request = vec![];
+					let mut buf = [0; 1024];
+					loop {
+						let n = stream.read(&mut buf).unwrap();
+						request.extend_from_slice(&buf[..n]);
+						let text = String::from_utf8_lossy(&request);
+						let Some(end) = text.find("\r\n\r\n") else { continue };
+						let len = text[..end]
+							.lines()
+							.find_map(|l| l.strip_prefix("Content-Length: "))
+							.map_or(0, |l| l.parse().unwrap());
+						if request.len() >= end + 4 + len {
+							break;
+						}
+					}
+					stream.write_all(&response).unwrap();
+				}
+			});
+			url
+		}
+
+		fn response(status: &str, body: &[u8]) -> Vec<u8> {
+			let mut response = format!(
+				"HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: \
+				close\r\n\r\n",
+				body.len()
+			)
+			.into_bytes();
+			response.extend_from_slice(body);
+			response
+		}
+
+		fn config(retries: u32) -> Config {
+			Config {
+				retries,
+				backoff: Duration::from_millis(1),
+				..Config::default()
+			}
+		}
+
+		#[test]
+		fn proxy_from_env_respects_scheme_and_no_proxy() {
+			let env = |vars: &'static [(&'static str, &'static str)]| {
+				move |name: &str| {
+					vars.iter()
+						.find(|(var, _)| *var == name)
+						.map(|(_, value)| (*value).to_string())
+				}
+			};
+			let vars = env(&[
+				("HTTPS_PROXY", "http://proxy:3128"),
+				("http_proxy", "http://plain-proxy:3128"),
+				("NO_PROXY", "localhost, .internal.example"),
+			]);
+
+			assert_eq!(
+				proxy_from_env("https://host.example:443/qos/message", vars),
+				Some("http://proxy:3128".to_string())
+			);
+			assert_eq!(
+				proxy_from_env("http://10.0.0.1:3000/qos/message", vars),
+				Some("http://plain-proxy:3128".to_string())
+			);
+			assert_eq!(
+				proxy_from_env("https://localhost:3000/qos/message", vars),
+				None
+			);
+			assert_eq!(
+				proxy_from_env("https://enclave.internal.example/qos", vars),
+				None
+			);
+			assert_eq!(
+				proxy_from_env("https://host.example/qos", env(&[])),
+				None
+			);
+		}
+
+		#[test]
+		fn post_retries_when_the_host_is_unavailable() {
+			let ok = borsh::to_vec(&ProtocolMsg::StatusRequest).unwrap();
+			let url = serve(vec![
+				response("503 Service Unavailable", b"busy"),
+				response("200 OK", &ok),
+			]);
+
+			assert_eq!(
+				post_with_config(&url, &ProtocolMsg::StatusRequest, &config(1))
+					.unwrap(),
+				ProtocolMsg::StatusRequest
+			);
+		}
+
+		#[test]
+		fn post_surfaces_the_status_and_body() {
+			let url = serve(vec![
+				response("503 Service Unavailable", b"busy"),
+				response("400 Bad Request", b"bad message"),
+			]);
+
+			match post_with_config(
+				&url,
+				&ProtocolMsg::StatusRequest,
+				&config(0),
+			) {
+				Err(Error::Status { code: 503, body, .. }) => {
+					assert_eq!(body.as_deref(), Some("busy"));
+				}
+				other => panic!("unexpected result: {other:?}"),
+			}
+
+			// Client errors are not retried
+			match post_with_config(
+				&url,
+				&ProtocolMsg::StatusRequest,
+				&config(3),
+			) {
+				Err(Error::Status { code: 400, body, .. }) => {
+					assert_eq!(body.as_deref(), Some("bad message"));
+				}
+				other => panic!("unexpected result: {other:?}"),
+			}
+		}
 	}
 }
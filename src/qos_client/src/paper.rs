@@ -0,0 +1,271 @@
+//! Printable paper backups of encrypted shares and personal keys, for cold
+//! storage of ceremony material.
+//!
+//! A backup is a text document with recovery instructions followed by an
+//! armored block:
+//!
+//! ```text
+//! -----BEGIN QOS PAPER BACKUP-----
+//! Version: 1
+//! Kind: share
+//! Name: alice.share
+//! Length: 64
+//! Checksum: <sha256 of the contents, hex>
+//!
+//! 0001  ab12 cd34 ... 9f00  | 1a2b
+//! ...
+//! -----END QOS PAPER BACKUP-----
+//! ```
+//!
+//! Each line of the body holds up to [`BYTES_PER_LINE`] bytes of the contents
+//! in hex, and ends with a checksum of the line, so a typo made while typing
+//! a backup back in can be pinned to its line. [`decode`] ignores everything
+//! outside the armored block, blank lines and spacing within lines.
+
+use std::str::FromStr;
+
+use qos_crypto::sha_256;
+
+const BEGIN: &str = "-----BEGIN QOS PAPER BACKUP-----";
+const END: &str = "-----END QOS PAPER BACKUP-----";
+const VERSION: u32 = 1;
+/// Number of bytes of the contents on each line of the body.
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Errors encoding or decoding paper backups.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PaperError {
+	/// The text is not a paper backup, or a header is missing or malformed.
+	InvalidBackup(&'static str),
+	/// The backup was made with a format version this client does not know.
+	UnsupportedVersion(String),
+	/// Not one of the kinds of [`PaperKind`].
+	UnknownKind(String),
+	/// The checksum of the given line of the body does not match, most
+	/// likely because of a typo.
+	LineChecksumMismatch(usize),
+	/// The contents do not match the length or checksum in the headers.
+	ChecksumMismatch,
+}
+
+/// Kinds of ceremony material that can be backed up on paper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperKind {
+	/// A share of the quorum key, encrypted to a personal key.
+	Share,
+	/// A personal key, possibly encrypted with a passphrase.
+	PersonalKey,
+}
+
+impl PaperKind {
+	/// Name of the kind, as written in the backup.
+	#[must_use]
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Share => "share",
+			Self::PersonalKey => "personal-key",
+		}
+	}
+
+	fn instructions(self) -> &'static str {
+		match self {
+			Self::Share => {
+				"This is an encrypted share of a QuorumOS quorum key. It is \
+				useless without\nthe personal key it is encrypted to."
+			}
+			Self::PersonalKey => {
+				"This is a QuorumOS personal key. Anyone holding it can \
+				decrypt the shares\nencrypted to it: store it apart from those \
+				shares."
+			}
+		}
+	}
+}
+
+impl FromStr for PaperKind {
+	type Err = PaperError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		[Self::Share, Self::PersonalKey]
+			.into_iter()
+			.find(|kind| kind.name().eq_ignore_ascii_case(s))
+			.ok_or_else(|| PaperError::UnknownKind(s.to_string()))
+	}
+}
+
+/// A decoded paper backup.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PaperBackup {
+	/// Kind of the backed up material.
+	pub kind: PaperKind,
+	/// File name the material was backed up from.
+	pub name: String,
+	/// The backed up material.
+	pub contents: Vec<u8>,
+}
+
+/// Render `backup` as a printable document.
+#[must_use]
+pub fn encode(backup: &PaperBackup) -> String {
+	let mut doc = format!(
+		"QuorumOS paper backup of {name}\n\n{instructions}\n\nTo recover it, \
+		type in the block below, from the BEGIN line to the END\nline, and \
+		run:\n\n    qos_client paper-backup-import --file-path <typed file> \
+		--output-path {name}\n\nEach line ends with a checksum of that line, \
+		so a typo is reported with its\nline number. Spacing within a line \
+		does not matter.\n\n{BEGIN}\nVersion: {VERSION}\nKind: \
+		{kind}\nName: {name}\nLength: {len}\nChecksum: {checksum}\n\n",
+		name = backup.name,
+		instructions = backup.kind.instructions(),
+		kind = backup.kind.name(),
+		len = backup.contents.len(),
+		checksum = qos_hex::encode(&sha_256(&backup.contents)),
+	);
+
+	for (i, chunk) in backup.contents.chunks(BYTES_PER_LINE).enumerate() {
+		let groups: Vec<_> = chunk.chunks(2).map(qos_hex::encode).collect();
+		doc.push_str(&format!(
+			"{:04}  {:<39}  | {}\n",
+			i + 1,
+			groups.join(" "),
+			line_checksum(chunk)
+		));
+	}
+	doc.push_str(END);
+	doc.push('\n');
+
+	doc
+}
+
+/// Decode a paper backup made with [`encode`], checking every line and the
+/// whole contents against their checksums.
+pub fn decode(doc: &str) -> Result<PaperBackup, PaperError> {
+	let mut lines = doc
+		.lines()
+		.map(str::trim)
+		.skip_while(|line| *line != BEGIN)
+		.skip(1)
+		.take_while(|line| *line != END)
+		.filter(|line| !line.is_empty());
+
+	let mut header = |name: &'static str| {
+		lines
+			.next()
+			.and_then(|line| line.strip_prefix(name))
+			.and_then(|line| line.strip_prefix(':'))
+			.map(str::trim)
+			.ok_or(PaperError::InvalidBackup(name))
+	};
+
+	let version = header("Version")?;
+	if version != VERSION.to_string() {
+		return Err(PaperError::UnsupportedVersion(version.to_string()));
+	}
+	let kind = header("Kind")?.parse()?;
+	let name = header("Name")?.to_string();
+	let len: usize = header("Length")?
+		.parse()
+		.map_err(|_| PaperError::InvalidBackup("Length"))?;
+	let checksum = header("Checksum")?.to_lowercase();
+
+	let mut contents = Vec::with_capacity(len);
+	for (i, line) in lines.enumerate() {
+		let number = i + 1;
+		let (data, line_sum) = line
+			.split_once('|')
+			.ok_or(PaperError::LineChecksumMismatch(number))?;
+		let mut data = data.split_whitespace();
+		if data.next() != Some(format!("{number:04}").as_str()) {
+			return Err(PaperError::LineChecksumMismatch(number));
+		}
+		let bytes = qos_hex::decode(&data.collect::<String>())
+			.map_err(|_| PaperError::LineChecksumMismatch(number))?;
+		if !line_sum.trim().eq_ignore_ascii_case(&line_checksum(&bytes)) {
+			return Err(PaperError::LineChecksumMismatch(number));
+		}
+		contents.extend(bytes);
+	}
+
+	if contents.len() != len || qos_hex::encode(&sha_256(&contents)) != checksum
+	{
+		return Err(PaperError::ChecksumMismatch);
+	}
+
+	Ok(PaperBackup { kind, name, contents })
+}
+
+/// First two bytes of the sha256 of a line, hex encoded.
+fn line_checksum(bytes: &[u8]) -> String {
+	qos_hex::encode(&sha_256(bytes)[..2])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn backup() -> PaperBackup {
+		PaperBackup {
+			kind: PaperKind::Share,
+			name: "alice.share".to_string(),
+			contents: (0..=40).collect(),
+		}
+	}
+
+	#[test]
+	fn encode_decode_round_trip() {
+		let doc = encode(&backup());
+		assert!(doc.contains("Kind: share\nName: alice.share\nLength: 41\n"));
+		assert!(
+			doc.contains("0001  0001 0203 0405 0607 0809 0a0b 0c0d 0e0f  | ")
+		);
+		assert_eq!(decode(&doc).unwrap(), backup());
+
+		// Typed in by hand, with different spacing and case
+		let typed = doc
+			.lines()
+			.map(|line| {
+				if line.starts_with("000") {
+					line.replace("  ", " ").to_uppercase()
+				} else {
+					line.to_string()
+				}
+			})
+			.collect::<Vec<_>>()
+			.join("\n");
+		assert_eq!(decode(&typed).unwrap(), backup());
+	}
+
+	#[test]
+	fn decode_reports_typos_and_unknown_versions() {
+		let doc = encode(&backup());
+
+		let typo = doc.replace("0001  0001 0203", "0001  0001 0204");
+		assert_eq!(decode(&typo), Err(PaperError::LineChecksumMismatch(1)));
+
+		let dropped_line = doc
+			.lines()
+			.filter(|line| !line.starts_with("0002"))
+			.collect::<Vec<_>>()
+			.join("\n");
+		assert_eq!(
+			decode(&dropped_line),
+			Err(PaperError::LineChecksumMismatch(2))
+		);
+
+		let truncated = doc
+			.lines()
+			.filter(|line| !line.starts_with("0003"))
+			.collect::<Vec<_>>()
+			.join("\n");
+		assert_eq!(decode(&truncated), Err(PaperError::ChecksumMismatch));
+
+		assert_eq!(
+			decode(&doc.replace("Version: 1", "Version: 2")),
+			Err(PaperError::UnsupportedVersion("2".to_string()))
+		);
+		assert_eq!(
+			decode("not a backup"),
+			Err(PaperError::InvalidBackup("Version"))
+		);
+	}
+}
@@ -0,0 +1,156 @@
+//! QR codes for moving compact ceremony artifacts between air gapped signing
+//! machines and the online ceremony machine.
+//!
+//! An artifact is encoded as the text `QOS:<KIND>:<HEX>`. The kind and the hex
+//! are upper case so the QR code can use the compact alphanumeric mode.
+//! Scanners output the same text, which [`decode`] turns back into the
+//! artifact.
+
+use std::str::FromStr;
+
+use qrcode::{
+	render::{svg, unicode::Dense1x2},
+	QrCode,
+};
+
+const PREFIX: &str = "QOS";
+
+/// Errors encoding or decoding QR codes.
+#[derive(Debug)]
+pub enum QrError {
+	/// The artifact does not fit in a QR code.
+	Encode(qrcode::types::QrError),
+	/// The scanned text is not a `QuorumOS` artifact.
+	InvalidPayload(String),
+	/// Not one of the kinds of [`QrArtifact`].
+	UnknownArtifact(String),
+}
+
+impl From<qrcode::types::QrError> for QrError {
+	fn from(err: qrcode::types::QrError) -> Self {
+		Self::Encode(err)
+	}
+}
+
+/// Kinds of artifacts that can be moved with QR codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrArtifact {
+	/// The qos hash of a manifest, which members sign to approve it.
+	ManifestHash,
+	/// A borsh encoded manifest approval.
+	Approval,
+	/// The sha256 digest of an attestation doc.
+	AttestationDigest,
+}
+
+impl QrArtifact {
+	/// Name of the artifact kind, as given on the command line.
+	#[must_use]
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::ManifestHash => "manifest-hash",
+			Self::Approval => "approval",
+			Self::AttestationDigest => "attestation-digest",
+		}
+	}
+}
+
+impl FromStr for QrArtifact {
+	type Err = QrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		[Self::ManifestHash, Self::Approval, Self::AttestationDigest]
+			.into_iter()
+			.find(|artifact| artifact.name().eq_ignore_ascii_case(s))
+			.ok_or_else(|| QrError::UnknownArtifact(s.to_string()))
+	}
+}
+
+/// Encode the `bytes` of an `artifact` as the text of a QR code.
+#[must_use]
+pub fn encode(artifact: QrArtifact, bytes: &[u8]) -> String {
+	format!(
+		"{PREFIX}:{}:{}",
+		artifact.name().to_uppercase(),
+		qos_hex::encode(bytes).to_uppercase()
+	)
+}
+
+/// Decode the scanned text of a QR code made with [`encode`].
+pub fn decode(payload: &str) -> Result<(QrArtifact, Vec<u8>), QrError> {
+	let invalid = || QrError::InvalidPayload(payload.to_string());
+
+	let mut parts = payload.trim().splitn(3, ':');
+	let (Some(prefix), Some(kind), Some(hex)) =
+		(parts.next(), parts.next(), parts.next())
+	else {
+		return Err(invalid());
+	};
+	if !prefix.eq_ignore_ascii_case(PREFIX) {
+		return Err(invalid());
+	}
+
+	let artifact = kind.parse()?;
+	let bytes = qos_hex::decode(hex).map_err(|_| invalid())?;
+
+	Ok((artifact, bytes))
+}
+
+/// Render the text of a QR code with unicode blocks, for a terminal.
+pub fn to_terminal(payload: &str) -> Result<String, QrError> {
+	// Light on dark so the code scans on terminals with a dark background.
+	Ok(QrCode::new(payload)?
+		.render::<Dense1x2>()
+		.dark_color(Dense1x2::Light)
+		.light_color(Dense1x2::Dark)
+		.build())
+}
+
+/// Render the text of a QR code as an SVG image.
+pub fn to_svg(payload: &str) -> Result<String, QrError> {
+	Ok(QrCode::new(payload)?
+		.render::<svg::Color>()
+		.min_dimensions(300, 300)
+		.build())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encode_decode_round_trip() {
+		let bytes = [0xab; 32];
+		let payload = encode(QrArtifact::ManifestHash, &bytes);
+		assert_eq!(payload, format!("QOS:MANIFEST-HASH:{}", "AB".repeat(32)));
+		assert_eq!(
+			decode(&payload).unwrap(),
+			(QrArtifact::ManifestHash, bytes.to_vec())
+		);
+
+		// Scanners may change the case or add a trailing newline
+		assert_eq!(
+			decode(&format!("{}\n", payload.to_lowercase())).unwrap(),
+			(QrArtifact::ManifestHash, bytes.to_vec())
+		);
+
+		assert!(!to_terminal(&payload).unwrap().is_empty());
+		assert!(to_svg(&payload).unwrap().starts_with("<?xml"));
+	}
+
+	#[test]
+	fn decode_rejects_other_payloads() {
+		assert!(matches!(
+			decode("https://example.com"),
+			Err(QrError::InvalidPayload(_))
+		));
+		assert!(matches!(
+			decode("QOS:SHARE:ABCD"),
+			Err(QrError::UnknownArtifact(_))
+		));
+		assert!(matches!(
+			decode("QOS:APPROVAL:XYZ"),
+			Err(QrError::InvalidPayload(_))
+		));
+	}
+}
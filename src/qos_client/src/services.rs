@@ -0,0 +1,7220 @@
+//! Client flows for `QuorumOS` ceremonies.
+//!
+//! The command line interface is a thin wrapper over these functions, so
+//! tools and tests can also drive a ceremony without spawning `qos_client`.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, BufRead, Write},
+	mem,
+	path::{Path, PathBuf},
+	str::FromStr,
+	thread,
+	time::{Duration, Instant},
+};
+
+use aws_nitro_enclaves_nsm_api::api::AttestationDoc;
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_core::{
+	audit::{AuditEntry, MAX_AUDIT_ENTRIES_PER_REQUEST},
+	hex, log,
+	protocol::{
+		msg::ProtocolMsg,
+		services::{
+			boot::{
+				Approval, CryptoConfig, EgressEndpoint, Manifest,
+				ManifestEnvelope, ManifestSet, MemberPubKey, Namespace,
+				NitroConfig, PatchSet, PivotConfig, QuorumMember,
+				RestartPolicy, ShareSet,
+			},
+			genesis::{GenesisOutput, GenesisSet},
+			key::EncryptedQuorumKey,
+			provision::{open_share, ShareRotation},
+		},
+		status::PivotStatus,
+		Hash256, ProtocolError, ProtocolPhase, QosHash,
+	},
+};
+use qos_crypto::{ct_eq, sha_256, sha_384, sha_512};
+use qos_nsm::{
+	nitro::{
+		attestation_doc_from_der, aws_root_cert,
+		unsafe_attestation_doc_from_der,
+		verify_attestation_doc_against_user_input,
+	},
+	types::NsmResponse,
+};
+use qos_p256::{P256Error, P256Pair, P256Public};
+use zeroize::Zeroizing;
+
+use crate::{
+	kms::{Kms, KmsError},
+	paper::{self, PaperBackup, PaperError, PaperKind},
+	qr::{self, QrArtifact, QrError},
+	request,
+};
+
+const PUB_EXT: &str = "pub";
+const SECRET_EXT: &str = "secret";
+const SHARE_EXT: &str = "share";
+const GENESIS_ATTESTATION_DOC_FILE: &str = "genesis_attestation_doc";
+const GENESIS_OUTPUT_FILE: &str = "genesis_output";
+const MANIFEST_ENVELOPE: &str = "manifest_envelope";
+const APPROVAL_EXT: &str = "approval";
+const QUORUM_THRESHOLD_FILE: &str = "quorum_threshold";
+const DR_WRAPPED_QUORUM_KEY: &str = "dr_wrapped_quorum_key";
+const PCRS_PATH: &str = "aws-x86_64.pcrs";
+const GENESIS_DR_ARTIFACTS: &str = "genesis_dr_artifacts";
+const SHARE_ROTATION_FILE: &str = "share_rotation";
+
+const DANGEROUS_DEV_BOOT_MEMBER: &str = "DANGEROUS_DEV_BOOT_MEMBER";
+const DANGEROUS_DEV_BOOT_NAMESPACE: &str =
+	"DANGEROUS_DEV_BOOT_MEMBER_NAMESPACE";
+const DANGEROUS_DEV_BOOT_PIVOT_TIMEOUT: Duration = Duration::from_secs(10);
+const DANGEROUS_DEV_BOOT_PIVOT_POLL: Duration = Duration::from_millis(100);
+
+#[allow(dead_code)]
+pub(crate) const SMARTCARD_FEAT_DISABLED_MSG: &str =
+	"The \"smartcard\" feature must be enabled to use YubiKey related functionality.";
+#[allow(dead_code)]
+pub(crate) const SIMULATE_FEAT_DISABLED_MSG: &str =
+	"The \"simulate\" feature must be enabled to simulate ceremonies.";
+
+const ENTER_PIN_PROMPT: &str = "Enter your pin: ";
+const TAP_MSG: &str = "Tap your YubiKey";
+const ENTER_PASSPHRASE_PROMPT: &str =
+	"Enter a passphrase for the master seed: ";
+const CONFIRM_PASSPHRASE_PROMPT: &str = "Confirm the passphrase: ";
+
+/// The type contained in a file to display.
+#[derive(Debug)]
+pub enum DisplayType {
+	/// A [`Manifest`].
+	Manifest,
+	/// A [`ManifestEnvelope`].
+	ManifestEnvelope,
+	/// A [`GenesisOutput`].
+	GenesisOutput,
+}
+
+impl FromStr for DisplayType {
+	type Err = Error;
+
+	fn from_str(ty: &str) -> Result<Self, Self::Err> {
+		match ty {
+			"manifest" => Ok(Self::Manifest),
+			"manifest-envelope" => Ok(Self::ManifestEnvelope),
+			"genesis-output" => Ok(Self::GenesisOutput),
+			unknown => Err(Error::UnknownDisplayType(unknown.to_string())),
+		}
+	}
+}
+
+/// Client errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Failed to open a yubikey. Make sure only 1 yubikey is connected to the
+	/// machine. Also try unplugging the yubikey to reset the PCSC session.
+	#[cfg(feature = "smartcard")]
+	OpenSingleYubiKey(yubikey::Error),
+	/// Error'ed while tried to generate a key and cert for the signing slot.
+	#[cfg(feature = "smartcard")]
+	GenerateSign(crate::yubikey::YubiKeyError),
+	/// Error'ed while tried to generate a key and cert for the encryption
+	/// slot.
+	#[cfg(feature = "smartcard")]
+	GenerateEncrypt(crate::yubikey::YubiKeyError),
+	/// General wrapper for yubikey error
+	#[cfg(feature = "smartcard")]
+	YubiKey(crate::yubikey::YubiKeyError),
+	/// Error from qos p256.
+	P256(qos_p256::P256Error),
+	/// The public key read from the yubikey for the pair did not match what
+	/// was expected.
+	#[cfg(feature = "smartcard")]
+	WrongPublicKey,
+	/// An error trying to read a pin from the terminal
+	#[allow(clippy::enum_variant_names)]
+	#[cfg(feature = "smartcard")]
+	PinEntryError(std::io::Error),
+	/// Failed to read share
+	ReadShare(String),
+	/// Error while try to read the quorum public key.
+	FailedToReadQuorumPublicKey(qos_p256::P256Error),
+	/// Error trying to the read a file that is supposed to have a manifest.
+	FailedToReadManifestFile(std::io::Error),
+	/// Error deserializing manifest.
+	FileDidNotHaveValidManifest,
+	/// Error trying to read a file that is supposed to have a manifest
+	/// envelope.
+	FailedToReadManifestEnvelopeFile(std::io::Error),
+	/// Error deserializing manifest envelope.
+	FileDidNotHaveValidManifestEnvelope,
+	/// Error trying to read a file that is supposed to contain attestation
+	/// doc.
+	FailedToReadAttestationDoc(std::io::Error),
+	/// Error trying to the read a file that is supposed to contain attestation
+	/// approval.
+	FailedToReadAttestationApproval(std::io::Error),
+	/// Error deserializing manifest envelope.
+	FileDidNotHaveValidAttestationApproval,
+	/// Failed to read file that was supposed to contain Ephemeral Key wrapped
+	/// share.
+	FailedToReadEphWrappedShare(std::io::Error),
+	/// Failed to read a file.
+	FailedToRead {
+		/// Path of the file.
+		path: String,
+		/// Why reading it failed.
+		error: String,
+	},
+	/// Failed to decode some hex
+	CouldNotDecodeHex(qos_hex::HexError),
+	/// Failed to deserialize something from borsh.
+	#[allow(clippy::enum_variant_names)]
+	BorshError,
+	/// Failed to read the disaster recovery public key.
+	FailedToReadDrKey(qos_p256::P256Error),
+	/// The attestation document could not be verified.
+	QosAttest(String),
+	/// Pivot file
+	FailedToReadPivot(std::io::Error),
+	/// Unexpected response from a request to the enclave - likely an error.
+	UnexpectedProtocolMsgResponse(String),
+	/// Could not read the file that should contain the encrypted quorum key.
+	FailedToReadEncryptedQuorumKey,
+	/// The contents of the file are not a valid encrypted quorum key struct.
+	InvalidEncryptedQuorumKey,
+	/// Signature could not be verified.
+	InvalidSignature,
+	/// Could not deterministically reproduce a signature.
+	CouldNotReproduceSignature,
+	/// Decryption did not result in the expected palintext.
+	BadDecryption,
+	/// The seed was read two different ways and the results did not match -
+	/// most likely a bug with the code.
+	#[allow(clippy::enum_variant_names)]
+	ErrorReadingSeed,
+	/// Given quorum key seed does not match the hash of the expected quorum
+	/// key seed.
+	SecretDoesNotMatch,
+	/// Failed to write a file.
+	FailedToWrite {
+		/// Path of the file.
+		path: String,
+		/// Why writing it failed.
+		error: String,
+	},
+	/// Talking to the host failed.
+	Request(request::Error),
+	/// The enclave has no manifest envelope yet; it is likely waiting for a
+	/// boot instruction.
+	ManifestEnvelopeNotFound,
+	/// The attestation document does not contain a valid ephemeral key.
+	InvalidEphemeralKey,
+	/// The given combination of args is not valid.
+	InvalidArgs(&'static str),
+	/// A hex argument could not be decoded.
+	InvalidHexArg {
+		/// Name of the argument.
+		arg: &'static str,
+		/// Why it could not be decoded.
+		error: hex::HexDecodeError,
+	},
+	/// `--display-type` is not a type that can be displayed.
+	UnknownDisplayType(String),
+	/// An error trying to read a passphrase from the terminal.
+	PassphraseEntry(std::io::Error),
+	/// The passphrase and its confirmation differ.
+	PassphrasesDoNotMatch,
+	/// The passphrase does not decrypt the master seed file.
+	WrongPassphrase(String),
+	/// The hash of the manifest is not the expected one.
+	ManifestHashDoesNotMatch {
+		/// Hash that was expected.
+		expected: String,
+		/// Hash of the manifest.
+		actual: String,
+	},
+	/// Some approvals are not valid or there are not enough to meet the
+	/// threshold.
+	InvalidApprovals,
+	/// The contents of a file are not valid.
+	InvalidFile {
+		/// Path of the file.
+		path: String,
+		/// Why the contents are not valid.
+		error: String,
+	},
+	/// The genesis output does not have the same members as the genesis set.
+	GenesisOutputMismatch,
+	/// The genesis output has no output for the given setup key and alias.
+	MemberOutputNotFound,
+	/// The decrypted share does not match the share hash in the genesis
+	/// output.
+	ShareHashMismatch,
+	/// The decrypted share is not a share of the expected quorum key and
+	/// threshold, or its header is corrupt.
+	InvalidShare(ProtocolError),
+	/// The manifest does not match the locally found sets, PCRs, pivot hash
+	/// or quorum key.
+	ManifestMismatch,
+	/// The user did not confirm an interactive check.
+	NotConfirmed,
+	/// Splitting or reconstructing a secret with shamir secret sharing failed.
+	Shamir(qos_crypto::QosCryptoError),
+	/// Failed to serialize to JSON.
+	Json(serde_json::Error),
+	/// Failed to encode or decode a QR code.
+	Qr(QrError),
+	/// Failed to decode a paper backup.
+	Paper(PaperError),
+	/// Failed to wrap or unwrap a share with KMS.
+	Kms(KmsError),
+	/// The manifest spec does not match the genesis output it names.
+	SpecDoesNotMatchGenesisOutput(&'static str),
+	/// The share rotation request does not match the manifest or genesis
+	/// output, or is not signed by its new key.
+	InvalidShareRotation(&'static str),
+	/// The manifest nonce is not greater than the highest nonce in the nonce
+	/// ledger for the namespace.
+	NonceNotIncreasing {
+		/// Name of the namespace.
+		namespace: String,
+		/// Nonce of the manifest.
+		nonce: u32,
+		/// Highest nonce used for the namespace.
+		highest: u32,
+	},
+	/// The enclave is not running the expected manifest.
+	EnclaveDoesNotMatchManifest,
+	/// The pivot did not start, or exited with an error.
+	PivotNotReady(PivotStatus),
+	/// Not enough shares were posted for the enclave to reconstruct the
+	/// quorum key.
+	ShareThresholdNotMet,
+	/// A crypto self test of the enclave failed.
+	SelfTestFailed,
+	/// The app did not echo back the data sent to it.
+	AppEchoMismatch {
+		/// Hex encoded data sent to the app.
+		sent: String,
+		/// Hex encoded data the app responded with.
+		received: String,
+	},
+	/// The exported audit log is not a valid hash chain.
+	AuditChainBroken {
+		/// Index of the first entry that is not intact or does not follow
+		/// the one before it.
+		index: u64,
+	},
+	/// The Quorum Key signature over a page of the exported audit log is not
+	/// valid.
+	AuditSignatureInvalid {
+		/// Index of the last entry of the page.
+		index: u64,
+	},
+	/// A step of a simulated ceremony failed.
+	#[cfg(feature = "simulate")]
+	SimulatedCeremony(qos_test_primitives::CeremonyError),
+}
+
+/// Exit codes of the client, so automation can tell failures apart. The
+/// values are stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+	/// A failure not covered by any other code.
+	Failure = 1,
+	/// The given args or the contents of given files are not valid.
+	InvalidInput = 2,
+	/// Reading or writing a file failed.
+	Io = 3,
+	/// The host could not be reached or responded with an error.
+	Network = 4,
+	/// The enclave responded, but not as expected.
+	Enclave = 5,
+	/// Attestation verification failed.
+	AttestationFailed = 6,
+	/// A signature, decryption or secret did not verify.
+	VerificationFailed = 7,
+	/// Talking to the yubikey failed.
+	YubiKey = 8,
+}
+
+impl Error {
+	/// The code to exit the process with for this error.
+	#[must_use]
+	pub fn exit_code(&self) -> ExitCode {
+		match self {
+			#[cfg(feature = "smartcard")]
+			Self::OpenSingleYubiKey(_)
+			| Self::GenerateSign(_)
+			| Self::GenerateEncrypt(_)
+			| Self::YubiKey(_)
+			| Self::WrongPublicKey
+			| Self::PinEntryError(_) => ExitCode::YubiKey,
+			Self::P256(_) => ExitCode::Failure,
+			Self::ReadShare(_)
+			| Self::FailedToReadQuorumPublicKey(_)
+			| Self::FileDidNotHaveValidManifest
+			| Self::FileDidNotHaveValidManifestEnvelope
+			| Self::FileDidNotHaveValidAttestationApproval
+			| Self::CouldNotDecodeHex(_)
+			| Self::BorshError
+			| Self::FailedToReadDrKey(_)
+			| Self::InvalidEncryptedQuorumKey
+			| Self::InvalidArgs(_)
+			| Self::InvalidHexArg { .. }
+			| Self::UnknownDisplayType(_)
+			| Self::PassphraseEntry(_)
+			| Self::PassphrasesDoNotMatch
+			| Self::WrongPassphrase(_)
+			| Self::InvalidFile { .. }
+			| Self::NonceNotIncreasing { .. }
+			| Self::MemberOutputNotFound
+			| Self::Shamir(_)
+			| Self::Qr(_)
+			| Self::Paper(_) => ExitCode::InvalidInput,
+			Self::FailedToReadManifestFile(_)
+			| Self::FailedToReadManifestEnvelopeFile(_)
+			| Self::FailedToReadAttestationDoc(_)
+			| Self::FailedToReadAttestationApproval(_)
+			| Self::FailedToReadEphWrappedShare(_)
+			| Self::FailedToRead { .. }
+			| Self::FailedToWrite { .. }
+			| Self::FailedToReadPivot(_)
+			| Self::FailedToReadEncryptedQuorumKey => ExitCode::Io,
+			Self::Request(_) => ExitCode::Network,
+			Self::UnexpectedProtocolMsgResponse(_)
+			| Self::ManifestEnvelopeNotFound
+			| Self::PivotNotReady(_)
+			| Self::ShareThresholdNotMet
+			| Self::SelfTestFailed
+			| Self::AppEchoMismatch { .. } => ExitCode::Enclave,
+			Self::QosAttest(_) | Self::InvalidEphemeralKey => {
+				ExitCode::AttestationFailed
+			}
+			Self::InvalidSignature
+			| Self::CouldNotReproduceSignature
+			| Self::BadDecryption
+			| Self::ErrorReadingSeed
+			| Self::SecretDoesNotMatch
+			| Self::ManifestHashDoesNotMatch { .. }
+			| Self::InvalidApprovals
+			| Self::GenesisOutputMismatch
+			| Self::ShareHashMismatch
+			| Self::InvalidShare(_)
+			| Self::ManifestMismatch
+			| Self::SpecDoesNotMatchGenesisOutput(_)
+			| Self::InvalidShareRotation(_)
+			| Self::EnclaveDoesNotMatchManifest
+			| Self::AuditChainBroken { .. }
+			| Self::AuditSignatureInvalid { .. } => ExitCode::VerificationFailed,
+			Self::NotConfirmed | Self::Json(_) | Self::Kms(_) => {
+				ExitCode::Failure
+			}
+			#[cfg(feature = "simulate")]
+			Self::SimulatedCeremony(_) => ExitCode::Failure,
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	#[allow(clippy::too_many_lines)]
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			#[cfg(feature = "smartcard")]
+			Self::OpenSingleYubiKey(e) => write!(
+				f,
+				"failed to open the YubiKey: {e}. Make sure exactly one \
+				YubiKey is connected; unplugging it resets the PCSC session"
+			),
+			#[cfg(feature = "smartcard")]
+			Self::GenerateSign(e) => {
+				write!(f, "failed to generate the YubiKey signing key: {e:?}")
+			}
+			#[cfg(feature = "smartcard")]
+			Self::GenerateEncrypt(e) => write!(
+				f,
+				"failed to generate the YubiKey encryption key: {e:?}"
+			),
+			#[cfg(feature = "smartcard")]
+			Self::YubiKey(e) => write!(f, "YubiKey error: {e:?}"),
+			#[cfg(feature = "smartcard")]
+			Self::WrongPublicKey => write!(
+				f,
+				"the YubiKey does not hold the expected key. Make sure the \
+				right YubiKey is connected"
+			),
+			#[cfg(feature = "smartcard")]
+			Self::PinEntryError(e) => write!(f, "failed to read the pin: {e}"),
+			Self::P256(e) => write!(f, "P256 error: {e:?}"),
+			Self::ReadShare(e) => write!(f, "failed to read the share: {e}"),
+			Self::FailedToReadQuorumPublicKey(e) => {
+				write!(f, "failed to read the quorum public key: {e:?}")
+			}
+			Self::FailedToReadManifestFile(e) => {
+				write!(f, "failed to read the manifest file: {e}")
+			}
+			Self::FileDidNotHaveValidManifest => {
+				write!(f, "the file does not contain a valid manifest")
+			}
+			Self::FailedToReadManifestEnvelopeFile(e) => {
+				write!(f, "failed to read the manifest envelope file: {e}")
+			}
+			Self::FileDidNotHaveValidManifestEnvelope => {
+				write!(f, "the file does not contain a valid manifest envelope")
+			}
+			Self::FailedToReadAttestationDoc(e) => {
+				write!(f, "failed to read the attestation doc file: {e}")
+			}
+			Self::FailedToReadAttestationApproval(e) => {
+				write!(f, "failed to read the approval file: {e}")
+			}
+			Self::FileDidNotHaveValidAttestationApproval => {
+				write!(f, "the file does not contain a valid approval")
+			}
+			Self::FailedToReadEphWrappedShare(e) => {
+				write!(f, "failed to read the ephemeral key wrapped share: {e}")
+			}
+			Self::FailedToRead { path, error } => {
+				write!(f, "failed to read {path}: {error}")
+			}
+			Self::CouldNotDecodeHex(e) => {
+				write!(f, "could not decode hex: {e:?}")
+			}
+			Self::BorshError => write!(f, "could not decode borsh"),
+			Self::FailedToReadDrKey(e) => {
+				write!(f, "failed to read the DR key: {e:?}")
+			}
+			Self::QosAttest(e) => write!(
+				f,
+				"attestation verification failed: {e}. Do not proceed; make \
+				sure the release dir and pcr3 preimage match the enclave"
+			),
+			Self::FailedToReadPivot(e) => {
+				write!(f, "failed to read the pivot binary: {e}")
+			}
+			Self::UnexpectedProtocolMsgResponse(r) => {
+				write!(f, "unexpected response from the enclave: {r}")
+			}
+			Self::FailedToReadEncryptedQuorumKey => {
+				write!(f, "failed to read the encrypted quorum key file")
+			}
+			Self::InvalidEncryptedQuorumKey => write!(
+				f,
+				"the file does not contain a valid encrypted quorum key"
+			),
+			Self::InvalidSignature => write!(f, "the signature is not valid"),
+			Self::CouldNotReproduceSignature => {
+				write!(f, "could not reproduce the signature")
+			}
+			Self::BadDecryption => {
+				write!(f, "decryption did not yield the expected plaintext")
+			}
+			Self::ErrorReadingSeed => write!(
+				f,
+				"reading the seed two ways gave different results; this is \
+				likely a bug"
+			),
+			Self::SecretDoesNotMatch => write!(
+				f,
+				"the quorum key seed does not match the expected quorum key"
+			),
+			Self::FailedToWrite { path, error } => {
+				write!(f, "failed to write {path}: {error}")
+			}
+			Self::Request(e) => write!(f, "{e}"),
+			Self::ManifestEnvelopeNotFound => write!(
+				f,
+				"the enclave has no manifest envelope; it is likely waiting \
+				for a boot instruction"
+			),
+			Self::InvalidEphemeralKey => write!(
+				f,
+				"the attestation doc does not contain a valid ephemeral key"
+			),
+			Self::InvalidArgs(msg) => write!(f, "invalid args: {msg}"),
+			Self::InvalidHexArg { arg, error } => {
+				write!(f, "invalid args: `--{arg}`: {error}")
+			}
+			Self::UnknownDisplayType(ty) => write!(
+				f,
+				"invalid args: `--display-type`: unknown type {ty:?}, expected \
+				manifest, manifest-envelope or genesis-output"
+			),
+			Self::PassphraseEntry(e) => {
+				write!(f, "failed to read the passphrase: {e}")
+			}
+			Self::PassphrasesDoNotMatch => {
+				write!(f, "the passphrases do not match")
+			}
+			Self::WrongPassphrase(path) => write!(
+				f,
+				"the passphrase does not decrypt the master seed at {path}"
+			),
+			Self::ManifestHashDoesNotMatch { expected, actual } => write!(
+				f,
+				"the manifest hash is {actual}, but {expected} was expected"
+			),
+			Self::InvalidApprovals => write!(
+				f,
+				"some approvals are not valid or the threshold is not met"
+			),
+			Self::InvalidFile { path, error } => {
+				write!(f, "{path} is not valid: {error}")
+			}
+			Self::GenesisOutputMismatch => write!(
+				f,
+				"the genesis output does not have the same members as the \
+				genesis set"
+			),
+			Self::MemberOutputNotFound => write!(
+				f,
+				"the genesis output has no output for the setup key and alias"
+			),
+			Self::ShareHashMismatch => write!(
+				f,
+				"the decrypted share does not match the expected share hash"
+			),
+			Self::InvalidShare(e) => write!(
+				f,
+				"the decrypted share is not a share of the expected quorum \
+				key: {e:?}"
+			),
+			Self::ManifestMismatch => write!(
+				f,
+				"the manifest does not match what was expected; not approving"
+			),
+			Self::NotConfirmed => write!(f, "exiting early without approving"),
+			Self::Shamir(e) => write!(f, "shamir secret sharing failed: {e:?}"),
+			Self::Json(e) => write!(f, "failed to serialize to JSON: {e}"),
+			Self::Qr(QrError::Encode(e)) => {
+				write!(f, "failed to encode QR code: {e}")
+			}
+			Self::Qr(QrError::InvalidPayload(payload)) => {
+				write!(f, "not a QuorumOS QR code: {payload}")
+			}
+			Self::Qr(QrError::UnknownArtifact(artifact)) => write!(
+				f,
+				"unknown QR artifact {artifact}, expected one of: \
+				manifest-hash, approval, attestation-digest"
+			),
+			Self::Paper(PaperError::InvalidBackup(header)) => write!(
+				f,
+				"not a QuorumOS paper backup: missing or malformed {header} \
+				header"
+			),
+			Self::Paper(PaperError::UnsupportedVersion(version)) => write!(
+				f,
+				"paper backup version {version} is not supported by this client"
+			),
+			Self::Paper(PaperError::UnknownKind(kind)) => write!(
+				f,
+				"unknown paper backup kind {kind}, expected one of: share, \
+				personal-key"
+			),
+			Self::Paper(PaperError::LineChecksumMismatch(line)) => write!(
+				f,
+				"line {line} of the paper backup does not match its checksum; \
+				check it for typos"
+			),
+			Self::Paper(PaperError::ChecksumMismatch) => write!(
+				f,
+				"the paper backup does not match its checksum; check for \
+				missing lines"
+			),
+			Self::Kms(KmsError::Spawn(e)) => write!(
+				f,
+				"failed to run the aws CLI: {e}. Make sure it is installed and \
+				on the PATH"
+			),
+			Self::Kms(KmsError::Failed { code, stderr }) => write!(
+				f,
+				"the aws CLI failed with exit code {code:?}: {stderr}"
+			),
+			Self::Kms(KmsError::InvalidOutput) => {
+				write!(f, "the aws CLI did not output base64")
+			}
+			Self::SpecDoesNotMatchGenesisOutput(what) => write!(
+				f,
+				"the {what} of the manifest spec does not match the genesis \
+				output"
+			),
+			Self::InvalidShareRotation(why) => {
+				write!(f, "the share rotation request is not valid: {why}")
+			}
+			Self::NonceNotIncreasing { namespace, nonce, highest } => write!(
+				f,
+				"nonce {nonce} is not greater than {highest}, the highest \
+				nonce used for namespace {namespace}; use `next-nonce` to get \
+				the next one"
+			),
+			Self::EnclaveDoesNotMatchManifest => {
+				write!(f, "the enclave is not running the manifest")
+			}
+			Self::ShareThresholdNotMet => write!(
+				f,
+				"not enough shares were posted to reconstruct the quorum key; \
+				run the ceremony again to post the remaining shares"
+			),
+			Self::PivotNotReady(status) => {
+				write!(f, "the pivot is not running: {status:?}")
+			}
+			Self::SelfTestFailed => {
+				write!(f, "the enclave failed its crypto self test")
+			}
+			Self::AppEchoMismatch { sent, received } => write!(
+				f,
+				"the app responded with {received} instead of echoing {sent}"
+			),
+			Self::AuditChainBroken { index } => {
+				write!(f, "the audit log chain is broken at entry {index}")
+			}
+			Self::AuditSignatureInvalid { index } => write!(
+				f,
+				"the quorum key signature over audit log entry {index} is not \
+				valid"
+			),
+			#[cfg(feature = "simulate")]
+			Self::SimulatedCeremony(e) => {
+				write!(f, "simulated ceremony failed: {e}")
+			}
+		}
+	}
+}
+
+impl From<request::Error> for Error {
+	fn from(err: request::Error) -> Self {
+		Self::Request(err)
+	}
+}
+
+impl From<borsh::io::Error> for Error {
+	fn from(_: borsh::io::Error) -> Self {
+		Self::BorshError
+	}
+}
+
+#[cfg(feature = "smartcard")]
+impl From<crate::yubikey::YubiKeyError> for Error {
+	fn from(err: crate::yubikey::YubiKeyError) -> Self {
+		Error::YubiKey(err)
+	}
+}
+
+impl From<P256Error> for Error {
+	fn from(err: P256Error) -> Self {
+		Error::P256(err)
+	}
+}
+
+impl From<qos_hex::HexError> for Error {
+	fn from(err: qos_hex::HexError) -> Error {
+		Error::CouldNotDecodeHex(err)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(err: serde_json::Error) -> Error {
+		Error::Json(err)
+	}
+}
+
+impl From<QrError> for Error {
+	fn from(err: QrError) -> Error {
+		Error::Qr(err)
+	}
+}
+
+impl From<KmsError> for Error {
+	fn from(err: KmsError) -> Error {
+		Error::Kms(err)
+	}
+}
+
+impl From<PaperError> for Error {
+	fn from(err: PaperError) -> Error {
+		Error::Paper(err)
+	}
+}
+
+impl From<qos_nsm::nitro::AttestError> for Error {
+	fn from(err: qos_nsm::nitro::AttestError) -> Error {
+		let msg = format!("{err:?}");
+		Error::QosAttest(msg)
+	}
+}
+
+/// Use a P256 key pair or Yubikey for signing operations.
+pub enum PairOrYubi {
+	/// Yubikey
+	#[cfg(feature = "smartcard")]
+	Yubi((yubikey::YubiKey, Vec<u8>)),
+	/// P256 keypair
+	Pair(P256Pair),
+}
+
+impl PairOrYubi {
+	/// Create a P256 key pair or yubikey from the given inputs
+	pub fn from_inputs(
+		yubikey_flag: bool,
+		secret_path: Option<String>,
+		maybe_pin_path: Option<String>,
+	) -> Result<Self, Error> {
+		let result = match (yubikey_flag, secret_path) {
+			(true, None) => {
+				#[cfg(feature = "smartcard")]
+				{
+					let yubi = crate::yubikey::open_single()?;
+
+					let pin = if let Some(pin_path) = maybe_pin_path {
+						pin_from_path(pin_path)?
+					} else {
+						rpassword::prompt_password(ENTER_PIN_PROMPT)
+							.map_err(Error::PinEntryError)?
+							.as_bytes()
+							.to_vec()
+					};
+
+					PairOrYubi::Yubi((yubi, pin))
+				}
+				#[cfg(not(feature = "smartcard"))]
+				{
+					return Err(Error::InvalidArgs(
+						SMARTCARD_FEAT_DISABLED_MSG,
+					));
+				}
+			}
+			(false, Some(path)) => PairOrYubi::Pair(read_master_seed(path)?),
+			(false, None) => {
+				return Err(Error::InvalidArgs(
+					"need either the yubikey flag or a file with the secret",
+				))
+			}
+			(true, Some(_)) => {
+				return Err(Error::InvalidArgs(
+					"cannot have both the yubikey flag and a file with the secret",
+				))
+			}
+		};
+
+		Ok(result)
+	}
+
+	/// Sign the payload
+	pub fn sign(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
+		match self {
+			#[cfg(feature = "smartcard")]
+			Self::Yubi((ref mut yubi, ref pin)) => {
+				println!("{TAP_MSG}");
+				crate::yubikey::sign_data(yubi, data, pin).map_err(Into::into)
+			}
+			Self::Pair(ref pair) => pair.sign(data).map_err(Into::into),
+		}
+	}
+
+	/// Decrypt the payload. The plaintext is wiped from memory when it is
+	/// dropped.
+	pub fn decrypt(
+		&mut self,
+		payload: &[u8],
+	) -> Result<Zeroizing<Vec<u8>>, Error> {
+		let plaintext = match self {
+			#[cfg(feature = "smartcard")]
+			Self::Yubi((ref mut yubi, ref pin)) => {
+				println!("{TAP_MSG}");
+				let shared_secret =
+					crate::yubikey::shared_secret(yubi, payload, pin)?;
+				let encrypt_pub = crate::yubikey::key_agree_public_key(yubi)?;
+				let public = qos_p256::encrypt::P256EncryptPublic::from_bytes(
+					&encrypt_pub,
+				)?;
+
+				public.decrypt_from_shared_secret(payload, &shared_secret)?
+			}
+			Self::Pair(ref pair) => pair.decrypt(payload)?,
+		};
+
+		Ok(Zeroizing::new(plaintext))
+	}
+
+	/// Get the public key in bytes
+	pub fn public_key_bytes(&mut self) -> Result<Vec<u8>, Error> {
+		match self {
+			#[cfg(feature = "smartcard")]
+			Self::Yubi((ref mut yubi, _)) => {
+				crate::yubikey::pair_public_key(yubi).map_err(Into::into)
+			}
+			Self::Pair(ref pair) => Ok(pair.public_key().to_bytes()),
+		}
+	}
+}
+
+/// Read a P256 key pair from a master seed file, prompting for the
+/// passphrase if the file is encrypted.
+pub fn read_master_seed<P: AsRef<Path>>(path: P) -> Result<P256Pair, Error> {
+	let path = path.as_ref();
+	let contents = fs::read(path).map_err(|e| Error::FailedToRead {
+		path: path.display().to_string(),
+		error: e.to_string(),
+	})?;
+	if !P256Pair::is_encrypted_master_seed(&contents) {
+		return P256Pair::from_hex_file(path).map_err(Into::into);
+	}
+
+	let passphrase = rpassword::prompt_password(format!(
+		"Enter the passphrase for {}: ",
+		path.display()
+	))
+	.map_err(Error::PassphraseEntry)?;
+	P256Pair::from_encrypted_master_seed_hex(&contents, passphrase.as_bytes())
+		.map_err(|e| match e {
+			P256Error::WrongPassphrase => {
+				Error::WrongPassphrase(path.display().to_string())
+			}
+			e => e.into(),
+		})
+}
+
+/// Prompt for a new passphrase to encrypt a master seed with.
+fn prompt_new_passphrase() -> Result<String, Error> {
+	let passphrase = rpassword::prompt_password(ENTER_PASSPHRASE_PROMPT)
+		.map_err(Error::PassphraseEntry)?;
+	let confirmation = rpassword::prompt_password(CONFIRM_PASSPHRASE_PROMPT)
+		.map_err(Error::PassphraseEntry)?;
+	if passphrase != confirmation {
+		return Err(Error::PassphrasesDoNotMatch);
+	}
+
+	Ok(passphrase)
+}
+
+/// Generate a P256 key pair, writing the master seed to `master_seed_path`
+/// and the public key to `pub_path`. With `encrypt`, the master seed is
+/// encrypted with a passphrase prompted for on the terminal. With
+/// `mnemonic`, a recovery phrase for the master seed is printed to write
+/// down.
+pub fn generate_file_key<P: AsRef<Path>>(
+	master_secret_path: P,
+	pub_key_path: P,
+	encrypt: bool,
+	mnemonic: bool,
+) -> Result<(), Error> {
+	let share_key_pair = P256Pair::generate()?;
+	// Fail before writing anything if recovery phrases are not available
+	let phrase = mnemonic.then(|| share_key_pair.to_mnemonic()).transpose()?;
+	write_file_key(&share_key_pair, master_secret_path, pub_key_path, encrypt)?;
+
+	if let Some(phrase) = phrase {
+		println!(
+			"Recovery phrase for the master seed. Write it down and store it \
+			as safely as the master seed itself:\n\n{}\n",
+			phrase.as_str()
+		);
+	}
+
+	Ok(())
+}
+
+/// Recreate a key pair made with [`generate_file_key`] from its recovery
+/// `phrase`, writing the master seed and public key like
+/// [`generate_file_key`].
+pub fn recover_file_key<P: AsRef<Path>>(
+	phrase: &str,
+	master_secret_path: P,
+	pub_key_path: P,
+	encrypt: bool,
+) -> Result<(), Error> {
+	let share_key_pair = P256Pair::from_mnemonic(phrase)?;
+	write_file_key(&share_key_pair, master_secret_path, pub_key_path, encrypt)
+}
+
+fn write_file_key<P: AsRef<Path>>(
+	share_key_pair: &P256Pair,
+	master_secret_path: P,
+	pub_key_path: P,
+	encrypt: bool,
+) -> Result<(), Error> {
+	let master_seed = if encrypt {
+		let passphrase = prompt_new_passphrase()?;
+		Zeroizing::new(
+			share_key_pair
+				.to_encrypted_master_seed_hex(passphrase.as_bytes())?,
+		)
+	} else {
+		share_key_pair.to_master_seed_hex()
+	};
+
+	// Write the personal key secret
+	write_with_msg(master_secret_path.as_ref(), &master_seed, "Master Seed")?;
+
+	// Write the setup key public key
+	write_with_msg(
+		pub_key_path.as_ref(),
+		&share_key_pair.public_key().to_hex_bytes(),
+		"File Key Public",
+	)?;
+
+	Ok(())
+}
+
+/// Generate a key on a yubikey and write its public key to `pub_path`.
+#[cfg(feature = "smartcard")]
+pub fn provision_yubikey<P: AsRef<Path>>(pub_path: P) -> Result<(), Error> {
+	let mut yubikey =
+		yubikey::YubiKey::open().map_err(Error::OpenSingleYubiKey)?;
+
+	let pin = rpassword::prompt_password(ENTER_PIN_PROMPT)
+		.map_err(Error::PinEntryError)?
+		.as_bytes()
+		.to_vec();
+
+	let _sign_public_key_bytes = crate::yubikey::generate_signed_certificate(
+		&mut yubikey,
+		crate::yubikey::SIGNING_SLOT,
+		&pin,
+		yubikey::MgmKey::default(),
+		yubikey::TouchPolicy::Always,
+	)
+	.map_err(Error::GenerateSign)?;
+
+	let _encrypt_public_key_bytes =
+		crate::yubikey::generate_signed_certificate(
+			&mut yubikey,
+			crate::yubikey::KEY_AGREEMENT_SLOT,
+			&pin,
+			yubikey::MgmKey::default(),
+			yubikey::TouchPolicy::Always,
+		)
+		.map_err(Error::GenerateEncrypt)?;
+
+	let public_key_bytes = crate::yubikey::pair_public_key(&mut yubikey)?;
+	let public_key_hex = qos_hex::encode(&public_key_bytes);
+
+	// Explicitly drop the yubikey to disconnect the PCSC session.
+	drop(yubikey);
+
+	write_with_msg(
+		pub_path.as_ref(),
+		public_key_hex.as_bytes(),
+		"YubiKey encrypt+sign public key",
+	)?;
+
+	Ok(())
+}
+
+/// Read a yubikey PIN from the first line of the file at `path`.
+pub fn pin_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, Error> {
+	let path = path.as_ref();
+	read_lines(path)?
+		.into_iter()
+		.next()
+		.map(String::into_bytes)
+		.ok_or_else(|| invalid_file(path, "first line missing from pin file"))
+}
+
+/// Import the key from the master seed at `master_seed_path` into a
+/// yubikey.
+#[cfg(feature = "smartcard")]
+pub fn advanced_provision_yubikey<P: AsRef<Path>>(
+	master_seed_path: P,
+	maybe_pin_path: Option<String>,
+) -> Result<(), Error> {
+	let mut yubikey =
+		yubikey::YubiKey::open().map_err(Error::OpenSingleYubiKey)?;
+
+	let pin = if let Some(pin_path) = maybe_pin_path {
+		pin_from_path(pin_path)?
+	} else {
+		rpassword::prompt_password(ENTER_PIN_PROMPT)
+			.map_err(Error::PinEntryError)?
+			.as_bytes()
+			.to_vec()
+	};
+
+	let pair = read_master_seed(master_seed_path)?;
+
+	let master_seed = pair.to_master_seed();
+	let encrypt_secret = Zeroizing::new(qos_p256::derive_secret(
+		master_seed,
+		qos_p256::P256_ENCRYPT_DERIVE_PATH,
+	)?);
+	let sign_secret = Zeroizing::new(qos_p256::derive_secret(
+		master_seed,
+		qos_p256::P256_SIGN_DERIVE_PATH,
+	)?);
+
+	crate::yubikey::import_key_and_generate_signed_certificate(
+		&mut yubikey,
+		sign_secret.as_slice(),
+		crate::yubikey::SIGNING_SLOT,
+		&pin,
+		yubikey::MgmKey::default(),
+		yubikey::TouchPolicy::Always,
+	)
+	.map_err(Error::GenerateSign)?;
+
+	crate::yubikey::import_key_and_generate_signed_certificate(
+		&mut yubikey,
+		encrypt_secret.as_slice(),
+		crate::yubikey::KEY_AGREEMENT_SLOT,
+		&pin,
+		yubikey::MgmKey::default(),
+		yubikey::TouchPolicy::Always,
+	)
+	.map_err(Error::GenerateEncrypt)?;
+
+	let public_key_bytes = crate::yubikey::pair_public_key(&mut yubikey)?;
+	let other = pair.public_key().to_bytes();
+
+	if public_key_bytes != other {
+		return Err(Error::WrongPublicKey);
+	}
+	// Explicitly drop the yubikey to disconnect the PCSC session.
+	drop(yubikey);
+
+	Ok(())
+}
+
+/// Arguments for [`boot_genesis`].
+pub struct BootGenesisArgs<'a, P: AsRef<Path>> {
+	/// URI of the host.
+	pub uri: &'a str,
+	/// Directory for the namespace of the ceremony.
+	pub namespace_dir: P,
+	/// Directory with the share set public keys and threshold.
+	pub share_set_dir: P,
+	/// Directory of the `QuorumOS` release with the PCRs.
+	pub qos_release_dir_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// Skip attestation doc verification. Only for testing.
+	pub unsafe_skip_attestation: bool,
+	/// Disaster recovery key to encrypt the quorum key to.
+	pub dr_key_path: Option<P>,
+}
+
+/// Boot an enclave in genesis mode and write the genesis output and
+/// attestation doc to the namespace directory.
+pub fn boot_genesis<P: AsRef<Path>>(
+	BootGenesisArgs {
+		uri,
+		namespace_dir,
+		share_set_dir,
+		qos_release_dir_path,
+		pcr3_preimage_path,
+		unsafe_skip_attestation,
+		dr_key_path,
+	}: BootGenesisArgs<P>,
+) -> Result<(), Error> {
+	let genesis_set = get_genesis_set(&share_set_dir)?;
+	let dr_key = if let Some(p) = dr_key_path {
+		let public =
+			P256Public::from_hex_file(p).map_err(Error::FailedToReadDrKey)?;
+		Some(public.to_bytes())
+	} else {
+		None
+	};
+
+	let req =
+		ProtocolMsg::BootGenesisRequest { set: genesis_set.clone(), dr_key };
+	let (cose_sign1, genesis_output) = match request::post(uri, &req)? {
+		ProtocolMsg::BootGenesisResponse {
+			nsm_response: NsmResponse::Attestation { document },
+			genesis_output,
+		} => (document, genesis_output),
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+	let quorum_key = P256Public::from_bytes(&genesis_output.quorum_key)?;
+	let attestation_doc =
+		extract_attestation_doc(&cose_sign1, unsafe_skip_attestation, None)?;
+
+	let qos_pcrs = extract_qos_pcrs(qos_release_dir_path)?;
+
+	// Sanity check the genesis output
+	if genesis_set.members.len() != genesis_output.member_outputs.len()
+		|| !genesis_output.member_outputs.iter().all(|member_out| {
+			genesis_set.members.contains(&member_out.share_set_member)
+		}) {
+		return Err(Error::GenesisOutputMismatch);
+	}
+
+	// Check the attestation document
+	if unsafe_skip_attestation {
+		log::warn(
+			"client",
+			"**WARNING:** Skipping attestation document verification.",
+		);
+	} else {
+		let user_data = &genesis_output.qos_hash();
+		verify_attestation_doc_against_user_input(
+			&attestation_doc,
+			user_data,
+			&qos_pcrs.pcr0,
+			&qos_pcrs.pcr1,
+			&qos_pcrs.pcr2,
+			&extract_pcr3(pcr3_preimage_path)?,
+		)?;
+	}
+
+	let dr_artifacts = [
+		("quorum_key_hash", &genesis_output.quorum_key_hash[..]),
+		("test_message_ciphertext", &genesis_output.test_message_ciphertext),
+		("test_message_signature", &genesis_output.test_message_signature),
+		("test_message", &genesis_output.test_message),
+	]
+	.map(|(k, v)| format!("{k} {}", qos_hex::encode(v)))
+	.join("\n");
+
+	// Write the dr artifacts.
+	let dr_artifacts_path = namespace_dir.as_ref().join(GENESIS_DR_ARTIFACTS);
+	write_with_msg(
+		&dr_artifacts_path,
+		dr_artifacts.as_bytes(),
+		"Genesis DR Artifacts",
+	)?;
+
+	// Write the attestation doc
+	let attestation_doc_path =
+		namespace_dir.as_ref().join(GENESIS_ATTESTATION_DOC_FILE);
+	write_with_msg(
+		&attestation_doc_path,
+		&cose_sign1,
+		"COSE Sign1 Attestation Doc",
+	)?;
+
+	// Write the genesis output
+	let genesis_output_path = namespace_dir.as_ref().join(GENESIS_OUTPUT_FILE);
+	write_with_msg(
+		&genesis_output_path,
+		&borsh::to_vec(&*genesis_output)?,
+		"`GenesisOutput`",
+	)?;
+
+	// Write the quorum public key
+	let quorum_key_path = namespace_dir.as_ref().join("quorum_key.pub");
+	write_with_msg(
+		&quorum_key_path,
+		&quorum_key.to_hex_bytes(),
+		"quorum_key.pub",
+	)?;
+
+	if let Some(dr_wrapped_quorum_key) =
+		genesis_output.dr_key_wrapped_quorum_key
+	{
+		let dr_wrapped_quorum_key_path =
+			namespace_dir.as_ref().join(DR_WRAPPED_QUORUM_KEY);
+		write_with_msg(
+			&dr_wrapped_quorum_key_path,
+			&dr_wrapped_quorum_key,
+			"DR Wrapped Quorum Key",
+		)?;
+	}
+
+	Ok(())
+}
+
+/// Verify that the genesis output in `namespace_dir` has an output for the
+/// key in `master_seed_path` and print the output.
+pub fn verify_genesis<P: AsRef<Path>>(
+	namespace_dir: P,
+	master_seed_path: P,
+) -> Result<(), Error> {
+	let genesis_output =
+		read_genesis_output(namespace_dir.as_ref().join(GENESIS_OUTPUT_FILE))?;
+
+	let master_seed_hex = Zeroizing::new(
+		String::from_utf8(read_file(&master_seed_path)?)
+			.map_err(|_| invalid_file(master_seed_path.as_ref(), "not utf8"))?,
+	);
+	let pair = P256Pair::from_hex_file(master_seed_path)?;
+
+	// sanity check our logic to read in master seed
+	if *pair.to_master_seed_hex() != master_seed_hex.as_bytes() {
+		return Err(Error::ErrorReadingSeed);
+	}
+
+	// check quorum_key_hash
+	if !ct_eq(
+		&sha_512(master_seed_hex.as_bytes()),
+		&genesis_output.quorum_key_hash,
+	) {
+		return Err(Error::SecretDoesNotMatch);
+	}
+	log::debug("client", "Quorum key hash is correct");
+
+	// check test_message_signature
+	if let Err(_e) = pair.public_key().verify(
+		&genesis_output.test_message,
+		&genesis_output.test_message_signature,
+	) {
+		return Err(Error::InvalidSignature);
+	}
+	log::debug(
+		"client",
+		"Quorum key signature over test message successfully verifies",
+	);
+	let expected_signature = pair.sign(&genesis_output.test_message)?;
+	if expected_signature != genesis_output.test_message_signature {
+		return Err(Error::CouldNotReproduceSignature);
+	}
+	log::debug(
+		"client",
+		"Quorum key signature over test message was deterministically \
+		reproduced",
+	);
+
+	// check test_message_ciphertext
+	let plaintext = pair.decrypt(&genesis_output.test_message_ciphertext)?;
+	if plaintext != genesis_output.test_message {
+		return Err(Error::BadDecryption);
+	}
+	log::debug("client", "Successfully decrypted test message ciphertext");
+
+	log::info("client", "verify-genesis successful");
+
+	Ok(())
+}
+
+/// Arguments for [`after_genesis`].
+pub struct AfterGenesisArgs<P: AsRef<Path>> {
+	/// Key of this member.
+	pub pair: PairOrYubi,
+	/// Path of the encrypted share.
+	pub share_path: P,
+	/// Alias of this member.
+	pub alias: String,
+	/// Directory for the namespace of the ceremony.
+	pub namespace_dir: P,
+	/// Directory of the `QuorumOS` release with the PCRs.
+	pub qos_release_dir_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// Skip attestation doc verification. Only for testing.
+	pub unsafe_skip_attestation: bool,
+	/// Unix time to validate the attestation doc certificates at.
+	pub validation_time_override: Option<u64>,
+}
+
+/// Verify the genesis attestation and output, then decrypt this member's
+/// share and check it against the share hash from the genesis output.
+pub fn after_genesis<P: AsRef<Path>>(
+	AfterGenesisArgs {
+		mut pair,
+		share_path,
+		alias,
+		namespace_dir,
+		qos_release_dir_path,
+		pcr3_preimage_path,
+		unsafe_skip_attestation,
+		validation_time_override,
+	}: AfterGenesisArgs<P>,
+) -> Result<(), Error> {
+	let attestation_doc_path =
+		namespace_dir.as_ref().join(GENESIS_ATTESTATION_DOC_FILE);
+	let genesis_set_path = namespace_dir.as_ref().join(GENESIS_OUTPUT_FILE);
+
+	// Get the PCRs for QOS so we can verify
+	let qos_pcrs = extract_qos_pcrs(&qos_release_dir_path)?;
+
+	// Read in the attestation doc from the genesis directory
+	let cose_sign1 = fs::read(attestation_doc_path)
+		.map_err(Error::FailedToReadAttestationDoc)?;
+	let attestation_doc = extract_attestation_doc(
+		&cose_sign1,
+		unsafe_skip_attestation,
+		validation_time_override,
+	)?;
+
+	// Read in the genesis output from the genesis directory
+	let genesis_output = read_genesis_output(genesis_set_path)?;
+
+	// Check the attestation document
+	if unsafe_skip_attestation {
+		log::warn(
+			"client",
+			"**WARNING:** Skipping attestation document verification.",
+		);
+	} else {
+		let user_data = &genesis_output.qos_hash();
+		verify_attestation_doc_against_user_input(
+			&attestation_doc,
+			user_data,
+			&qos_pcrs.pcr0,
+			&qos_pcrs.pcr1,
+			&qos_pcrs.pcr2,
+			&extract_pcr3(pcr3_preimage_path)?,
+		)?;
+	}
+
+	// Get the members specific output based on alias & setup key
+	let share_key_public = pair.public_key_bytes()?;
+	let member_output = genesis_output
+		.member_outputs
+		.iter()
+		.find(|m| {
+			m.share_set_member.pub_key == share_key_public
+				&& m.share_set_member.alias == alias
+		})
+		.ok_or(Error::MemberOutputNotFound)?;
+
+	// Make sure we can decrypt the Share with the Personal Key
+	let plaintext_share = Zeroizing::new(
+		open_share(
+			&Zeroizing::new(
+				pair.decrypt(&member_output.encrypted_quorum_key_share)?,
+			),
+			&genesis_output.quorum_key,
+			genesis_output.threshold,
+		)
+		.map_err(Error::InvalidShare)?,
+	);
+
+	if !ct_eq(&sha_512(&plaintext_share), &member_output.share_hash) {
+		return Err(Error::ShareHashMismatch);
+	}
+
+	drop(plaintext_share);
+
+	// Store the encrypted share
+	write_with_msg(
+		share_path.as_ref(),
+		&member_output.encrypted_quorum_key_share,
+		"Encrypted Quorum Share",
+	)?;
+
+	Ok(())
+}
+
+/// Arguments for [`generate_manifest`].
+pub struct GenerateManifestArgs<P: AsRef<Path>> {
+	/// Nonce of the manifest.
+	pub nonce: u32,
+	/// Name of the namespace.
+	pub namespace: String,
+	/// Restart policy of the pivot.
+	pub restart_policy: RestartPolicy,
+	/// Where to get the pivot hash from.
+	pub pivot: PivotHashSource<P>,
+	/// Directory of the `QuorumOS` release with the PCRs.
+	pub qos_release_dir_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// Directory with the share set public keys and threshold.
+	pub share_set_dir: P,
+	/// Directory with the manifest set public keys and threshold.
+	pub manifest_set_dir: P,
+	/// Directory with the patch set public keys and threshold.
+	pub patch_set_dir: P,
+	/// Path of the quorum public key.
+	pub quorum_key_path: P,
+	/// Path of the manifest.
+	pub manifest_path: P,
+	/// Arguments for the pivot.
+	pub pivot_args: Vec<String>,
+	/// File with the opaque config for the app, if any.
+	pub app_config_path: Option<P>,
+	/// Remote endpoints the pivot may connect to.
+	pub egress: Vec<EgressEndpoint>,
+	/// Nonce ledger to check the nonce against and record it in.
+	pub nonce_ledger_path: Option<P>,
+	/// Whether the enclave must only use approved algorithms.
+	pub approved_only: bool,
+}
+
+/// Where [`generate_manifest`] gets the pivot hash from.
+pub enum PivotHashSource<P: AsRef<Path>> {
+	/// File with the hex encoded pivot hash.
+	HashFile(P),
+	/// The pivot binary, which is hashed.
+	Binary(P),
+}
+
+impl<P: AsRef<Path>> PivotHashSource<P> {
+	fn pivot_hash(&self) -> Result<[u8; 32], Error> {
+		match self {
+			Self::HashFile(path) => extract_pivot_hash(path),
+			Self::Binary(path) => {
+				Ok(sha_256(&fs::read(path).map_err(Error::FailedToReadPivot)?))
+			}
+		}
+	}
+}
+
+/// Generate a manifest from the release, pivot and set directories and
+/// write it to the manifest path.
+pub fn generate_manifest<P: AsRef<Path>>(
+	args: GenerateManifestArgs<P>,
+) -> Result<(), Error> {
+	let GenerateManifestArgs {
+		nonce,
+		namespace,
+		pivot,
+		restart_policy,
+		qos_release_dir_path,
+		pcr3_preimage_path,
+		manifest_set_dir,
+		share_set_dir,
+		patch_set_dir,
+		quorum_key_path,
+		manifest_path,
+		pivot_args,
+		app_config_path,
+		egress,
+		nonce_ledger_path,
+		approved_only,
+	} = args;
+
+	let nitro_config =
+		extract_nitro_config(qos_release_dir_path, pcr3_preimage_path)?;
+	let pivot_hash = pivot.pivot_hash()?;
+	let app_config = app_config_path.map(read_file).transpose()?;
+
+	// Get manifest set keys & threshold
+	let manifest_set = get_manifest_set(manifest_set_dir)?;
+	// Get share set keys & threshold
+	let share_set = get_share_set(share_set_dir)?;
+	let patch_set = get_patch_set(patch_set_dir)?;
+	// Get quorum key from namespaces dir
+	let quorum_key = P256Public::from_hex_file(&quorum_key_path)
+		.map_err(Error::FailedToReadQuorumPublicKey)?;
+
+	let manifest = Manifest {
+		namespace: Namespace {
+			name: namespace,
+			nonce,
+			quorum_key: quorum_key.to_bytes(),
+		},
+		pivot: PivotConfig {
+			hash: pivot_hash,
+			restart: restart_policy,
+			args: pivot_args,
+			app_config: app_config.unwrap_or_default(),
+			egress,
+		},
+		manifest_set,
+		share_set,
+		patch_set,
+		enclave: nitro_config,
+		crypto: CryptoConfig::new(approved_only),
+	};
+
+	write_new_manifest(&manifest, manifest_path.as_ref(), nonce_ledger_path)
+}
+
+/// The highest manifest nonce used for each namespace, kept in a local JSON
+/// file so a nonce is not used twice, even by different operators sharing the
+/// file.
+struct NonceLedger {
+	path: PathBuf,
+	nonces: BTreeMap<String, u32>,
+}
+
+impl NonceLedger {
+	/// Read the ledger at `path`. A ledger that does not exist yet is empty.
+	fn open(path: &Path) -> Result<Self, Error> {
+		let nonces = if path.exists() {
+			serde_json::from_slice(&read_file(path)?).map_err(|e| {
+				invalid_file(path, format!("not a nonce ledger: {e}"))
+			})?
+		} else {
+			BTreeMap::new()
+		};
+
+		Ok(Self { path: path.to_path_buf(), nonces })
+	}
+
+	/// The lowest nonce not yet used for `namespace`.
+	fn next_nonce(&self, namespace: &str) -> u32 {
+		self.nonces
+			.get(namespace)
+			.map_or(0, |highest| highest.saturating_add(1))
+	}
+
+	/// Refuse a nonce that is not greater than every nonce used for the
+	/// namespace.
+	fn check(&self, namespace: &Namespace) -> Result<(), Error> {
+		match self.nonces.get(&namespace.name) {
+			Some(&highest) if namespace.nonce <= highest => {
+				Err(Error::NonceNotIncreasing {
+					namespace: namespace.name.clone(),
+					nonce: namespace.nonce,
+					highest,
+				})
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Record the nonce of `namespace` as the highest used.
+	fn record(mut self, namespace: &Namespace) -> Result<(), Error> {
+		self.nonces.insert(namespace.name.clone(), namespace.nonce);
+		fs::write(&self.path, serde_json::to_string_pretty(&self.nonces)?)
+			.map_err(|e| Error::FailedToWrite {
+				path: self.path.display().to_string(),
+				error: e.to_string(),
+			})
+	}
+}
+
+/// Write a newly generated manifest. With a nonce ledger, the manifest nonce
+/// must be greater than any used before for the namespace, and is recorded.
+fn write_new_manifest<P: AsRef<Path>>(
+	manifest: &Manifest,
+	manifest_path: &Path,
+	nonce_ledger_path: Option<P>,
+) -> Result<(), Error> {
+	let ledger = nonce_ledger_path
+		.map(|path| NonceLedger::open(path.as_ref()))
+		.transpose()?;
+	if let Some(ledger) = &ledger {
+		ledger.check(&manifest.namespace)?;
+	}
+
+	write_with_msg(manifest_path, &borsh::to_vec(manifest)?, "Manifest")?;
+
+	if let Some(ledger) = ledger {
+		ledger.record(&manifest.namespace)?;
+	}
+
+	Ok(())
+}
+
+/// Print the next manifest nonce to use for `namespace`, according to the
+/// nonce ledger at `nonce_ledger_path`.
+pub fn next_nonce<P: AsRef<Path>>(
+	nonce_ledger_path: P,
+	namespace: &str,
+) -> Result<(), Error> {
+	let ledger = NonceLedger::open(nonce_ledger_path.as_ref())?;
+	println!("{}", ledger.next_nonce(namespace));
+
+	Ok(())
+}
+
+fn extract_nitro_config<P: AsRef<Path>>(
+	qos_release_dir_path: P,
+	pcr3_preimage_path: P,
+) -> Result<NitroConfig, Error> {
+	let pcr3 = extract_pcr3(pcr3_preimage_path)?;
+	let QosPcrs { pcr0, pcr1, pcr2 } = extract_qos_pcrs(&qos_release_dir_path)?;
+
+	Ok(NitroConfig {
+		pcr0,
+		pcr1,
+		pcr2,
+		pcr3,
+		qos_commit: String::new(),
+		aws_root_certificate: aws_root_cert()?,
+	})
+}
+
+/// A declarative description of a manifest, read from a TOML spec file by
+/// [`generate_manifest_from_spec`].
+///
+/// ```toml
+/// namespace = "quit-coding-to-vape"
+/// nonce = 2
+/// restart-policy = "never"
+/// # Or `pivot-path = "<path of the pivot binary>"` to hash the binary.
+/// pivot-hash = "<hex sha256 of the pivot binary>"
+/// pivot-args = ["--msg", "hello"]
+/// # Optional; file with the opaque config for the app.
+/// app-config = "app-config.json"
+/// # Optional; endpoints the pivot may connect to through the egress proxy,
+/// # optionally pinning hashes of keys their TLS certificates chain through.
+/// egress = [
+///   "api.example.com:443",
+///   { endpoint = "*.example.org:443", tls-pins = ["<hex sha256 of SPKI>"] },
+/// ]
+/// quorum-key = "<hex quorum public key>"
+/// pcr0 = "<hex>"
+/// pcr1 = "<hex>"
+/// pcr2 = "<hex>"
+/// pcr3-preimage = "arn:aws:iam::123456789012:role/Webserver"
+/// manifest-set-dir = "manifest-set"
+/// share-set-dir = "share-set"
+/// patch-set-dir = "patch-set"
+/// # Optional; checked against the quorum key and share set.
+/// genesis-output = "genesis_output"
+/// # Optional; whether the enclave must only use approved algorithms.
+/// approved-only = false
+/// ```
+///
+/// Relative paths are relative to the directory of the spec file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSpec {
+	/// Name of the namespace.
+	pub namespace: String,
+	/// Nonce of the manifest.
+	pub nonce: u32,
+	/// Restart policy of the pivot.
+	pub restart_policy: RestartPolicy,
+	/// Hash of the pivot binary.
+	pub pivot_hash: [u8; 32],
+	/// Arguments for the pivot.
+	pub pivot_args: Vec<String>,
+	/// Opaque config for the app.
+	pub app_config: Vec<u8>,
+	/// Remote endpoints the pivot may connect to.
+	pub egress: Vec<EgressEndpoint>,
+	/// Quorum public key, as bytes.
+	pub quorum_key: Vec<u8>,
+	/// PCR0 of the `QuorumOS` release.
+	pub pcr0: Vec<u8>,
+	/// PCR1 of the `QuorumOS` release.
+	pub pcr1: Vec<u8>,
+	/// PCR2 of the `QuorumOS` release.
+	pub pcr2: Vec<u8>,
+	/// IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage: String,
+	/// Directory with the manifest set public keys and threshold.
+	pub manifest_set_dir: PathBuf,
+	/// Directory with the share set public keys and threshold.
+	pub share_set_dir: PathBuf,
+	/// Directory with the patch set public keys and threshold.
+	pub patch_set_dir: PathBuf,
+	/// Genesis output to check the quorum key and share set against.
+	pub genesis_output_path: Option<PathBuf>,
+	/// Whether the enclave must only use approved algorithms.
+	pub approved_only: bool,
+}
+
+const SPEC_KEYS: &[&str] = &[
+	"namespace",
+	"nonce",
+	"restart-policy",
+	"pivot-hash",
+	"pivot-path",
+	"pivot-args",
+	"app-config",
+	"egress",
+	"quorum-key",
+	"pcr0",
+	"pcr1",
+	"pcr2",
+	"pcr3-preimage",
+	"manifest-set-dir",
+	"share-set-dir",
+	"patch-set-dir",
+	"genesis-output",
+	"approved-only",
+];
+
+impl ManifestSpec {
+	/// Read a spec from the TOML file at `path`.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let contents = String::from_utf8(read_file(path)?)
+			.map_err(|_| invalid_file(path, "not UTF-8"))?;
+		let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+		Self::parse(&contents, base_dir).map_err(|e| invalid_file(path, e))
+	}
+
+	fn parse(contents: &str, base_dir: &Path) -> Result<Self, String> {
+		let spec = SpecDocument::parse(contents, base_dir)?;
+
+		let quorum_key = spec.hex_value("quorum-key")?;
+		P256Public::from_bytes(&quorum_key)
+			.map_err(|_| "`quorum-key` is not a quorum public key")?;
+
+		Ok(Self {
+			namespace: spec.str_value("namespace")?,
+			nonce: spec.nonce()?,
+			restart_policy: spec.restart_policy()?,
+			pivot_hash: spec.pivot_hash()?,
+			pivot_args: spec.pivot_args()?,
+			app_config: spec.app_config()?,
+			egress: spec.egress()?,
+			quorum_key,
+			pcr0: spec.pcr_value("pcr0")?,
+			pcr1: spec.pcr_value("pcr1")?,
+			pcr2: spec.pcr_value("pcr2")?,
+			pcr3_preimage: spec.str_value("pcr3-preimage")?,
+			manifest_set_dir: spec.path_value("manifest-set-dir")?,
+			share_set_dir: spec.path_value("share-set-dir")?,
+			patch_set_dir: spec.path_value("patch-set-dir")?,
+			genesis_output_path: spec
+				.contains_key("genesis-output")
+				.then(|| spec.path_value("genesis-output"))
+				.transpose()?,
+			approved_only: spec.approved_only()?,
+		})
+	}
+}
+
+/// The TOML of a [`ManifestSpec`], read value by value. Errors name the key
+/// that is missing or invalid.
+struct SpecDocument<'a> {
+	doc: toml_edit::Document,
+	base_dir: &'a Path,
+}
+
+impl<'a> SpecDocument<'a> {
+	fn parse(contents: &str, base_dir: &'a Path) -> Result<Self, String> {
+		let doc: toml_edit::Document =
+			contents.parse().map_err(|e| format!("not valid TOML: {e}"))?;
+		if let Some((key, _)) =
+			doc.iter().find(|(key, _)| !SPEC_KEYS.contains(key))
+		{
+			return Err(format!("unknown key `{key}`"));
+		}
+
+		Ok(Self { doc, base_dir })
+	}
+
+	fn contains_key(&self, key: &str) -> bool {
+		self.doc.contains_key(key)
+	}
+
+	fn str_value(&self, key: &str) -> Result<String, String> {
+		self.doc
+			.get(key)
+			.ok_or_else(|| format!("missing `{key}`"))?
+			.as_str()
+			.map(String::from)
+			.ok_or_else(|| format!("`{key}` must be a string"))
+	}
+
+	fn hex_value(&self, key: &str) -> Result<Vec<u8>, String> {
+		qos_hex::decode(&self.str_value(key)?)
+			.map_err(|_| format!("`{key}` must be hex"))
+	}
+
+	fn pcr_value(&self, key: &str) -> Result<Vec<u8>, String> {
+		hex::decode_pcr48(&self.str_value(key)?)
+			.map(Vec::from)
+			.map_err(|e| format!("`{key}` is not a PCR: {e}"))
+	}
+
+	/// A path relative to the directory of the spec file.
+	fn path_value(&self, key: &str) -> Result<PathBuf, String> {
+		Ok(self.base_dir.join(self.str_value(key)?))
+	}
+
+	fn read_path_value(&self, key: &str) -> Result<Vec<u8>, String> {
+		let path = self.path_value(key)?;
+		fs::read(&path)
+			.map_err(|e| format!("failed to read {}: {e}", path.display()))
+	}
+
+	fn nonce(&self) -> Result<u32, String> {
+		Ok(self
+			.doc
+			.get("nonce")
+			.ok_or("missing `nonce`")?
+			.as_integer()
+			.and_then(|nonce| u32::try_from(nonce).ok())
+			.ok_or("`nonce` must be a non negative integer")?)
+	}
+
+	fn restart_policy(&self) -> Result<RestartPolicy, String> {
+		Ok(self
+			.str_value("restart-policy")?
+			.try_into()
+			.map_err(|_| "`restart-policy` must be `never` or `always`")?)
+	}
+
+	/// The `pivot-hash`, or the hash of the binary at `pivot-path`.
+	fn pivot_hash(&self) -> Result<[u8; 32], String> {
+		match (self.doc.get("pivot-hash"), self.doc.get("pivot-path")) {
+			(Some(_), None) => hex::decode_hash256(
+				&self.str_value("pivot-hash")?,
+			)
+			.map_err(|e| format!("`pivot-hash` is not a 256 bit hash: {e}")),
+			(None, Some(_)) => {
+				Ok(sha_256(&self.read_path_value("pivot-path")?))
+			}
+			_ => Err("needs one of `pivot-hash` or `pivot-path`".into()),
+		}
+	}
+
+	fn pivot_args(&self) -> Result<Vec<String>, String> {
+		let Some(item) = self.doc.get("pivot-args") else {
+			return Ok(vec![]);
+		};
+		let not_strings = "`pivot-args` must be an array of strings";
+
+		Ok(item
+			.as_array()
+			.ok_or(not_strings)?
+			.iter()
+			.map(|arg| arg.as_str().map(String::from).ok_or(not_strings))
+			.collect::<Result<_, _>>()?)
+	}
+
+	fn app_config(&self) -> Result<Vec<u8>, String> {
+		if self.contains_key("app-config") {
+			self.read_path_value("app-config")
+		} else {
+			Ok(vec![])
+		}
+	}
+
+	fn egress(&self) -> Result<Vec<EgressEndpoint>, String> {
+		let Some(item) = self.doc.get("egress") else {
+			return Ok(vec![]);
+		};
+
+		item.as_array()
+			.ok_or("`egress` must be an array")?
+			.iter()
+			.map(parse_egress_endpoint)
+			.collect()
+	}
+
+	fn approved_only(&self) -> Result<bool, String> {
+		match self.doc.get("approved-only") {
+			None => Ok(false),
+			Some(item) => {
+				Ok(item.as_bool().ok_or("`approved-only` must be a boolean")?)
+			}
+		}
+	}
+}
+
+/// Generate a manifest from the spec file at `spec_path` and write it to
+/// `manifest_path`. If the spec names a genesis output, the quorum key and
+/// share set of the manifest must match it.
+pub fn generate_manifest_from_spec<P: AsRef<Path>>(
+	spec_path: P,
+	manifest_path: P,
+	nonce_ledger_path: Option<P>,
+) -> Result<(), Error> {
+	let spec = ManifestSpec::from_file(spec_path)?;
+
+	let mut share_set = get_share_set(&spec.share_set_dir)?;
+	if let Some(genesis_output_path) = &spec.genesis_output_path {
+		let genesis_output = read_genesis_output(genesis_output_path)?;
+		check_spec_against_genesis(&spec, &share_set, &genesis_output)?;
+		share_set.share_commitments = genesis_output.share_commitments;
+	}
+
+	let manifest = Manifest {
+		namespace: Namespace {
+			name: spec.namespace,
+			nonce: spec.nonce,
+			quorum_key: spec.quorum_key,
+		},
+		pivot: PivotConfig {
+			hash: spec.pivot_hash,
+			restart: spec.restart_policy,
+			args: spec.pivot_args,
+			app_config: spec.app_config,
+			egress: spec.egress,
+		},
+		manifest_set: get_manifest_set(&spec.manifest_set_dir)?,
+		share_set,
+		patch_set: get_patch_set(&spec.patch_set_dir)?,
+		enclave: NitroConfig {
+			pcr0: spec.pcr0,
+			pcr1: spec.pcr1,
+			pcr2: spec.pcr2,
+			pcr3: pcr3_from_role_arn(&spec.pcr3_preimage),
+			qos_commit: String::new(),
+			aws_root_certificate: aws_root_cert()?,
+		},
+		crypto: CryptoConfig::new(spec.approved_only),
+	};
+
+	write_new_manifest(&manifest, manifest_path.as_ref(), nonce_ledger_path)
+}
+
+/// Parse an entry of the `egress` spec key: either a `host:port` string or
+/// an inline table with an `endpoint` and its `tls-pins`.
+fn parse_egress_endpoint(
+	value: &toml_edit::Value,
+) -> Result<EgressEndpoint, String> {
+	let invalid_endpoint = || {
+		"`egress` entries must be `host:port` or \
+		{ endpoint = \"host:port\", tls-pins = [...] }"
+			.to_string()
+	};
+	let parse = |endpoint: Option<&str>| {
+		endpoint
+			.and_then(|e| EgressEndpoint::try_from(e.to_string()).ok())
+			.ok_or_else(invalid_endpoint)
+	};
+
+	let Some(table) = value.as_inline_table() else {
+		return parse(value.as_str());
+	};
+	if let Some((key, _)) =
+		table.iter().find(|(key, _)| !["endpoint", "tls-pins"].contains(key))
+	{
+		return Err(format!("unknown key `{key}` in `egress`"));
+	}
+	let mut endpoint = parse(table.get("endpoint").and_then(|e| e.as_str()))?;
+	if let Some(pins) = table.get("tls-pins") {
+		let invalid_pins =
+			"`tls-pins` must be an array of hex sha256 hashes".to_string();
+		endpoint.tls_pins = pins
+			.as_array()
+			.ok_or_else(|| invalid_pins.clone())?
+			.iter()
+			.map(|pin| {
+				pin.as_str()
+					.and_then(|pin| qos_hex::decode(pin).ok())
+					.and_then(|pin| pin.try_into().ok())
+					.ok_or_else(|| invalid_pins.clone())
+			})
+			.collect::<Result<_, _>>()?;
+	}
+
+	Ok(endpoint)
+}
+
+fn check_spec_against_genesis(
+	spec: &ManifestSpec,
+	share_set: &ShareSet,
+	genesis_output: &GenesisOutput,
+) -> Result<(), Error> {
+	if spec.quorum_key != genesis_output.quorum_key {
+		return Err(Error::SpecDoesNotMatchGenesisOutput("quorum key"));
+	}
+	if share_set.threshold != genesis_output.threshold {
+		return Err(Error::SpecDoesNotMatchGenesisOutput(
+			"share set threshold",
+		));
+	}
+
+	let mut genesis_members: Vec<_> = genesis_output
+		.member_outputs
+		.iter()
+		.map(|output| output.share_set_member.clone())
+		.collect();
+	genesis_members.sort();
+	if share_set.members != genesis_members {
+		return Err(Error::SpecDoesNotMatchGenesisOutput("share set members"));
+	}
+
+	Ok(())
+}
+
+/// Arguments for [`approve_manifest`].
+pub struct ApproveManifestArgs<P: AsRef<Path>> {
+	/// Key of this member.
+	pub pair: PairOrYubi,
+	/// Path of the manifest.
+	pub manifest_path: P,
+	/// Directory to write the approval to.
+	pub manifest_approvals_dir: P,
+	/// Directory of the `QuorumOS` release with the PCRs.
+	pub qos_release_dir_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// File with the hex encoded pivot hash.
+	pub pivot_hash_path: P,
+	/// Path of the quorum public key.
+	pub quorum_key_path: P,
+	/// Directory with the manifest set public keys and threshold.
+	pub manifest_set_dir: P,
+	/// Directory with the share set public keys and threshold.
+	pub share_set_dir: P,
+	/// Directory with the patch set public keys and threshold.
+	pub patch_set_dir: P,
+	/// Alias of this member.
+	pub alias: String,
+	/// Skip the interactive confirmations. Only for testing.
+	pub unsafe_auto_confirm: bool,
+}
+
+/// Check a manifest against the local release, pivot and sets, ask for
+/// confirmation, and write an approval signed by this member.
+pub fn approve_manifest<P: AsRef<Path>>(
+	args: ApproveManifestArgs<P>,
+) -> Result<(), Error> {
+	let ApproveManifestArgs {
+		mut pair,
+		manifest_path,
+		manifest_approvals_dir,
+		qos_release_dir_path,
+		pcr3_preimage_path,
+		pivot_hash_path,
+		quorum_key_path,
+		manifest_set_dir,
+		share_set_dir,
+		patch_set_dir,
+		alias,
+		unsafe_auto_confirm,
+	} = args;
+
+	let manifest = read_manifest(&manifest_path)?;
+	let previous = find_previous_manifest(
+		[
+			manifest_path.as_ref().parent(),
+			Some(manifest_approvals_dir.as_ref()),
+		],
+		&manifest,
+	);
+	let quorum_key = P256Public::from_hex_file(&quorum_key_path)
+		.map_err(Error::FailedToReadQuorumPublicKey)?;
+
+	if !approve_manifest_programmatic_verifications(
+		&manifest,
+		&get_manifest_set(manifest_set_dir)?,
+		&get_share_set(share_set_dir)?,
+		&get_patch_set(patch_set_dir)?,
+		&extract_nitro_config(qos_release_dir_path, pcr3_preimage_path)?,
+		&extract_pivot_hash(pivot_hash_path)?,
+		&quorum_key,
+	) {
+		return Err(Error::ManifestMismatch);
+	}
+
+	if !unsafe_auto_confirm {
+		let stdin = io::stdin();
+		let stdin_locked = stdin.lock();
+		let mut prompter =
+			Prompter { reader: stdin_locked, writer: io::stdout() };
+		if review_manifest(&manifest, previous.as_ref(), &mut prompter).is_err()
+			|| !approve_manifest_human_verifications(&manifest, &mut prompter)
+			|| !approve_manifest_typed_confirmation(&manifest, &mut prompter)
+		{
+			return Err(Error::NotConfirmed);
+		}
+		drop(prompter);
+	}
+
+	let approval = Approval {
+		signature: pair.sign(&manifest.qos_hash())?,
+		member: QuorumMember {
+			pub_key: pair.public_key_bytes()?,
+			alias: alias.clone(),
+		},
+	};
+
+	let approval_path = manifest_approvals_dir.as_ref().join(format!(
+		"{}-{}-{}.{}",
+		alias,
+		manifest.namespace.name.replace('/', "-"),
+		manifest.namespace.nonce,
+		APPROVAL_EXT
+	));
+	write_with_msg(
+		&approval_path,
+		&borsh::to_vec(&approval)?,
+		"Manifest Approval",
+	)?;
+
+	drop(pair);
+
+	Ok(())
+}
+
+fn approve_manifest_programmatic_verifications(
+	manifest: &Manifest,
+	manifest_set: &ManifestSet,
+	share_set: &ShareSet,
+	patch_set: &PatchSet,
+	nitro_config: &NitroConfig,
+	pivot_hash: &[u8],
+	quorum_key: &P256Public,
+) -> bool {
+	// Verify manifest set composition
+	if manifest.manifest_set != *manifest_set {
+		log::error("client", "Manifest Set composition does not match");
+		return false;
+	}
+
+	// Verify share set composition. The share commitments come from the
+	// genesis output rather than the share set directory.
+	if manifest.share_set.members != share_set.members
+		|| manifest.share_set.threshold != share_set.threshold
+	{
+		log::error("client", "Share Set composition does not match");
+		return false;
+	}
+
+	// Verify share set composition
+	if manifest.patch_set != *patch_set {
+		log::error("client", "Share Set composition does not match");
+		return false;
+	}
+
+	// Verify pcrs 0, 1, 2, 3.
+	if manifest.enclave != *nitro_config {
+		log::error("client", "Nitro configuration does not match");
+		return false;
+	}
+
+	// Verify the pivot could be built deterministically
+	if !ct_eq(&manifest.pivot.hash, pivot_hash) {
+		log::error("client", "Pivot hash does not match");
+		return false;
+	}
+
+	// Verify the intended Quorum Key is being used
+	if manifest.namespace.quorum_key != quorum_key.to_bytes() {
+		log::error("client", "Quorum public key does not match");
+		return false;
+	}
+
+	true
+}
+
+fn approve_manifest_human_verifications<R, W>(
+	manifest: &Manifest,
+	prompter: &mut Prompter<R, W>,
+) -> bool
+where
+	R: BufRead,
+	W: Write,
+{
+	// Check the namespace name
+	{
+		let prompt = format!(
+			"Is this the correct namespace name: {}? (yes/no)",
+			manifest.namespace.name
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check the namespace nonce
+	{
+		let prompt = format!(
+			"Is this the correct namespace nonce: {}? (yes/no)",
+			manifest.namespace.nonce
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check pivot restart policy
+	{
+		let prompt = format!(
+			"Is this the correct pivot restart policy: {:?}? (yes/no)",
+			manifest.pivot.restart
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check pivot arguments
+	{
+		let prompt = format!(
+			"Are these the correct pivot args:\n{:?}?\n(yes/no)",
+			manifest.pivot.args
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check where the pivot may connect to
+	if !manifest.pivot.egress.is_empty() {
+		let endpoints: Vec<_> = manifest
+			.pivot
+			.egress
+			.iter()
+			.map(|endpoint| {
+				if endpoint.tls_pins.is_empty() {
+					endpoint.to_string()
+				} else {
+					let pins: Vec<_> = endpoint
+						.tls_pins
+						.iter()
+						.map(|pin| qos_hex::encode(pin))
+						.collect();
+					format!("{endpoint} (TLS pins: {})", pins.join(", "))
+				}
+			})
+			.collect();
+		let prompt = format!(
+			"Are these the correct endpoints for the pivot to connect to:\n{}\n(yes/no)",
+			endpoints.join("\n")
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check the app config, which is opaque, by its hash
+	if !manifest.pivot.app_config.is_empty() {
+		let prompt = format!(
+			"Is this the correct app config: {} bytes with sha256 {}? (yes/no)",
+			manifest.pivot.app_config.len(),
+			qos_hex::encode(&sha_256(&manifest.pivot.app_config))
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	true
+}
+
+/// Ask the member to type the namespace name and nonce of the manifest, so
+/// approving it takes more than answering yes.
+fn approve_manifest_typed_confirmation<R, W>(
+	manifest: &Manifest,
+	prompter: &mut Prompter<R, W>,
+) -> bool
+where
+	R: BufRead,
+	W: Write,
+{
+	let expected =
+		format!("{} {}", manifest.namespace.name, manifest.namespace.nonce);
+	prompter
+		.prompt(&format!("Type `{expected}` to approve this manifest:"))
+		.is_ok_and(|answer| answer == expected)
+}
+
+/// Show a summary of the manifest and, if there is one, what changed since
+/// the manifest for the previous nonce.
+fn review_manifest<R, W>(
+	manifest: &Manifest,
+	previous: Option<&Manifest>,
+	prompter: &mut Prompter<R, W>,
+) -> io::Result<()>
+where
+	R: BufRead,
+	W: Write,
+{
+	let writer = &mut prompter.writer;
+	writeln!(writer, "Manifest to approve:")?;
+	for line in manifest_review_lines(manifest) {
+		writeln!(writer, "\t{line}")?;
+	}
+
+	match previous {
+		Some(previous) => {
+			writeln!(
+				writer,
+				"Changes since the manifest with nonce {}:",
+				previous.namespace.nonce
+			)?;
+			for line in manifest_diff(previous, manifest) {
+				writeln!(writer, "\t{line}")?;
+			}
+		}
+		None => writeln!(
+			writer,
+			"No manifest for an earlier nonce of this namespace found to \
+			compare against."
+		)?,
+	}
+
+	Ok(())
+}
+
+/// The fields of a manifest as `label: value` lines, one per set member.
+fn manifest_review_lines(manifest: &Manifest) -> Vec<String> {
+	let Manifest {
+		namespace,
+		pivot,
+		manifest_set,
+		share_set,
+		enclave,
+		patch_set,
+		crypto,
+	} = manifest;
+
+	let mut lines = vec![
+		format!("qos hash: {}", qos_hex::encode(&manifest.qos_hash())),
+		format!("namespace name: {}", namespace.name),
+		format!("namespace nonce: {}", namespace.nonce),
+		format!("quorum key: {}", qos_hex::encode(&namespace.quorum_key)),
+		format!("pivot hash: {}", qos_hex::encode(&pivot.hash)),
+		format!("pivot restart policy: {:?}", pivot.restart),
+		format!("pivot args: {:?}", pivot.args),
+		format!("manifest set threshold: {}", manifest_set.threshold),
+	];
+	lines.extend(manifest_set.members.iter().map(|member| {
+		format!(
+			"manifest set member: {} {}",
+			member.alias,
+			qos_hex::encode(&member.pub_key)
+		)
+	}));
+	lines.push(format!("share set threshold: {}", share_set.threshold));
+	lines.extend(share_set.members.iter().map(|member| {
+		format!(
+			"share set member: {} {}",
+			member.alias,
+			qos_hex::encode(&member.pub_key)
+		)
+	}));
+	lines.extend(share_set.share_commitments.iter().map(|commitment| {
+		format!("share commitment: {}", qos_hex::encode(commitment))
+	}));
+	lines.push(format!("patch set threshold: {}", patch_set.threshold));
+	lines.extend(patch_set.members.iter().map(|member| {
+		format!("patch set member: {}", qos_hex::encode(&member.pub_key))
+	}));
+	lines.extend([
+		format!("pcr0: {}", qos_hex::encode(&enclave.pcr0)),
+		format!("pcr1: {}", qos_hex::encode(&enclave.pcr1)),
+		format!("pcr2: {}", qos_hex::encode(&enclave.pcr2)),
+		format!("pcr3: {}", qos_hex::encode(&enclave.pcr3)),
+		format!("qos commit: {}", enclave.qos_commit),
+		format!(
+			"aws root certificate sha256: {}",
+			qos_hex::encode(&sha_256(&enclave.aws_root_certificate))
+		),
+		format!("approved algorithms only: {}", crypto.approved_only),
+	]);
+	lines.extend(
+		crypto
+			.algorithms
+			.iter()
+			.map(|algorithm| format!("algorithm: {algorithm:?}")),
+	);
+
+	lines
+}
+
+/// The review lines of `previous` that are not in `manifest`, prefixed with
+/// `-`, followed by the review lines of `manifest` that are new, prefixed
+/// with `+`.
+fn manifest_diff(previous: &Manifest, manifest: &Manifest) -> Vec<String> {
+	let before = manifest_review_lines(previous);
+	let after = manifest_review_lines(manifest);
+
+	let removed = before
+		.iter()
+		.filter(|line| !after.contains(line))
+		.map(|line| format!("- {line}"));
+	let added = after
+		.iter()
+		.filter(|line| !before.contains(line))
+		.map(|line| format!("+ {line}"));
+
+	removed.chain(added).collect()
+}
+
+/// Find the manifest, or manifest envelope, in `dirs` for the same namespace
+/// as `manifest` with the highest nonce lower than its nonce.
+fn find_previous_manifest<'a>(
+	dirs: impl IntoIterator<Item = Option<&'a Path>>,
+	manifest: &Manifest,
+) -> Option<Manifest> {
+	let mut dirs: Vec<_> = dirs.into_iter().flatten().collect();
+	dirs.dedup();
+
+	dirs.into_iter()
+		.filter_map(|dir| find_file_paths(dir).ok())
+		.flatten()
+		.filter_map(|path| {
+			let bytes = fs::read(path).ok()?;
+			Manifest::try_from_slice(&bytes).ok().or_else(|| {
+				ManifestEnvelope::try_from_slice(&bytes)
+					.ok()
+					.map(|envelope| envelope.manifest)
+			})
+		})
+		.filter(|previous| {
+			previous.namespace.name == manifest.namespace.name
+				&& previous.namespace.nonce < manifest.namespace.nonce
+		})
+		.max_by_key(|previous| previous.namespace.nonce)
+}
+
+/// Bundle a manifest with its approvals into a manifest envelope, failing
+/// if the approvals do not meet the manifest set threshold.
+pub fn generate_manifest_envelope<P: AsRef<Path>>(
+	manifest_approvals_dir: P,
+	manifest_path: P,
+	maybe_manifest_envelope_path: Option<String>,
+) -> Result<(), Error> {
+	let manifest = read_manifest(&manifest_path)?;
+	let approvals = find_approvals(&manifest_approvals_dir, &manifest)?;
+
+	// Create manifest envelope
+	let manifest_envelope = ManifestEnvelope {
+		manifest,
+		manifest_set_approvals: approvals,
+		share_set_approvals: vec![],
+	};
+
+	if let Err(e) = manifest_envelope.check_approvals() {
+		log::error("client", format_args!("Error with approvals: {e:?}"));
+		return Err(Error::InvalidApprovals);
+	}
+
+	let path = maybe_manifest_envelope_path.map_or_else(
+		|| manifest_approvals_dir.as_ref().join(MANIFEST_ENVELOPE),
+		PathBuf::from,
+	);
+	write_with_msg(
+		&path,
+		&borsh::to_vec(&manifest_envelope)?,
+		"Manifest Envelope",
+	)?;
+
+	Ok(())
+}
+
+/// Report which members of the manifest set have a valid approval in
+/// `manifest_approvals_dir` and which are still outstanding.
+pub fn verify_approvals<P: AsRef<Path>>(
+	manifest_approvals_dir: P,
+	manifest_path: P,
+	manifest_hash: &Hash256,
+) -> Result<(), Error> {
+	let manifest = read_manifest(&manifest_path)?;
+	let actual = manifest.qos_hash();
+	if actual != *manifest_hash {
+		return Err(Error::ManifestHashDoesNotMatch {
+			expected: qos_hex::encode(manifest_hash),
+			actual: qos_hex::encode(&actual),
+		});
+	}
+	log::info(
+		"client",
+		format_args!("Manifest hash: {}", qos_hex::encode(&actual)),
+	);
+
+	let dir = manifest_approvals_dir.as_ref();
+	let entries = fs::read_dir(dir).map_err(|e| Error::FailedToRead {
+		path: dir.display().to_string(),
+		error: e.to_string(),
+	})?;
+	let mut approvals = vec![];
+	for entry in entries {
+		let path = entry
+			.map_err(|e| Error::FailedToRead {
+				path: dir.display().to_string(),
+				error: e.to_string(),
+			})?
+			.path();
+		if path.extension().map_or(true, |ext| ext != APPROVAL_EXT) {
+			continue;
+		}
+		let bytes = fs::read(&path).map_err(|e| Error::FailedToRead {
+			path: path.display().to_string(),
+			error: e.to_string(),
+		})?;
+		approvals.push((path.display().to_string(), bytes));
+	}
+	approvals.sort();
+
+	let report = ApprovalsReport::new(&manifest, &approvals);
+	for (path, reason) in &report.invalid {
+		log::warn("client", format_args!("Invalid approval {path}: {reason}"));
+	}
+	println!(
+		"Signed ({} of threshold {}): {}",
+		report.signed.len(),
+		manifest.manifest_set.threshold,
+		report.signed.join(", ")
+	);
+	println!("Outstanding: {}", report.outstanding.join(", "));
+
+	if !report.invalid.is_empty() || !report.threshold_met {
+		return Err(Error::InvalidApprovals);
+	}
+	println!("Threshold is met");
+
+	Ok(())
+}
+
+/// Verification status of a set of manifest approvals.
+#[derive(Debug, PartialEq, Eq)]
+struct ApprovalsReport {
+	/// Aliases of the manifest set members with a valid approval.
+	signed: Vec<String>,
+	/// Name of each approval that is not valid, with the reason.
+	invalid: Vec<(String, String)>,
+	/// Aliases of the manifest set members without a valid approval.
+	outstanding: Vec<String>,
+	/// Whether the valid approvals meet the manifest set threshold.
+	threshold_met: bool,
+}
+
+impl ApprovalsReport {
+	/// Verify each borsh encoded approval, given with a name to report it
+	/// by, against `manifest`.
+	fn new(manifest: &Manifest, approvals: &[(String, Vec<u8>)]) -> Self {
+		let manifest_hash = manifest.qos_hash();
+		let members = &manifest.manifest_set.members;
+		let mut signed: Vec<&QuorumMember> = vec![];
+		let mut invalid = vec![];
+
+		for (name, bytes) in approvals {
+			let Ok(approval) = Approval::try_from_slice(bytes) else {
+				invalid.push((name.clone(), "not an approval".to_string()));
+				continue;
+			};
+			let alias = &approval.member.alias;
+
+			let reason = if !members.contains(&approval.member) {
+				format!("{alias} is not a member of the manifest set")
+			} else if P256Public::from_bytes(&approval.member.pub_key)
+				.and_then(|public| {
+					public.verify(&manifest_hash, &approval.signature)
+				})
+				.is_err()
+			{
+				format!("signature by {alias} does not verify")
+			} else if signed.contains(&&approval.member) {
+				format!("duplicate approval by {alias}")
+			} else {
+				signed.extend(members.iter().find(|m| **m == approval.member));
+				continue;
+			};
+			invalid.push((name.clone(), reason));
+		}
+
+		let threshold_met =
+			signed.len() >= manifest.manifest_set.threshold as usize;
+		Self {
+			signed: signed.iter().map(|m| m.alias.clone()).collect(),
+			invalid,
+			outstanding: members
+				.iter()
+				.filter(|m| !signed.contains(m))
+				.map(|m| m.alias.clone())
+				.collect(),
+			threshold_met,
+		}
+	}
+}
+
+/// Print the phase of the enclave at `uri` and which share set members have
+/// posted shares.
+pub fn provision_status(uri: &str) -> Result<(), Error> {
+	let status = match request::post(uri, &ProtocolMsg::EnclaveStatusRequest)? {
+		ProtocolMsg::EnclaveStatusResponse(status) => status,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+	let reconstruction =
+		status.manifest.ok_or(Error::ManifestEnvelopeNotFound)?.reconstruction;
+	let manifest_envelope =
+		match request::post(uri, &ProtocolMsg::ManifestEnvelopeRequest)? {
+			ProtocolMsg::ManifestEnvelopeResponse { manifest_envelope } => {
+				manifest_envelope.ok_or(Error::ManifestEnvelopeNotFound)?
+			}
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+	let (posted, outstanding) = share_set_progress(&manifest_envelope);
+
+	println!("Enclave phase: {:?}", status.phase);
+	if status.phase == ProtocolPhase::QuorumKeyProvisioned {
+		println!("The quorum key has been reconstructed.");
+	} else {
+		println!(
+			"Shares posted: {} of threshold {}",
+			reconstruction.shares_received, reconstruction.threshold
+		);
+	}
+	println!("Posted by: {}", posted.join(", "));
+	println!("Outstanding: {}", outstanding.join(", "));
+
+	Ok(())
+}
+
+/// Aliases of the share set members that have posted a share, going by the
+/// share set approvals recorded in the manifest envelope, and of the ones
+/// that have not.
+fn share_set_progress(
+	manifest_envelope: &ManifestEnvelope,
+) -> (Vec<String>, Vec<String>) {
+	let (posted, outstanding): (Vec<_>, Vec<_>) =
+		manifest_envelope.manifest.share_set.members.iter().partition(
+			|member| {
+				manifest_envelope
+					.share_set_approvals
+					.iter()
+					.any(|approval| approval.member == **member)
+			},
+		);
+	let aliases = |members: Vec<&QuorumMember>| {
+		members.into_iter().map(|m| m.alias.clone()).collect()
+	};
+
+	(aliases(posted), aliases(outstanding))
+}
+
+/// Boot an enclave in key forwarding mode.
+pub fn boot_key_fwd<P: AsRef<Path>>(
+	uri: &str,
+	manifest_envelope_path: P,
+	pivot_path: P,
+	attestation_doc_path: P,
+) -> Result<(), Error> {
+	let pivot =
+		fs::read(pivot_path.as_ref()).map_err(Error::FailedToReadPivot)?;
+	let manifest_envelope = read_manifest_envelope(manifest_envelope_path)?;
+
+	let req = ProtocolMsg::BootKeyForwardRequest {
+		manifest_envelope: Box::new(manifest_envelope),
+		pivot,
+	};
+	let cose_sign1 = match request::post(uri, &req)? {
+		ProtocolMsg::BootKeyForwardResponse {
+			nsm_response: NsmResponse::Attestation { document },
+		} => document,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	write_with_msg(
+		attestation_doc_path.as_ref(),
+		&cose_sign1,
+		"COSE Sign1 Attestation Doc",
+	)?;
+
+	Ok(())
+}
+
+/// Request the quorum key from a key forwarding enclave and write the
+/// encrypted quorum key.
+pub fn export_key<P: AsRef<Path>>(
+	uri: &str,
+	manifest_envelope_path: P,
+	attestation_doc_path: P,
+	encrypted_quorum_key_path: P,
+) -> Result<(), Error> {
+	let manifest_envelope = read_manifest_envelope(manifest_envelope_path)?;
+	let cose_sign1_attestation_doc = fs::read(attestation_doc_path.as_ref())
+		.map_err(Error::FailedToReadAttestationDoc)?;
+
+	let req = ProtocolMsg::ExportKeyRequest {
+		manifest_envelope: Box::new(manifest_envelope),
+		cose_sign1_attestation_doc,
+	};
+
+	let encrypted_quorum_key = match request::post(uri, &req)? {
+		ProtocolMsg::ExportKeyResponse { encrypted_quorum_key, signature } => {
+			EncryptedQuorumKey { encrypted_quorum_key, signature }
+		}
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	write_with_msg(
+		encrypted_quorum_key_path.as_ref(),
+		&borsh::to_vec(&encrypted_quorum_key)?,
+		"Encrypted Quorum Key",
+	)?;
+
+	Ok(())
+}
+
+/// Inject an encrypted quorum key into an enclave booted in key forwarding
+/// mode.
+pub fn inject_key<P: AsRef<Path>>(
+	uri: &str,
+	encrypted_quorum_key_path: P,
+) -> Result<(), Error> {
+	let encrypted_quorum_key = {
+		let bytes = std::fs::read(encrypted_quorum_key_path)
+			.map_err(|_| Error::FailedToReadEncryptedQuorumKey)?;
+		EncryptedQuorumKey::try_from_slice(&bytes)
+			.map_err(|_| Error::InvalidEncryptedQuorumKey)?
+	};
+
+	let req = ProtocolMsg::InjectKeyRequest {
+		encrypted_quorum_key: encrypted_quorum_key.encrypted_quorum_key,
+		signature: encrypted_quorum_key.signature,
+	};
+
+	match request::post(uri, &req)? {
+		ProtocolMsg::InjectKeyResponse => {
+			log::info("client", "Successful key injection!");
+		}
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	Ok(())
+}
+
+/// Arguments for [`boot_standard`].
+pub struct BootStandardArgs<P: AsRef<Path>> {
+	/// URI of the host.
+	pub uri: String,
+	/// Path of the pivot binary.
+	pub pivot_path: P,
+	/// Path of the manifest envelope.
+	pub manifest_envelope_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// Skip attestation doc verification. Only for testing.
+	pub unsafe_skip_attestation: bool,
+}
+
+/// Boot an enclave in standard mode with the pivot and manifest envelope.
+pub fn boot_standard<P: AsRef<Path>>(
+	BootStandardArgs {
+		uri,
+		pivot_path,
+		manifest_envelope_path,
+		pcr3_preimage_path,
+		unsafe_skip_attestation,
+	}: BootStandardArgs<P>,
+) -> Result<(), Error> {
+	// Read in pivot binary
+	let pivot =
+		fs::read(pivot_path.as_ref()).map_err(Error::FailedToReadPivot)?;
+
+	// Create manifest envelope
+	let manifest_envelope = read_manifest_envelope(manifest_envelope_path)?;
+	let manifest = manifest_envelope.manifest.clone();
+
+	let req = ProtocolMsg::BootStandardRequest {
+		manifest_envelope: Box::new(manifest_envelope),
+		pivot,
+	};
+	// Broadcast boot standard instruction and extract the attestation doc from
+	// the response.
+	let cose_sign1 = match request::post(&uri, &req)? {
+		ProtocolMsg::BootStandardResponse {
+			nsm_response: NsmResponse::Attestation { document },
+		} => document,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	let attestation_doc =
+		extract_attestation_doc(&cose_sign1, unsafe_skip_attestation, None)?;
+
+	// Verify attestation document
+	if unsafe_skip_attestation {
+		log::warn(
+			"client",
+			"**WARNING:** Skipping attestation document verification.",
+		);
+	} else {
+		verify_attestation_doc_against_user_input(
+			&attestation_doc,
+			&manifest.qos_hash(),
+			&manifest.enclave.pcr0,
+			&manifest.enclave.pcr1,
+			&manifest.enclave.pcr2,
+			&extract_pcr3(pcr3_preimage_path)?,
+		)?;
+
+		// Sanity check the ephemeral key is valid
+		let eph_pub_bytes =
+			attestation_doc.public_key.ok_or(Error::InvalidEphemeralKey)?;
+		P256Public::from_bytes(&eph_pub_bytes)
+			.map_err(|_| Error::InvalidEphemeralKey)?;
+	}
+
+	Ok(())
+}
+
+/// Request an attestation doc and manifest envelope from the enclave and
+/// write them to the given paths.
+pub fn get_attestation_doc<P: AsRef<Path>>(
+	uri: &str,
+	attestation_doc_path: P,
+	manifest_envelope_path: P,
+) -> Result<(), Error> {
+	let (cose_sign1, manifest_envelope) =
+		match request::post(uri, &ProtocolMsg::LiveAttestationDocRequest)? {
+			ProtocolMsg::LiveAttestationDocResponse {
+				nsm_response: NsmResponse::Attestation { document },
+				manifest_envelope: Some(manifest_envelope),
+			} => (document, manifest_envelope),
+			ProtocolMsg::LiveAttestationDocResponse {
+				nsm_response: _,
+				manifest_envelope: None,
+			} => return Err(Error::ManifestEnvelopeNotFound),
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+
+	write_with_msg(
+		attestation_doc_path.as_ref(),
+		&cose_sign1,
+		"COSE Sign1 Attestation Doc",
+	)?;
+	write_with_msg(
+		manifest_envelope_path.as_ref(),
+		&borsh::to_vec(&manifest_envelope)?,
+		"Manifest envelope",
+	)?;
+
+	Ok(())
+}
+
+/// Fetch the live attestation doc of an enclave and check that the enclave is
+/// running the manifest at `manifest_path`, printing whether each check
+/// passed. The manifest file may also be a manifest envelope.
+pub fn verify_enclave<P: AsRef<Path>>(
+	uri: &str,
+	manifest_path: P,
+) -> Result<(), Error> {
+	let bytes =
+		fs::read(manifest_path).map_err(Error::FailedToReadManifestFile)?;
+	let manifest = ManifestEnvelope::try_from_slice(&bytes)
+		.map(|envelope| envelope.manifest)
+		.or_else(|_| Manifest::try_from_slice(&bytes))
+		.map_err(|_| Error::FileDidNotHaveValidManifest)?;
+
+	let (cose_sign1, enclave_manifest_envelope) =
+		match request::post(uri, &ProtocolMsg::LiveAttestationDocRequest)? {
+			ProtocolMsg::LiveAttestationDocResponse {
+				nsm_response: NsmResponse::Attestation { document },
+				manifest_envelope,
+			} => (document, manifest_envelope),
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+
+	let root_cert = aws_root_cert()?;
+	let validation_time = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+	let (attestation_doc, valid_cert_chain) = match attestation_doc_from_der(
+		&cose_sign1,
+		&root_cert,
+		validation_time,
+	) {
+		Ok(attestation_doc) => (attestation_doc, true),
+		Err(_) => (unsafe_attestation_doc_from_der(&cose_sign1)?, false),
+	};
+
+	let checks = enclave_checks(
+		&attestation_doc,
+		valid_cert_chain,
+		&root_cert,
+		&manifest,
+		enclave_manifest_envelope.as_ref().map(|envelope| &envelope.manifest),
+	);
+	for (check, passed) in &checks {
+		println!("{}: {check}", if *passed { "PASS" } else { "FAIL" });
+	}
+
+	let manifest_hash = qos_hex::encode(&manifest.qos_hash());
+	if checks.iter().all(|(_, passed)| *passed) {
+		println!("PASS: the enclave is running manifest {manifest_hash}");
+		Ok(())
+	} else {
+		println!("FAIL: the enclave is not running manifest {manifest_hash}");
+		Err(Error::EnclaveDoesNotMatchManifest)
+	}
+}
+
+/// Run the crypto self test of the enclave at `uri` and print whether each
+/// test passed. The report is checked against the signature of the Ephemeral
+/// Key from the live attestation doc.
+pub fn self_test(
+	uri: &str,
+	unsafe_skip_attestation: bool,
+) -> Result<(), Error> {
+	let cose_sign1 =
+		match request::post(uri, &ProtocolMsg::LiveAttestationDocRequest)? {
+			ProtocolMsg::LiveAttestationDocResponse {
+				nsm_response: NsmResponse::Attestation { document },
+				..
+			} => document,
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+	let attestation_doc =
+		extract_attestation_doc(&cose_sign1, unsafe_skip_attestation, None)?;
+
+	let (report, signature) =
+		match request::post(uri, &ProtocolMsg::SelfTestRequest)? {
+			ProtocolMsg::SelfTestResponse { report, signature } => {
+				(report, signature)
+			}
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+
+	// The mock attestation doc does not contain the real Ephemeral Key.
+	if unsafe_skip_attestation {
+		log::warn(
+			"client",
+			"**WARNING:** Skipping self test signature verification.",
+		);
+	} else {
+		let eph_pub = P256Public::from_bytes(
+			&attestation_doc.public_key.ok_or(Error::InvalidEphemeralKey)?,
+		)
+		.map_err(|_| Error::InvalidEphemeralKey)?;
+		eph_pub
+			.verify(&report.qos_hash(), &signature)
+			.map_err(|_| Error::InvalidSignature)?;
+	}
+
+	for result in &report.results {
+		println!(
+			"{}: {:?}",
+			if result.passed { "PASS" } else { "FAIL" },
+			result.test
+		);
+	}
+
+	if report.passed() {
+		Ok(())
+	} else {
+		Err(Error::SelfTestFailed)
+	}
+}
+
+/// Check an attestation doc, and the manifest the enclave was booted with,
+/// against a manifest. Returns a description of each check and whether it
+/// passed.
+fn enclave_checks(
+	attestation_doc: &AttestationDoc,
+	valid_cert_chain: bool,
+	root_cert: &[u8],
+	manifest: &Manifest,
+	enclave_manifest: Option<&Manifest>,
+) -> Vec<(&'static str, bool)> {
+	let pcr_matches = |index: usize, expected: &[u8]| {
+		attestation_doc.pcrs.get(&index).is_some_and(|pcr| ct_eq(pcr, expected))
+	};
+
+	vec![
+		(
+			"the attestation doc is signed by the AWS root certificate",
+			valid_cert_chain,
+		),
+		(
+			"the manifest has the AWS root certificate",
+			manifest.enclave.aws_root_certificate == root_cert,
+		),
+		("PCR0 matches the manifest", pcr_matches(0, &manifest.enclave.pcr0)),
+		("PCR1 matches the manifest", pcr_matches(1, &manifest.enclave.pcr1)),
+		("PCR2 matches the manifest", pcr_matches(2, &manifest.enclave.pcr2)),
+		("PCR3 matches the manifest", pcr_matches(3, &manifest.enclave.pcr3)),
+		(
+			"the attestation doc user data is the manifest hash",
+			attestation_doc.user_data.as_ref().is_some_and(|user_data| {
+				ct_eq(user_data, &manifest.qos_hash())
+			}),
+		),
+		(
+			"the enclave was booted with the manifest",
+			enclave_manifest == Some(manifest),
+		),
+	]
+}
+
+/// Which attestation doc to request from the enclave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttestationDocSource {
+	/// A new attestation doc, optionally including a nonce chosen by the
+	/// caller.
+	Live(Option<Vec<u8>>),
+	/// The attestation doc the enclave returned when it was booted.
+	Boot,
+}
+
+/// Arguments for [`export_attestation`].
+pub struct ExportAttestationArgs<P: AsRef<Path>> {
+	/// URI of the host.
+	pub uri: String,
+	/// Which attestation doc to request.
+	pub source: AttestationDocSource,
+	/// Path to write the COSE Sign1 attestation doc to.
+	pub attestation_doc_path: P,
+	/// Path to write the JSON rendering of the attestation doc to.
+	pub json_path: P,
+}
+
+/// Request an attestation doc from the enclave and write it, along with a
+/// JSON rendering, for others to verify independently. The attestation doc is
+/// not verified.
+pub fn export_attestation<P: AsRef<Path>>(
+	ExportAttestationArgs { uri, source, attestation_doc_path, json_path }: ExportAttestationArgs<P>,
+) -> Result<(), Error> {
+	let req = match source {
+		AttestationDocSource::Live(None) => {
+			ProtocolMsg::LiveAttestationDocRequest
+		}
+		AttestationDocSource::Live(Some(nonce)) => {
+			ProtocolMsg::NoncedAttestationDocRequest { nonce }
+		}
+		AttestationDocSource::Boot => ProtocolMsg::BootAttestationDocRequest,
+	};
+	let cose_sign1 = match request::post(&uri, &req)? {
+		ProtocolMsg::LiveAttestationDocResponse {
+			nsm_response: NsmResponse::Attestation { document },
+			..
+		} => document,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+	let attestation_doc = unsafe_attestation_doc_from_der(&cose_sign1)?;
+
+	write_with_msg(
+		attestation_doc_path.as_ref(),
+		&cose_sign1,
+		"COSE Sign1 Attestation Doc",
+	)?;
+	write_with_msg(
+		json_path.as_ref(),
+		serde_json::to_string_pretty(&attestation_doc_json(&attestation_doc))?
+			.as_bytes(),
+		"Attestation Doc JSON",
+	)
+}
+
+/// Render an attestation doc as JSON, with byte fields hex encoded.
+fn attestation_doc_json(attestation_doc: &AttestationDoc) -> serde_json::Value {
+	let hex =
+		|bytes: Option<&Vec<u8>>| bytes.map(|bytes| qos_hex::encode(bytes));
+	let pcrs: serde_json::Map<_, _> = attestation_doc
+		.pcrs
+		.iter()
+		.map(|(index, pcr)| (index.to_string(), qos_hex::encode(pcr).into()))
+		.collect();
+
+	serde_json::json!({
+		"moduleId": attestation_doc.module_id,
+		"digest": format!("{:?}", attestation_doc.digest),
+		"timestamp": attestation_doc.timestamp,
+		"pcrs": pcrs,
+		"certificate": qos_hex::encode(&attestation_doc.certificate),
+		"cabundle": attestation_doc
+			.cabundle
+			.iter()
+			.map(|cert| qos_hex::encode(cert))
+			.collect::<Vec<_>>(),
+		"publicKey": hex(attestation_doc.public_key.as_deref()),
+		"userData": hex(attestation_doc.user_data.as_deref()),
+		"nonce": hex(attestation_doc.nonce.as_deref()),
+	})
+}
+
+/// Arguments for [`export_audit_log`].
+pub struct ExportAuditLogArgs<P: AsRef<Path>> {
+	/// URI of the message route of the host.
+	pub uri: String,
+	/// Path of the public Quorum Key the log must be signed by.
+	pub quorum_key_path: P,
+	/// Path to write the JSON audit log entries to.
+	pub output_path: P,
+	/// Path to write the JSON verification report to.
+	pub report_path: P,
+}
+
+/// Pull the enclave's hash chained audit log, check the chain and the Quorum
+/// Key signatures over it, and write the entries along with a verification
+/// report. Both are written even if the checks fail, so the entries can be
+/// investigated.
+///
+/// The log is read in pages of up to
+/// [`qos_core::audit::MAX_AUDIT_ENTRIES_PER_REQUEST`] entries, each signed
+/// over its last entry; the chain ties the pages together.
+pub fn export_audit_log<P: AsRef<Path>>(
+	ExportAuditLogArgs { uri, quorum_key_path, output_path, report_path }: ExportAuditLogArgs<P>,
+) -> Result<(), Error> {
+	let quorum_key = P256Public::from_hex_file(&quorum_key_path)
+		.map_err(Error::FailedToReadQuorumPublicKey)?;
+
+	let mut entries: Vec<AuditEntry> = Vec::new();
+	let mut bad_signature = None;
+	loop {
+		let since = entries.last().map_or(0, |entry| entry.index + 1);
+		let req = ProtocolMsg::AuditLogRequest {
+			since,
+			max: MAX_AUDIT_ENTRIES_PER_REQUEST,
+		};
+		let (page, signature) = match request::post(&uri, &req)? {
+			ProtocolMsg::AuditLogResponse { entries, signature } => {
+				(entries, signature)
+			}
+			r => {
+				return Err(Error::UnexpectedProtocolMsgResponse(format!(
+					"{r:?}"
+				)))
+			}
+		};
+		let Some(head) = page.last().map(|entry| entry.index) else { break };
+		if bad_signature.is_none()
+			&& !AuditEntry::verify_signed(&page, &quorum_key, &signature)
+		{
+			bad_signature = Some(head);
+		}
+		// A page that does not move forward breaks the chain, so stop there
+		let done =
+			page.len() < MAX_AUDIT_ENTRIES_PER_REQUEST as usize || head < since;
+		entries.extend(page);
+		if done {
+			break;
+		}
+	}
+	let report = audit_log_report(&entries, bad_signature);
+
+	write_with_msg(
+		output_path.as_ref(),
+		serde_json::to_string_pretty(&entries)?.as_bytes(),
+		"Audit log",
+	)?;
+	write_with_msg(
+		report_path.as_ref(),
+		serde_json::to_string_pretty(&report)?.as_bytes(),
+		"Audit log verification report",
+	)?;
+
+	if let Some(position) = AuditEntry::first_break(&entries) {
+		return Err(Error::AuditChainBroken { index: entries[position].index });
+	}
+	if let Some(index) = bad_signature {
+		return Err(Error::AuditSignatureInvalid { index });
+	}
+	log::info(
+		"client",
+		format_args!(
+			"Audit log chain and quorum key signatures verified: {} entries",
+			entries.len()
+		),
+	);
+
+	Ok(())
+}
+
+/// Summarize the result of checking the chain of audit log `entries`, and
+/// the index of the page with the first invalid signature, if any.
+fn audit_log_report(
+	entries: &[AuditEntry],
+	bad_signature: Option<u64>,
+) -> serde_json::Value {
+	let first_break = AuditEntry::first_break(entries);
+
+	serde_json::json!({
+		"entries": entries.len(),
+		"firstIndex": entries.first().map(|entry| entry.index),
+		"lastIndex": entries.last().map(|entry| entry.index),
+		"headHash": entries.last().map(|entry| qos_hex::encode(&entry.hash)),
+		"chainIntact": first_break.is_none(),
+		"firstBreak": first_break.map(|position| entries[position].index),
+		"signaturesValid": bad_signature.is_none(),
+		"firstInvalidSignature": bad_signature,
+	})
+}
+
+/// Arguments for [`proxy_re_encrypt_share`].
+pub struct ProxyReEncryptShareArgs<P: AsRef<Path>> {
+	/// Key of this member.
+	pub pair: PairOrYubi,
+	/// Path of the encrypted share.
+	pub share_path: P,
+	/// Path of the attestation doc.
+	pub attestation_doc_path: P,
+	/// Path to write the approval to.
+	pub approval_path: P,
+	/// Path to write the ephemeral key wrapped share to.
+	pub eph_wrapped_share_path: P,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pub pcr3_preimage_path: P,
+	/// Path of the manifest envelope.
+	pub manifest_envelope_path: P,
+	/// Directory with the manifest set public keys and threshold.
+	pub manifest_set_dir: P,
+	/// Alias of this member.
+	pub alias: String,
+	/// Skip attestation doc verification. Only for testing.
+	pub unsafe_skip_attestation: bool,
+	/// Ephemeral key to encrypt to instead of the one in the attestation
+	/// doc. Only for testing.
+	pub unsafe_eph_path_override: Option<String>,
+	/// Skip the interactive confirmations. Only for testing.
+	pub unsafe_auto_confirm: bool,
+	/// KMS key the share is wrapped with, if it was wrapped with
+	/// [`kms_wrap_share`].
+	pub kms_key_id: Option<String>,
+}
+
+// Verifications in this focus around ensuring
+// - the intended manifest is being used
+// - the manifest set approved the manifest and is the correct set
+// - the enclave belongs to the intended organization (and not an attackers
+//   organization)
+/// Verify the attestation doc and manifest envelope, ask for confirmation,
+/// and re-encrypt this member's share to the enclave's ephemeral key.
+pub fn proxy_re_encrypt_share<P: AsRef<Path>>(
+	ProxyReEncryptShareArgs {
+		mut pair,
+		share_path,
+		attestation_doc_path,
+		approval_path,
+		eph_wrapped_share_path,
+		pcr3_preimage_path,
+		manifest_set_dir,
+		manifest_envelope_path,
+		alias,
+		unsafe_skip_attestation,
+		unsafe_eph_path_override,
+		unsafe_auto_confirm,
+		kms_key_id,
+	}: ProxyReEncryptShareArgs<P>,
+) -> Result<(), Error> {
+	let manifest_envelope = read_manifest_envelope(&manifest_envelope_path)?;
+	let attestation_doc =
+		read_attestation_doc(&attestation_doc_path, unsafe_skip_attestation)?;
+	let mut encrypted_share = std::fs::read(share_path)
+		.map_err(|e| Error::ReadShare(e.to_string()))?;
+	if let Some(key_id) = kms_key_id {
+		encrypted_share = Kms::new(key_id).decrypt(&encrypted_share)?;
+	}
+
+	let pcr3_preimage = find_pcr3(&pcr3_preimage_path)?;
+
+	// Verify the attestation doc matches up with the pcrs in the manifest
+	if unsafe_skip_attestation {
+		log::warn(
+			"client",
+			"**WARNING:** Skipping attestation document verification.",
+		);
+	} else {
+		verify_attestation_doc_against_user_input(
+			&attestation_doc,
+			&manifest_envelope.manifest.qos_hash(),
+			&manifest_envelope.manifest.enclave.pcr0,
+			&manifest_envelope.manifest.enclave.pcr1,
+			&manifest_envelope.manifest.enclave.pcr2,
+			&extract_pcr3(pcr3_preimage_path)?,
+		)?;
+	}
+
+	// Pull out the ephemeral key or use the override
+	let eph_pub: P256Public = if let Some(eph_path) = unsafe_eph_path_override {
+		P256Pair::from_hex_file(eph_path)?.public_key()
+	} else {
+		P256Public::from_bytes(
+			&attestation_doc.public_key.ok_or(Error::InvalidEphemeralKey)?,
+		)
+		.map_err(|_| Error::InvalidEphemeralKey)?
+	};
+
+	let member = QuorumMember { pub_key: pair.public_key_bytes()?, alias };
+
+	if !proxy_re_encrypt_share_programmatic_verifications(
+		&manifest_envelope,
+		&get_manifest_set(manifest_set_dir)?,
+		&member,
+	) {
+		return Err(Error::ManifestMismatch);
+	}
+
+	if !unsafe_auto_confirm {
+		let stdin = io::stdin();
+		let stdin_locked = stdin.lock();
+		let mut prompter =
+			Prompter { reader: stdin_locked, writer: io::stdout() };
+		if !proxy_re_encrypt_share_human_verifications(
+			&manifest_envelope,
+			&pcr3_preimage,
+			&mut prompter,
+		) {
+			return Err(Error::NotConfirmed);
+		}
+		drop(prompter);
+	}
+
+	let share = {
+		let plaintext_share = pair.decrypt(&encrypted_share)?;
+		// Don't send the enclave a share of another quorum key
+		let _share = Zeroizing::new(
+			open_share(
+				&plaintext_share,
+				&manifest_envelope.manifest.namespace.quorum_key,
+				manifest_envelope.manifest.share_set.threshold,
+			)
+			.map_err(Error::InvalidShare)?,
+		);
+		eph_pub.encrypt(&plaintext_share)?
+	};
+
+	let approval = borsh::to_vec(&Approval {
+		signature: pair.sign(&manifest_envelope.manifest.qos_hash())?,
+		member,
+	})?;
+
+	write_with_msg(approval_path.as_ref(), &approval, "Share Set Approval")?;
+
+	write_with_msg(
+		eph_wrapped_share_path.as_ref(),
+		&share,
+		"Ephemeral key wrapped share",
+	)?;
+
+	drop(pair);
+
+	Ok(())
+}
+
+fn proxy_re_encrypt_share_programmatic_verifications(
+	manifest_envelope: &ManifestEnvelope,
+	manifest_set: &ManifestSet,
+	member: &QuorumMember,
+) -> bool {
+	if let Err(e) = manifest_envelope.check_approvals() {
+		log::error(
+			"client",
+			format_args!(
+				"Manifest envelope did not have valid approvals: {e:?}"
+			),
+		);
+		return false;
+	};
+
+	if manifest_envelope.manifest.manifest_set != *manifest_set {
+		log::error(
+			"client",
+			"Manifest's manifest set does not match locally found Manifest Set",
+		);
+		return false;
+	}
+
+	if !manifest_envelope.manifest.share_set.members.contains(member) {
+		log::error(
+			"client",
+			"The provided share set key and alias are not part of the Share Set",
+		);
+		return false;
+	}
+
+	true
+}
+
+fn proxy_re_encrypt_share_human_verifications<R, W>(
+	manifest_envelope: &ManifestEnvelope,
+	pcr3_preimage: &str,
+	prompter: &mut Prompter<R, W>,
+) -> bool
+where
+	R: BufRead,
+	W: Write,
+{
+	// Check the namespace name
+	{
+		let prompt = format!(
+			"Is this the correct namespace name: {}? (yes/no)",
+			manifest_envelope.manifest.namespace.name
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check the namespace nonce
+	{
+		let prompt = format!(
+			"Is this the correct namespace nonce: {}? (yes/no)",
+			manifest_envelope.manifest.namespace.nonce
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	// Check that the IAM role is correct
+	{
+		let prompt = format!(
+			"Does this AWS IAM role belong to the intended organization: {pcr3_preimage}? (yes/no)"
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	{
+		let mut approvers = manifest_envelope
+			.manifest_set_approvals
+			.iter()
+			.cloned()
+			.map(|m| m.member.alias)
+			.map(|a| format!("\talias: {a}"))
+			.collect::<Vec<_>>();
+		approvers.sort();
+		let approvers = approvers.join("\n");
+
+		let prompt = format!(
+			"The following manifest set members approved:\n{approvers}\nIs this ok? (yes/no)"
+		);
+
+		if !prompter.prompt_is_yes(&prompt) {
+			return false;
+		}
+	}
+
+	true
+}
+
+/// Wrap the encrypted share at `share_path` with the KMS key `kms_key_id`
+/// and write it to `output_path`. Give the same key to
+/// `proxy-re-encrypt-share` to unwrap it at ceremony time.
+pub fn kms_wrap_share<P: AsRef<Path>>(
+	share_path: P,
+	kms_key_id: String,
+	output_path: P,
+) -> Result<(), Error> {
+	let encrypted_share =
+		fs::read(share_path).map_err(|e| Error::ReadShare(e.to_string()))?;
+	let wrapped = Kms::new(kms_key_id).encrypt(&encrypted_share)?;
+
+	write_with_msg(output_path.as_ref(), &wrapped, "KMS wrapped share")
+}
+
+/// Post an ephemeral key wrapped share and approval to the enclave.
+pub fn post_share<P: AsRef<Path>>(
+	uri: &str,
+	eph_wrapped_share_path: P,
+	approval_path: P,
+) -> Result<(), Error> {
+	// Get the ephemeral key wrapped share
+	let share = fs::read(eph_wrapped_share_path)
+		.map_err(Error::FailedToReadEphWrappedShare)?;
+	let approval = read_attestation_approval(&approval_path)?;
+
+	let req = ProtocolMsg::ProvisionRequest { share, approval };
+	let is_reconstructed = match request::post(uri, &req)? {
+		ProtocolMsg::ProvisionResponse { reconstructed } => reconstructed,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	if is_reconstructed {
+		log::info("client", "The quorum key has been reconstructed.");
+	} else {
+		log::info("client", "The quorum key has *not* been reconstructed.");
+	};
+
+	Ok(())
+}
+
+/// A boot ceremony for [`run_boot_ceremony`], read from a TOML file. Paths
+/// are relative to the plan file.
+#[derive(Debug, PartialEq, Eq)]
+struct BootCeremonyPlan {
+	/// The manifest to boot.
+	manifest_path: PathBuf,
+	/// Hash of the manifest, as announced to the manifest set.
+	manifest_hash: Hash256,
+	/// Directory the manifest set members put their approvals in.
+	manifest_approvals_dir: PathBuf,
+	/// Path to write the manifest envelope to.
+	manifest_envelope_path: PathBuf,
+	/// The pivot binary.
+	pivot_path: PathBuf,
+	/// File with the IAM role ARN used as the PCR3 preimage.
+	pcr3_preimage_path: PathBuf,
+	/// Directory the share set members put their ephemeral key wrapped
+	/// shares and approvals in, as `<alias>.eph_wrapped.share` and
+	/// `<alias>.attestation.approval`.
+	shares_dir: PathBuf,
+	/// File recording the completed steps, so an interrupted ceremony
+	/// resumes where it stopped. Defaults to the plan path with a
+	/// `checkpoint` extension.
+	checkpoint_path: PathBuf,
+	/// Skip attestation doc verification. Only for testing.
+	unsafe_skip_attestation: bool,
+}
+
+const PLAN_KEYS: &[&str] = &[
+	"manifest",
+	"manifest-hash",
+	"manifest-approvals-dir",
+	"manifest-envelope",
+	"pivot",
+	"pcr3-preimage",
+	"shares-dir",
+	"checkpoint",
+	"unsafe-skip-attestation",
+];
+
+impl BootCeremonyPlan {
+	/// Read a plan from the TOML file at `path`.
+	fn from_file(path: &Path) -> Result<Self, Error> {
+		let contents = String::from_utf8(read_file(path)?)
+			.map_err(|_| invalid_file(path, "not UTF-8"))?;
+
+		Self::parse(&contents, path).map_err(|e| invalid_file(path, e))
+	}
+
+	fn parse(contents: &str, path: &Path) -> Result<Self, String> {
+		let plan: toml_edit::Document =
+			contents.parse().map_err(|e| format!("not valid TOML: {e}"))?;
+		if let Some((key, _)) =
+			plan.iter().find(|(key, _)| !PLAN_KEYS.contains(key))
+		{
+			return Err(format!("unknown key `{key}`"));
+		}
+
+		let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+		let str_value = |key: &str| -> Result<String, String> {
+			plan.get(key)
+				.ok_or_else(|| format!("missing `{key}`"))?
+				.as_str()
+				.map(String::from)
+				.ok_or_else(|| format!("`{key}` must be a string"))
+		};
+		let path_value = |key: &str| -> Result<PathBuf, String> {
+			Ok(base_dir.join(str_value(key)?))
+		};
+
+		let checkpoint_path = if plan.contains_key("checkpoint") {
+			path_value("checkpoint")?
+		} else {
+			path.with_extension("checkpoint")
+		};
+		let manifest_hash = str_value("manifest-hash")?;
+		let manifest_hash =
+			hex::decode_hash256(&manifest_hash).map_err(|e| {
+				format!("`manifest-hash` is not a 256 bit hash: {e}")
+			})?;
+		let unsafe_skip_attestation = match plan.get("unsafe-skip-attestation")
+		{
+			None => false,
+			Some(item) => item
+				.as_bool()
+				.ok_or("`unsafe-skip-attestation` must be a boolean")?,
+		};
+
+		Ok(Self {
+			manifest_path: path_value("manifest")?,
+			manifest_hash,
+			manifest_approvals_dir: path_value("manifest-approvals-dir")?,
+			manifest_envelope_path: path_value("manifest-envelope")?,
+			pivot_path: path_value("pivot")?,
+			pcr3_preimage_path: path_value("pcr3-preimage")?,
+			shares_dir: path_value("shares-dir")?,
+			checkpoint_path,
+			unsafe_skip_attestation,
+		})
+	}
+}
+
+/// Steps of a boot ceremony, in the order they are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CeremonyStep {
+	ValidateManifest,
+	CollectApprovals,
+	BootStandard,
+	VerifyAttestation,
+	PostShares,
+}
+
+impl CeremonyStep {
+	const ALL: [Self; 5] = [
+		Self::ValidateManifest,
+		Self::CollectApprovals,
+		Self::BootStandard,
+		Self::VerifyAttestation,
+		Self::PostShares,
+	];
+
+	fn name(self) -> &'static str {
+		match self {
+			Self::ValidateManifest => "validate-manifest",
+			Self::CollectApprovals => "collect-approvals",
+			Self::BootStandard => "boot-standard",
+			Self::VerifyAttestation => "verify-attestation",
+			Self::PostShares => "post-shares",
+		}
+	}
+}
+
+/// Progress of a boot ceremony, stored as JSON.
+#[derive(Debug, PartialEq, Eq)]
+struct CeremonyCheckpoint {
+	path: PathBuf,
+	manifest_hash: String,
+	completed: Vec<String>,
+	shares_posted: Vec<String>,
+}
+
+impl CeremonyCheckpoint {
+	/// Read the checkpoint at `path`, refusing one made for another
+	/// manifest. A checkpoint that does not exist yet has no progress.
+	fn open(path: &Path, manifest_hash: &str) -> Result<Self, Error> {
+		let mut checkpoint = Self {
+			path: path.to_path_buf(),
+			manifest_hash: manifest_hash.to_string(),
+			completed: vec![],
+			shares_posted: vec![],
+		};
+		if !path.exists() {
+			return Ok(checkpoint);
+		}
+
+		let value: serde_json::Value =
+			serde_json::from_slice(&read_file(path)?).map_err(|e| {
+				invalid_file(path, format!("not a checkpoint: {e}"))
+			})?;
+		if value["manifestHash"] != manifest_hash {
+			return Err(invalid_file(
+				path,
+				"checkpoint of a ceremony for another manifest; remove it to \
+				start over",
+			));
+		}
+		let strings = |key: &str| {
+			value[key]
+				.as_array()
+				.map(|values| {
+					values
+						.iter()
+						.filter_map(|v| v.as_str().map(String::from))
+						.collect()
+				})
+				.unwrap_or_default()
+		};
+		checkpoint.completed = strings("completed");
+		checkpoint.shares_posted = strings("sharesPosted");
+
+		Ok(checkpoint)
+	}
+
+	fn is_completed(&self, step: CeremonyStep) -> bool {
+		self.completed.iter().any(|name| name == step.name())
+	}
+
+	fn complete(&mut self, step: CeremonyStep) -> Result<(), Error> {
+		self.completed.push(step.name().to_string());
+		self.save()
+	}
+
+	fn share_posted(&mut self, alias: &str) -> Result<(), Error> {
+		self.shares_posted.push(alias.to_string());
+		self.save()
+	}
+
+	fn save(&self) -> Result<(), Error> {
+		let value = serde_json::json!({
+			"manifestHash": self.manifest_hash,
+			"completed": self.completed,
+			"sharesPosted": self.shares_posted,
+		});
+		fs::write(&self.path, serde_json::to_string_pretty(&value)?).map_err(
+			|e| Error::FailedToWrite {
+				path: self.path.display().to_string(),
+				error: e.to_string(),
+			},
+		)
+	}
+}
+
+/// Run the boot ceremony in the TOML plan at `plan_path`: validate the
+/// manifest, collect the manifest set approvals into a manifest envelope,
+/// boot the enclave, verify its attestation and post the shares of the share
+/// set as the members provide them. Completed steps are checkpointed, so
+/// running it again resumes an interrupted ceremony.
+pub fn run_boot_ceremony<P: AsRef<Path>>(
+	uri: &str,
+	plan_path: P,
+) -> Result<(), Error> {
+	let plan = BootCeremonyPlan::from_file(plan_path.as_ref())?;
+	let stdin = io::stdin();
+	let mut prompter = Prompter { reader: stdin.lock(), writer: io::stdout() };
+
+	run_boot_ceremony_steps(uri, &plan, &mut prompter)
+}
+
+fn run_boot_ceremony_steps<R, W>(
+	uri: &str,
+	plan: &BootCeremonyPlan,
+	prompter: &mut Prompter<R, W>,
+) -> Result<(), Error>
+where
+	R: BufRead,
+	W: Write,
+{
+	let mut checkpoint = CeremonyCheckpoint::open(
+		&plan.checkpoint_path,
+		&qos_hex::encode(&plan.manifest_hash),
+	)?;
+
+	for (i, step) in CeremonyStep::ALL.into_iter().enumerate() {
+		let progress = format!("[{}/{}]", i + 1, CeremonyStep::ALL.len());
+		if checkpoint.is_completed(step) {
+			log::info(
+				"client",
+				format_args!("{progress} {}: already completed", step.name()),
+			);
+			continue;
+		}
+		log::info("client", format_args!("{progress} {}", step.name()));
+
+		match step {
+			CeremonyStep::ValidateManifest => {
+				display_manifest(&plan.manifest_path)?;
+				let prompt = format!(
+					"Is this the manifest to boot, with hash {}? (yes/no)",
+					qos_hex::encode(&plan.manifest_hash)
+				);
+				if !prompter.prompt_is_yes(&prompt) {
+					return Err(Error::NotConfirmed);
+				}
+			}
+			CeremonyStep::CollectApprovals => {
+				verify_approvals(
+					&plan.manifest_approvals_dir,
+					&plan.manifest_path,
+					&plan.manifest_hash,
+				)?;
+				generate_manifest_envelope(
+					&plan.manifest_approvals_dir,
+					&plan.manifest_path,
+					Some(plan.manifest_envelope_path.display().to_string()),
+				)?;
+			}
+			CeremonyStep::BootStandard => boot_standard(BootStandardArgs {
+				uri: uri.to_string(),
+				pivot_path: &plan.pivot_path,
+				manifest_envelope_path: &plan.manifest_envelope_path,
+				pcr3_preimage_path: &plan.pcr3_preimage_path,
+				unsafe_skip_attestation: plan.unsafe_skip_attestation,
+			})?,
+			CeremonyStep::VerifyAttestation => {
+				if plan.unsafe_skip_attestation {
+					log::warn(
+						"client",
+						"**WARNING:** Skipping attestation document \
+						verification.",
+					);
+				} else {
+					verify_enclave(uri, &plan.manifest_envelope_path)?;
+				}
+			}
+			CeremonyStep::PostShares => {
+				post_ceremony_shares(uri, plan, &mut checkpoint, prompter)?;
+			}
+		}
+
+		checkpoint.complete(step)?;
+	}
+
+	println!("The boot ceremony is complete.");
+
+	Ok(())
+}
+
+/// Post the share of each share set member once they put it in the shares
+/// dir, until the enclave has reconstructed the quorum key.
+fn post_ceremony_shares<R, W>(
+	uri: &str,
+	plan: &BootCeremonyPlan,
+	checkpoint: &mut CeremonyCheckpoint,
+	prompter: &mut Prompter<R, W>,
+) -> Result<(), Error>
+where
+	R: BufRead,
+	W: Write,
+{
+	let manifest = read_manifest(&plan.manifest_path)?;
+
+	'members: for member in &manifest.share_set.members {
+		if enclave_phase(uri)? == ProtocolPhase::QuorumKeyProvisioned {
+			break;
+		}
+		let alias = &member.alias;
+		if checkpoint.shares_posted.contains(alias) {
+			continue;
+		}
+
+		let share_path =
+			plan.shares_dir.join(format!("{alias}.eph_wrapped.share"));
+		let approval_path =
+			plan.shares_dir.join(format!("{alias}.attestation.approval"));
+		while !(share_path.exists() && approval_path.exists()) {
+			let prompt = format!(
+				"Waiting for {alias} to provide {} and {}. Are they in \
+				place? (yes/no, no skips {alias})",
+				share_path.display(),
+				approval_path.display()
+			);
+			if !prompter.prompt_is_yes(&prompt) {
+				log::info("client", format_args!("Skipping {alias}"));
+				continue 'members;
+			}
+		}
+
+		post_share(uri, &share_path, &approval_path)?;
+		checkpoint.share_posted(alias)?;
+	}
+
+	if enclave_phase(uri)? == ProtocolPhase::QuorumKeyProvisioned {
+		Ok(())
+	} else {
+		Err(Error::ShareThresholdNotMet)
+	}
+}
+
+fn enclave_phase(uri: &str) -> Result<ProtocolPhase, Error> {
+	match request::post(uri, &ProtocolMsg::StatusRequest)? {
+		ProtocolMsg::StatusResponse(phase) => Ok(phase),
+		r => Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}"))),
+	}
+}
+
+/// A [`ShareRotation`] signed by the member's new personal key.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct ShareRotationRequest {
+	rotation: ShareRotation,
+	signature: Vec<u8>,
+}
+
+/// Arguments for [`rotate_personal_key`].
+pub struct RotatePersonalKeyArgs<P: AsRef<Path>> {
+	/// URI of the host's message endpoint.
+	pub uri: String,
+	/// Alias of this member.
+	pub alias: String,
+	/// Directory of the namespace, with the genesis output.
+	pub namespace_dir: P,
+	/// Path of the manifest envelope the enclave was provisioned with.
+	pub manifest_envelope_path: P,
+	/// Path of the new master seed. It is generated if it does not exist.
+	pub master_seed_path: P,
+	/// Path to write the new public key to.
+	pub pub_path: P,
+	/// Encrypt the new master seed with a passphrase.
+	pub encrypt: bool,
+	/// Directory for the rotation request and its approvals.
+	pub rotation_dir: P,
+	/// Path to write the share encrypted to the new key to.
+	pub share_path: P,
+}
+
+/// Rotation of `alias`'s share of the quorum key to `new_pub_key`.
+fn new_share_rotation(
+	manifest: &Manifest,
+	genesis_output: &GenesisOutput,
+	alias: &str,
+	new_pub_key: Vec<u8>,
+) -> Result<ShareRotation, Error> {
+	let member = manifest
+		.share_set
+		.members
+		.iter()
+		.find(|m| m.alias == alias)
+		.cloned()
+		.ok_or(Error::InvalidShareRotation(
+			"the alias is not in the share set",
+		))?;
+	let member_output = genesis_output
+		.member_outputs
+		.iter()
+		.find(|o| o.share_set_member.alias == alias)
+		.ok_or(Error::MemberOutputNotFound)?;
+
+	Ok(ShareRotation {
+		manifest_hash: manifest.qos_hash(),
+		member,
+		share_hash: member_output.share_hash,
+		new_pub_key,
+	})
+}
+
+/// Rotate the personal key of a share set member, e.g. after the old one is
+/// lost.
+///
+/// The first run generates the new personal key and writes a rotation
+/// request, signed by the new key, to the rotation dir. Once a threshold of
+/// the share set has approved it with [`approve_share_rotation`], the second
+/// run sends the request to the enclave, which encrypts the member's share to
+/// the new key, and writes the new encrypted share.
+pub fn rotate_personal_key<P: AsRef<Path>>(
+	args: RotatePersonalKeyArgs<P>,
+) -> Result<(), Error> {
+	let RotatePersonalKeyArgs {
+		uri,
+		alias,
+		namespace_dir,
+		manifest_envelope_path,
+		master_seed_path,
+		pub_path,
+		encrypt,
+		rotation_dir,
+		share_path,
+	} = args;
+
+	let manifest = read_manifest_envelope(&manifest_envelope_path)?.manifest;
+	let request_path = rotation_dir.as_ref().join(SHARE_ROTATION_FILE);
+	if !request_path.exists() {
+		if !master_seed_path.as_ref().exists() {
+			generate_file_key(&master_seed_path, &pub_path, encrypt, false)?;
+		}
+		let new_pair = read_master_seed(&master_seed_path)?;
+		let genesis_output = read_genesis_output(
+			namespace_dir.as_ref().join(GENESIS_OUTPUT_FILE),
+		)?;
+
+		let rotation = new_share_rotation(
+			&manifest,
+			&genesis_output,
+			&alias,
+			new_pair.public_key().to_bytes(),
+		)?;
+		let signature = new_pair.sign(&rotation.qos_hash())?;
+		write_with_msg(
+			&request_path,
+			&borsh::to_vec(&ShareRotationRequest { rotation, signature })?,
+			"Share Rotation Request",
+		)?;
+		log::info(
+			"client",
+			format_args!(
+				"Have {} share set members approve the request with \
+				`approve-share-rotation`, then run `rotate-personal-key` again",
+				manifest.share_set.threshold
+			),
+		);
+
+		return Ok(());
+	}
+
+	let ShareRotationRequest { rotation, signature } =
+		read_share_rotation_request(&request_path)?;
+	let new_pair = read_master_seed(&master_seed_path)?;
+	if new_pair.public_key().to_bytes() != rotation.new_pub_key {
+		return Err(Error::InvalidShareRotation(
+			"the request is not for the key at the master seed path",
+		));
+	}
+	if !ct_eq(&rotation.manifest_hash, &manifest.qos_hash()) {
+		return Err(Error::InvalidShareRotation(
+			"the request is for a different manifest",
+		));
+	}
+	let approvals = find_share_rotation_approvals(
+		&rotation_dir,
+		&rotation,
+		&manifest.share_set,
+	)?;
+
+	let share_hash = rotation.share_hash;
+	let req =
+		ProtocolMsg::RotateShareRequest { rotation, signature, approvals };
+	let encrypted_share = match request::post(&uri, &req)? {
+		ProtocolMsg::RotateShareResponse { encrypted_share } => encrypted_share,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	let plaintext = Zeroizing::new(
+		new_pair.decrypt(&encrypted_share).map_err(|_| Error::BadDecryption)?,
+	);
+	let share = Zeroizing::new(
+		open_share(
+			&plaintext,
+			&manifest.namespace.quorum_key,
+			manifest.share_set.threshold,
+		)
+		.map_err(Error::InvalidShare)?,
+	);
+	if !ct_eq(&sha_512(&share), &share_hash) {
+		return Err(Error::ShareHashMismatch);
+	}
+
+	write_with_msg(
+		share_path.as_ref(),
+		&encrypted_share,
+		"Encrypted Quorum Share",
+	)
+}
+
+/// Arguments for [`approve_share_rotation`].
+pub struct ApproveShareRotationArgs<P: AsRef<Path>> {
+	/// Key of this member.
+	pub pair: PairOrYubi,
+	/// Alias of this member.
+	pub alias: String,
+	/// Directory of the namespace, with the genesis output.
+	pub namespace_dir: P,
+	/// Path of the manifest envelope the enclave was provisioned with.
+	pub manifest_envelope_path: P,
+	/// Directory with the rotation request, to write the approval to.
+	pub rotation_dir: P,
+	/// Skip the interactive confirmations. Only for testing.
+	pub unsafe_auto_confirm: bool,
+}
+
+/// Check a share rotation request against the manifest and genesis output,
+/// ask for confirmation, and write an approval signed by this member.
+pub fn approve_share_rotation<P: AsRef<Path>>(
+	args: ApproveShareRotationArgs<P>,
+) -> Result<(), Error> {
+	let ApproveShareRotationArgs {
+		mut pair,
+		alias,
+		namespace_dir,
+		manifest_envelope_path,
+		rotation_dir,
+		unsafe_auto_confirm,
+	} = args;
+
+	let request = read_share_rotation_request(
+		rotation_dir.as_ref().join(SHARE_ROTATION_FILE),
+	)?;
+	let manifest = read_manifest_envelope(&manifest_envelope_path)?.manifest;
+	let genesis_output =
+		read_genesis_output(namespace_dir.as_ref().join(GENESIS_OUTPUT_FILE))?;
+	check_share_rotation(&request, &manifest, &genesis_output)?;
+
+	let member = QuorumMember { pub_key: pair.public_key_bytes()?, alias };
+	if !manifest.share_set.members.contains(&member) {
+		return Err(Error::InvalidShareRotation(
+			"the approver is not in the share set",
+		));
+	}
+
+	if !unsafe_auto_confirm {
+		let stdin = io::stdin();
+		let stdin_locked = stdin.lock();
+		let mut prompter =
+			Prompter { reader: stdin_locked, writer: io::stdout() };
+		let prompt = format!(
+			"Has {} told you, in person or over a trusted channel, that their \
+			new personal key is {}? (yes/no)",
+			request.rotation.member.alias,
+			qos_hex::encode(&request.rotation.new_pub_key)
+		);
+		if !prompter.prompt_is_yes(&prompt) {
+			return Err(Error::NotConfirmed);
+		}
+	}
+
+	let approval = Approval {
+		signature: pair.sign(&request.rotation.qos_hash())?,
+		member,
+	};
+	let approval_path = rotation_dir
+		.as_ref()
+		.join(format!("{}.{APPROVAL_EXT}", approval.member.alias));
+	write_with_msg(
+		&approval_path,
+		&borsh::to_vec(&approval)?,
+		"Share Rotation Approval",
+	)?;
+
+	drop(pair);
+
+	Ok(())
+}
+
+/// Check that the rotation is signed by its new key, is for the manifest and
+/// a member of its share set, and names the member's share from the genesis
+/// output.
+fn check_share_rotation(
+	request: &ShareRotationRequest,
+	manifest: &Manifest,
+	genesis_output: &GenesisOutput,
+) -> Result<(), Error> {
+	let ShareRotationRequest { rotation, signature } = request;
+
+	P256Public::from_bytes(&rotation.new_pub_key)
+		.and_then(|key| key.verify(&rotation.qos_hash(), signature))
+		.map_err(|_| {
+			Error::InvalidShareRotation("not signed by the new personal key")
+		})?;
+
+	if !ct_eq(&rotation.manifest_hash, &manifest.qos_hash()) {
+		return Err(Error::InvalidShareRotation(
+			"the request is for a different manifest",
+		));
+	}
+
+	if !manifest.share_set.members.contains(&rotation.member) {
+		return Err(Error::InvalidShareRotation(
+			"the member is not in the share set",
+		));
+	}
+
+	if !genesis_output.member_outputs.iter().any(|o| {
+		o.share_set_member.alias == rotation.member.alias
+			&& ct_eq(&o.share_hash, &rotation.share_hash)
+	}) {
+		return Err(Error::InvalidShareRotation(
+			"the share hash is not the member's share in the genesis output",
+		));
+	}
+
+	Ok(())
+}
+
+fn read_share_rotation_request<P: AsRef<Path>>(
+	path: P,
+) -> Result<ShareRotationRequest, Error> {
+	let path = path.as_ref();
+	ShareRotationRequest::try_from_slice(&read_file(path)?)
+		.map_err(|_| invalid_file(path, "not a share rotation request"))
+}
+
+fn find_share_rotation_approvals<P: AsRef<Path>>(
+	rotation_dir: P,
+	rotation: &ShareRotation,
+	share_set: &ShareSet,
+) -> Result<Vec<Approval>, Error> {
+	let mut approvals = vec![];
+	for path in find_file_paths(&rotation_dir)? {
+		let file_name = split_file_name(&path)?;
+		// Only look at files with the approval extension
+		if file_name.last().map_or(true, |s| s.as_str() != APPROVAL_EXT) {
+			continue;
+		};
+
+		let approval =
+			Approval::try_from_slice(&read_file(&path)?).map_err(|_| {
+				invalid_file(&path, "failed to deserialize approval")
+			})?;
+
+		if !share_set.members.contains(&approval.member) {
+			return Err(invalid_file(
+				&path,
+				format!(
+					"approval from member ({:?}) not included in the Share Set",
+					approval.member.alias
+				),
+			));
+		}
+
+		let pub_key = P256Public::from_bytes(&approval.member.pub_key)
+			.map_err(|_| invalid_file(&path, "failed to interpret pub key"))?;
+		if pub_key.verify(&rotation.qos_hash(), &approval.signature).is_err() {
+			return Err(invalid_file(
+				&path,
+				"approval signature could not be verified against the rotation",
+			));
+		}
+
+		approvals.push(approval);
+	}
+
+	Ok(approvals)
+}
+
+/// Where [`app_proxy`] gets the bytes to send to the app from.
+pub enum AppProxyData<P: AsRef<Path>> {
+	/// Hex encoded bytes.
+	Hex(String),
+	/// File with the raw bytes.
+	File(P),
+}
+
+impl<P: AsRef<Path>> AppProxyData<P> {
+	fn bytes(&self) -> Result<Vec<u8>, Error> {
+		match self {
+			Self::Hex(hex) => Ok(qos_hex::decode(hex)?),
+			Self::File(path) => read_file(path),
+		}
+	}
+}
+
+/// Send raw bytes to the app running in the enclave and print its hex
+/// encoded response.
+pub fn app_proxy<P: AsRef<Path>>(
+	uri: &str,
+	data: &AppProxyData<P>,
+) -> Result<(), Error> {
+	let req = ProtocolMsg::ProxyRequest { data: data.bytes()? };
+	let data = match request::post(uri, &req)? {
+		ProtocolMsg::ProxyResponse { data } => data,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	println!("{}", qos_hex::encode(&data));
+
+	Ok(())
+}
+
+/// Sign a hex encoded payload with the yubikey and print the signature.
+#[cfg(feature = "smartcard")]
+pub fn yubikey_sign(hex_payload: &str) -> Result<(), Error> {
+	let bytes = qos_hex::decode(hex_payload)?;
+
+	let mut pair = PairOrYubi::from_inputs(true, None, None)?;
+	let signature_bytes = pair.sign(&bytes)?;
+	let signature = qos_hex::encode(&signature_bytes);
+
+	println!("{signature}");
+
+	Ok(())
+}
+
+/// Print the public key of the yubikey.
+#[cfg(feature = "smartcard")]
+pub fn yubikey_public() -> Result<(), Error> {
+	let mut yubi = crate::yubikey::open_single()?;
+	let public = crate::yubikey::pair_public_key(&mut yubi)?;
+
+	let hex = qos_hex::encode(&public);
+	println!("{hex}");
+
+	Ok(())
+}
+
+/// A key or share file found by [`list_keys`].
+#[derive(Debug, PartialEq, Eq)]
+struct KeyListing {
+	file_name: String,
+	kind: &'static str,
+	alias: String,
+	namespace: Option<String>,
+	fingerprint: Option<String>,
+	warnings: Vec<String>,
+}
+
+/// List the public keys, master seeds and shares in a personal dir, with the
+/// alias and namespace from each file name (`<alias>[.<namespace>].<ext>`)
+/// and the fingerprint of each key, warning about secrets other users can
+/// read.
+pub fn list_keys<P: AsRef<Path>>(personal_dir: P) -> Result<(), Error> {
+	let listings = key_listings(personal_dir)?;
+	if listings.is_empty() {
+		println!("No keys or shares found");
+	}
+
+	for listing in listings {
+		println!("{}", listing.file_name);
+		println!("\tkind: {}", listing.kind);
+		println!("\talias: {}", listing.alias);
+		if let Some(namespace) = listing.namespace {
+			println!("\tnamespace: {namespace}");
+		}
+		if let Some(fingerprint) = listing.fingerprint {
+			println!("\tfingerprint: {fingerprint}");
+		}
+		for warning in listing.warnings {
+			println!("\tWARNING: {warning}");
+		}
+	}
+
+	Ok(())
+}
+
+fn key_listings<P: AsRef<Path>>(dir: P) -> Result<Vec<KeyListing>, Error> {
+	use std::os::unix::fs::PermissionsExt;
+
+	let mut paths = find_file_paths(dir)?;
+	paths.sort();
+
+	let mut listings = vec![];
+	for path in paths {
+		let mut parts = split_file_name(&path)?;
+		if parts.len() < 2 {
+			continue;
+		}
+		let ext = parts.pop().expect("has at least two parts. qed.");
+		let alias = parts.remove(0);
+		let namespace = (!parts.is_empty()).then(|| parts.join("."));
+
+		let mut warnings = vec![];
+		let (kind, fingerprint) = match ext.as_str() {
+			PUB_EXT => {
+				let fingerprint = P256Public::from_hex_file(&path)
+					.map(|public| public_key_fingerprint(&public));
+				if fingerprint.is_err() {
+					warnings.push("not a valid public key".to_string());
+				}
+				("public key", fingerprint.ok())
+			}
+			SECRET_EXT => {
+				let contents = read_file(&path)?;
+				if P256Pair::is_encrypted_master_seed(&contents) {
+					("encrypted master seed", None)
+				} else {
+					let fingerprint = P256Pair::from_hex_file(&path)
+						.map(|pair| public_key_fingerprint(&pair.public_key()));
+					if fingerprint.is_err() {
+						warnings.push("not a valid master seed".to_string());
+					}
+					("master seed", fingerprint.ok())
+				}
+			}
+			SHARE_EXT => ("encrypted share", None),
+			_ => continue,
+		};
+
+		if ext != PUB_EXT {
+			let mode = fs::metadata(&path)
+				.map_err(|e| Error::FailedToRead {
+					path: path.display().to_string(),
+					error: e.to_string(),
+				})?
+				.permissions()
+				.mode();
+			if mode & 0o077 != 0 {
+				warnings.push(format!(
+					"other users can access this file (mode {:o}); run `chmod \
+					600 {}`",
+					mode & 0o777,
+					path.display()
+				));
+			}
+		}
+
+		listings.push(KeyListing {
+			file_name: path
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+				.unwrap_or_default(),
+			kind,
+			alias,
+			namespace,
+			fingerprint,
+			warnings,
+		});
+	}
+
+	Ok(listings)
+}
+
+/// Hex encoded sha256 of the public key.
+fn public_key_fingerprint(public: &P256Public) -> String {
+	qos_hex::encode(&sha_256(&public.to_bytes()))
+}
+
+/// Verify a signature over the payload with the public key.
+pub fn p256_verify<P: AsRef<Path>>(
+	payload_path: P,
+	signature_path: P,
+	pub_path: P,
+) -> Result<(), Error> {
+	let payload = fs::read(payload_path)?;
+	let public = P256Public::from_hex_file(pub_path)?;
+	let signature_bytes = {
+		let signature_hex = fs::read_to_string(signature_path)?;
+
+		let signature_hex = signature_hex.trim();
+		qos_hex::decode(signature_hex)?
+	};
+
+	if let Err(e) = public.verify(&payload, &signature_bytes) {
+		log::error("client", format_args!("Signature not valid: {e:?}"));
+		Err(e.into())
+	} else {
+		log::info("client", "Valid signature!");
+		Ok(())
+	}
+}
+
+/// Sign the payload and write the signature.
+pub fn p256_sign<P: AsRef<Path>>(
+	payload_path: &str,
+	signature_path: P,
+	mut pair: PairOrYubi,
+) -> Result<(), Error> {
+	let payload = fs::read(payload_path)?;
+
+	let signature = {
+		let signature = pair.sign(&payload)?;
+		qos_hex::encode(&signature)
+	};
+	write_with_msg(
+		signature_path.as_ref(),
+		signature.as_bytes(),
+		"p256 signature",
+	)?;
+
+	Ok(())
+}
+
+/// Encrypt the plaintext to the public key.
+pub fn p256_asymmetric_encrypt<P: AsRef<Path>>(
+	plaintext_path: P,
+	ciphertext_path: P,
+	pub_path: P,
+) -> Result<(), Error> {
+	let public = P256Public::from_hex_file(pub_path)?;
+	let plaintext = std::fs::read(plaintext_path.as_ref())?;
+
+	let ciphertext = public.encrypt(&plaintext)?;
+
+	write_with_msg(ciphertext_path.as_ref(), &ciphertext, "Ciphertext")?;
+
+	Ok(())
+}
+
+/// Decrypt the ciphertext with the master seed or yubikey.
+pub fn p256_asymmetric_decrypt<P: AsRef<Path>>(
+	plaintext_path: P,
+	ciphertext_path: P,
+	mut pair: PairOrYubi,
+	output_hex: bool,
+) -> Result<(), Error> {
+	let ciphertext = std::fs::read(ciphertext_path.as_ref())?;
+
+	let plaintext = pair.decrypt(&ciphertext)?;
+	let file_contents = if output_hex {
+		Zeroizing::new(qos_hex::encode_to_vec(&plaintext))
+	} else {
+		plaintext
+	};
+
+	write_with_msg(plaintext_path.as_ref(), &file_contents, "Plaintext")?;
+
+	Ok(())
+}
+
+/// Print the contents of a file as the given display type.
+pub fn display<P: AsRef<Path>>(
+	display_type: &DisplayType,
+	file_path: P,
+	json: bool,
+) -> Result<(), Error> {
+	let bytes =
+		fs::read(file_path).map_err(|e| Error::ReadShare(e.to_string()))?;
+	match *display_type {
+		DisplayType::Manifest => {
+			let decoded = Manifest::try_from_slice(&bytes)?;
+			if json {
+				println!("{}", serde_json::to_string(&decoded)?);
+			} else {
+				println!("{decoded:#?}");
+			}
+		}
+		DisplayType::ManifestEnvelope => {
+			let decoded = ManifestEnvelope::try_from_slice(&bytes)?;
+			if json {
+				println!("{}", serde_json::to_string(&decoded)?);
+			} else {
+				println!("{decoded:#?}");
+			}
+		}
+		DisplayType::GenesisOutput => {
+			let decoded = GenesisOutput::try_from_slice(&bytes)?;
+			println!("{decoded:#?}");
+		}
+	};
+	Ok(())
+}
+
+/// Print a summary of a manifest or manifest envelope as JSON, verifying
+/// each approval.
+pub fn display_manifest<P: AsRef<Path>>(file_path: P) -> Result<(), Error> {
+	let bytes = fs::read(file_path).map_err(Error::FailedToReadManifestFile)?;
+	let summary = manifest_summary(&bytes)?;
+	println!("{}", serde_json::to_string_pretty(&summary)?);
+	Ok(())
+}
+
+/// Summarize a borsh encoded manifest or manifest envelope: the manifest, the
+/// hash members sign to approve it and, for an envelope, whether each
+/// approval verifies.
+fn manifest_summary(bytes: &[u8]) -> Result<serde_json::Value, Error> {
+	let (manifest, manifest_set_approvals, share_set_approvals) =
+		if let Ok(envelope) = ManifestEnvelope::try_from_slice(bytes) {
+			(
+				envelope.manifest,
+				envelope.manifest_set_approvals,
+				envelope.share_set_approvals,
+			)
+		} else {
+			let manifest = Manifest::try_from_slice(bytes)
+				.map_err(|_| Error::FileDidNotHaveValidManifest)?;
+			(manifest, vec![], vec![])
+		};
+
+	let manifest_hash = manifest.qos_hash();
+	let approval_statuses = |approvals: &[Approval],
+	                         members: &[QuorumMember]|
+	 -> Vec<serde_json::Value> {
+		approvals
+			.iter()
+			.map(|approval| {
+				let valid_signature =
+					P256Public::from_bytes(&approval.member.pub_key)
+						.and_then(|public| {
+							public.verify(&manifest_hash, &approval.signature)
+						})
+						.is_ok();
+				serde_json::json!({
+					"alias": approval.member.alias,
+					"pubKey": qos_hex::encode(&approval.member.pub_key),
+					"validSignature": valid_signature,
+					"member": members.contains(&approval.member),
+				})
+			})
+			.collect()
+	};
+
+	Ok(serde_json::json!({
+		"qosHash": qos_hex::encode(&manifest_hash),
+		"manifestSetApprovals": approval_statuses(
+			&manifest_set_approvals,
+			&manifest.manifest_set.members,
+		),
+		"shareSetApprovals": approval_statuses(
+			&share_set_approvals,
+			&manifest.share_set.members,
+		),
+		"manifest": manifest,
+	}))
+}
+
+/// Show a QR code with the `artifact` from the file at `file_path`: the qos
+/// hash of a manifest, an approval, or the digest of an attestation doc. The
+/// QR code is also written as an SVG image to `svg_path`, if given.
+pub fn qr_export<P: AsRef<Path>>(
+	artifact: QrArtifact,
+	file_path: P,
+	svg_path: Option<P>,
+) -> Result<(), Error> {
+	let bytes = match artifact {
+		QrArtifact::ManifestHash => {
+			read_manifest(&file_path)?.qos_hash().to_vec()
+		}
+		QrArtifact::Approval => {
+			let bytes = read_file(&file_path)?;
+			Approval::try_from_slice(&bytes).map_err(|_| {
+				invalid_file(file_path.as_ref(), "not an approval")
+			})?;
+			bytes
+		}
+		QrArtifact::AttestationDigest => {
+			sha_256(&read_file(&file_path)?).to_vec()
+		}
+	};
+
+	let payload = qr::encode(artifact, &bytes);
+	println!("{}", qr::to_terminal(&payload)?);
+	println!("{payload}");
+
+	if let Some(svg_path) = svg_path {
+		write_with_msg(
+			svg_path.as_ref(),
+			qr::to_svg(&payload)?.as_bytes(),
+			"QR code",
+		)?;
+	}
+
+	Ok(())
+}
+
+/// Write the artifact in the scanned text of a QR code to `output_path`.
+/// Approvals are written borsh encoded, like `approve-manifest` writes them;
+/// hashes and digests are written hex encoded.
+pub fn qr_import<P: AsRef<Path>>(
+	payload: &str,
+	output_path: P,
+) -> Result<(), Error> {
+	let (artifact, bytes) = qr::decode(payload)?;
+
+	let contents = match artifact {
+		QrArtifact::Approval => {
+			let approval = Approval::try_from_slice(&bytes)
+				.map_err(|_| QrError::InvalidPayload(payload.to_string()))?;
+			log::info(
+				"client",
+				format_args!("Approval by {}", approval.member.alias),
+			);
+			bytes
+		}
+		QrArtifact::ManifestHash | QrArtifact::AttestationDigest => {
+			let hex = qos_hex::encode(&bytes);
+			log::info("client", format_args!("{}: {hex}", artifact.name()));
+			hex.into_bytes()
+		}
+	};
+
+	write_with_msg(output_path.as_ref(), &contents, artifact.name())
+}
+
+/// Write a printable paper backup of the encrypted share (`.share`) or
+/// personal key (`.secret`) at `file_path` to `output_path`.
+pub fn paper_backup_export<P: AsRef<Path>>(
+	file_path: P,
+	output_path: P,
+) -> Result<(), Error> {
+	let file_path = file_path.as_ref();
+	let kind = match split_file_name(file_path)?.last().map(String::as_str) {
+		Some(SHARE_EXT) => PaperKind::Share,
+		Some(SECRET_EXT) => PaperKind::PersonalKey,
+		_ => {
+			return Err(invalid_file(
+				file_path,
+				"expected a .share or .secret file",
+			))
+		}
+	};
+	let backup = PaperBackup {
+		kind,
+		name: file_path
+			.file_name()
+			.map(|name| name.to_string_lossy().into_owned())
+			.unwrap_or_default(),
+		contents: read_file(file_path)?,
+	};
+
+	write_with_msg(
+		output_path.as_ref(),
+		paper::encode(&backup).as_bytes(),
+		"Paper backup",
+	)
+}
+
+/// Restore the share or personal key from a paper backup made with
+/// `paper-backup-export` to `output_path`.
+pub fn paper_backup_import<P: AsRef<Path>>(
+	file_path: P,
+	output_path: P,
+) -> Result<(), Error> {
+	let file_path = file_path.as_ref();
+	let doc =
+		fs::read_to_string(file_path).map_err(|e| Error::FailedToRead {
+			path: file_path.display().to_string(),
+			error: e.to_string(),
+		})?;
+	let backup = paper::decode(&doc)?;
+
+	log::info(
+		"client",
+		format_args!("Paper backup of {} {}", backup.kind.name(), backup.name),
+	);
+	write_with_msg(output_path.as_ref(), &backup.contents, backup.kind.name())
+}
+
+/// Boot an enclave with a throwaway quorum key, manifest set and share set
+/// for development, wait for the pivot to start and, if `app_echo_hex` is
+/// given, check that the app echoes it back. Never use this in production.
+///
+/// Shares are encrypted to the key at `unsafe_eph_path_override` if given.
+/// Otherwise a mock enclave is asked for its Ephemeral Key, falling back to
+/// the key in the attestation document.
+#[allow(clippy::too_many_lines)]
+pub fn dangerous_dev_boot<P: AsRef<Path>>(
+	uri: &str,
+	pivot_path: P,
+	restart: RestartPolicy,
+	args: Vec<String>,
+	unsafe_eph_path_override: Option<String>,
+	app_echo_hex: Option<&str>,
+) -> Result<(), Error> {
+	// Generate a quorum key
+	let quorum_pair = P256Pair::generate()?;
+	let quorum_public_der = quorum_pair.public_key().to_bytes();
+	let member = QuorumMember {
+		alias: DANGEROUS_DEV_BOOT_MEMBER.to_string(),
+		pub_key: quorum_public_der.clone(),
+	};
+
+	// Shard it with N=2, K=2
+	let (shares, _) =
+		qos_crypto::shamir::shares_generate(quorum_pair.to_master_seed(), 2, 2)
+			.map_err(Error::Shamir)?;
+
+	// Read in the pivot
+	let pivot = fs::read(&pivot_path).map_err(Error::FailedToReadPivot)?;
+
+	let mock_pcr = vec![0; 48];
+	// Create a manifest with manifest set of 1
+	// everything below is hardcoded except pivot config
+	let manifest = Manifest {
+		namespace: Namespace {
+			name: DANGEROUS_DEV_BOOT_NAMESPACE.to_string(),
+			nonce: u32::MAX,
+			quorum_key: quorum_public_der,
+		},
+		enclave: NitroConfig {
+			pcr0: mock_pcr.clone(),
+			pcr1: mock_pcr.clone(),
+			pcr2: mock_pcr.clone(),
+			pcr3: mock_pcr,
+			qos_commit: "mock-qos-commit-ref".to_string(),
+			aws_root_certificate: aws_root_cert()?,
+		},
+		pivot: PivotConfig {
+			hash: sha_256(&pivot),
+			restart,
+			args,
+			app_config: vec![],
+			egress: vec![],
+		},
+		manifest_set: ManifestSet {
+			threshold: 1,
+			// The only member is the quorum member
+			members: vec![member.clone()],
+		},
+		share_set: ShareSet {
+			threshold: 2,
+			// The only member is the quorum member
+			members: vec![member.clone()],
+			share_commitments: vec![],
+		},
+		patch_set: PatchSet { threshold: 0, members: vec![] },
+		crypto: CryptoConfig::new(false),
+	};
+
+	// Create and post the boot standard instruction
+	let manifest_envelope = {
+		let signature = quorum_pair.sign(&manifest.qos_hash())?;
+		Box::new(ManifestEnvelope {
+			manifest,
+			manifest_set_approvals: vec![Approval { signature, member }],
+			share_set_approvals: vec![],
+		})
+	};
+
+	let req = ProtocolMsg::BootStandardRequest {
+		manifest_envelope: manifest_envelope.clone(),
+		pivot,
+	};
+	let attestation_doc = match request::post(uri, &req)? {
+		ProtocolMsg::BootStandardResponse {
+			nsm_response: NsmResponse::Attestation { document },
+		} => extract_attestation_doc(&document, true, None)?,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	// Use the override, the mock ephemeral key or the attested ephemeral key
+	let eph_pub: P256Public = if let Some(eph_path) = unsafe_eph_path_override {
+		P256Pair::from_hex_file(eph_path)?.public_key()
+	} else if let Some(eph_pub) = mock_ephemeral_key(uri)? {
+		eph_pub
+	} else {
+		P256Public::from_bytes(
+			&attestation_doc.public_key.ok_or(Error::InvalidEphemeralKey)?,
+		)
+		.map_err(|_| Error::InvalidEphemeralKey)?
+	};
+
+	// Create ShareSet approval
+	let approval = Approval {
+		signature: quorum_pair.sign(&manifest_envelope.manifest.qos_hash())?,
+		member: QuorumMember {
+			pub_key: quorum_pair.public_key().to_bytes(),
+			alias: DANGEROUS_DEV_BOOT_MEMBER.to_string(),
+		},
+	};
+
+	// Post the share a first time. It won't work (1/2 shares aren't enough)
+	let req1 = ProtocolMsg::ProvisionRequest {
+		share: eph_pub.encrypt(&shares[0])?,
+		approval: approval.clone(),
+	};
+	match request::post(uri, &req1)? {
+		ProtocolMsg::ProvisionResponse { reconstructed: false } => {}
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	}
+
+	// Post the second share; expected to reconstruct.
+	let req2 = ProtocolMsg::ProvisionRequest {
+		share: eph_pub.encrypt(&shares[1])?,
+		approval,
+	};
+	match request::post(uri, &req2)? {
+		ProtocolMsg::ProvisionResponse { reconstructed: true } => {}
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	}
+
+	log::info("client", "Enclave is provisioned!");
+
+	let status = wait_for_pivot(uri)?;
+	log::info("client", format_args!("Pivot is ready: {status:?}"));
+
+	if let Some(hex) = app_echo_hex {
+		app_echo(uri, hex)?;
+		log::info("client", format_args!("App echoed {hex}"));
+	}
+
+	Ok(())
+}
+
+/// Run genesis, manifest generation, approvals, boot and provisioning with
+/// `members` simulated members and a mock enclave, all in this process,
+/// checking every artifact along the way. The enclave echoes requests to its
+/// app. Nothing is written to disk or sent over the network.
+#[cfg(feature = "simulate")]
+pub fn simulate_ceremony(
+	members: usize,
+	threshold: usize,
+) -> Result<(), Error> {
+	struct Echo;
+	impl qos_core::server::RequestProcessor for Echo {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	let threshold = u32::try_from(threshold)
+		.map_err(|_| Error::InvalidArgs("`--threshold` is too large"))?;
+	let output = qos_test_primitives::Ceremony::new(members, threshold)
+		.run(Echo)
+		.map_err(Error::SimulatedCeremony)?;
+
+	let manifest = &output.manifest_envelope.manifest;
+	log::info(
+		"client",
+		format_args!("Simulated a {threshold} of {members} ceremony"),
+	);
+	log::info(
+		"client",
+		format_args!(
+			"Quorum key: {}",
+			qos_hex::encode(&output.genesis_output.quorum_key)
+		),
+	);
+	log::info(
+		"client",
+		format_args!(
+			"Manifest hash: {}",
+			qos_hex::encode(&manifest.qos_hash())
+		),
+	);
+	for approval in &output.manifest_envelope.manifest_set_approvals {
+		log::info(
+			"client",
+			format_args!("Manifest approved by {}", approval.member.alias),
+		);
+	}
+	for approval in &output.manifest_envelope.share_set_approvals {
+		log::info(
+			"client",
+			format_args!("Share posted by {}", approval.member.alias),
+		);
+	}
+	log::info("client", "Enclave is provisioned, every artifact verified");
+
+	Ok(())
+}
+
+/// Ask the enclave for its Ephemeral Key. Returns `None` if the enclave was
+/// not built with the "mock" feature.
+fn mock_ephemeral_key(uri: &str) -> Result<Option<P256Public>, Error> {
+	match request::post(uri, &ProtocolMsg::MockEphemeralKeyRequest)? {
+		ProtocolMsg::MockEphemeralKeyResponse { ephemeral_public_key } => {
+			P256Public::from_bytes(&ephemeral_public_key)
+				.map(Some)
+				.map_err(|_| Error::InvalidEphemeralKey)
+		}
+		ProtocolMsg::ProtocolErrorResponse(
+			ProtocolError::MockFeatureDisabled,
+		) => Ok(None),
+		r => Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}"))),
+	}
+}
+
+/// Poll the enclave status until the pivot is running or has exited
+/// successfully.
+fn wait_for_pivot(uri: &str) -> Result<PivotStatus, Error> {
+	let deadline = Instant::now() + DANGEROUS_DEV_BOOT_PIVOT_TIMEOUT;
+	loop {
+		let status =
+			match request::post(uri, &ProtocolMsg::EnclaveStatusRequest)? {
+				ProtocolMsg::EnclaveStatusResponse(status) => status.pivot,
+				r => {
+					return Err(Error::UnexpectedProtocolMsgResponse(format!(
+						"{r:?}"
+					)))
+				}
+			};
+
+		match status {
+			PivotStatus::Running { .. }
+			| PivotStatus::Exited { code: Some(0), .. } => return Ok(status),
+			PivotStatus::Exited { .. } => {
+				return Err(Error::PivotNotReady(status))
+			}
+			PivotStatus::NotStarted if Instant::now() >= deadline => {
+				return Err(Error::PivotNotReady(status))
+			}
+			PivotStatus::NotStarted => {
+				thread::sleep(DANGEROUS_DEV_BOOT_PIVOT_POLL);
+			}
+		}
+	}
+}
+
+/// Send hex encoded `data` to the app and check that it responds with the
+/// same bytes.
+fn app_echo(uri: &str, hex: &str) -> Result<(), Error> {
+	let sent = qos_hex::decode(hex)?;
+	let req = ProtocolMsg::ProxyRequest { data: sent.clone() };
+	let data = match request::post(uri, &req)? {
+		ProtocolMsg::ProxyResponse { data } => data,
+		r => {
+			return Err(Error::UnexpectedProtocolMsgResponse(format!("{r:?}")))
+		}
+	};
+
+	if data == sent {
+		Ok(())
+	} else {
+		Err(Error::AppEchoMismatch {
+			sent: qos_hex::encode(&sent),
+			received: qos_hex::encode(&data),
+		})
+	}
+}
+
+/// Split a secret into `total_shares` shares with a reconstruction
+/// `threshold`.
+pub fn shamir_split(
+	secret_path: String,
+	total_shares: usize,
+	threshold: usize,
+	output_dir: &str,
+) -> Result<(), Error> {
+	let secret = fs::read(&secret_path).map_err(|e| Error::FailedToRead {
+		path: secret_path,
+		error: e.to_string(),
+	})?;
+	let (shares, _) =
+		qos_crypto::shamir::shares_generate(&secret, total_shares, threshold)
+			.map_err(Error::Shamir)?;
+
+	for (i, share) in shares.iter().enumerate() {
+		let file_name = format!("{}.share", i + 1);
+		let file_path = PathBuf::from(&output_dir).join(&file_name);
+		write_with_msg(&file_path, share, &file_name)?;
+	}
+
+	Ok(())
+}
+
+/// Reconstruct a secret from shares.
+pub fn shamir_reconstruct(
+	shares: Vec<String>,
+	output_path: &str,
+) -> Result<(), Error> {
+	let shares = shares
+		.into_iter()
+		.map(|p| {
+			fs::read(&p).map_err(|e| Error::FailedToRead {
+				path: p,
+				error: e.to_string(),
+			})
+		})
+		.collect::<Result<Vec<Vec<u8>>, Error>>()
+		.map(Zeroizing::new)?;
+
+	let secret = qos_crypto::shamir::shares_reconstruct(&*shares)
+		.map_err(Error::Shamir)?;
+
+	write_with_msg(output_path.as_ref(), &secret, "Reconstructed secret")?;
+
+	Ok(())
+}
+
+fn find_file_paths<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, Error> {
+	let dir = dir.as_ref();
+	let read_error = |e: io::Error| Error::FailedToRead {
+		path: dir.display().to_string(),
+		error: e.to_string(),
+	};
+
+	fs::read_dir(dir)
+		.map_err(read_error)?
+		.map(|entry| entry.map(|entry| entry.path()).map_err(read_error))
+		.collect()
+}
+
+fn find_threshold<P: AsRef<Path>>(dir: P) -> Result<u32, Error> {
+	// We expect the threshold file to be named `quorum_threshold` and contain a
+	// single line with just the a base 10 number. It should live in the
+	// directory containing the keys in the set.
+
+	let paths: Vec<_> = find_file_paths(&dir)?
+		.into_iter()
+		.filter(|path| path.file_name() == Some(QUORUM_THRESHOLD_FILE.as_ref()))
+		.collect();
+	let [path] = &paths[..] else {
+		return Err(invalid_file(
+			dir.as_ref(),
+			"did not find exactly 1 threshold",
+		));
+	};
+
+	read_lines(path)?
+		.first() // First line
+		// Trim any whitespace just to be sure
+		.and_then(|line| line.trim().parse().ok())
+		.ok_or_else(|| invalid_file(path, "could not parse threshold into u32"))
+}
+
+/// Read the members of a set from the `<alias>.pub` files in `dir`.
+fn find_members<P: AsRef<Path>>(dir: P) -> Result<Vec<QuorumMember>, Error> {
+	let mut members = vec![];
+	for path in find_file_paths(dir)? {
+		let mut file_name = split_file_name(&path)?;
+		if file_name.last().map_or(true, |s| s.as_str() != PUB_EXT) {
+			continue;
+		};
+
+		let public = P256Public::from_hex_file(&path)
+			.map_err(|e| invalid_file(&path, format!("{e:?}")))?;
+		members.push(QuorumMember {
+			alias: mem::take(&mut file_name[0]),
+			pub_key: public.to_bytes(),
+		});
+	}
+
+	// We want to try and build the same manifest regardless of the OS.
+	members.sort();
+
+	Ok(members)
+}
+
+fn get_share_set<P: AsRef<Path>>(dir: P) -> Result<ShareSet, Error> {
+	Ok(ShareSet {
+		members: find_members(&dir)?,
+		threshold: find_threshold(dir)?,
+		share_commitments: vec![],
+	})
+}
+
+fn get_manifest_set<P: AsRef<Path>>(dir: P) -> Result<ManifestSet, Error> {
+	Ok(ManifestSet {
+		members: find_members(&dir)?,
+		threshold: find_threshold(dir)?,
+	})
+}
+
+fn get_patch_set<P: AsRef<Path>>(dir: P) -> Result<PatchSet, Error> {
+	let mut members: Vec<_> = find_members(&dir)?
+		.into_iter()
+		.map(|member| MemberPubKey { pub_key: member.pub_key })
+		.collect();
+
+	// We want to try and build the same manifest regardless of the OS.
+	members.sort();
+
+	Ok(PatchSet { members, threshold: find_threshold(dir)? })
+}
+
+fn get_genesis_set<P: AsRef<Path>>(dir: P) -> Result<GenesisSet, Error> {
+	Ok(GenesisSet {
+		members: find_members(&dir)?,
+		threshold: find_threshold(dir)?,
+	})
+}
+
+fn find_approvals<P: AsRef<Path>>(
+	boot_dir: P,
+	manifest: &Manifest,
+) -> Result<Vec<Approval>, Error> {
+	let mut approvals = vec![];
+	for path in find_file_paths(&boot_dir)? {
+		let file_name = split_file_name(&path)?;
+		// Only look at files with the approval extension
+		if file_name.last().map_or(true, |s| s.as_str() != APPROVAL_EXT) {
+			continue;
+		};
+
+		let approval =
+			Approval::try_from_slice(&read_file(&path)?).map_err(|_| {
+				invalid_file(&path, "failed to deserialize approval")
+			})?;
+
+		if !manifest.manifest_set.members.contains(&approval.member) {
+			return Err(invalid_file(
+				&path,
+				format!(
+					"approval from member ({:?}) not included in the Manifest Set",
+					approval.member.alias
+				),
+			));
+		}
+
+		let pub_key = P256Public::from_bytes(&approval.member.pub_key)
+			.map_err(|_| invalid_file(&path, "failed to interpret pub key"))?;
+		if pub_key.verify(&manifest.qos_hash(), &approval.signature).is_err() {
+			return Err(invalid_file(
+				&path,
+				"approval signature could not be verified against manifest",
+			));
+		}
+
+		approvals.push(approval);
+	}
+
+	Ok(approvals)
+}
+
+fn read_manifest<P: AsRef<Path>>(file: P) -> Result<Manifest, Error> {
+	let buf = fs::read(file).map_err(Error::FailedToReadManifestFile)?;
+	Manifest::try_from_slice(&buf)
+		.map_err(|_| Error::FileDidNotHaveValidManifest)
+}
+
+fn read_genesis_output<P: AsRef<Path>>(
+	path: P,
+) -> Result<GenesisOutput, Error> {
+	let path = path.as_ref();
+	GenesisOutput::try_from_slice(&read_file(path)?).map_err(|_| {
+		invalid_file(
+			path,
+			"not a genesis output - check that qos_client and qos_core \
+			version line up",
+		)
+	})
+}
+
+fn read_attestation_doc<P: AsRef<Path>>(
+	path: P,
+	unsafe_skip_attestation: bool,
+) -> Result<AttestationDoc, Error> {
+	let cose_sign1_der =
+		fs::read(path).map_err(Error::FailedToReadAttestationDoc)?;
+
+	extract_attestation_doc(
+		cose_sign1_der.as_ref(),
+		unsafe_skip_attestation,
+		None,
+	)
+}
+
+fn read_manifest_envelope<P: AsRef<Path>>(
+	file: P,
+) -> Result<ManifestEnvelope, Error> {
+	let buf =
+		fs::read(file).map_err(Error::FailedToReadManifestEnvelopeFile)?;
+	ManifestEnvelope::try_from_slice(&buf)
+		.map_err(|_| Error::FileDidNotHaveValidManifestEnvelope)
+}
+
+fn read_attestation_approval<P: AsRef<Path>>(
+	path: P,
+) -> Result<Approval, Error> {
+	let manifest_envelope =
+		fs::read(path).map_err(Error::FailedToReadAttestationApproval)?;
+
+	Approval::try_from_slice(&manifest_envelope)
+		.map_err(|_| Error::FileDidNotHaveValidAttestationApproval)
+}
+
+fn lines_to_entries<P: AsRef<Path>>(
+	path: P,
+) -> Result<Vec<[String; 2]>, Error> {
+	let path = path.as_ref();
+	read_lines(path)?
+		.into_iter()
+		.map(|line| {
+			let entry: Vec<_> = line.split(' ').map(String::from).collect();
+			entry.try_into().map_err(|_| {
+				invalid_file(path, "not exactly 2 words in line of file")
+			})
+		})
+		.collect()
+}
+
+struct QosPcrs {
+	pcr0: Vec<u8>,
+	pcr1: Vec<u8>,
+	pcr2: Vec<u8>,
+}
+
+fn get_entry(
+	path: &Path,
+	entries: &[[String; 2]],
+	index: usize,
+	expected_label: &str,
+) -> Result<Vec<u8>, Error> {
+	match entries.get(index) {
+		Some([value, label]) if label == expected_label => {
+			hex::decode_pcr48(value).map(Vec::from).map_err(|e| {
+				invalid_file(path, format!("invalid {expected_label}: {e}"))
+			})
+		}
+		_ => Err(invalid_file(
+			path,
+			format!("expected {expected_label} on line {}", index + 1),
+		)),
+	}
+}
+
+fn extract_qos_pcrs<P: AsRef<Path>>(
+	qos_release_dir_path: P,
+) -> Result<QosPcrs, Error> {
+	let pcr_path = PathBuf::from(qos_release_dir_path.as_ref()).join(PCRS_PATH);
+
+	let entries = lines_to_entries(&pcr_path)?;
+	Ok(QosPcrs {
+		pcr0: get_entry(&pcr_path, &entries, 0, "PCR0")?,
+		pcr1: get_entry(&pcr_path, &entries, 1, "PCR1")?,
+		pcr2: get_entry(&pcr_path, &entries, 2, "PCR2")?,
+	})
+}
+
+fn find_pcr3<P: AsRef<Path>>(file_path: P) -> Result<String, Error> {
+	let path = file_path.as_ref();
+	read_lines(path)?
+		.into_iter()
+		.next()
+		.ok_or_else(|| invalid_file(path, "missing the pcr3 preimage"))
+}
+
+fn extract_pcr3<P: AsRef<Path>>(file_path: P) -> Result<Vec<u8>, Error> {
+	Ok(pcr3_from_role_arn(&find_pcr3(file_path)?))
+}
+
+fn pcr3_from_role_arn(role_arn: &str) -> Vec<u8> {
+	let preimage = {
+		// Pad preimage with 48 bytes
+		let mut preimage = [0u8; 48].to_vec();
+		preimage.extend_from_slice(role_arn.as_bytes());
+		preimage
+	};
+
+	sha_384(&preimage).to_vec()
+}
+
+fn extract_pivot_hash<P: AsRef<Path>>(file_path: P) -> Result<[u8; 32], Error> {
+	let path = file_path.as_ref();
+	let line = read_lines(path)?.into_iter().next().unwrap_or_default();
+	hex::decode_hash256(&line).map_err(|e| {
+		invalid_file(path, format!("first line is not a 256 bit hash: {e}"))
+	})
+}
+
+/// Extract the attestation doc from a COSE Sign1 structure. Validates the cert
+/// chain and basic semantics.
+pub fn extract_attestation_doc(
+	cose_sign1_der: &[u8],
+	unsafe_skip_attestation: bool,
+	// in seconds since unix epoch
+	validation_time_override: Option<u64>,
+) -> Result<AttestationDoc, Error> {
+	let attestation_doc = if unsafe_skip_attestation {
+		unsafe_attestation_doc_from_der(cose_sign1_der)?
+	} else {
+		let validation_time = if let Some(t) = validation_time_override {
+			t
+		} else {
+			std::time::SystemTime::now()
+				.duration_since(std::time::UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs()
+		};
+
+		attestation_doc_from_der(
+			cose_sign1_der,
+			&aws_root_cert()?,
+			validation_time,
+		)?
+	};
+
+	Ok(attestation_doc)
+}
+
+/// Get the file name from a path and split on `"."`.
+fn split_file_name(p: &Path) -> Result<Vec<String>, Error> {
+	let file_name = p
+		.file_name()
+		.map(std::ffi::OsStr::to_string_lossy)
+		.ok_or_else(|| invalid_file(p, "the path does not name a file"))?;
+	Ok(file_name.split('.').map(String::from).collect())
+}
+
+/// Write `buf` to the file specified by `path` and write to stdout that
+/// `item_name` was written to `path`.
+fn write_with_msg(
+	path: &Path,
+	buf: &[u8],
+	item_name: &str,
+) -> Result<(), Error> {
+	fs::write(path, buf).map_err(|e| Error::FailedToWrite {
+		path: path.display().to_string(),
+		error: e.to_string(),
+	})?;
+	log::info(
+		"client",
+		format_args!("{item_name} written to: {}", path.display()),
+	);
+	Ok(())
+}
+
+/// Read the file at `path`.
+fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, Error> {
+	let path = path.as_ref();
+	fs::read(path).map_err(|e| Error::FailedToRead {
+		path: path.display().to_string(),
+		error: e.to_string(),
+	})
+}
+
+/// Read the lines of the file at `path`.
+fn read_lines<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error> {
+	let path = path.as_ref();
+	let contents =
+		fs::read_to_string(path).map_err(|e| Error::FailedToRead {
+			path: path.display().to_string(),
+			error: e.to_string(),
+		})?;
+	Ok(contents.lines().map(String::from).collect())
+}
+
+fn invalid_file(path: &Path, error: impl Into<String>) -> Error {
+	Error::InvalidFile { path: path.display().to_string(), error: error.into() }
+}
+
+struct Prompter<R, W> {
+	reader: R,
+	writer: W,
+}
+
+impl<R, W> Prompter<R, W>
+where
+	R: BufRead,
+	W: Write,
+{
+	fn prompt(&mut self, question: &str) -> io::Result<String> {
+		writeln!(&mut self.writer, "{question}")?;
+		let mut s = String::new();
+		let _amt = self.reader.read_line(&mut s)?;
+		Ok(s.trim().to_string())
+	}
+
+	/// Whether the answer is yes. An answer that cannot be read is a no.
+	fn prompt_is_yes(&mut self, question: &str) -> bool {
+		self.prompt(question).is_ok_and(|answer| answer == "yes")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::vec;
+
+	use qos_core::{
+		audit::AuditEntry,
+		protocol::{
+			services::boot::{
+				Approval, CryptoConfig, Manifest, ManifestEnvelope,
+				ManifestSet, MemberPubKey, Namespace, NitroConfig, PatchSet,
+				PivotConfig, QuorumMember, RestartPolicy, ShareSet,
+			},
+			QosHash,
+		},
+	};
+	use qos_nsm::nitro::{cert_from_pem, AWS_ROOT_CERT_PEM};
+	use qos_p256::{P256Error, P256Pair, P256Public};
+
+	use std::{
+		fs,
+		path::{Path, PathBuf},
+	};
+
+	use borsh::BorshDeserialize;
+	use qos_test_primitives::PathWrapper;
+
+	use super::{
+		approve_manifest_human_verifications,
+		approve_manifest_programmatic_verifications,
+		approve_manifest_typed_confirmation, audit_log_report, enclave_checks,
+		find_previous_manifest, get_share_set, key_listings, manifest_summary,
+		paper_backup_export, paper_backup_import,
+		proxy_re_encrypt_share_human_verifications,
+		proxy_re_encrypt_share_programmatic_verifications, recover_file_key,
+		request, share_set_progress, ApprovalsReport, DisplayType, Error,
+		ExitCode, PaperError, PivotHashSource, Prompter, QUORUM_THRESHOLD_FILE,
+	};
+
+	struct Setup {
+		manifest: Manifest,
+		manifest_set: ManifestSet,
+		share_set: ShareSet,
+		nitro_config: NitroConfig,
+		pivot_hash: Vec<u8>,
+		quorum_key: P256Public,
+		manifest_envelope: ManifestEnvelope,
+		patch_set: PatchSet,
+	}
+	fn setup() -> Setup {
+		let pairs: Vec<_> =
+			(0..3).map(|_| P256Pair::generate().unwrap()).collect();
+
+		let members: Vec<_> = pairs
+			.iter()
+			.enumerate()
+			.map(|(i, pair)| QuorumMember {
+				pub_key: pair.public_key().to_bytes(),
+				alias: i.to_string(),
+			})
+			.collect();
+		let patch_members = members
+			.iter()
+			.cloned()
+			.map(|m| MemberPubKey { pub_key: m.pub_key })
+			.collect();
+
+		let manifest_set =
+			ManifestSet { members: members.clone(), threshold: 2 };
+		let share_set = ShareSet {
+			members: members.clone(),
+			threshold: 2,
+			share_commitments: vec![],
+		};
+		let patch_set = PatchSet { members: patch_members, threshold: 2 };
+		let nitro_config = NitroConfig {
+			pcr0: vec![1; 48],
+			pcr1: vec![2; 48],
+			pcr2: vec![3; 48],
+			pcr3: vec![4; 48],
+			qos_commit: "good-qos-commit".to_string(),
+			aws_root_certificate: cert_from_pem(AWS_ROOT_CERT_PEM).unwrap(),
+		};
+		let pivot_hash = vec![5; 32];
+		let quorum_key: P256Public = P256Pair::generate().unwrap().public_key();
+
+		let manifest = Manifest {
+			namespace: Namespace {
+				name: "test-namespace".to_string(),
+				nonce: 2,
+				quorum_key: quorum_key.to_bytes(),
+			},
+			pivot: PivotConfig {
+				hash: pivot_hash.clone().try_into().unwrap(),
+				restart: RestartPolicy::Never,
+				args: ["--option1", "argument"]
+					.into_iter()
+					.map(String::from)
+					.collect(),
+				app_config: vec![],
+				egress: vec![],
+			},
+			manifest_set: manifest_set.clone(),
+			share_set: share_set.clone(),
+			patch_set: patch_set.clone(),
+			enclave: nitro_config.clone(),
+			crypto: CryptoConfig::new(false),
+		};
+
+		let manifest_envelope = ManifestEnvelope {
+			manifest: manifest.clone(),
+			manifest_set_approvals: std::iter::zip(
+				pairs[..2].iter(),
+				members.iter(),
+			)
+			.map(|(pair, member)| Approval {
+				signature: pair.sign(&manifest.qos_hash()).unwrap(),
+				member: member.clone(),
+			})
+			.collect(),
+			share_set_approvals: vec![],
+		};
+
+		Setup {
+			manifest,
+			manifest_set,
+			share_set,
+			nitro_config,
+			pivot_hash,
+			quorum_key,
+			manifest_envelope,
+			patch_set,
+		}
+	}
+
+	#[test]
+	fn manifest_summary_verifies_each_approval() {
+		let Setup { manifest, mut manifest_envelope, .. } = setup();
+		manifest_envelope.manifest_set_approvals[1].signature = vec![0; 64];
+
+		let summary =
+			manifest_summary(&borsh::to_vec(&manifest_envelope).unwrap())
+				.unwrap();
+		assert_eq!(summary["qosHash"], qos_hex::encode(&manifest.qos_hash()));
+		assert_eq!(summary["manifest"]["namespace"]["name"], "test-namespace");
+		let approvals = summary["manifestSetApprovals"].as_array().unwrap();
+		assert_eq!(approvals.len(), 2);
+		assert_eq!(approvals[0]["alias"], "0");
+		assert_eq!(approvals[0]["validSignature"], true);
+		assert_eq!(approvals[0]["member"], true);
+		assert_eq!(approvals[1]["validSignature"], false);
+
+		let summary =
+			manifest_summary(&borsh::to_vec(&manifest).unwrap()).unwrap();
+		assert_eq!(summary["qosHash"], qos_hex::encode(&manifest.qos_hash()));
+		assert!(summary["manifestSetApprovals"].as_array().unwrap().is_empty());
+
+		assert!(matches!(
+			manifest_summary(b"not a manifest"),
+			Err(Error::FileDidNotHaveValidManifest)
+		));
+	}
+
+	#[test]
+	fn approvals_report_tells_who_signed() {
+		let Setup { manifest, manifest_envelope, .. } = setup();
+		let approvals: Vec<_> = manifest_envelope
+			.manifest_set_approvals
+			.iter()
+			.map(|approval| borsh::to_vec(approval).unwrap())
+			.collect();
+		let mut bad_signature =
+			manifest_envelope.manifest_set_approvals[1].clone();
+		bad_signature.signature = vec![0; 64];
+
+		let report = ApprovalsReport::new(
+			&manifest,
+			&[
+				("0.approval".to_string(), approvals[0].clone()),
+				(
+					"1.approval".to_string(),
+					borsh::to_vec(&bad_signature).unwrap(),
+				),
+				("dup.approval".to_string(), approvals[0].clone()),
+				("junk.approval".to_string(), b"junk".to_vec()),
+			],
+		);
+		assert_eq!(
+			report,
+			ApprovalsReport {
+				signed: vec!["0".to_string()],
+				invalid: vec![
+					(
+						"1.approval".to_string(),
+						"signature by 1 does not verify".to_string()
+					),
+					(
+						"dup.approval".to_string(),
+						"duplicate approval by 0".to_string()
+					),
+					(
+						"junk.approval".to_string(),
+						"not an approval".to_string()
+					),
+				],
+				outstanding: vec!["1".to_string(), "2".to_string()],
+				threshold_met: false,
+			}
+		);
+
+		let report = ApprovalsReport::new(
+			&manifest,
+			&[
+				("0.approval".to_string(), approvals[0].clone()),
+				("1.approval".to_string(), approvals[1].clone()),
+			],
+		);
+		assert!(report.invalid.is_empty());
+		assert_eq!(report.outstanding, vec!["2".to_string()]);
+		assert!(report.threshold_met);
+	}
+
+	#[test]
+	fn share_set_progress_lists_outstanding_members() {
+		let Setup { mut manifest_envelope, .. } = setup();
+		manifest_envelope.share_set_approvals =
+			vec![manifest_envelope.manifest_set_approvals[1].clone()];
+
+		assert_eq!(
+			share_set_progress(&manifest_envelope),
+			(vec!["1".to_string()], vec!["0".to_string(), "2".to_string()])
+		);
+	}
+
+	#[test]
+	fn invalid_set_dirs_are_errors() {
+		let dir: PathWrapper = "/tmp/qos_client_invalid_set_dirs".into();
+		let missing = Path::new(&*dir).join("missing");
+		assert!(matches!(
+			get_share_set(&missing),
+			Err(Error::FailedToRead { .. })
+		));
+
+		fs::create_dir_all(&*dir).unwrap();
+		P256Pair::generate()
+			.unwrap()
+			.public_key()
+			.to_hex_file(Path::new(&*dir).join("0.pub"))
+			.unwrap();
+		// No threshold file
+		assert!(matches!(get_share_set(&*dir), Err(Error::InvalidFile { .. })));
+
+		fs::write(Path::new(&*dir).join(QUORUM_THRESHOLD_FILE), "two").unwrap();
+		assert!(matches!(get_share_set(&*dir), Err(Error::InvalidFile { .. })));
+
+		fs::write(Path::new(&*dir).join(QUORUM_THRESHOLD_FILE), "1\n").unwrap();
+		let share_set = get_share_set(&*dir).unwrap();
+		assert_eq!(share_set.threshold, 1);
+		assert_eq!(share_set.members[0].alias, "0");
+	}
+
+	mod approve_manifest_programmatic_verifications {
+		use super::*;
+
+		#[test]
+		fn works() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				nitro_config,
+				pivot_hash,
+				quorum_key,
+				patch_set,
+				..
+			} = setup();
+
+			assert!(approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatch_manifest_set() {
+			let Setup {
+				manifest,
+				mut manifest_set,
+				share_set,
+				nitro_config,
+				pivot_hash,
+				quorum_key,
+				patch_set,
+				..
+			} = setup();
+
+			manifest_set.members.get_mut(0).unwrap().alias =
+				"vape2live".to_string();
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_share_set() {
+			let Setup {
+				manifest,
+				manifest_set,
+				mut share_set,
+				patch_set,
+				nitro_config,
+				pivot_hash,
+				quorum_key,
+				..
+			} = setup();
+
+			share_set.members.get_mut(0).unwrap().alias =
+				"vape2live".to_string();
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_patch_set() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				mut patch_set,
+				nitro_config,
+				pivot_hash,
+				quorum_key,
+				..
+			} = setup();
+
+			patch_set.members.get_mut(0).unwrap().pub_key = vec![1; 65];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_pcr0() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				mut nitro_config,
+				pivot_hash,
+				quorum_key,
+				patch_set,
+				..
+			} = setup();
+
+			nitro_config.pcr0 = vec![42; 42];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_pcr1() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				patch_set,
+				mut nitro_config,
+				pivot_hash,
+				quorum_key,
+				..
+			} = setup();
+
+			nitro_config.pcr1 = vec![42; 42];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_pcr2() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				mut nitro_config,
+				pivot_hash,
+				quorum_key,
+				patch_set,
+				..
+			} = setup();
+
+			nitro_config.pcr2 = vec![42; 42];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_pcr3() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				mut nitro_config,
+				pivot_hash,
+				quorum_key,
+				patch_set,
+				..
+			} = setup();
+
+			nitro_config.pcr3 = vec![42; 42];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_qos_commit() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				patch_set,
+				mut nitro_config,
+				pivot_hash,
+				quorum_key,
+				..
+			} = setup();
+
+			nitro_config.qos_commit = "bad qos commit".to_string();
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_pivot_hash() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				patch_set,
+				nitro_config,
+				pivot_hash: _,
+				quorum_key,
+				..
+			} = setup();
+
+			let pivot_hash = vec![42; 32];
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_quorum_key() {
+			let Setup {
+				manifest,
+				manifest_set,
+				share_set,
+				patch_set,
+				nitro_config,
+				pivot_hash,
+				..
+			} = setup();
+
+			let quorum_key: P256Public =
+				P256Pair::generate().unwrap().public_key();
+
+			assert!(!approve_manifest_programmatic_verifications(
+				&manifest,
+				&manifest_set,
+				&share_set,
+				&patch_set,
+				&nitro_config,
+				&pivot_hash,
+				&quorum_key,
+			));
+		}
+	}
+
+	#[test]
+	fn pivot_hash_source_hashes_the_binary() {
+		let dir: PathWrapper = "/tmp/qos_client_pivot_hash_source".into();
+		fs::create_dir_all(&*dir).unwrap();
+		let binary_path = Path::new(&*dir).join("pivot");
+		let hash_path = Path::new(&*dir).join("pivot_hash");
+		fs::write(&binary_path, b"pivot binary").unwrap();
+		let hash = qos_crypto::sha_256(b"pivot binary");
+		fs::write(&hash_path, qos_hex::encode(&hash)).unwrap();
+
+		assert_eq!(
+			PivotHashSource::Binary(&binary_path).pivot_hash().unwrap(),
+			hash
+		);
+		assert_eq!(
+			PivotHashSource::HashFile(&hash_path).pivot_hash().unwrap(),
+			hash
+		);
+		assert!(matches!(
+			PivotHashSource::Binary(Path::new(&*dir).join("missing"))
+				.pivot_hash(),
+			Err(Error::FailedToReadPivot(_))
+		));
+	}
+
+	mod review_manifest {
+		use super::*;
+
+		#[test]
+		fn shows_what_changed_since_the_previous_nonce() {
+			let Setup { manifest, .. } = setup();
+			let mut previous = manifest.clone();
+			previous.namespace.nonce -= 1;
+			previous.pivot.args = vec!["--old".to_string()];
+
+			let mut vec_out: Vec<u8> = vec![];
+			let mut prompter =
+				Prompter { reader: "".as_bytes(), writer: &mut vec_out };
+			super::super::review_manifest(
+				&manifest,
+				Some(&previous),
+				&mut prompter,
+			)
+			.unwrap();
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let diff = output
+				.split_once(&format!(
+					"Changes since the manifest with nonce {}:\n",
+					previous.namespace.nonce
+				))
+				.unwrap()
+				.1;
+			let changed: Vec<_> = diff
+				.lines()
+				.map(|line| line.trim().split(':').next().unwrap())
+				.collect();
+			assert_eq!(
+				changed,
+				[
+					"- qos hash",
+					"- namespace nonce",
+					"- pivot args",
+					"+ qos hash",
+					"+ namespace nonce",
+					"+ pivot args"
+				]
+			);
+		}
+
+		#[test]
+		fn finds_the_manifest_for_the_previous_nonce() {
+			let Setup { manifest, manifest_envelope, .. } = setup();
+			let dir: PathWrapper = "/tmp/qos_client_previous_manifest".into();
+			fs::create_dir_all(&*dir).unwrap();
+
+			let mut older = manifest.clone();
+			older.namespace.nonce -= 2;
+			let mut previous = manifest_envelope.clone();
+			previous.manifest.namespace.nonce -= 1;
+			let mut other_namespace = manifest.clone();
+			other_namespace.namespace.name = "other".to_string();
+			other_namespace.namespace.nonce -= 1;
+			for (file, bytes) in [
+				("older", borsh::to_vec(&older).unwrap()),
+				("manifest_envelope", borsh::to_vec(&previous).unwrap()),
+				("other", borsh::to_vec(&other_namespace).unwrap()),
+				("manifest", borsh::to_vec(&manifest).unwrap()),
+				("notes.txt", b"not a manifest".to_vec()),
+			] {
+				fs::write(Path::new(&*dir).join(file), bytes).unwrap();
+			}
+
+			assert_eq!(
+				find_previous_manifest([Some(Path::new(&*dir))], &manifest),
+				Some(previous.manifest)
+			);
+			assert_eq!(
+				find_previous_manifest([Some(Path::new(&*dir))], &older),
+				None
+			);
+		}
+
+		#[test]
+		fn typed_confirmation_requires_the_namespace_and_nonce() {
+			let Setup { manifest, .. } = setup();
+			let expected = format!(
+				"{} {}",
+				manifest.namespace.name, manifest.namespace.nonce
+			);
+
+			for (answer, approved) in
+				[("yes\n".to_string(), false), (format!("{expected}\n"), true)]
+			{
+				let mut vec_out: Vec<u8> = vec![];
+				let mut prompter = Prompter {
+					reader: answer.as_bytes(),
+					writer: &mut vec_out,
+				};
+				assert_eq!(
+					approve_manifest_typed_confirmation(
+						&manifest,
+						&mut prompter
+					),
+					approved
+				);
+				assert_eq!(
+					String::from_utf8(vec_out).unwrap(),
+					format!("Type `{expected}` to approve this manifest:\n")
+				);
+			}
+		}
+	}
+
+	mod manifest_spec {
+		use qos_core::protocol::services::{
+			boot::EgressEndpoint,
+			genesis::{GenesisMemberOutput, GenesisOutput},
+		};
+
+		use super::*;
+		use crate::services::{
+			generate_manifest_from_spec, ManifestSpec, NonceLedger,
+		};
+
+		const ROLE_ARN: &str = "arn:aws:iam::123456789012:role/Webserver";
+
+		fn write_set(dir: &Path, members: &[QuorumMember], threshold: u32) {
+			fs::create_dir_all(dir).unwrap();
+			for member in members {
+				P256Public::from_bytes(&member.pub_key)
+					.unwrap()
+					.to_hex_file(dir.join(format!("{}.pub", member.alias)))
+					.unwrap();
+			}
+			fs::write(dir.join(QUORUM_THRESHOLD_FILE), threshold.to_string())
+				.unwrap();
+		}
+
+		fn genesis_output(setup: &Setup) -> GenesisOutput {
+			GenesisOutput {
+				quorum_key: setup.quorum_key.to_bytes(),
+				member_outputs: setup
+					.share_set
+					.members
+					.iter()
+					.map(|member| GenesisMemberOutput {
+						share_set_member: member.clone(),
+						encrypted_quorum_key_share: vec![],
+						share_hash: [0; 64],
+					})
+					.collect(),
+				recovery_permutations: vec![],
+				threshold: setup.share_set.threshold,
+				dr_key_wrapped_quorum_key: None,
+				quorum_key_hash: [0; 64],
+				test_message_ciphertext: vec![],
+				test_message_signature: vec![],
+				test_message: vec![],
+				share_commitments: vec![[7; 33]],
+			}
+		}
+
+		fn write_spec(dir: &Path, setup: &Setup, extra: &str) -> PathBuf {
+			let Setup { manifest, nitro_config, .. } = setup;
+			write_set(
+				&dir.join("manifest-set"),
+				&manifest.manifest_set.members,
+				manifest.manifest_set.threshold,
+			);
+			write_set(
+				&dir.join("share-set"),
+				&manifest.share_set.members,
+				manifest.share_set.threshold,
+			);
+			write_set(
+				&dir.join("patch-set"),
+				&manifest.manifest_set.members,
+				manifest.patch_set.threshold,
+			);
+
+			let spec = format!(
+				r#"namespace = "{}"
+nonce = {}
+restart-policy = "never"
+pivot-hash = "{}"
+pivot-args = ["--option1", "argument"]
+quorum-key = "{}"
+pcr0 = "{}"
+pcr1 = "{}"
+pcr2 = "{}"
+pcr3-preimage = "{ROLE_ARN}"
+manifest-set-dir = "manifest-set"
+share-set-dir = "share-set"
+patch-set-dir = "patch-set"
+{extra}"#,
+				manifest.namespace.name,
+				manifest.namespace.nonce,
+				qos_hex::encode(&manifest.pivot.hash),
+				qos_hex::encode(&manifest.namespace.quorum_key),
+				qos_hex::encode(&nitro_config.pcr0),
+				qos_hex::encode(&nitro_config.pcr1),
+				qos_hex::encode(&nitro_config.pcr2),
+			);
+			let spec_path = dir.join("manifest.toml");
+			fs::write(&spec_path, spec).unwrap();
+			spec_path
+		}
+
+		#[test]
+		fn generates_the_manifest_described_by_the_spec() {
+			let setup = setup();
+			let dir: PathWrapper = "/tmp/qos_client_manifest_spec".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+			fs::write(
+				dir.join("genesis_output"),
+				borsh::to_vec(&genesis_output(&setup)).unwrap(),
+			)
+			.unwrap();
+			fs::write(dir.join("app_config.json"), b"{\"region\":\"eu\"}")
+				.unwrap();
+			let spec_path = write_spec(
+				dir,
+				&setup,
+				"genesis-output = \"genesis_output\"\napproved-only = true\n\
+				app-config = \"app_config.json\"\n\
+				egress = [\"api.example.com:443\", { endpoint = \"*.example.org:443\", \
+				tls-pins = [\"0707070707070707070707070707070707070707070707070707070707070707\"] }]",
+			);
+			let manifest_path = dir.join("manifest");
+
+			generate_manifest_from_spec(&spec_path, &manifest_path, None)
+				.unwrap();
+
+			let mut expected = setup.manifest;
+			expected.enclave.pcr3 = super::super::pcr3_from_role_arn(ROLE_ARN);
+			expected.enclave.qos_commit = String::new();
+			expected.patch_set.members.sort();
+			expected.crypto = CryptoConfig::new(true);
+			expected.share_set.share_commitments = vec![[7; 33]];
+			expected.pivot.app_config = b"{\"region\":\"eu\"}".to_vec();
+			expected.pivot.egress = vec![
+				EgressEndpoint {
+					host: "api.example.com".to_string(),
+					port: 443,
+					tls_pins: vec![],
+				},
+				EgressEndpoint {
+					host: "*.example.org".to_string(),
+					port: 443,
+					tls_pins: vec![[7; 32]],
+				},
+			];
+			let manifest =
+				Manifest::try_from_slice(&fs::read(&manifest_path).unwrap())
+					.unwrap();
+			assert_eq!(manifest, expected);
+		}
+
+		#[test]
+		fn nonce_ledger_refuses_nonces_that_do_not_increase() {
+			let setup = setup();
+			let dir: PathWrapper = "/tmp/qos_client_nonce_ledger".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+			let spec_path = write_spec(dir, &setup, "");
+			let manifest_path = dir.join("manifest");
+			let ledger_path = dir.join("nonce_ledger.json");
+
+			let nonce = setup.manifest.namespace.nonce;
+			let ledger = NonceLedger::open(&ledger_path).unwrap();
+			assert_eq!(ledger.next_nonce(&setup.manifest.namespace.name), 0);
+
+			generate_manifest_from_spec(
+				&spec_path,
+				&manifest_path,
+				Some(&ledger_path),
+			)
+			.unwrap();
+			let ledger = NonceLedger::open(&ledger_path).unwrap();
+			assert_eq!(
+				ledger.next_nonce(&setup.manifest.namespace.name),
+				nonce + 1
+			);
+			assert_eq!(ledger.next_nonce("another-namespace"), 0);
+
+			// Reusing the nonce is refused
+			fs::remove_file(&manifest_path).unwrap();
+			assert!(matches!(
+				generate_manifest_from_spec(
+					&spec_path,
+					&manifest_path,
+					Some(&ledger_path),
+				),
+				Err(Error::NonceNotIncreasing { highest, .. }) if highest == nonce
+			));
+			assert!(!manifest_path.exists());
+		}
+
+		#[test]
+		fn spec_can_hash_the_pivot_binary() {
+			let setup = setup();
+			let dir: PathWrapper = "/tmp/qos_client_manifest_spec_pivot".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+			let spec_path = write_spec(dir, &setup, "");
+			let spec = fs::read_to_string(&spec_path).unwrap().replace(
+				&format!(
+					"pivot-hash = \"{}\"",
+					qos_hex::encode(&setup.pivot_hash)
+				),
+				"pivot-path = \"pivot\"",
+			);
+			fs::write(&spec_path, spec).unwrap();
+			fs::write(dir.join("pivot"), b"pivot binary").unwrap();
+
+			assert_eq!(
+				ManifestSpec::from_file(&spec_path).unwrap().pivot_hash,
+				qos_crypto::sha_256(b"pivot binary")
+			);
+
+			// Not both
+			let spec = fs::read_to_string(&spec_path).unwrap();
+			fs::write(&spec_path, format!("{spec}pivot-hash = \"00\"\n"))
+				.unwrap();
+			assert!(matches!(
+				ManifestSpec::from_file(&spec_path),
+				Err(Error::InvalidFile { error, .. })
+					if error == "needs one of `pivot-hash` or `pivot-path`"
+			));
+		}
+
+		#[test]
+		fn spec_must_match_the_genesis_output() {
+			let setup = setup();
+			let dir: PathWrapper =
+				"/tmp/qos_client_manifest_spec_genesis".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+			let spec_path =
+				write_spec(dir, &setup, "genesis-output = \"genesis_output\"");
+			let manifest_path = dir.join("manifest");
+
+			let mut other_quorum_key = genesis_output(&setup);
+			other_quorum_key.quorum_key =
+				P256Pair::generate().unwrap().public_key().to_bytes();
+			let mut other_threshold = genesis_output(&setup);
+			other_threshold.threshold += 1;
+			let mut other_members = genesis_output(&setup);
+			other_members.member_outputs.pop();
+
+			for (genesis_output, mismatch) in [
+				(other_quorum_key, "quorum key"),
+				(other_threshold, "share set threshold"),
+				(other_members, "share set members"),
+			] {
+				fs::write(
+					dir.join("genesis_output"),
+					borsh::to_vec(&genesis_output).unwrap(),
+				)
+				.unwrap();
+				let err = generate_manifest_from_spec(
+					&spec_path,
+					&manifest_path,
+					None,
+				)
+				.unwrap_err();
+				assert!(matches!(
+					err,
+					Error::SpecDoesNotMatchGenesisOutput(what) if what == mismatch
+				));
+				assert_eq!(err.exit_code(), ExitCode::VerificationFailed);
+			}
+			assert!(!manifest_path.exists());
+		}
+
+		#[test]
+		fn invalid_specs_are_errors() {
+			let setup = setup();
+			let dir: PathWrapper =
+				"/tmp/qos_client_manifest_spec_invalid".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+
+			for (extra, error) in [
+				("pivot-hahs = \"00\"", "unknown key `pivot-hahs`"),
+				("nonce = -1", "not valid TOML"),
+				("approved-only = 1", "`approved-only` must be a boolean"),
+				("egress = [\"example.com\"]", "`egress` entries must be"),
+				(
+					"egress = [{ endpoint = \"example.com:443\", pins = [] }]",
+					"unknown key `pins` in `egress`",
+				),
+				(
+					"egress = [{ endpoint = \"example.com:443\", tls-pins = [\"00\"] }]",
+					"`tls-pins` must be an array of hex sha256 hashes",
+				),
+			] {
+				let spec_path = write_spec(dir, &setup, extra);
+				assert!(matches!(
+					ManifestSpec::from_file(&spec_path),
+					Err(Error::InvalidFile { error: e, .. }) if e.starts_with(error)
+				));
+			}
+
+			let spec_path = write_spec(dir, &setup, "");
+			let spec = fs::read_to_string(&spec_path).unwrap();
+			for (from, to, error) in [
+				("restart-policy = \"never\"", "", "missing `restart-policy`"),
+				("nonce = 2", "nonce = -1", "`nonce` must be a non negative"),
+				("pcr0 = \"", "pcr0 = \"zz", "`pcr0` is not a PCR: invalid"),
+				(
+					"pcr1 = \"0202",
+					"pcr1 = \"",
+					"`pcr1` is not a PCR: expected 48 bytes",
+				),
+				("quorum-key = \"", "quorum-key = \"00", "`quorum-key` is not"),
+			] {
+				fs::write(&spec_path, spec.replace(from, to)).unwrap();
+				assert!(matches!(
+					ManifestSpec::from_file(&spec_path),
+					Err(Error::InvalidFile { error: e, .. }) if e.starts_with(error)
+				));
+			}
+		}
+	}
+
+	mod boot_ceremony {
+		use super::*;
+		use crate::services::{
+			BootCeremonyPlan, CeremonyCheckpoint, CeremonyStep,
+		};
+
+		const PLAN: &str = r#"
+manifest = "manifest"
+manifest-hash = "0xABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABABAB"
+manifest-approvals-dir = "approvals"
+manifest-envelope = "manifest_envelope"
+pivot = "pivot"
+pcr3-preimage = "pcr3-preimage.txt"
+shares-dir = "shares"
+"#;
+
+		#[test]
+		fn plan_paths_are_relative_to_the_plan() {
+			let plan =
+				BootCeremonyPlan::parse(PLAN, Path::new("/ceremony/plan.toml"))
+					.unwrap();
+			assert_eq!(
+				plan,
+				BootCeremonyPlan {
+					manifest_path: "/ceremony/manifest".into(),
+					manifest_hash: [0xab; 32],
+					manifest_approvals_dir: "/ceremony/approvals".into(),
+					manifest_envelope_path: "/ceremony/manifest_envelope"
+						.into(),
+					pivot_path: "/ceremony/pivot".into(),
+					pcr3_preimage_path: "/ceremony/pcr3-preimage.txt".into(),
+					shares_dir: "/ceremony/shares".into(),
+					checkpoint_path: "/ceremony/plan.checkpoint".into(),
+					unsafe_skip_attestation: false,
+				}
+			);
+
+			let err = BootCeremonyPlan::parse(
+				&format!("{PLAN}pivot-hash = \"00\""),
+				Path::new("plan.toml"),
+			)
+			.unwrap_err();
+			assert_eq!(err, "unknown key `pivot-hash`");
+			let err = BootCeremonyPlan::parse(
+				&PLAN.replace("pivot = \"pivot\"", ""),
+				Path::new("plan.toml"),
+			)
+			.unwrap_err();
+			assert_eq!(err, "missing `pivot`");
+		}
+
+		#[test]
+		fn checkpoint_resumes_only_the_same_manifest() {
+			let path: PathWrapper =
+				"/tmp/qos_client_boot_ceremony.checkpoint".into();
+			let path = Path::new(&*path);
+
+			let mut checkpoint =
+				CeremonyCheckpoint::open(path, "abcd").unwrap();
+			assert!(!checkpoint.is_completed(CeremonyStep::ValidateManifest));
+			checkpoint.complete(CeremonyStep::ValidateManifest).unwrap();
+			checkpoint.share_posted("alice").unwrap();
+
+			let resumed = CeremonyCheckpoint::open(path, "abcd").unwrap();
+			assert_eq!(resumed, checkpoint);
+			assert!(resumed.is_completed(CeremonyStep::ValidateManifest));
+			assert!(!resumed.is_completed(CeremonyStep::CollectApprovals));
+
+			assert!(matches!(
+				CeremonyCheckpoint::open(path, "ef01"),
+				Err(Error::InvalidFile { .. })
+			));
+		}
+	}
+
+	mod approve_manifest_human_verifications {
+		use super::*;
+		#[test]
+		fn human_verification_works() {
+			let Setup { manifest, .. } = setup();
+
+			let mut vec_out = Vec::<u8>::new();
+			let vec_in = "yes\nyes\nyes\nyes\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(approve_manifest_human_verifications(
+				&manifest,
+				&mut prompter
+			));
+		}
+
+		#[test]
+		fn exits_early_with_bad_namespace_name() {
+			let Setup { manifest, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "ye\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!super::approve_manifest_human_verifications(
+				&manifest,
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			assert_eq!(&output, "Is this the correct namespace name: test-namespace? (yes/no)\n");
+		}
+
+		#[test]
+		fn exits_early_with_bad_namespace_nonce() {
+			let Setup { manifest, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nye".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!super::approve_manifest_human_verifications(
+				&manifest,
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.split('\n').collect();
+
+			assert_eq!(
+				output[1],
+				"Is this the correct namespace nonce: 2? (yes/no)"
+			);
+		}
+
+		#[test]
+		fn exits_early_with_bad_restart_policy() {
+			let Setup { manifest, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nyes\ny".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!super::approve_manifest_human_verifications(
+				&manifest,
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.split('\n').collect();
+
+			assert_eq!(
+				output[2],
+				"Is this the correct pivot restart policy: RestartPolicy::Never? (yes/no)"
+			);
+		}
+
+		#[test]
+		fn exits_early_with_bad_pivot_args() {
+			let Setup { manifest, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nyes\nyes\nno".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!super::approve_manifest_human_verifications(
+				&manifest,
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.split('\n').collect();
+
+			assert_eq!(output[3], "Are these the correct pivot args:");
+			assert_eq!(output[4], "[\"--option1\", \"argument\"]?");
+			assert_eq!(output[5], "(yes/no)");
+		}
+	}
+
+	mod proxy_re_encrypt_share_programmatic_verifications {
+		use super::*;
+
+		#[test]
+		fn accepts_valid() {
+			let Setup { manifest_set, share_set, manifest_envelope, .. } =
+				setup();
+
+			let member = share_set.members[0].clone();
+			assert!(proxy_re_encrypt_share_programmatic_verifications(
+				&manifest_envelope,
+				&manifest_set,
+				&member
+			));
+		}
+
+		#[test]
+		fn rejects_invalid_approval() {
+			let Setup {
+				manifest_set, share_set, mut manifest_envelope, ..
+			} = setup();
+
+			manifest_envelope
+				.manifest_set_approvals
+				.get_mut(0)
+				.unwrap()
+				.signature = vec![0; 32];
+
+			let member = share_set.members[0].clone();
+			assert!(!proxy_re_encrypt_share_programmatic_verifications(
+				&manifest_envelope,
+				&manifest_set,
+				&member
+			));
+		}
+
+		#[test]
+		fn rejects_approval_from_member_not_part_of_manifest_set() {
+			let Setup {
+				manifest_set, share_set, mut manifest_envelope, ..
+			} = setup();
+
+			manifest_envelope
+				.manifest_set_approvals
+				.get_mut(0)
+				.unwrap()
+				.member
+				.alias = "yoloswag420blazeit".to_string();
+
+			let member = share_set.members[0].clone();
+			assert!(!proxy_re_encrypt_share_programmatic_verifications(
+				&manifest_envelope,
+				&manifest_set,
+				&member
+			));
+		}
+
+		#[test]
+		fn rejects_if_not_enough_approvals() {
+			let Setup {
+				manifest_set, share_set, mut manifest_envelope, ..
+			} = setup();
+
+			manifest_envelope.manifest_set_approvals.pop().unwrap();
+
+			let member = share_set.members[0].clone();
+			assert!(!proxy_re_encrypt_share_programmatic_verifications(
+				&manifest_envelope,
+				&manifest_set,
+				&member
+			));
+		}
+
+		#[test]
+		fn rejects_mismatched_manifest_sets() {
+			let Setup {
+				mut manifest_set, share_set, manifest_envelope, ..
+			} = setup();
+
+			manifest_set.members.push(QuorumMember {
+				alias: "got what plants need".to_string(),
+				pub_key: P256Pair::generate().unwrap().public_key().to_bytes(),
+			});
+
+			let member = share_set.members[0].clone();
+			assert!(!proxy_re_encrypt_share_programmatic_verifications(
+				&manifest_envelope,
+				&manifest_set,
+				&member
+			));
+		}
+	}
+
+	mod proxy_re_encrypt_share_human_verifications {
+		use super::*;
+		#[test]
+		fn accepts_all_yes_responses() {
+			let Setup { manifest_envelope, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nyes\nyes\nyes\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(proxy_re_encrypt_share_human_verifications(
+				&manifest_envelope,
+				"pr3",
+				&mut prompter
+			));
+		}
+
+		#[test]
+		fn exits_early_bad_namespace_name() {
+			let Setup { manifest_envelope, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "no\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!proxy_re_encrypt_share_human_verifications(
+				&manifest_envelope,
+				"pr3",
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			assert_eq!(&output, "Is this the correct namespace name: test-namespace? (yes/no)\n");
+		}
+
+		#[test]
+		fn exits_early_bad_namespace_nonce() {
+			let Setup { manifest_envelope, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nno\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!proxy_re_encrypt_share_human_verifications(
+				&manifest_envelope,
+				"pr3",
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.lines().collect();
+			assert_eq!(
+				output.last().unwrap(),
+				&"Is this the correct namespace nonce: 2? (yes/no)"
+			);
+		}
+
+		#[test]
+		fn exits_early_bad_iam_role() {
+			let Setup { manifest_envelope, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nyes\nNO\n".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!proxy_re_encrypt_share_human_verifications(
+				&manifest_envelope,
+				"pr3",
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.lines().collect();
+			assert_eq!(
+				output.last().unwrap(),
+				&"Does this AWS IAM role belong to the intended organization: pr3? (yes/no)"
+			);
+		}
+
+		#[test]
+		fn exits_early_bad_manifest_set_members() {
+			let Setup { manifest_envelope, .. } = setup();
+
+			let mut vec_out: Vec<u8> = vec![];
+			let vec_in = "yes\nyes\nyes\ny".as_bytes();
+
+			let mut prompter =
+				Prompter { reader: vec_in, writer: &mut vec_out };
+
+			assert!(!proxy_re_encrypt_share_human_verifications(
+				&manifest_envelope,
+				"pr3",
+				&mut prompter
+			));
+
+			let output = String::from_utf8(vec_out).unwrap();
+			let output: Vec<_> = output.lines().collect();
+
+			assert_eq!(
+				output[3],
+				"The following manifest set members approved:"
+			);
+			assert_eq!(output[4], "\talias: 0");
+			assert_eq!(output[5], "\talias: 1");
+			assert_eq!(output[6], "Is this ok? (yes/no)");
+			assert_eq!(output.len(), 7);
+		}
+	}
+
+	mod share_rotation {
+		use qos_core::protocol::services::{
+			genesis::{GenesisMemberOutput, GenesisOutput},
+			provision::ShareRotation,
+		};
+
+		use super::*;
+		use crate::services::{
+			approve_share_rotation, check_share_rotation,
+			find_share_rotation_approvals, ApproveShareRotationArgs,
+			PairOrYubi, ShareRotationRequest, GENESIS_OUTPUT_FILE,
+			SHARE_ROTATION_FILE,
+		};
+
+		struct RotationSetup {
+			manifest: Manifest,
+			member_pairs: Vec<P256Pair>,
+			genesis_output: GenesisOutput,
+			request: ShareRotationRequest,
+		}
+
+		fn rotation_setup() -> RotationSetup {
+			let member_pairs: Vec<_> =
+				(0..3).map(|_| P256Pair::generate().unwrap()).collect();
+			let members: Vec<_> = member_pairs
+				.iter()
+				.enumerate()
+				.map(|(i, pair)| QuorumMember {
+					pub_key: pair.public_key().to_bytes(),
+					alias: i.to_string(),
+				})
+				.collect();
+			let manifest = Manifest {
+				share_set: ShareSet {
+					members: members.clone(),
+					threshold: 2,
+					share_commitments: vec![],
+				},
+				..setup().manifest
+			};
+
+			let genesis_output = GenesisOutput {
+				quorum_key: manifest.namespace.quorum_key.clone(),
+				member_outputs: members
+					.iter()
+					.zip(1u8..)
+					.map(|(member, i)| GenesisMemberOutput {
+						share_set_member: member.clone(),
+						encrypted_quorum_key_share: vec![],
+						share_hash: [i; 64],
+					})
+					.collect(),
+				recovery_permutations: vec![],
+				threshold: 2,
+				dr_key_wrapped_quorum_key: None,
+				quorum_key_hash: [0; 64],
+				test_message_ciphertext: vec![],
+				test_message_signature: vec![],
+				test_message: vec![],
+				share_commitments: vec![],
+			};
+
+			let new_pair = P256Pair::generate().unwrap();
+			let rotation = ShareRotation {
+				manifest_hash: manifest.qos_hash(),
+				member: members[0].clone(),
+				share_hash: [1; 64],
+				new_pub_key: new_pair.public_key().to_bytes(),
+			};
+			let signature = new_pair.sign(&rotation.qos_hash()).unwrap();
+
+			RotationSetup {
+				manifest,
+				member_pairs,
+				genesis_output,
+				request: ShareRotationRequest { rotation, signature },
+			}
+		}
+
+		#[test]
+		fn share_rotation_must_match_the_manifest_and_genesis_output() {
+			let RotationSetup { manifest, genesis_output, request, .. } =
+				rotation_setup();
+			assert!(check_share_rotation(&request, &manifest, &genesis_output)
+				.is_ok());
+
+			let mismatches = [
+				ShareRotationRequest {
+					signature: vec![0; 64],
+					rotation: request.rotation.clone(),
+				},
+				ShareRotationRequest {
+					rotation: ShareRotation {
+						manifest_hash: [0; 32],
+						..request.rotation.clone()
+					},
+					signature: request.signature.clone(),
+				},
+				ShareRotationRequest {
+					rotation: ShareRotation {
+						// The share of another member
+						share_hash: [2; 64],
+						..request.rotation.clone()
+					},
+					signature: request.signature.clone(),
+				},
+			];
+			for mismatch in mismatches {
+				assert!(matches!(
+					check_share_rotation(&mismatch, &manifest, &genesis_output),
+					Err(Error::InvalidShareRotation(_))
+				));
+			}
+		}
+
+		#[test]
+		fn approvals_are_written_to_the_rotation_dir() {
+			let RotationSetup {
+				manifest,
+				member_pairs,
+				genesis_output,
+				request,
+			} = rotation_setup();
+
+			let dir: PathWrapper = "/tmp/qos_client_share_rotation".into();
+			let dir = Path::new(&*dir);
+			fs::create_dir_all(dir).unwrap();
+			let manifest_envelope_path = dir.join("manifest_envelope");
+			fs::write(
+				&manifest_envelope_path,
+				borsh::to_vec(&ManifestEnvelope {
+					manifest: manifest.clone(),
+					manifest_set_approvals: vec![],
+					share_set_approvals: vec![],
+				})
+				.unwrap(),
+			)
+			.unwrap();
+			fs::write(
+				dir.join(GENESIS_OUTPUT_FILE),
+				borsh::to_vec(&genesis_output).unwrap(),
+			)
+			.unwrap();
+			fs::write(
+				dir.join(SHARE_ROTATION_FILE),
+				borsh::to_vec(&request).unwrap(),
+			)
+			.unwrap();
+
+			for (i, pair) in member_pairs.into_iter().enumerate().skip(1) {
+				approve_share_rotation(ApproveShareRotationArgs {
+					pair: PairOrYubi::Pair(pair),
+					alias: i.to_string(),
+					namespace_dir: dir.to_path_buf(),
+					manifest_envelope_path: manifest_envelope_path.clone(),
+					rotation_dir: dir.to_path_buf(),
+					unsafe_auto_confirm: true,
+				})
+				.unwrap();
+			}
+
+			let approvals = find_share_rotation_approvals(
+				dir,
+				&request.rotation,
+				&manifest.share_set,
+			)
+			.unwrap();
+			assert_eq!(approvals.len(), 2);
+
+			// Members outside the share set can not approve
+			let outsider = approve_share_rotation(ApproveShareRotationArgs {
+				pair: PairOrYubi::Pair(P256Pair::generate().unwrap()),
+				alias: "2".to_string(),
+				namespace_dir: dir.to_path_buf(),
+				manifest_envelope_path,
+				rotation_dir: dir.to_path_buf(),
+				unsafe_auto_confirm: true,
+			});
+			assert!(matches!(outsider, Err(Error::InvalidShareRotation(_))));
+		}
+	}
+
+	#[test]
+	fn enclave_checks_compare_the_attestation_doc_to_the_manifest() {
+		use aws_nitro_enclaves_nsm_api::api::{AttestationDoc, Digest};
+
+		let Setup { manifest, nitro_config, .. } = setup();
+		let root_cert = nitro_config.aws_root_certificate.clone();
+		let attestation_doc = |pcr3: Vec<u8>| {
+			AttestationDoc::new(
+				"module".to_string(),
+				Digest::SHA384,
+				0,
+				[
+					(0, nitro_config.pcr0.clone()),
+					(1, nitro_config.pcr1.clone()),
+					(2, nitro_config.pcr2.clone()),
+					(3, pcr3),
+				]
+				.into(),
+				vec![],
+				vec![],
+				Some(manifest.qos_hash().to_vec()),
+				None,
+				None,
+			)
+		};
+
+		let checks = enclave_checks(
+			&attestation_doc(nitro_config.pcr3.clone()),
+			true,
+			&root_cert,
+			&manifest,
+			Some(&manifest),
+		);
+		assert_eq!(checks.len(), 8);
+		assert!(checks.iter().all(|(_, passed)| *passed));
+
+		let checks = enclave_checks(
+			&attestation_doc(vec![0; 42]),
+			false,
+			&root_cert,
+			&manifest,
+			None,
+		);
+		let failed: Vec<_> = checks
+			.iter()
+			.filter(|(_, passed)| !passed)
+			.map(|(check, _)| *check)
+			.collect();
+		assert_eq!(
+			failed,
+			[
+				"the attestation doc is signed by the AWS root certificate",
+				"PCR3 matches the manifest",
+				"the enclave was booted with the manifest",
+			]
+		);
+	}
+
+	#[test]
+	fn key_listings_show_fingerprints_and_loose_permissions() {
+		use std::os::unix::fs::PermissionsExt;
+
+		use qos_crypto::sha_256;
+
+		let dir: PathWrapper = "/tmp/qos_client_key_listings".into();
+		let dir = Path::new(&*dir);
+		fs::create_dir_all(dir).unwrap();
+
+		let pair = P256Pair::generate().unwrap();
+		let fingerprint =
+			qos_hex::encode(&sha_256(&pair.public_key().to_bytes()));
+		pair.public_key().to_hex_file(dir.join("alice.pub")).unwrap();
+		pair.to_hex_file(dir.join("alice.secret")).unwrap();
+		fs::set_permissions(
+			dir.join("alice.secret"),
+			fs::Permissions::from_mode(0o600),
+		)
+		.unwrap();
+		fs::write(dir.join("alice.my-namespace.share"), [1, 2, 3]).unwrap();
+		fs::set_permissions(
+			dir.join("alice.my-namespace.share"),
+			fs::Permissions::from_mode(0o644),
+		)
+		.unwrap();
+		fs::write(dir.join("notes.txt"), "not a key").unwrap();
+
+		let listings = key_listings(dir).unwrap();
+		assert_eq!(listings.len(), 3);
+
+		assert_eq!(listings[0].file_name, "alice.my-namespace.share");
+		assert_eq!(listings[0].kind, "encrypted share");
+		assert_eq!(listings[0].alias, "alice");
+		assert_eq!(listings[0].namespace.as_deref(), Some("my-namespace"));
+		assert_eq!(listings[0].fingerprint, None);
+		assert_eq!(listings[0].warnings.len(), 1);
+		assert!(listings[0].warnings[0].contains("mode 644"));
+
+		assert_eq!(listings[1].kind, "public key");
+		assert_eq!(listings[1].namespace, None);
+		assert_eq!(listings[1].fingerprint.as_ref(), Some(&fingerprint));
+		assert!(listings[1].warnings.is_empty());
+
+		assert_eq!(listings[2].kind, "master seed");
+		assert_eq!(listings[2].fingerprint.as_ref(), Some(&fingerprint));
+		assert!(listings[2].warnings.is_empty());
+	}
+
+	#[test]
+	fn paper_backup_restores_the_share() {
+		let dir: PathWrapper = "/tmp/qos_client_paper_backup".into();
+		let dir = Path::new(&*dir);
+		fs::create_dir_all(dir).unwrap();
+		let share_path = dir.join("alice.share");
+		let backup_path = dir.join("alice.share.txt");
+		let restored_path = dir.join("restored.share");
+		fs::write(&share_path, [7; 100]).unwrap();
+
+		paper_backup_export(&share_path, &backup_path).unwrap();
+		let doc = fs::read_to_string(&backup_path).unwrap();
+		assert!(doc.contains("Kind: share\nName: alice.share\n"));
+		assert!(doc.contains("qos_client paper-backup-import"));
+
+		paper_backup_import(&backup_path, &restored_path).unwrap();
+		assert_eq!(fs::read(&restored_path).unwrap(), [7; 100]);
+
+		assert!(matches!(
+			paper_backup_export(&backup_path, &restored_path),
+			Err(Error::InvalidFile { .. })
+		));
+		assert!(matches!(
+			paper_backup_export(&dir.join(".."), &restored_path),
+			Err(Error::InvalidFile { .. })
+		));
+		fs::write(&backup_path, doc.replace("0001  0707", "0001  0708"))
+			.unwrap();
+		assert!(matches!(
+			paper_backup_import(&backup_path, &restored_path),
+			Err(Error::Paper(PaperError::LineChecksumMismatch(1)))
+		));
+	}
+
+	#[test]
+	fn display_type_rejects_unknown_types() {
+		assert!(matches!(
+			"manifest-envelope".parse(),
+			Ok(DisplayType::ManifestEnvelope)
+		));
+		let err = "manifest_envelope".parse::<DisplayType>().unwrap_err();
+		assert!(matches!(err, Error::UnknownDisplayType(_)));
+		assert_eq!(
+			err.to_string(),
+			"invalid args: `--display-type`: unknown type \"manifest_envelope\", \
+			expected manifest, manifest-envelope or genesis-output"
+		);
+	}
+
+	#[test]
+	fn recover_file_key_recreates_the_key() {
+		let dir: PathWrapper = "/tmp/qos_client_recover_file_key".into();
+		let dir = Path::new(&*dir);
+		fs::create_dir_all(dir).unwrap();
+		let secret_path = dir.join("alice.secret");
+		let pub_path = dir.join("alice.pub");
+
+		let pair = P256Pair::generate().unwrap();
+		if qos_crypto::algorithms::APPROVED_ONLY {
+			assert!(matches!(
+				pair.to_mnemonic(),
+				Err(P256Error::UnapprovedAlgorithm(_))
+			));
+			return;
+		}
+		let phrase = pair.to_mnemonic().unwrap();
+		recover_file_key(&phrase, &secret_path, &pub_path, false).unwrap();
+		assert_eq!(
+			fs::read(&pub_path).unwrap(),
+			pair.public_key().to_hex_bytes()
+		);
+		assert_eq!(fs::read(&secret_path).unwrap(), *pair.to_master_seed_hex());
+
+		assert!(matches!(
+			recover_file_key("legal winner", &secret_path, &pub_path, false),
+			Err(Error::P256(P256Error::InvalidMnemonic(_)))
+		));
+	}
+
+	#[test]
+	fn exit_codes_tell_failures_apart() {
+		let network = Error::Request(request::Error::Transport {
+			url: "http://127.0.0.1:3000/qos/message".to_string(),
+			error: "connection refused".to_string(),
+		});
+		assert_eq!(network.exit_code(), ExitCode::Network);
+		assert_eq!(network.exit_code() as i32, 4);
+
+		let attestation = Error::QosAttest("invalid cert chain".to_string());
+		assert_eq!(attestation.exit_code(), ExitCode::AttestationFailed);
+		assert_eq!(attestation.exit_code() as i32, 6);
+
+		assert_eq!(
+			Error::UnexpectedProtocolMsgResponse(String::new()).exit_code(),
+			ExitCode::Enclave
+		);
+		assert_eq!(
+			Error::InvalidSignature.exit_code(),
+			ExitCode::VerificationFailed
+		);
+	}
+
+	#[test]
+	fn audit_log_report_points_at_the_first_break() {
+		let mut prev_hash = [0; 32];
+		let mut entries: Vec<_> = (5..8)
+			.map(|index| {
+				let mut entry = AuditEntry {
+					index,
+					route: "/qos/message".to_string(),
+					payload_hash: [1; 32],
+					timestamp_ms: 1_000,
+					status: 200,
+					prev_hash,
+					hash: [0; 32],
+				};
+				entry.hash = entry.compute_hash();
+				prev_hash = entry.hash;
+				entry
+			})
+			.collect();
+
+		let report = audit_log_report(&entries, None);
+		assert_eq!(report["entries"], 3);
+		assert_eq!(report["firstIndex"], 5);
+		assert_eq!(report["lastIndex"], 7);
+		assert_eq!(report["headHash"], qos_hex::encode(&prev_hash));
+		assert_eq!(report["chainIntact"], true);
+		assert!(report["firstBreak"].is_null());
+		assert_eq!(report["signaturesValid"], true);
+
+		entries[1].route = "/qos/other".to_string();
+		let report = audit_log_report(&entries, Some(7));
+		assert_eq!(report["chainIntact"], false);
+		assert_eq!(report["firstBreak"], 6);
+		assert_eq!(report["signaturesValid"], false);
+		assert_eq!(report["firstInvalidSignature"], 7);
+	}
+}
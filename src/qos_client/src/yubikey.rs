@@ -1,6 +1,5 @@
 //! Yubikey interfaces
 
-use borsh::BorshDeserialize;
 use p256::{
 	ecdsa::{signature::Verifier, Signature, VerifyingKey},
 	elliptic_curve::sec1::ToEncodedPoint,
@@ -278,7 +277,7 @@ pub fn shared_secret(
 ) -> Result<Zeroizing<Vec<u8>>, YubiKeyError> {
 	// get the sender eph key from the encryption envelope
 	let sender_pub_bytes = {
-		let decoded = Envelope::try_from_slice(serialized_envelope)
+		let decoded = Envelope::from_bytes(serialized_envelope)
 			.map_err(|_| YubiKeyError::EnvelopeDeserialize)?;
 		decoded.ephemeral_sender_public
 	};
@@ -122,3 +122,66 @@ fn p256_asymmetric_encrypt_decrypt_roundtrip() {
 
 	assert_eq!(qos_hex::decode(&hex_plaintext).unwrap(), decrypted_bytes);
 }
+
+#[test]
+fn quiet_only_prints_warnings_and_errors() {
+	let tmp: PathWrapper = "/tmp/quiet_only_prints_warnings_and_errors".into();
+	std::fs::create_dir_all(&*tmp).unwrap();
+
+	let payload_path = "/tmp/quiet_only_prints_warnings_and_errors/payload";
+	let signature_path = "/tmp/quiet_only_prints_warnings_and_errors/signature";
+	std::fs::write(payload_path, DATA).unwrap();
+
+	let sign = |log_flag: &str| {
+		Command::new("../target/debug/qos_client")
+			.arg("p256-sign")
+			.arg("--payload-path")
+			.arg(payload_path)
+			.arg("--signature-path")
+			.arg(signature_path)
+			.arg("--master-seed-path")
+			.arg(MOCK_PRIMARY_SEED_PATH)
+			.args((!log_flag.is_empty()).then_some(log_flag))
+			.output()
+			.unwrap()
+	};
+	let verify = |log_flag: &str| {
+		Command::new("../target/debug/qos_client")
+			.arg("p256-verify")
+			.arg("--payload-path")
+			.arg(payload_path)
+			.arg("--signature-path")
+			.arg(signature_path)
+			.arg("--pub-path")
+			.arg(MOCK_PRIMARY_PUB_PATH)
+			.args((!log_flag.is_empty()).then_some(log_flag))
+			.output()
+			.unwrap()
+	};
+
+	let output = sign("");
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains(&format!("written to: {signature_path}")));
+	let output = verify("");
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8(output.stdout).unwrap(), "Valid signature!\n");
+
+	// Status output is suppressed, but the commands still succeed
+	let output = sign("--quiet");
+	assert!(output.status.success());
+	assert!(output.stdout.is_empty());
+	let output = verify("--quiet");
+	assert!(output.status.success());
+	assert!(output.stdout.is_empty());
+	assert!(output.stderr.is_empty());
+
+	// Errors are still printed
+	std::fs::write(payload_path, "other data").unwrap();
+	let output = verify("--quiet");
+	assert!(!output.status.success());
+	assert!(output.stdout.is_empty());
+	assert!(String::from_utf8(output.stderr)
+		.unwrap()
+		.contains("Signature not valid"));
+}
@@ -4,7 +4,6 @@ use std::{
 	process::{Command, Stdio},
 };
 
-use borsh::BorshDeserialize;
 use qos_client::yubikey::{
 	generate_signed_certificate, import_key_and_generate_signed_certificate,
 	key_agreement, sign_data, DEFAULT_PIN, KEY_AGREEMENT_SLOT, SIGNING_SLOT,
@@ -95,7 +94,7 @@ fn key_agreement_works(yubikey: &mut YubiKey) {
 
 	// get the sender eph key from the encryption envelope
 	let sender_pub_bytes = {
-		let decoded = Envelope::try_from_slice(&envelope).unwrap();
+		let decoded = Envelope::from_bytes(&envelope).unwrap();
 		decoded.ephemeral_sender_public
 	};
 
@@ -171,7 +170,7 @@ fn import_key_agreement_works(yubikey: &mut YubiKey) {
 
 	// get the sender eph key from the encryption envelope
 	let sender_pub_bytes = {
-		let decoded = Envelope::try_from_slice(&envelope).unwrap();
+		let decoded = Envelope::from_bytes(&envelope).unwrap();
 		decoded.ephemeral_sender_public
 	};
 
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qos_core::protocol::decode_request;
+
+// let the fuzzer send arbitrary bytes to the enclave, as a host could
+fuzz_target!(|data: &[u8]| {
+	let Ok(msg) = decode_request(data) else {
+		return;
+	};
+
+	// anything that decodes must encode and decode back to the same message
+	let encoded = borsh::to_vec(&msg).expect("decoded messages encode");
+	assert_eq!(decode_request(&encoded).expect("encoded messages decode"), msg);
+});
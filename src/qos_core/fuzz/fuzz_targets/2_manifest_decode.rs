@@ -0,0 +1,16 @@
+#![no_main]
+
+use borsh::BorshDeserialize;
+use libfuzzer_sys::fuzz_target;
+use qos_core::protocol::services::boot::Manifest;
+
+// let the fuzzer come up with arbitrary bytes to decode as a Manifest
+fuzz_target!(|data: &[u8]| {
+	let Ok(decoded) = Manifest::try_from_slice(data) else {
+		return;
+	};
+
+	// anything that decodes must encode and decode back to the same value
+	let encoded = borsh::to_vec(&decoded).expect("decoded values encode");
+	assert_eq!(Manifest::try_from_slice(&encoded).unwrap(), decoded);
+});
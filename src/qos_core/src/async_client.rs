@@ -0,0 +1,60 @@
+//! Async streaming socket based client to connect with
+//! [`crate::async_server::AsyncSocketServer`] or
+//! [`crate::server::SocketServer`].
+
+use std::time::Duration;
+
+use crate::{
+	client::ClientError,
+	io::{AsyncStream, Backoff, IOError, SocketAddress, TimeVal, TimeValLike},
+};
+
+/// Async client for communicating with the enclave
+/// [`crate::server::SocketServer`].
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+	addr: SocketAddress,
+	timeout: Duration,
+	backoff: Option<Backoff>,
+}
+
+impl AsyncClient {
+	/// Create a new client.
+	#[must_use]
+	pub fn new(addr: SocketAddress, timeout: TimeVal) -> Self {
+		let timeout = Duration::from_micros(
+			u64::try_from(timeout.num_microseconds()).unwrap_or_default(),
+		);
+		Self { addr, timeout, backoff: None }
+	}
+
+	/// Keep retrying to connect with `backoff` when the server is not
+	/// reachable. Requests are only retried if they could not be sent, so a
+	/// request is never processed twice.
+	#[must_use]
+	pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+		self.backoff = Some(backoff);
+		self
+	}
+
+	/// Send raw bytes and wait for a response until the clients configured
+	/// timeout.
+	pub async fn send(&self, request: &[u8]) -> Result<Vec<u8>, ClientError> {
+		let exchange = async {
+			let stream = match self.backoff {
+				Some(backoff) => {
+					AsyncStream::connect_with_backoff(&self.addr, backoff)
+						.await?
+				}
+				None => AsyncStream::connect(&self.addr).await?,
+			};
+			stream.send(request).await?;
+			stream.recv().await
+		};
+
+		tokio::time::timeout(self.timeout, exchange)
+			.await
+			.map_err(|_| IOError::RecvTimeout)?
+			.map_err(Into::into)
+	}
+}
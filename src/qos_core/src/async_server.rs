@@ -0,0 +1,301 @@
+//! Async streaming socket based server for use in an enclave. Listens for
+//! connections from [`crate::async_client::AsyncClient`] or
+//! [`crate::client::Client`].
+//!
+//! Unlike [`crate::server::SocketServer`], each connection is handled in its
+//! own task so slow requests do not block reading from other connections.
+
+use std::{
+	future::Future,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use tokio::task::JoinSet;
+
+use crate::{
+	io::{AsyncListener, AsyncStream, IOError, SocketAddress},
+	log,
+	server::{RequestProcessor, SocketServerError, SOCKET_SERVER_TIMEOUT_SECS},
+};
+
+/// Something that can asynchronously process requests.
+pub trait AsyncRequestProcessor: Send + Sync + 'static {
+	/// Process an incoming request and return a response.
+	///
+	/// See [`RequestProcessor::process`].
+	fn process(&self, request: Vec<u8>)
+		-> impl Future<Output = Vec<u8>> + Send;
+}
+
+/// Adapter to use a blocking [`RequestProcessor`] with [`AsyncSocketServer`].
+///
+/// Requests are still processed one at a time, on a thread where blocking is
+/// allowed, but reading and writing to connections can overlap.
+pub struct SharedProcessor<R> {
+	inner: Arc<Mutex<R>>,
+}
+
+impl<R: RequestProcessor> SharedProcessor<R> {
+	/// Create a new instance of [`Self`].
+	pub fn new(processor: R) -> Self {
+		Self { inner: Arc::new(Mutex::new(processor)) }
+	}
+}
+
+impl<R: RequestProcessor + Send + 'static> AsyncRequestProcessor
+	for SharedProcessor<R>
+{
+	async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+		let inner = self.inner.clone();
+		tokio::task::spawn_blocking(move || {
+			inner
+				.lock()
+				.expect("a request processor panicked. qed.")
+				.process(request)
+		})
+		.await
+		.expect("a request processor panicked. qed.")
+	}
+}
+
+/// A bare bones, async socket based server.
+pub struct AsyncSocketServer;
+
+impl AsyncSocketServer {
+	/// Listen and respond to incoming requests with the given `processor`.
+	///
+	/// Must be called from within a tokio runtime.
+	pub async fn listen<P: AsyncRequestProcessor>(
+		addr: SocketAddress,
+		processor: P,
+	) -> Result<(), SocketServerError> {
+		Self::listen_with_timeout(
+			addr,
+			processor,
+			Duration::from_secs(SOCKET_SERVER_TIMEOUT_SECS.unsigned_abs()),
+		)
+		.await
+	}
+
+	/// Like [`Self::listen`], but with a custom `timeout` for receiving a
+	/// request and for sending its response.
+	pub async fn listen_with_timeout<P: AsyncRequestProcessor>(
+		addr: SocketAddress,
+		processor: P,
+		timeout: Duration,
+	) -> Result<(), SocketServerError> {
+		Self::serve(addr, processor, timeout, None, std::future::pending())
+			.await
+	}
+
+	/// Like [`Self::listen_with_timeout`], but also close connections that do
+	/// not make progress receiving a request or sending its response for
+	/// `idle_timeout`, so leaked connections are reaped long before `timeout`
+	/// while slow but steady transfers of large messages are left alone.
+	pub async fn listen_with_idle_timeout<P: AsyncRequestProcessor>(
+		addr: SocketAddress,
+		processor: P,
+		timeout: Duration,
+		idle_timeout: Duration,
+	) -> Result<(), SocketServerError> {
+		Self::serve(
+			addr,
+			processor,
+			timeout,
+			Some(idle_timeout),
+			std::future::pending(),
+		)
+		.await
+	}
+
+	/// Like [`Self::listen_with_timeout`], but stop accepting connections
+	/// once `shutdown` resolves. Returns after the requests already accepted
+	/// have been responded to, and the socket has been removed.
+	pub async fn listen_with_shutdown<P: AsyncRequestProcessor>(
+		addr: SocketAddress,
+		processor: P,
+		timeout: Duration,
+		shutdown: impl Future<Output = ()>,
+	) -> Result<(), SocketServerError> {
+		Self::serve(addr, processor, timeout, None, shutdown).await
+	}
+
+	async fn serve<P: AsyncRequestProcessor>(
+		addr: SocketAddress,
+		processor: P,
+		timeout: Duration,
+		idle_timeout: Option<Duration>,
+		shutdown: impl Future<Output = ()>,
+	) -> Result<(), SocketServerError> {
+		log::info(
+			"server",
+			format_args!("`AsyncSocketServer` listening on {addr:?}"),
+		);
+
+		let listener = AsyncListener::listen(addr)?;
+		let processor = Arc::new(processor);
+		let mut connections = JoinSet::new();
+		tokio::pin!(shutdown);
+
+		loop {
+			let accepted = tokio::select! {
+				accepted = listener.accept() => accepted,
+				() = &mut shutdown => break,
+				// Reap finished connections so the set does not grow forever
+				Some(_) = connections.join_next() => continue,
+			};
+			let stream = match accepted {
+				Ok(stream) => stream,
+				Err(err) => {
+					log::error(
+						"server",
+						format_args!(
+							"AsyncSocketServer::listen accept error: {err:?}"
+						),
+					);
+					continue;
+				}
+			};
+
+			let processor = processor.clone();
+			connections.spawn(async move {
+				let request = tokio::select! {
+					request = tokio::time::timeout(timeout, stream.recv()) => {
+						request.unwrap_or(Err(IOError::RecvTimeout))
+					}
+					() = Self::idle(&stream, idle_timeout) => {
+						Err(IOError::RecvTimeout)
+					}
+				};
+				match request {
+					Ok(payload) => {
+						let response = processor.process(payload).await;
+						tokio::select! {
+							_ = tokio::time::timeout(
+								timeout,
+								stream.send(&response),
+							) => {},
+							() = Self::idle(&stream, idle_timeout) => {},
+						}
+					}
+					Err(err) => {
+						log::error(
+							"server",
+							format_args!(
+								"AsyncSocketServer::listen error: {err:?}"
+							),
+						);
+					}
+				}
+			});
+		}
+
+		drop(listener);
+		while connections.join_next().await.is_some() {}
+
+		Ok(())
+	}
+
+	/// Resolves once `stream` has been idle for `limit`, or never if there
+	/// is no limit.
+	async fn idle(stream: &AsyncStream, limit: Option<Duration>) {
+		match limit {
+			Some(limit) => stream.idle(limit).await,
+			None => std::future::pending().await,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{io::Write, os::unix::net::UnixStream};
+
+	use super::*;
+	use crate::io::frame::FrameHeader;
+
+	struct EchoProcessor;
+	impl AsyncRequestProcessor for EchoProcessor {
+		async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[tokio::test]
+	async fn idle_connections_are_reaped() {
+		let path = "./async_idle_connections_are_reaped.sock";
+		let addr = SocketAddress::new_unix(path);
+		let server = tokio::spawn(AsyncSocketServer::listen_with_idle_timeout(
+			addr.clone(),
+			EchoProcessor,
+			Duration::from_secs(30),
+			Duration::from_millis(200),
+		));
+
+		// A client that connects but never sends anything gets disconnected,
+		// long before the timeout
+		let start = std::time::Instant::now();
+		let idle = AsyncStream::connect(&addr).await.unwrap();
+		assert!(matches!(
+			tokio::time::timeout(Duration::from_secs(5), idle.recv())
+				.await
+				.unwrap(),
+			Err(IOError::RecvConnectionClosed)
+		));
+		assert!(start.elapsed() < Duration::from_secs(5));
+
+		// A client that takes longer than the idle timeout overall, but keeps
+		// making progress, is served
+		let payload = b"slow but steady".to_vec();
+		let response = tokio::task::spawn_blocking(move || {
+			let mut stream = UnixStream::connect(path).unwrap();
+			let header = FrameHeader::for_payload(&payload).unwrap().encode();
+			for byte in header.iter().chain(payload.iter()) {
+				stream.write_all(&[*byte]).unwrap();
+				std::thread::sleep(Duration::from_millis(50));
+			}
+			let mut response = vec![0; header.len() + payload.len()];
+			std::io::Read::read_exact(&mut stream, &mut response).unwrap();
+			response[header.len()..].to_vec()
+		})
+		.await
+		.unwrap();
+		assert_eq!(response, b"slow but steady".to_vec());
+
+		server.abort();
+	}
+
+	#[tokio::test]
+	async fn shutdown_waits_for_accepted_requests() {
+		struct SlowProcessor;
+		impl AsyncRequestProcessor for SlowProcessor {
+			async fn process(&self, request: Vec<u8>) -> Vec<u8> {
+				tokio::time::sleep(Duration::from_millis(200)).await;
+				request
+			}
+		}
+
+		let path = "./async_shutdown_waits_for_accepted_requests.sock";
+		let addr = SocketAddress::new_unix(path);
+		let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+		let server = tokio::spawn(AsyncSocketServer::listen_with_shutdown(
+			addr.clone(),
+			SlowProcessor,
+			Duration::from_secs(5),
+			async {
+				let _ = shutdown_rx.await;
+			},
+		));
+
+		let stream = AsyncStream::connect(&addr).await.unwrap();
+		stream.send(b"in flight").await.unwrap();
+		// Let the server accept the connection before shutting down
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		shutdown_tx.send(()).unwrap();
+
+		assert_eq!(stream.recv().await.unwrap(), b"in flight".to_vec());
+		server.await.unwrap().unwrap();
+		assert!(!std::path::Path::new(path).exists());
+		assert!(AsyncStream::connect(&addr).await.is_err());
+	}
+}
@@ -0,0 +1,323 @@
+//! Hash chained audit logs. Each entry commits to the one before it, so
+//! entries cannot be altered, removed or reordered without breaking the
+//! chain.
+//!
+//! Two logs are kept:
+//!
+//! - `qos_host` logs the messages it proxied to the enclave and exports them
+//!   under its `/audit-log` route. The host is not trusted, so its log can
+//!   only be checked with [`AuditEntry::verify_chain`].
+//! - The enclave logs the requests that change its state or use its keys, see
+//!   [`crate::protocol::msg::ProtocolMsg::AuditLogRequest`]. Exported entries
+//!   are signed by the Quorum Key and checked with
+//!   [`AuditEntry::verify_signed`].
+
+use std::{
+	collections::VecDeque,
+	fmt,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+use qos_crypto::sha_256;
+use qos_p256::P256Public;
+
+use crate::{protocol::Hash256, time::TimeSource};
+
+/// Entries the enclave keeps of its audit log.
+pub const ENCLAVE_AUDIT_LOG_CAPACITY: usize = 4_096;
+/// Most entries returned for one
+/// [`crate::protocol::msg::ProtocolMsg::AuditLogRequest`].
+pub const MAX_AUDIT_ENTRIES_PER_REQUEST: u32 = 256;
+/// [`AuditEntry::status`] of an enclave request that succeeded.
+pub const AUDIT_STATUS_OK: u16 = 200;
+/// [`AuditEntry::status`] of an enclave request that failed.
+pub const AUDIT_STATUS_ERROR: u16 = 500;
+
+/// Prefix of the message the Quorum Key signs to vouch for an entry of the
+/// enclave's log, so the signature cannot be mistaken for one over app data.
+const SIGNED_HEAD_DOMAIN: &[u8] = b"QOS_AUDIT_LOG_HEAD";
+
+/// A message the host proxied to the enclave, or a request the enclave
+/// handled.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+	/// Position of the entry in the log, starting at 0.
+	pub index: u64,
+	/// Path the message was posted to, or for the enclave's log the kind of
+	/// request, e.g. `BootStandardRequest`.
+	pub route: String,
+	/// Sha256 of the message.
+	#[serde(with = "qos_hex::serde")]
+	pub payload_hash: Hash256,
+	/// Milliseconds since the unix epoch when the host responded.
+	pub timestamp_ms: u64,
+	/// Status code the host responded with. For the enclave's log
+	/// [`AUDIT_STATUS_OK`] or [`AUDIT_STATUS_ERROR`].
+	pub status: u16,
+	/// [`Self::hash`] of the previous entry, all zeros for the first one.
+	#[serde(with = "qos_hex::serde")]
+	pub prev_hash: Hash256,
+	/// Sha256 over all other fields of this entry.
+	#[serde(with = "qos_hex::serde")]
+	pub hash: Hash256,
+}
+
+impl AuditEntry {
+	/// Sha256 over all fields of this entry but [`Self::hash`].
+	///
+	/// # Panics
+	///
+	/// Never, the fields can always be serialized.
+	#[must_use]
+	pub fn compute_hash(&self) -> Hash256 {
+		let encoded = borsh::to_vec(&(
+			self.index,
+			&self.route,
+			self.payload_hash,
+			self.timestamp_ms,
+			self.status,
+			self.prev_hash,
+		))
+		.expect("audit entries can always serialize. qed.");
+
+		sha_256(&encoded)
+	}
+
+	/// Whether `entries` are consecutive and each one is intact and commits to
+	/// the one before it. The first entry is taken on trust, so an exported
+	/// log that starts after index 0 can be checked too.
+	#[must_use]
+	pub fn verify_chain(entries: &[Self]) -> bool {
+		Self::first_break(entries).is_none()
+	}
+
+	/// Position in `entries` of the first entry that is not intact or does
+	/// not follow the one before it, if any. See [`Self::verify_chain`].
+	#[must_use]
+	pub fn first_break(entries: &[Self]) -> Option<usize> {
+		entries.iter().enumerate().position(|(i, entry)| {
+			let follows = i.checked_sub(1).map_or(true, |prev| {
+				let prev = &entries[prev];
+				prev.index.checked_add(1) == Some(entry.index)
+					&& entry.prev_hash == prev.hash
+			});
+			entry.hash != entry.compute_hash() || !follows
+		})
+	}
+
+	/// The message the Quorum Key signs to vouch for this entry and, through
+	/// the chain, every entry before it.
+	#[must_use]
+	pub fn signed_head(&self) -> Vec<u8> {
+		[SIGNED_HEAD_DOMAIN, &self.hash[..]].concat()
+	}
+
+	/// Whether `entries` form a chain, see [`Self::verify_chain`], and
+	/// `signature` is a signature of `quorum_key` over the
+	/// [`Self::signed_head`] of the last entry. An empty list has nothing to
+	/// vouch for and never verifies.
+	#[must_use]
+	pub fn verify_signed(
+		entries: &[Self],
+		quorum_key: &P256Public,
+		signature: &[u8],
+	) -> bool {
+		entries.last().is_some_and(|head| {
+			quorum_key.verify(&head.signed_head(), signature).is_ok()
+		}) && Self::verify_chain(entries)
+	}
+}
+
+/// Keeps the latest entries of an audit log in memory.
+pub struct AuditLog {
+	capacity: usize,
+	time: Arc<dyn TimeSource>,
+	inner: Mutex<Inner>,
+}
+
+impl fmt::Debug for AuditLog {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AuditLog")
+			.field("capacity", &self.capacity)
+			.field("inner", &self.inner)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+	entries: VecDeque<AuditEntry>,
+	next_index: u64,
+	last_hash: Hash256,
+}
+
+impl AuditLog {
+	/// Create an empty log that keeps the latest `capacity` entries,
+	/// timestamped with `time`.
+	#[must_use]
+	pub fn new(capacity: usize, time: Arc<dyn TimeSource>) -> Self {
+		Self { capacity, time, inner: Mutex::default() }
+	}
+
+	/// Append an entry for a message with `payload_hash` posted to `route`
+	/// that was answered with `status`.
+	pub fn record(&self, route: &str, payload_hash: Hash256, status: u16) {
+		let timestamp_ms = self.time.now_ms().unwrap_or(0);
+
+		let mut inner =
+			self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+		let mut entry = AuditEntry {
+			index: inner.next_index,
+			route: route.to_string(),
+			payload_hash,
+			timestamp_ms,
+			status,
+			prev_hash: inner.last_hash,
+			hash: [0; 32],
+		};
+		entry.hash = entry.compute_hash();
+
+		inner.next_index += 1;
+		inner.last_hash = entry.hash;
+		if inner.entries.len() == self.capacity {
+			inner.entries.pop_front();
+		}
+		if self.capacity > 0 {
+			inner.entries.push_back(entry);
+		}
+	}
+
+	/// The entries kept in memory, oldest first.
+	#[must_use]
+	pub fn entries(&self) -> Vec<AuditEntry> {
+		self.read(0, usize::MAX)
+	}
+
+	/// Up to `max` of the entries kept in memory with an index of at least
+	/// `since`, oldest first.
+	#[must_use]
+	pub fn read(&self, since: u64, max: usize) -> Vec<AuditEntry> {
+		let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+		inner
+			.entries
+			.iter()
+			.skip_while(|entry| entry.index < since)
+			.take(max)
+			.cloned()
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use qos_p256::P256Pair;
+
+	use super::*;
+	use crate::time::MockClock;
+
+	fn chain(len: u64) -> Vec<AuditEntry> {
+		let mut prev_hash = [0; 32];
+		(0..len)
+			.map(|index| {
+				let mut entry = AuditEntry {
+					index,
+					route: "/qos/message".to_string(),
+					payload_hash: [1; 32],
+					timestamp_ms: 1_000,
+					status: 200,
+					prev_hash,
+					hash: [0; 32],
+				};
+				entry.hash = entry.compute_hash();
+				prev_hash = entry.hash;
+				entry
+			})
+			.collect()
+	}
+
+	#[test]
+	fn first_break_finds_the_first_bad_entry() {
+		let entries = chain(4);
+		assert_eq!(AuditEntry::first_break(&entries), None);
+		assert_eq!(AuditEntry::first_break(&entries[1..]), None);
+		assert_eq!(AuditEntry::first_break(&[]), None);
+
+		let mut tampered = entries.clone();
+		tampered[2].status = 500;
+		assert_eq!(AuditEntry::first_break(&tampered), Some(2));
+
+		let mut reordered = entries;
+		reordered.swap(1, 2);
+		assert_eq!(AuditEntry::first_break(&reordered), Some(1));
+		assert!(!AuditEntry::verify_chain(&reordered));
+	}
+
+	#[test]
+	fn first_break_does_not_overflow() {
+		let mut entries = chain(2);
+		entries[0].index = u64::MAX;
+		entries[0].hash = entries[0].compute_hash();
+		entries[1].prev_hash = entries[0].hash;
+		entries[1].index = 0;
+		entries[1].hash = entries[1].compute_hash();
+		assert_eq!(AuditEntry::first_break(&entries), Some(1));
+	}
+
+	#[test]
+	fn log_chains_entries_and_detects_tampering() {
+		let log =
+			AuditLog::new(3, Arc::new(MockClock::new(Duration::from_secs(1))));
+		for i in 0..5 {
+			log.record("/qos/message", [i; 32], 200);
+		}
+
+		let mut entries = log.entries();
+		assert_eq!(entries.len(), 3);
+		assert_eq!(entries[0].index, 2);
+		assert!(entries.iter().all(|entry| entry.timestamp_ms == 1_000));
+		assert!(AuditEntry::verify_chain(&entries));
+		assert_eq!(log.read(3, 1), entries[1..2]);
+		assert_eq!(log.read(0, 10), entries);
+
+		// Dropping an entry breaks the chain
+		let mut gapped = entries.clone();
+		gapped.remove(1);
+		assert!(!AuditEntry::verify_chain(&gapped));
+
+		// So does changing one
+		entries[1].status = 500;
+		assert!(!AuditEntry::verify_chain(&entries));
+	}
+
+	#[test]
+	fn verify_signed_checks_the_head_signature_and_chain() {
+		let quorum_key = P256Pair::generate().unwrap();
+		let entries = chain(3);
+		let signature = quorum_key.sign(&entries[2].signed_head()).unwrap();
+		let public = quorum_key.public_key();
+
+		assert!(AuditEntry::verify_signed(&entries, &public, &signature));
+		// The signature vouches for the head only
+		assert!(!AuditEntry::verify_signed(&entries[..2], &public, &signature));
+		assert!(!AuditEntry::verify_signed(&[], &public, &signature));
+
+		let other = P256Pair::generate().unwrap().public_key();
+		assert!(!AuditEntry::verify_signed(&entries, &other, &signature));
+
+		let mut tampered = entries;
+		tampered[0].status = 500;
+		assert!(!AuditEntry::verify_signed(&tampered, &public, &signature));
+	}
+}
@@ -6,10 +6,14 @@ use qos_nsm::{Nsm, NsmProvider};
 
 use crate::{
 	handles::Handles,
-	io::SocketAddress,
+	io::{SocketAddress, SocketPermissions, TimeVal, TimeValLike},
+	log,
 	parser::{GetParserForOptions, OptionsParser, Parser, Token},
+	protocol::ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
 	reaper::Reaper,
-	EPHEMERAL_KEY_FILE, MANIFEST_FILE, PIVOT_FILE, QUORUM_FILE, SEC_APP_SOCK,
+	server::ServerOptions,
+	EPHEMERAL_KEY_FILE, MANIFEST_FILE, PIVOT_FILE, PIVOT_INFO_FILE,
+	QUORUM_FILE, SEC_APP_SOCK,
 };
 
 /// "cid"
@@ -27,7 +31,26 @@ pub const PIVOT_FILE_OPT: &str = "pivot-file";
 pub const EPHEMERAL_FILE_OPT: &str = "ephemeral-file";
 /// Name for the option to specify the manifest file.
 pub const MANIFEST_FILE_OPT: &str = "manifest-file";
+/// Name for the option to specify the pivot info file.
+pub const PIVOT_INFO_FILE_OPT: &str = "pivot-info-file";
 const APP_USOCK: &str = "app-usock";
+/// Name for the option to specify an additional unix socket to listen on.
+pub const DEBUG_USOCK: &str = "debug-usock";
+/// Name for the option to specify the mode of the unix sockets.
+pub const USOCK_MODE: &str = "usock-mode";
+/// Name for the option to specify the owning user id of the unix sockets.
+pub const USOCK_UID: &str = "usock-uid";
+/// Name for the option to specify the owning group id of the unix sockets.
+pub const USOCK_GID: &str = "usock-gid";
+/// Name for the flag to serve requests with the async socket server.
+#[cfg(feature = "async")]
+pub const ASYNC: &str = "async";
+/// Name for the option to specify the mode of files written by [`Handles`].
+pub const FILE_MODE: &str = "file-mode";
+/// Name for the option to specify the user id key files must be owned by.
+pub const KEY_FILE_OWNER: &str = "key-file-owner";
+/// Seconds to wait on each send and receive to the secure app.
+pub const APP_TIMEOUT: &str = "app-timeout";
 
 /// CLI options for starting up the enclave server.
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -57,7 +80,7 @@ impl EnclaveOpts {
 		) {
 			#[cfg(feature = "vm")]
 			(Some(c), Some(p), None) => SocketAddress::new_vsock(
-				c.parse::<u32>().unwrap(),
+				parse_cid(c),
 				p.parse::<u32>().unwrap(),
 				crate::io::VMADDR_NO_FLAGS,
 			),
@@ -66,6 +89,76 @@ impl EnclaveOpts {
 		}
 	}
 
+	/// All addresses the enclave server should listen on: [`Self::addr`] and,
+	/// if specified, the debug unix socket.
+	fn addrs(&self) -> Vec<SocketAddress> {
+		let mut addrs = vec![self.addr()];
+		if let Some(debug_usock) = self.parsed.single(DEBUG_USOCK) {
+			addrs.push(SocketAddress::new_unix(debug_usock));
+		}
+
+		addrs
+	}
+
+	/// Permissions for the unix sockets the enclave server listens on, if
+	/// any were specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the mode is not octal or an id is not a valid `u32`.
+	fn socket_permissions(&self) -> Option<SocketPermissions> {
+		let mode = self.parsed.single(USOCK_MODE)?;
+		let parse_id = |opt| {
+			self.parsed.single(opt).map(|id| {
+				id.parse::<u32>().expect("Could not parse socket owner id")
+			})
+		};
+
+		Some(SocketPermissions {
+			mode: u32::from_str_radix(mode, 8)
+				.expect("Could not parse usock-mode as octal"),
+			uid: parse_id(USOCK_UID),
+			gid: parse_id(USOCK_GID),
+		})
+	}
+
+	/// [`Handles`] for the configured file paths, file mode and key file
+	/// owner.
+	///
+	/// # Panics
+	///
+	/// Panics if the file mode is not octal or the owner is neither `any` nor
+	/// a valid `u32`.
+	fn handles(&self) -> Handles {
+		let mut handles = Handles::new(
+			self.ephemeral_file(),
+			self.quorum_file(),
+			self.manifest_file(),
+			self.pivot_file(),
+			self.pivot_info_file(),
+		);
+		if let Some(mode) = self.parsed.single(FILE_MODE) {
+			handles = handles.with_file_mode(
+				u32::from_str_radix(mode, 8)
+					.expect("Could not parse file-mode as octal"),
+			);
+		}
+		if let Some(owner) = self.parsed.single(KEY_FILE_OWNER) {
+			let owner = if owner == "any" {
+				None
+			} else {
+				Some(
+					owner
+						.parse::<u32>()
+						.expect("Could not parse key-file-owner to u32"),
+				)
+			};
+			handles = handles.with_key_file_owner(owner);
+		}
+
+		handles
+	}
+
 	fn app_addr(&self) -> SocketAddress {
 		SocketAddress::new_unix(
 			self.parsed
@@ -74,6 +167,27 @@ impl EnclaveOpts {
 		)
 	}
 
+	/// Timeout for each send and receive to the secure app.
+	///
+	/// # Panics
+	///
+	/// Panics if the timeout is not a valid number of seconds.
+	fn app_timeout(&self) -> TimeVal {
+		TimeVal::seconds(self.parsed.single(APP_TIMEOUT).map_or(
+			ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
+			|secs| {
+				secs.parse::<i64>()
+					.expect("Could not parse app-timeout as seconds")
+			},
+		))
+	}
+
+	/// Whether to serve requests with the async socket server.
+	#[cfg(feature = "async")]
+	fn is_async(&self) -> bool {
+		self.parsed.flag(ASYNC).unwrap_or(false)
+	}
+
 	/// Get the [`NsmProvider`]
 	fn nsm(&self) -> Box<dyn NsmProvider + Send> {
 		if self.parsed.flag(MOCK).unwrap_or(false) {
@@ -120,6 +234,14 @@ impl EnclaveOpts {
 			.expect("has a default value.")
 			.clone()
 	}
+
+	/// Defaults to [`PIVOT_INFO_FILE`] if not explicitly specified
+	fn pivot_info_file(&self) -> String {
+		self.parsed
+			.single(PIVOT_INFO_FILE_OPT)
+			.expect("has a default value.")
+			.clone()
+	}
 }
 
 /// Enclave server CLI.
@@ -129,35 +251,63 @@ impl CLI {
 	pub fn execute() {
 		let mut args: Vec<String> = env::args().collect();
 		let opts = EnclaveOpts::new(&mut args);
+		log::global().set_print_level(opts.parsed.log_level());
 
 		if opts.parsed.version() {
 			println!("version: {}", env!("CARGO_PKG_VERSION"));
 		} else if opts.parsed.help() {
 			println!("{}", opts.parsed.info());
 		} else {
-			Reaper::execute(
-				&Handles::new(
-					opts.ephemeral_file(),
-					opts.quorum_file(),
-					opts.manifest_file(),
-					opts.pivot_file(),
-				),
+			#[cfg(feature = "async")]
+			if opts.is_async() {
+				Reaper::execute_async(
+					&opts.handles(),
+					opts.nsm(),
+					opts.addrs(),
+					opts.app_addr(),
+					None,
+					opts.app_timeout(),
+				);
+				return;
+			}
+
+			Reaper::execute_with_options(
+				&opts.handles(),
 				opts.nsm(),
-				opts.addr(),
+				opts.addrs(),
 				opts.app_addr(),
 				None,
+				ServerOptions {
+					permissions: opts.socket_permissions(),
+					..Default::default()
+				},
+				opts.app_timeout(),
 			);
 		}
 	}
 }
 
+/// Parse a VSOCK cid, accepting `any` for [`crate::io::VMADDR_CID_ANY`].
+///
+/// # Panics
+///
+/// Panics if `cid` is neither `any` nor a valid `u32`.
+#[cfg(feature = "vm")]
+fn parse_cid(cid: &str) -> u32 {
+	if cid == "any" {
+		crate::io::VMADDR_CID_ANY
+	} else {
+		cid.parse::<u32>().expect("Could not parse cid to u32")
+	}
+}
+
 /// Parser for enclave CLI
 struct EnclaveParser;
 impl GetParserForOptions for EnclaveParser {
 	fn parser() -> Parser {
-		Parser::new()
+		let parser = Parser::new()
 			.token(
-				Token::new(CID, "cid of the VSOCK the enclave should listen on.")
+				Token::new(CID, "cid of the VSOCK the enclave should listen on. Use `any` to listen on any cid.")
 					.takes_value(true)
 					.forbids(vec![USOCK])
 					.requires(PORT),
@@ -169,10 +319,14 @@ impl GetParserForOptions for EnclaveParser {
 					.requires(CID),
 			)
 			.token(
-				Token::new(USOCK, "unix socket (`.sock`) to listen on.")
+				Token::new(USOCK, "unix socket (`.sock`) to listen on. Prefix with `@` for an abstract socket.")
 					.takes_value(true)
 					.forbids(vec!["port", "cid"]),
 			)
+			.token(
+				Token::new(DEBUG_USOCK, "additional unix socket (`.sock`) to listen on. Useful for debugging an enclave locally while it serves the host.")
+					.takes_value(true),
+			)
 			.token(
 				Token::new(MOCK, "include to use the mock Nitro Secure Module; helpful for local dev.")
 			)
@@ -196,17 +350,58 @@ impl GetParserForOptions for EnclaveParser {
 					.takes_value(true)
 					.default_value(MANIFEST_FILE)
 			)
+			.token(
+				Token::new(PIVOT_INFO_FILE_OPT, "path to file where the pivot info should be written. Use default for production")
+					.takes_value(true)
+					.default_value(PIVOT_INFO_FILE)
+			)
+			.token(
+				Token::new(FILE_MODE, "octal mode to set on the files the enclave writes, e.g. `400`. Write bits are ignored. Defaults to `444`.")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(KEY_FILE_OWNER, "user id that key files must be owned by, or `any` to skip the check. Defaults to the user running the enclave.")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(USOCK_MODE, "octal mode to set on the unix sockets the enclave listens on, e.g. `660`.")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(USOCK_UID, "user id to own the unix sockets the enclave listens on.")
+					.takes_value(true)
+					.requires(USOCK_MODE)
+			)
+			.token(
+				Token::new(USOCK_GID, "group id to own the unix sockets the enclave listens on.")
+					.takes_value(true)
+					.requires(USOCK_MODE)
+			)
 			.token(
 				Token::new(APP_USOCK, "the socket the secure app is listening on.")
 					.takes_value(true)
 					.default_value(SEC_APP_SOCK)
 			)
+			.token(
+				Token::new(APP_TIMEOUT, "seconds to wait on each send and receive to the secure app before failing the request with a timeout error. Defaults to 5.")
+					.takes_value(true)
+			);
+
+		#[cfg(feature = "async")]
+		let parser = parser.token(
+			Token::new(ASYNC, "include to serve requests with the async socket server, so requests to the secure app do not wait on each other. Cannot set the permissions of the unix sockets.")
+				.forbids(vec![USOCK_MODE, USOCK_UID, USOCK_GID]),
+		);
+
+		parser
 	}
 }
 
 #[cfg(test)]
 mod test {
 	use super::*;
+	#[cfg(feature = "async")]
+	use crate::parser::ParserError;
 
 	#[test]
 	fn parse_is_idempotent() {
@@ -306,6 +501,31 @@ mod test {
 		assert_eq!(opts.manifest_file(), "brawndo".to_string());
 	}
 
+	#[test]
+	fn parse_pivot_info_file() {
+		let mut args: Vec<_> = vec!["binary", "--usock", "./test.sock"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let opts = EnclaveOpts::new(&mut args);
+
+		assert_eq!(opts.pivot_info_file(), PIVOT_INFO_FILE.to_string());
+
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./test.sock",
+			"--pivot-info-file",
+			"electrolytes",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = EnclaveOpts::new(&mut args);
+
+		assert_eq!(opts.pivot_info_file(), "electrolytes".to_string());
+	}
+
 	#[test]
 	#[should_panic = "Entered invalid CLI args: MutuallyExclusiveInput(\"cid\", \"usock\")"]
 	fn panic_on_too_many_opts() {
@@ -343,6 +563,159 @@ mod test {
 		);
 	}
 
+	#[test]
+	#[cfg(feature = "vm")]
+	fn build_vsock_with_any_cid() {
+		let mut args: Vec<_> = vec!["binary", "--cid", "any", "--port", "3"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let opts = EnclaveOpts::new(&mut args);
+
+		assert_eq!(
+			opts.addr(),
+			SocketAddress::new_vsock(
+				crate::io::VMADDR_CID_ANY,
+				3,
+				crate::io::VMADDR_NO_FLAGS
+			)
+		);
+	}
+
+	#[test]
+	fn build_addrs_with_debug_usock() {
+		let mut args: Vec<_> =
+			vec!["binary", "--usock", "./x.sock", "--debug-usock", "./y.sock"]
+				.into_iter()
+				.map(String::from)
+				.collect();
+		let opts = EnclaveOpts::new(&mut args);
+
+		assert_eq!(
+			opts.addrs(),
+			vec![
+				SocketAddress::new_unix("./x.sock"),
+				SocketAddress::new_unix("./y.sock")
+			]
+		);
+	}
+
+	#[test]
+	fn build_socket_permissions() {
+		let mut args: Vec<_> = vec!["binary", "--usock", "./x.sock"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let opts = EnclaveOpts::new(&mut args);
+		assert_eq!(opts.socket_permissions(), None);
+
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./x.sock",
+			"--usock-mode",
+			"660",
+			"--usock-gid",
+			"1000",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = EnclaveOpts::new(&mut args);
+		assert_eq!(
+			opts.socket_permissions(),
+			Some(SocketPermissions { mode: 0o660, uid: None, gid: Some(1000) })
+		);
+	}
+
+	#[test]
+	fn build_handles_with_file_mode_and_owner() {
+		use std::os::unix::fs::MetadataExt;
+
+		let quorum_file: qos_test_primitives::PathWrapper =
+			"./build_handles_with_file_mode_and_owner.secret".into();
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./x.sock",
+			"--quorum-file",
+			&*quorum_file,
+			"--file-mode",
+			"400",
+			"--key-file-owner",
+			"any",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let handles = EnclaveOpts::new(&mut args).handles();
+
+		let pair = qos_p256::P256Pair::generate().unwrap();
+		handles.put_quorum_key(&pair).unwrap();
+		let mode = std::fs::metadata(&*quorum_file).unwrap().mode() & 0o777;
+		assert_eq!(mode, 0o400);
+		assert!(handles.get_quorum_key().unwrap() == pair);
+	}
+
+	#[test]
+	fn parse_app_timeout() {
+		let mut args: Vec<_> = vec!["binary", "--usock", "./x.sock"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let opts = EnclaveOpts::new(&mut args);
+		assert_eq!(
+			opts.app_timeout(),
+			TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS)
+		);
+
+		let mut args: Vec<_> =
+			vec!["binary", "--usock", "./x.sock", "--app-timeout", "30"]
+				.into_iter()
+				.map(String::from)
+				.collect();
+		let opts = EnclaveOpts::new(&mut args);
+		assert_eq!(opts.app_timeout(), TimeVal::seconds(30));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn async_keeps_app_timeout_and_forbids_socket_permissions() {
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./x.sock",
+			"--async",
+			"--app-timeout",
+			"30",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = EnclaveOpts::new(&mut args);
+		assert!(opts.is_async());
+		assert_eq!(opts.app_timeout(), TimeVal::seconds(30));
+
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./x.sock",
+			"--async",
+			"--usock-mode",
+			"660",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		assert_eq!(
+			OptionsParser::<EnclaveParser>::parse(&mut args),
+			Err(ParserError::MutuallyExclusiveInput(
+				ASYNC.to_string(),
+				USOCK_MODE.to_string()
+			))
+		);
+	}
+
 	#[test]
 	#[should_panic = "Entered invalid CLI args: UnexpectedInput(\"--durp\")"]
 	fn panic_when_mistyped_cid() {
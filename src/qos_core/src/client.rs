@@ -1,7 +1,12 @@
 //! Streaming socket based client to connect with
 //! [`crate::server::SocketServer`].
 
-use crate::io::{self, SocketAddress, Stream, TimeVal};
+#[cfg(any(feature = "mock", test))]
+use crate::io::memory::{MemoryConnector, MemoryStream};
+use crate::io::{
+	self, Backoff, Connection, SocketAddress, SocketPermissions, Stream,
+	TimeVal,
+};
 
 /// Enclave client error.
 #[derive(Debug)]
@@ -27,22 +32,163 @@ impl From<borsh::io::Error> for ClientError {
 /// Client for communicating with the enclave [`crate::server::SocketServer`].
 #[derive(Debug, Clone)]
 pub struct Client {
-	addr: SocketAddress,
+	target: Target,
 	timeout: TimeVal,
+	backoff: Option<Backoff>,
+	permissions: Option<SocketPermissions>,
+}
+
+/// Where a [`Client`] sends its requests.
+#[derive(Debug, Clone)]
+enum Target {
+	Socket(SocketAddress),
+	#[cfg(any(feature = "mock", test))]
+	Memory(MemoryConnector),
 }
 
 impl Client {
 	/// Create a new client.
 	#[must_use]
 	pub fn new(addr: SocketAddress, timeout: TimeVal) -> Self {
-		Self { addr, timeout }
+		Self {
+			target: Target::Socket(addr),
+			timeout,
+			backoff: None,
+			permissions: None,
+		}
+	}
+
+	/// Create a new client that talks to an in memory server. See
+	/// [`crate::server::SocketServer::listen_in_memory`].
+	#[cfg(any(feature = "mock", test))]
+	#[must_use]
+	pub fn in_memory(connector: MemoryConnector, timeout: TimeVal) -> Self {
+		Self {
+			target: Target::Memory(connector),
+			timeout,
+			backoff: None,
+			permissions: None,
+		}
+	}
+
+	/// Replace the timeout used for connecting, sending and receiving.
+	#[must_use]
+	pub fn with_timeout(mut self, timeout: TimeVal) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Keep retrying to connect with `backoff` when the server is not
+	/// reachable. Requests are only retried if they could not be sent, so a
+	/// request is never processed twice.
+	#[must_use]
+	pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+		self.backoff = Some(backoff);
+		self
+	}
+
+	/// Refuse to connect unless the unix socket file has exactly
+	/// `permissions`, so requests are not sent to a socket some other process
+	/// could have bound.
+	#[must_use]
+	pub fn with_socket_permissions(
+		mut self,
+		permissions: SocketPermissions,
+	) -> Self {
+		self.permissions = Some(permissions);
+		self
 	}
 
 	/// Send raw bytes and wait for a response until the clients configured
 	/// timeout.
 	pub fn send(&self, request: &[u8]) -> Result<Vec<u8>, ClientError> {
-		let stream = Stream::connect(&self.addr, self.timeout)?;
+		match &self.target {
+			Target::Socket(addr) => {
+				Self::exchange(&self.connect(addr)?, request)
+			}
+			#[cfg(any(feature = "mock", test))]
+			Target::Memory(connector) => {
+				let stream = connector.connect()?;
+				stream.set_timeout(self.timeout)?;
+				Self::exchange(&stream, request)
+			}
+		}
+	}
+
+	/// Start a request whose body is streamed to the server in chunks, so it
+	/// never has to be held in memory at once. See [`StreamingRequest`].
+	pub fn stream_request(&self) -> Result<StreamingRequest, ClientError> {
+		let inner = match &self.target {
+			Target::Socket(addr) => Streaming::Socket(self.connect(addr)?),
+			// In memory transports move whole messages, so just buffer.
+			#[cfg(any(feature = "mock", test))]
+			Target::Memory(connector) => {
+				let stream = connector.connect()?;
+				stream.set_timeout(self.timeout)?;
+				Streaming::Memory(stream, Vec::new())
+			}
+		};
+
+		Ok(StreamingRequest { inner })
+	}
+
+	fn connect(&self, addr: &SocketAddress) -> Result<Stream, ClientError> {
+		if let Some(permissions) = &self.permissions {
+			permissions.verify(addr)?;
+		}
+		let stream = match self.backoff {
+			Some(backoff) => {
+				Stream::connect_with_backoff(addr, self.timeout, backoff)?
+			}
+			None => Stream::connect(addr, self.timeout)?,
+		};
+
+		Ok(stream)
+	}
+
+	fn exchange(
+		stream: &impl Connection,
+		request: &[u8],
+	) -> Result<Vec<u8>, ClientError> {
 		stream.send(request)?;
 		stream.recv().map_err(Into::into)
 	}
 }
+
+/// A request being streamed to the server, created with
+/// [`Client::stream_request`]. Dropping it before calling [`Self::finish`]
+/// closes the connection, so the server never processes a partial request.
+pub struct StreamingRequest {
+	inner: Streaming,
+}
+
+enum Streaming {
+	Socket(Stream),
+	#[cfg(any(feature = "mock", test))]
+	Memory(MemoryStream, Vec<u8>),
+}
+
+impl StreamingRequest {
+	/// Send the next chunk of the request.
+	pub fn send_chunk(&mut self, chunk: &[u8]) -> Result<(), ClientError> {
+		match &mut self.inner {
+			Streaming::Socket(stream) => stream.send_partial(chunk)?,
+			#[cfg(any(feature = "mock", test))]
+			Streaming::Memory(_, buf) => buf.extend_from_slice(chunk),
+		}
+
+		Ok(())
+	}
+
+	/// Send the last chunk of the request and wait for the response.
+	pub fn finish(self, last_chunk: &[u8]) -> Result<Vec<u8>, ClientError> {
+		match self.inner {
+			Streaming::Socket(stream) => Client::exchange(&stream, last_chunk),
+			#[cfg(any(feature = "mock", test))]
+			Streaming::Memory(stream, mut buf) => {
+				buf.extend_from_slice(last_chunk);
+				Client::exchange(&stream, &buf)
+			}
+		}
+	}
+}
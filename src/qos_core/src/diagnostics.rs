@@ -0,0 +1,131 @@
+//! Reports of why the enclave process died, kept across restarts.
+//!
+//! A panic only writes to stderr, which is lost inside a production enclave.
+//! The hook installed by [`Diagnostics::install_panic_hook`] also writes a
+//! [`PanicReport`] to a file, so the enclave can report it in
+//! [`crate::protocol::status::EnclaveStatus::last_panic`], including after
+//! the enclave process is restarted. The [`crate::reaper::Reaper`] also logs
+//! the report of an earlier run when it starts, see [`crate::log`].
+
+use std::{fmt, fs, panic, thread};
+
+use crate::time::{SystemClock, TimeSource};
+
+/// Why and where the enclave process panicked.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicReport {
+	/// The panic message.
+	pub message: String,
+	/// Source location of the panic, as `file:line:column`.
+	pub location: Option<String>,
+	/// Name of the thread that panicked, if it has one.
+	pub thread: Option<String>,
+	/// Milliseconds since the unix epoch when the panic happened.
+	pub timestamp_ms: u64,
+}
+
+impl fmt::Display for PanicReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let thread = self.thread.as_deref().unwrap_or("<unnamed>");
+		let location = self.location.as_deref().unwrap_or("<unknown>");
+		write!(f, "thread '{thread}' panicked at {location}: {}", self.message)
+	}
+}
+
+/// Handle to the [`PanicReport`] file of the enclave, see
+/// [`crate::handles::Handles::panic_report_path`].
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+	path: String,
+}
+
+impl Diagnostics {
+	/// Create a new instance of [`Self`] keeping the report at `path`.
+	#[must_use]
+	pub fn new(path: String) -> Self {
+		Self { path }
+	}
+
+	/// Record every panic of this process as the latest report, before
+	/// running the panic hook that was installed before.
+	pub fn install_panic_hook(&self) {
+		let diagnostics = self.clone();
+		let previous = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			let message = info
+				.payload()
+				.downcast_ref::<&str>()
+				.map(ToString::to_string)
+				.or_else(|| info.payload().downcast_ref::<String>().cloned())
+				.unwrap_or_else(|| "Box<dyn Any>".to_string());
+			let report = PanicReport {
+				message,
+				location: info.location().map(ToString::to_string),
+				thread: thread::current().name().map(ToString::to_string),
+				timestamp_ms: SystemClock.now_ms().unwrap_or(0),
+			};
+			// The process may be going down, so there is nowhere to report
+			// failing to record the panic to.
+			drop(diagnostics.record(&report));
+
+			previous(info);
+		}));
+	}
+
+	/// Replace the latest report with `report`.
+	///
+	/// # Errors
+	///
+	/// Errors if the report cannot be written.
+	pub fn record(&self, report: &PanicReport) -> std::io::Result<()> {
+		fs::write(&self.path, borsh::to_vec(report)?)
+	}
+
+	/// The latest report, if the enclave ever panicked.
+	#[must_use]
+	pub fn last_panic(&self) -> Option<PanicReport> {
+		let encoded = fs::read(&self.path).ok()?;
+		borsh::from_slice(&encoded).ok()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+
+	#[test]
+	fn keeps_the_latest_report() {
+		let path: PathWrapper =
+			"/tmp/diagnostics_keeps_the_latest_report".into();
+		let diagnostics = Diagnostics::new(path.to_string());
+		assert_eq!(diagnostics.last_panic(), None);
+
+		let mut report = PanicReport {
+			message: "boom".to_string(),
+			location: Some("src/lib.rs:1:2".to_string()),
+			thread: None,
+			timestamp_ms: 7,
+		};
+		diagnostics.record(&report).unwrap();
+		report.message = "boom again".to_string();
+		diagnostics.record(&report).unwrap();
+
+		assert_eq!(diagnostics.last_panic(), Some(report.clone()));
+		assert_eq!(
+			report.to_string(),
+			"thread '<unnamed>' panicked at src/lib.rs:1:2: boom again"
+		);
+	}
+}
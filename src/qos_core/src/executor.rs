@@ -0,0 +1,351 @@
+//! Server the pivot can reach the executor on, for requests that only the
+//! pivot may make: attestation documents embedding app data, and secrets
+//! derived from the Quorum Key. Unlike the enclave server, it is not
+//! reachable from the host.
+//!
+//! The [`crate::reaper::Reaper`] listens on
+//! [`crate::handles::Handles::executor_socket_path`] and passes the path to
+//! the pivot in [`crate::EXECUTOR_SOCKET_ENV`].
+
+use std::{
+	sync::{Arc, Mutex, PoisonError},
+	time::Duration,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_nsm::{
+	nitro::AttestError,
+	types::{NsmRequest, NsmResponse},
+	NsmProvider,
+};
+use qos_p256::{
+	kdf::{DerivationContext, KeyPurpose},
+	P256_SECRET_LEN,
+};
+use zeroize::Zeroizing;
+
+use crate::{
+	handles::Handles,
+	protocol::{Hash256, ProtocolError, QosHash},
+	server::RequestProcessor,
+	time::{SystemClock, TimeSource},
+};
+
+/// Most bytes the NSM accepts as the `user_data` of an attestation document.
+const NSM_MAX_USER_DATA_LEN: usize = 512;
+
+/// Most bytes of app data an attestation document can embed: what is left of
+/// the NSM `user_data` after the manifest hash and the length prefix of the
+/// encoded [`AppAttestationData`].
+pub const MAX_APP_DATA_LEN: usize = NSM_MAX_USER_DATA_LEN - 32 - 4;
+
+/// Request/response messages between the pivot and the executor.
+#[derive(Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum ExecutorMsg {
+	/// Request a fresh attestation document whose `user_data` is the borsh
+	/// encoded [`AppAttestationData`] for `app_data`.
+	AppAttestationRequest {
+		/// Bytes chosen by the pivot, at most [`MAX_APP_DATA_LEN`] long.
+		app_data: Vec<u8>,
+		/// Nonce to include in the attestation document.
+		nonce: Option<Vec<u8>>,
+	},
+	/// Response to [`Self::AppAttestationRequest`].
+	AppAttestationResponse {
+		/// Response from the NSM with the attestation document.
+		nsm_response: NsmResponse,
+	},
+	/// Request the secret named `name`, derived from the Quorum Key for the
+	/// namespace of the manifest. The same name always gives the same secret
+	/// for a given Quorum Key and namespace.
+	AppSecretRequest {
+		/// Name of the secret, chosen by the pivot.
+		name: String,
+	},
+	/// Response to [`Self::AppSecretRequest`].
+	AppSecretResponse {
+		/// The derived secret.
+		secret: [u8; P256_SECRET_LEN],
+	},
+	/// The request could not be handled.
+	ErrorResponse(ProtocolError),
+}
+
+/// `user_data` of an attestation document requested by the pivot.
+///
+/// The documents QOS requests for itself have the manifest hash alone as
+/// their `user_data`, so the two cannot be mistaken for each other.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AppAttestationData {
+	/// Hash of the manifest the pivot is running under.
+	pub manifest_hash: Hash256,
+	/// Bytes chosen by the pivot.
+	pub app_data: Vec<u8>,
+}
+
+/// Processes [`ExecutorMsg`]s from the pivot.
+pub struct ExecutorProcessor {
+	attestor: Box<dyn NsmProvider + Send>,
+	handles: Handles,
+}
+
+impl ExecutorProcessor {
+	/// Create a new instance of [`Self`].
+	#[must_use]
+	pub fn new(
+		attestor: Box<dyn NsmProvider + Send>,
+		handles: Handles,
+	) -> Self {
+		Self { attestor, handles }
+	}
+
+	fn app_attestation_doc(
+		&self,
+		app_data: Vec<u8>,
+		nonce: Option<Vec<u8>>,
+	) -> Result<NsmResponse, ProtocolError> {
+		if app_data.len() > MAX_APP_DATA_LEN {
+			return Err(ProtocolError::OversizedAppData);
+		}
+		let manifest_hash =
+			self.handles.get_manifest_envelope()?.manifest.qos_hash();
+		let user_data =
+			borsh::to_vec(&AppAttestationData { manifest_hash, app_data })
+				.expect("AppAttestationData can always be serialized. qed.");
+
+		let request = NsmRequest::Attestation {
+			user_data: Some(user_data),
+			nonce,
+			public_key: None,
+		};
+
+		Ok(self.attestor.nsm_process_request(request))
+	}
+
+	fn app_secret(
+		&self,
+		name: &str,
+	) -> Result<Zeroizing<[u8; P256_SECRET_LEN]>, ProtocolError> {
+		if name.is_empty() {
+			return Err(ProtocolError::InvalidAppSecretName);
+		}
+		let namespace =
+			self.handles.get_manifest_envelope()?.manifest.namespace;
+		let context = DerivationContext::new(KeyPurpose::Application(name))
+			.with_namespace(&namespace.name);
+
+		Ok(self.handles.get_quorum_key()?.derive_key(&context)?)
+	}
+}
+
+impl RequestProcessor for ExecutorProcessor {
+	fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+		let response = match ExecutorMsg::try_from_slice(&request) {
+			Ok(ExecutorMsg::AppAttestationRequest { app_data, nonce }) => self
+				.app_attestation_doc(app_data, nonce)
+				.map_or_else(ExecutorMsg::ErrorResponse, |nsm_response| {
+					ExecutorMsg::AppAttestationResponse { nsm_response }
+				}),
+			Ok(ExecutorMsg::AppSecretRequest { name }) => self
+				.app_secret(&name)
+				.map_or_else(ExecutorMsg::ErrorResponse, |secret| {
+					ExecutorMsg::AppSecretResponse { secret: *secret }
+				}),
+			Ok(_) => ExecutorMsg::ErrorResponse(ProtocolError::InvalidMsg),
+			Err(_) => ExecutorMsg::ErrorResponse(
+				ProtocolError::ProtocolMsgDeserialization,
+			),
+		};
+
+		borsh::to_vec(&response)
+			.expect("ExecutorMsg can always be serialized. qed.")
+	}
+}
+
+/// [`NsmProvider`] shared between the enclave server and the executor
+/// server. Requests are made one at a time.
+#[derive(Clone)]
+pub(crate) struct SharedNsm(Arc<Mutex<Box<dyn NsmProvider + Send>>>);
+
+impl SharedNsm {
+	pub(crate) fn new(nsm: Box<dyn NsmProvider + Send>) -> Self {
+		Self(Arc::new(Mutex::new(nsm)))
+	}
+}
+
+impl NsmProvider for SharedNsm {
+	fn nsm_process_request(&self, request: NsmRequest) -> NsmResponse {
+		self.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.nsm_process_request(request)
+	}
+
+	fn timestamp_ms(&self) -> Result<u64, AttestError> {
+		self.0.lock().unwrap_or_else(PoisonError::into_inner).timestamp_ms()
+	}
+}
+
+/// The NSM is the trusted wall clock of the enclave: it reads the time from
+/// a fresh attestation document.
+impl TimeSource for SharedNsm {
+	fn now(&self) -> Result<Duration, ProtocolError> {
+		Ok(Duration::from_millis(self.timestamp_ms()?))
+	}
+
+	fn monotonic(&self) -> Duration {
+		SystemClock.monotonic()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_p256::P256Pair;
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+	use crate::protocol::services::boot::ManifestEnvelope;
+
+	/// Responds to attestation requests with the `user_data` as the document.
+	struct EchoNsm;
+	impl NsmProvider for EchoNsm {
+		fn nsm_process_request(&self, request: NsmRequest) -> NsmResponse {
+			match request {
+				NsmRequest::Attestation { user_data, .. } => {
+					NsmResponse::Attestation {
+						document: user_data.unwrap_or_default(),
+					}
+				}
+				_ => NsmResponse::LockPCR,
+			}
+		}
+
+		fn timestamp_ms(&self) -> Result<u64, AttestError> {
+			Ok(0)
+		}
+	}
+
+	fn process(
+		processor: &mut ExecutorProcessor,
+		msg: &ExecutorMsg,
+	) -> ExecutorMsg {
+		let response = processor.process(borsh::to_vec(msg).unwrap());
+		ExecutorMsg::try_from_slice(&response).unwrap()
+	}
+
+	#[test]
+	fn app_attestation_doc_embeds_app_data() {
+		let manifest_file: PathWrapper =
+			"./app_attestation_doc_embeds_app_data.manifest".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			"quorum".to_string(),
+			(*manifest_file).to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		let mut processor =
+			ExecutorProcessor::new(Box::new(EchoNsm), handles.clone());
+		let request = |app_data: Vec<u8>| ExecutorMsg::AppAttestationRequest {
+			app_data,
+			nonce: None,
+		};
+
+		// The manifest hash is embedded, so there must be a manifest
+		assert_eq!(
+			process(&mut processor, &request(vec![1; 33])),
+			ExecutorMsg::ErrorResponse(
+				ProtocolError::FailedToGetManifestEnvelope
+			)
+		);
+
+		let manifest_envelope = ManifestEnvelope::default();
+		handles.put_manifest_envelope(&manifest_envelope).unwrap();
+
+		let ExecutorMsg::AppAttestationResponse {
+			nsm_response: NsmResponse::Attestation { document },
+		} = process(&mut processor, &request(vec![1; 33]))
+		else {
+			panic!("expected an attestation document")
+		};
+		assert_eq!(
+			AppAttestationData::try_from_slice(&document).unwrap(),
+			AppAttestationData {
+				manifest_hash: manifest_envelope.manifest.qos_hash(),
+				app_data: vec![1; 33],
+			}
+		);
+
+		// The largest app data fits in the NSM user data
+		let ExecutorMsg::AppAttestationResponse {
+			nsm_response: NsmResponse::Attestation { document },
+		} = process(&mut processor, &request(vec![2; MAX_APP_DATA_LEN]))
+		else {
+			panic!("expected an attestation document")
+		};
+		assert_eq!(document.len(), NSM_MAX_USER_DATA_LEN);
+
+		assert_eq!(
+			process(&mut processor, &request(vec![2; MAX_APP_DATA_LEN + 1])),
+			ExecutorMsg::ErrorResponse(ProtocolError::OversizedAppData)
+		);
+		assert_eq!(
+			process(
+				&mut processor,
+				&ExecutorMsg::ErrorResponse(ProtocolError::InvalidMsg)
+			),
+			ExecutorMsg::ErrorResponse(ProtocolError::InvalidMsg)
+		);
+	}
+
+	#[test]
+	fn app_secrets_are_derived_from_the_quorum_key() {
+		let quorum_file: PathWrapper =
+			"./app_secrets_are_derived_from_the_quorum_key.quorum".into();
+		let manifest_file: PathWrapper =
+			"./app_secrets_are_derived_from_the_quorum_key.manifest".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			(*quorum_file).to_string(),
+			(*manifest_file).to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		let mut manifest_envelope = ManifestEnvelope::default();
+		manifest_envelope.manifest.namespace.name = "alpha".to_string();
+		handles.put_manifest_envelope(&manifest_envelope).unwrap();
+		let mut processor =
+			ExecutorProcessor::new(Box::new(EchoNsm), handles.clone());
+		let mut secret = |name: &str| {
+			process(
+				&mut processor,
+				&ExecutorMsg::AppSecretRequest { name: name.to_string() },
+			)
+		};
+
+		assert!(matches!(
+			secret("tls"),
+			ExecutorMsg::ErrorResponse(ProtocolError::FailedToGetQuorumKey(_))
+		));
+
+		let quorum_pair = P256Pair::generate().unwrap();
+		handles.put_quorum_key(&quorum_pair).unwrap();
+		let expected = quorum_pair
+			.derive_key(
+				&DerivationContext::new(KeyPurpose::Application("tls"))
+					.with_namespace("alpha"),
+			)
+			.unwrap();
+
+		assert_eq!(
+			secret("tls"),
+			ExecutorMsg::AppSecretResponse { secret: *expected }
+		);
+		assert_eq!(secret("tls"), secret("tls"));
+		assert_ne!(secret("tls"), secret("db"));
+		assert_eq!(
+			secret(""),
+			ExecutorMsg::ErrorResponse(ProtocolError::InvalidAppSecretName)
+		);
+	}
+}
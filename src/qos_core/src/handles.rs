@@ -1,23 +1,47 @@
 //! Logic for accessing read only QOS state.
 
-use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+use std::{
+	fs,
+	io::Write,
+	os::unix::fs::{MetadataExt, PermissionsExt},
+	path::Path,
+};
 
 use borsh::BorshDeserialize;
 use qos_p256::P256Pair;
 
-use crate::protocol::{services::boot::ManifestEnvelope, ProtocolError};
+use crate::protocol::{
+	services::boot::{EgressEndpoint, Manifest, ManifestEnvelope},
+	Hash256, ProtocolError, QosHash,
+};
+
+/// Default permission bits for files written by [`Handles`]: read only for
+/// everyone.
+pub const DEFAULT_FILE_MODE: u32 = 0o444;
+/// Permission bit for world writable files.
+const WORLD_WRITABLE: u32 = 0o002;
 
 /// Handle for accessing the quorum key.
 #[derive(Debug, Clone)]
 pub struct QuorumKeyHandle {
 	quorum: String,
+	key_file_owner: Option<u32>,
 }
 
 impl QuorumKeyHandle {
-	/// Create a new instance of [`Self`].
+	/// Create a new instance of [`Self`]. The owner of the key file is not
+	/// checked; see [`Self::with_key_file_owner`].
 	#[must_use]
 	pub fn new(quorum: String) -> Self {
-		Self { quorum }
+		Self { quorum, key_file_owner: None }
+	}
+
+	/// Refuse to load the key unless the key file is owned by the user with
+	/// id `owner`. `None` disables the check.
+	#[must_use]
+	pub fn with_key_file_owner(mut self, owner: Option<u32>) -> Self {
+		self.key_file_owner = owner;
+		self
 	}
 
 	/// Get the Quorum Key pair.
@@ -25,13 +49,138 @@ impl QuorumKeyHandle {
 	/// # Errors
 	///
 	/// Errors if the Quorum Key has not been put.
+	///
+	/// Refuses to load the key if the file is world writable or is not owned
+	/// by the expected user.
 	pub fn get_quorum_key(&self) -> Result<P256Pair, ProtocolError> {
+		check_key_file(&self.quorum, self.key_file_owner)?;
 		let pair = P256Pair::from_hex_file(&self.quorum)
 			.map_err(ProtocolError::FailedToGetQuorumKey)?;
 		Ok(pair)
 	}
 }
 
+/// Verify that the key file at `path` is not world writable and, if `owner`
+/// is given, is owned by that user.
+///
+/// If the file does not exist this does nothing so the caller can surface the
+/// relevant error when it tries to read the file.
+fn check_key_file<P: AsRef<Path>>(
+	path: P,
+	owner: Option<u32>,
+) -> Result<(), ProtocolError> {
+	let Ok(metadata) = fs::metadata(path) else {
+		return Ok(());
+	};
+
+	if metadata.mode() & WORLD_WRITABLE != 0 {
+		return Err(ProtocolError::WorldWritableKeyFile);
+	}
+	if owner.is_some_and(|owner| metadata.uid() != owner) {
+		return Err(ProtocolError::UnexpectedKeyFileOwner);
+	}
+
+	Ok(())
+}
+
+/// Information about the manifest the enclave booted with that is made
+/// available to the pivot.
+///
+/// This only contains fields the pivot is allowed to see. It is written by the
+/// [`crate::reaper::Reaper`] right before the pivot is spawned.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct PivotInfo {
+	/// Name of the namespace the enclave belongs to.
+	pub namespace: String,
+	/// Nonce of the manifest.
+	pub nonce: u32,
+	/// Quorum Key public key.
+	#[serde(with = "qos_hex::serde")]
+	pub quorum_key: Vec<u8>,
+	/// Hash of the manifest the enclave booted with.
+	#[serde(with = "qos_hex::serde")]
+	pub manifest_hash: Hash256,
+	/// Remote endpoints the pivot may connect to.
+	pub egress: Vec<EgressEndpoint>,
+}
+
+impl From<&Manifest> for PivotInfo {
+	fn from(manifest: &Manifest) -> Self {
+		Self {
+			namespace: manifest.namespace.name.clone(),
+			nonce: manifest.namespace.nonce,
+			quorum_key: manifest.namespace.quorum_key.clone(),
+			manifest_hash: manifest.qos_hash(),
+			egress: manifest.pivot.egress.clone(),
+		}
+	}
+}
+
+/// Handle for accessing the [`PivotInfo`]. Intended for use by the pivot.
+#[derive(Debug, Clone)]
+pub struct PivotInfoHandle {
+	pivot_info: String,
+}
+
+impl PivotInfoHandle {
+	/// Create a new instance of [`Self`].
+	#[must_use]
+	pub fn new(pivot_info: String) -> Self {
+		Self { pivot_info }
+	}
+
+	/// Create a new instance of [`Self`] for the path the
+	/// [`crate::reaper::Reaper`] passes to the pivot in
+	/// [`crate::PIVOT_INFO_FILE_ENV`]. Falls back to
+	/// [`crate::PIVOT_INFO_FILE`] if the variable is not set.
+	#[must_use]
+	pub fn from_env() -> Self {
+		Self::new(
+			std::env::var(crate::PIVOT_INFO_FILE_ENV)
+				.unwrap_or_else(|_| crate::PIVOT_INFO_FILE.to_string()),
+		)
+	}
+
+	/// Get the [`PivotInfo`].
+	///
+	/// # Errors
+	///
+	/// Errors if the pivot info has not been put.
+	pub fn get_pivot_info(&self) -> Result<PivotInfo, ProtocolError> {
+		let contents = fs::read(&self.pivot_info)
+			.map_err(|_| ProtocolError::FailedToGetPivotInfo)?;
+		PivotInfo::try_from_slice(&contents)
+			.map_err(|_| ProtocolError::FailedToGetPivotInfo)
+	}
+
+	/// Get the path to the app config, next to the pivot info.
+	#[must_use]
+	pub fn app_config_path(&self) -> String {
+		format!("{}.app_config", self.pivot_info)
+	}
+
+	/// Get the app config from the manifest, see
+	/// [`crate::protocol::services::boot::PivotConfig::app_config`].
+	///
+	/// # Errors
+	///
+	/// Errors if the app config has not been put.
+	pub fn get_app_config(&self) -> Result<Vec<u8>, ProtocolError> {
+		fs::read(self.app_config_path())
+			.map_err(|_| ProtocolError::FailedToGetAppConfig)
+	}
+}
+
 /// Handles for read only state accessible to all of QOS.
 ///
 /// All data here should be "put" once at some point in the boot flow. Once
@@ -46,6 +195,10 @@ pub struct Handles {
 	manifest: String,
 	/// Path to the file containing the pivot.
 	pivot: String,
+	/// Path to the file containing the Borsh encoded [`PivotInfo`].
+	pivot_info: PivotInfoHandle,
+	/// Permission bits for files written by these handles.
+	file_mode: u32,
 }
 
 impl Handles {
@@ -56,15 +209,41 @@ impl Handles {
 		quorum: String,
 		manifest: String,
 		pivot: String,
+		pivot_info: String,
 	) -> Self {
+		let owner = Some(nix::unistd::geteuid().as_raw());
 		Self {
 			ephemeral,
-			quorum: QuorumKeyHandle::new(quorum),
+			quorum: QuorumKeyHandle::new(quorum).with_key_file_owner(owner),
 			manifest,
 			pivot,
+			pivot_info: PivotInfoHandle::new(pivot_info),
+			file_mode: DEFAULT_FILE_MODE,
 		}
 	}
 
+	/// Set the permission bits used for files written by these handles.
+	/// Defaults to [`DEFAULT_FILE_MODE`]. The pivot binary is not affected
+	/// since it must always be executable.
+	///
+	/// Note that the write bits are always masked out so written files stay
+	/// read only.
+	#[must_use]
+	pub fn with_file_mode(mut self, file_mode: u32) -> Self {
+		self.file_mode = file_mode & !0o222;
+		self
+	}
+
+	/// Refuse to load keys unless the key files are owned by the user with id
+	/// `owner`. Defaults to the effective user id of the current process.
+	/// `None` disables the check, e.g. when keys are written by a different
+	/// user than the one reading them.
+	#[must_use]
+	pub fn with_key_file_owner(mut self, owner: Option<u32>) -> Self {
+		self.quorum = self.quorum.with_key_file_owner(owner);
+		self
+	}
+
 	/// Get the path to the Ephemeral Key.
 	#[must_use]
 	pub fn ephemeral_key_path(&self) -> String {
@@ -77,6 +256,7 @@ impl Handles {
 	///
 	/// Errors if the Ephemeral Key has not been put.
 	pub fn get_ephemeral_key(&self) -> Result<P256Pair, ProtocolError> {
+		check_key_file(&self.ephemeral, self.quorum.key_file_owner)?;
 		let pair = P256Pair::from_hex_file(&self.ephemeral)
 			.map_err(ProtocolError::FailedToGetEphemeralKey)?;
 		Ok(pair)
@@ -91,17 +271,69 @@ impl Handles {
 		&self,
 		pair: &P256Pair,
 	) -> Result<(), ProtocolError> {
-		Self::write_as_read_only(
+		self.write_as_read_only(
 			&self.ephemeral,
 			&pair.to_master_seed_hex(),
 			ProtocolError::FailedToPutEphemeralKey,
 		)
 	}
 
-	/// Delete the Ephemeral Key. Silently fails if the Ephemeral Key does not
-	/// exist.
+	/// Replace the Ephemeral Key pair with `pair`, securely deleting the old
+	/// key file if one exists.
+	///
+	/// Unlike [`Self::put_ephemeral_key`], this may be called multiple times.
+	/// It is used when resetting provisioning, where a fresh Ephemeral Key is
+	/// needed without restarting the enclave. The new key is written to a
+	/// temporary file and renamed over the old one, so a failed write leaves
+	/// the old key in place.
+	///
+	/// # Errors
+	///
+	/// Errors with [`ProtocolError::FailedToPutEphemeralKey`] if the new key
+	/// could not be written and [`ProtocolError::FailedToDeleteEphemeralKey`]
+	/// if the old key could not be overwritten.
+	pub fn rotate_ephemeral_key(
+		&self,
+		pair: &P256Pair,
+	) -> Result<(), ProtocolError> {
+		let tmp = format!("{}.tmp", self.ephemeral);
+		// Left over from a previous failed rotation
+		drop(fs::remove_file(&tmp));
+		self.write_as_read_only(
+			&tmp,
+			&pair.to_master_seed_hex(),
+			ProtocolError::FailedToPutEphemeralKey,
+		)?;
+
+		// Hold on to the old key file so it can be overwritten after the new
+		// key has taken its place.
+		let old = if Path::new(&self.ephemeral).exists() {
+			let Ok(old) = Self::open_for_overwrite(&self.ephemeral) else {
+				drop(fs::remove_file(&tmp));
+				return Err(ProtocolError::FailedToDeleteEphemeralKey);
+			};
+			Some(old)
+		} else {
+			None
+		};
+
+		if fs::rename(&tmp, &self.ephemeral).is_err() {
+			drop(fs::remove_file(&tmp));
+			return Err(ProtocolError::FailedToPutEphemeralKey);
+		}
+
+		if let Some(mut old) = old {
+			Self::overwrite_with_zeros(&mut old)
+				.map_err(|_| ProtocolError::FailedToDeleteEphemeralKey)?;
+		}
+
+		Ok(())
+	}
+
+	/// Securely delete the Ephemeral Key. Silently fails if the Ephemeral Key
+	/// does not exist.
 	pub fn delete_ephemeral_key(&self) {
-		drop(fs::remove_file(&self.ephemeral));
+		drop(Self::secure_delete(&self.ephemeral));
 	}
 
 	/// Get the Quorum Key pair.
@@ -119,7 +351,7 @@ impl Handles {
 	///
 	/// Errors if the Quorum Key has already been put.
 	pub fn put_quorum_key(&self, pair: &P256Pair) -> Result<(), ProtocolError> {
-		Self::write_as_read_only(
+		self.write_as_read_only(
 			&self.quorum.quorum,
 			&pair.to_master_seed_hex(),
 			ProtocolError::FailedToPutQuorumKey,
@@ -156,7 +388,7 @@ impl Handles {
 		&self,
 		manifest_envelope: &ManifestEnvelope,
 	) -> Result<(), ProtocolError> {
-		Self::write_as_read_only(
+		self.write_as_read_only(
 			&self.manifest,
 			&borsh::to_vec(manifest_envelope)?,
 			ProtocolError::FailedToPutManifestEnvelope,
@@ -188,7 +420,7 @@ impl Handles {
 		// Set the permissions back to read only
 		fs::set_permissions(
 			&self.manifest,
-			std::fs::Permissions::from_mode(0o444),
+			std::fs::Permissions::from_mode(self.file_mode),
 		)?;
 
 		Ok(())
@@ -235,8 +467,129 @@ impl Handles {
 		Path::new(&self.pivot).exists()
 	}
 
+	/// Overwrite the contents of the file at `path` with zeros before
+	/// unlinking it, so the old contents do not linger on the file system.
+	/// Does nothing if the file does not exist.
+	fn secure_delete<P: AsRef<Path>>(path: P) -> Result<(), std::io::Error> {
+		let path = path.as_ref();
+		if !path.exists() {
+			return Ok(());
+		}
+
+		let mut file = Self::open_for_overwrite(path)?;
+		Self::overwrite_with_zeros(&mut file)?;
+		drop(file);
+
+		fs::remove_file(path)
+	}
+
+	/// Open the file at `path` for writing. The file is likely read only, so
+	/// it is made writable first.
+	fn open_for_overwrite<P: AsRef<Path>>(
+		path: P,
+	) -> Result<fs::File, std::io::Error> {
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+		fs::OpenOptions::new().write(true).open(path)
+	}
+
+	/// Overwrite the whole contents of `file` with zeros.
+	fn overwrite_with_zeros(file: &mut fs::File) -> Result<(), std::io::Error> {
+		let len = file.metadata()?.len();
+		let len = usize::try_from(len).map_err(|_| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"file too large to overwrite",
+			)
+		})?;
+		file.write_all(&vec![0u8; len])?;
+		file.sync_all()
+	}
+
+	/// Get the [`PivotInfo`].
+	///
+	/// # Errors
+	///
+	/// Errors if the pivot info has not been put.
+	pub fn get_pivot_info(&self) -> Result<PivotInfo, ProtocolError> {
+		self.pivot_info.get_pivot_info()
+	}
+
+	/// Put the [`PivotInfo`] derived from `manifest`.
+	///
+	/// # Errors
+	///
+	/// Errors if the pivot info has already been put.
+	pub fn put_pivot_info(
+		&self,
+		manifest: &Manifest,
+	) -> Result<(), ProtocolError> {
+		self.write_as_read_only(
+			&self.pivot_info.pivot_info,
+			&borsh::to_vec(&PivotInfo::from(manifest))?,
+			ProtocolError::FailedToPutPivotInfo,
+		)
+	}
+
+	/// Get the path to the pivot info.
+	#[must_use]
+	pub fn pivot_info_path(&self) -> String {
+		self.pivot_info.pivot_info.clone()
+	}
+
+	/// Get the app config from the manifest.
+	///
+	/// # Errors
+	///
+	/// Errors if the app config has not been put.
+	pub fn get_app_config(&self) -> Result<Vec<u8>, ProtocolError> {
+		self.pivot_info.get_app_config()
+	}
+
+	/// Put the app config from `manifest`, which may be empty.
+	///
+	/// # Errors
+	///
+	/// Errors if the app config has already been put.
+	pub fn put_app_config(
+		&self,
+		manifest: &Manifest,
+	) -> Result<(), ProtocolError> {
+		self.write_as_read_only(
+			self.app_config_path(),
+			&manifest.pivot.app_config,
+			ProtocolError::FailedToPutAppConfig,
+		)
+	}
+
+	/// Get the path to the app config, next to the pivot info.
+	#[must_use]
+	pub fn app_config_path(&self) -> String {
+		self.pivot_info.app_config_path()
+	}
+
+	/// Get the path to the socket of the [`crate::executor`] server, next to
+	/// the pivot info.
+	#[must_use]
+	pub fn executor_socket_path(&self) -> String {
+		format!("{}.executor.sock", self.pivot_info.pivot_info)
+	}
+
+	/// Get the path to the [`crate::diagnostics::PanicReport`] of the
+	/// enclave, next to the pivot info.
+	#[must_use]
+	pub fn panic_report_path(&self) -> String {
+		format!("{}.panic", self.pivot_info.pivot_info)
+	}
+
+	/// Returns true if the pivot info file exists.
+	#[must_use]
+	pub fn pivot_info_exists(&self) -> bool {
+		Path::new(&self.pivot_info.pivot_info).exists()
+	}
+
 	/// Helper function for ready only writes.
 	fn write_as_read_only<P: AsRef<Path>>(
+		&self,
 		path: P,
 		buf: &[u8],
 		err: ProtocolError,
@@ -252,7 +605,7 @@ impl Handles {
 		}
 
 		fs::write(&path, buf).map_err(|_| err.clone())?;
-		fs::set_permissions(&path, fs::Permissions::from_mode(0o444))
+		fs::set_permissions(&path, fs::Permissions::from_mode(self.file_mode))
 			.map_err(|_| err)?;
 
 		Ok(())
@@ -267,14 +620,16 @@ mod test {
 
 	use super::*;
 	use crate::protocol::services::boot::{
-		Manifest, ManifestSet, Namespace, NitroConfig, PatchSet, PivotConfig,
-		RestartPolicy, ShareSet,
+		CryptoConfig, Manifest, ManifestSet, Namespace, NitroConfig, PatchSet,
+		PivotConfig, RestartPolicy, ShareSet,
 	};
 
 	#[test]
 	fn put_ephemeral_key_is_read_only_write() {
 		let pivot_file: PathWrapper =
 			"put_ephemeral_key_is_read_only_write.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"put_ephemeral_key_is_read_only_write.pivot_info".into();
 		let ephemeral_file: PathWrapper =
 			"put_ephemeral_key_is_read_only_write_eph.secret".into();
 		let quorum_file: PathWrapper =
@@ -287,6 +642,7 @@ mod test {
 			(*quorum_file).to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
 		);
 
 		let ephemeral_key = P256Pair::generate().unwrap();
@@ -298,10 +654,53 @@ mod test {
 		assert!(handles.get_ephemeral_key().unwrap() == ephemeral_key);
 	}
 
+	#[test]
+	fn rotate_ephemeral_key_replaces_old_key() {
+		let pivot_file: PathWrapper =
+			"rotate_ephemeral_key_replaces_old_key.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"rotate_ephemeral_key_replaces_old_key.pivot_info".into();
+		let ephemeral_file: PathWrapper =
+			"rotate_ephemeral_key_replaces_old_key_eph.secret".into();
+		let quorum_file: PathWrapper =
+			"rotate_ephemeral_key_replaces_old_key_quor.secret".into();
+		let manifest_file: PathWrapper =
+			"rotate_ephemeral_key_replaces_old_key.manifest".into();
+
+		let handles = Handles::new(
+			(*ephemeral_file).to_string(),
+			(*quorum_file).to_string(),
+			(*manifest_file).to_string(),
+			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
+		);
+
+		// Rotating works even if there is no existing key
+		let first_key = P256Pair::generate().unwrap();
+		handles.rotate_ephemeral_key(&first_key).unwrap();
+		assert!(handles.get_ephemeral_key().unwrap() == first_key);
+
+		let second_key = P256Pair::generate().unwrap();
+		handles.rotate_ephemeral_key(&second_key).unwrap();
+		assert!(handles.get_ephemeral_key().unwrap() == second_key);
+		assert!(!Path::new(&format!("{}.tmp", &*ephemeral_file)).exists());
+
+		// The new key is still read only
+		assert_eq!(
+			handles.put_ephemeral_key(&first_key).unwrap_err(),
+			ProtocolError::CannotModifyPostPivotStatic
+		);
+
+		handles.delete_ephemeral_key();
+		assert!(!Path::new(&*ephemeral_file).exists());
+	}
+
 	#[test]
 	fn put_quorum_key_is_read_only_write() {
 		let pivot_file: PathWrapper =
 			"put_quorum_key_is_read_only_write.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"put_quorum_key_is_read_only_write.pivot_info".into();
 		let ephemeral_file: PathWrapper =
 			"put_quorum_key_is_read_only_write_eph.secret".into();
 		let quorum_file: PathWrapper =
@@ -314,6 +713,7 @@ mod test {
 			(*quorum_file).to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
 		);
 
 		let quorum_key = P256Pair::generate().unwrap();
@@ -326,10 +726,87 @@ mod test {
 		assert!(handles.get_quorum_key().unwrap() == quorum_key);
 	}
 
+	#[test]
+	fn refuses_to_load_world_writable_quorum_key() {
+		let quorum_file: PathWrapper =
+			"refuses_to_load_world_writable_quorum_key.secret".into();
+
+		let handle = QuorumKeyHandle::new((*quorum_file).to_string());
+		let quorum_key = P256Pair::generate().unwrap();
+		quorum_key.to_hex_file(&*quorum_file).unwrap();
+		fs::set_permissions(&*quorum_file, fs::Permissions::from_mode(0o666))
+			.unwrap();
+
+		assert!(matches!(
+			handle.get_quorum_key(),
+			Err(ProtocolError::WorldWritableKeyFile)
+		));
+
+		fs::set_permissions(&*quorum_file, fs::Permissions::from_mode(0o644))
+			.unwrap();
+		assert!(handle.get_quorum_key().unwrap() == quorum_key);
+	}
+
+	#[test]
+	fn checks_key_file_owner_if_configured() {
+		let quorum_file: PathWrapper =
+			"checks_key_file_owner_if_configured.secret".into();
+
+		let quorum_key = P256Pair::generate().unwrap();
+		quorum_key.to_hex_file(&*quorum_file).unwrap();
+		let euid = nix::unistd::geteuid().as_raw();
+
+		let handle = QuorumKeyHandle::new((*quorum_file).to_string())
+			.with_key_file_owner(Some(euid.wrapping_add(1)));
+		assert!(matches!(
+			handle.get_quorum_key(),
+			Err(ProtocolError::UnexpectedKeyFileOwner)
+		));
+
+		let handle = handle.with_key_file_owner(Some(euid));
+		assert!(handle.get_quorum_key().unwrap() == quorum_key);
+
+		let handle = handle.with_key_file_owner(None);
+		assert!(handle.get_quorum_key().unwrap() == quorum_key);
+	}
+
+	#[test]
+	fn with_file_mode_is_used_for_writes() {
+		let pivot_file: PathWrapper =
+			"with_file_mode_is_used_for_writes.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"with_file_mode_is_used_for_writes.pivot_info".into();
+		let ephemeral_file: PathWrapper =
+			"with_file_mode_is_used_for_writes_eph.secret".into();
+		let quorum_file: PathWrapper =
+			"with_file_mode_is_used_for_writes_quor.secret".into();
+		let manifest_file: PathWrapper =
+			"with_file_mode_is_used_for_writes.manifest".into();
+
+		let handles = Handles::new(
+			(*ephemeral_file).to_string(),
+			(*quorum_file).to_string(),
+			(*manifest_file).to_string(),
+			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
+		)
+		// Write bits get masked out
+		.with_file_mode(0o600);
+
+		let quorum_key = P256Pair::generate().unwrap();
+		handles.put_quorum_key(&quorum_key).unwrap();
+
+		let mode = fs::metadata(&*quorum_file).unwrap().mode() & 0o777;
+		assert_eq!(mode, 0o400);
+		assert!(handles.get_quorum_key().unwrap() == quorum_key);
+	}
+
 	#[test]
 	fn put_pivot_is_read_only_write() {
 		let pivot_file: PathWrapper =
 			"put_pivot_is_read_only_write.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"put_pivot_is_read_only_write.pivot_info".into();
 		let ephemeral_file: PathWrapper =
 			"put_pivot_is_read_only_write_eph.secret".into();
 		let quorum_file: PathWrapper =
@@ -343,6 +820,7 @@ mod test {
 			(*quorum_file).to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
 		);
 
 		let pivot = b"this is a pivot binary".to_vec();
@@ -358,6 +836,8 @@ mod test {
 	fn put_manifest_is_read_only_write() {
 		let pivot_file: PathWrapper =
 			"put_manifest_is_read_only_write.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"put_manifest_is_read_only_write.pivot_info".into();
 		let ephemeral_file: PathWrapper =
 			"put_manifest_is_read_only_write_eph.secret".into();
 		let quorum_file: PathWrapper =
@@ -370,6 +850,7 @@ mod test {
 			(*quorum_file).to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
 		);
 
 		let pivot = b"this is a pivot binary".to_vec();
@@ -395,10 +876,17 @@ mod test {
 				hash: sha_256(&pivot),
 				restart: RestartPolicy::Always,
 				args: vec![],
+				app_config: vec![],
+				egress: vec![],
 			},
 			manifest_set: ManifestSet { threshold: 2, members: vec![] },
-			share_set: ShareSet { threshold: 2, members: vec![] },
+			share_set: ShareSet {
+				threshold: 2,
+				members: vec![],
+				share_commitments: vec![],
+			},
 			patch_set: PatchSet::default(),
+			crypto: CryptoConfig::default(),
 		};
 
 		let manifest_envelope = ManifestEnvelope {
@@ -416,4 +904,99 @@ mod test {
 		assert!(handles.manifest_envelope_exists());
 		assert!(handles.get_manifest_envelope().unwrap() == manifest_envelope);
 	}
+
+	#[test]
+	fn put_pivot_info_is_read_only_write() {
+		let pivot_file: PathWrapper =
+			"put_pivot_info_is_read_only_write.pivot".into();
+		let pivot_info_file: PathWrapper =
+			"put_pivot_info_is_read_only_write.pivot_info".into();
+		let ephemeral_file: PathWrapper =
+			"put_pivot_info_is_read_only_write_eph.secret".into();
+		let quorum_file: PathWrapper =
+			"put_pivot_info_is_read_only_write_quor.secret".into();
+		let manifest_file: PathWrapper =
+			"put_pivot_info_is_read_only_write.manifest".into();
+
+		let handles = Handles::new(
+			(*ephemeral_file).to_string(),
+			(*quorum_file).to_string(),
+			(*manifest_file).to_string(),
+			(*pivot_file).to_string(),
+			(*pivot_info_file).to_string(),
+		);
+
+		let quorum_key = P256Pair::generate().unwrap().public_key().to_bytes();
+		let mut manifest = Manifest::default();
+		manifest.namespace.name = "vape lord".to_string();
+		manifest.namespace.nonce = 420;
+		manifest.namespace.quorum_key.clone_from(&quorum_key);
+		let egress = vec![EgressEndpoint {
+			host: "example.com".to_string(),
+			port: 443,
+			tls_pins: vec![[7; 32]],
+		}];
+		manifest.pivot.egress.clone_from(&egress);
+
+		assert!(!handles.pivot_info_exists());
+		let result = handles.put_pivot_info(&manifest);
+		let error = handles.put_pivot_info(&manifest).unwrap_err();
+
+		assert!(result.is_ok());
+		assert_eq!(error, ProtocolError::CannotModifyPostPivotStatic);
+		assert!(handles.pivot_info_exists());
+
+		// The pivot reads the info through its own handle
+		let pivot_info = PivotInfoHandle::new((*pivot_info_file).to_string())
+			.get_pivot_info()
+			.unwrap();
+		assert_eq!(
+			pivot_info,
+			PivotInfo {
+				namespace: "vape lord".to_string(),
+				nonce: 420,
+				quorum_key,
+				manifest_hash: manifest.qos_hash(),
+				egress,
+			}
+		);
+		assert_eq!(handles.get_pivot_info().unwrap(), pivot_info);
+	}
+
+	#[test]
+	fn put_app_config_is_read_only_write() {
+		let pivot_info_file: PathWrapper =
+			"put_app_config_is_read_only_write.pivot_info".into();
+		let app_config_file: PathWrapper =
+			"put_app_config_is_read_only_write.pivot_info.app_config".into();
+
+		let handles = Handles::new(
+			"put_app_config_is_read_only_write_eph.secret".to_string(),
+			"put_app_config_is_read_only_write_quor.secret".to_string(),
+			"put_app_config_is_read_only_write.manifest".to_string(),
+			"put_app_config_is_read_only_write.pivot".to_string(),
+			(*pivot_info_file).to_string(),
+		);
+		assert_eq!(handles.app_config_path(), *app_config_file);
+
+		let mut manifest = Manifest::default();
+		manifest.pivot.app_config = b"{\"region\":\"eu\"}".to_vec();
+
+		assert_eq!(
+			handles.get_app_config().unwrap_err(),
+			ProtocolError::FailedToGetAppConfig
+		);
+		let result = handles.put_app_config(&manifest);
+		let error = handles.put_app_config(&manifest).unwrap_err();
+
+		assert!(result.is_ok());
+		assert_eq!(error, ProtocolError::CannotModifyPostPivotStatic);
+
+		// The pivot reads the config through its own handle
+		let app_config = PivotInfoHandle::new((*pivot_info_file).to_string())
+			.get_app_config()
+			.unwrap();
+		assert_eq!(app_config, manifest.pivot.app_config);
+		assert_eq!(handles.get_app_config().unwrap(), app_config);
+	}
 }
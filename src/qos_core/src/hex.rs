@@ -0,0 +1,156 @@
+//! Strict decoding of hex strings into typed, fixed length values.
+//!
+//! [`qos_hex::decode`] accepts hex of any length, so a truncated hash or PCR
+//! is only caught once it fails to match something downstream. The helpers
+//! here check the length up front and say what was expected, so inputs can
+//! be rejected with a clear message where they are parsed.
+
+use std::fmt;
+
+use crate::protocol::Hash256;
+
+/// A PCR of a Nitro enclave, a sha384 digest.
+pub type Pcr48 = [u8; 48];
+
+/// Why a hex string could not be decoded, see [`decode_array`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexDecodeError {
+	/// The input has an odd number of hex characters.
+	OddLength {
+		/// Number of hex characters in the input, without the `0x` prefix.
+		chars: usize,
+	},
+	/// The input has a character that is not hex.
+	InvalidChar {
+		/// Position of the character in the input, without the `0x` prefix.
+		position: usize,
+		/// The character.
+		char: char,
+	},
+	/// The input decodes to the wrong number of bytes.
+	WrongLength {
+		/// Number of bytes expected.
+		expected: usize,
+		/// Number of bytes the input decodes to.
+		actual: usize,
+	},
+}
+
+impl fmt::Display for HexDecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OddLength { chars } => {
+				write!(f, "odd number of hex characters ({chars})")
+			}
+			Self::InvalidChar { position, char } => {
+				write!(
+					f,
+					"invalid hex character {char:?} at position {position}"
+				)
+			}
+			Self::WrongLength { expected, actual } => write!(
+				f,
+				"expected {expected} bytes ({} hex characters), got {actual} \
+				bytes ({} hex characters)",
+				expected * 2,
+				actual * 2
+			),
+		}
+	}
+}
+
+impl std::error::Error for HexDecodeError {}
+
+/// `hex` without a leading `0x` or `0X`.
+#[must_use]
+pub fn strip_prefix(hex: &str) -> &str {
+	hex.strip_prefix("0x").or_else(|| hex.strip_prefix("0X")).unwrap_or(hex)
+}
+
+/// Decode `hex`, with or without a `0x` prefix, into bytes of any length.
+///
+/// # Errors
+///
+/// [`HexDecodeError::OddLength`] or [`HexDecodeError::InvalidChar`] if `hex`
+/// is not valid hex.
+///
+/// # Panics
+///
+/// Never, `hex` is checked before it is decoded.
+pub fn decode(hex: &str) -> Result<Vec<u8>, HexDecodeError> {
+	let hex = strip_prefix(hex);
+	if let Some((position, char)) =
+		hex.chars().enumerate().find(|(_, c)| !c.is_ascii_hexdigit())
+	{
+		return Err(HexDecodeError::InvalidChar { position, char });
+	}
+	if hex.len() % 2 != 0 {
+		return Err(HexDecodeError::OddLength { chars: hex.len() });
+	}
+
+	Ok(qos_hex::decode(hex).expect("input is checked to be valid hex. qed."))
+}
+
+/// Decode `hex`, with or without a `0x` prefix, into exactly `N` bytes.
+///
+/// # Errors
+///
+/// [`HexDecodeError::WrongLength`] if `hex` does not decode to `N` bytes, and
+/// see [`decode`].
+pub fn decode_array<const N: usize>(
+	hex: &str,
+) -> Result<[u8; N], HexDecodeError> {
+	let bytes = decode(hex)?;
+	let actual = bytes.len();
+	bytes
+		.try_into()
+		.map_err(|_| HexDecodeError::WrongLength { expected: N, actual })
+}
+
+/// Decode `hex` into a [`Hash256`], see [`decode_array`].
+pub fn decode_hash256(hex: &str) -> Result<Hash256, HexDecodeError> {
+	decode_array(hex)
+}
+
+/// Decode `hex` into a [`Pcr48`], see [`decode_array`].
+pub fn decode_pcr48(hex: &str) -> Result<Pcr48, HexDecodeError> {
+	decode_array(hex)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn decodes_only_the_expected_length() {
+		let hash = [0xab; 32];
+		let hex = qos_hex::encode(&hash);
+		assert_eq!(decode_hash256(&hex), Ok(hash));
+		assert_eq!(decode_hash256(&format!("0x{hex}")), Ok(hash));
+		assert_eq!(
+			decode_hash256(&format!("0X{}", hex.to_uppercase())),
+			Ok(hash)
+		);
+
+		let truncated = decode_pcr48(&qos_hex::encode(&[1; 47])).unwrap_err();
+		assert_eq!(
+			truncated,
+			HexDecodeError::WrongLength { expected: 48, actual: 47 }
+		);
+		assert_eq!(
+			truncated.to_string(),
+			"expected 48 bytes (96 hex characters), got 47 bytes (94 hex \
+			characters)"
+		);
+
+		assert_eq!(
+			decode("0xabc"),
+			Err(HexDecodeError::OddLength { chars: 3 })
+		);
+		assert_eq!(
+			decode("abzd"),
+			Err(HexDecodeError::InvalidChar { position: 2, char: 'z' })
+		);
+		assert_eq!(decode(""), Ok(vec![]));
+	}
+}
@@ -0,0 +1,380 @@
+//! Async abstractions to handle connection based socket streams. These use the
+//! same wire format as [`super::Stream`], so async and blocking peers can talk
+//! to each other.
+
+use std::{
+	os::unix::io::{AsRawFd, RawFd},
+	sync::atomic::{AtomicU64, Ordering},
+	time::{Duration, Instant},
+};
+
+use nix::{
+	sys::socket::{
+		accept4, bind, connect, getsockopt, listen, recv, send, shutdown,
+		socket, sockopt, MsgFlags, Shutdown, SockFlag, SockType,
+	},
+	unistd::close,
+};
+use tokio::io::unix::AsyncFd;
+
+use super::{
+	frame::{check_message_size, FrameHeader, FRAME_HEADER_SIZE},
+	Backoff, IOError, SocketAddress,
+};
+
+// 25(retries) x 10(milliseconds) = 1/4 a second of retrying
+const MAX_RETRY: usize = 25;
+const BACKOFF_MILLISECONDS: u64 = 10;
+const BACKLOG: usize = 128;
+
+/// Owned, non-blocking socket file descriptor.
+struct SocketFd(RawFd);
+
+impl AsRawFd for SocketFd {
+	fn as_raw_fd(&self) -> RawFd {
+		self.0
+	}
+}
+
+impl Drop for SocketFd {
+	fn drop(&mut self) {
+		// Its ok if either of these error - likely means the other end of the
+		// connection has been shutdown
+		let _ = shutdown(self.0, Shutdown::Both);
+		let _ = close(self.0);
+	}
+}
+
+/// Async handle on a stream. Must be used from within a tokio runtime.
+pub struct AsyncStream {
+	fd: AsyncFd<SocketFd>,
+	created: Instant,
+	/// Microseconds between `created` and the last send or receive.
+	last_activity: AtomicU64,
+}
+
+impl AsyncStream {
+	/// Create a new `AsyncStream` connected to `addr`.
+	pub async fn connect(addr: &SocketAddress) -> Result<Self, IOError> {
+		let mut err = IOError::UnknownError;
+
+		for _ in 0..MAX_RETRY {
+			match Self::try_connect(addr).await {
+				Ok(stream) => return Ok(stream),
+				Err(e) => err = e,
+			}
+
+			tokio::time::sleep(std::time::Duration::from_millis(
+				BACKOFF_MILLISECONDS,
+			))
+			.await;
+		}
+
+		Err(err)
+	}
+
+	/// Like [`Self::connect`], but keep retrying with exponential `backoff`.
+	/// See [`super::Stream::connect_with_backoff`].
+	pub async fn connect_with_backoff(
+		addr: &SocketAddress,
+		backoff: Backoff,
+	) -> Result<Self, IOError> {
+		let start = Instant::now();
+		let mut delay = backoff.initial;
+
+		loop {
+			let err = match Self::try_connect(addr).await {
+				Ok(stream) => return Ok(stream),
+				Err(e) => e,
+			};
+
+			if start.elapsed() + delay > backoff.max_elapsed {
+				return Err(err);
+			}
+			tokio::time::sleep(delay).await;
+			delay = (delay * 2).min(backoff.max);
+		}
+	}
+
+	async fn try_connect(addr: &SocketAddress) -> Result<Self, IOError> {
+		let fd = SocketFd(socket_fd(addr)?);
+
+		// Don't hold on to the (not `Send`) address across awaits
+		let connected = connect(fd.0, &*addr.addr());
+		match connected {
+			Ok(()) => Self::from_fd(fd),
+			Err(nix::Error::EINPROGRESS) => {
+				let stream = Self::from_fd(fd)?;
+				stream.finish_connect().await?;
+				Ok(stream)
+			}
+			Err(e) => Err(IOError::ConnectNixError(e)),
+		}
+	}
+
+	fn from_fd(fd: SocketFd) -> Result<Self, IOError> {
+		Ok(Self {
+			fd: AsyncFd::new(fd).map_err(IOError::StdIoError)?,
+			created: Instant::now(),
+			last_activity: AtomicU64::new(0),
+		})
+	}
+
+	/// Time since bytes were last sent or received over this stream, or
+	/// since it was created if there has been no traffic yet.
+	#[must_use]
+	pub fn idle_for(&self) -> Duration {
+		let last_activity =
+			Duration::from_micros(self.last_activity.load(Ordering::Relaxed));
+		self.created.elapsed().saturating_sub(last_activity)
+	}
+
+	/// Resolves once the stream has been idle for `limit`. See
+	/// [`Self::idle_for`].
+	pub async fn idle(&self, limit: Duration) {
+		loop {
+			let idle = self.idle_for();
+			if idle >= limit {
+				return;
+			}
+			tokio::time::sleep(limit - idle).await;
+		}
+	}
+
+	fn touch(&self) {
+		let micros = u64::try_from(self.created.elapsed().as_micros())
+			.unwrap_or(u64::MAX);
+		self.last_activity.store(micros, Ordering::Relaxed);
+	}
+
+	/// Wait for a non-blocking connect to complete.
+	async fn finish_connect(&self) -> Result<(), IOError> {
+		let _guard = self.fd.writable().await.map_err(IOError::StdIoError)?;
+		match getsockopt(self.fd.as_raw_fd(), sockopt::SocketError)? {
+			0 => Ok(()),
+			errno => Err(IOError::ConnectNixError(nix::Error::from_i32(errno))),
+		}
+	}
+
+	/// Sends a buffer over the underlying socket as a single frame.
+	pub async fn send(&self, buf: &[u8]) -> Result<(), IOError> {
+		let header = FrameHeader::for_payload(buf)?;
+
+		// First, send the header
+		self.send_all(&header.encode()).await?;
+		// Then, send the contents of the buffer
+		self.send_all(buf).await
+	}
+
+	/// Receive a message, which may span several frames, from the underlying
+	/// socket.
+	pub async fn recv(&self) -> Result<Vec<u8>, IOError> {
+		let mut message = Vec::new();
+		loop {
+			let header = {
+				let mut buf = [0u8; FRAME_HEADER_SIZE];
+				match self.recv_exact(&mut buf).await? {
+					0 => return Err(IOError::RecvConnectionClosed),
+					FRAME_HEADER_SIZE => FrameHeader::decode(buf)?,
+					received => {
+						return Err(IOError::TruncatedFrame {
+							expected: FRAME_HEADER_SIZE,
+							received,
+						})
+					}
+				}
+			};
+			check_message_size(message.len() + header.len)?;
+
+			// Read the payload
+			let start = message.len();
+			message.resize(start + header.len, 0);
+			let received = self.recv_exact(&mut message[start..]).await?;
+			header.verify(&message[start..start + received])?;
+
+			if !header.more {
+				return Ok(message);
+			}
+		}
+	}
+
+	async fn send_all(&self, buf: &[u8]) -> Result<(), IOError> {
+		let mut sent_bytes = 0;
+		while sent_bytes < buf.len() {
+			let mut guard =
+				self.fd.writable().await.map_err(IOError::StdIoError)?;
+			match send(
+				self.fd.as_raw_fd(),
+				&buf[sent_bytes..],
+				MsgFlags::empty(),
+			) {
+				Ok(size) => {
+					sent_bytes += size;
+					self.touch();
+				}
+				Err(nix::Error::EAGAIN) => guard.clear_ready(),
+				Err(err) => return Err(IOError::SendNixError(err)),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Fill `buf` from the socket, returning the number of bytes received.
+	/// This is only less than `buf.len()` if the peer closed the connection.
+	async fn recv_exact(&self, buf: &mut [u8]) -> Result<usize, IOError> {
+		let mut received_bytes = 0;
+		while received_bytes < buf.len() {
+			let mut guard =
+				self.fd.readable().await.map_err(IOError::StdIoError)?;
+			match recv(
+				self.fd.as_raw_fd(),
+				&mut buf[received_bytes..],
+				MsgFlags::empty(),
+			) {
+				Ok(0) => break,
+				Ok(size) => {
+					received_bytes += size;
+					self.touch();
+				}
+				Err(nix::Error::EAGAIN) => guard.clear_ready(),
+				Err(nix::Error::EINTR) => return Err(IOError::RecvInterrupted),
+				Err(err) => return Err(IOError::RecvNixError(err)),
+			}
+		}
+
+		Ok(received_bytes)
+	}
+}
+
+/// Abstraction to asynchronously listen for incoming stream connections.
+pub struct AsyncListener {
+	fd: AsyncFd<SocketFd>,
+	addr: SocketAddress,
+}
+
+impl AsyncListener {
+	/// Bind and listen on the given address. Must be called from within a
+	/// tokio runtime.
+	pub fn listen(addr: SocketAddress) -> Result<Self, IOError> {
+		// In case the last connection at this addr did not shutdown correctly
+		clean(&addr);
+
+		let fd = SocketFd(socket_fd(&addr)?);
+		bind(fd.0, &*addr.addr())?;
+		listen(fd.0, BACKLOG)?;
+
+		Ok(Self { fd: AsyncFd::new(fd).map_err(IOError::StdIoError)?, addr })
+	}
+
+	/// Accept the next incoming connection.
+	pub async fn accept(&self) -> Result<AsyncStream, IOError> {
+		loop {
+			let mut guard =
+				self.fd.readable().await.map_err(IOError::StdIoError)?;
+			match accept4(self.fd.as_raw_fd(), SockFlag::SOCK_NONBLOCK) {
+				Ok(fd) => return AsyncStream::from_fd(SocketFd(fd)),
+				Err(nix::Error::EAGAIN) => guard.clear_ready(),
+				Err(err) => return Err(IOError::NixError(err)),
+			}
+		}
+	}
+}
+
+impl Drop for AsyncListener {
+	fn drop(&mut self) {
+		// The socket itself is closed when `fd` is dropped.
+		clean(&self.addr);
+	}
+}
+
+/// Remove Unix socket if it exists
+fn clean(addr: &SocketAddress) {
+	// Not irrefutable when "vm" is enabled
+	#[allow(irrefutable_let_patterns)]
+	if let SocketAddress::Unix(addr) = addr {
+		if let Some(path) = addr.path() {
+			if path.exists() {
+				drop(std::fs::remove_file(path));
+			}
+		}
+	}
+}
+
+fn socket_fd(addr: &SocketAddress) -> Result<RawFd, IOError> {
+	socket(
+		addr.family(),
+		SockType::Stream,
+		// Non-blocking so we can register the socket with the tokio reactor
+		SockFlag::SOCK_NONBLOCK,
+		None,
+	)
+	.map_err(IOError::NixError)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::io::{Listener, Stream, TimeVal, TimeValLike};
+
+	#[tokio::test]
+	async fn async_stream_integration_test() {
+		let addr =
+			SocketAddress::new_unix("./async_stream_integration_test.sock");
+		let listener = AsyncListener::listen(addr.clone()).unwrap();
+
+		let handler = tokio::spawn(async move {
+			let stream = listener.accept().await.unwrap();
+			let req = stream.recv().await.unwrap();
+			stream.send(&req).await.unwrap();
+		});
+
+		let client = AsyncStream::connect(&addr).await.unwrap();
+		let data = vec![1, 2, 3, 4, 5, 6, 6, 6];
+		client.send(&data).await.unwrap();
+		let resp = client.recv().await.unwrap();
+
+		assert_eq!(data, resp);
+		handler.await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn async_stream_talks_to_blocking_listener() {
+		let addr = SocketAddress::new_unix(
+			"./async_stream_talks_to_blocking_listener.sock",
+		);
+		let mut listener = Listener::listen(addr.clone()).unwrap();
+
+		let handler = std::thread::spawn(move || {
+			if let Some(stream) = listener.next() {
+				let req = stream.recv().unwrap();
+				stream.send(&req).unwrap();
+			}
+		});
+
+		let client = AsyncStream::connect(&addr).await.unwrap();
+		let data = vec![6; 1024 * 1024];
+		client.send(&data).await.unwrap();
+		let resp = client.recv().await.unwrap();
+		assert_eq!(data, resp);
+
+		handler.join().unwrap();
+
+		// And the other way around
+		let addr = SocketAddress::new_unix(
+			"./async_listener_talks_to_blocking_stream.sock",
+		);
+		let listener = AsyncListener::listen(addr.clone()).unwrap();
+		let handler = std::thread::spawn(move || {
+			let stream = Stream::connect(&addr, TimeVal::seconds(1)).unwrap();
+			stream.send(b"ping").unwrap();
+			stream.recv().unwrap()
+		});
+
+		let stream = listener.accept().await.unwrap();
+		let req = stream.recv().await.unwrap();
+		stream.send(&req).await.unwrap();
+
+		assert_eq!(handler.join().unwrap(), b"ping".to_vec());
+	}
+}
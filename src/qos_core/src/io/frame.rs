@@ -0,0 +1,190 @@
+//! Wire framing shared by [`super::Stream`] and the async stream.
+//!
+//! Every message is sent as one or more frames:
+//!
+//! ```text
+//! | length (u32 LE) | checksum ([u8; 4]) | payload ([u8; length]) |
+//! ```
+//!
+//! The checksum is the first 4 bytes of the SHA-256 digest of the payload. It
+//! is not a security measure; it exists to detect corrupted or desynchronized
+//! streams.
+//!
+//! The most significant bit of the length is set on every frame but the last
+//! of a message, so large messages can be streamed in chunks without knowing
+//! their full size up front. The receiver concatenates the payloads.
+
+use std::mem::size_of;
+
+use super::IOError;
+
+/// Size in bytes of the length prefix of a frame.
+pub const FRAME_LENGTH_SIZE: usize = size_of::<u32>();
+/// Size in bytes of the payload checksum of a frame.
+pub const FRAME_CHECKSUM_SIZE: usize = 4;
+/// Size in bytes of the frame header (length prefix and checksum).
+pub const FRAME_HEADER_SIZE: usize = FRAME_LENGTH_SIZE + FRAME_CHECKSUM_SIZE;
+/// Maximum payload size of a single frame. This is large enough for any
+/// message accepted by the host.
+pub const MAX_FRAME_PAYLOAD_SIZE: usize = 256 * 1024 * 1024;
+/// Maximum size of a message, summed over all its frames.
+pub const MAX_MESSAGE_SIZE: usize = 512 * 1024 * 1024;
+/// Set in the length prefix of frames that are followed by more frames of the
+/// same message.
+const MORE_FRAMES_FLAG: u32 = 1 << 31;
+
+/// Header preceding every frame's payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FrameHeader {
+	/// Length of the payload in bytes.
+	pub(crate) len: usize,
+	/// Whether more frames of the same message follow this one.
+	pub(crate) more: bool,
+	checksum: [u8; FRAME_CHECKSUM_SIZE],
+}
+
+impl FrameHeader {
+	/// Create the header for `payload`, the last frame of a message.
+	pub(crate) fn for_payload(payload: &[u8]) -> Result<Self, IOError> {
+		check_size(payload.len())?;
+		Ok(Self {
+			len: payload.len(),
+			more: false,
+			checksum: checksum(payload),
+		})
+	}
+
+	/// Create the header for `payload`, a frame that is followed by more
+	/// frames of the same message.
+	pub(crate) fn for_partial_payload(payload: &[u8]) -> Result<Self, IOError> {
+		Ok(Self { more: true, ..Self::for_payload(payload)? })
+	}
+
+	/// Encode the header into its wire representation.
+	pub(crate) fn encode(&self) -> [u8; FRAME_HEADER_SIZE] {
+		let mut buf = [0u8; FRAME_HEADER_SIZE];
+		// Cast is safe because `check_size` bounds `len` well below
+		// `u32::MAX`.
+		#[allow(clippy::cast_possible_truncation)]
+		let mut len = self.len as u32;
+		if self.more {
+			len |= MORE_FRAMES_FLAG;
+		}
+		buf[..FRAME_LENGTH_SIZE].copy_from_slice(&len.to_le_bytes());
+		buf[FRAME_LENGTH_SIZE..].copy_from_slice(&self.checksum);
+		buf
+	}
+
+	/// Decode a header from its wire representation, rejecting headers that
+	/// announce an oversized payload.
+	pub(crate) fn decode(
+		buf: [u8; FRAME_HEADER_SIZE],
+	) -> Result<Self, IOError> {
+		let mut len_buf = [0u8; FRAME_LENGTH_SIZE];
+		len_buf.copy_from_slice(&buf[..FRAME_LENGTH_SIZE]);
+		let len = u32::from_le_bytes(len_buf);
+		let more = len & MORE_FRAMES_FLAG != 0;
+		let len: usize = (len & !MORE_FRAMES_FLAG)
+			.try_into()
+			// Should only be possible on a 16bit architecture
+			.map_err(|_| IOError::ArithmeticSaturation)?;
+		check_size(len)?;
+
+		let mut checksum = [0u8; FRAME_CHECKSUM_SIZE];
+		checksum.copy_from_slice(&buf[FRAME_LENGTH_SIZE..]);
+
+		Ok(Self { len, more, checksum })
+	}
+
+	/// Verify that `payload` matches this header.
+	pub(crate) fn verify(&self, payload: &[u8]) -> Result<(), IOError> {
+		if payload.len() != self.len {
+			return Err(IOError::TruncatedFrame {
+				expected: self.len,
+				received: payload.len(),
+			});
+		}
+		if checksum(payload) != self.checksum {
+			return Err(IOError::FrameChecksumMismatch);
+		}
+
+		Ok(())
+	}
+}
+
+/// Fail if `size` exceeds [`MAX_FRAME_PAYLOAD_SIZE`].
+pub(crate) fn check_size(size: usize) -> Result<(), IOError> {
+	if size > MAX_FRAME_PAYLOAD_SIZE {
+		Err(IOError::OversizedFrame { size, max: MAX_FRAME_PAYLOAD_SIZE })
+	} else {
+		Ok(())
+	}
+}
+
+/// Fail if a message of `size` bytes exceeds [`MAX_MESSAGE_SIZE`].
+pub(crate) fn check_message_size(size: usize) -> Result<(), IOError> {
+	if size > MAX_MESSAGE_SIZE {
+		Err(IOError::OversizedFrame { size, max: MAX_MESSAGE_SIZE })
+	} else {
+		Ok(())
+	}
+}
+
+fn checksum(payload: &[u8]) -> [u8; FRAME_CHECKSUM_SIZE] {
+	let digest = qos_crypto::sha_256(payload);
+	let mut checksum = [0u8; FRAME_CHECKSUM_SIZE];
+	checksum.copy_from_slice(&digest[..FRAME_CHECKSUM_SIZE]);
+	checksum
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn header_round_trips() {
+		let payload = b"a frame payload";
+		let header = FrameHeader::for_payload(payload).unwrap();
+		let decoded = FrameHeader::decode(header.encode()).unwrap();
+
+		assert_eq!(decoded, header);
+		assert_eq!(decoded.len, payload.len());
+		decoded.verify(payload).unwrap();
+	}
+
+	#[test]
+	fn decode_rejects_oversized_frames() {
+		let mut buf = [0u8; FRAME_HEADER_SIZE];
+		buf[..FRAME_LENGTH_SIZE].copy_from_slice(&u32::MAX.to_le_bytes());
+
+		assert!(matches!(
+			FrameHeader::decode(buf),
+			Err(IOError::OversizedFrame { size, max: MAX_FRAME_PAYLOAD_SIZE })
+				if size == (u32::MAX & !MORE_FRAMES_FLAG) as usize
+		));
+	}
+
+	#[test]
+	fn partial_header_round_trips() {
+		let header = FrameHeader::for_partial_payload(b"chunk").unwrap();
+		let decoded = FrameHeader::decode(header.encode()).unwrap();
+
+		assert!(decoded.more);
+		assert_eq!(decoded.len, 5);
+		assert!(!FrameHeader::for_payload(b"chunk").unwrap().more);
+	}
+
+	#[test]
+	fn verify_rejects_corrupted_payload() {
+		let header = FrameHeader::for_payload(b"original").unwrap();
+
+		assert!(matches!(
+			header.verify(b"0riginal"),
+			Err(IOError::FrameChecksumMismatch)
+		));
+		assert!(matches!(
+			header.verify(b"orig"),
+			Err(IOError::TruncatedFrame { expected: 8, received: 4 })
+		));
+	}
+}
@@ -0,0 +1,186 @@
+//! In-process transport backed by channels, for exercising servers and
+//! clients end to end without creating sockets.
+//!
+//! Errors mirror the ones a socket would produce, so code under test observes
+//! the same failures it would in production.
+
+use std::{
+	cell::{Cell, RefCell},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+	},
+	time::Duration,
+};
+
+use super::{frame, Acceptor, Connection, IOError, TimeVal, TimeValLike};
+
+/// Create a connected [`MemoryListener`] and [`MemoryConnector`] pair.
+#[must_use]
+pub fn listener() -> (MemoryListener, MemoryConnector) {
+	let (tx, rx) = channel();
+	(
+		MemoryListener { incoming: rx, closed: AtomicBool::new(false) },
+		MemoryConnector { outgoing: tx },
+	)
+}
+
+/// Accepts connections made through the matching [`MemoryConnector`]s.
+#[derive(Debug)]
+pub struct MemoryListener {
+	incoming: Receiver<MemoryStream>,
+	closed: AtomicBool,
+}
+
+impl Acceptor for MemoryListener {
+	type Connection = MemoryStream;
+
+	/// Block until the next connection. Fails once every [`MemoryConnector`]
+	/// has been dropped.
+	fn accept(&self) -> Result<MemoryStream, IOError> {
+		self.incoming.recv().map_err(|_| {
+			self.closed.store(true, Ordering::Release);
+			IOError::RecvConnectionClosed
+		})
+	}
+
+	fn is_shutdown(&self) -> bool {
+		self.closed.load(Ordering::Acquire)
+	}
+}
+
+/// Opens connections to a [`MemoryListener`]; the in memory equivalent of a
+/// socket address.
+#[derive(Debug, Clone)]
+pub struct MemoryConnector {
+	outgoing: Sender<MemoryStream>,
+}
+
+impl MemoryConnector {
+	/// Open a new connection to the listener.
+	pub fn connect(&self) -> Result<MemoryStream, IOError> {
+		let (client_tx, server_rx) = channel();
+		let (server_tx, client_rx) = channel();
+
+		self.outgoing
+			.send(MemoryStream::new(server_tx, server_rx))
+			.map_err(|_| IOError::ConnectNixError(nix::Error::ECONNREFUSED))?;
+
+		Ok(MemoryStream::new(client_tx, client_rx))
+	}
+}
+
+/// One end of an in memory connection.
+#[derive(Debug)]
+pub struct MemoryStream {
+	tx: Sender<Vec<u8>>,
+	rx: Receiver<Vec<u8>>,
+	timeout: Cell<Option<Duration>>,
+	/// Message received by [`Connection::wait_readable`], but not yet by
+	/// [`Connection::recv`].
+	pending: RefCell<Option<Vec<u8>>>,
+}
+
+impl MemoryStream {
+	fn new(tx: Sender<Vec<u8>>, rx: Receiver<Vec<u8>>) -> Self {
+		Self { tx, rx, timeout: Cell::new(None), pending: RefCell::new(None) }
+	}
+}
+
+impl Connection for MemoryStream {
+	fn send(&self, buf: &[u8]) -> Result<(), IOError> {
+		frame::check_size(buf.len())?;
+		self.tx
+			.send(buf.to_vec())
+			.map_err(|_| IOError::SendNixError(nix::Error::EPIPE))
+	}
+
+	fn recv(&self) -> Result<Vec<u8>, IOError> {
+		if let Some(buf) = self.pending.borrow_mut().take() {
+			return Ok(buf);
+		}
+
+		match self.timeout.get() {
+			Some(timeout) => {
+				self.rx.recv_timeout(timeout).map_err(|e| match e {
+					RecvTimeoutError::Timeout => IOError::RecvTimeout,
+					RecvTimeoutError::Disconnected => {
+						IOError::RecvConnectionClosed
+					}
+				})
+			}
+			None => self.rx.recv().map_err(|_| IOError::RecvConnectionClosed),
+		}
+	}
+
+	fn set_timeout(&self, timeout: TimeVal) -> Result<(), IOError> {
+		let micros = u64::try_from(timeout.num_microseconds())
+			.map_err(|_| IOError::ArithmeticSaturation)?;
+		self.timeout.set(Some(Duration::from_micros(micros)));
+		Ok(())
+	}
+
+	fn wait_readable(&self, timeout: TimeVal) -> Result<bool, IOError> {
+		if self.pending.borrow().is_some() {
+			return Ok(true);
+		}
+
+		let micros = u64::try_from(timeout.num_microseconds())
+			.map_err(|_| IOError::ArithmeticSaturation)?;
+		match self.rx.recv_timeout(Duration::from_micros(micros)) {
+			Ok(buf) => {
+				*self.pending.borrow_mut() = Some(buf);
+				Ok(true)
+			}
+			Err(RecvTimeoutError::Timeout) => Ok(false),
+			// Let the following receive report the closed connection
+			Err(RecvTimeoutError::Disconnected) => Ok(true),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn memory_stream_round_trip() {
+		let (listener, connector) = listener();
+
+		let client = connector.connect().unwrap();
+		let server = listener.accept().unwrap();
+
+		client.send(b"ping").unwrap();
+		assert_eq!(server.recv().unwrap(), b"ping".to_vec());
+		server.send(b"pong").unwrap();
+		assert_eq!(client.recv().unwrap(), b"pong".to_vec());
+	}
+
+	#[test]
+	fn memory_stream_reports_socket_like_errors() {
+		let (listener, connector) = listener();
+
+		let client = connector.connect().unwrap();
+		let server = listener.accept().unwrap();
+		server.set_timeout(TimeVal::milliseconds(10)).unwrap();
+		assert!(matches!(server.recv(), Err(IOError::RecvTimeout)));
+
+		drop(client);
+		assert!(matches!(server.recv(), Err(IOError::RecvConnectionClosed)));
+		assert!(matches!(
+			server.send(b"pong"),
+			Err(IOError::SendNixError(nix::Error::EPIPE))
+		));
+
+		drop(connector);
+		assert!(listener.accept().is_err());
+		assert!(listener.is_shutdown());
+
+		let (listener, connector) = super::listener();
+		drop(listener);
+		assert!(matches!(
+			connector.connect(),
+			Err(IOError::ConnectNixError(nix::Error::ECONNREFUSED))
+		));
+	}
+}
@@ -0,0 +1,206 @@
+//! Hooks for observing socket servers without depending on a metrics crate.
+//!
+//! Implement [`Metrics`] to export the events to a metrics backend, or use
+//! [`Counters`] for a simple in memory implementation.
+
+use std::{
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+use super::IOError;
+
+/// Upper bounds (inclusive, in milliseconds) of the request latency histogram
+/// buckets. Latencies above the last bound fall in an implicit overflow
+/// bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 8] = [1, 5, 10, 50, 100, 500, 1_000, 5_000];
+
+/// Coarse classification of errors encountered while serving a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+	/// Accepting a new connection failed.
+	Accept,
+	/// A send or receive timed out.
+	Timeout,
+	/// The peer closed the connection before a request was received.
+	ConnectionClosed,
+	/// A malformed frame was received.
+	Framing,
+	/// Any other I/O error.
+	Other,
+}
+
+impl ErrorKind {
+	/// Number of [`ErrorKind`] variants.
+	pub const COUNT: usize = 5;
+
+	fn index(self) -> usize {
+		self as usize
+	}
+}
+
+impl From<&IOError> for ErrorKind {
+	fn from(err: &IOError) -> Self {
+		match err {
+			IOError::RecvTimeout | IOError::SendTimeout => Self::Timeout,
+			IOError::RecvConnectionClosed => Self::ConnectionClosed,
+			IOError::OversizedFrame { .. }
+			| IOError::TruncatedFrame { .. }
+			| IOError::FrameChecksumMismatch => Self::Framing,
+			_ => Self::Other,
+		}
+	}
+}
+
+/// Events emitted by a socket server. All methods default to doing nothing,
+/// so implementors only need to override the events they care about.
+pub trait Metrics: Send + Sync {
+	/// A new connection was accepted.
+	fn connection_accepted(&self) {}
+
+	/// A request of `bytes` length was received.
+	fn request_received(&self, _bytes: usize) {}
+
+	/// A response of `bytes` length was sent, `latency` after the request was
+	/// received.
+	fn response_sent(&self, _bytes: usize, _latency: Duration) {}
+
+	/// The app handled a request, but failed to serve it. Socket servers do
+	/// not know whether a request failed, so only app middleware emits this.
+	fn request_failed(&self) {}
+
+	/// An error of the given `kind` occurred.
+	fn error(&self, _kind: ErrorKind) {}
+}
+
+/// [`Metrics`] that ignores all events.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+/// Index of the [`LATENCY_BUCKETS_MS`] bucket `latency` falls in. Returns
+/// `LATENCY_BUCKETS_MS.len()` for the overflow bucket.
+#[must_use]
+pub fn latency_bucket(latency: Duration) -> usize {
+	let millis = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+	LATENCY_BUCKETS_MS
+		.iter()
+		.position(|bound| millis <= *bound)
+		.unwrap_or(LATENCY_BUCKETS_MS.len())
+}
+
+/// [`Metrics`] backed by atomic counters.
+#[derive(Debug, Default)]
+pub struct Counters {
+	connections: AtomicU64,
+	requests: AtomicU64,
+	failed_requests: AtomicU64,
+	bytes_received: AtomicU64,
+	bytes_sent: AtomicU64,
+	errors: [AtomicU64; ErrorKind::COUNT],
+	latency: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+/// Point in time copy of [`Counters`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CountersSnapshot {
+	/// Number of accepted connections.
+	pub connections: u64,
+	/// Number of received requests.
+	pub requests: u64,
+	/// Number of requests the app failed to serve.
+	pub failed_requests: u64,
+	/// Total bytes of received requests.
+	pub bytes_received: u64,
+	/// Total bytes of sent responses.
+	pub bytes_sent: u64,
+	/// Number of errors, indexed by [`ErrorKind`] discriminant.
+	pub errors: [u64; ErrorKind::COUNT],
+	/// Number of responses per latency bucket. See [`LATENCY_BUCKETS_MS`].
+	pub latency: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl CountersSnapshot {
+	/// Number of errors of the given `kind`.
+	#[must_use]
+	pub fn errors_of(&self, kind: ErrorKind) -> u64 {
+		self.errors[kind.index()]
+	}
+}
+
+impl Counters {
+	/// Take a snapshot of the current counter values.
+	#[must_use]
+	pub fn snapshot(&self) -> CountersSnapshot {
+		let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+		CountersSnapshot {
+			connections: load(&self.connections),
+			requests: load(&self.requests),
+			failed_requests: load(&self.failed_requests),
+			bytes_received: load(&self.bytes_received),
+			bytes_sent: load(&self.bytes_sent),
+			errors: std::array::from_fn(|i| load(&self.errors[i])),
+			latency: std::array::from_fn(|i| load(&self.latency[i])),
+		}
+	}
+}
+
+impl Metrics for Counters {
+	fn connection_accepted(&self) {
+		self.connections.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn request_received(&self, bytes: usize) {
+		self.requests.fetch_add(1, Ordering::Relaxed);
+		self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	fn response_sent(&self, bytes: usize, latency: Duration) {
+		self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+		self.latency[latency_bucket(latency)].fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn request_failed(&self) {
+		self.failed_requests.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn error(&self, kind: ErrorKind) {
+		self.errors[kind.index()].fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn latency_bucket_boundaries() {
+		assert_eq!(latency_bucket(Duration::ZERO), 0);
+		assert_eq!(latency_bucket(Duration::from_millis(1)), 0);
+		assert_eq!(latency_bucket(Duration::from_millis(2)), 1);
+		assert_eq!(latency_bucket(Duration::from_millis(5_000)), 7);
+		assert_eq!(latency_bucket(Duration::from_secs(60)), 8);
+	}
+
+	#[test]
+	fn counters_record_events() {
+		let counters = Counters::default();
+		counters.connection_accepted();
+		counters.request_received(10);
+		counters.response_sent(20, Duration::from_millis(3));
+		counters.error((&IOError::RecvTimeout).into());
+		counters.request_failed();
+
+		let snapshot = counters.snapshot();
+		assert_eq!(snapshot.connections, 1);
+		assert_eq!(snapshot.requests, 1);
+		assert_eq!(snapshot.failed_requests, 1);
+		assert_eq!(snapshot.bytes_received, 10);
+		assert_eq!(snapshot.bytes_sent, 20);
+		assert_eq!(snapshot.errors_of(ErrorKind::Timeout), 1);
+		assert_eq!(snapshot.errors_of(ErrorKind::Framing), 0);
+		assert_eq!(snapshot.latency[1], 1);
+	}
+}
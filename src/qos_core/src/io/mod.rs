@@ -3,13 +3,50 @@
 //! NOTE TO MAINTAINERS: Interaction with any sys calls should be contained
 //! within this module.
 
+#[cfg(feature = "async")]
+mod async_stream;
+pub mod frame;
+#[cfg(any(feature = "mock", test))]
+pub mod memory;
+pub mod metrics;
 mod stream;
 
+#[cfg(feature = "async")]
+pub use async_stream::{AsyncListener, AsyncStream};
 pub use stream::{
-	Listener, SocketAddress, Stream, TimeVal, TimeValLike, VMADDR_FLAG_TO_HOST,
-	VMADDR_NO_FLAGS,
+	Backoff, Listener, SocketAddress, SocketPermissions, Stream, TimeVal,
+	TimeValLike, VMADDR_CID_ANY, VMADDR_FLAG_TO_HOST, VMADDR_NO_FLAGS,
 };
 
+/// A connection over which whole messages are exchanged.
+pub trait Connection {
+	/// Send `buf` as a single message.
+	fn send(&self, buf: &[u8]) -> Result<(), IOError>;
+
+	/// Receive a single message.
+	fn recv(&self) -> Result<Vec<u8>, IOError>;
+
+	/// Set the timeout for each individual send and receive.
+	fn set_timeout(&self, timeout: TimeVal) -> Result<(), IOError>;
+
+	/// Wait up to `timeout` for the peer to send something or close the
+	/// connection. Returns `false` if the connection stayed idle.
+	fn wait_readable(&self, timeout: TimeVal) -> Result<bool, IOError>;
+}
+
+/// Source of incoming [`Connection`]s.
+pub trait Acceptor {
+	/// Type of the accepted connections.
+	type Connection: Connection;
+
+	/// Block until the next incoming connection is accepted.
+	fn accept(&self) -> Result<Self::Connection, IOError>;
+
+	/// Whether the acceptor was shut down, in which case [`Self::accept`]
+	/// failing is expected.
+	fn is_shutdown(&self) -> bool;
+}
+
 /// QOS I/O error
 #[derive(Debug)]
 pub enum IOError {
@@ -21,6 +58,8 @@ pub enum IOError {
 	UnknownError,
 	/// Timed out while calling `recv` over a socket.
 	RecvTimeout,
+	/// Timed out while calling `send` over a socket.
+	SendTimeout,
 	/// The `recv` system call was interrupted while receiving over a socket.
 	RecvInterrupted,
 	/// Receive was called on a closed connection.
@@ -31,6 +70,30 @@ pub enum IOError {
 	SendNixError(nix::Error),
 	/// A nix error encountered while calling `recv`.
 	RecvNixError(nix::Error),
+	/// `std::io::Error` wrapper. Returned by the async I/O abstractions when
+	/// interacting with the runtime's reactor fails.
+	StdIoError(std::io::Error),
+	/// The peer announced a frame larger than
+	/// [`frame::MAX_FRAME_PAYLOAD_SIZE`], or the caller tried to send one. Also
+	/// returned for messages whose frames add up to more than
+	/// [`frame::MAX_MESSAGE_SIZE`].
+	OversizedFrame {
+		/// Size of the frame payload or message in bytes.
+		size: usize,
+		/// Maximum allowed size in bytes.
+		max: usize,
+	},
+	/// The connection closed before a complete frame was received.
+	TruncatedFrame {
+		/// Number of bytes the frame (or its header) should have had.
+		expected: usize,
+		/// Number of bytes actually received.
+		received: usize,
+	},
+	/// The received frame payload does not match its checksum.
+	FrameChecksumMismatch,
+	/// A unix socket file does not have the expected mode or owner.
+	UnexpectedSocketPermissions,
 }
 
 impl From<nix::Error> for IOError {
@@ -2,14 +2,19 @@
 
 use std::{
 	io::{ErrorKind, Read, Write},
-	mem::size_of,
-	os::unix::io::RawFd,
+	os::unix::{
+		fs::{MetadataExt, PermissionsExt},
+		io::RawFd,
+	},
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
 };
 
 #[cfg(feature = "vm")]
 use nix::sys::socket::VsockAddr;
 pub use nix::sys::time::{TimeVal, TimeValLike};
 use nix::{
+	poll::{poll, PollFd, PollFlags},
 	sys::socket::{
 		accept, bind, connect, listen, recv, send, shutdown, socket, sockopt,
 		AddressFamily, MsgFlags, SetSockOpt, Shutdown, SockFlag, SockType,
@@ -18,7 +23,11 @@ use nix::{
 	unistd::close,
 };
 
-use super::IOError;
+use super::{
+	frame::{check_message_size, FrameHeader, FRAME_HEADER_SIZE},
+	Acceptor, Connection, IOError,
+};
+use crate::time::{SystemClock, TimeSource};
 
 // 25(retries) x 10(milliseconds) = 1/4 a second of retrying
 const MAX_RETRY: usize = 25;
@@ -39,19 +48,42 @@ pub enum SocketAddress {
 pub const VMADDR_FLAG_TO_HOST: u8 = 0x01;
 /// Don't specify any flags for a VSOCK.
 pub const VMADDR_NO_FLAGS: u8 = 0x00;
+/// Wildcard VSOCK CID. Listening on it accepts connections regardless of the
+/// CID the enclave was assigned.
+pub const VMADDR_CID_ANY: u32 = u32::MAX;
 
 impl SocketAddress {
 	/// Create a new Unix socket.
 	///
+	/// A `path` starting with `@` is treated as the name of an abstract
+	/// socket, see [`Self::new_unix_abstract`].
+	///
 	/// # Panics
 	///
 	/// Panics if `nix::sys::socket::UnixAddr::new` panics.
 	#[must_use]
 	pub fn new_unix(path: &str) -> Self {
+		if let Some(name) = path.strip_prefix('@') {
+			return Self::new_unix_abstract(name);
+		}
+
 		let addr = UnixAddr::new(path).unwrap();
 		Self::Unix(addr)
 	}
 
+	/// Create a new Unix socket in the Linux abstract namespace. Abstract
+	/// sockets have no file system entry, so there is nothing to clean up
+	/// after they are closed.
+	///
+	/// # Panics
+	///
+	/// Panics if `name` is too long for a Unix socket address.
+	#[must_use]
+	pub fn new_unix_abstract(name: &str) -> Self {
+		let addr = UnixAddr::new_abstract(name.as_bytes()).unwrap();
+		Self::Unix(addr)
+	}
+
 	/// Create a new Vsock socket.
 	///
 	/// For flags see: [Add flags field in the vsock address](<https://lkml.org/lkml/2020/12/11/249>).
@@ -78,7 +110,8 @@ impl SocketAddress {
 			svm_flags: flags,
 			svm_zero: [0; 3],
 		};
-		let vsock_addr_len = size_of::<sockaddr_vm>() as libc::socklen_t;
+		let vsock_addr_len =
+			std::mem::size_of::<sockaddr_vm>() as libc::socklen_t;
 		let addr = unsafe {
 			VsockAddr::from_raw(
 				&vsock_addr as *const sockaddr_vm as *const libc::sockaddr,
@@ -110,6 +143,70 @@ impl SocketAddress {
 	}
 }
 
+/// Mode and ownership of a unix socket file. Abstract sockets have no file
+/// and therefore no permissions, so they are exempt from these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketPermissions {
+	/// Permission bits, e.g. `0o660`.
+	pub mode: u32,
+	/// Owning user id. `None` to leave it unchanged / not check it.
+	pub uid: Option<u32>,
+	/// Owning group id. `None` to leave it unchanged / not check it.
+	pub gid: Option<u32>,
+}
+
+impl SocketPermissions {
+	/// Apply these permissions to the socket file of `addr`.
+	pub fn apply(&self, addr: &SocketAddress) -> Result<(), IOError> {
+		let Some(path) = unix_path(addr) else { return Ok(()) };
+
+		std::os::unix::fs::chown(path, self.uid, self.gid)
+			.map_err(IOError::StdIoError)?;
+		std::fs::set_permissions(
+			path,
+			std::fs::Permissions::from_mode(self.mode),
+		)
+		.map_err(IOError::StdIoError)
+	}
+
+	/// Check that the socket file of `addr` has exactly these permissions.
+	pub fn verify(&self, addr: &SocketAddress) -> Result<(), IOError> {
+		let Some(path) = unix_path(addr) else { return Ok(()) };
+
+		let metadata = std::fs::metadata(path).map_err(IOError::StdIoError)?;
+		let mode_ok = metadata.mode() & 0o777 == self.mode;
+		let uid_ok = self.uid.map_or(true, |uid| metadata.uid() == uid);
+		let gid_ok = self.gid.map_or(true, |gid| metadata.gid() == gid);
+
+		if mode_ok && uid_ok && gid_ok {
+			Ok(())
+		} else {
+			Err(IOError::UnexpectedSocketPermissions)
+		}
+	}
+}
+
+fn unix_path(addr: &SocketAddress) -> Option<&std::path::Path> {
+	// Not irrefutable when "vm" is enabled
+	#[allow(irrefutable_let_patterns)]
+	if let SocketAddress::Unix(addr) = addr {
+		addr.path()
+	} else {
+		None
+	}
+}
+
+/// Exponential backoff for retrying [`Stream::connect_with_backoff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+	/// Delay after the first failed attempt.
+	pub initial: Duration,
+	/// Upper bound for the delay between two attempts.
+	pub max: Duration,
+	/// Stop retrying once this much time has passed since the first attempt.
+	pub max_elapsed: Duration,
+}
+
 /// Handle on a stream
 pub struct Stream {
 	fd: RawFd,
@@ -126,13 +223,7 @@ impl Stream {
 		for _ in 0..MAX_RETRY {
 			let fd = socket_fd(addr)?;
 			let stream = Self { fd };
-
-			// set `SO_RCVTIMEO`
-			let receive_timeout = sockopt::ReceiveTimeout;
-			receive_timeout.set(fd, &timeout)?;
-
-			let send_timeout = sockopt::SendTimeout;
-			send_timeout.set(fd, &timeout)?;
+			stream.set_timeout(timeout)?;
 
 			match connect(stream.fd, &*addr.addr()) {
 				Ok(()) => return Ok(stream),
@@ -147,110 +238,187 @@ impl Stream {
 		Err(err)
 	}
 
-	/// Sends a buffer over the underlying socket
-	pub fn send(&self, buf: &[u8]) -> Result<(), IOError> {
-		let len = buf.len();
-		// First, send the length of the buffer
-		{
-			let len_buf: [u8; size_of::<u64>()] = (len as u64).to_le_bytes();
-
-			// First, send the length of the buffer
-			let mut sent_bytes = 0;
-			while sent_bytes < len_buf.len() {
-				sent_bytes += match send(
-					self.fd,
-					&len_buf[sent_bytes..len_buf.len()],
-					MsgFlags::empty(),
-				) {
-					Ok(size) => size,
-					Err(err) => return Err(IOError::SendNixError(err)),
-				};
-			}
-		}
+	/// Like [`Self::connect`], but keep retrying with exponential `backoff`.
+	/// This is useful for riding out a peer restarting, during which its
+	/// socket is briefly missing.
+	pub fn connect_with_backoff(
+		addr: &SocketAddress,
+		timeout: TimeVal,
+		backoff: Backoff,
+	) -> Result<Self, IOError> {
+		Self::connect_with_backoff_and_clock(
+			addr,
+			timeout,
+			backoff,
+			&SystemClock,
+		)
+	}
 
-		// Then, send the contents of the buffer
-		{
-			let mut sent_bytes = 0;
-			while sent_bytes < len {
-				sent_bytes += match send(
-					self.fd,
-					&buf[sent_bytes..len],
-					MsgFlags::empty(),
-				) {
-					Ok(size) => size,
-					Err(err) => return Err(IOError::SendNixError(err)),
-				}
+	/// Like [`Self::connect_with_backoff`], but measure and wait out the
+	/// delays with `clock`.
+	pub fn connect_with_backoff_and_clock(
+		addr: &SocketAddress,
+		timeout: TimeVal,
+		backoff: Backoff,
+		clock: &dyn TimeSource,
+	) -> Result<Self, IOError> {
+		let start = clock.monotonic();
+		let mut delay = backoff.initial;
+
+		loop {
+			let fd = socket_fd(addr)?;
+			let stream = Self { fd };
+			stream.set_timeout(timeout)?;
+
+			let err = match connect(stream.fd, &*addr.addr()) {
+				Ok(()) => return Ok(stream),
+				Err(e) => IOError::ConnectNixError(e),
+			};
+
+			if clock.monotonic() - start + delay > backoff.max_elapsed {
+				return Err(err);
 			}
+			clock.sleep(delay);
+			delay = (delay * 2).min(backoff.max);
 		}
+	}
+
+	/// Set the timeout for each individual send and receive on this stream.
+	/// Operations that exceed the timeout return [`IOError::SendTimeout`] or
+	/// [`IOError::RecvTimeout`].
+	pub fn set_timeout(&self, timeout: TimeVal) -> Result<(), IOError> {
+		// set `SO_RCVTIMEO`
+		let receive_timeout = sockopt::ReceiveTimeout;
+		receive_timeout.set(self.fd, &timeout)?;
+
+		// set `SO_SNDTIMEO`
+		let send_timeout = sockopt::SendTimeout;
+		send_timeout.set(self.fd, &timeout)?;
 
 		Ok(())
 	}
 
-	/// Receive from the underlying socket
+	/// Sends a buffer over the underlying socket as a single frame. See
+	/// [`super::frame`] for the wire format.
+	pub fn send(&self, buf: &[u8]) -> Result<(), IOError> {
+		self.send_frame(FrameHeader::for_payload(buf)?, buf)
+	}
+
+	/// Send `chunk` as part of a message that is continued by further calls
+	/// to this and completed by a call to [`Self::send`]. Lets large messages
+	/// be streamed without holding them in memory at once.
+	pub fn send_partial(&self, chunk: &[u8]) -> Result<(), IOError> {
+		self.send_frame(FrameHeader::for_partial_payload(chunk)?, chunk)
+	}
+
+	fn send_frame(
+		&self,
+		header: FrameHeader,
+		buf: &[u8],
+	) -> Result<(), IOError> {
+		// First, send the header
+		self.send_all(&header.encode())?;
+		// Then, send the contents of the buffer
+		self.send_all(buf)
+	}
+
+	/// Receive a message, which may span several frames, from the underlying
+	/// socket.
 	pub fn recv(&self) -> Result<Vec<u8>, IOError> {
-		let length: usize = {
-			{
-				let mut buf = [0u8; size_of::<u64>()];
-				let len = buf.len();
-				std::debug_assert!(buf.len() == 8);
-
-				let mut received_bytes = 0;
-				while received_bytes < len {
-					received_bytes += match recv(
-						self.fd,
-						&mut buf[received_bytes..len],
-						MsgFlags::empty(),
-					) {
-						Ok(0) => {
-							return Err(IOError::RecvConnectionClosed);
-						}
-						Ok(size) => size,
-						Err(nix::Error::EINTR) => {
-							return Err(IOError::RecvInterrupted);
-						}
-						Err(nix::Error::EAGAIN) => {
-							return Err(IOError::RecvTimeout);
-						}
-						Err(err) => {
-							return Err(IOError::RecvNixError(err));
-						}
-					};
+		let mut message = Vec::new();
+		loop {
+			let header = {
+				let mut buf = [0u8; FRAME_HEADER_SIZE];
+				match self.recv_exact(&mut buf)? {
+					0 => return Err(IOError::RecvConnectionClosed),
+					FRAME_HEADER_SIZE => FrameHeader::decode(buf)?,
+					received => {
+						return Err(IOError::TruncatedFrame {
+							expected: FRAME_HEADER_SIZE,
+							received,
+						})
+					}
 				}
+			};
+			check_message_size(message.len() + header.len)?;
+
+			// Read the payload
+			let start = message.len();
+			message.resize(start + header.len, 0);
+			let received = self.recv_exact(&mut message[start..])?;
+			header.verify(&message[start..start + received])?;
 
-				u64::from_le_bytes(buf)
-					.try_into()
-					// Should only be possible if we are on 32bit architecture
-					.map_err(|_| IOError::ArithmeticSaturation)?
+			if !header.more {
+				return Ok(message);
 			}
-		};
+		}
+	}
 
-		// Read the buffer
-		let mut buf = vec![0; length];
-		{
-			let mut received_bytes = 0;
-			while received_bytes < length {
-				received_bytes += match recv(
-					self.fd,
-					&mut buf[received_bytes..length],
-					MsgFlags::empty(),
-				) {
-					Ok(0) => {
-						return Err(IOError::RecvConnectionClosed);
-					}
+	fn send_all(&self, buf: &[u8]) -> Result<(), IOError> {
+		let mut sent_bytes = 0;
+		while sent_bytes < buf.len() {
+			sent_bytes +=
+				match send(self.fd, &buf[sent_bytes..], MsgFlags::empty()) {
 					Ok(size) => size,
-					Err(nix::Error::EINTR) => {
-						return Err(IOError::RecvInterrupted);
-					}
 					Err(nix::Error::EAGAIN) => {
-						return Err(IOError::RecvTimeout);
-					}
-					Err(err) => {
-						return Err(IOError::NixError(err));
+						return Err(IOError::SendTimeout)
 					}
+					Err(err) => return Err(IOError::SendNixError(err)),
 				};
-			}
 		}
-		Ok(buf)
+
+		Ok(())
+	}
+
+	/// Fill `buf` from the socket, returning the number of bytes received.
+	/// This is only less than `buf.len()` if the peer closed the connection.
+	fn recv_exact(&self, buf: &mut [u8]) -> Result<usize, IOError> {
+		let mut received_bytes = 0;
+		while received_bytes < buf.len() {
+			received_bytes += match recv(
+				self.fd,
+				&mut buf[received_bytes..],
+				MsgFlags::empty(),
+			) {
+				Ok(0) => break,
+				Ok(size) => size,
+				Err(nix::Error::EINTR) => {
+					return Err(IOError::RecvInterrupted);
+				}
+				Err(nix::Error::EAGAIN) => {
+					return Err(IOError::RecvTimeout);
+				}
+				Err(err) => {
+					return Err(IOError::RecvNixError(err));
+				}
+			};
+		}
+
+		Ok(received_bytes)
+	}
+}
+
+impl Connection for Stream {
+	fn send(&self, buf: &[u8]) -> Result<(), IOError> {
+		Stream::send(self, buf)
+	}
+
+	fn recv(&self) -> Result<Vec<u8>, IOError> {
+		Stream::recv(self)
+	}
+
+	fn set_timeout(&self, timeout: TimeVal) -> Result<(), IOError> {
+		Stream::set_timeout(self, timeout)
+	}
+
+	fn wait_readable(&self, timeout: TimeVal) -> Result<bool, IOError> {
+		let millis = i32::try_from(timeout.num_milliseconds())
+			.map_err(|_| IOError::ArithmeticSaturation)?;
+		let mut fds = [PollFd::new(self.fd, PollFlags::POLLIN)];
+
+		// A closed connection also counts as readable, so the following
+		// receive reports it.
+		Ok(poll(&mut fds, millis)? > 0)
 	}
 }
 
@@ -298,27 +466,60 @@ impl Drop for Stream {
 pub struct Listener {
 	fd: RawFd,
 	addr: SocketAddress,
+	shutdown: AtomicBool,
 }
 
 impl Listener {
 	/// Bind and listen on the given address.
 	pub(crate) fn listen(addr: SocketAddress) -> Result<Self, IOError> {
+		Self::listen_with_permissions(addr, None)
+	}
+
+	/// Bind and listen on the given address, applying `permissions` to the
+	/// socket file before accepting any connections.
+	pub(crate) fn listen_with_permissions(
+		addr: SocketAddress,
+		permissions: Option<&SocketPermissions>,
+	) -> Result<Self, IOError> {
 		// In case the last connection at this addr did not shutdown correctly
 		Self::clean(&addr);
 
 		let fd = socket_fd(&addr)?;
 		bind(fd, &*addr.addr())?;
+		if let Some(permissions) = permissions {
+			if let Err(err) = permissions.apply(&addr) {
+				let _ = close(fd);
+				Self::clean(&addr);
+				return Err(err);
+			}
+		}
 		listen(fd, BACKLOG)?;
 
-		Ok(Self { fd, addr })
+		Ok(Self { fd, addr, shutdown: AtomicBool::new(false) })
 	}
 
-	fn accept(&self) -> Result<Stream, IOError> {
+	/// Block until the next incoming connection is accepted.
+	pub(crate) fn accept(&self) -> Result<Stream, IOError> {
 		let fd = accept(self.fd)?;
 
 		Ok(Stream { fd })
 	}
 
+	/// Stop listening for new connections. Any call to [`Self::accept`],
+	/// including one blocked in another thread, returns an error afterwards.
+	/// The socket is closed and unlinked once `self` is dropped.
+	pub(crate) fn shutdown(&self) {
+		self.shutdown.store(true, Ordering::Release);
+		// Its ok if this errors - likely means the listener was already shut
+		// down
+		let _ = shutdown(self.fd, Shutdown::Both);
+	}
+
+	/// Whether [`Self::shutdown`] has been called.
+	pub(crate) fn is_shutdown(&self) -> bool {
+		self.shutdown.load(Ordering::Acquire)
+	}
+
 	/// Remove Unix socket if it exists
 	fn clean(addr: &SocketAddress) {
 		// Not irrefutable when "vm" is enabled
@@ -333,6 +534,18 @@ impl Listener {
 	}
 }
 
+impl Acceptor for Listener {
+	type Connection = Stream;
+
+	fn accept(&self) -> Result<Stream, IOError> {
+		Listener::accept(self)
+	}
+
+	fn is_shutdown(&self) -> bool {
+		Listener::is_shutdown(self)
+	}
+}
+
 impl Iterator for Listener {
 	type Item = Stream;
 	fn next(&mut self) -> Option<Self::Item> {
@@ -373,9 +586,11 @@ mod test {
 		path::Path,
 		str::from_utf8,
 		thread,
+		time::Instant,
 	};
 
 	use super::*;
+	use crate::time::MockClock;
 
 	fn timeval() -> TimeVal {
 		TimeVal::seconds(1)
@@ -506,4 +721,174 @@ mod test {
 
 		handler.join().unwrap();
 	}
+
+	#[test]
+	fn recv_assembles_partial_frames() {
+		let addr =
+			SocketAddress::new_unix("./recv_assembles_partial_frames.sock");
+		let listener = Listener::listen(addr.clone()).unwrap();
+
+		let client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		client.send_partial(b"one ").unwrap();
+		client.send_partial(b"").unwrap();
+		client.send_partial(b"two ").unwrap();
+		client.send(b"three").unwrap();
+		client.send(b"next message").unwrap();
+
+		assert_eq!(server.recv().unwrap(), b"one two three".to_vec());
+		assert_eq!(server.recv().unwrap(), b"next message".to_vec());
+	}
+
+	#[test]
+	fn recv_reports_malformed_frames() {
+		let addr =
+			SocketAddress::new_unix("./recv_reports_malformed_frames.sock");
+		let listener = Listener::listen(addr.clone()).unwrap();
+
+		// Connection closes part way through the header
+		let mut client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		client.write_all(&[1, 0]).unwrap();
+		drop(client);
+		assert!(matches!(
+			server.recv(),
+			Err(IOError::TruncatedFrame {
+				expected: FRAME_HEADER_SIZE,
+				received: 2
+			})
+		));
+
+		// Connection closes part way through the payload
+		let mut client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		let header = FrameHeader::for_payload(b"PING").unwrap();
+		client.write_all(&header.encode()).unwrap();
+		client.write_all(b"PI").unwrap();
+		drop(client);
+		assert!(matches!(
+			server.recv(),
+			Err(IOError::TruncatedFrame { expected: 4, received: 2 })
+		));
+
+		// Header announces a payload that is too large
+		let mut client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		client.write_all(&[0xff; FRAME_HEADER_SIZE]).unwrap();
+		assert!(matches!(server.recv(), Err(IOError::OversizedFrame { .. })));
+
+		// Payload does not match the checksum
+		let mut client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		client.write_all(&header.encode()).unwrap();
+		client.write_all(b"PONG").unwrap();
+		assert!(matches!(server.recv(), Err(IOError::FrameChecksumMismatch)));
+	}
+
+	#[test]
+	fn set_timeout_bounds_stalled_operations() {
+		let addr = SocketAddress::new_unix(
+			"./set_timeout_bounds_stalled_operations.sock",
+		);
+		let listener = Listener::listen(addr.clone()).unwrap();
+		let _client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+		server.set_timeout(TimeVal::milliseconds(100)).unwrap();
+
+		// The client never sends anything
+		assert!(matches!(server.recv(), Err(IOError::RecvTimeout)));
+
+		// The client never reads, so the socket buffer eventually fills up
+		let data = vec![0; 8 * 1024 * 1024];
+		assert!(matches!(server.send(&data), Err(IOError::SendTimeout)));
+	}
+
+	#[test]
+	fn connect_with_backoff_waits_for_listener() {
+		let path = "./connect_with_backoff_waits_for_listener.sock";
+		let addr = SocketAddress::new_unix(path);
+		let backoff = Backoff {
+			initial: Duration::from_millis(10),
+			max: Duration::from_millis(50),
+			max_elapsed: Duration::from_secs(2),
+		};
+
+		let server_addr = addr.clone();
+		let handler = thread::spawn(move || {
+			// Start listening well after the plain `connect` retries would
+			// have given up
+			thread::sleep(Duration::from_millis(500));
+			let listener = Listener::listen(server_addr).unwrap();
+			let stream = listener.accept().unwrap();
+			let req = stream.recv().unwrap();
+			stream.send(&req).unwrap();
+		});
+
+		let client =
+			Stream::connect_with_backoff(&addr, timeval(), backoff).unwrap();
+		client.send(b"ping").unwrap();
+		assert_eq!(client.recv().unwrap(), b"ping".to_vec());
+		handler.join().unwrap();
+
+		// Gives up once `max_elapsed` has passed
+		let start = Instant::now();
+		let backoff =
+			Backoff { max_elapsed: Duration::from_millis(100), ..backoff };
+		assert!(matches!(
+			Stream::connect_with_backoff(&addr, timeval(), backoff),
+			Err(IOError::ConnectNixError(nix::Error::ENOENT))
+		));
+		assert!(start.elapsed() < Duration::from_secs(1));
+	}
+
+	#[test]
+	fn connect_with_backoff_doubles_delays_up_to_max() {
+		let addr = SocketAddress::new_unix(
+			"./connect_with_backoff_doubles_delays_up_to_max.sock",
+		);
+		let backoff = Backoff {
+			initial: Duration::from_millis(10),
+			max: Duration::from_millis(50),
+			max_elapsed: Duration::from_secs(1),
+		};
+		let clock = MockClock::default();
+
+		assert!(matches!(
+			Stream::connect_with_backoff_and_clock(
+				&addr,
+				timeval(),
+				backoff,
+				&clock
+			),
+			Err(IOError::ConnectNixError(nix::Error::ENOENT))
+		));
+		// Waits 10, 20 and 40ms, then 50ms until the next wait would pass
+		// `max_elapsed`
+		assert_eq!(clock.monotonic(), Duration::from_millis(970));
+	}
+
+	#[test]
+	fn abstract_unix_socket_leaves_no_file() {
+		let addr =
+			SocketAddress::new_unix("@abstract_unix_socket_leaves_no_file");
+		assert_eq!(
+			addr,
+			SocketAddress::new_unix_abstract(
+				"abstract_unix_socket_leaves_no_file"
+			)
+		);
+
+		let listener = Listener::listen(addr.clone()).unwrap();
+		let client = Stream::connect(&addr, timeval()).unwrap();
+		let server = listener.accept().unwrap();
+
+		client.send(b"ping").unwrap();
+		assert_eq!(server.recv().unwrap(), b"ping".to_vec());
+		assert!(!Path::new("abstract_unix_socket_leaves_no_file").exists());
+		assert!(!Path::new("@abstract_unix_socket_leaves_no_file").exists());
+
+		// The name is free again as soon as the listener is closed
+		drop(listener);
+		drop(Listener::listen(addr).unwrap());
+	}
 }
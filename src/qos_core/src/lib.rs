@@ -17,14 +17,24 @@ compile_error!(
 	"feature \"vm\" and feature \"mock\" cannot be enabled at the same time"
 );
 
+#[cfg(feature = "async")]
+pub mod async_client;
+#[cfg(feature = "async")]
+pub mod async_server;
+pub mod audit;
 pub mod cli;
 pub mod client;
+pub mod diagnostics;
+pub mod executor;
 pub mod handles;
+pub mod hex;
 pub mod io;
+pub mod log;
 pub mod parser;
 pub mod protocol;
 pub mod reaper;
 pub mod server;
+pub mod time;
 
 /// Path to Quorum Key secret.
 #[cfg(not(feature = "vm"))]
@@ -54,6 +64,22 @@ pub const MANIFEST_FILE: &str = "./local-enclave/qos.manifest";
 #[cfg(feature = "vm")]
 pub const MANIFEST_FILE: &str = "/qos.manifest";
 
+/// Path to the pivot info, which the pivot can read at startup.
+#[cfg(not(feature = "vm"))]
+pub const PIVOT_INFO_FILE: &str = "./local-enclave/qos.pivot.info";
+/// Path to the pivot info, which the pivot can read at startup.
+#[cfg(feature = "vm")]
+pub const PIVOT_INFO_FILE: &str = "/qos.pivot.info";
+/// Environment variable the [`reaper::Reaper`] sets for the pivot with the
+/// path to the pivot info.
+pub const PIVOT_INFO_FILE_ENV: &str = "QOS_PIVOT_INFO_FILE";
+/// Environment variable the [`reaper::Reaper`] sets for the pivot with the
+/// path to the socket of the [`executor`] server.
+pub const EXECUTOR_SOCKET_ENV: &str = "QOS_EXECUTOR_SOCKET";
+/// Environment variable the [`reaper::Reaper`] sets for the pivot with the
+/// path to the app config from the manifest.
+pub const APP_CONFIG_FILE_ENV: &str = "QOS_APP_CONFIG_FILE";
+
 /// Default socket for enclave <-> secure app communication.
 #[cfg(not(feature = "vm"))]
 pub const SEC_APP_SOCK: &str = "./local-enclave/sec_app.sock";
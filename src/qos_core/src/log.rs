@@ -0,0 +1,294 @@
+//! Level tagged log of the enclave, kept in a bounded buffer in memory.
+//!
+//! Output to stdout and stderr is lost inside a production enclave, so
+//! everything logged here is also kept for the host to pull with
+//! [`crate::protocol::msg::ProtocolMsg::LogsRequest`]. Entries are still
+//! written to stdout, or stderr for warnings and errors, for local runs, unless
+//! they are less severe than [`Logger::set_print_level`]. The CLIs set it with
+//! `--verbose` and `--quiet`, see [`crate::parser::Parser::log_level`].
+//!
+//! Never log secrets: the host, and so anyone with access to it, can read
+//! every entry.
+
+use std::{
+	collections::VecDeque,
+	fmt,
+	sync::{
+		atomic::{AtomicU8, Ordering},
+		Arc, Mutex, OnceLock, PoisonError,
+	},
+};
+
+use crate::time::{SystemClock, TimeSource};
+
+/// Number of entries the [`global`] logger keeps.
+pub const LOG_CAPACITY: usize = 4_096;
+/// Most entries returned for one
+/// [`crate::protocol::msg::ProtocolMsg::LogsRequest`].
+pub const MAX_LOGS_PER_REQUEST: u32 = 1_024;
+
+/// Severity of a [`LogEntry`].
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+	/// Something failed.
+	Error,
+	/// Something unexpected happened, but was recovered from.
+	Warn,
+	/// Progress of the enclave.
+	Info,
+	/// Details for debugging.
+	Debug,
+}
+
+impl fmt::Display for LogLevel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let level = match self {
+			Self::Error => "ERROR",
+			Self::Warn => "WARN",
+			Self::Info => "INFO",
+			Self::Debug => "DEBUG",
+		};
+		f.write_str(level)
+	}
+}
+
+/// A logged message.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+	/// Position of the entry in the log, starting at 0.
+	pub seq: u64,
+	/// Milliseconds since the unix epoch when the entry was logged.
+	pub timestamp_ms: u64,
+	/// Severity of the entry.
+	pub level: LogLevel,
+	/// Component that logged the entry, such as `reaper`.
+	pub target: String,
+	/// The message.
+	pub message: String,
+}
+
+/// Entries read from a [`Logger`], see [`Logger::read`].
+#[derive(
+	Debug,
+	Clone,
+	Default,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct LogBatch {
+	/// The entries, oldest first.
+	pub entries: Vec<LogEntry>,
+	/// Sequence number to read from next.
+	pub next_seq: u64,
+	/// Number of entries that were requested but have already been dropped
+	/// from the buffer.
+	pub dropped: u64,
+}
+
+/// Keeps the latest entries of a log in memory.
+pub struct Logger {
+	capacity: usize,
+	time: Arc<dyn TimeSource>,
+	print_level: AtomicU8,
+	inner: Mutex<Inner>,
+}
+
+impl fmt::Debug for Logger {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Logger")
+			.field("capacity", &self.capacity)
+			.field("print_level", &self.print_level)
+			.field("inner", &self.inner)
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+	entries: VecDeque<LogEntry>,
+	next_seq: u64,
+}
+
+impl Logger {
+	/// Create an empty log that keeps the latest `capacity` entries,
+	/// timestamped with `time`. Every entry is printed until
+	/// [`Self::set_print_level`] is called.
+	#[must_use]
+	pub fn new(capacity: usize, time: Arc<dyn TimeSource>) -> Self {
+		Self {
+			capacity,
+			time,
+			print_level: AtomicU8::new(LogLevel::Debug as u8),
+			inner: Mutex::default(),
+		}
+	}
+
+	/// Only print entries at `level` or more severe. Less severe entries are
+	/// still kept.
+	pub fn set_print_level(&self, level: LogLevel) {
+		self.print_level.store(level as u8, Ordering::Relaxed);
+	}
+
+	/// Log `message` from `target` at `level`, dropping the oldest entry if
+	/// the buffer is full.
+	pub fn log(
+		&self,
+		level: LogLevel,
+		target: &str,
+		message: impl fmt::Display,
+	) {
+		let message = message.to_string();
+		let print = level as u8 <= self.print_level.load(Ordering::Relaxed);
+		if print && level <= LogLevel::Warn {
+			eprintln!("{message}");
+		} else if print {
+			println!("{message}");
+		}
+
+		let timestamp_ms = self.time.now_ms().unwrap_or(0);
+		let mut inner =
+			self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+		let entry = LogEntry {
+			seq: inner.next_seq,
+			timestamp_ms,
+			level,
+			target: target.to_string(),
+			message,
+		};
+
+		inner.next_seq += 1;
+		if inner.entries.len() == self.capacity {
+			inner.entries.pop_front();
+		}
+		if self.capacity > 0 {
+			inner.entries.push_back(entry);
+		}
+	}
+
+	/// Read up to `max` entries starting at sequence number `since`.
+	#[must_use]
+	pub fn read(&self, since: u64, max: usize) -> LogBatch {
+		let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+		let oldest =
+			inner.entries.front().map_or(inner.next_seq, |entry| entry.seq);
+		let start = since.max(oldest);
+
+		let entries: Vec<_> = inner
+			.entries
+			.iter()
+			.skip_while(|entry| entry.seq < start)
+			.take(max)
+			.cloned()
+			.collect();
+		let next_seq = entries.last().map_or(start, |entry| entry.seq + 1);
+
+		LogBatch {
+			entries,
+			next_seq: next_seq.min(inner.next_seq),
+			dropped: start - since,
+		}
+	}
+}
+
+/// The logger of this process, keeping the latest [`LOG_CAPACITY`] entries.
+pub fn global() -> &'static Arc<Logger> {
+	static GLOBAL: OnceLock<Arc<Logger>> = OnceLock::new();
+	GLOBAL.get_or_init(|| {
+		Arc::new(Logger::new(LOG_CAPACITY, Arc::new(SystemClock)))
+	})
+}
+
+/// Log `message` from `target` at [`LogLevel::Error`] to the [`global`]
+/// logger.
+pub fn error(target: &str, message: impl fmt::Display) {
+	global().log(LogLevel::Error, target, message);
+}
+
+/// Log `message` from `target` at [`LogLevel::Warn`] to the [`global`]
+/// logger.
+pub fn warn(target: &str, message: impl fmt::Display) {
+	global().log(LogLevel::Warn, target, message);
+}
+
+/// Log `message` from `target` at [`LogLevel::Info`] to the [`global`]
+/// logger.
+pub fn info(target: &str, message: impl fmt::Display) {
+	global().log(LogLevel::Info, target, message);
+}
+
+/// Log `message` from `target` at [`LogLevel::Debug`] to the [`global`]
+/// logger.
+pub fn debug(target: &str, message: impl fmt::Display) {
+	global().log(LogLevel::Debug, target, message);
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::time::MockClock;
+
+	#[test]
+	fn keeps_the_latest_entries_and_reports_dropped_ones() {
+		let logger =
+			Logger::new(3, Arc::new(MockClock::new(Duration::from_secs(2))));
+		for i in 0..5 {
+			logger.log(LogLevel::Info, "test", format!("message {i}"));
+		}
+
+		let batch = logger.read(0, 10);
+		assert_eq!(batch.dropped, 2);
+		assert_eq!(batch.next_seq, 5);
+		let seqs: Vec<_> =
+			batch.entries.iter().map(|entry| entry.seq).collect();
+		assert_eq!(seqs, [2, 3, 4]);
+		assert_eq!(batch.entries[0].message, "message 2");
+		assert_eq!(batch.entries[0].timestamp_ms, 2_000);
+
+		// Reading from where the last read stopped only returns new entries
+		let batch = logger.read(3, 1);
+		assert_eq!((batch.dropped, batch.next_seq), (0, 4));
+		assert_eq!(batch.entries[0].message, "message 3");
+		// Entries that are not printed are still kept
+		logger.set_print_level(LogLevel::Warn);
+		logger.log(LogLevel::Error, "test", "failed");
+		logger.log(LogLevel::Debug, "test", "details");
+		let batch = logger.read(5, 10);
+		assert_eq!(batch.entries.len(), 2);
+		assert_eq!(batch.entries[0].level, LogLevel::Error);
+		assert_eq!(batch.entries[1].level, LogLevel::Debug);
+		assert_eq!(
+			logger.read(7, 10),
+			LogBatch { next_seq: 7, ..LogBatch::default() }
+		);
+	}
+}
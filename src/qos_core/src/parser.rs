@@ -1,12 +1,17 @@
 //! Command line token parser.
 use core::marker::PhantomData;
-use std::{collections::BTreeMap, convert::From, fmt};
+use std::{collections::BTreeMap, convert::From, fmt, str::FromStr};
+
+use crate::log::LogLevel;
 
 const HELP: &str = "help";
 const HELP_INPUT: &str = "--help";
 const VERSION: &str = "version";
 const VERSION_INPUT: &str = "--version";
+const VERBOSE: &str = "verbose";
+const QUIET: &str = "quiet";
 const INPUT_PREFIX: &str = "--";
+const VALUE_SEPARATOR: char = '=';
 
 /// Token parsing error.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,6 +26,14 @@ pub enum ParserError {
 	MissingValue(String),
 	/// An expected input is missing.
 	MissingInput(String),
+	/// A value was given with `--name=value` for the given token, which does
+	/// not take one.
+	UnexpectedValue(String),
+	/// The value given for a token could not be parsed. Holds the name of the
+	/// token and the value.
+	InvalidValue(String, String),
+	/// None of the tokens of a required [`TokenGroup`] were given.
+	MissingOneOf(Vec<String>),
 }
 
 impl fmt::Display for ParserError {
@@ -38,6 +51,13 @@ impl fmt::Display for ParserError {
 				write!(f, "found argument {i}, which requires a value, but no value was given")
 			}
 			Self::MissingInput(i) => write!(f, "argument {i} is required but was not found"),
+			Self::UnexpectedValue(i) => {
+				write!(f, "found a value for argument {i}, which does not take one")
+			}
+			Self::InvalidValue(i, v) => write!(f, "invalid value {v} for argument {i}"),
+			Self::MissingOneOf(names) => {
+				write!(f, "one of the arguments {} is required but none was found", names.join(", "))
+			}
 		}
 	}
 }
@@ -122,20 +142,27 @@ impl<C: From<String> + GetParserForCommand> CommandParser<C> {
 ///
 /// After registering tokens with [`Self::token`], you can call [`Self::parse`]
 /// to populate the parser with the provided input. To display a help / info
-/// message based on all the registered tokens see [`Self::info`].
+/// message based on all the registered tokens see [`Self::info`] and
+/// [`Self::usage`].
 ///
-/// Both `--help` and `--version` are always registered as tokens.
+/// `--help`, `--version`, `--verbose` and `--quiet` are always registered as
+/// tokens. Values can
+/// be given as `--name value` or `--name=value`. Constraints on several tokens
+/// are registered with [`Self::group`].
 ///
 /// To access the parsed input based on the expected type see:
 ///
 /// * [`Self::help`]
 /// * [`Self::version`]
+/// * [`Self::log_level`]
 /// * [`Self::flag`]
 /// * [`Self::single`]
 /// * [`Self::multiple`]
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Parser {
 	token_map: TokenMap,
+	about: Option<String>,
+	examples: Vec<String>,
 }
 
 impl Parser {
@@ -152,6 +179,27 @@ impl Parser {
 		self
 	}
 
+	/// Describe what the command does at the top of [`Self::info`].
+	#[must_use]
+	pub fn about(mut self, about: &str) -> Self {
+		self.about = Some(about.to_string());
+		self
+	}
+
+	/// Add an example invocation to the end of [`Self::info`].
+	#[must_use]
+	pub fn example(mut self, example: &str) -> Self {
+		self.examples.push(example.to_string());
+		self
+	}
+
+	/// Register a constraint on a group of tokens with the parser.
+	#[must_use]
+	pub fn group(mut self, group: TokenGroup) -> Self {
+		self.token_map.groups.push(group);
+		self
+	}
+
 	/// Whether or not the user passed in `--help`. Should always be checked.
 	#[must_use]
 	pub fn help(&self) -> bool {
@@ -164,8 +212,22 @@ impl Parser {
 		self.token_map.get_flag(VERSION).unwrap_or(false)
 	}
 
+	/// How much to log, according to `--verbose` and `--quiet`:
+	/// [`LogLevel::Debug`], [`LogLevel::Warn`] or [`LogLevel::Info`] if
+	/// neither was passed.
+	#[must_use]
+	pub fn log_level(&self) -> LogLevel {
+		if self.token_map.get_flag(VERBOSE).unwrap_or(false) {
+			LogLevel::Debug
+		} else if self.token_map.get_flag(QUIET).unwrap_or(false) {
+			LogLevel::Warn
+		} else {
+			LogLevel::Info
+		}
+	}
+
 	/// Returns a bool indicating if the flag with `name` was passed. None if
-	/// `name` is not a token in registered in the parser.
+	/// `name` is not a token registered in the parser.
 	#[must_use]
 	pub fn flag(&self, name: &str) -> Option<bool> {
 		self.token_map.get_flag(name)
@@ -178,6 +240,24 @@ impl Parser {
 		self.token_map.get_single(name)
 	}
 
+	/// Parse the value of `name`, see [`Self::single`].
+	///
+	/// # Errors
+	///
+	/// [`ParserError::InvalidValue`] if the value cannot be parsed as `T`.
+	pub fn parse_single<T: FromStr>(
+		&self,
+		name: &str,
+	) -> Result<Option<T>, ParserError> {
+		self.single(name)
+			.map(|value| {
+				value.parse().map_err(|_| {
+					ParserError::InvalidValue(name.to_string(), value.clone())
+				})
+			})
+			.transpose()
+	}
+
 	/// Returns the value of `name` if the token exists and it can be in the
 	/// input multiple times.
 	#[must_use]
@@ -185,6 +265,13 @@ impl Parser {
 		self.token_map.get_multiple(name)
 	}
 
+	/// Whether the token `name` takes a value. None if `name` is not a token
+	/// registered in the parser.
+	#[must_use]
+	pub fn takes_value(&self, name: &str) -> Option<bool> {
+		self.token_map.tokens.get(name).map(|token| token.takes_value)
+	}
+
 	/// Parse the command line arguments. Instead of using this directly it is
 	/// preferred to use [`OptionsParser`] or [`CommandParser`].
 	///
@@ -196,11 +283,33 @@ impl Parser {
 		self.token_map.parse(inputs)
 	}
 
+	/// One line usage of `command` with the required tokens, e.g.
+	/// `Usage: qos_client host-health --host-ip <host-ip> [options]`.
+	#[must_use]
+	pub fn usage(&self, command: &str) -> String {
+		let mut usage = vec![format!("Usage: {command}")];
+		usage.extend(
+			self.token_map
+				.tokens
+				.values()
+				.filter(|token| token.required)
+				.map(|token| token.name().trim().to_string()),
+		);
+		usage.push("[options]".to_string());
+
+		usage.join(" ")
+	}
+
 	/// Info message about tokens.
 	#[must_use]
 	pub fn info(&self) -> String {
 		let mut info = vec![];
 
+		if let Some(about) = &self.about {
+			info.push(about.clone());
+			info.push(String::new());
+		}
+
 		let required = self.tokens_info(true);
 		if !required.is_empty() {
 			info.push("Required CLI inputs:".to_string());
@@ -216,6 +325,21 @@ impl Parser {
 			info.push(optional);
 		}
 
+		let groups = &self.token_map.groups;
+		if !groups.is_empty() {
+			info.push(String::new());
+			info.push("Groups of CLI inputs:".to_string());
+			info.extend(groups.iter().map(TokenGroup::info));
+		}
+
+		if !self.examples.is_empty() {
+			info.push(String::new());
+			info.push("Examples:".to_string());
+			info.extend(
+				self.examples.iter().map(|example| format!("\t{example}")),
+			);
+		}
+
 		info.join("\n")
 	}
 
@@ -263,7 +387,8 @@ impl Token {
 		}
 	}
 
-	/// Let their multiple of this token.
+	/// Let the token be given multiple times, collecting each value in order.
+	/// See [`Parser::multiple`].
 	#[must_use]
 	pub fn allow_multiple(mut self, multiple: bool) -> Self {
 		self.allow_multiple = multiple;
@@ -308,7 +433,9 @@ impl Token {
 	}
 
 	fn name(&self) -> String {
-		if self.takes_value {
+		if self.takes_value && self.allow_multiple {
+			format!("	--{} <{}>...", self.name, self.name)
+		} else if self.takes_value {
 			format!("	--{} <{}>", self.name, self.name)
 		} else {
 			format!("	--{}", self.name)
@@ -327,6 +454,55 @@ impl Token {
 	}
 }
 
+/// Constraint on a group of tokens, registered with [`Parser::group`].
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct TokenGroup {
+	name: String,
+	members: Vec<String>,
+	required: bool,
+	exclusive: bool,
+}
+
+impl TokenGroup {
+	/// Create `name` group of the tokens named `members`. Without
+	/// [`Self::required`] or [`Self::exclusive`] it does not constrain them.
+	#[must_use]
+	pub fn new(name: &str, members: Vec<&str>) -> Self {
+		TokenGroup {
+			name: name.to_string(),
+			members: members.into_iter().map(String::from).collect(),
+			..Default::default()
+		}
+	}
+
+	/// Require that the user must provide at least one token of the group.
+	#[must_use]
+	pub fn required(mut self, required: bool) -> Self {
+		self.required = required;
+		self
+	}
+
+	/// Allow the user to provide at most one token of the group.
+	#[must_use]
+	pub fn exclusive(mut self, exclusive: bool) -> Self {
+		self.exclusive = exclusive;
+		self
+	}
+
+	fn info(&self) -> String {
+		let quantity = match (self.required, self.exclusive) {
+			(true, true) => "exactly one of",
+			(true, false) => "at least one of",
+			(false, true) => "at most one of",
+			(false, false) => "any of",
+		};
+		let members: Vec<_> =
+			self.members.iter().map(|name| format!("--{name}")).collect();
+
+		format!("\t{}: {quantity} {}", self.name, members.join(", "))
+	}
+}
+
 /// Token type.
 #[derive(Clone, Debug, PartialEq)]
 enum TokenType {
@@ -383,6 +559,8 @@ impl fmt::Display for TokenType {
 struct TokenMap {
 	/// Map of token name to `Token`.
 	tokens: BTreeMap<String, Token>,
+	/// Constraints on groups of tokens.
+	groups: Vec<TokenGroup>,
 }
 impl TokenMap {
 	/// Parse input based on expected tokens.
@@ -427,13 +605,28 @@ impl TokenMap {
 	/// Get a bool indicating if the flag was passed. None if the `name` is not
 	/// a [`Token`] in the parser.
 	fn get_flag(&self, name: &str) -> Option<bool> {
-		self.type_of(name).map(TokenType::as_flag)
+		self.tokens
+			.get(name)
+			.map(|_| self.type_of(name).is_some_and(TokenType::as_flag))
+	}
+
+	/// Whether the user provided the token with `name`.
+	fn is_present(&self, name: &str) -> bool {
+		self.tokens.get(name).is_some_and(|token| token.user_value.is_some())
 	}
 
 	/// Fill the tokens in the map with user provided inputs.
 	fn do_parse(&mut self, inputs: &[String]) -> Result<(), ParserError> {
 		let mut iter = inputs.iter().peekable();
 		while let Some(input) = iter.next() {
+			// Split `--name=value`
+			let (input, inline_value) = match input.split_once(VALUE_SEPARATOR)
+			{
+				Some((input, value)) if input.starts_with(INPUT_PREFIX) => {
+					(input, Some(value))
+				}
+				_ => (input.as_str(), None),
+			};
 			self.check_input(input)?;
 
 			let name = &input[INPUT_PREFIX.len()..];
@@ -446,7 +639,9 @@ impl TokenMap {
 
 			let user_value = if token.takes_value {
 				// Find the value
-				let value = if iter
+				let value = if let Some(value) = inline_value {
+					value.to_string()
+				} else if iter
 					.peek()
 					.filter(|i| !i.starts_with(INPUT_PREFIX))
 					.is_some()
@@ -473,6 +668,8 @@ impl TokenMap {
 				} else {
 					TokenType::Single(value)
 				}
+			} else if inline_value.is_some() {
+				return Err(ParserError::UnexpectedValue(name.to_string()));
 			} else {
 				// This token doesn't take a value
 				TokenType::Flag
@@ -482,7 +679,7 @@ impl TokenMap {
 		}
 
 		// Check constraints based on the found tokens.
-		self.check_constraints(inputs)?;
+		self.check_constraints()?;
 
 		Ok(())
 	}
@@ -493,7 +690,8 @@ impl TokenMap {
 	/// - if this token requires the presence of another token
 	/// - if there already exists a token that is mutually exclusive of this
 	///   token.
-	fn check_constraints(&self, inputs: &[String]) -> Result<(), ParserError> {
+	/// - if the constraints of each group hold.
+	fn check_constraints(&self) -> Result<(), ParserError> {
 		for token in self.tokens.values() {
 			// Check if a value is required for the token
 			if token.required && token.user_value.is_none() {
@@ -503,7 +701,7 @@ impl TokenMap {
 			if token.user_value.is_some() {
 				// Check if this token requires the presence of another token
 				if let Some(ref other_name) = token.requires {
-					if !inputs.contains(&(format!("--{other_name}"))) {
+					if !self.is_present(other_name) {
 						return Err(ParserError::MissingInput(
 							other_name.to_string(),
 						));
@@ -513,7 +711,7 @@ impl TokenMap {
 				// Check if there already exists a token that is mutually
 				// exclusive of this token.
 				for other_name in &token.forbids {
-					if inputs.contains(&(format!("--{other_name}"))) {
+					if self.is_present(other_name) {
 						Err(ParserError::MutuallyExclusiveInput(
 							token.name.to_string(),
 							other_name.to_string(),
@@ -522,6 +720,26 @@ impl TokenMap {
 				}
 			}
 		}
+
+		for group in &self.groups {
+			let present: Vec<_> = group
+				.members
+				.iter()
+				.filter(|name| self.is_present(name))
+				.collect();
+			if group.required && present.is_empty() {
+				return Err(ParserError::MissingOneOf(group.members.clone()));
+			}
+			if let [first, second, ..] = present[..] {
+				if group.exclusive {
+					return Err(ParserError::MutuallyExclusiveInput(
+						first.to_string(),
+						second.to_string(),
+					));
+				}
+			}
+		}
+
 		Ok(())
 	}
 
@@ -562,13 +780,25 @@ impl TokenMap {
 
 impl Default for TokenMap {
 	fn default() -> Self {
-		let mut token_map =
-			Self { tokens: BTreeMap::<String, Token>::default() };
+		let mut token_map = Self {
+			tokens: BTreeMap::<String, Token>::default(),
+			groups: Vec::new(),
+		};
 
 		// Add the help and version token to ensure that the options are always
 		// displayed in the help menu.
 		token_map.insert(Token::new(HELP, "Display the help message."));
 		token_map.insert(Token::new(VERSION, "Display the version."));
+		// Add the verbosity tokens to every CLI so they can be used the same
+		// way everywhere.
+		token_map.insert(
+			Token::new(VERBOSE, "Print debugging details.")
+				.forbids(vec![QUIET]),
+		);
+		token_map.insert(
+			Token::new(QUIET, "Only print warnings and errors.")
+				.forbids(vec![VERBOSE]),
+		);
 
 		token_map
 	}
@@ -797,8 +1027,157 @@ mod test {
 					.takes_value(false)
 					.default_value("token3-default"),
 			);
-		let expected = "Required CLI inputs:\n\t--token1 <token1> info 1\n\nOptional CLI inputs:\n\t--help                 Display the help message.\n\t--token2-is-super-long info 2\n\t--token3               info 3 [default: token3-default]\n\t--version              Display the version.";
+		let expected = "Required CLI inputs:\n\t--token1 <token1> info 1\n\nOptional CLI inputs:\n\t--help                 Display the help message.\n\t--quiet                Only print warnings and errors.\n\t--token2-is-super-long info 2\n\t--token3               info 3 [default: token3-default]\n\t--verbose              Print debugging details.\n\t--version              Display the version.";
 
 		assert_eq!(parser.info(), expected);
 	}
+
+	#[test]
+	fn inline_values_work() {
+		let input: Vec<_> = [
+			"--required-with-value=val1",
+			"--multiple=a=b",
+			"--multiple",
+			"c",
+			"--optional-value=",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+
+		let mut parser = setup();
+		parser.parse(&input).unwrap();
+
+		assert_eq!(parser.single("required-with-value").unwrap(), "val1");
+		assert_eq!(
+			parser.multiple("multiple"),
+			Some(&["a=b".to_string(), "c".to_string()][..])
+		);
+		assert_eq!(parser.single("optional-value").unwrap(), "");
+		// Flags that were not passed are false, unknown ones are None
+		assert_eq!(parser.flag("forbid2-no-value"), Some(false));
+		assert_eq!(parser.flag("forbid2-no-valu"), None);
+
+		let input: Vec<_> = ["--required-with-value=a", "--forbid2-no-value=1"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		assert_eq!(
+			setup().parse(&input),
+			Err(ParserError::UnexpectedValue("forbid2-no-value".to_string()))
+		);
+	}
+
+	#[test]
+	fn groups_and_typed_values_work() {
+		let parser = || {
+			Parser::new()
+				.token(Token::new("path", "info 1").takes_value(true))
+				.token(Token::new("yubikey", "info 2"))
+				.token(Token::new("count", "info 3").takes_value(true))
+				.group(
+					TokenGroup::new("key", vec!["path", "yubikey"])
+						.required(true)
+						.exclusive(true),
+				)
+		};
+		let parse = |input: &[&str]| {
+			let input: Vec<_> =
+				input.iter().copied().map(String::from).collect();
+			let mut parser = parser();
+			parser.parse(&input).map(|()| parser)
+		};
+
+		let parsed = parse(&["--yubikey", "--count=3"]).unwrap();
+		assert_eq!(parsed.parse_single::<u32>("count"), Ok(Some(3)));
+		assert_eq!(parsed.parse_single::<u32>("path"), Ok(None));
+
+		let parsed = parse(&["--path", "a", "--count", "three"]).unwrap();
+		assert_eq!(
+			parsed.parse_single::<u32>("count"),
+			Err(ParserError::InvalidValue(
+				"count".to_string(),
+				"three".to_string()
+			))
+		);
+
+		assert_eq!(
+			parse(&["--count", "3"]).unwrap_err(),
+			ParserError::MissingOneOf(vec![
+				"path".to_string(),
+				"yubikey".to_string()
+			])
+		);
+		assert_eq!(
+			parse(&["--yubikey", "--path=a"]).unwrap_err(),
+			ParserError::MutuallyExclusiveInput(
+				"path".to_string(),
+				"yubikey".to_string()
+			)
+		);
+
+		assert!(parser().info().ends_with(
+			"Groups of CLI inputs:\n\tkey: exactly one of --path, --yubikey"
+		));
+	}
+
+	#[test]
+	fn usage_and_examples_work() {
+		let parser = Parser::new()
+			.about("Run the pivot.")
+			.token(
+				Token::new("path", "info 1").required(true).takes_value(true),
+			)
+			.token(
+				Token::new("arg", "info 2")
+					.required(true)
+					.takes_value(true)
+					.allow_multiple(true),
+			)
+			.token(Token::new("dry-run", "info 3"))
+			.example("run --path ./pivot --arg=--usock --arg ./pivot.sock");
+
+		assert_eq!(
+			parser.usage("qos run"),
+			"Usage: qos run --arg <arg>... --path <path> [options]"
+		);
+		let info = parser.info();
+		assert!(info.starts_with("Run the pivot.\n\nRequired CLI inputs:\n"));
+		assert!(info.ends_with(
+			"Examples:\n\trun --path ./pivot --arg=--usock --arg ./pivot.sock"
+		));
+
+		let input: Vec<_> =
+			["--path", "./pivot", "--arg=--usock", "--arg", "./pivot.sock"]
+				.into_iter()
+				.map(String::from)
+				.collect();
+		let mut parser = parser;
+		parser.parse(&input).unwrap();
+		assert_eq!(
+			parser.multiple("arg"),
+			Some(&["--usock".to_string(), "./pivot.sock".to_string()][..])
+		);
+	}
+
+	#[test]
+	fn verbosity_flags_set_the_log_level() {
+		let parse = |input: &[&str]| {
+			let input: Vec<_> =
+				input.iter().copied().map(String::from).collect();
+			let mut parser = Parser::new();
+			parser.parse(&input).map(|()| parser.log_level())
+		};
+
+		assert_eq!(parse(&[]), Ok(LogLevel::Info));
+		assert_eq!(parse(&["--verbose"]), Ok(LogLevel::Debug));
+		assert_eq!(parse(&["--quiet"]), Ok(LogLevel::Warn));
+		assert_eq!(
+			parse(&["--quiet", "--verbose"]),
+			Err(ParserError::MutuallyExclusiveInput(
+				"quiet".to_string(),
+				"verbose".to_string()
+			))
+		);
+	}
 }
@@ -0,0 +1,135 @@
+//! Unary gRPC calls carried over the app socket.
+//!
+//! The host turns each gRPC-Web call it receives into an [`AppGrpcRequest`]
+//! and sends it as the data of a [`super::msg::ProtocolMsg::ProxyRequest`].
+//! The pivot answers with an [`AppGrpcResponse`]. Messages are passed through
+//! as encoded protobuf, so neither QOS nor the host need the service
+//! definitions.
+
+/// A unary gRPC call, as sent by the host to the pivot.
+#[derive(
+	Debug, Clone, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct AppGrpcRequest {
+	/// Path of the method, e.g. `/echo.Echo/Say`.
+	pub path: String,
+	/// Custom metadata sent by the client.
+	pub metadata: Vec<(String, String)>,
+	/// The encoded request message.
+	pub message: Vec<u8>,
+}
+
+/// Outcome of a unary gRPC call, as sent by the pivot to the host.
+#[derive(
+	Debug, Clone, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct AppGrpcResponse {
+	/// Status of the call.
+	pub status: GrpcStatus,
+	/// Custom metadata to send to the client.
+	pub metadata: Vec<(String, String)>,
+	/// The encoded response message, if the call succeeded.
+	pub message: Option<Vec<u8>>,
+}
+
+impl AppGrpcResponse {
+	/// A successful call responding with `message`.
+	#[must_use]
+	pub fn ok(message: Vec<u8>) -> Self {
+		Self {
+			status: GrpcStatus::new(GrpcCode::Ok, ""),
+			metadata: vec![],
+			message: Some(message),
+		}
+	}
+
+	/// A failed call.
+	#[must_use]
+	pub fn error(status: GrpcStatus) -> Self {
+		Self { status, metadata: vec![], message: None }
+	}
+}
+
+/// Status of a gRPC call.
+#[derive(
+	Debug, Clone, PartialEq, Eq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct GrpcStatus {
+	/// Status code.
+	pub code: GrpcCode,
+	/// Description of the error for the client. Empty on success.
+	pub message: String,
+}
+
+impl GrpcStatus {
+	/// Create a new instance of [`Self`].
+	#[must_use]
+	pub fn new(code: GrpcCode, message: &str) -> Self {
+		Self { code, message: message.to_string() }
+	}
+}
+
+/// The canonical gRPC status codes. The borsh encoding of each code is its
+/// numeric value.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum GrpcCode {
+	/// The call succeeded.
+	Ok = 0,
+	/// The call was cancelled.
+	Cancelled = 1,
+	/// An error that fits no other code.
+	Unknown = 2,
+	/// The client sent an invalid argument.
+	InvalidArgument = 3,
+	/// The deadline expired before the call completed.
+	DeadlineExceeded = 4,
+	/// A requested entity was not found.
+	NotFound = 5,
+	/// An entity the client tried to create already exists.
+	AlreadyExists = 6,
+	/// The caller is not permitted to make the call.
+	PermissionDenied = 7,
+	/// A resource has been exhausted.
+	ResourceExhausted = 8,
+	/// The system is not in a state required for the call.
+	FailedPrecondition = 9,
+	/// The call was aborted, typically because of a concurrency issue.
+	Aborted = 10,
+	/// The call went past the valid range.
+	OutOfRange = 11,
+	/// The method is not implemented by the service.
+	Unimplemented = 12,
+	/// An internal invariant was broken.
+	Internal = 13,
+	/// The service is currently unavailable.
+	Unavailable = 14,
+	/// Unrecoverable data loss or corruption.
+	DataLoss = 15,
+	/// The caller does not have valid authentication credentials.
+	Unauthenticated = 16,
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn codes_encode_as_their_value() {
+		assert_eq!(borsh::to_vec(&GrpcCode::Ok).unwrap(), vec![0]);
+		assert_eq!(borsh::to_vec(&GrpcCode::Unimplemented).unwrap(), vec![12]);
+		assert_eq!(
+			borsh::to_vec(&GrpcCode::Unauthenticated).unwrap(),
+			vec![GrpcCode::Unauthenticated as u8]
+		);
+	}
+}
@@ -0,0 +1,91 @@
+//! Health protocol every pivot can answer, whatever its own application
+//! protocol.
+//!
+//! The enclave probes the pivot for
+//! [`super::msg::ProtocolMsg::AppHealthRequest`] by sending it
+//! [`APP_HEALTH_REQUEST`] on the app socket. The pivot answers with a borsh
+//! encoded [`AppHealth`]. Pivots built with `qos_app` answer automatically.
+
+use super::ProtocolError;
+
+/// Request the enclave sends on the app socket to probe the health of the
+/// pivot. It is not valid borsh for any sensible app request, so pivots can
+/// check for it before decoding their own requests.
+pub const APP_HEALTH_REQUEST: &[u8] = b"\xffQOS_APP_HEALTH_REQUEST\xff";
+
+/// Health of the pivot, the response to [`APP_HEALTH_REQUEST`].
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum AppHealth {
+	/// The pivot is running and can serve requests.
+	Ready,
+	/// The pivot is running but cannot serve requests right now, for example
+	/// because it is still loading state or a dependency is down.
+	#[serde(rename_all = "camelCase")]
+	NotReady {
+		/// Why the pivot is not ready.
+		reason: String,
+	},
+}
+
+impl AppHealth {
+	/// Whether the pivot can serve requests.
+	#[must_use]
+	pub fn is_ready(&self) -> bool {
+		matches!(self, Self::Ready)
+	}
+
+	/// Decode the response of the pivot to [`APP_HEALTH_REQUEST`].
+	///
+	/// # Errors
+	///
+	/// [`ProtocolError::InvalidAppHealthResponse`] if the pivot does not
+	/// speak the health protocol.
+	pub fn from_response(response: &[u8]) -> Result<Self, ProtocolError> {
+		borsh::from_slice(response)
+			.map_err(|_| ProtocolError::InvalidAppHealthResponse)
+	}
+
+	/// Encode `self` as the response to [`APP_HEALTH_REQUEST`].
+	///
+	/// # Panics
+	///
+	/// Never, [`AppHealth`] can always be serialized.
+	#[must_use]
+	pub fn to_response(&self) -> Vec<u8> {
+		borsh::to_vec(self).expect("AppHealth can always be serialized. qed.")
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn responses_round_trip() {
+		for health in [
+			AppHealth::Ready,
+			AppHealth::NotReady { reason: "loading".to_string() },
+		] {
+			assert_eq!(
+				AppHealth::from_response(&health.to_response()).unwrap(),
+				health
+			);
+		}
+
+		// A pivot echoing the request does not speak the protocol
+		assert_eq!(
+			AppHealth::from_response(APP_HEALTH_REQUEST),
+			Err(ProtocolError::InvalidAppHealthResponse)
+		);
+	}
+}
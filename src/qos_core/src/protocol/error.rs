@@ -143,6 +143,91 @@ pub enum ProtocolError {
 	DifferentManifest,
 	/// Error from the qos crypto library.
 	QosCrypto(String),
+	/// For some reason the pivot info could not be read from the file system
+	/// or decoded.
+	FailedToGetPivotInfo,
+	/// Failed to put the pivot info.
+	FailedToPutPivotInfo,
+	/// Refused to load a key from a world writable file.
+	WorldWritableKeyFile,
+	/// Refused to load a key from a file not owned by the current user.
+	UnexpectedKeyFileOwner,
+	/// The socket client timed out while sending a request to the enclave
+	/// app.
+	AppClientSendTimeout,
+	/// The host only accepts messages from clients that present a trusted
+	/// TLS certificate.
+	ClientCertificateRequired,
+	/// Failed to securely delete the old Ephemeral Key while rotating it.
+	FailedToDeleteEphemeralKey,
+	/// The host timed out while sending a request to the enclave or waiting
+	/// for its response.
+	EnclaveClientTimeout,
+	/// The host is not sending requests to the enclave for now because the
+	/// last ones failed.
+	EnclaveUnavailable,
+	/// The share rotation is for a different manifest.
+	ShareRotationManifestMismatch,
+	/// The share rotation is not signed by the new personal key.
+	InvalidShareRotationSignature,
+	/// The quorum key was not reconstructed from shares, so there are no
+	/// shares to rotate.
+	NoReconstructedShares,
+	/// None of the shares of the quorum key has the given hash.
+	ShareNotFound,
+	/// The enclave has not booted, so there is no boot attestation document.
+	BootAttestationDocNotFound,
+	/// Got a request that is only answered when the "mock" feature is
+	/// enabled, which should never be the case in production.
+	MockFeatureDisabled,
+	/// The share is in a format version this code does not know.
+	UnsupportedShareVersion(u8),
+	/// The share does not match the checksum in its header.
+	ShareChecksumMismatch,
+	/// The share is of a different quorum key or threshold than expected, so
+	/// it is most likely from another ceremony.
+	ShareCeremonyMismatch,
+	/// A share with the same identifier was already posted.
+	DuplicateShareIndex(u8),
+	/// The share does not match its commitment in the share set.
+	ShareCommitmentMismatch,
+	/// A share posted by a share set member was rejected before
+	/// reconstruction. The shares posted so far are kept, so the other
+	/// members can continue provisioning.
+	RejectedShare {
+		/// The member who posted the share.
+		member: boot::QuorumMember,
+		/// Why the share was rejected.
+		reason: Box<ProtocolError>,
+	},
+	/// The app data for an attestation document requested by the pivot is
+	/// longer than `executor::MAX_APP_DATA_LEN`.
+	OversizedAppData,
+	/// The pivot requested an app secret without a name.
+	InvalidAppSecretName,
+	/// The pivot answered a health probe with something other than an
+	/// encoded `app_health::AppHealth`, so it does not speak the health
+	/// protocol.
+	InvalidAppHealthResponse,
+	/// The manifest requires only approved algorithms, but the enclave was
+	/// not built with the `approved-only` feature.
+	NotApprovedOnlyBuild,
+	/// The manifest lists an algorithm that is not approved, while requiring
+	/// only approved algorithms or running in a build that only offers them.
+	UnapprovedAlgorithm(qos_crypto::algorithms::Algorithm),
+	/// For some reason the app config could not be read from the file
+	/// system.
+	FailedToGetAppConfig,
+	/// Failed to put the app config.
+	FailedToPutAppConfig,
+	/// The trace ID of a `msg::ProtocolMsg::TracedRequest` is not a valid
+	/// `trace::TraceId`.
+	InvalidTraceId,
+	/// The provisioning reset is for a different manifest.
+	ProvisionResetManifestMismatch,
+	/// The provisioning reset is for an Ephemeral Key that was already
+	/// rotated.
+	StaleProvisionReset,
 }
 
 impl From<std::io::Error> for ProtocolError {
@@ -157,6 +242,9 @@ impl From<client::ClientError> for ProtocolError {
 			ClientError::IOError(IOError::RecvTimeout) => {
 				ProtocolError::AppClientRecvTimeout
 			}
+			ClientError::IOError(IOError::SendTimeout) => {
+				ProtocolError::AppClientSendTimeout
+			}
 			ClientError::IOError(IOError::RecvInterrupted) => {
 				ProtocolError::AppClientRecvInterrupted
 			}
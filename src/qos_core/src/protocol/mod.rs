@@ -1,29 +1,117 @@
 //! Quorum protocol
 
-use borsh::BorshSerialize;
-use qos_crypto::sha_256;
+use std::io;
 
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_crypto::{sha_256, DigestAlgorithm, TaggedDigest};
+
+pub mod app_grpc;
+pub mod app_health;
 mod error;
 pub mod msg;
 mod processor;
 pub mod services;
 mod state;
+pub mod status;
+pub mod trace;
 
 pub use error::ProtocolError;
-pub use processor::Processor;
+#[cfg(feature = "async")]
+pub use processor::AsyncProcessor;
+pub use processor::{decode_request, Processor, MAX_ENCODED_MSG_LEN};
 use state::ProtocolState;
-pub use state::{ProtocolPhase, ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS};
+pub use state::{
+	ProtocolPhase, ENCLAVE_APP_SOCKET_CLIENT_BACKOFF,
+	ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
+};
 
 /// 256bit hash
 pub type Hash256 = [u8; 32];
 
+/// Hex encoded SHA256 over the golden borsh encodings of the types that
+/// approvals and hashes are made over: `Manifest`, `ManifestEnvelope`,
+/// `Approval` and `GenesisOutput`. It changes whenever one of their
+/// encodings does, such as when a field is added or reordered. Tests check
+/// it, so such a change has to be made on purpose, and in a new version of
+/// the encoding so existing approvals stay valid, see [`VERSIONED_MAGIC`].
+pub const SCHEMA_HASH: &str =
+	"5d20bd417eb8d723365fd90294c14ff8f31327354764cf3f1c743ed6225ae866";
+
+/// Magic bytes at the start of the borsh encoding of an approved type that
+/// uses fields added after the type was first released, followed by the
+/// version of the encoding. Encodings without them have the original layout,
+/// so existing approvals, hashes and attestations of them stay valid. The
+/// original layouts start with the `u32` length of a string or byte vector,
+/// which is never `u32::MAX`, so the two can be told apart.
+///
+/// See [`services::boot::MANIFEST_VERSION`] and
+/// [`services::genesis::GENESIS_OUTPUT_VERSION`].
+pub const VERSIONED_MAGIC: [u8; 4] = [0xff; 4];
+
+/// Read the start of the encoding of a type with a versioned encoding, see
+/// [`VERSIONED_MAGIC`]. Returns the version, or the bytes read if they are
+/// not the magic bytes and so the start of the original layout.
+pub(crate) fn read_encoding_version<R: io::Read>(
+	reader: &mut R,
+) -> io::Result<Result<u8, [u8; 4]>> {
+	let start = <[u8; 4]>::deserialize_reader(reader)?;
+	if start == VERSIONED_MAGIC {
+		u8::deserialize_reader(reader).map(Ok)
+	} else {
+		Ok(Err(start))
+	}
+}
+
+/// Error for a versioned encoding of `type_name` with unknown `version`.
+pub(crate) fn unsupported_version(type_name: &str, version: u8) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		format!("unsupported {type_name} encoding version {version}"),
+	)
+}
+
 /// Canonical hash of `QuorumOS` types.
 pub trait QosHash: BorshSerialize {
 	/// Get the canonical hash.
 	fn qos_hash(&self) -> Hash256 {
 		sha_256(&borsh::to_vec(self).expect("Implements borsh serialize"))
 	}
+
+	/// Get the canonical hash made with `algorithm`, tagged with the
+	/// algorithm. With [`DigestAlgorithm::Sha256`] the digest is the same as
+	/// [`Self::qos_hash`].
+	fn qos_hash_tagged(&self, algorithm: DigestAlgorithm) -> TaggedDigest {
+		algorithm
+			.digest(&borsh::to_vec(self).expect("Implements borsh serialize"))
+	}
+
+	/// Whether `tagged` is the canonical hash of `self`, made with the
+	/// algorithm it is tagged with.
+	fn matches_qos_hash(&self, tagged: &TaggedDigest) -> bool {
+		tagged
+			.matches(&borsh::to_vec(self).expect("Implements borsh serialize"))
+	}
 }
 
 // Blanket implement QosHash for any type that implements BorshSerialize.
 impl<T: BorshSerialize> QosHash for T {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn qos_hash_tagged_names_its_algorithm() {
+		let value = (1u8, "quorum".to_string());
+
+		let tagged = value.qos_hash_tagged(DigestAlgorithm::Sha256);
+		assert_eq!(tagged.digest(), value.qos_hash());
+		assert!(value.matches_qos_hash(&tagged));
+
+		let tagged = value.qos_hash_tagged(DigestAlgorithm::Sha384);
+		let tagged = TaggedDigest::from_bytes(&tagged.to_bytes()).unwrap();
+		assert_eq!(tagged.algorithm(), DigestAlgorithm::Sha384);
+		assert!(value.matches_qos_hash(&tagged));
+		assert!(!(2u8, "quorum".to_string()).matches_qos_hash(&tagged));
+	}
+}
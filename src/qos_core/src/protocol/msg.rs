@@ -2,16 +2,26 @@
 
 use qos_nsm::types::NsmResponse;
 
-use crate::protocol::{
-	services::{
-		boot::{Approval, ManifestEnvelope},
-		genesis::{GenesisOutput, GenesisSet},
+use crate::{
+	audit::AuditEntry,
+	log::LogBatch,
+	protocol::{
+		app_health::AppHealth,
+		services::{
+			boot::{Approval, ManifestEnvelope},
+			genesis::{GenesisOutput, GenesisSet},
+			provision::{ProvisionReset, ShareRotation},
+			self_test::SelfTestReport,
+		},
+		status::EnclaveStatus,
+		ProtocolError,
 	},
-	ProtocolError,
 };
 
 /// Message types for communicating with protocol executor.
-#[derive(Debug, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
+#[derive(
+	Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
 pub enum ProtocolMsg {
 	/// A error from executing the protocol.
 	ProtocolErrorResponse(ProtocolError),
@@ -80,7 +90,9 @@ pub enum ProtocolMsg {
 	/// Request an attestation document that includes references to the
 	/// manifest (in `user_data`) and the ephemeral key (`public_key`).
 	LiveAttestationDocRequest,
-	/// Response to live attestation document request.
+	/// Response to [`Self::LiveAttestationDocRequest`],
+	/// [`Self::NoncedAttestationDocRequest`] and
+	/// [`Self::BootAttestationDocRequest`].
 	LiveAttestationDocResponse {
 		/// COSE SIGN1 structure with Attestation Doc
 		nsm_response: NsmResponse,
@@ -138,6 +150,120 @@ pub enum ProtocolMsg {
 		/// if the manifest envelope does not exist.
 		manifest_envelope: Box<Option<ManifestEnvelope>>,
 	},
+
+	/// Request a detailed [`EnclaveStatus`].
+	EnclaveStatusRequest,
+	/// Response for [`Self::EnclaveStatusRequest`].
+	EnclaveStatusResponse(Box<EnclaveStatus>),
+
+	/// Restart provisioning: discard the shares posted so far along with the
+	/// recorded share set approvals and rotate the Ephemeral Key, so shares
+	/// must be re-encrypted to the new key.
+	ProvisionResetRequest {
+		/// The manifest and Ephemeral Key to reset provisioning of.
+		reset: ProvisionReset,
+		/// Approval of the reset by a share set member.
+		approval: Approval,
+	},
+	/// Response to [`Self::ProvisionResetRequest`].
+	ProvisionResetResponse {
+		/// COSE SIGN1 structure with an Attestation Doc referencing the new
+		/// Ephemeral Key.
+		nsm_response: NsmResponse,
+	},
+
+	/// Encrypt the share of a share set member to a new personal key, e.g.
+	/// after they lost the old one.
+	RotateShareRequest {
+		/// The share to rotate and the new personal key.
+		rotation: ShareRotation,
+		/// Signature over the rotation by the new personal key.
+		signature: Vec<u8>,
+		/// Approvals of the rotation by a threshold of the share set.
+		approvals: Vec<Approval>,
+	},
+	/// Response to [`Self::RotateShareRequest`].
+	RotateShareResponse {
+		/// The share, encrypted to the new personal key.
+		encrypted_share: Vec<u8>,
+	},
+
+	/// Like [`Self::LiveAttestationDocRequest`], but the attestation document
+	/// also includes the given `nonce`, so it can be shown to be fresh.
+	NoncedAttestationDocRequest {
+		/// Nonce chosen by the caller.
+		nonce: Vec<u8>,
+	},
+	/// Request the attestation document the enclave returned when it was
+	/// booted.
+	BootAttestationDocRequest,
+
+	/// Request the public key of the Ephemeral Key. The mock attestation
+	/// document does not contain the real Ephemeral Key, so this is the only
+	/// way for local development tooling to learn it. Only answered when
+	/// the "mock" feature is enabled.
+	MockEphemeralKeyRequest,
+	/// Response to [`Self::MockEphemeralKeyRequest`].
+	MockEphemeralKeyResponse {
+		/// Encoded public key of the Ephemeral Key.
+		ephemeral_public_key: Vec<u8>,
+	},
+
+	/// Run known-answer tests of the enclave's cryptography.
+	SelfTestRequest,
+	/// Response to [`Self::SelfTestRequest`].
+	SelfTestResponse {
+		/// Outcome of each test.
+		report: SelfTestReport,
+		/// Signature of the Ephemeral Key over the `QosHash` of the report.
+		signature: Vec<u8>,
+	},
+
+	/// Probe the health of the pivot with the
+	/// [`super::app_health::APP_HEALTH_REQUEST`].
+	AppHealthRequest,
+	/// Response to [`Self::AppHealthRequest`].
+	AppHealthResponse(AppHealth),
+
+	/// Read the log of the enclave, see [`crate::log`].
+	LogsRequest {
+		/// Sequence number of the first entry to read.
+		since: u64,
+		/// Most entries to read, capped at
+		/// [`crate::log::MAX_LOGS_PER_REQUEST`].
+		max: u32,
+	},
+	/// Response to [`Self::LogsRequest`].
+	LogsResponse(LogBatch),
+
+	/// Handle `request`, logging its outcome with `trace_id`. The response is
+	/// that of `request`. See [`super::trace`].
+	TracedRequest {
+		/// ID correlating the request across the host, the enclave and the
+		/// pivot, see [`super::trace::TraceId`].
+		trace_id: String,
+		/// The request to handle.
+		request: Box<ProtocolMsg>,
+	},
+
+	/// Read the enclave's audit log of the requests that changed its state or
+	/// used its keys, see [`crate::audit`].
+	AuditLogRequest {
+		/// Index of the first entry to read.
+		since: u64,
+		/// Most entries to read, capped at
+		/// [`crate::audit::MAX_AUDIT_ENTRIES_PER_REQUEST`].
+		max: u32,
+	},
+	/// Response to [`Self::AuditLogRequest`].
+	AuditLogResponse {
+		/// The entries, oldest first.
+		entries: Vec<AuditEntry>,
+		/// Signature of the Quorum Key over the
+		/// [`AuditEntry::signed_head`] of the last entry, empty if there are
+		/// no entries.
+		signature: Vec<u8>,
+	},
 }
 
 #[cfg(test)]
@@ -166,6 +292,7 @@ mod test {
 				test_message_ciphertext: vec![],
 				test_message_signature: vec![],
 				test_message: vec![],
+				share_commitments: vec![],
 			}),
 		};
 
@@ -3,12 +3,31 @@ use borsh::BorshDeserialize;
 use qos_nsm::NsmProvider;
 
 use super::{
-	error::ProtocolError, msg::ProtocolMsg, state::ProtocolState, ProtocolPhase,
+	error::ProtocolError,
+	msg::ProtocolMsg,
+	state::ProtocolState,
+	status::{SharedBootTimeline, SharedPivotStatus},
+	trace, ProtocolPhase,
+};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+#[cfg(feature = "async")]
+use crate::{async_client::AsyncClient, async_server::AsyncRequestProcessor};
+use crate::{
+	audit::{AuditLog, ENCLAVE_AUDIT_LOG_CAPACITY},
+	client::Client,
+	handles::Handles,
+	io::{SocketAddress, TimeVal},
+	log::Logger,
+	server,
+	time::TimeSource,
 };
-use crate::{handles::Handles, io::SocketAddress, server};
 
 const MEGABYTE: usize = 1024 * 1024;
-const MAX_ENCODED_MSG_LEN: usize = 128 * MEGABYTE;
+/// Largest request the enclave decodes.
+pub const MAX_ENCODED_MSG_LEN: usize = 512 * MEGABYTE;
 
 /// Enclave state machine that executes when given a `ProtocolMsg`.
 pub struct Processor {
@@ -19,7 +38,7 @@ impl Processor {
 	/// Create a new `Self`.
 	#[must_use]
 	pub fn new(
-		attestor: Box<dyn NsmProvider>,
+		attestor: Box<dyn NsmProvider + Send>,
 		handles: Handles,
 		app_addr: SocketAddress,
 		test_only_init_phase_override: Option<ProtocolPhase>,
@@ -33,24 +52,491 @@ impl Processor {
 			),
 		}
 	}
+
+	/// Use `app_client` to talk to the enclave app instead of a socket client
+	/// for the `app_addr` passed to [`Self::new`].
+	#[must_use]
+	pub fn with_app_client(mut self, app_client: Client) -> Self {
+		self.state.app_client = app_client;
+		self
+	}
+
+	/// Report `pivot_status` for
+	/// [`ProtocolMsg::EnclaveStatusRequest`]s. The reaper updates it as it
+	/// supervises the pivot.
+	#[must_use]
+	pub fn with_pivot_status(
+		mut self,
+		pivot_status: SharedPivotStatus,
+	) -> Self {
+		self.state.pivot_status = pivot_status;
+		self
+	}
+
+	/// Record boot events in `boot_timeline` and report it for
+	/// [`ProtocolMsg::EnclaveStatusRequest`]s. The reaper records the pivot
+	/// being spawned in it.
+	#[must_use]
+	pub fn with_boot_timeline(
+		mut self,
+		boot_timeline: SharedBootTimeline,
+	) -> Self {
+		self.state.boot_timeline = boot_timeline;
+		self
+	}
+
+	/// Read the time from `time` when checking the validity of attestation
+	/// documents and timestamping audit log entries, instead of from the
+	/// NSM.
+	#[must_use]
+	pub fn with_time_source(mut self, time: Arc<dyn TimeSource>) -> Self {
+		self.state.audit_log =
+			AuditLog::new(ENCLAVE_AUDIT_LOG_CAPACITY, time.clone());
+		self.state.time = time;
+		self
+	}
+
+	/// Serve the entries of `logger` to the host, instead of those of the
+	/// [`crate::log::global`] logger.
+	#[must_use]
+	pub fn with_logger(mut self, logger: Arc<Logger>) -> Self {
+		self.state.logger = logger;
+		self
+	}
+
+	/// Use `timeout` for each send and receive when talking to the enclave
+	/// app, instead of
+	/// [`super::ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS`].
+	#[must_use]
+	pub fn with_app_client_timeout(mut self, timeout: TimeVal) -> Self {
+		self.state.app_client = self.state.app_client.with_timeout(timeout);
+		self
+	}
+}
+
+/// Decode a request from the host. This is where all bytes sent to the
+/// enclave are first parsed, so it must handle any input without panicking.
+///
+/// # Errors
+///
+/// [`ProtocolError::OversizedPayload`] if `req_bytes` is over
+/// [`MAX_ENCODED_MSG_LEN`], or [`ProtocolError::ProtocolMsgDeserialization`]
+/// if it is not a [`ProtocolMsg`].
+pub fn decode_request(req_bytes: &[u8]) -> Result<ProtocolMsg, ProtocolError> {
+	if req_bytes.len() > MAX_ENCODED_MSG_LEN {
+		return Err(ProtocolError::OversizedPayload);
+	}
+
+	ProtocolMsg::try_from_slice(req_bytes)
+		.map_err(|_| ProtocolError::ProtocolMsgDeserialization)
+}
+
+/// Encode `error` as the response to a request that could not be decoded.
+fn error_response(error: ProtocolError) -> Vec<u8> {
+	encode_response(&ProtocolMsg::ProtocolErrorResponse(error))
+}
+
+fn encode_response(response: &ProtocolMsg) -> Vec<u8> {
+	borsh::to_vec(response).expect("ProtocolMsg can always be serialized. qed.")
 }
 
 impl server::RequestProcessor for Processor {
 	fn process(&mut self, req_bytes: Vec<u8>) -> Vec<u8> {
-		if req_bytes.len() > MAX_ENCODED_MSG_LEN {
-			return borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
-				ProtocolError::OversizedPayload,
-			))
-			.expect("ProtocolMsg can always be serialized. qed.");
+		let (trace_id, msg_req) =
+			match decode_request(&req_bytes).and_then(trace::untrace) {
+				Ok(decoded) => decoded,
+				Err(error) => return error_response(error),
+			};
+
+		let response = self.state.handle_msg(&msg_req);
+		trace::log_response(&self.state.logger, trace_id.as_ref(), &response);
+		encode_response(&response)
+	}
+}
+
+/// [`Processor`] for the [`crate::async_server::AsyncSocketServer`].
+///
+/// Requests that change the enclave state are still processed one at a time.
+/// [`ProtocolMsg::ProxyRequest`]s only need to check the phase, so they are
+/// forwarded to the enclave app with an [`AsyncClient`] without holding on to
+/// the state, letting app requests overlap with each other and with other
+/// requests.
+#[cfg(feature = "async")]
+#[derive(Clone)]
+pub struct AsyncProcessor {
+	inner: Arc<Mutex<Processor>>,
+	app_client: AsyncClient,
+}
+
+#[cfg(feature = "async")]
+impl AsyncProcessor {
+	/// Create a new `Self`. `app_client` is used for proxy requests instead of
+	/// the app client of `processor`.
+	#[must_use]
+	pub fn new(processor: Processor, app_client: AsyncClient) -> Self {
+		Self { inner: Arc::new(Mutex::new(processor)), app_client }
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Processor> {
+		self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncRequestProcessor for AsyncProcessor {
+	async fn process(&self, req_bytes: Vec<u8>) -> Vec<u8> {
+		let (trace_id, msg_req) =
+			match decode_request(&req_bytes).and_then(trace::untrace) {
+				Ok(decoded) => decoded,
+				Err(error) => return error_response(error),
+			};
+		drop(req_bytes);
+
+		if let ProtocolMsg::ProxyRequest { data } = &msg_req {
+			// Proxying does not change the phase, so there is no need to keep
+			// the state locked while waiting on the app.
+			let (phase, logger) = {
+				let processor = self.lock();
+				(processor.state.get_phase(), processor.state.logger.clone())
+			};
+			if phase == ProtocolPhase::QuorumKeyProvisioned {
+				let response = match self.app_client.send(data).await {
+					Ok(data) => ProtocolMsg::ProxyResponse { data },
+					Err(e) => ProtocolMsg::ProtocolErrorResponse(e.into()),
+				};
+				trace::log_response(&logger, trace_id.as_ref(), &response);
+				return encode_response(&response);
+			}
 		}
 
-		let Ok(msg_req) = ProtocolMsg::try_from_slice(&req_bytes) else {
-			return borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
-				ProtocolError::ProtocolMsgDeserialization,
-			))
-			.expect("ProtocolMsg can always be serialized. qed.");
+		let inner = self.inner.clone();
+		tokio::task::spawn_blocking(move || {
+			let mut processor =
+				inner.lock().unwrap_or_else(PoisonError::into_inner);
+			let response = processor.state.handle_msg(&msg_req);
+			trace::log_response(
+				&processor.state.logger,
+				trace_id.as_ref(),
+				&response,
+			);
+			encode_response(&response)
+		})
+		.await
+		.expect("a request processor panicked. qed.")
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use qos_nsm::mock::MockNsm;
+	use qos_p256::P256Pair;
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+	use crate::{
+		audit::{AuditEntry, AUDIT_STATUS_ERROR},
+		io::{memory, TimeVal, TimeValLike},
+		log::LogLevel,
+		protocol::{
+			app_health::{AppHealth, APP_HEALTH_REQUEST},
+			status::{BootTimeline, EnclaveStatus, PivotStatus},
+			trace::TraceId,
+		},
+		server::{RequestProcessor, SocketServer},
+		time::MockClock,
+	};
+
+	struct EchoApp;
+	impl RequestProcessor for EchoApp {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[test]
+	fn enclave_status_reports_pivot_status() {
+		let pivot_status = SharedPivotStatus::new();
+		let mut processor = Processor::new(
+			Box::new(MockNsm),
+			Handles::new(
+				"eph".to_string(),
+				"quorum".to_string(),
+				"manifest".to_string(),
+				"pivot".to_string(),
+				"pivot_info".to_string(),
+			),
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::WaitingForQuorumShards),
+		)
+		.with_pivot_status(pivot_status.clone());
+		pivot_status.set(PivotStatus::Running { restarts: 2 });
+
+		let request =
+			borsh::to_vec(&ProtocolMsg::EnclaveStatusRequest).unwrap();
+		let response = processor.process(request);
+
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&response).unwrap(),
+			ProtocolMsg::EnclaveStatusResponse(Box::new(EnclaveStatus {
+				phase: ProtocolPhase::WaitingForQuorumShards,
+				manifest: None,
+				pivot: PivotStatus::Running { restarts: 2 },
+				last_panic: None,
+				timeline: BootTimeline::default(),
+			}))
+		);
+	}
+
+	#[test]
+	fn serves_logs_in_every_phase() {
+		let logger = Arc::new(Logger::new(
+			8,
+			Arc::new(MockClock::new(Duration::from_secs(1))),
+		));
+		logger.log(LogLevel::Info, "test", "booting");
+		logger.log(LogLevel::Warn, "test", "slow");
+		let mut processor = Processor::new(
+			Box::new(MockNsm),
+			Handles::new(
+				"eph".to_string(),
+				"quorum".to_string(),
+				"manifest".to_string(),
+				"pivot".to_string(),
+				"pivot_info".to_string(),
+			),
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::UnrecoverableError),
+		)
+		.with_logger(logger.clone());
+
+		let request =
+			borsh::to_vec(&ProtocolMsg::LogsRequest { since: 1, max: 10 })
+				.unwrap();
+		let response = processor.process(request);
+
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&response).unwrap(),
+			ProtocolMsg::LogsResponse(logger.read(1, 10))
+		);
+		assert_eq!(logger.read(1, 10).entries[0].message, "slow");
+	}
+
+	#[test]
+	fn audits_requests_that_change_state() {
+		let quorum_file: PathWrapper =
+			"./audits_requests_that_change_state.quorum".into();
+		let handles = Handles::new(
+			"eph".to_string(),
+			(*quorum_file).to_string(),
+			"manifest".to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+		let quorum_key = P256Pair::generate().unwrap();
+		handles.put_quorum_key(&quorum_key).unwrap();
+		let mut processor = Processor::new(
+			Box::new(MockNsm),
+			handles,
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		)
+		.with_time_source(Arc::new(MockClock::new(Duration::from_secs(1))));
+
+		// Reads are not audited, failed attempts to change state are
+		let inject = ProtocolMsg::InjectKeyRequest {
+			encrypted_quorum_key: vec![1],
+			signature: vec![2],
 		};
+		for request in [ProtocolMsg::StatusRequest, inject.clone()] {
+			processor.process(borsh::to_vec(&request).unwrap());
+		}
+
+		let request =
+			borsh::to_vec(&ProtocolMsg::AuditLogRequest { since: 0, max: 10 })
+				.unwrap();
+		let ProtocolMsg::AuditLogResponse { entries, signature } =
+			ProtocolMsg::try_from_slice(&processor.process(request)).unwrap()
+		else {
+			panic!("expected an audit log response")
+		};
+
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].route, "InjectKeyRequest");
+		assert_eq!(entries[0].status, AUDIT_STATUS_ERROR);
+		assert_eq!(entries[0].timestamp_ms, 1_000);
+		assert_eq!(
+			entries[0].payload_hash,
+			qos_crypto::sha_256(&borsh::to_vec(&inject).unwrap())
+		);
+		assert!(AuditEntry::verify_signed(
+			&entries,
+			&quorum_key.public_key(),
+			&signature
+		));
+	}
+
+	#[test]
+	fn logs_the_outcome_of_traced_requests() {
+		let logger = Arc::new(Logger::new(
+			8,
+			Arc::new(MockClock::new(Duration::from_secs(1))),
+		));
+		let mut processor = Processor::new(
+			Box::new(MockNsm),
+			Handles::new(
+				"eph".to_string(),
+				"quorum".to_string(),
+				"manifest".to_string(),
+				"pivot".to_string(),
+				"pivot_info".to_string(),
+			),
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::WaitingForQuorumShards),
+		)
+		.with_logger(logger.clone());
+		let trace_id = TraceId::parse("req-1").unwrap();
+
+		let request =
+			borsh::to_vec(&trace_id.wrap(ProtocolMsg::StatusRequest)).unwrap();
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&processor.process(request)).unwrap(),
+			ProtocolMsg::StatusResponse(ProtocolPhase::WaitingForQuorumShards)
+		);
+		let request = borsh::to_vec(
+			&trace_id.wrap(ProtocolMsg::ProxyRequest { data: b"hi".to_vec() }),
+		)
+		.unwrap();
+		let response = processor.process(request);
+		assert!(matches!(
+			ProtocolMsg::try_from_slice(&response).unwrap(),
+			ProtocolMsg::ProtocolErrorResponse(ProtocolError::NoMatchingRoute(
+				_
+			))
+		));
+
+		let entries = logger.read(0, 10).entries;
+		assert_eq!(entries[0].level, LogLevel::Debug);
+		assert_eq!(entries[0].message, "trace_id=req-1 request succeeded");
+		assert_eq!(entries[1].level, LogLevel::Warn);
+		assert!(entries[1]
+			.message
+			.starts_with("trace_id=req-1 request failed"));
+
+		let request = borsh::to_vec(&ProtocolMsg::TracedRequest {
+			trace_id: "not\nvalid".to_string(),
+			request: Box::new(ProtocolMsg::StatusRequest),
+		})
+		.unwrap();
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&processor.process(request)).unwrap(),
+			ProtocolMsg::ProtocolErrorResponse(ProtocolError::InvalidTraceId)
+		);
+	}
+
+	#[test]
+	fn proxies_to_app_in_memory() {
+		let timeout = TimeVal::seconds(1);
+		let (app_listener, app_connector) = memory::listener();
+		let (enclave_listener, enclave_connector) = memory::listener();
+
+		let processor = Processor::new(
+			Box::new(MockNsm),
+			Handles::new(
+				"eph".to_string(),
+				"quorum".to_string(),
+				"manifest".to_string(),
+				"pivot".to_string(),
+				"pivot_info".to_string(),
+			),
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		)
+		.with_app_client(Client::in_memory(app_connector, timeout));
+
+		let app = std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&app_listener, EchoApp);
+		});
+		let enclave = std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&enclave_listener, processor);
+		});
+
+		let client = Client::in_memory(enclave_connector, timeout);
+		let request =
+			borsh::to_vec(&ProtocolMsg::ProxyRequest { data: b"hi".to_vec() })
+				.unwrap();
+		let response = client.send(&request).unwrap();
+
+		assert_eq!(
+			ProtocolMsg::try_from_slice(&response).unwrap(),
+			ProtocolMsg::ProxyResponse { data: b"hi".to_vec() }
+		);
+
+		// Dropping the last connector for each server lets it return
+		drop(client);
+		enclave.join().unwrap();
+		app.join().unwrap();
+	}
+
+	/// Answers health probes as not ready and echoes everything else.
+	struct LoadingApp;
+	impl RequestProcessor for LoadingApp {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			if request == APP_HEALTH_REQUEST {
+				AppHealth::NotReady { reason: "loading".to_string() }
+					.to_response()
+			} else {
+				request
+			}
+		}
+	}
+
+	fn probe_app_health<A>(app: A) -> ProtocolMsg
+	where
+		A: RequestProcessor + Send + 'static,
+	{
+		let timeout = TimeVal::seconds(1);
+		let (app_listener, app_connector) = memory::listener();
+		let mut processor = Processor::new(
+			Box::new(MockNsm),
+			Handles::new(
+				"eph".to_string(),
+				"quorum".to_string(),
+				"manifest".to_string(),
+				"pivot".to_string(),
+				"pivot_info".to_string(),
+			),
+			SocketAddress::new_unix("./never.sock"),
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		)
+		.with_app_client(Client::in_memory(app_connector, timeout));
+		let app = std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&app_listener, app);
+		});
+
+		let request = borsh::to_vec(&ProtocolMsg::AppHealthRequest).unwrap();
+		let response = processor.process(request);
+		// Dropping the last connector lets the app server return
+		drop(processor);
+		app.join().unwrap();
+
+		ProtocolMsg::try_from_slice(&response).unwrap()
+	}
 
-		self.state.handle_msg(&msg_req)
+	#[test]
+	fn probes_app_health() {
+		assert_eq!(
+			probe_app_health(LoadingApp),
+			ProtocolMsg::AppHealthResponse(AppHealth::NotReady {
+				reason: "loading".to_string()
+			})
+		);
+		assert_eq!(
+			probe_app_health(EchoApp),
+			ProtocolMsg::ProtocolErrorResponse(
+				ProtocolError::InvalidAppHealthResponse
+			)
+		);
 	}
 }
@@ -20,6 +20,24 @@ pub(in crate::protocol) fn live_attestation_doc(
 	))
 }
 
+pub(in crate::protocol) fn nonced_attestation_doc(
+	state: &mut ProtocolState,
+	nonce: Vec<u8>,
+) -> Result<NsmResponse, ProtocolError> {
+	let ephemeral_public_key =
+		state.handles.get_ephemeral_key()?.public_key().to_bytes();
+	let manifest_hash =
+		state.handles.get_manifest_envelope()?.manifest.qos_hash().to_vec();
+
+	let request = NsmRequest::Attestation {
+		user_data: Some(manifest_hash),
+		nonce: Some(nonce),
+		public_key: Some(ephemeral_public_key),
+	};
+
+	Ok(state.attestor.nsm_process_request(request))
+}
+
 pub(super) fn get_post_boot_attestation_doc(
 	attestor: &dyn NsmProvider,
 	ephemeral_public_key: Vec<u8>,
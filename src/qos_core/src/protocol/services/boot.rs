@@ -1,13 +1,23 @@
 //! Standard boot logic and types.
 
-use std::{collections::HashSet, fmt};
+use std::{
+	collections::HashSet,
+	fmt,
+	io::{self, Read},
+};
 
-use qos_crypto::sha_256;
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_crypto::{
+	algorithms::{self, Algorithm},
+	ct_eq, sha_256,
+	shamir::ShareCommitment,
+};
 use qos_nsm::types::NsmResponse;
 use qos_p256::{P256Pair, P256Public};
 
 use crate::protocol::{
-	services::attestation, Hash256, ProtocolError, ProtocolState, QosHash,
+	read_encoding_version, services::attestation, unsupported_version, Hash256,
+	ProtocolError, ProtocolState, QosHash, VERSIONED_MAGIC,
 };
 
 /// Enclave configuration specific to AWS Nitro.
@@ -123,6 +133,19 @@ pub struct PivotConfig {
 	/// Arguments to invoke the binary with. Leave this empty if none are
 	/// needed.
 	pub args: Vec<String>,
+	/// Opaque configuration for the app, approved by the manifest set along
+	/// with the rest of the manifest. The pivot can read it from the file
+	/// given by [`crate::APP_CONFIG_FILE_ENV`]. Leave this empty if none is
+	/// needed. Manifests with an app config use the versioned encoding, see
+	/// [`Manifest`].
+	#[serde(default, with = "qos_hex::serde")]
+	pub app_config: Vec<u8>,
+	/// Remote endpoints the pivot may open TCP connections to through the
+	/// egress proxy on the host, see [`EgressEndpoint`]. Leave this empty if
+	/// the pivot needs no network. Manifests with egress endpoints, pinned or
+	/// not, use the versioned encoding, see [`Manifest`].
+	#[serde(default)]
+	pub egress: Vec<EgressEndpoint>,
 }
 
 impl fmt::Debug for PivotConfig {
@@ -131,10 +154,87 @@ impl fmt::Debug for PivotConfig {
 			.field("hash", &qos_hex::encode(&self.hash))
 			.field("restart", &self.restart)
 			.field("args", &self.args.join(" "))
+			.field("app_config", &qos_hex::encode(&self.app_config))
+			.field("egress", &self.egress)
 			.finish()
 	}
 }
 
+/// A remote endpoint the pivot may connect to, written `host:port`,
+/// optionally pinning the keys its TLS certificates must chain through.
+#[derive(
+	Debug,
+	PartialEq,
+	Eq,
+	Clone,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressEndpoint {
+	/// Hostname or IP address. A leading `*.` matches any subdomain, so
+	/// `*.example.com` matches `api.example.com` but not `example.com`.
+	pub host: String,
+	/// TCP port.
+	pub port: u16,
+	/// SHA-256 hashes of DER encoded `SubjectPublicKeyInfo`s. When not
+	/// empty, TLS connections to the endpoint from inside the enclave only
+	/// succeed if the verified certificate chain, from the server
+	/// certificate up to the root CA, contains one of these keys. See
+	/// `qos_net::tls`.
+	#[serde(default, with = "qos_hex::serde_vec")]
+	pub tls_pins: Vec<[u8; 32]>,
+}
+
+impl EgressEndpoint {
+	/// Whether this endpoint allows connecting to `host` on `port`.
+	/// Hostnames are compared case insensitively.
+	#[must_use]
+	pub fn allows(&self, host: &str, port: u16) -> bool {
+		if port != self.port {
+			return false;
+		}
+		let host = host.to_ascii_lowercase();
+		let allowed = self.host.to_ascii_lowercase();
+
+		match allowed.strip_prefix("*.") {
+			Some(domain) => host
+				.strip_suffix(domain)
+				.is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+			None => host == allowed,
+		}
+	}
+}
+
+impl TryFrom<String> for EgressEndpoint {
+	type Error = ProtocolError;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		let (host, port) =
+			s.rsplit_once(':').ok_or(ProtocolError::FailedToParseFromString)?;
+		let host = host.trim_start_matches('[').trim_end_matches(']');
+		let port =
+			port.parse().map_err(|_| ProtocolError::FailedToParseFromString)?;
+		if host.is_empty() {
+			return Err(ProtocolError::FailedToParseFromString);
+		}
+
+		Ok(Self { host: host.to_string(), port, tls_pins: vec![] })
+	}
+}
+
+impl fmt::Display for EgressEndpoint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.host.contains(':') {
+			write!(f, "[{}]:{}", self.host, self.port)
+		} else {
+			write!(f, "{}:{}", self.host, self.port)
+		}
+	}
+}
+
 /// A quorum member's alias and public key.
 #[derive(
 	PartialEq,
@@ -208,6 +308,12 @@ pub struct ShareSet {
 	/// Members composing the set. The length of this, N, must be gte to the
 	/// `threshold`, K.
 	pub members: Vec<QuorumMember>,
+	/// Pedersen commitments to the polynomials the quorum key was split
+	/// with, from the genesis output. When empty, posted shares are not
+	/// checked against commitments. Manifests with commitments use the
+	/// versioned encoding, see [`Manifest`].
+	#[serde(default, with = "qos_hex::serde_vec")]
+	pub share_commitments: Vec<ShareCommitment>,
 }
 
 /// A member of a quorum set identified solely by their public key.
@@ -258,6 +364,68 @@ pub struct PatchSet {
 	pub members: Vec<MemberPubKey>,
 }
 
+/// Algorithms the enclave uses for the quorum key, its shares and the keys
+/// of the quorum members. See [`qos_crypto::algorithms`].
+pub const ENCLAVE_ALGORITHMS: &[Algorithm] = &[
+	Algorithm::Sha256,
+	Algorithm::Sha512,
+	Algorithm::HmacSha512,
+	Algorithm::HkdfSha512,
+	Algorithm::EcdhP256,
+	Algorithm::EcdsaP256Sha256,
+	Algorithm::AesGcm256,
+];
+
+/// The cryptographic algorithms of the enclave.
+#[derive(
+	PartialEq,
+	Eq,
+	Debug,
+	Clone,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CryptoConfig {
+	/// Whether the enclave must be built with the `approved-only` feature,
+	/// and only use approved algorithms.
+	pub approved_only: bool,
+	/// Algorithms the enclave uses.
+	pub algorithms: Vec<Algorithm>,
+}
+
+impl CryptoConfig {
+	/// Config listing [`ENCLAVE_ALGORITHMS`].
+	#[must_use]
+	pub fn new(approved_only: bool) -> Self {
+		Self { approved_only, algorithms: ENCLAVE_ALGORITHMS.to_vec() }
+	}
+
+	/// Check that this build of the enclave can run with the config.
+	pub fn check(&self) -> Result<(), ProtocolError> {
+		if self.approved_only && !algorithms::APPROVED_ONLY {
+			return Err(ProtocolError::NotApprovedOnlyBuild);
+		}
+		for &algorithm in &self.algorithms {
+			if self.approved_only && !algorithm.is_approved() {
+				return Err(ProtocolError::UnapprovedAlgorithm(algorithm));
+			}
+			algorithms::require_approved(algorithm)
+				.map_err(|_| ProtocolError::UnapprovedAlgorithm(algorithm))?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Default for CryptoConfig {
+	fn default() -> Self {
+		Self::new(algorithms::APPROVED_ONLY)
+	}
+}
+
 /// A Namespace and its relative nonce.
 #[derive(
 	PartialEq,
@@ -294,17 +462,20 @@ impl fmt::Debug for Namespace {
 	}
 }
 
+/// Version of the encoding of [`Manifest`] made by this code, see
+/// [`VERSIONED_MAGIC`].
+pub const MANIFEST_VERSION: u8 = 1;
+
 /// The Manifest for the enclave.
-#[derive(
-	PartialEq,
-	Eq,
-	Debug,
-	Clone,
-	borsh::BorshSerialize,
-	borsh::BorshDeserialize,
-	serde::Serialize,
-	serde::Deserialize,
-)]
+///
+/// Manifests that only use the fields of the first release are encoded in
+/// its layout, so manifests, approvals and envelopes made before the other
+/// fields were added keep decoding and verifying. Manifests that use any of
+/// [`Self::crypto`], [`PivotConfig::app_config`], [`PivotConfig::egress`] or
+/// [`ShareSet::share_commitments`] are encoded with [`VERSIONED_MAGIC`] and
+/// [`MANIFEST_VERSION`], so enclaves from before these fields reject them
+/// instead of misreading them.
+#[derive(PartialEq, Eq, Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(any(feature = "mock", test), derive(Default))]
 pub struct Manifest {
@@ -320,6 +491,109 @@ pub struct Manifest {
 	pub enclave: NitroConfig,
 	/// Patch set members and threshold
 	pub patch_set: PatchSet,
+	/// Cryptographic algorithms of the enclave. Manifests in the original
+	/// layout have [`CryptoConfig::new`] without approved only.
+	pub crypto: CryptoConfig,
+}
+
+/// [`Manifest`] in the layout of the first release.
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+struct ManifestV0 {
+	namespace: Namespace,
+	pivot_hash: Hash256,
+	pivot_restart: RestartPolicy,
+	pivot_args: Vec<String>,
+	manifest_set: ManifestSet,
+	share_set_threshold: u32,
+	share_set_members: Vec<QuorumMember>,
+	enclave: NitroConfig,
+	patch_set: PatchSet,
+}
+
+impl Manifest {
+	/// The manifest in the original layout, if it does not use any of the
+	/// fields added since.
+	fn to_v0(&self) -> Option<ManifestV0> {
+		let original = self.crypto == CryptoConfig::new(false)
+			&& self.pivot.app_config.is_empty()
+			&& self.pivot.egress.is_empty()
+			&& self.share_set.share_commitments.is_empty();
+
+		original.then(|| ManifestV0 {
+			namespace: self.namespace.clone(),
+			pivot_hash: self.pivot.hash,
+			pivot_restart: self.pivot.restart,
+			pivot_args: self.pivot.args.clone(),
+			manifest_set: self.manifest_set.clone(),
+			share_set_threshold: self.share_set.threshold,
+			share_set_members: self.share_set.members.clone(),
+			enclave: self.enclave.clone(),
+			patch_set: self.patch_set.clone(),
+		})
+	}
+}
+
+impl From<ManifestV0> for Manifest {
+	fn from(v0: ManifestV0) -> Self {
+		Self {
+			namespace: v0.namespace,
+			pivot: PivotConfig {
+				hash: v0.pivot_hash,
+				restart: v0.pivot_restart,
+				args: v0.pivot_args,
+				app_config: vec![],
+				egress: vec![],
+			},
+			manifest_set: v0.manifest_set,
+			share_set: ShareSet {
+				threshold: v0.share_set_threshold,
+				members: v0.share_set_members,
+				share_commitments: vec![],
+			},
+			enclave: v0.enclave,
+			patch_set: v0.patch_set,
+			crypto: CryptoConfig::new(false),
+		}
+	}
+}
+
+impl BorshSerialize for Manifest {
+	fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		if let Some(v0) = self.to_v0() {
+			return v0.serialize(writer);
+		}
+
+		VERSIONED_MAGIC.serialize(writer)?;
+		MANIFEST_VERSION.serialize(writer)?;
+		self.namespace.serialize(writer)?;
+		self.pivot.serialize(writer)?;
+		self.manifest_set.serialize(writer)?;
+		self.share_set.serialize(writer)?;
+		self.enclave.serialize(writer)?;
+		self.patch_set.serialize(writer)?;
+		self.crypto.serialize(writer)
+	}
+}
+
+impl BorshDeserialize for Manifest {
+	fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+		match read_encoding_version(reader)? {
+			Ok(MANIFEST_VERSION) => Ok(Self {
+				namespace: BorshDeserialize::deserialize_reader(reader)?,
+				pivot: BorshDeserialize::deserialize_reader(reader)?,
+				manifest_set: BorshDeserialize::deserialize_reader(reader)?,
+				share_set: BorshDeserialize::deserialize_reader(reader)?,
+				enclave: BorshDeserialize::deserialize_reader(reader)?,
+				patch_set: BorshDeserialize::deserialize_reader(reader)?,
+				crypto: BorshDeserialize::deserialize_reader(reader)?,
+			}),
+			Ok(version) => Err(unsupported_version("Manifest", version)),
+			Err(start) => ManifestV0::deserialize_reader(
+				&mut start.as_slice().chain(reader),
+			)
+			.map(Self::from),
+		}
+	}
 }
 
 /// An approval by a Quorum Member.
@@ -439,7 +713,8 @@ pub(in crate::protocol::services) fn put_manifest_and_pivot(
 	if !manifest_envelope.share_set_approvals.is_empty() {
 		return Err(ProtocolError::BadShareSetApprovals);
 	}
-	if sha_256(pivot) != manifest_envelope.manifest.pivot.hash {
+	manifest_envelope.manifest.crypto.check()?;
+	if !ct_eq(&sha_256(pivot), &manifest_envelope.manifest.pivot.hash) {
 		return Err(ProtocolError::InvalidPivotHash);
 	};
 
@@ -529,9 +804,15 @@ mod test {
 				hash: sha_256(&pivot),
 				restart: RestartPolicy::Always,
 				args: vec![],
+				app_config: vec![],
+				egress: vec![],
 			},
 			manifest_set: ManifestSet { threshold: 2, members: quorum_members },
-			share_set: ShareSet { threshold: 2, members: vec![] },
+			share_set: ShareSet {
+				threshold: 2,
+				members: vec![],
+				share_commitments: vec![],
+			},
 			..Default::default()
 		};
 
@@ -547,6 +828,46 @@ mod test {
 		assert!(is_valid);
 	}
 
+	#[test]
+	fn manifest_encoding_is_only_versioned_when_needed() {
+		let (mut manifest, _members, _pivot) = get_manifest();
+		manifest.crypto = CryptoConfig::new(false);
+		let original = borsh::to_vec(&manifest).unwrap();
+		assert!(!original.starts_with(&VERSIONED_MAGIC));
+		assert_eq!(borsh::from_slice::<Manifest>(&original).unwrap(), manifest);
+
+		manifest.crypto.approved_only = true;
+		let versioned = borsh::to_vec(&manifest).unwrap();
+		assert_eq!(versioned[..5], [0xff, 0xff, 0xff, 0xff, MANIFEST_VERSION]);
+		assert_eq!(
+			borsh::from_slice::<Manifest>(&versioned).unwrap(),
+			manifest
+		);
+
+		let mut unknown = versioned;
+		unknown[4] = MANIFEST_VERSION + 1;
+		assert!(borsh::from_slice::<Manifest>(&unknown).is_err());
+
+		manifest.crypto = CryptoConfig::new(false);
+		let endpoint =
+			EgressEndpoint::try_from("example.com:443".to_string()).unwrap();
+		let pinned =
+			EgressEndpoint { tls_pins: vec![[2; 32]], ..endpoint.clone() };
+		let mut with_added_fields = vec![manifest.clone(); 4];
+		with_added_fields[0].share_set.share_commitments = vec![[1; 33]];
+		with_added_fields[1].pivot.app_config = b"config".to_vec();
+		with_added_fields[2].pivot.egress = vec![endpoint];
+		with_added_fields[3].pivot.egress = vec![pinned];
+		for manifest in with_added_fields {
+			let versioned = borsh::to_vec(&manifest).unwrap();
+			assert!(versioned.starts_with(&VERSIONED_MAGIC));
+			assert_eq!(
+				borsh::from_slice::<Manifest>(&versioned).unwrap(),
+				manifest
+			);
+		}
+	}
+
 	#[test]
 	fn boot_standard_accepts_approved_manifest() {
 		let (manifest, members, pivot) = get_manifest();
@@ -579,6 +900,7 @@ mod test {
 			"quorum_key".to_string(),
 			manifest_file.clone(),
 			pivot_file.clone(),
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -637,6 +959,7 @@ mod test {
 			"quorum_key".to_string(),
 			manifest_file,
 			pivot_file,
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -685,6 +1008,7 @@ mod test {
 			"quorum_key".to_string(),
 			manifest_file,
 			pivot_file,
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -735,6 +1059,7 @@ mod test {
 			"quorum_key".to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -795,6 +1120,7 @@ mod test {
 			"quorum_key".to_string(),
 			(*manifest_file).to_string(),
 			(*pivot_file).to_string(),
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -844,4 +1170,72 @@ mod test {
 		let err = manifest_envelope.check_approvals().unwrap_err();
 		assert_eq!(err, ProtocolError::DuplicateApproval);
 	}
+
+	#[test]
+	fn crypto_config_check() {
+		assert_eq!(
+			CryptoConfig::new(algorithms::APPROVED_ONLY).check(),
+			Ok(())
+		);
+
+		let approved_only = CryptoConfig::new(true);
+		if algorithms::APPROVED_ONLY {
+			assert_eq!(approved_only.check(), Ok(()));
+		} else {
+			assert_eq!(
+				approved_only.check(),
+				Err(ProtocolError::NotApprovedOnlyBuild)
+			);
+		}
+
+		let mut mnemonic = CryptoConfig::new(algorithms::APPROVED_ONLY);
+		mnemonic.algorithms.push(Algorithm::Bip39Mnemonic);
+		assert_eq!(
+			mnemonic.check(),
+			if algorithms::APPROVED_ONLY {
+				Err(ProtocolError::UnapprovedAlgorithm(
+					Algorithm::Bip39Mnemonic,
+				))
+			} else {
+				Ok(())
+			}
+		);
+	}
+
+	#[test]
+	fn egress_endpoints_parse_and_match() {
+		let endpoint =
+			EgressEndpoint::try_from("api.example.com:443".to_string())
+				.unwrap();
+		assert!(endpoint.allows("api.example.com", 443));
+		assert!(endpoint.allows("API.example.com", 443));
+		assert!(!endpoint.allows("api.example.com", 80));
+		assert!(!endpoint.allows("evil.api.example.com", 443));
+		assert_eq!(endpoint.to_string(), "api.example.com:443");
+
+		let wildcard =
+			EgressEndpoint::try_from("*.example.com:443".to_string()).unwrap();
+		assert!(wildcard.allows("api.example.com", 443));
+		assert!(wildcard.allows("a.b.example.com", 443));
+		assert!(!wildcard.allows("example.com", 443));
+		assert!(!wildcard.allows("badexample.com", 443));
+
+		let ipv6 = EgressEndpoint::try_from("[::1]:8080".to_string()).unwrap();
+		assert_eq!(
+			ipv6,
+			EgressEndpoint {
+				host: "::1".to_string(),
+				port: 8080,
+				tls_pins: vec![]
+			}
+		);
+		assert_eq!(ipv6.to_string(), "[::1]:8080");
+
+		for invalid in ["example.com", ":443", "example.com:https"] {
+			assert_eq!(
+				EgressEndpoint::try_from(invalid.to_string()),
+				Err(ProtocolError::FailedToParseFromString)
+			);
+		}
+	}
 }
@@ -1,13 +1,21 @@
 //! Genesis boot logic and types.
 
-use std::{fmt, iter::zip};
+use std::{
+	fmt,
+	io::{self, Read},
+	iter::zip,
+};
 
-use qos_crypto::sha_512;
+use borsh::{BorshDeserialize, BorshSerialize};
+use qos_crypto::{sha_512, shamir::ShareCommitment};
 use qos_nsm::types::{NsmRequest, NsmResponse};
 use qos_p256::{P256Pair, P256Public};
 
 use crate::protocol::{
-	services::boot::QuorumMember, ProtocolError, ProtocolState, QosHash,
+	read_encoding_version,
+	services::{boot::QuorumMember, provision::VersionedShare},
+	unsupported_version, ProtocolError, ProtocolState, QosHash,
+	VERSIONED_MAGIC,
 };
 
 const QOS_TEST_MESSAGE: &[u8] = b"qos-test-message";
@@ -49,6 +57,19 @@ impl fmt::Debug for MemberShard {
 )]
 pub struct RecoveredPermutation(Vec<MemberShard>);
 
+#[cfg(test)]
+impl RecoveredPermutation {
+	/// Create a permutation of the given member and shard pairs.
+	pub(crate) fn new(shards: Vec<(QuorumMember, Vec<u8>)>) -> Self {
+		Self(
+			shards
+				.into_iter()
+				.map(|(member, shard)| MemberShard { member, shard })
+				.collect(),
+		)
+	}
+}
+
 /// Genesis output per Setup Member.
 #[derive(
 	PartialEq,
@@ -85,9 +106,18 @@ impl fmt::Debug for GenesisMemberOutput {
 	}
 }
 
+/// Version of the encoding of [`GenesisOutput`] made by this code, see
+/// [`VERSIONED_MAGIC`].
+pub const GENESIS_OUTPUT_VERSION: u8 = 1;
+
 /// Output from running Genesis Boot. Should contain all information relevant to
 /// how the quorum shares where created.
-#[derive(PartialEq, Clone, borsh::BorshSerialize, borsh::BorshDeserialize)]
+///
+/// Outputs without [`Self::share_commitments`] are encoded in the layout of
+/// the first release, so outputs and genesis attestations made before it was
+/// added stay valid. Outputs with them are encoded with [`VERSIONED_MAGIC`]
+/// and [`GENESIS_OUTPUT_VERSION`].
+#[derive(PartialEq, Clone)]
 pub struct GenesisOutput {
 	/// Public Quorum Key, DER encoded.
 	pub quorum_key: Vec<u8>,
@@ -109,6 +139,77 @@ pub struct GenesisOutput {
 	/// The message that was used to generate [`Self::test_message_signature`]
 	/// and [`Self::test_message_ciphertext`]
 	pub test_message: Vec<u8>,
+	/// Pedersen commitments to the polynomials the quorum key was split
+	/// with, see [`qos_crypto::shamir::shares_generate`]. Copied into the
+	/// share set of manifests so posted shares can be checked. Empty for
+	/// outputs in the original layout.
+	pub share_commitments: Vec<ShareCommitment>,
+}
+
+impl BorshSerialize for GenesisOutput {
+	fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+		if !self.share_commitments.is_empty() {
+			VERSIONED_MAGIC.serialize(writer)?;
+			GENESIS_OUTPUT_VERSION.serialize(writer)?;
+		}
+		self.quorum_key.serialize(writer)?;
+		self.member_outputs.serialize(writer)?;
+		self.recovery_permutations.serialize(writer)?;
+		self.threshold.serialize(writer)?;
+		self.dr_key_wrapped_quorum_key.serialize(writer)?;
+		self.quorum_key_hash.serialize(writer)?;
+		self.test_message_ciphertext.serialize(writer)?;
+		self.test_message_signature.serialize(writer)?;
+		self.test_message.serialize(writer)?;
+		if !self.share_commitments.is_empty() {
+			self.share_commitments.serialize(writer)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl BorshDeserialize for GenesisOutput {
+	fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+		match read_encoding_version(reader)? {
+			Ok(GENESIS_OUTPUT_VERSION) => {
+				let mut output = Self::deserialize_fields(reader)?;
+				output.share_commitments =
+					BorshDeserialize::deserialize_reader(reader)?;
+				Ok(output)
+			}
+			Ok(version) => Err(unsupported_version("GenesisOutput", version)),
+			Err(start) => {
+				Self::deserialize_fields(&mut start.as_slice().chain(reader))
+			}
+		}
+	}
+}
+
+impl GenesisOutput {
+	/// Decode the fields of the original layout.
+	fn deserialize_fields<R: Read>(reader: &mut R) -> io::Result<Self> {
+		Ok(Self {
+			quorum_key: BorshDeserialize::deserialize_reader(reader)?,
+			member_outputs: BorshDeserialize::deserialize_reader(reader)?,
+			recovery_permutations: BorshDeserialize::deserialize_reader(
+				reader,
+			)?,
+			threshold: BorshDeserialize::deserialize_reader(reader)?,
+			dr_key_wrapped_quorum_key: BorshDeserialize::deserialize_reader(
+				reader,
+			)?,
+			quorum_key_hash: BorshDeserialize::deserialize_reader(reader)?,
+			test_message_ciphertext: BorshDeserialize::deserialize_reader(
+				reader,
+			)?,
+			test_message_signature: BorshDeserialize::deserialize_reader(
+				reader,
+			)?,
+			test_message: BorshDeserialize::deserialize_reader(reader)?,
+			share_commitments: vec![],
+		})
+	}
 }
 
 impl fmt::Debug for GenesisOutput {
@@ -132,23 +233,26 @@ pub(in crate::protocol) fn boot_genesis(
 	let quorum_pair = P256Pair::generate()?;
 	let master_seed = &quorum_pair.to_master_seed()[..];
 
-	let shares = qos_crypto::shamir::shares_generate(
+	let (shares, share_commitments) = qos_crypto::shamir::shares_generate(
 		master_seed,
 		genesis_set.members.len(),
 		genesis_set.threshold as usize,
 	)
 	.map_err(|e| ProtocolError::QosCrypto(format!("{e:?}")))?;
+	let quorum_key = quorum_pair.public_key().to_bytes();
 
 	let member_outputs: Result<Vec<_>, _> = zip(shares, genesis_set.members.iter().cloned())
 		.map(|(share, share_set_member)| -> Result<GenesisMemberOutput, ProtocolError> {
 			// 1) encrypt the share to quorum key
 			let personal_pub = P256Public::from_bytes(&share_set_member.pub_key)?;
-			let encrypted_quorum_key_share = personal_pub.encrypt(&share)?;
+			let share_hash = sha_512(&share);
+			let share = VersionedShare::new(share, genesis_set.threshold, &quorum_key);
+			let encrypted_quorum_key_share = personal_pub.encrypt(&share.to_bytes())?;
 
 			Ok(GenesisMemberOutput {
 				share_set_member,
 				encrypted_quorum_key_share,
-				share_hash: sha_512(&share),
+				share_hash,
 			})
 		})
 		.collect();
@@ -164,7 +268,7 @@ pub(in crate::protocol) fn boot_genesis(
 	let hex_master_seed = qos_hex::encode(master_seed);
 	let genesis_output = GenesisOutput {
 		member_outputs: member_outputs?,
-		quorum_key: quorum_pair.public_key().to_bytes(),
+		quorum_key,
 		threshold: genesis_set.threshold,
 		// TODO: generate N choose K recovery permutations
 		recovery_permutations: vec![],
@@ -175,6 +279,7 @@ pub(in crate::protocol) fn boot_genesis(
 			.encrypt(QOS_TEST_MESSAGE)?,
 		test_message_signature: quorum_pair.sign(QOS_TEST_MESSAGE)?,
 		test_message: QOS_TEST_MESSAGE.to_vec(),
+		share_commitments,
 	};
 
 	let nsm_response = {
@@ -195,7 +300,10 @@ mod test {
 	use qos_p256::MASTER_SEED_LEN;
 
 	use super::*;
-	use crate::{handles::Handles, io::SocketAddress};
+	use crate::{
+		handles::Handles, io::SocketAddress,
+		protocol::services::provision::open_share,
+	};
 
 	#[test]
 	fn boot_genesis_works() {
@@ -204,6 +312,7 @@ mod test {
 			"QUO".to_string(),
 			"MAN".to_string(),
 			"PIV".to_string(),
+			"pivot_info".to_string(),
 		);
 		let mut protocol_state = ProtocolState::new(
 			Box::new(MockNsm),
@@ -237,15 +346,20 @@ mod test {
 
 		let (output, _nsm_response) =
 			boot_genesis(&mut protocol_state, &genesis_set, None).unwrap();
-		let zipped = std::iter::zip(output.member_outputs, member_pairs);
+		let zipped =
+			std::iter::zip(output.member_outputs.clone(), member_pairs);
 		let shares: Vec<Vec<u8>> = zipped
-			.map(|(output, pair)| {
-				let decrypted_share =
-					&pair.decrypt(&output.encrypted_quorum_key_share).unwrap();
+			.map(|(member_output, pair)| {
+				let decrypted_share = pair
+					.decrypt(&member_output.encrypted_quorum_key_share)
+					.unwrap();
+				let share =
+					open_share(&decrypted_share, &output.quorum_key, threshold)
+						.unwrap();
 
-				assert_eq!(sha_512(decrypted_share), output.share_hash);
+				assert_eq!(sha_512(&share), member_output.share_hash);
 
-				decrypted_share.clone()
+				share
 			})
 			.collect();
 
@@ -254,6 +368,7 @@ mod test {
 				&shares[0..threshold as usize],
 			)
 			.unwrap()
+			.as_slice()
 			.try_into()
 			.unwrap();
 		let reconstructed_quorum_key =
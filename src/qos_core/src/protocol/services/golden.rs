@@ -0,0 +1,219 @@
+//! Golden borsh encodings of the types approvals and hashes are made over.
+//!
+//! Each value sets every field, with distinct contents, so adding, removing,
+//! retyping or reordering a field changes its encoding. If that change is on
+//! purpose, rerun the tests with `UPDATE_GOLDEN=1` to rewrite the vectors in
+//! `static/golden` and update [`SCHEMA_HASH`] with the hash they print.
+//!
+//! The `_v0` vectors are encodings made by the first release, of values that
+//! only set the fields it had. They must never change: they check that such
+//! values are still encoded in the original layout, see
+//! [`crate::protocol::VERSIONED_MAGIC`].
+
+use std::{env, fs};
+
+use qos_crypto::{algorithms::Algorithm, sha_256};
+
+use super::{
+	boot::{
+		Approval, CryptoConfig, EgressEndpoint, Manifest, ManifestEnvelope,
+		ManifestSet, MemberPubKey, Namespace, NitroConfig, PatchSet,
+		PivotConfig, QuorumMember, RestartPolicy, ShareSet,
+	},
+	genesis::{GenesisMemberOutput, GenesisOutput, RecoveredPermutation},
+};
+use crate::protocol::{SCHEMA_HASH, VERSIONED_MAGIC};
+
+fn member(alias: &str, key: u8) -> QuorumMember {
+	QuorumMember { alias: alias.to_string(), pub_key: vec![key; 65] }
+}
+
+fn manifest() -> Manifest {
+	Manifest {
+		namespace: Namespace {
+			name: "golden".to_string(),
+			nonce: 7,
+			quorum_key: vec![1; 65],
+		},
+		pivot: PivotConfig {
+			hash: [2; 32],
+			restart: RestartPolicy::Always,
+			args: vec!["--port".to_string(), "3000".to_string()],
+			app_config: vec![3; 4],
+			egress: vec![EgressEndpoint {
+				host: "api.example.com".to_string(),
+				port: 443,
+				tls_pins: vec![[4; 32]],
+			}],
+		},
+		manifest_set: ManifestSet {
+			threshold: 2,
+			members: vec![member("alice", 5), member("bob", 6)],
+		},
+		share_set: ShareSet {
+			threshold: 1,
+			members: vec![member("carol", 7)],
+			share_commitments: vec![[8; 33]],
+		},
+		enclave: NitroConfig {
+			pcr0: vec![9; 48],
+			pcr1: vec![10; 48],
+			pcr2: vec![11; 48],
+			pcr3: vec![12; 48],
+			aws_root_certificate: vec![13; 4],
+			qos_commit: "0123abcd".to_string(),
+		},
+		patch_set: PatchSet {
+			threshold: 1,
+			members: vec![MemberPubKey { pub_key: vec![14; 65] }],
+		},
+		crypto: CryptoConfig {
+			approved_only: true,
+			algorithms: vec![Algorithm::Sha256, Algorithm::EcdsaP256Sha256],
+		},
+	}
+}
+
+fn manifest_v0() -> Manifest {
+	let mut manifest = manifest();
+	manifest.pivot.app_config = vec![];
+	manifest.pivot.egress = vec![];
+	manifest.share_set.share_commitments = vec![];
+	manifest.crypto = CryptoConfig::new(false);
+	manifest
+}
+
+fn approval() -> Approval {
+	Approval { signature: vec![15; 64], member: member("alice", 5) }
+}
+
+fn manifest_envelope() -> ManifestEnvelope {
+	ManifestEnvelope {
+		manifest: manifest(),
+		manifest_set_approvals: vec![approval()],
+		share_set_approvals: vec![Approval {
+			signature: vec![16; 64],
+			member: member("carol", 7),
+		}],
+	}
+}
+
+fn genesis_output() -> GenesisOutput {
+	GenesisOutput {
+		quorum_key: vec![17; 65],
+		member_outputs: vec![GenesisMemberOutput {
+			share_set_member: member("carol", 7),
+			encrypted_quorum_key_share: vec![18; 8],
+			share_hash: [19; 64],
+		}],
+		recovery_permutations: vec![RecoveredPermutation::new(vec![(
+			member("dave", 20),
+			vec![21; 8],
+		)])],
+		threshold: 1,
+		dr_key_wrapped_quorum_key: Some(vec![22; 8]),
+		quorum_key_hash: [23; 64],
+		test_message_ciphertext: vec![24; 8],
+		test_message_signature: vec![25; 64],
+		test_message: b"golden".to_vec(),
+		share_commitments: vec![[26; 33]],
+	}
+}
+
+fn genesis_output_v0() -> GenesisOutput {
+	GenesisOutput { share_commitments: vec![], ..genesis_output() }
+}
+
+/// The golden encodings, by the name of their file in `static/golden`.
+fn encodings() -> Vec<(&'static str, Vec<u8>)> {
+	vec![
+		("manifest.hex", borsh::to_vec(&manifest()).unwrap()),
+		("manifest_envelope.hex", borsh::to_vec(&manifest_envelope()).unwrap()),
+		("approval.hex", borsh::to_vec(&approval()).unwrap()),
+		("genesis_output.hex", borsh::to_vec(&genesis_output()).unwrap()),
+		("manifest_v0.hex", borsh::to_vec(&manifest_v0()).unwrap()),
+		("genesis_output_v0.hex", borsh::to_vec(&genesis_output_v0()).unwrap()),
+	]
+}
+
+#[test]
+fn encodings_match_the_golden_vectors() {
+	let dir = concat!(
+		env!("CARGO_MANIFEST_DIR"),
+		"/src/protocol/services/static/golden"
+	);
+	let update = env::var("UPDATE_GOLDEN").is_ok();
+
+	for (file, encoded) in encodings() {
+		let path = format!("{dir}/{file}");
+		let encoded = qos_hex::encode(&encoded);
+		if update && !file.ends_with("_v0.hex") {
+			fs::write(&path, format!("{encoded}\n")).unwrap();
+		}
+
+		let golden = fs::read_to_string(&path).unwrap();
+		assert_eq!(
+			encoded,
+			golden.trim(),
+			"the borsh encoding of {file} changed, breaking existing approvals"
+		);
+	}
+}
+
+#[test]
+fn golden_vectors_decode() {
+	let encoded = |name: &str| {
+		encodings()
+			.into_iter()
+			.find_map(|(file, encoded)| (file == name).then_some(encoded))
+			.unwrap()
+	};
+	let manifest_bytes = encoded("manifest.hex");
+	let manifest_v0_bytes = encoded("manifest_v0.hex");
+	let genesis_bytes = encoded("genesis_output.hex");
+	let genesis_v0_bytes = encoded("genesis_output_v0.hex");
+
+	assert_eq!(
+		borsh::from_slice::<Manifest>(&manifest_bytes).unwrap(),
+		manifest()
+	);
+	assert_eq!(
+		borsh::from_slice::<ManifestEnvelope>(&encoded(
+			"manifest_envelope.hex"
+		))
+		.unwrap(),
+		manifest_envelope()
+	);
+	assert_eq!(
+		borsh::from_slice::<Approval>(&encoded("approval.hex")).unwrap(),
+		approval()
+	);
+	assert_eq!(
+		borsh::from_slice::<GenesisOutput>(&genesis_bytes).unwrap(),
+		genesis_output()
+	);
+	assert_eq!(
+		borsh::from_slice::<Manifest>(&manifest_v0_bytes).unwrap(),
+		manifest_v0()
+	);
+	assert_eq!(
+		borsh::from_slice::<GenesisOutput>(&genesis_v0_bytes).unwrap(),
+		genesis_output_v0()
+	);
+	assert!(manifest_bytes.starts_with(&VERSIONED_MAGIC));
+	assert!(!manifest_v0_bytes.starts_with(&VERSIONED_MAGIC));
+	assert!(genesis_bytes.starts_with(&VERSIONED_MAGIC));
+	assert!(!genesis_v0_bytes.starts_with(&VERSIONED_MAGIC));
+}
+
+#[test]
+fn schema_hash_covers_the_golden_vectors() {
+	let all: Vec<u8> =
+		encodings().into_iter().flat_map(|(_, encoded)| encoded).collect();
+	let hash = qos_hex::encode(&sha_256(&all));
+
+	assert_eq!(
+		hash, SCHEMA_HASH,
+		"the borsh encodings changed, update SCHEMA_HASH to {hash} if on purpose"
+	);
+}
@@ -2,15 +2,20 @@
 
 use aws_nitro_enclaves_nsm_api::api::AttestationDoc;
 use borsh::{BorshDeserialize, BorshSerialize};
+use qos_crypto::ct_eq;
 use qos_nsm::{
-	nitro::{attestation_doc_from_der, cert_from_pem, AWS_ROOT_CERT_PEM},
+	nitro::{attestation_doc_from_der, aws_root_cert},
 	types::NsmResponse,
 };
-use qos_p256::{P256Pair, P256Public};
-
-use crate::protocol::{
-	services::boot::{put_manifest_and_pivot, ManifestEnvelope},
-	ProtocolError, ProtocolState, QosHash,
+use qos_p256::{P256Pair, P256Public, MASTER_SEED_LEN};
+use zeroize::Zeroizing;
+
+use crate::{
+	protocol::{
+		services::boot::{put_manifest_and_pivot, ManifestEnvelope},
+		ProtocolError, ProtocolState, QosHash,
+	},
+	time::TimeSource,
 };
 
 /// An encrypted quorum key along with a signature over the encrypted payload
@@ -42,10 +47,12 @@ pub(in crate::protocol) fn inject_key(
 	// Key.
 	let quorum_master_seed = {
 		let ephemeral_pair = state.handles.get_ephemeral_key()?;
-		let bytes = ephemeral_pair.decrypt(&encrypted_quorum_key)?;
-		bytes
-			.try_into()
-			.map_err(|_| ProtocolError::EncryptedQuorumKeyInvalidLen)?
+		let bytes =
+			Zeroizing::new(ephemeral_pair.decrypt(&encrypted_quorum_key)?);
+		Zeroizing::new(
+			<[u8; MASTER_SEED_LEN]>::try_from(bytes.as_slice())
+				.map_err(|_| ProtocolError::EncryptedQuorumKeyInvalidLen)?,
+		)
 	};
 
 	// 3. Check that the decrypted Quorum Key public key matches the one
@@ -81,7 +88,7 @@ pub(in crate::protocol) fn export_key(
 	// NSM module and the document's timestamp was recent.
 	let attestation_doc = verify_and_extract_attestation_doc_from_der(
 		cose_sign1_attestation_document,
-		&*state.attestor,
+		&*state.time,
 	)?;
 
 	export_key_internal(state, new_manifest_envelope, &attestation_doc)
@@ -147,9 +154,10 @@ fn validate_manifest(
 	// 3. Check that the Quorum Key of the Local Manifest matches the Quorum Key
 	// of the New Manifest. This ensures the request is for the correct Quorum
 	// Key.
-	if old_manifest_envelope.manifest.namespace.quorum_key
-		!= new_manifest_envelope.manifest.namespace.quorum_key
-	{
+	if !ct_eq(
+		&old_manifest_envelope.manifest.namespace.quorum_key,
+		&new_manifest_envelope.manifest.namespace.quorum_key,
+	) {
 		return Err(ProtocolError::DifferentQuorumKey);
 	}
 
@@ -195,9 +203,10 @@ fn validate_manifest(
 		return Err(ProtocolError::LowNonce);
 	} else if old_manifest_envelope.manifest.namespace.nonce
 		== new_manifest_envelope.manifest.namespace.nonce
-		&& old_manifest_envelope.manifest.qos_hash()
-			!= new_manifest_envelope.manifest.qos_hash()
-	{
+		&& !ct_eq(
+			&old_manifest_envelope.manifest.qos_hash(),
+			&new_manifest_envelope.manifest.qos_hash(),
+		) {
 		return Err(ProtocolError::DifferentManifest);
 	}
 
@@ -229,9 +238,10 @@ fn validate_manifest(
 	// is controlled by the operator, not an enclave that some malicious entity
 	// runs that otherwise configured identically to one of the operator's
 	// enclaves.
-	if old_manifest_envelope.manifest.enclave.pcr3
-		!= new_manifest_envelope.manifest.enclave.pcr3
-	{
+	if !ct_eq(
+		&old_manifest_envelope.manifest.enclave.pcr3,
+		&new_manifest_envelope.manifest.enclave.pcr3,
+	) {
 		return Err(ProtocolError::DifferentPcr3);
 	}
 
@@ -240,13 +250,10 @@ fn validate_manifest(
 
 fn verify_and_extract_attestation_doc_from_der(
 	cose_sign1_der: &[u8],
-	nsm: &dyn qos_nsm::NsmProvider,
+	time: &dyn TimeSource,
 ) -> Result<AttestationDoc, ProtocolError> {
-	let current_time_milliseconds = nsm.timestamp_ms()?;
-	let current_time_seconds = current_time_milliseconds / 1_000;
-	let der_cert = cert_from_pem(AWS_ROOT_CERT_PEM)
-		.expect("hardcoded cert is valid. qed.");
-	attestation_doc_from_der(cose_sign1_der, &der_cert, current_time_seconds)
+	let der_cert = aws_root_cert()?;
+	attestation_doc_from_der(cose_sign1_der, &der_cert, time.now_secs()?)
 		.map_err(Into::into)
 }
 
@@ -339,9 +346,15 @@ mod test {
 				hash: sha_256(&pivot),
 				restart: RestartPolicy::Always,
 				args: vec![],
+				app_config: vec![],
+				egress: vec![],
 			},
 			manifest_set: ManifestSet { threshold: 2, members: quorum_members },
-			share_set: ShareSet { threshold: 2, members: vec![] },
+			share_set: ShareSet {
+				threshold: 2,
+				members: vec![],
+				share_commitments: vec![],
+			},
 			..Default::default()
 		};
 
@@ -411,6 +424,7 @@ mod test {
 				"qorum".to_string(),
 				manifest_file.deref().to_string(),
 				pivot_file.deref().to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -455,6 +469,7 @@ mod test {
 				"qorum".to_string(),
 				manifest_file.deref().to_string(),
 				pivot_file.deref().to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -497,6 +512,7 @@ mod test {
 				"qorum".to_string(),
 				manifest_file.deref().to_string(),
 				pivot_file.deref().to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -538,6 +554,7 @@ mod test {
 				"quorum".to_string(),
 				manifest_file.deref().to_string(),
 				pivot_file.deref().to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -595,6 +612,7 @@ mod test {
 				"quorum".to_string(),
 				manifest_file.deref().to_string(),
 				pivot_file.deref().to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -1037,6 +1055,41 @@ mod test {
 			);
 		}
 	}
+	mod verify_attestation_doc {
+		use std::time::Duration;
+
+		use qos_nsm::mock::{
+			MOCK_NSM_ATTESTATION_DOCUMENT, MOCK_SECONDS_SINCE_EPOCH,
+		};
+
+		use super::*;
+		use crate::{
+			protocol::services::key::verify_and_extract_attestation_doc_from_der,
+			time::MockClock,
+		};
+
+		#[test]
+		fn checks_validity_against_the_clock() {
+			let clock =
+				MockClock::new(Duration::from_secs(MOCK_SECONDS_SINCE_EPOCH));
+			assert!(verify_and_extract_attestation_doc_from_der(
+				MOCK_NSM_ATTESTATION_DOCUMENT,
+				&clock
+			)
+			.is_ok());
+
+			// The certificates of the document have expired a day later
+			clock.advance(Duration::from_secs(24 * 60 * 60));
+			assert!(matches!(
+				verify_and_extract_attestation_doc_from_der(
+					MOCK_NSM_ATTESTATION_DOCUMENT,
+					&clock
+				),
+				Err(ProtocolError::QosAttestError(_))
+			));
+		}
+	}
+
 	mod export_key_inner {
 		use super::*;
 		use crate::protocol::services::key::EncryptedQuorumKey;
@@ -1072,6 +1125,7 @@ mod test {
 				quorum_file.deref().to_string(),
 				manifest_file.deref().to_string(),
 				"pivot".to_string(),
+				"pivot_info".to_string(),
 			);
 
 			let mut protocol_state = ProtocolState::new(
@@ -1136,6 +1190,7 @@ mod test {
 				quorum_file.deref().to_string(),
 				manifest_file.deref().to_string(),
 				"pivot".to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut protocol_state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -1189,6 +1244,7 @@ mod test {
 				quorum_file.deref().to_string(),
 				manifest_file.deref().to_string(),
 				"pivot".to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut protocol_state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -1244,6 +1300,7 @@ mod test {
 				quorum_file.deref().to_string(),
 				manifest_file.deref().to_string(),
 				"pivot".to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut protocol_state = ProtocolState::new(
 				Box::new(MockNsm),
@@ -1299,6 +1356,7 @@ mod test {
 				quorum_file.deref().to_string(),
 				manifest_file.deref().to_string(),
 				"pivot".to_string(),
+				"pivot_info".to_string(),
 			);
 			let mut protocol_state = ProtocolState::new(
 				Box::new(MockNsm),
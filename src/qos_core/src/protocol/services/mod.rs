@@ -3,5 +3,8 @@
 pub(crate) mod attestation;
 pub mod boot;
 pub mod genesis;
+#[cfg(test)]
+mod golden;
 pub mod key;
 pub mod provision;
+pub mod self_test;
@@ -1,30 +1,162 @@
 //! Quorum Key provisioning logic and types.
+use std::mem;
+
+use qos_crypto::{ct_eq, sha_256, sha_512, shamir};
+use qos_nsm::types::NsmResponse;
+use qos_p256::{P256Pair, P256Public};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
 use crate::protocol::{
-	services::boot::Approval, ProtocolError, ProtocolState, QosHash,
+	services::{
+		attestation,
+		boot::{Approval, Manifest, QuorumMember},
+	},
+	Hash256, ProtocolError, ProtocolState, QosHash,
 };
 
-type Secret = Vec<u8>;
+type Secret = Zeroizing<Vec<u8>>;
 type Share = Vec<u8>;
 type Shares = Vec<Share>;
 
-/// Shamir Secret builder.
+/// Magic bytes at the start of a serialized [`VersionedShare`]. Shares that do
+/// not start with them are raw Shamir shares from before shares were
+/// versioned.
+pub const SHARE_MAGIC: [u8; 4] = *b"QOSS";
+/// Version of [`VersionedShare`] made by this code.
+pub const SHARE_VERSION: u8 = 1;
+
+/// A Shamir share of a quorum key with a header saying which ceremony it is
+/// from, so shares of different quorum keys are not silently combined into
+/// the wrong secret.
+#[derive(
+	Debug, PartialEq, Eq, Clone, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct VersionedShare {
+	/// Always [`SHARE_MAGIC`].
+	pub magic: [u8; 4],
+	/// Version of the format, [`SHARE_VERSION`].
+	pub version: u8,
+	/// Number of shares needed to reconstruct the quorum key.
+	pub threshold: u32,
+	/// Index of the share, which is also its Shamir identifier.
+	pub index: u8,
+	/// Sha256 of the quorum public key the share is of.
+	pub quorum_key_fingerprint: Hash256,
+	/// The raw Shamir share.
+	pub share: Vec<u8>,
+	/// Sha256 of all the other fields.
+	pub checksum: Hash256,
+}
+
+impl VersionedShare {
+	/// Add a header to `share` of the quorum public key `quorum_key`.
+	#[must_use]
+	pub fn new(share: Vec<u8>, threshold: u32, quorum_key: &[u8]) -> Self {
+		let mut versioned = Self {
+			magic: SHARE_MAGIC,
+			version: SHARE_VERSION,
+			threshold,
+			index: shamir::share_identifier(&share).unwrap_or_default(),
+			quorum_key_fingerprint: sha_256(quorum_key),
+			share,
+			checksum: [0; 32],
+		};
+		versioned.checksum = versioned.compute_checksum();
+
+		versioned
+	}
+
+	/// Serialize with borsh.
+	///
+	/// # Panics
+	///
+	/// Panics if borsh serialization fails, which should never happen.
+	#[must_use]
+	pub fn to_bytes(&self) -> Vec<u8> {
+		borsh::to_vec(self).expect("VersionedShare implements borsh. qed.")
+	}
+
+	fn compute_checksum(&self) -> Hash256 {
+		let fields = (
+			self.magic,
+			self.version,
+			self.threshold,
+			self.index,
+			self.quorum_key_fingerprint,
+			&self.share,
+		);
+		sha_256(&borsh::to_vec(&fields).expect("borsh works with tuples. qed."))
+	}
+}
+
+/// Get the raw Shamir share out of a decrypted share, checking that it is a
+/// share of `quorum_key` with the given `threshold`.
+///
+/// Raw shares from before shares were versioned are returned as is.
+pub fn open_share(
+	bytes: &[u8],
+	quorum_key: &[u8],
+	threshold: u32,
+) -> Result<Share, ProtocolError> {
+	if !bytes.starts_with(&SHARE_MAGIC) {
+		return Ok(bytes.to_vec());
+	}
+	match bytes.get(SHARE_MAGIC.len()) {
+		Some(&SHARE_VERSION) => {}
+		Some(version) => {
+			return Err(ProtocolError::UnsupportedShareVersion(*version))
+		}
+		None => return Err(ProtocolError::InvalidShare),
+	}
+
+	let versioned: VersionedShare =
+		borsh::from_slice(bytes).map_err(|_| ProtocolError::InvalidShare)?;
+	if !ct_eq(&versioned.checksum, &versioned.compute_checksum()) {
+		return Err(ProtocolError::ShareChecksumMismatch);
+	}
+	if shamir::share_identifier(&versioned.share) != Some(versioned.index) {
+		return Err(ProtocolError::InvalidShare);
+	}
+	if !ct_eq(&versioned.quorum_key_fingerprint, &sha_256(quorum_key))
+		|| versioned.threshold != threshold
+	{
+		return Err(ProtocolError::ShareCeremonyMismatch);
+	}
+
+	Ok(versioned.share)
+}
+
+/// Shamir Secret builder. The shares are wiped from memory when they are
+/// cleared or dropped.
+#[derive(Zeroize, ZeroizeOnDrop)]
 pub(crate) struct SecretBuilder {
 	shares: Shares,
+	/// The shares the secret was reconstructed from, kept so shares can be
+	/// re-encrypted with [`rotate_share`].
+	reconstructed_from: Shares,
 }
 
 impl SecretBuilder {
 	/// Create a instance of [`Self`].
 	pub fn new() -> Self {
-		Self { shares: Vec::new() }
+		Self { shares: Vec::new(), reconstructed_from: Vec::new() }
 	}
 
-	/// Add a share to later be used to reconstruct.
+	/// Add a share to later be used to reconstruct. Each share must have a
+	/// different identifier.
 	pub(crate) fn add_share(
 		&mut self,
 		share: Share,
 	) -> Result<(), ProtocolError> {
-		if share.is_empty() {
+		let Some(identifier) = shamir::share_identifier(&share) else {
 			return Err(ProtocolError::InvalidShare);
+		};
+		if self
+			.shares
+			.iter()
+			.any(|s| shamir::share_identifier(s) == Some(identifier))
+		{
+			return Err(ProtocolError::DuplicateShareIndex(identifier));
 		}
 
 		self.shares.push(share);
@@ -49,8 +181,94 @@ impl SecretBuilder {
 	}
 
 	pub(crate) fn clear(&mut self) {
-		self.shares = vec![];
+		self.shares.zeroize();
+	}
+
+	/// Keep the shares the secret was reconstructed from and start over.
+	pub(crate) fn finish(&mut self) {
+		self.reconstructed_from.zeroize();
+		self.reconstructed_from = mem::take(&mut self.shares);
+	}
+
+	/// Find the share with the given hash among all the shares of the
+	/// reconstructed secret.
+	pub(crate) fn reconstructed_share(
+		&self,
+		share_hash: &[u8; 64],
+	) -> Result<Share, ProtocolError> {
+		if self.reconstructed_from.is_empty() {
+			return Err(ProtocolError::NoReconstructedShares);
+		}
+
+		(1..=u8::MAX)
+			.filter_map(|identifier| {
+				qos_crypto::shamir::share_at(
+					&self.reconstructed_from,
+					identifier,
+				)
+				.ok()
+			})
+			.find(|share| ct_eq(&sha_512(share), share_hash))
+			.ok_or(ProtocolError::ShareNotFound)
+	}
+}
+
+/// A share set member's request to have their share encrypted to a new
+/// personal key.
+#[derive(
+	Debug, PartialEq, Eq, Clone, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct ShareRotation {
+	/// Hash of the manifest the enclave was provisioned with.
+	pub manifest_hash: Hash256,
+	/// The share set member, with their old personal key.
+	pub member: QuorumMember,
+	/// Sha512 hash of the member's share, as recorded in the genesis output.
+	pub share_hash: [u8; 64],
+	/// The new personal public key of the member.
+	pub new_pub_key: Vec<u8>,
+}
+
+/// A share set member's request to restart provisioning.
+///
+/// The reset names the Ephemeral Key it discards, so an approval of it
+/// cannot be replayed once the key was rotated.
+#[derive(
+	Debug, PartialEq, Eq, Clone, borsh::BorshSerialize, borsh::BorshDeserialize,
+)]
+pub struct ProvisionReset {
+	/// Hash of the manifest being provisioned.
+	pub manifest_hash: Hash256,
+	/// Public key of the Ephemeral Key to discard.
+	pub ephemeral_key: Vec<u8>,
+}
+
+/// Decrypt a posted share and check it is a share of the quorum key of
+/// `manifest` that matches its commitment, if the share set has commitments.
+fn open_posted_share(
+	encrypted_share: &[u8],
+	ephemeral_key: &P256Pair,
+	manifest: &Manifest,
+) -> Result<Share, ProtocolError> {
+	let share = Zeroizing::new(
+		ephemeral_key
+			.decrypt(encrypted_share)
+			.map_err(|_| ProtocolError::DecryptionFailed)?,
+	);
+	let share = open_share(
+		&share,
+		&manifest.namespace.quorum_key,
+		manifest.share_set.threshold,
+	)?;
+
+	let commitments = &manifest.share_set.share_commitments;
+	if !commitments.is_empty()
+		&& shamir::share_verify(&share, commitments).is_err()
+	{
+		return Err(ProtocolError::ShareCommitmentMismatch);
 	}
+
+	Ok(share)
 }
 
 pub(in crate::protocol) fn provision(
@@ -70,20 +288,26 @@ pub(in crate::protocol) fn provision(
 		return Err(ProtocolError::NotShareSetMember);
 	}
 
+	// Check the share before using it, so a bad share is pinned on the member
+	// who posted it rather than failing reconstruction
+	let ephemeral_key = state.handles.get_ephemeral_key()?;
+	open_posted_share(
+		encrypted_share,
+		&ephemeral_key,
+		&manifest_envelope.manifest,
+	)
+	.and_then(|share| state.provisioner.add_share(share))
+	.map_err(|reason| ProtocolError::RejectedShare {
+		member: approval.member.clone(),
+		reason: Box::new(reason),
+	})?;
+
 	// Record the share set approval
 	state.handles.mutate_manifest_envelope(|mut envelope| {
 		envelope.share_set_approvals.push(approval);
 		envelope
 	})?;
 
-	let ephemeral_key = state.handles.get_ephemeral_key()?;
-
-	let share = ephemeral_key
-		.decrypt(encrypted_share)
-		.map_err(|_| ProtocolError::DecryptionFailed)?;
-
-	state.provisioner.add_share(share)?;
-
 	let quorum_threshold =
 		manifest_envelope.manifest.share_set.threshold as usize;
 	if state.provisioner.count() < quorum_threshold {
@@ -92,16 +316,22 @@ pub(in crate::protocol) fn provision(
 	}
 
 	let master_seed = state.provisioner.build()?;
-	state.provisioner.clear();
-
-	let master_seed: [u8; qos_p256::MASTER_SEED_LEN] =
-		master_seed
-			.try_into()
-			.map_err(|_| ProtocolError::IncorrectSecretLen)?;
+	state.provisioner.finish();
+
+	let master_seed: Zeroizing<[u8; qos_p256::MASTER_SEED_LEN]> =
+		Zeroizing::new(
+			master_seed
+				.as_slice()
+				.try_into()
+				.map_err(|_| ProtocolError::IncorrectSecretLen)?,
+		);
 	let pair = qos_p256::P256Pair::from_master_seed(&master_seed)?;
 	let public_key_bytes = pair.public_key().to_bytes();
 
-	if public_key_bytes != manifest_envelope.manifest.namespace.quorum_key {
+	if !ct_eq(
+		&public_key_bytes,
+		&manifest_envelope.manifest.namespace.quorum_key,
+	) {
 		// We did not construct the intended key
 		return Err(ProtocolError::ReconstructionErrorIncorrectPubKey);
 	}
@@ -114,14 +344,101 @@ pub(in crate::protocol) fn provision(
 	Ok(true)
 }
 
+/// Encrypt the share described by `rotation` to the new personal key in it.
+///
+/// `signature` must be a signature over the rotation by the new personal key
+/// and a threshold of the share set must have approved the rotation. Returns
+/// the encrypted share.
+pub(in crate::protocol) fn rotate_share(
+	rotation: &ShareRotation,
+	signature: &[u8],
+	approvals: &[Approval],
+	state: &ProtocolState,
+) -> Result<Vec<u8>, ProtocolError> {
+	let manifest = state.handles.get_manifest_envelope()?.manifest;
+	if !ct_eq(&rotation.manifest_hash, &manifest.qos_hash()) {
+		return Err(ProtocolError::ShareRotationManifestMismatch);
+	}
+	if !manifest.share_set.members.contains(&rotation.member) {
+		return Err(ProtocolError::NotShareSetMember);
+	}
+
+	let rotation_hash = rotation.qos_hash();
+	let new_pub_key = P256Public::from_bytes(&rotation.new_pub_key)?;
+	new_pub_key
+		.verify(&rotation_hash, signature)
+		.map_err(|_| ProtocolError::InvalidShareRotationSignature)?;
+
+	let mut approvers = Vec::with_capacity(approvals.len());
+	for approval in approvals {
+		if !manifest.share_set.members.contains(&approval.member) {
+			return Err(ProtocolError::NotShareSetMember);
+		}
+		approval.verify(&rotation_hash)?;
+		if approvers.contains(&&approval.member) {
+			return Err(ProtocolError::DuplicateApproval);
+		}
+		approvers.push(&approval.member);
+	}
+	if approvers.len() < manifest.share_set.threshold as usize {
+		return Err(ProtocolError::NotEnoughApprovals);
+	}
+
+	let share = state.provisioner.reconstructed_share(&rotation.share_hash)?;
+	let share = VersionedShare::new(
+		share,
+		manifest.share_set.threshold,
+		&manifest.namespace.quorum_key,
+	);
+
+	Ok(new_pub_key.encrypt(&share.to_bytes())?)
+}
+
+/// Discard the shares posted so far and rotate the Ephemeral Key. Returns an
+/// attestation document for the new Ephemeral Key, which share holders must
+/// encrypt their shares to.
+///
+/// `approval` must be a signature over `reset` by a share set member, and
+/// `reset` must name the manifest and the current Ephemeral Key.
+pub(in crate::protocol) fn reset(
+	reset: &ProvisionReset,
+	approval: &Approval,
+	state: &mut ProtocolState,
+) -> Result<NsmResponse, ProtocolError> {
+	let manifest = state.handles.get_manifest_envelope()?.manifest;
+	if !manifest.share_set.members.contains(&approval.member) {
+		return Err(ProtocolError::NotShareSetMember);
+	}
+	approval.verify(&reset.qos_hash())?;
+	if !ct_eq(&reset.manifest_hash, &manifest.qos_hash()) {
+		return Err(ProtocolError::ProvisionResetManifestMismatch);
+	}
+	let ephemeral_key = state.handles.get_ephemeral_key()?;
+	if !ct_eq(&reset.ephemeral_key, &ephemeral_key.public_key().to_bytes()) {
+		return Err(ProtocolError::StaleProvisionReset);
+	}
+
+	state.provisioner.clear();
+	state.handles.mutate_manifest_envelope(|mut envelope| {
+		envelope.share_set_approvals.clear();
+		envelope
+	})?;
+
+	let ephemeral_key = P256Pair::generate()?;
+	state.handles.rotate_ephemeral_key(&ephemeral_key)?;
+
+	attestation::live_attestation_doc(state)
+}
+
 #[cfg(test)]
 mod test {
 	use std::path::Path;
 
-	use qos_crypto::{sha_256, shamir::shares_generate};
+	use qos_crypto::{sha_256, sha_512, shamir::shares_generate};
 	use qos_nsm::mock::MockNsm;
 	use qos_p256::P256Pair;
 	use qos_test_primitives::PathWrapper;
+	use zeroize::{Zeroize, ZeroizeOnDrop};
 
 	use crate::{
 		handles::Handles,
@@ -129,11 +446,14 @@ mod test {
 		protocol::{
 			services::{
 				boot::{
-					Approval, Manifest, ManifestEnvelope, ManifestSet,
-					Namespace, NitroConfig, PatchSet, PivotConfig,
+					Approval, CryptoConfig, Manifest, ManifestEnvelope,
+					ManifestSet, Namespace, NitroConfig, PatchSet, PivotConfig,
 					QuorumMember, RestartPolicy, ShareSet,
 				},
-				provision::provision,
+				provision::{
+					open_share, provision, reset, rotate_share, ProvisionReset,
+					SecretBuilder, ShareRotation, VersionedShare,
+				},
 			},
 			ProtocolError, ProtocolPhase, ProtocolState, QosHash,
 		},
@@ -145,6 +465,7 @@ mod test {
 		threshold: usize,
 		state: ProtocolState,
 		approvals: Vec<Approval>,
+		member_pairs: Vec<P256Pair>,
 	}
 
 	fn setup(eph_file: &str, quorum_file: &str, manifest_file: &str) -> Setup {
@@ -153,6 +474,7 @@ mod test {
 			quorum_file.to_string(),
 			manifest_file.to_string(),
 			"pivot".to_string(),
+			"pivot_info".to_string(),
 		);
 		// 1) Create and write eph key
 		let eph_pair = P256Pair::generate().unwrap();
@@ -193,6 +515,8 @@ mod test {
 				hash: sha_256(pivot),
 				restart: RestartPolicy::Always,
 				args: vec![],
+				app_config: vec![],
+				egress: vec![],
 			},
 			manifest_set: ManifestSet {
 				threshold: threshold.try_into().unwrap(),
@@ -201,15 +525,17 @@ mod test {
 			share_set: ShareSet {
 				threshold: threshold.try_into().unwrap(),
 				members: members.clone().into_iter().map(|(m, _)| m).collect(),
+				share_commitments: vec![],
 			},
 			patch_set: PatchSet::default(),
+			crypto: CryptoConfig::default(),
 		};
 
 		let approvals: Vec<_> = members
-			.into_iter()
+			.iter()
 			.map(|(member, pair)| {
 				let approval = Approval {
-					member,
+					member: member.clone(),
 					signature: pair.sign(&manifest.qos_hash()).unwrap(),
 				};
 
@@ -235,7 +561,26 @@ mod test {
 		);
 		state.transition(ProtocolPhase::WaitingForQuorumShards).unwrap();
 
-		Setup { quorum_pair, eph_pair, threshold, state, approvals }
+		let member_pairs = members.into_iter().map(|(_, pair)| pair).collect();
+
+		Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			state,
+			approvals,
+			member_pairs,
+		}
+	}
+
+	fn rejected(
+		approval: &Approval,
+		reason: ProtocolError,
+	) -> Result<bool, ProtocolError> {
+		Err(ProtocolError::RejectedShare {
+			member: approval.member.clone(),
+			reason: Box::new(reason),
+		})
 	}
 
 	#[test]
@@ -244,14 +589,21 @@ mod test {
 		let eph_file: PathWrapper = "./provision_works.eph.key".into();
 		let manifest_file: PathWrapper = "./provision_works.manifest".into();
 
-		let Setup { quorum_pair, eph_pair, threshold, mut state, approvals } =
-			setup(&eph_file, &quorum_file, &manifest_file);
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			..
+		} = setup(&eph_file, &quorum_file, &manifest_file);
 
 		// 4) Create shards and encrypt them to eph key
 		let quorum_key = quorum_pair.to_master_seed();
 		let encrypted_shares: Vec<_> =
 			shares_generate(quorum_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -275,7 +627,7 @@ mod test {
 		assert_eq!(provision(share, approval, &mut state), Ok(true));
 		let quorum_key = std::fs::read(&*quorum_file).unwrap();
 
-		assert_eq!(quorum_key, quorum_pair.to_master_seed_hex());
+		assert_eq!(quorum_key, *quorum_pair.to_master_seed_hex());
 
 		// Make sure the EK is deleted
 		assert!(!Path::new(&*eph_file).exists());
@@ -310,6 +662,7 @@ mod test {
 		let encrypted_shares: Vec<_> =
 			shares_generate(&random_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -338,6 +691,176 @@ mod test {
 		assert_eq!(state.get_phase(), ProtocolPhase::WaitingForQuorumShards);
 	}
 
+	#[test]
+	fn provision_checks_versioned_shares() {
+		let eph_file: PathWrapper =
+			"./provision_checks_versioned_shares.eph.key".into();
+		let quorum_file: PathWrapper =
+			"./provision_checks_versioned_shares.quorum.key".into();
+		let manifest_file: PathWrapper =
+			"./provision_checks_versioned_shares.manifest".into();
+
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			..
+		} = setup(&eph_file, &quorum_file, &manifest_file);
+		let quorum_key = quorum_pair.public_key().to_bytes();
+		let (shares, _) =
+			shares_generate(quorum_pair.to_master_seed(), 4, threshold)
+				.unwrap();
+		let versioned = |share: &[u8], threshold: usize, quorum_key: &[u8]| {
+			VersionedShare::new(
+				share.to_vec(),
+				threshold.try_into().unwrap(),
+				quorum_key,
+			)
+		};
+		let encrypt = |versioned: &VersionedShare| {
+			eph_pair.public_key().encrypt(&versioned.to_bytes()).unwrap()
+		};
+
+		// A share of another quorum key
+		let other_key = P256Pair::generate().unwrap().public_key().to_bytes();
+		let share = encrypt(&versioned(&shares[0], threshold, &other_key));
+		assert_eq!(
+			provision(&share, approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::ShareCeremonyMismatch)
+		);
+
+		// A share from a ceremony with another threshold
+		let share = encrypt(&versioned(&shares[0], threshold - 1, &quorum_key));
+		assert_eq!(
+			provision(&share, approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::ShareCeremonyMismatch)
+		);
+
+		let mut corrupted = versioned(&shares[0], threshold, &quorum_key);
+		corrupted.share[1] ^= 1;
+		assert_eq!(
+			provision(&encrypt(&corrupted), approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::ShareChecksumMismatch)
+		);
+
+		let mut future = versioned(&shares[0], threshold, &quorum_key);
+		future.version = 2;
+		assert_eq!(
+			provision(&encrypt(&future), approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::UnsupportedShareVersion(2))
+		);
+		assert_eq!(state.provisioner.count(), 0);
+
+		// Versioned and raw shares can be mixed
+		let share = encrypt(&versioned(&shares[0], threshold, &quorum_key));
+		assert_eq!(
+			provision(&share, approvals[0].clone(), &mut state),
+			Ok(false)
+		);
+		let share = eph_pair.public_key().encrypt(&shares[1]).unwrap();
+		assert_eq!(
+			provision(&share, approvals[1].clone(), &mut state),
+			Ok(false)
+		);
+		let share = encrypt(&versioned(&shares[2], threshold, &quorum_key));
+		assert_eq!(
+			provision(&share, approvals[2].clone(), &mut state),
+			Ok(true)
+		);
+
+		let quorum_key = std::fs::read(&*quorum_file).unwrap();
+		assert_eq!(quorum_key, *quorum_pair.to_master_seed_hex());
+	}
+
+	#[test]
+	fn provision_checks_shares_against_commitments() {
+		let eph_file: PathWrapper =
+			"./provision_checks_shares_against_commitments.eph.key".into();
+		let quorum_file: PathWrapper =
+			"./provision_checks_shares_against_commitments.quorum.key".into();
+		let manifest_file: PathWrapper =
+			"./provision_checks_shares_against_commitments.manifest".into();
+
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			member_pairs,
+			..
+		} = setup(&eph_file, &quorum_file, &manifest_file);
+		let (shares, commitments) =
+			shares_generate(quorum_pair.to_master_seed(), 4, threshold)
+				.unwrap();
+		state
+			.handles
+			.mutate_manifest_envelope(|mut envelope| {
+				envelope.manifest.share_set.share_commitments = commitments;
+				envelope
+			})
+			.unwrap();
+		let manifest = state.handles.get_manifest_envelope().unwrap().manifest;
+		let approvals: Vec<_> = manifest
+			.share_set
+			.members
+			.iter()
+			.zip(&member_pairs)
+			.map(|(member, pair)| Approval {
+				member: member.clone(),
+				signature: pair.sign(&manifest.qos_hash()).unwrap(),
+			})
+			.collect();
+		let encrypt =
+			|share: &[u8]| eph_pair.public_key().encrypt(share).unwrap();
+
+		// A share that does not match its commitment
+		let mut corrupted = shares[0].clone();
+		corrupted[10] ^= 1;
+		assert_eq!(
+			provision(&encrypt(&corrupted), approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::ShareCommitmentMismatch)
+		);
+
+		assert_eq!(
+			provision(&encrypt(&shares[0]), approvals[0].clone(), &mut state),
+			Ok(false)
+		);
+
+		// Another member posting a share with the same identifier
+		assert_eq!(
+			provision(&encrypt(&shares[0]), approvals[1].clone(), &mut state),
+			rejected(&approvals[1], ProtocolError::DuplicateShareIndex(1))
+		);
+
+		// Rejected shares are neither kept nor recorded as approvals
+		assert_eq!(state.provisioner.count(), 1);
+		assert_eq!(
+			state
+				.handles
+				.get_manifest_envelope()
+				.unwrap()
+				.share_set_approvals
+				.len(),
+			1
+		);
+		assert_eq!(state.get_phase(), ProtocolPhase::WaitingForQuorumShards);
+
+		for (i, share) in shares[1..threshold].iter().enumerate() {
+			assert_eq!(
+				provision(
+					&encrypt(share),
+					approvals[i + 1].clone(),
+					&mut state
+				),
+				Ok(i + 2 == threshold)
+			);
+		}
+		let quorum_key = std::fs::read(&*quorum_file).unwrap();
+		assert_eq!(quorum_key, *quorum_pair.to_master_seed_hex());
+	}
+
 	#[test]
 	fn provision_rejects_if_a_shard_is_invalid() {
 		let eph_file: PathWrapper =
@@ -346,8 +869,14 @@ mod test {
 			"./provision_rejects_if_a_shard_is_invalid.quorum.key".into();
 		let manifest_file: PathWrapper =
 			"./provision_rejects_if_a_shard_is_invalid.manifest".into();
-		let Setup { quorum_pair, eph_pair, threshold, mut state, approvals } =
-			setup(&eph_file, &quorum_file, &manifest_file);
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			..
+		} = setup(&eph_file, &quorum_file, &manifest_file);
 
 		// 4) Create shards and encrypt them to eph key
 		let quorum_key = quorum_pair.to_master_seed();
@@ -355,6 +884,7 @@ mod test {
 		let encrypted_shares: Vec<_> =
 			shares_generate(quorum_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -371,8 +901,9 @@ mod test {
 			);
 		}
 
-		// 6) Add a bogus shard as the Kth shard
-		let bogus_share = &[69u8; 33];
+		// 6) Add a share of another secret as the Kth shard
+		let bogus_share =
+			&shares_generate(&[69u8; 32], 4, threshold).unwrap().0[threshold];
 		let encrypted_bogus_share =
 			eph_pair.public_key().encrypt(bogus_share).unwrap();
 		let approval = approvals[threshold].clone();
@@ -400,12 +931,14 @@ mod test {
 			threshold,
 			mut state,
 			mut approvals,
+			..
 		} = setup(&eph_file, &quorum_file, &manifest_file);
 
 		let quorum_key = quorum_pair.to_master_seed();
 		let mut encrypted_shares: Vec<_> =
 			shares_generate(quorum_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -437,12 +970,14 @@ mod test {
 			threshold,
 			mut state,
 			mut approvals,
+			..
 		} = setup(&eph_file, &quorum_file, &manifest_file);
 
 		let quorum_key = quorum_pair.to_master_seed();
 		let mut encrypted_shares: Vec<_> =
 			shares_generate(quorum_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -480,12 +1015,14 @@ mod test {
 			threshold,
 			mut state,
 			mut approvals,
+			..
 		} = setup(&eph_file, &quorum_file, &manifest_file);
 
 		let quorum_key = quorum_pair.to_master_seed();
 		let mut encrypted_shares: Vec<_> =
 			shares_generate(quorum_key, 4, threshold)
 				.unwrap()
+				.0
 				.iter()
 				.map(|shard| eph_pair.public_key().encrypt(shard).unwrap())
 				.collect();
@@ -507,4 +1044,353 @@ mod test {
 		assert!(!Path::new(&*quorum_file).exists());
 		assert_eq!(state.get_phase(), ProtocolPhase::WaitingForQuorumShards);
 	}
+
+	#[test]
+	fn reset_discards_shares_and_rotates_ephemeral_key() {
+		let eph_file: PathWrapper =
+			"./reset_discards_shares_and_rotates_ephemeral_key.eph.key".into();
+		let quorum_file: PathWrapper =
+			"./reset_discards_shares_and_rotates_ephemeral_key.quorum.key"
+				.into();
+		let manifest_file: PathWrapper =
+			"./reset_discards_shares_and_rotates_ephemeral_key.manifest".into();
+
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			member_pairs,
+		} = setup(&eph_file, &quorum_file, &manifest_file);
+
+		let quorum_key = quorum_pair.to_master_seed();
+		let (shares, _) = shares_generate(quorum_key, 4, threshold).unwrap();
+
+		// Post all but one share to the original Ephemeral Key
+		for (i, share) in shares[..threshold - 1].iter().enumerate() {
+			let share = eph_pair.public_key().encrypt(share).unwrap();
+			assert_eq!(
+				provision(&share, approvals[i].clone(), &mut state),
+				Ok(false)
+			);
+		}
+
+		let (provision_reset, approval) =
+			approve_reset(&state, &approvals[0], &member_pairs[0]);
+		reset(&provision_reset, &approval, &mut state).unwrap();
+		assert_eq!(state.provisioner.count(), 0);
+		assert!(state
+			.handles
+			.get_manifest_envelope()
+			.unwrap()
+			.share_set_approvals
+			.is_empty());
+		let new_eph_pair = state.handles.get_ephemeral_key().unwrap();
+		assert!(new_eph_pair != eph_pair);
+
+		// The reset named the old Ephemeral Key, so it cannot be replayed
+		assert_eq!(
+			reset(&provision_reset, &approval, &mut state).unwrap_err(),
+			ProtocolError::StaleProvisionReset
+		);
+
+		// Shares encrypted to the old key can no longer be used
+		let share = eph_pair.public_key().encrypt(&shares[0]).unwrap();
+		assert_eq!(
+			provision(&share, approvals[0].clone(), &mut state),
+			rejected(&approvals[0], ProtocolError::DecryptionFailed)
+		);
+
+		// Provisioning starts over with the new key
+		for (i, share) in shares[..threshold].iter().enumerate() {
+			let share = new_eph_pair.public_key().encrypt(share).unwrap();
+			assert_eq!(
+				provision(&share, approvals[i].clone(), &mut state),
+				Ok(i + 1 == threshold)
+			);
+		}
+		assert!(Path::new(&*quorum_file).exists());
+	}
+
+	fn approve_reset(
+		state: &ProtocolState,
+		approval: &Approval,
+		member_pair: &P256Pair,
+	) -> (ProvisionReset, Approval) {
+		let provision_reset = ProvisionReset {
+			manifest_hash: state
+				.handles
+				.get_manifest_envelope()
+				.unwrap()
+				.manifest
+				.qos_hash(),
+			ephemeral_key: state
+				.handles
+				.get_ephemeral_key()
+				.unwrap()
+				.public_key()
+				.to_bytes(),
+		};
+		let approval = Approval {
+			member: approval.member.clone(),
+			signature: member_pair.sign(&provision_reset.qos_hash()).unwrap(),
+		};
+
+		(provision_reset, approval)
+	}
+
+	#[test]
+	fn reset_requires_a_share_set_member_approval() {
+		let eph_file: PathWrapper =
+			"./reset_requires_a_share_set_member_approval.eph.key".into();
+		let quorum_file: PathWrapper =
+			"./reset_requires_a_share_set_member_approval.quorum.key".into();
+		let manifest_file: PathWrapper =
+			"./reset_requires_a_share_set_member_approval.manifest".into();
+
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			member_pairs,
+		} = setup(&eph_file, &quorum_file, &manifest_file);
+		let (shares, _) =
+			shares_generate(quorum_pair.to_master_seed(), 4, threshold)
+				.unwrap();
+		let share = eph_pair.public_key().encrypt(&shares[0]).unwrap();
+		assert_eq!(
+			provision(&share, approvals[0].clone(), &mut state),
+			Ok(false)
+		);
+
+		let (provision_reset, approval) =
+			approve_reset(&state, &approvals[1], &member_pairs[1]);
+		let outsider = P256Pair::generate().unwrap();
+		let rejected_resets = [
+			// Not signed
+			(
+				provision_reset.clone(),
+				Approval { signature: vec![], ..approval.clone() },
+				ProtocolError::CouldNotVerifyApproval,
+			),
+			// Signed by someone else than the member
+			(
+				provision_reset.clone(),
+				Approval {
+					signature: member_pairs[0]
+						.sign(&provision_reset.qos_hash())
+						.unwrap(),
+					..approval.clone()
+				},
+				ProtocolError::CouldNotVerifyApproval,
+			),
+			// Signed by someone outside the share set
+			(
+				provision_reset.clone(),
+				Approval {
+					member: QuorumMember {
+						alias: "outsider".to_string(),
+						pub_key: outsider.public_key().to_bytes(),
+					},
+					signature: outsider
+						.sign(&provision_reset.qos_hash())
+						.unwrap(),
+				},
+				ProtocolError::NotShareSetMember,
+			),
+			// Approval of a different reset
+			(
+				ProvisionReset {
+					manifest_hash: [0; 32],
+					..provision_reset.clone()
+				},
+				approval.clone(),
+				ProtocolError::CouldNotVerifyApproval,
+			),
+		];
+		for (provision_reset, approval, error) in rejected_resets {
+			assert_eq!(
+				reset(&provision_reset, &approval, &mut state).unwrap_err(),
+				error
+			);
+		}
+
+		// Correctly signed, but for another manifest
+		let other_manifest =
+			ProvisionReset { manifest_hash: [0; 32], ..provision_reset };
+		let signature =
+			member_pairs[1].sign(&other_manifest.qos_hash()).unwrap();
+		assert_eq!(
+			reset(
+				&other_manifest,
+				&Approval { signature, ..approval },
+				&mut state
+			)
+			.unwrap_err(),
+			ProtocolError::ProvisionResetManifestMismatch
+		);
+
+		// Nothing was discarded
+		assert_eq!(state.provisioner.count(), 1);
+		assert_eq!(
+			state.handles.get_manifest_envelope().unwrap().share_set_approvals,
+			approvals[..1]
+		);
+		assert!(state.handles.get_ephemeral_key().unwrap() == eph_pair);
+	}
+
+	#[test]
+	fn secret_builder_wipes_shares() {
+		fn zeroize_on_drop<T: ZeroizeOnDrop>() {}
+		zeroize_on_drop::<SecretBuilder>();
+
+		let (shares, _) = shares_generate(&[7; 32], 3, 2).unwrap();
+		let mut builder = SecretBuilder::new();
+		for share in &shares[..2] {
+			builder.add_share(share.clone()).unwrap();
+		}
+		assert_eq!(*builder.build().unwrap(), [7; 32]);
+
+		builder.finish();
+		let share_hash = sha_512(&shares[2]);
+		assert_eq!(
+			builder.reconstructed_share(&share_hash).unwrap(),
+			shares[2]
+		);
+
+		builder.add_share(shares[0].clone()).unwrap();
+		builder.zeroize();
+		assert_eq!(builder.count(), 0);
+		assert_eq!(
+			builder.reconstructed_share(&share_hash),
+			Err(ProtocolError::NoReconstructedShares)
+		);
+	}
+
+	fn approve_rotation(
+		rotation: &ShareRotation,
+		approvals: &[Approval],
+		member_pairs: &[P256Pair],
+	) -> Vec<Approval> {
+		approvals
+			.iter()
+			.zip(member_pairs)
+			.map(|(approval, pair)| Approval {
+				member: approval.member.clone(),
+				signature: pair.sign(&rotation.qos_hash()).unwrap(),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn rotate_share_encrypts_a_share_to_the_new_key() {
+		let quorum_file: PathWrapper =
+			"./rotate_share_encrypts_a_share_to_the_new_key.quorum.key".into();
+		let eph_file: PathWrapper =
+			"./rotate_share_encrypts_a_share_to_the_new_key.eph.key".into();
+		let manifest_file: PathWrapper =
+			"./rotate_share_encrypts_a_share_to_the_new_key.manifest".into();
+
+		let Setup {
+			quorum_pair,
+			eph_pair,
+			threshold,
+			mut state,
+			approvals,
+			member_pairs,
+		} = setup(&eph_file, &quorum_file, &manifest_file);
+		let (shares, _) =
+			shares_generate(quorum_pair.to_master_seed(), 4, threshold)
+				.unwrap();
+
+		// The last member lost their key and has a new one
+		let new_pair = P256Pair::generate().unwrap();
+		let rotation = ShareRotation {
+			manifest_hash: state
+				.handles
+				.get_manifest_envelope()
+				.unwrap()
+				.manifest
+				.qos_hash(),
+			member: approvals[3].member.clone(),
+			share_hash: sha_512(&shares[3]),
+			new_pub_key: new_pair.public_key().to_bytes(),
+		};
+		let signature = new_pair.sign(&rotation.qos_hash()).unwrap();
+		let rotation_approvals =
+			approve_rotation(&rotation, &approvals[..threshold], &member_pairs);
+
+		// Nothing to rotate before the quorum key is reconstructed
+		assert_eq!(
+			rotate_share(&rotation, &signature, &rotation_approvals, &state),
+			Err(ProtocolError::NoReconstructedShares)
+		);
+
+		for (share, approval) in shares[..threshold].iter().zip(&approvals) {
+			let share = eph_pair.public_key().encrypt(share).unwrap();
+			provision(&share, approval.clone(), &mut state).unwrap();
+		}
+
+		let encrypted_share =
+			rotate_share(&rotation, &signature, &rotation_approvals, &state)
+				.unwrap();
+		let share = open_share(
+			&new_pair.decrypt(&encrypted_share).unwrap(),
+			&quorum_pair.public_key().to_bytes(),
+			threshold.try_into().unwrap(),
+		)
+		.unwrap();
+		assert_eq!(share, shares[3]);
+
+		assert_eq!(
+			rotate_share(
+				&rotation,
+				&signature,
+				&rotation_approvals[1..],
+				&state
+			),
+			Err(ProtocolError::NotEnoughApprovals)
+		);
+		assert_eq!(
+			rotate_share(
+				&rotation,
+				&signature,
+				&[rotation_approvals.clone(), rotation_approvals[..1].to_vec()]
+					.concat(),
+				&state
+			),
+			Err(ProtocolError::DuplicateApproval)
+		);
+		assert_eq!(
+			rotate_share(
+				&rotation,
+				&member_pairs[3].sign(&rotation.qos_hash()).unwrap(),
+				&rotation_approvals,
+				&state
+			),
+			Err(ProtocolError::InvalidShareRotationSignature)
+		);
+
+		for (changed, error) in [
+			(
+				ShareRotation { manifest_hash: [0; 32], ..rotation.clone() },
+				ProtocolError::ShareRotationManifestMismatch,
+			),
+			(
+				ShareRotation { share_hash: [0; 64], ..rotation.clone() },
+				ProtocolError::ShareNotFound,
+			),
+		] {
+			let signature = new_pair.sign(&changed.qos_hash()).unwrap();
+			let approvals =
+				approve_rotation(&changed, &rotation_approvals, &member_pairs);
+			assert_eq!(
+				rotate_share(&changed, &signature, &approvals, &state),
+				Err(error)
+			);
+		}
+	}
 }
@@ -0,0 +1,182 @@
+//! Crypto self test, run on request so operators can check the health of the
+//! enclave's cryptography after boot.
+
+use qos_crypto::self_test::{sha2_known_answer, shamir_round_trip};
+use qos_p256::self_test::{
+	aes_gcm_known_answer, ecdh_known_answer, ecdsa_known_answer,
+};
+
+use crate::protocol::{ProtocolError, ProtocolState, QosHash};
+
+/// A test run by the crypto self test.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum SelfTest {
+	/// AES-256-GCM known-answer test.
+	AesGcm256,
+	/// ECDH over P256 known-answer test.
+	EcdhP256,
+	/// ECDSA over P256 with SHA256 known-answer test.
+	EcdsaP256Sha256,
+	/// SHA256, SHA384 and SHA512 known-answer tests.
+	Sha2,
+	/// Shamir secret sharing split and reconstruct round trip.
+	Shamir,
+}
+
+impl SelfTest {
+	/// Every test, in the order they are run.
+	pub const ALL: [Self; 5] = [
+		Self::AesGcm256,
+		Self::EcdhP256,
+		Self::EcdsaP256Sha256,
+		Self::Sha2,
+		Self::Shamir,
+	];
+
+	/// Run the test and return whether it passed.
+	#[must_use]
+	pub fn run(self) -> bool {
+		match self {
+			Self::AesGcm256 => aes_gcm_known_answer(),
+			Self::EcdhP256 => ecdh_known_answer(),
+			Self::EcdsaP256Sha256 => ecdsa_known_answer(),
+			Self::Sha2 => sha2_known_answer(),
+			Self::Shamir => shamir_round_trip(),
+		}
+	}
+}
+
+/// Outcome of a single [`SelfTest`].
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestResult {
+	/// The test that was run.
+	pub test: SelfTest,
+	/// Whether the test passed.
+	pub passed: bool,
+}
+
+/// Report of a crypto self test, signed by the Ephemeral Key of the enclave
+/// that ran it.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+	/// Outcome of every test in [`SelfTest::ALL`].
+	pub results: Vec<SelfTestResult>,
+}
+
+impl SelfTestReport {
+	/// Run every test in [`SelfTest::ALL`].
+	#[must_use]
+	pub fn run() -> Self {
+		let results = SelfTest::ALL
+			.into_iter()
+			.map(|test| SelfTestResult { test, passed: test.run() })
+			.collect();
+
+		Self { results }
+	}
+
+	/// Whether every test passed.
+	#[must_use]
+	pub fn passed(&self) -> bool {
+		self.results.iter().all(|result| result.passed)
+	}
+}
+
+/// Run the crypto self test. Returns the report and the signature of the
+/// Ephemeral Key over the report's [`QosHash`].
+pub(in crate::protocol) fn self_test(
+	state: &mut ProtocolState,
+) -> Result<(SelfTestReport, Vec<u8>), ProtocolError> {
+	let ephemeral_key = state.handles.get_ephemeral_key()?;
+	let report = SelfTestReport::run();
+	let signature = ephemeral_key.sign(&report.qos_hash())?;
+
+	Ok((report, signature))
+}
+
+#[cfg(test)]
+mod test {
+	use qos_nsm::mock::MockNsm;
+	use qos_p256::P256Pair;
+	use qos_test_primitives::PathWrapper;
+
+	use super::*;
+	use crate::{handles::Handles, io::SocketAddress};
+
+	fn state(eph_file: &str) -> ProtocolState {
+		let handles = Handles::new(
+			eph_file.to_string(),
+			"quorum".to_string(),
+			"manifest".to_string(),
+			"pivot".to_string(),
+			"pivot_info".to_string(),
+		);
+
+		ProtocolState::new(
+			Box::new(MockNsm),
+			handles,
+			SocketAddress::new_unix("./never.sock"),
+			None,
+		)
+	}
+
+	#[test]
+	fn self_test_signs_a_passing_report() {
+		let eph_file: PathWrapper =
+			"./self_test_signs_a_passing_report.eph.key".into();
+		let mut state = state(&eph_file);
+		let eph_pair = P256Pair::generate().unwrap();
+		state.handles.put_ephemeral_key(&eph_pair).unwrap();
+
+		let (report, signature) = self_test(&mut state).unwrap();
+
+		assert!(report.passed());
+		assert_eq!(report.results.len(), SelfTest::ALL.len());
+		assert!(eph_pair
+			.public_key()
+			.verify(&report.qos_hash(), &signature)
+			.is_ok());
+	}
+
+	#[test]
+	fn self_test_errors_without_an_ephemeral_key() {
+		let mut state =
+			state("./self_test_errors_without_an_ephemeral_key.eph.key");
+
+		assert!(matches!(
+			self_test(&mut state),
+			Err(ProtocolError::FailedToGetEphemeralKey(_))
+		));
+	}
+}
@@ -1,14 +1,38 @@
 //! Quorum protocol state machine
+use std::{sync::Arc, time::Duration};
+
 use nix::sys::time::{TimeVal, TimeValLike};
-use qos_nsm::NsmProvider;
+use qos_nsm::{types::NsmResponse, NsmProvider};
 
 use super::{
-	error::ProtocolError, msg::ProtocolMsg, services::provision::SecretBuilder,
+	error::ProtocolError,
+	msg::ProtocolMsg,
+	services::provision::SecretBuilder,
+	status::{SharedBootTimeline, SharedPivotStatus},
+};
+use crate::{
+	audit::{
+		AuditLog, AUDIT_STATUS_ERROR, AUDIT_STATUS_OK,
+		ENCLAVE_AUDIT_LOG_CAPACITY,
+	},
+	client::Client,
+	executor::SharedNsm,
+	handles::Handles,
+	io::{Backoff, SocketAddress},
+	log::{self, Logger},
+	time::TimeSource,
 };
-use crate::{client::Client, handles::Handles, io::SocketAddress};
 
 /// The timeout for the qos core when making requests to an enclave app.
 pub const ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS: i64 = 5;
+/// Backoff for reconnecting to the enclave app, e.g. while the reaper
+/// restarts it. The sync [`crate::server::SocketServer`] holds the protocol
+/// state while reconnecting, so the window is kept short.
+pub const ENCLAVE_APP_SOCKET_CLIENT_BACKOFF: Backoff = Backoff {
+	initial: Duration::from_millis(10),
+	max: Duration::from_millis(100),
+	max_elapsed: Duration::from_millis(500),
+};
 
 /// Enclave phase
 #[derive(
@@ -63,6 +87,13 @@ impl ProtocolRoute {
 				return resp;
 			}
 		}
+		// a rejected share is not used, so the other members can continue
+		if let Some(Err(ProtocolMsg::ProtocolErrorResponse(
+			ProtocolError::RejectedShare { .. },
+		))) = resp
+		{
+			return resp;
+		}
 
 		// handle state transitions
 		let transition = match resp {
@@ -90,6 +121,14 @@ impl ProtocolRoute {
 		)
 	}
 
+	pub fn enclave_status(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::enclave_status),
+			current_phase,
+			current_phase,
+		)
+	}
+
 	pub fn manifest_envelope(current_phase: ProtocolPhase) -> Self {
 		ProtocolRoute::new(
 			Box::new(handlers::manifest_envelope),
@@ -106,6 +145,22 @@ impl ProtocolRoute {
 		)
 	}
 
+	pub fn nonced_attestation_doc(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::nonced_attestation_doc),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn boot_attestation_doc(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::boot_attestation_doc),
+			current_phase,
+			current_phase,
+		)
+	}
+
 	pub fn boot_genesis(_current_phase: ProtocolPhase) -> Self {
 		ProtocolRoute::new(
 			Box::new(handlers::boot_genesis),
@@ -138,6 +193,14 @@ impl ProtocolRoute {
 		)
 	}
 
+	pub fn provision_reset(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::provision_reset),
+			current_phase,
+			current_phase,
+		)
+	}
+
 	pub fn proxy(current_phase: ProtocolPhase) -> Self {
 		ProtocolRoute::new(
 			Box::new(handlers::proxy),
@@ -154,6 +217,54 @@ impl ProtocolRoute {
 		)
 	}
 
+	pub fn rotate_share(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::rotate_share),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn mock_ephemeral_key(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::mock_ephemeral_key),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn self_test(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::self_test),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn app_health(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::app_health),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn logs(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::logs),
+			current_phase,
+			current_phase,
+		)
+	}
+
+	pub fn audit_log(current_phase: ProtocolPhase) -> Self {
+		ProtocolRoute::new(
+			Box::new(handlers::audit_log),
+			current_phase,
+			current_phase,
+		)
+	}
+
 	pub fn inject_key(_current_phase: ProtocolPhase) -> Self {
 		ProtocolRoute::new(
 			Box::new(handlers::inject_key),
@@ -174,20 +285,33 @@ impl ProtocolRoute {
 /// Enclave state
 pub(crate) struct ProtocolState {
 	pub provisioner: SecretBuilder,
-	pub attestor: Box<dyn NsmProvider>,
+	pub attestor: Box<dyn NsmProvider + Send>,
 	pub app_client: Client,
 	pub handles: Handles,
+	pub pivot_status: SharedPivotStatus,
+	pub boot_timeline: SharedBootTimeline,
+	/// The attestation document returned when the enclave was booted.
+	pub boot_attestation_doc: Option<NsmResponse>,
+	/// Clock for checking the validity of attestation documents. The NSM
+	/// unless replaced.
+	pub time: Arc<dyn TimeSource>,
+	/// Log served to the host. The [`log::global`] logger unless replaced.
+	pub logger: Arc<Logger>,
+	/// Audit log of the requests that changed the state or used the keys of
+	/// the enclave, see [`audited_route`].
+	pub audit_log: AuditLog,
 	phase: ProtocolPhase,
 }
 
 impl ProtocolState {
 	pub fn new(
-		attestor: Box<dyn NsmProvider>,
+		attestor: Box<dyn NsmProvider + Send>,
 		handles: Handles,
 		app_addr: SocketAddress,
 		test_only_init_phase_override: Option<ProtocolPhase>,
 	) -> Self {
 		let provisioner = SecretBuilder::new();
+		let attestor = SharedNsm::new(attestor);
 
 		#[cfg(any(feature = "mock", test))]
 		let init_phase = if let Some(phase) = test_only_init_phase_override {
@@ -198,15 +322,24 @@ impl ProtocolState {
 		#[cfg(not(any(feature = "mock", test)))]
 		let init_phase = ProtocolPhase::WaitingForBootInstruction;
 
+		let time: Arc<dyn TimeSource> = Arc::new(attestor.clone());
+
 		Self {
-			attestor,
+			attestor: Box::new(attestor),
+			audit_log: AuditLog::new(ENCLAVE_AUDIT_LOG_CAPACITY, time.clone()),
+			time,
+			logger: log::global().clone(),
 			provisioner,
 			phase: init_phase,
 			handles,
+			pivot_status: SharedPivotStatus::new(),
+			boot_timeline: SharedBootTimeline::default(),
+			boot_attestation_doc: None,
 			app_client: Client::new(
 				app_addr,
 				TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS),
-			),
+			)
+			.with_backoff(ENCLAVE_APP_SOCKET_CLIENT_BACKOFF),
 		}
 	}
 
@@ -214,23 +347,33 @@ impl ProtocolState {
 		self.phase
 	}
 
-	pub fn handle_msg(&mut self, msg_req: &ProtocolMsg) -> Vec<u8> {
+	pub fn handle_msg(&mut self, msg_req: &ProtocolMsg) -> ProtocolMsg {
+		let response = self.route_msg(msg_req);
+
+		if let Some(route) = audited_route(msg_req) {
+			let status = match response {
+				ProtocolMsg::ProtocolErrorResponse(_) => AUDIT_STATUS_ERROR,
+				_ => AUDIT_STATUS_OK,
+			};
+			let encoded = borsh::to_vec(msg_req)
+				.expect("ProtocolMsg can always be serialized. qed.");
+			self.audit_log.record(route, qos_crypto::sha_256(&encoded), status);
+		}
+
+		response
+	}
+
+	fn route_msg(&mut self, msg_req: &ProtocolMsg) -> ProtocolMsg {
 		for route in &self.routes() {
 			match route.try_msg(msg_req, self) {
 				None => continue,
-				Some(result) => match result {
-					Ok(msg_resp) | Err(msg_resp) => {
-						return borsh::to_vec(&msg_resp).expect(
-							"ProtocolMsg can always be serialized. qed.",
-						)
-					}
-				},
+				Some(Ok(msg_resp) | Err(msg_resp)) => return msg_resp,
 			}
 		}
 
-		let err = ProtocolError::NoMatchingRoute(self.phase);
-		borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(err))
-			.expect("ProtocolMsg can always be serialized. qed.")
+		ProtocolMsg::ProtocolErrorResponse(ProtocolError::NoMatchingRoute(
+			self.phase,
+		))
 	}
 
 	#[allow(clippy::too_many_lines)]
@@ -240,16 +383,26 @@ impl ProtocolState {
 			ProtocolPhase::UnrecoverableError => {
 				vec![
 					ProtocolRoute::status(self.phase),
+					ProtocolRoute::enclave_status(self.phase),
+					ProtocolRoute::logs(self.phase),
 					ProtocolRoute::manifest_envelope(self.phase),
 					ProtocolRoute::live_attestation_doc(self.phase),
+					ProtocolRoute::nonced_attestation_doc(self.phase),
+					ProtocolRoute::boot_attestation_doc(self.phase),
 				]
 			}
 			ProtocolPhase::GenesisBooted => {
-				vec![ProtocolRoute::status(self.phase)]
+				vec![
+					ProtocolRoute::status(self.phase),
+					ProtocolRoute::enclave_status(self.phase),
+					ProtocolRoute::logs(self.phase),
+				]
 			}
 			ProtocolPhase::WaitingForBootInstruction => vec![
 				// baseline routes
 				ProtocolRoute::status(self.phase),
+				ProtocolRoute::enclave_status(self.phase),
+				ProtocolRoute::logs(self.phase),
 				ProtocolRoute::manifest_envelope(self.phase),
 				// phase specific routes
 				ProtocolRoute::boot_genesis(self.phase),
@@ -260,29 +413,49 @@ impl ProtocolState {
 				vec![
 					// baseline routes
 					ProtocolRoute::status(self.phase),
+					ProtocolRoute::enclave_status(self.phase),
+					ProtocolRoute::logs(self.phase),
 					ProtocolRoute::live_attestation_doc(self.phase),
+					ProtocolRoute::nonced_attestation_doc(self.phase),
+					ProtocolRoute::boot_attestation_doc(self.phase),
 					ProtocolRoute::manifest_envelope(self.phase),
+					ProtocolRoute::self_test(self.phase),
 					// phase specific routes
 					ProtocolRoute::provision(self.phase),
+					ProtocolRoute::provision_reset(self.phase),
+					ProtocolRoute::mock_ephemeral_key(self.phase),
 				]
 			}
 			ProtocolPhase::QuorumKeyProvisioned => {
 				vec![
 					// baseline routes
 					ProtocolRoute::status(self.phase),
+					ProtocolRoute::enclave_status(self.phase),
+					ProtocolRoute::logs(self.phase),
 					ProtocolRoute::live_attestation_doc(self.phase),
+					ProtocolRoute::nonced_attestation_doc(self.phase),
+					ProtocolRoute::boot_attestation_doc(self.phase),
 					ProtocolRoute::manifest_envelope(self.phase),
+					ProtocolRoute::self_test(self.phase),
 					// phase specific routes
 					ProtocolRoute::proxy(self.phase),
+					ProtocolRoute::app_health(self.phase),
+					ProtocolRoute::audit_log(self.phase),
 					ProtocolRoute::export_key(self.phase),
+					ProtocolRoute::rotate_share(self.phase),
 				]
 			}
 			ProtocolPhase::WaitingForForwardedKey => {
 				vec![
 					// baseline routes
 					ProtocolRoute::status(self.phase),
+					ProtocolRoute::enclave_status(self.phase),
+					ProtocolRoute::logs(self.phase),
 					ProtocolRoute::live_attestation_doc(self.phase),
+					ProtocolRoute::nonced_attestation_doc(self.phase),
+					ProtocolRoute::boot_attestation_doc(self.phase),
 					ProtocolRoute::manifest_envelope(self.phase),
+					ProtocolRoute::self_test(self.phase),
 					// phase specific routes
 					ProtocolRoute::inject_key(self.phase),
 				]
@@ -338,15 +511,40 @@ impl ProtocolState {
 	}
 }
 
+/// Name to record `msg` under in the audit log, if it changes the state or
+/// uses the keys of the enclave. Reads are not recorded, and neither is app
+/// traffic, which the pivot can audit itself.
+fn audited_route(msg: &ProtocolMsg) -> Option<&'static str> {
+	let route = match msg {
+		ProtocolMsg::BootStandardRequest { .. } => "BootStandardRequest",
+		ProtocolMsg::BootGenesisRequest { .. } => "BootGenesisRequest",
+		ProtocolMsg::BootKeyForwardRequest { .. } => "BootKeyForwardRequest",
+		ProtocolMsg::ProvisionRequest { .. } => "ProvisionRequest",
+		ProtocolMsg::ProvisionResetRequest { .. } => "ProvisionResetRequest",
+		ProtocolMsg::ExportKeyRequest { .. } => "ExportKeyRequest",
+		ProtocolMsg::InjectKeyRequest { .. } => "InjectKeyRequest",
+		ProtocolMsg::RotateShareRequest { .. } => "RotateShareRequest",
+		_ => return None,
+	};
+
+	Some(route)
+}
+
 mod handlers {
 	use super::ProtocolRouteResponse;
 	use crate::protocol::{
+		app_health::{AppHealth, APP_HEALTH_REQUEST},
 		msg::ProtocolMsg,
 		services::{
-			attestation, boot, genesis, key, key::EncryptedQuorumKey, provision,
+			attestation, boot, genesis, key, key::EncryptedQuorumKey,
+			provision, self_test,
 		},
-		ProtocolState,
+		status::{
+			BootEvent, EnclaveStatus, ManifestStatus, ReconstructionStatus,
+		},
+		ProtocolError, ProtocolState, QosHash,
 	};
+	use crate::{audit, diagnostics::Diagnostics, log};
 
 	// TODO: Add tests for this in the middle of some integration tests
 	/// Status of the enclave.
@@ -361,6 +559,43 @@ mod handlers {
 		}
 	}
 
+	/// Detailed status of the enclave.
+	pub(super) fn enclave_status(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::EnclaveStatusRequest = req {
+			let manifest =
+				state.handles.get_manifest_envelope().ok().map(|envelope| {
+					ManifestStatus {
+						hash: envelope.manifest.qos_hash(),
+						reconstruction: ReconstructionStatus {
+							shares_received: u32::try_from(
+								state.provisioner.count(),
+							)
+							.unwrap_or(u32::MAX),
+							threshold: envelope.manifest.share_set.threshold,
+						},
+					}
+				});
+
+			Some(Ok(ProtocolMsg::EnclaveStatusResponse(Box::new(
+				EnclaveStatus {
+					phase: state.get_phase(),
+					manifest,
+					pivot: state.pivot_status.get(),
+					last_panic: Diagnostics::new(
+						state.handles.panic_report_path(),
+					)
+					.last_panic(),
+					timeline: state.boot_timeline.get(),
+				},
+			))))
+		} else {
+			None
+		}
+	}
+
 	pub(super) fn manifest_envelope(
 		req: &ProtocolMsg,
 		state: &mut ProtocolState,
@@ -393,14 +628,102 @@ mod handlers {
 		}
 	}
 
+	/// Read the log of the enclave.
+	pub(super) fn logs(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::LogsRequest { since, max } = req {
+			let max = (*max).min(log::MAX_LOGS_PER_REQUEST) as usize;
+			Some(Ok(ProtocolMsg::LogsResponse(state.logger.read(*since, max))))
+		} else {
+			None
+		}
+	}
+
+	/// Read the audit log of the enclave, vouched for by the Quorum Key.
+	pub(super) fn audit_log(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::AuditLogRequest { since, max } = req {
+			let max = (*max).min(audit::MAX_AUDIT_ENTRIES_PER_REQUEST) as usize;
+			let entries = state.audit_log.read(*since, max);
+			let result = match entries.last() {
+				None => Ok(Vec::new()),
+				Some(head) => state.handles.get_quorum_key().and_then(|pair| {
+					pair.sign(&head.signed_head()).map_err(Into::into)
+				}),
+			}
+			.map(|signature| ProtocolMsg::AuditLogResponse {
+				entries,
+				signature,
+			})
+			.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
+	/// Handle `ProtocolMsg::AppHealthRequest` by probing the pivot.
+	pub(super) fn app_health(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::AppHealthRequest = req {
+			let result = state
+				.app_client
+				.send(APP_HEALTH_REQUEST)
+				.map_err(ProtocolError::from)
+				.and_then(|response| AppHealth::from_response(&response))
+				.map(|health| {
+					if health.is_ready() {
+						state.boot_timeline.record(BootEvent::PivotReady);
+					}
+					ProtocolMsg::AppHealthResponse(health)
+				})
+				.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
 	pub(super) fn provision(
 		req: &ProtocolMsg,
 		state: &mut ProtocolState,
 	) -> ProtocolRouteResponse {
 		if let ProtocolMsg::ProvisionRequest { share, approval } = req {
 			let result = provision::provision(share, approval.clone(), state)
-				.map(|reconstructed| ProtocolMsg::ProvisionResponse {
-					reconstructed,
+				.map(|reconstructed| {
+					state.boot_timeline.record(BootEvent::ShareReceived);
+					if reconstructed {
+						state
+							.boot_timeline
+							.record(BootEvent::QuorumReconstructed);
+					}
+					ProtocolMsg::ProvisionResponse { reconstructed }
+				})
+				.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
+	/// Handle `ProtocolMsg::ProvisionResetRequest`.
+	pub(super) fn provision_reset(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::ProvisionResetRequest { reset, approval } = req {
+			let result = provision::reset(reset, approval, state)
+				.map(|nsm_response| ProtocolMsg::ProvisionResetResponse {
+					nsm_response,
 				})
 				.map_err(ProtocolMsg::ProtocolErrorResponse);
 
@@ -419,8 +742,10 @@ mod handlers {
 			req
 		{
 			let result = boot::boot_standard(state, manifest_envelope, pivot)
-				.map(|nsm_response| ProtocolMsg::BootStandardResponse {
-					nsm_response,
+				.map(|nsm_response| {
+					state.boot_timeline.record(BootEvent::BootStandardAccepted);
+					state.boot_attestation_doc = Some(nsm_response.clone());
+					ProtocolMsg::BootStandardResponse { nsm_response }
 				})
 				.map_err(ProtocolMsg::ProtocolErrorResponse);
 
@@ -472,6 +797,57 @@ mod handlers {
 		}
 	}
 
+	pub(super) fn nonced_attestation_doc(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::NoncedAttestationDocRequest { nonce } = req {
+			let result =
+				attestation::nonced_attestation_doc(state, nonce.clone())
+					.map(|nsm_response| {
+						ProtocolMsg::LiveAttestationDocResponse {
+							nsm_response,
+							manifest_envelope: state
+								.handles
+								.get_manifest_envelope()
+								.ok()
+								.map(Box::new),
+						}
+					})
+					.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
+	pub(super) fn boot_attestation_doc(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::BootAttestationDocRequest = req {
+			let result = state
+				.boot_attestation_doc
+				.clone()
+				.map(|nsm_response| ProtocolMsg::LiveAttestationDocResponse {
+					nsm_response,
+					manifest_envelope: state
+						.handles
+						.get_manifest_envelope()
+						.ok()
+						.map(Box::new),
+				})
+				.ok_or(ProtocolMsg::ProtocolErrorResponse(
+					ProtocolError::BootAttestationDocNotFound,
+				));
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
 	pub(super) fn boot_key_forward(
 		req: &ProtocolMsg,
 		state: &mut ProtocolState,
@@ -480,8 +856,10 @@ mod handlers {
 			req
 		{
 			let result = key::boot_key_forward(state, manifest_envelope, pivot)
-				.map(|nsm_response| ProtocolMsg::BootKeyForwardResponse {
-					nsm_response,
+				.map(|nsm_response| {
+					state.boot_timeline.record(BootEvent::BootStandardAccepted);
+					state.boot_attestation_doc = Some(nsm_response.clone());
+					ProtocolMsg::BootKeyForwardResponse { nsm_response }
 				})
 				.map_err(ProtocolMsg::ProtocolErrorResponse);
 
@@ -521,6 +899,77 @@ mod handlers {
 		}
 	}
 
+	/// Handle `ProtocolMsg::RotateShareRequest`.
+	pub(super) fn rotate_share(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::RotateShareRequest {
+			rotation,
+			signature,
+			approvals,
+		} = req
+		{
+			let result =
+				provision::rotate_share(rotation, signature, approvals, state)
+					.map(|encrypted_share| ProtocolMsg::RotateShareResponse {
+						encrypted_share,
+					})
+					.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
+	/// Handle `ProtocolMsg::MockEphemeralKeyRequest`.
+	pub(super) fn mock_ephemeral_key(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::MockEphemeralKeyRequest = req {
+			#[cfg(feature = "mock")]
+			let result = state
+				.handles
+				.get_ephemeral_key()
+				.map(|pair| ProtocolMsg::MockEphemeralKeyResponse {
+					ephemeral_public_key: pair.public_key().to_bytes(),
+				})
+				.map_err(ProtocolMsg::ProtocolErrorResponse);
+			#[cfg(not(feature = "mock"))]
+			let result = {
+				let _ = state;
+				Err(ProtocolMsg::ProtocolErrorResponse(
+					ProtocolError::MockFeatureDisabled,
+				))
+			};
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
+	/// Handle `ProtocolMsg::SelfTestRequest`.
+	pub(super) fn self_test(
+		req: &ProtocolMsg,
+		state: &mut ProtocolState,
+	) -> ProtocolRouteResponse {
+		if let ProtocolMsg::SelfTestRequest = req {
+			let result = self_test::self_test(state)
+				.map(|(report, signature)| ProtocolMsg::SelfTestResponse {
+					report,
+					signature,
+				})
+				.map_err(ProtocolMsg::ProtocolErrorResponse);
+
+			Some(result)
+		} else {
+			None
+		}
+	}
+
 	pub(super) fn inject_key(
 		req: &ProtocolMsg,
 		state: &mut ProtocolState,
@@ -0,0 +1,309 @@
+//! Detailed enclave status, as returned for
+//! [`super::msg::ProtocolMsg::EnclaveStatusRequest`].
+
+use std::{
+	fmt,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+use super::{Hash256, ProtocolPhase};
+use crate::{
+	diagnostics::PanicReport,
+	time::{SystemClock, TimeSource},
+};
+
+/// Snapshot of everything an operator needs to know whether an enclave is
+/// provisioned and running its pivot.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveStatus {
+	/// Current phase of the enclave.
+	pub phase: ProtocolPhase,
+	/// The manifest the enclave was booted with, if any.
+	pub manifest: Option<ManifestStatus>,
+	/// Status of the pivot binary, as seen by the reaper.
+	pub pivot: PivotStatus,
+	/// The latest panic of the enclave process, kept across restarts, see
+	/// [`crate::diagnostics`].
+	pub last_panic: Option<PanicReport>,
+	/// When the enclave reached each step of booting.
+	pub timeline: BootTimeline,
+}
+
+/// Status of the manifest the enclave was booted with.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestStatus {
+	/// Hash of the manifest.
+	#[serde(with = "qos_hex::serde")]
+	pub hash: Hash256,
+	/// Progress of reconstructing the quorum key from shares.
+	pub reconstruction: ReconstructionStatus,
+}
+
+/// Progress of reconstructing the quorum key from shares.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconstructionStatus {
+	/// Number of shares posted that have not yet been used to reconstruct
+	/// the quorum key. This is reset once the key is reconstructed.
+	pub shares_received: u32,
+	/// Number of shares needed to reconstruct the quorum key.
+	pub threshold: u32,
+}
+
+/// Status of the pivot binary supervised by the [`crate::reaper::Reaper`].
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum PivotStatus {
+	/// The pivot has not been started yet.
+	NotStarted,
+	/// The pivot is running.
+	#[serde(rename_all = "camelCase")]
+	Running {
+		/// Number of times the pivot has been restarted.
+		restarts: u32,
+	},
+	/// The pivot exited. It may be restarted, depending on its restart
+	/// policy.
+	#[serde(rename_all = "camelCase")]
+	Exited {
+		/// Exit code, if the pivot was not terminated by a signal.
+		code: Option<i32>,
+		/// Number of times the pivot has been restarted.
+		restarts: u32,
+	},
+}
+
+/// [`PivotStatus`] shared between the reaper, which updates it, and the
+/// enclave server, which reports it.
+#[derive(Debug, Clone)]
+pub struct SharedPivotStatus(Arc<Mutex<PivotStatus>>);
+
+impl SharedPivotStatus {
+	/// Create a new [`Self`] for a pivot that has not been started.
+	#[must_use]
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(PivotStatus::NotStarted)))
+	}
+
+	/// Get the current status.
+	#[must_use]
+	pub fn get(&self) -> PivotStatus {
+		*self.0.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Replace the current status.
+	pub fn set(&self, status: PivotStatus) {
+		*self.0.lock().unwrap_or_else(PoisonError::into_inner) = status;
+	}
+}
+
+impl Default for SharedPivotStatus {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Milliseconds since the unix epoch when the enclave reached each step of
+/// booting, for measuring how long the ceremony and booting took. Steps that
+/// have not happened yet are `None`.
+#[derive(
+	Debug,
+	Clone,
+	Default,
+	PartialEq,
+	Eq,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BootTimeline {
+	/// The boot standard instruction was accepted.
+	pub boot_standard_accepted_ms: Option<u64>,
+	/// Each accepted share, in the order they were posted. Shares discarded
+	/// by a provision reset are kept.
+	pub shares_received_ms: Vec<u64>,
+	/// The quorum key was reconstructed from shares.
+	pub quorum_reconstructed_ms: Option<u64>,
+	/// The pivot was first spawned.
+	pub pivot_spawned_ms: Option<u64>,
+	/// The pivot first reported it was ready to an app health probe.
+	pub pivot_ready_ms: Option<u64>,
+}
+
+impl BootTimeline {
+	/// Milliseconds from the first share being posted until the quorum key
+	/// was reconstructed.
+	#[must_use]
+	pub fn ceremony_ms(&self) -> Option<u64> {
+		let first_share = self.shares_received_ms.first()?;
+		Some(self.quorum_reconstructed_ms?.saturating_sub(*first_share))
+	}
+
+	/// Milliseconds from the boot standard instruction being accepted until
+	/// the pivot was ready.
+	#[must_use]
+	pub fn boot_ms(&self) -> Option<u64> {
+		Some(
+			self.pivot_ready_ms?
+				.saturating_sub(self.boot_standard_accepted_ms?),
+		)
+	}
+}
+
+/// A step of booting recorded in a [`BootTimeline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootEvent {
+	/// See [`BootTimeline::boot_standard_accepted_ms`].
+	BootStandardAccepted,
+	/// See [`BootTimeline::shares_received_ms`].
+	ShareReceived,
+	/// See [`BootTimeline::quorum_reconstructed_ms`].
+	QuorumReconstructed,
+	/// See [`BootTimeline::pivot_spawned_ms`].
+	PivotSpawned,
+	/// See [`BootTimeline::pivot_ready_ms`].
+	PivotReady,
+}
+
+/// [`BootTimeline`] shared between the reaper and the enclave server, which
+/// both record events in it.
+#[derive(Clone)]
+pub struct SharedBootTimeline {
+	time: Arc<dyn TimeSource>,
+	timeline: Arc<Mutex<BootTimeline>>,
+}
+
+impl fmt::Debug for SharedBootTimeline {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("SharedBootTimeline")
+			.field("timeline", &self.timeline)
+			.finish_non_exhaustive()
+	}
+}
+
+impl SharedBootTimeline {
+	/// Create a new, empty [`Self`] timestamping events with `time`.
+	#[must_use]
+	pub fn new(time: Arc<dyn TimeSource>) -> Self {
+		Self { time, timeline: Arc::default() }
+	}
+
+	/// Get the current timeline.
+	#[must_use]
+	pub fn get(&self) -> BootTimeline {
+		self.timeline.lock().unwrap_or_else(PoisonError::into_inner).clone()
+	}
+
+	/// Record that `event` happened now. Only the first occurrence of an
+	/// event is kept, except for [`BootEvent::ShareReceived`].
+	pub fn record(&self, event: BootEvent) {
+		let Ok(now) = self.time.now_ms() else { return };
+		let mut timeline =
+			self.timeline.lock().unwrap_or_else(PoisonError::into_inner);
+		let slot = match event {
+			BootEvent::BootStandardAccepted => {
+				&mut timeline.boot_standard_accepted_ms
+			}
+			BootEvent::ShareReceived => {
+				timeline.shares_received_ms.push(now);
+				return;
+			}
+			BootEvent::QuorumReconstructed => {
+				&mut timeline.quorum_reconstructed_ms
+			}
+			BootEvent::PivotSpawned => &mut timeline.pivot_spawned_ms,
+			BootEvent::PivotReady => &mut timeline.pivot_ready_ms,
+		};
+		slot.get_or_insert(now);
+	}
+}
+
+impl Default for SharedBootTimeline {
+	fn default() -> Self {
+		Self::new(Arc::new(SystemClock))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::time::MockClock;
+
+	#[test]
+	fn boot_timeline_keeps_the_first_occurrence_of_events() {
+		let clock = Arc::new(MockClock::new(Duration::from_secs(1)));
+		let timeline = SharedBootTimeline::new(clock.clone());
+		assert_eq!(timeline.get(), BootTimeline::default());
+
+		timeline.record(BootEvent::BootStandardAccepted);
+		for _ in 0..2 {
+			clock.advance(Duration::from_millis(100));
+			timeline.record(BootEvent::ShareReceived);
+		}
+		timeline.record(BootEvent::QuorumReconstructed);
+		clock.advance(Duration::from_millis(50));
+		timeline.record(BootEvent::PivotSpawned);
+		timeline.record(BootEvent::PivotReady);
+		clock.advance(Duration::from_millis(50));
+		timeline.record(BootEvent::PivotSpawned);
+		timeline.record(BootEvent::PivotReady);
+
+		let timeline = timeline.get();
+		assert_eq!(
+			timeline,
+			BootTimeline {
+				boot_standard_accepted_ms: Some(1_000),
+				shares_received_ms: vec![1_100, 1_200],
+				quorum_reconstructed_ms: Some(1_200),
+				pivot_spawned_ms: Some(1_250),
+				pivot_ready_ms: Some(1_250),
+			}
+		);
+		assert_eq!(timeline.ceremony_ms(), Some(100));
+		assert_eq!(timeline.boot_ms(), Some(250));
+	}
+}
@@ -0,0 +1,162 @@
+//! Trace IDs correlating a request across the host, the enclave and the
+//! pivot.
+//!
+//! With tracing enabled, `qos_host` assigns every request a [`TraceId`],
+//! reusing the one in the [`TRACE_ID_HEADER`] of the request if it is valid,
+//! and returns it in the same header of the response. Requests to the enclave
+//! are wrapped in a [`ProtocolMsg::TracedRequest`], and the enclave logs the
+//! trace ID with their outcome, see [`crate::log`]. Requests the host proxies
+//! to the pivot as HTTP or gRPC carry the trace ID in the [`TRACE_ID_HEADER`]
+//! header or metadata.
+//!
+//! Trace IDs are chosen by clients, so they must not be trusted for anything
+//! but correlating logs.
+
+use std::fmt;
+
+use super::{msg::ProtocolMsg, ProtocolError};
+use crate::log::{LogLevel, Logger};
+
+/// Header carrying the trace ID of a request, in requests and responses of
+/// `qos_host` and in requests proxied to the pivot.
+pub const TRACE_ID_HEADER: &str = "x-qos-trace-id";
+/// Most characters in a [`TraceId`].
+pub const MAX_TRACE_ID_LEN: usize = 64;
+
+/// ID correlating a request across the host, the enclave and the pivot.
+///
+/// Only ASCII letters, digits, `-`, `_` and `.` are allowed, so a trace ID
+/// can be put in a header or a log line as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraceId(String);
+
+impl TraceId {
+	/// A new random trace ID.
+	#[must_use]
+	pub fn random() -> Self {
+		Self(qos_hex::encode(&qos_p256::bytes_os_rng::<16>()))
+	}
+
+	/// Parse `id`.
+	///
+	/// # Errors
+	///
+	/// [`ProtocolError::InvalidTraceId`] if `id` is empty, longer than
+	/// [`MAX_TRACE_ID_LEN`] or has a character that is not allowed.
+	pub fn parse(id: &str) -> Result<Self, ProtocolError> {
+		let valid = !id.is_empty()
+			&& id.len() <= MAX_TRACE_ID_LEN
+			&& id.bytes().all(|b| {
+				b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')
+			});
+		if valid {
+			Ok(Self(id.to_string()))
+		} else {
+			Err(ProtocolError::InvalidTraceId)
+		}
+	}
+
+	/// The trace ID as a string.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
+	/// Wrap `request` in a [`ProtocolMsg::TracedRequest`] with this ID.
+	#[must_use]
+	pub fn wrap(&self, request: ProtocolMsg) -> ProtocolMsg {
+		ProtocolMsg::TracedRequest {
+			trace_id: self.0.clone(),
+			request: Box::new(request),
+		}
+	}
+
+	/// Bytes that, followed by an encoded [`ProtocolMsg`], are the encoded
+	/// [`ProtocolMsg::TracedRequest`] wrapping it with this ID. Lets the host
+	/// trace requests it only has the encoding of, without decoding them.
+	///
+	/// # Panics
+	///
+	/// Never, [`ProtocolMsg`] can always be serialized.
+	#[must_use]
+	pub fn wrap_prefix(&self) -> Vec<u8> {
+		let mut prefix = borsh::to_vec(&self.wrap(ProtocolMsg::StatusRequest))
+			.expect("ProtocolMsg can always be serialized. qed.");
+		// Drop the encoding of the placeholder request, a lone variant index
+		prefix.pop();
+		prefix
+	}
+}
+
+impl fmt::Display for TraceId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+/// Unwrap `msg` if it is a [`ProtocolMsg::TracedRequest`].
+pub(crate) fn untrace(
+	msg: ProtocolMsg,
+) -> Result<(Option<TraceId>, ProtocolMsg), ProtocolError> {
+	match msg {
+		ProtocolMsg::TracedRequest { trace_id, request } => {
+			Ok((Some(TraceId::parse(&trace_id)?), *request))
+		}
+		msg => Ok((None, msg)),
+	}
+}
+
+/// Log the outcome of the request traced with `trace_id`, if any, to
+/// `logger`.
+pub(crate) fn log_response(
+	logger: &Logger,
+	trace_id: Option<&TraceId>,
+	response: &ProtocolMsg,
+) {
+	let Some(trace_id) = trace_id else { return };
+	match response {
+		ProtocolMsg::ProtocolErrorResponse(err) => logger.log(
+			LogLevel::Warn,
+			"processor",
+			format_args!("trace_id={trace_id} request failed: {err:?}"),
+		),
+		_ => logger.log(
+			LogLevel::Debug,
+			"processor",
+			format_args!("trace_id={trace_id} request succeeded"),
+		),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn parses_only_header_and_log_safe_ids() {
+		assert_eq!(TraceId::parse("req-1_a.B").unwrap().as_str(), "req-1_a.B");
+		assert_eq!(TraceId::random().as_str().len(), 32);
+		for invalid in ["", "a b", "a\nb", "ü", &"a".repeat(65)] {
+			assert_eq!(
+				TraceId::parse(invalid),
+				Err(ProtocolError::InvalidTraceId)
+			);
+		}
+	}
+
+	#[test]
+	fn wrap_prefix_wraps_encoded_requests() {
+		let trace_id = TraceId::parse("abc").unwrap();
+		let request = ProtocolMsg::ProxyRequest { data: b"hi".to_vec() };
+
+		let mut wrapped = trace_id.wrap_prefix();
+		wrapped.extend(borsh::to_vec(&request).unwrap());
+		assert_eq!(
+			wrapped,
+			borsh::to_vec(&trace_id.wrap(request.clone())).unwrap()
+		);
+
+		let decoded = borsh::from_slice(&wrapped).unwrap();
+		assert_eq!(untrace(decoded).unwrap(), (Some(trace_id), request));
+	}
+}
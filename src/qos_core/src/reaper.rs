@@ -8,14 +8,28 @@ use std::process::Command;
 
 use qos_nsm::NsmProvider;
 
+#[cfg(feature = "async")]
 use crate::{
-	handles::Handles,
-	io::SocketAddress,
+	async_client::AsyncClient,
+	async_server::AsyncSocketServer,
+	protocol::{AsyncProcessor, ENCLAVE_APP_SOCKET_CLIENT_BACKOFF},
+};
+use crate::{
+	diagnostics::Diagnostics,
+	executor::{ExecutorProcessor, SharedNsm},
+	handles::{Handles, PivotInfo},
+	io::{SocketAddress, TimeVal, TimeValLike},
+	log,
 	protocol::{
-		services::boot::{PivotConfig, RestartPolicy},
-		Processor, ProtocolPhase,
+		services::boot::{Manifest, PivotConfig, RestartPolicy},
+		status::{
+			BootEvent, PivotStatus, SharedBootTimeline, SharedPivotStatus,
+		},
+		Processor, ProtocolError, ProtocolPhase,
+		ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
 	},
-	server::SocketServer,
+	server::{ServerOptions, SocketServer, SocketServerHandle},
+	APP_CONFIG_FILE_ENV, EXECUTOR_SOCKET_ENV, PIVOT_INFO_FILE_ENV,
 };
 
 /// Delay for restarting the pivot app if the process exits.
@@ -28,7 +42,7 @@ pub const REAPER_EXIT_DELAY_IN_SECONDS: u64 = 3;
 /// and pivot binary.
 pub struct Reaper;
 impl Reaper {
-	/// Run the Reaper.
+	/// Run the Reaper. The enclave server listens on all of `addrs`.
 	///
 	/// # Panics
 	///
@@ -38,21 +52,198 @@ impl Reaper {
 	pub fn execute(
 		handles: &Handles,
 		nsm: Box<dyn NsmProvider + Send>,
-		addr: SocketAddress,
+		addrs: Vec<SocketAddress>,
+		app_addr: SocketAddress,
+		test_only_init_phase_override: Option<ProtocolPhase>,
+	) {
+		Self::execute_with_options(
+			handles,
+			nsm,
+			addrs,
+			app_addr,
+			test_only_init_phase_override,
+			ServerOptions::default(),
+			TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS),
+		);
+	}
+
+	/// Like [`Self::execute`], but spawn the enclave server with `options`
+	/// and give up on each send and receive to the enclave app after
+	/// `app_timeout`.
+	///
+	/// # Panics
+	///
+	/// - If spawning the pivot errors.
+	/// - If waiting for the pivot errors.
+	pub fn execute_with_options(
+		handles: &Handles,
+		nsm: Box<dyn NsmProvider + Send>,
+		addrs: Vec<SocketAddress>,
+		app_addr: SocketAddress,
+		test_only_init_phase_override: Option<ProtocolPhase>,
+		options: ServerOptions,
+		app_timeout: TimeVal,
+	) {
+		Self::install_diagnostics(handles);
+		let pivot_status = SharedPivotStatus::new();
+		let boot_timeline = SharedBootTimeline::default();
+		let nsm = SharedNsm::new(nsm);
+		let executor = Self::spawn_executor(handles, &nsm);
+		let processor = Processor::new(
+			Box::new(nsm),
+			handles.clone(),
+			app_addr,
+			test_only_init_phase_override,
+		)
+		.with_app_client_timeout(app_timeout)
+		.with_pivot_status(pivot_status.clone())
+		.with_boot_timeline(boot_timeline.clone());
+		let server =
+			match SocketServer::spawn_with_options(addrs, processor, options) {
+				Ok(server) => Some(server),
+				Err(err) => {
+					log::error(
+						"reaper",
+						format_args!(
+							"Reaper::execute failed to start server: {err:?}"
+						),
+					);
+					None
+				}
+			};
+
+		Self::supervise_pivot(handles, &pivot_status, &boot_timeline);
+
+		if let Some(server) = server {
+			server.shutdown();
+		}
+		if let Some(executor) = executor {
+			executor.shutdown();
+		}
+		log::info("reaper", "Reaper exiting ... ");
+	}
+
+	/// Like [`Self::execute`], but serve requests with the
+	/// [`AsyncSocketServer`], so requests to the enclave app can overlap with
+	/// each other and with other requests. Each send and receive to the
+	/// enclave app gives up after `app_timeout`.
+	///
+	/// # Panics
+	///
+	/// - If the async runtime cannot be started.
+	/// - If spawning the pivot errors.
+	/// - If waiting for the pivot errors.
+	#[cfg(feature = "async")]
+	pub fn execute_async(
+		handles: &Handles,
+		nsm: Box<dyn NsmProvider + Send>,
+		addrs: Vec<SocketAddress>,
 		app_addr: SocketAddress,
 		test_only_init_phase_override: Option<ProtocolPhase>,
+		app_timeout: TimeVal,
 	) {
-		let handles2 = handles.clone();
-		std::thread::spawn(move || {
-			let processor = Processor::new(
-				nsm,
-				handles2,
+		Self::install_diagnostics(handles);
+		let pivot_status = SharedPivotStatus::new();
+		let boot_timeline = SharedBootTimeline::default();
+		let nsm = SharedNsm::new(nsm);
+		let executor = Self::spawn_executor(handles, &nsm);
+		let app_client = AsyncClient::new(app_addr.clone(), app_timeout)
+			.with_backoff(ENCLAVE_APP_SOCKET_CLIENT_BACKOFF);
+		let processor = AsyncProcessor::new(
+			Processor::new(
+				Box::new(nsm),
+				handles.clone(),
 				app_addr,
 				test_only_init_phase_override,
-			);
-			SocketServer::listen(addr, processor).unwrap();
+			)
+			.with_app_client_timeout(app_timeout)
+			.with_pivot_status(pivot_status.clone())
+			.with_boot_timeline(boot_timeline.clone()),
+			app_client,
+		);
+
+		let (shutdown, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.expect("Failed to start the async runtime");
+		let server = std::thread::spawn(move || {
+			runtime.block_on(async move {
+				let servers: Vec<_> = addrs
+					.into_iter()
+					.map(|addr| {
+						tokio::spawn(AsyncSocketServer::listen(
+							addr,
+							processor.clone(),
+						))
+					})
+					.collect();
+
+				// Either an explicit shutdown or the reaper exiting
+				drop(shutdown_rx.await);
+				for server in servers {
+					// Dropping the listeners removes the socket files
+					server.abort();
+					drop(server.await);
+				}
+			});
 		});
 
+		Self::supervise_pivot(handles, &pivot_status, &boot_timeline);
+
+		shutdown.send(()).ok();
+		drop(server.join());
+		if let Some(executor) = executor {
+			executor.shutdown();
+		}
+		log::info("reaper", "Reaper exiting ... ");
+	}
+
+	/// Record panics of the enclave process, and log the panic of an earlier
+	/// run if there was one. See [`crate::diagnostics`].
+	fn install_diagnostics(handles: &Handles) {
+		let diagnostics = Diagnostics::new(handles.panic_report_path());
+		if let Some(report) = diagnostics.last_panic() {
+			log::warn(
+				"reaper",
+				format_args!("the enclave panicked before: {report}"),
+			);
+		}
+		diagnostics.install_panic_hook();
+	}
+
+	/// Start the [`crate::executor`] server for the pivot. Errors are logged
+	/// rather than stopping the enclave, since not every pivot uses it.
+	fn spawn_executor(
+		handles: &Handles,
+		nsm: &SharedNsm,
+	) -> Option<SocketServerHandle> {
+		let addr = SocketAddress::new_unix(&handles.executor_socket_path());
+		let processor =
+			ExecutorProcessor::new(Box::new(nsm.clone()), handles.clone());
+		match SocketServer::spawn(vec![addr], processor) {
+			Ok(executor) => Some(executor),
+			Err(err) => {
+				log::error(
+					"reaper",
+					format_args!(
+						"Reaper::execute failed to start executor: {err:?}"
+					),
+				);
+				None
+			}
+		}
+	}
+
+	/// Wait until everything needed to pivot exists, then run the pivot
+	/// according to its restart policy. Only returns if the pivot is not
+	/// restarted, or if it could not be given the pivot info and app config
+	/// of the current manifest.
+	fn supervise_pivot(
+		handles: &Handles,
+		pivot_status: &SharedPivotStatus,
+		boot_timeline: &SharedBootTimeline,
+	) {
 		loop {
 			if handles.quorum_key_exists()
 				&& handles.pivot_exists()
@@ -66,25 +257,50 @@ impl Reaper {
 			std::thread::sleep(std::time::Duration::from_secs(1));
 		}
 
-		println!("Reaper::execute about to spawn pivot");
+		log::info("reaper", "Reaper::execute about to spawn pivot");
 
-		let PivotConfig { args, restart, .. } = handles
+		let manifest = handles
 			.get_manifest_envelope()
 			.expect("Checked above that the manifest exists.")
-			.manifest
-			.pivot;
+			.manifest;
+
+		// Let the pivot know what manifest it is running under. Refuse to
+		// start it rather than have it run with another manifest's settings.
+		if let Err(err) = Self::put_pivot_info(handles, &manifest)
+			.and_then(|()| Self::put_app_config(handles, &manifest))
+		{
+			log::error(
+				"reaper",
+				format_args!("Reaper::execute refusing to spawn pivot: {err}"),
+			);
+			return;
+		}
+
+		let PivotConfig { args, restart, .. } = manifest.pivot;
 
 		let mut pivot = Command::new(handles.pivot_path());
 		pivot.args(&args[..]);
+		pivot.env(PIVOT_INFO_FILE_ENV, handles.pivot_info_path());
+		pivot.env(EXECUTOR_SOCKET_ENV, handles.executor_socket_path());
+		pivot.env(APP_CONFIG_FILE_ENV, handles.app_config_path());
+		let mut restarts = 0;
+		let mut run_pivot = |restarts: u32| {
+			let mut child = pivot.spawn().expect("Failed to spawn");
+			boot_timeline.record(BootEvent::PivotSpawned);
+			pivot_status.set(PivotStatus::Running { restarts });
+			let status =
+				child.wait().expect("Pivot executable never started...");
+			pivot_status
+				.set(PivotStatus::Exited { code: status.code(), restarts });
+			log::info(
+				"reaper",
+				format_args!("Pivot exited with status: {status}"),
+			);
+		};
+
 		match restart {
 			RestartPolicy::Always => loop {
-				let status = pivot
-					.spawn()
-					.expect("Failed to spawn")
-					.wait()
-					.expect("Pivot executable never started...");
-
-				println!("Pivot exited with status: {status}");
+				run_pivot(restarts);
 
 				// pause to ensure OS has enough time to clean up resources
 				// before restarting
@@ -92,22 +308,67 @@ impl Reaper {
 					REAPER_RESTART_DELAY_IN_SECONDS,
 				));
 
-				println!("Restarting pivot ...");
+				log::info("reaper", "Restarting pivot ...");
+				restarts += 1;
 			},
-			RestartPolicy::Never => {
-				let status = pivot
-					.spawn()
-					.expect("Failed to spawn")
-					.wait()
-					.expect("Pivot executable never started...");
-				println!("Pivot exited with status: {status}");
-			}
+			RestartPolicy::Never => run_pivot(restarts),
 		}
 
 		std::thread::sleep(std::time::Duration::from_secs(
 			REAPER_EXIT_DELAY_IN_SECONDS,
 		));
-		println!("Reaper exiting ... ");
+	}
+
+	/// Write the [`PivotInfo`] for `manifest`. The pivot info is write once,
+	/// so a file left over from an earlier boot is only accepted if it
+	/// matches.
+	///
+	/// Fails closed: the pivot, and `qos_net` egress policies, trust this
+	/// file, so on any error the pivot must not be started.
+	fn put_pivot_info(
+		handles: &Handles,
+		manifest: &Manifest,
+	) -> Result<(), String> {
+		match handles.put_pivot_info(manifest) {
+			Ok(()) => Ok(()),
+			Err(ProtocolError::CannotModifyPostPivotStatic) => {
+				match handles.get_pivot_info() {
+					Ok(info) if info == PivotInfo::from(manifest) => Ok(()),
+					Ok(_) => Err(format!(
+						"found stale pivot info for another manifest at {}",
+						handles.pivot_info_path()
+					)),
+					Err(err) => Err(format!(
+						"failed to read existing pivot info: {err:?}"
+					)),
+				}
+			}
+			Err(err) => Err(format!("failed to put pivot info: {err:?}")),
+		}
+	}
+
+	/// Write the app config from `manifest`, accepting a file left over from
+	/// an earlier boot only if it matches, like [`Self::put_pivot_info`].
+	fn put_app_config(
+		handles: &Handles,
+		manifest: &Manifest,
+	) -> Result<(), String> {
+		match handles.put_app_config(manifest) {
+			Ok(()) => Ok(()),
+			Err(ProtocolError::CannotModifyPostPivotStatic) => {
+				match handles.get_app_config() {
+					Ok(config) if config == manifest.pivot.app_config => Ok(()),
+					Ok(_) => Err(format!(
+						"found stale app config for another manifest at {}",
+						handles.app_config_path()
+					)),
+					Err(err) => Err(format!(
+						"failed to read existing app config: {err:?}"
+					)),
+				}
+			}
+			Err(err) => Err(format!("failed to put app config: {err:?}")),
+		}
 	}
 }
 
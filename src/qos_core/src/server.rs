@@ -1,9 +1,57 @@
 //! Streaming socket based server for use in an enclave. Listens for connections
 //! from [`crate::client::Client`].
 
-use std::marker::PhantomData;
+use std::{
+	marker::PhantomData,
+	sync::{Arc, Mutex},
+	thread::JoinHandle,
+	time::Instant,
+};
 
-use crate::io::{self, Listener, SocketAddress};
+#[cfg(any(feature = "mock", test))]
+use crate::io::memory::MemoryListener;
+use crate::{
+	io::{
+		self,
+		metrics::{ErrorKind, Metrics, NoopMetrics},
+		Acceptor, Connection, Listener, SocketAddress, SocketPermissions,
+		TimeVal, TimeValLike,
+	},
+	log,
+};
+
+/// Default timeout for each send and receive on a connection accepted by the
+/// [`SocketServer`]. Since the timeout applies to each underlying system call,
+/// this is the longest a connection may stay idle before it is closed.
+pub const SOCKET_SERVER_TIMEOUT_SECS: i64 = 5;
+
+/// Options for [`SocketServer::spawn_with_options`].
+#[derive(Clone)]
+pub struct ServerOptions {
+	/// Timeout for each send and receive. Defaults to
+	/// [`SOCKET_SERVER_TIMEOUT_SECS`].
+	pub timeout: TimeVal,
+	/// Receives connection and request events. Defaults to [`NoopMetrics`].
+	pub metrics: Arc<dyn Metrics>,
+	/// Permissions to apply to unix sockets right after binding them.
+	/// Defaults to leaving them as created.
+	pub permissions: Option<SocketPermissions>,
+	/// Close connections that have not sent anything this long after being
+	/// accepted, independent of `timeout`. Defaults to only applying
+	/// `timeout`.
+	pub idle_timeout: Option<TimeVal>,
+}
+
+impl Default for ServerOptions {
+	fn default() -> Self {
+		Self {
+			timeout: TimeVal::seconds(SOCKET_SERVER_TIMEOUT_SECS),
+			metrics: Arc::new(NoopMetrics),
+			permissions: None,
+			idle_timeout: None,
+		}
+	}
+}
 
 /// Error variants for [`SocketServer`]
 #[derive(Debug)]
@@ -37,22 +85,405 @@ impl<R: RequestProcessor> SocketServer<R> {
 	/// Listen and respond to incoming requests with the given `processor`.
 	pub fn listen(
 		addr: SocketAddress,
-		mut processor: R,
+		processor: R,
+	) -> Result<(), SocketServerError> {
+		Self::listen_with_timeout(
+			addr,
+			processor,
+			TimeVal::seconds(SOCKET_SERVER_TIMEOUT_SECS),
+		)
+	}
+
+	/// Like [`Self::listen`], but with a custom `timeout` for each send and
+	/// receive so a stalled client cannot block the server indefinitely.
+	pub fn listen_with_timeout(
+		addr: SocketAddress,
+		processor: R,
+		timeout: TimeVal,
 	) -> Result<(), SocketServerError> {
-		println!("`SocketServer` listening on {addr:?}");
+		log::info(
+			"server",
+			format_args!("`SocketServer` listening on {addr:?}"),
+		);
 
 		let listener = Listener::listen(addr)?;
+		Self::serve(
+			&listener,
+			&Mutex::new(processor),
+			timeout,
+			None,
+			&NoopMetrics,
+		);
+
+		Ok(())
+	}
+
+	/// Like [`Self::listen`], but accept connections from an in memory
+	/// `listener` instead of a socket. Returns once every
+	/// [`crate::io::memory::MemoryConnector`] for `listener` has been
+	/// dropped.
+	#[cfg(any(feature = "mock", test))]
+	pub fn listen_in_memory(listener: &MemoryListener, processor: R) {
+		Self::serve(
+			listener,
+			&Mutex::new(processor),
+			TimeVal::seconds(SOCKET_SERVER_TIMEOUT_SECS),
+			None,
+			&NoopMetrics,
+		);
+	}
+
+	/// Like [`Self::listen`], but listen on all of `addrs` at once, serving
+	/// requests from background threads. Requests are still processed one at
+	/// a time, regardless of which address they arrive on.
+	///
+	/// The returned [`SocketServerHandle`] can be used to stop the server.
+	/// Dropping the handle without calling [`SocketServerHandle::shutdown`]
+	/// leaves the server running.
+	pub fn spawn(
+		addrs: Vec<SocketAddress>,
+		processor: R,
+	) -> Result<SocketServerHandle, SocketServerError>
+	where
+		R: Send + 'static,
+	{
+		Self::spawn_with_options(addrs, processor, ServerOptions::default())
+	}
+
+	/// Like [`Self::spawn`], but with custom [`ServerOptions`].
+	pub fn spawn_with_options(
+		addrs: Vec<SocketAddress>,
+		processor: R,
+		options: ServerOptions,
+	) -> Result<SocketServerHandle, SocketServerError>
+	where
+		R: Send + 'static,
+	{
+		let ServerOptions { timeout, metrics, permissions, idle_timeout } =
+			options;
+
+		let listeners = addrs
+			.into_iter()
+			.map(|addr| {
+				log::info(
+					"server",
+					format_args!("`SocketServer` listening on {addr:?}"),
+				);
+				Listener::listen_with_permissions(addr, permissions.as_ref())
+					.map(Arc::new)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let processor = Arc::new(Mutex::new(processor));
+		let threads = listeners
+			.iter()
+			.map(|listener| {
+				let listener = listener.clone();
+				let processor = processor.clone();
+				let metrics = metrics.clone();
+				std::thread::spawn(move || {
+					Self::serve(
+						&*listener,
+						&processor,
+						timeout,
+						idle_timeout,
+						&*metrics,
+					);
+				})
+			})
+			.collect();
+
+		Ok(SocketServerHandle { listeners, threads })
+	}
+
+	/// Serve requests one at a time until the listener is shut down.
+	fn serve<A: Acceptor>(
+		listener: &A,
+		processor: &Mutex<R>,
+		timeout: TimeVal,
+		idle_timeout: Option<TimeVal>,
+		metrics: &dyn Metrics,
+	) {
+		loop {
+			let stream = match listener.accept() {
+				Ok(stream) => stream,
+				Err(_) if listener.is_shutdown() => break,
+				Err(_) => {
+					metrics.error(ErrorKind::Accept);
+					break;
+				}
+			};
+			metrics.connection_accepted();
+
+			if let Err(err) = stream.set_timeout(timeout) {
+				log::error(
+					"server",
+					format_args!("Server::listen error: {err:?}"),
+				);
+				metrics.error((&err).into());
+				continue;
+			}
+
+			// Don't let a connection that never sends a request hold up the
+			// listener until its receive times out.
+			if let Some(idle_timeout) = idle_timeout {
+				match stream.wait_readable(idle_timeout) {
+					Ok(true) => {}
+					Ok(false) => {
+						metrics.error(ErrorKind::Timeout);
+						continue;
+					}
+					Err(err) => {
+						log::error(
+							"server",
+							format_args!("Server::listen error: {err:?}"),
+						);
+						metrics.error((&err).into());
+						continue;
+					}
+				}
+			}
 
-		for stream in listener {
 			match stream.recv() {
 				Ok(payload) => {
-					let response = processor.process(payload);
-					let _ = stream.send(&response);
+					let received_at = Instant::now();
+					metrics.request_received(payload.len());
+					let response = processor
+						.lock()
+						.expect("a request processor panicked. qed.")
+						.process(payload);
+					match stream.send(&response) {
+						Ok(()) => metrics.response_sent(
+							response.len(),
+							received_at.elapsed(),
+						),
+						Err(err) => metrics.error((&err).into()),
+					}
+				}
+				Err(err) => {
+					log::error(
+						"server",
+						format_args!("Server::listen error: {err:?}"),
+					);
+					metrics.error((&err).into());
 				}
-				Err(err) => eprintln!("Server::listen error: {err:?}"),
 			}
 		}
+	}
+}
 
-		Ok(())
+/// Handle on a [`SocketServer`] started with [`SocketServer::spawn`].
+pub struct SocketServerHandle {
+	listeners: Vec<Arc<Listener>>,
+	threads: Vec<JoinHandle<()>>,
+}
+
+impl SocketServerHandle {
+	/// Stop accepting new connections, wait for the request currently being
+	/// processed (if any) to finish, and remove any unix socket files.
+	pub fn shutdown(self) {
+		for listener in &self.listeners {
+			listener.shutdown();
+		}
+		for thread in self.threads {
+			// A panic in the processor has already been reported by the
+			// default panic hook, so there is nothing left to do with it here.
+			let _ = thread.join();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::Path;
+
+	use super::*;
+	use crate::{
+		client::{Client, ClientError},
+		io::IOError,
+	};
+
+	struct EchoProcessor;
+	impl RequestProcessor for EchoProcessor {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[test]
+	fn spawn_with_options_sets_socket_permissions() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let path = "./spawn_with_options_sets_socket_permissions.sock";
+		let addr = SocketAddress::new_unix(path);
+		let permissions = SocketPermissions {
+			mode: 0o600,
+			uid: Some(nix::unistd::getuid().as_raw()),
+			gid: None,
+		};
+		let handle = SocketServer::spawn_with_options(
+			vec![addr.clone()],
+			EchoProcessor,
+			ServerOptions {
+				permissions: Some(permissions),
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		let mode = std::fs::metadata(path).unwrap().permissions().mode();
+		assert_eq!(mode & 0o777, 0o600);
+
+		let client = Client::new(addr.clone(), TimeVal::seconds(1))
+			.with_socket_permissions(permissions);
+		assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+
+		// A client expecting different permissions refuses to connect
+		let client =
+			Client::new(addr, TimeVal::seconds(1)).with_socket_permissions(
+				SocketPermissions { mode: 0o660, ..permissions },
+			);
+		assert!(matches!(
+			client.send(b"ping"),
+			Err(ClientError::IOError(IOError::UnexpectedSocketPermissions))
+		));
+
+		handle.shutdown();
+	}
+
+	#[test]
+	fn handles_streamed_requests() {
+		let path = "./handles_streamed_requests.sock";
+		let addr = SocketAddress::new_unix(path);
+		let handle =
+			SocketServer::spawn(vec![addr.clone()], EchoProcessor).unwrap();
+
+		let client = Client::new(addr, TimeVal::seconds(1));
+		let mut request = client.stream_request().unwrap();
+		let chunk = vec![7; 1024 * 1024];
+		for _ in 0..3 {
+			request.send_chunk(&chunk).unwrap();
+		}
+		let response = request.finish(b"end").unwrap();
+
+		assert_eq!(response.len(), 3 * chunk.len() + 3);
+		assert!(response.ends_with(b"end"));
+
+		// An abandoned request is never processed
+		let mut request = client.stream_request().unwrap();
+		request.send_chunk(b"partial").unwrap();
+		drop(request);
+		assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+
+		handle.shutdown();
+	}
+
+	#[test]
+	fn shutdown_stops_server_and_removes_socket() {
+		let path = "./shutdown_stops_server_and_removes_socket.sock";
+		let addr = SocketAddress::new_unix(path);
+		let handle =
+			SocketServer::spawn(vec![addr.clone()], EchoProcessor).unwrap();
+		assert!(Path::new(path).exists());
+
+		let client = Client::new(addr, TimeVal::seconds(1));
+		assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+
+		handle.shutdown();
+
+		assert!(!Path::new(path).exists());
+		assert!(client.send(b"ping").is_err());
+	}
+
+	#[test]
+	fn spawn_listens_on_all_addrs() {
+		let addrs = vec![
+			SocketAddress::new_unix("./spawn_listens_on_all_addrs.0.sock"),
+			SocketAddress::new_unix("./spawn_listens_on_all_addrs.1.sock"),
+		];
+		let handle = SocketServer::spawn(addrs.clone(), EchoProcessor).unwrap();
+
+		for addr in addrs {
+			let client = Client::new(addr, TimeVal::seconds(1));
+			assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+		}
+
+		handle.shutdown();
+	}
+
+	#[test]
+	fn spawn_with_options_reports_metrics() {
+		use std::io::Write;
+
+		use crate::io::{
+			metrics::{Counters, ErrorKind},
+			Stream,
+		};
+
+		let addr = SocketAddress::new_unix(
+			"./spawn_with_options_reports_metrics.sock",
+		);
+		let counters = Arc::new(Counters::default());
+		let handle = SocketServer::spawn_with_options(
+			vec![addr.clone()],
+			EchoProcessor,
+			ServerOptions { metrics: counters.clone(), ..Default::default() },
+		)
+		.unwrap();
+
+		let client = Client::new(addr.clone(), TimeVal::seconds(1));
+		assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+
+		// Send a partial frame header and hang up
+		let mut stream = Stream::connect(&addr, TimeVal::seconds(1)).unwrap();
+		stream.write_all(&[1]).unwrap();
+		drop(stream);
+
+		// Requests are served in order, so once this returns the partial
+		// frame has been handled
+		assert_eq!(client.send(b"pong").unwrap(), b"pong".to_vec());
+		handle.shutdown();
+
+		let snapshot = counters.snapshot();
+		assert_eq!(snapshot.connections, 3);
+		assert_eq!(snapshot.requests, 2);
+		assert_eq!(snapshot.bytes_received, 8);
+		assert_eq!(snapshot.bytes_sent, 8);
+		assert_eq!(snapshot.latency.iter().sum::<u64>(), 2);
+		assert_eq!(snapshot.errors_of(ErrorKind::Framing), 1);
+		assert_eq!(snapshot.errors_of(ErrorKind::Accept), 0);
+	}
+
+	#[test]
+	fn spawn_with_options_reaps_idle_connections() {
+		use std::time::Duration;
+
+		use crate::io::Stream;
+
+		let addr = SocketAddress::new_unix(
+			"./spawn_with_options_reaps_idle_connections.sock",
+		);
+		let handle = SocketServer::spawn_with_options(
+			vec![addr.clone()],
+			EchoProcessor,
+			ServerOptions {
+				idle_timeout: Some(TimeVal::milliseconds(200)),
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		// A connection that never sends anything is closed well before the
+		// receive timeout, instead of blocking the next client
+		let start = Instant::now();
+		let idle = Stream::connect(&addr, TimeVal::seconds(10)).unwrap();
+		assert!(matches!(idle.recv(), Err(IOError::RecvConnectionClosed)));
+		assert!(start.elapsed() < Duration::from_secs(2));
+
+		// A client that sends its request right away is served as usual
+		let client = Client::new(addr, TimeVal::seconds(1));
+		assert_eq!(client.send(b"ping").unwrap(), b"ping".to_vec());
+
+		handle.shutdown();
 	}
 }
@@ -0,0 +1,121 @@
+//! Clocks that time dependent logic reads through, so it can be tested
+//! deterministically with a [`MockClock`].
+//!
+//! Inside the enclave the trusted source of wall clock time is the NSM, see
+//! [`crate::protocol::Processor::with_time_source`]. Elsewhere,
+//! [`SystemClock`] reads the system clock.
+
+#[cfg(any(feature = "mock", test))]
+use std::sync::{Mutex, PoisonError};
+use std::{
+	sync::OnceLock,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use qos_nsm::nitro::AttestError;
+
+use crate::protocol::ProtocolError;
+
+/// Source of wall clock and monotonic time.
+pub trait TimeSource: Send + Sync {
+	/// Wall clock time since the unix epoch.
+	fn now(&self) -> Result<Duration, ProtocolError>;
+
+	/// Monotonic time since an unspecified point, for measuring how long
+	/// something took.
+	fn monotonic(&self) -> Duration;
+
+	/// Block the current thread for `duration`.
+	fn sleep(&self, duration: Duration) {
+		std::thread::sleep(duration);
+	}
+
+	/// [`Self::now`] in whole seconds.
+	fn now_secs(&self) -> Result<u64, ProtocolError> {
+		self.now().map(|now| now.as_secs())
+	}
+
+	/// [`Self::now`] in whole milliseconds.
+	fn now_ms(&self) -> Result<u64, ProtocolError> {
+		let millis = self.now()?.as_millis();
+		u64::try_from(millis).map_err(|_| AttestError::InvalidTimeStamp.into())
+	}
+}
+
+/// [`TimeSource`] reading the system clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+	fn now(&self) -> Result<Duration, ProtocolError> {
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_err(|_| AttestError::InvalidTimeStamp.into())
+	}
+
+	fn monotonic(&self) -> Duration {
+		static START: OnceLock<Instant> = OnceLock::new();
+		START.get_or_init(Instant::now).elapsed()
+	}
+}
+
+/// [`TimeSource`] that only moves when told to. Sleeping advances it instead
+/// of blocking, so code that waits runs instantly in tests.
+#[cfg(any(feature = "mock", test))]
+#[derive(Debug, Default)]
+pub struct MockClock {
+	now: Mutex<Duration>,
+}
+
+#[cfg(any(feature = "mock", test))]
+impl MockClock {
+	/// Create a new instance of [`Self`] reading `now` since the unix epoch.
+	#[must_use]
+	pub fn new(now: Duration) -> Self {
+		Self { now: Mutex::new(now) }
+	}
+
+	/// Move the clock forward by `duration`.
+	pub fn advance(&self, duration: Duration) {
+		*self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+	}
+}
+
+#[cfg(any(feature = "mock", test))]
+impl TimeSource for MockClock {
+	fn now(&self) -> Result<Duration, ProtocolError> {
+		Ok(self.monotonic())
+	}
+
+	fn monotonic(&self) -> Duration {
+		*self.now.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn sleep(&self, duration: Duration) {
+		self.advance(duration);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn mock_clock_moves_only_when_told_to() {
+		let clock = MockClock::new(Duration::from_millis(1_500));
+		assert_eq!(clock.now_secs().unwrap(), 1);
+		assert_eq!(clock.now_ms().unwrap(), 1_500);
+
+		clock.sleep(Duration::from_secs(60));
+		clock.advance(Duration::from_millis(500));
+		assert_eq!(clock.now_secs().unwrap(), 62);
+		assert_eq!(clock.monotonic(), Duration::from_secs(62));
+	}
+
+	#[test]
+	fn system_clock_is_after_the_epoch() {
+		assert!(SystemClock.now_secs().unwrap() > 1_700_000_000);
+		let before = SystemClock.monotonic();
+		assert!(SystemClock.monotonic() >= before);
+	}
+}
@@ -32,13 +32,13 @@ fuzz_target!(|fuzzerdata: FuzzShamirStruct| {
 
 	match all_shares_res {
 		Err(_) => {}
-		Ok(all_shares) => {
+		Ok((all_shares, _)) => {
 			// Reconstruct with all the shares
 			let shares = all_shares.clone();
 			let reconstructed =
 				shares_reconstruct(&shares).expect("should succeed");
 			// expect the reconstruction to work
-			assert_eq!(secret.to_vec(), reconstructed);
+			assert_eq!(secret.to_vec(), *reconstructed);
 
 			// Reconstruct with enough shares
 			let shares = &all_shares[..k];
@@ -46,7 +46,7 @@ fuzz_target!(|fuzzerdata: FuzzShamirStruct| {
 				shares_reconstruct(shares).expect("should succeed");
 
 			// expect the reconstruction to work
-			assert_eq!(secret.to_vec(), reconstructed);
+			assert_eq!(secret.to_vec(), *reconstructed);
 
 			// Reconstruct with not enough shares
 			let shares = &all_shares[..(k - 1)];
@@ -62,7 +62,7 @@ fuzz_target!(|fuzzerdata: FuzzShamirStruct| {
 				Ok(reconstructed) => {
 					// if we managed to reconstruct the secret with less than the minimum number of shares
 					// the something is wrong, or we have a random collision
-					if reconstructed == secret.to_vec() {
+					if *reconstructed == secret.to_vec() {
 						panic!("reconstructed the secret with less than k shares, this should not happen")
 					}
 				}
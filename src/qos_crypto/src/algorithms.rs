@@ -0,0 +1,106 @@
+//! Identifiers of the cryptographic algorithms `QuorumOS` uses, and which of
+//! them are approved.
+//!
+//! With the `approved-only` feature, only approved algorithms are offered:
+//! the others are compiled out or rejected with
+//! [`QosCryptoError::UnapprovedAlgorithm`]. A manifest lists the algorithms
+//! its enclave uses, so a deployment bound to an approved algorithm set can
+//! prove what was used.
+//!
+//! Shamir secret sharing is not one of these algorithms: it splits the quorum
+//! key among the share set, which then protect their shares with the
+//! algorithms below.
+
+use crate::QosCryptoError;
+
+/// Whether this build only offers approved algorithms.
+pub const APPROVED_ONLY: bool = cfg!(feature = "approved-only");
+
+/// A cryptographic algorithm used by `QuorumOS`.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	borsh::BorshSerialize,
+	borsh::BorshDeserialize,
+	serde::Serialize,
+	serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Algorithm {
+	/// SHA256.
+	Sha256,
+	/// SHA384.
+	Sha384,
+	/// SHA512.
+	Sha512,
+	/// HMAC-SHA512.
+	HmacSha512,
+	/// HKDF-SHA256.
+	HkdfSha256,
+	/// HKDF-SHA512.
+	HkdfSha512,
+	/// PBKDF2-HMAC-SHA256, for passphrase encrypted master seeds.
+	Pbkdf2HmacSha256,
+	/// ECDH over NIST-P256.
+	EcdhP256,
+	/// ECDSA over NIST-P256 with SHA256.
+	EcdsaP256Sha256,
+	/// AES 256 GCM.
+	AesGcm256,
+	/// BIP-39 recovery phrases, which take a secret out of its key file as
+	/// plain words.
+	Bip39Mnemonic,
+}
+
+impl Algorithm {
+	/// Every algorithm.
+	pub const ALL: [Self; 11] = [
+		Self::Sha256,
+		Self::Sha384,
+		Self::Sha512,
+		Self::HmacSha512,
+		Self::HkdfSha256,
+		Self::HkdfSha512,
+		Self::Pbkdf2HmacSha256,
+		Self::EcdhP256,
+		Self::EcdsaP256Sha256,
+		Self::AesGcm256,
+		Self::Bip39Mnemonic,
+	];
+
+	/// Whether the algorithm is in the approved set.
+	#[must_use]
+	pub fn is_approved(self) -> bool {
+		!matches!(self, Self::Bip39Mnemonic)
+	}
+}
+
+/// Check that `algorithm` can be used by this build: any algorithm can be,
+/// unless the build is [`APPROVED_ONLY`].
+pub fn require_approved(algorithm: Algorithm) -> Result<(), QosCryptoError> {
+	if APPROVED_ONLY && !algorithm.is_approved() {
+		Err(QosCryptoError::UnapprovedAlgorithm(algorithm))
+	} else {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn require_approved_follows_the_feature() {
+		for algorithm in Algorithm::ALL {
+			let allowed = !APPROVED_ONLY || algorithm.is_approved();
+			assert_eq!(require_approved(algorithm).is_ok(), allowed);
+		}
+		assert!(!Algorithm::Bip39Mnemonic.is_approved());
+		assert!(Algorithm::EcdsaP256Sha256.is_approved());
+	}
+}
@@ -8,9 +8,12 @@
 use std::fmt;
 
 use sha2::Digest;
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 
+pub mod algorithms;
 pub mod n_choose_k;
+pub mod self_test;
 pub mod shamir;
 
 /// Errors for this crate
@@ -18,6 +21,34 @@ pub mod shamir;
 pub enum QosCryptoError {
 	/// Errors from vsss-rs lib
 	Vsss(vsss_rs::Error),
+	/// The share does not match its commitment.
+	ShareCommitmentMismatch,
+	/// The digest is tagged with an algorithm this version does not know.
+	UnknownDigestAlgorithm(u8),
+	/// The digest is not the length of a digest of its algorithm.
+	InvalidDigestLength,
+	/// The algorithm is not approved, and this build only offers approved
+	/// algorithms.
+	UnapprovedAlgorithm(algorithms::Algorithm),
+	/// Fewer shares were given than are needed to reconstruct the secret.
+	NotEnoughShares {
+		/// Number of shares given.
+		got: usize,
+		/// Number of shares needed.
+		needed: usize,
+	},
+	/// A share is too short to hold any of the secret, or the shares are not
+	/// all the same length.
+	InvalidShareLength,
+	/// A share has 0 as its identifier, which is where the secret is.
+	InvalidShareIdentifier,
+	/// Two shares have the same identifier.
+	DuplicateShareIdentifier(u8),
+	/// The share with the given identifier is not a share of the same secret
+	/// as the others.
+	InconsistentShares(u8),
+	/// A share commitment is not a valid compressed P256 point.
+	InvalidShareCommitment,
 }
 
 impl fmt::Display for QosCryptoError {
@@ -50,6 +81,117 @@ pub fn sha_512(buf: &[u8]) -> [u8; 64] {
 	hasher.finalize().into()
 }
 
+/// Compare `a` and `b` in constant time, so how long the comparison takes
+/// does not reveal how much of an expected value was guessed right. Slices of
+/// different lengths are not equal.
+#[must_use]
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+	a.ct_eq(b).into()
+}
+
+/// Hash algorithm of a [`TaggedDigest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+	/// SHA256.
+	Sha256,
+	/// SHA384.
+	Sha384,
+	/// SHA512.
+	Sha512,
+}
+
+impl DigestAlgorithm {
+	/// Identifier of the algorithm in a serialized [`TaggedDigest`].
+	#[must_use]
+	pub fn id(self) -> u8 {
+		match self {
+			Self::Sha256 => 1,
+			Self::Sha384 => 2,
+			Self::Sha512 => 3,
+		}
+	}
+
+	/// The algorithm with the given identifier.
+	pub fn from_id(id: u8) -> Result<Self, QosCryptoError> {
+		match id {
+			1 => Ok(Self::Sha256),
+			2 => Ok(Self::Sha384),
+			3 => Ok(Self::Sha512),
+			id => Err(QosCryptoError::UnknownDigestAlgorithm(id)),
+		}
+	}
+
+	/// Length of a digest made with the algorithm.
+	#[must_use]
+	pub fn digest_len(self) -> usize {
+		match self {
+			Self::Sha256 => 32,
+			Self::Sha384 => 48,
+			Self::Sha512 => 64,
+		}
+	}
+
+	/// Create a digest of `buf` with the algorithm.
+	#[must_use]
+	pub fn digest(self, buf: &[u8]) -> TaggedDigest {
+		let digest = match self {
+			Self::Sha256 => sha_256(buf).to_vec(),
+			Self::Sha384 => sha_384(buf).to_vec(),
+			Self::Sha512 => sha_512(buf).to_vec(),
+		};
+
+		TaggedDigest { algorithm: self, digest }
+	}
+}
+
+/// A digest together with the algorithm it was made with, so a digest can
+/// be checked without knowing the algorithm up front.
+///
+/// Serialized as the algorithm identifier followed by the digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedDigest {
+	algorithm: DigestAlgorithm,
+	digest: Vec<u8>,
+}
+
+impl TaggedDigest {
+	/// Algorithm the digest was made with.
+	#[must_use]
+	pub fn algorithm(&self) -> DigestAlgorithm {
+		self.algorithm
+	}
+
+	/// The digest, without the algorithm identifier.
+	#[must_use]
+	pub fn digest(&self) -> &[u8] {
+		&self.digest
+	}
+
+	/// Whether this is the digest of `buf`.
+	#[must_use]
+	pub fn matches(&self, buf: &[u8]) -> bool {
+		ct_eq(self.algorithm.digest(buf).digest(), &self.digest)
+	}
+
+	/// Serialize as the algorithm identifier followed by the digest.
+	#[must_use]
+	pub fn to_bytes(&self) -> Vec<u8> {
+		[&[self.algorithm.id()], self.digest.as_slice()].concat()
+	}
+
+	/// Deserialize from [`Self::to_bytes`].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, QosCryptoError> {
+		let (id, digest) =
+			bytes.split_first().ok_or(QosCryptoError::InvalidDigestLength)?;
+		let algorithm = DigestAlgorithm::from_id(*id)?;
+		if digest.len() != algorithm.digest_len() {
+			return Err(QosCryptoError::InvalidDigestLength);
+		}
+
+		Ok(Self { algorithm, digest: digest.to_vec() })
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -81,4 +223,46 @@ mod test {
 			"ed1cd50fc10f1129894f38f0dfa2d00642cfc7302cbf11ec22303d65697e18eb7594a03e2b8b9d3aac58b4b8c9af7d45335ecd34dc779ab1d3516c3e12bd171e"
 		);
 	}
+
+	#[test]
+	fn ct_eq_works() {
+		assert!(ct_eq(b"", b""));
+		assert!(ct_eq(&sha_256(b"a"), &sha_256(b"a")));
+		assert!(!ct_eq(&sha_256(b"a"), &sha_256(b"b")));
+		assert!(!ct_eq(&sha_256(b"a"), &sha_256(b"a")[..31]));
+	}
+
+	#[test]
+	fn tagged_digest_round_trip() {
+		let msg = b"test-msg";
+
+		for algorithm in [
+			DigestAlgorithm::Sha256,
+			DigestAlgorithm::Sha384,
+			DigestAlgorithm::Sha512,
+		] {
+			let tagged = algorithm.digest(msg);
+			assert_eq!(tagged.digest().len(), algorithm.digest_len());
+			assert!(tagged.matches(msg));
+			assert!(!tagged.matches(b"other-msg"));
+
+			let bytes = tagged.to_bytes();
+			assert_eq!(bytes[0], algorithm.id());
+			assert_eq!(TaggedDigest::from_bytes(&bytes).unwrap(), tagged);
+			assert_eq!(
+				TaggedDigest::from_bytes(&bytes[..bytes.len() - 1]),
+				Err(QosCryptoError::InvalidDigestLength)
+			);
+		}
+		assert_eq!(DigestAlgorithm::Sha512.digest(msg).digest(), sha_512(msg));
+
+		assert_eq!(
+			TaggedDigest::from_bytes(&[0xff; 33]),
+			Err(QosCryptoError::UnknownDigestAlgorithm(0xff))
+		);
+		assert_eq!(
+			TaggedDigest::from_bytes(&[]),
+			Err(QosCryptoError::InvalidDigestLength)
+		);
+	}
 }
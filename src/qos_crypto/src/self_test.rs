@@ -0,0 +1,72 @@
+//! Known-answer tests of the primitives of this crate, to check at runtime
+//! that they work on the machine they run on.
+//!
+//! Each test returns whether it passed. A test never panics, so a failure can
+//! be reported rather than taking down the caller.
+
+use crate::{ct_eq, sha_256, sha_384, sha_512, shamir};
+
+/// Message of the FIPS 180-2 SHA-2 examples.
+const SHA2_MESSAGE: &[u8] = b"abc";
+const SHA256_ABC: &str =
+	"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+const SHA384_ABC: &str = "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7";
+const SHA512_ABC: &str = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+const SHAMIR_SECRET: &[u8] = b"qos shamir self test secret";
+const SHAMIR_SHARE_COUNT: usize = 5;
+const SHAMIR_THRESHOLD: usize = 3;
+
+/// Check SHA256, SHA384 and SHA512 against the FIPS 180-2 examples.
+#[must_use]
+pub fn sha2_known_answer() -> bool {
+	known_answer(&sha_256(SHA2_MESSAGE), SHA256_ABC)
+		&& known_answer(&sha_384(SHA2_MESSAGE), SHA384_ABC)
+		&& known_answer(&sha_512(SHA2_MESSAGE), SHA512_ABC)
+}
+
+/// Split a fixed secret, check every share against the commitments and
+/// reconstruct the secret from two different sets of threshold shares.
+#[must_use]
+pub fn shamir_round_trip() -> bool {
+	let Ok((shares, commitments)) = shamir::shares_generate(
+		SHAMIR_SECRET,
+		SHAMIR_SHARE_COUNT,
+		SHAMIR_THRESHOLD,
+	) else {
+		return false;
+	};
+
+	let verified = shares
+		.iter()
+		.all(|share| shamir::share_verify(share, &commitments).is_ok());
+	let reconstructs = |shares: &[Vec<u8>]| {
+		shamir::shares_reconstruct(shares)
+			.is_ok_and(|secret| ct_eq(&secret, SHAMIR_SECRET))
+	};
+
+	verified
+		&& reconstructs(&shares[..SHAMIR_THRESHOLD])
+		&& reconstructs(&shares[SHAMIR_SHARE_COUNT - SHAMIR_THRESHOLD..])
+}
+
+fn known_answer(actual: &[u8], expected_hex: &str) -> bool {
+	qos_hex::decode(expected_hex).is_ok_and(|expected| ct_eq(actual, &expected))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn self_tests_pass() {
+		assert!(sha2_known_answer());
+		assert!(shamir_round_trip());
+	}
+
+	#[test]
+	fn known_answer_rejects_a_wrong_digest() {
+		assert!(!known_answer(&sha_256(b"abd"), SHA256_ABC));
+		assert!(!known_answer(&sha_256(SHA2_MESSAGE), SHA512_ABC));
+	}
+}
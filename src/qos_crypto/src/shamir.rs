@@ -1,11 +1,62 @@
-//! Shamir Secret Sharing module. We use the [`vsss-rs`](https://crates.io/crates/vsss-rs)
+//! Shamir Secret Sharing module.
+//!
+//! [`shares_generate`] splits secrets over the scalar field of P256 and
+//! makes Pedersen commitments to the coefficients of the sharing polynomials.
+//! [`share_verify`] checks a share against the commitments, which pins a
+//! corrupted or mixed-up share to its holder and proves that the dealer's
+//! shares are all shares of the same secret. Pedersen commitments reveal
+//! nothing about the secret, however few bytes it has.
+//!
+//! Shares made with earlier versions, split over GF(256) with
+//! [`vsss-rs`](https://crates.io/crates/vsss-rs), still reconstruct but have
+//! no commitments to verify against.
+use std::sync::OnceLock;
+
+use p256::{
+	elliptic_curve::{
+		ff::{Field, PrimeField},
+		group::GroupEncoding,
+		hash2curve::{ExpandMsgXmd, GroupDigest},
+	},
+	FieldBytes, NistP256, ProjectivePoint, Scalar,
+};
 use rand_core::OsRng;
+use sha2::Sha256;
 use vsss_rs::Gf256;
+use zeroize::Zeroizing;
+
+use crate::{ct_eq, QosCryptoError};
+
+/// Commitment to a coefficient of a sharing polynomial, made by
+/// [`shares_generate`]: a compressed P256 point.
+pub type ShareCommitment = [u8; 33];
 
-use crate::QosCryptoError;
+/// First byte of the shares made by [`shares_generate`]. Shares split over
+/// GF(256) start with their identifier, which is never 0.
+pub const PEDERSEN_SHARE_TAG: u8 = 0;
+
+/// Bytes of the secret shared by each polynomial, few enough to always be
+/// less than the order of P256.
+const CHUNK_LEN: usize = 31;
+/// Length of an encoded scalar.
+const SCALAR_LEN: usize = 32;
+/// Tag, identifier and big endian `u32` length of the secret.
+const HEADER_LEN: usize = 6;
+/// Domain separation tag to hash to the second commitment generator.
+const GENERATOR_DST: &[u8] = b"QOS-SHAMIR-PEDERSEN-V1_XMD:SHA-256_SSWU_RO_";
 
 /// Generate `share_count` shares requiring `threshold` shares to reconstruct.
 ///
+/// The secret is shared in chunks of 31 bytes, each with its own polynomial
+/// of degree `threshold - 1`. A share is [`PEDERSEN_SHARE_TAG`], its
+/// identifier, the length of the secret and, for each chunk, the evaluation
+/// of the chunk's polynomial and of a random blinding polynomial at the
+/// identifier.
+///
+/// Returns the shares and, to check shares with [`share_verify`], the
+/// commitments `a·G + b·H` to each pair of coefficients, `threshold` per
+/// chunk.
+///
 /// Known limitations:
 /// threshold >= 2
 /// `share_count` <= 255
@@ -13,20 +64,414 @@ pub fn shares_generate(
 	secret: &[u8],
 	share_count: usize,
 	threshold: usize,
-) -> Result<Vec<Vec<u8>>, QosCryptoError> {
-	Gf256::split_array(threshold, share_count, secret, OsRng)
-		.map_err(QosCryptoError::Vsss)
+) -> Result<(Vec<Vec<u8>>, Vec<ShareCommitment>), QosCryptoError> {
+	if threshold < 2 {
+		return Err(QosCryptoError::Vsss(vsss_rs::Error::SharingMinThreshold));
+	}
+	if share_count < threshold {
+		return Err(QosCryptoError::Vsss(
+			vsss_rs::Error::SharingLimitLessThanThreshold,
+		));
+	}
+	let identifiers = u8::try_from(share_count).map_err(|_| {
+		QosCryptoError::Vsss(vsss_rs::Error::InvalidSizeRequest)
+	})?;
+	let secret_len = u32::try_from(secret.len())
+		.ok()
+		.filter(|len| *len > 0)
+		.ok_or(QosCryptoError::Vsss(vsss_rs::Error::InvalidSecret))?;
+
+	let mut shares: Vec<Vec<u8>> = (1..=identifiers)
+		.map(|identifier| {
+			[&[PEDERSEN_SHARE_TAG, identifier][..], &secret_len.to_be_bytes()]
+				.concat()
+		})
+		.collect();
+	let mut commitments = Vec::new();
+	for chunk in secret.chunks(CHUNK_LEN) {
+		let values = random_polynomial(chunk_scalar(chunk), threshold);
+		let blindings = random_polynomial(Scalar::random(OsRng), threshold);
+		commitments.extend(
+			values.iter().zip(blindings.iter()).map(|(a, b)| commit(a, b)),
+		);
+
+		for (share, identifier) in shares.iter_mut().zip(1..=identifiers) {
+			let x = Scalar::from(u64::from(identifier));
+			share.extend_from_slice(&evaluate(&values, x).to_repr());
+			share.extend_from_slice(&evaluate(&blindings, x).to_repr());
+		}
+	}
+
+	Ok((shares, commitments))
+}
+
+/// Check `share` against the `commitments` made when it was generated.
+///
+/// Any share of the committed polynomials verifies, including shares
+/// computed with [`share_at`].
+pub fn share_verify(
+	share: &[u8],
+	commitments: &[ShareCommitment],
+) -> Result<(), QosCryptoError> {
+	let share = PedersenShare::decode(share)?;
+	let chunks = share.chunks.len();
+	if commitments.is_empty() || commitments.len() % chunks != 0 {
+		return Err(QosCryptoError::ShareCommitmentMismatch);
+	}
+	let threshold = commitments.len() / chunks;
+
+	let x = Scalar::from(u64::from(share.identifier));
+	for ((value, blinding), commitments) in
+		share.chunks.iter().zip(commitments.chunks(threshold))
+	{
+		// Sum of the commitments times the powers of `x`, with Horner's rule
+		let mut expected = ProjectivePoint::IDENTITY;
+		for commitment in commitments.iter().rev() {
+			expected = expected * x + decode_point(commitment)?;
+		}
+
+		if !ct_eq(&expected.to_bytes(), &commit(value, blinding)) {
+			return Err(QosCryptoError::ShareCommitmentMismatch);
+		}
+	}
+
+	Ok(())
+}
+
+/// The identifier of `share`, from either share format.
+#[must_use]
+pub fn share_identifier(share: &[u8]) -> Option<u8> {
+	match share.first() {
+		Some(&PEDERSEN_SHARE_TAG) => share.get(1).copied(),
+		first => first.copied(),
+	}
 }
 
-/// Reconstruct our secret from the given `shares`.
+/// Reconstruct our secret from the given `shares`. The secret is wiped from
+/// memory when it is dropped.
+///
+/// Shares that cannot be combined, such as shares of different lengths or
+/// formats, or with the same identifier, are refused. Fewer shares than the
+/// threshold still reconstruct, to the wrong secret, so use
+/// [`shares_reconstruct_checked`] when the threshold is known.
 pub fn shares_reconstruct<B: AsRef<[Vec<u8>]>>(
 	shares: B,
+) -> Result<Zeroizing<Vec<u8>>, QosCryptoError> {
+	let shares = shares.as_ref();
+	let Some(shares) = check_shares(shares)? else {
+		return Gf256::combine_array(shares)
+			.map(Zeroizing::new)
+			.map_err(QosCryptoError::Vsss);
+	};
+
+	let secret_len = shares[0].secret_len;
+	let mut secret = Zeroizing::new(Vec::with_capacity(secret_len));
+	for (chunk, values) in
+		chunk_points(&shares, |(value, _)| *value).enumerate()
+	{
+		let len = CHUNK_LEN.min(secret_len - chunk * CHUNK_LEN);
+		let scalar = Zeroizing::new(interpolate(&values, Scalar::ZERO));
+		secret.extend_from_slice(&scalar.to_repr()[SCALAR_LEN - len..]);
+	}
+
+	Ok(secret)
+}
+
+/// Reconstruct our secret from at least `threshold` of its `shares`, like
+/// [`shares_reconstruct`]. The secret is reconstructed from the first
+/// `threshold` shares, and every other share must be a share of that same
+/// secret, so a corrupted share is detected instead of silently changing the
+/// secret as long as one more share than needed is given.
+pub fn shares_reconstruct_checked<B: AsRef<[Vec<u8>]>>(
+	shares: B,
+	threshold: usize,
+) -> Result<Zeroizing<Vec<u8>>, QosCryptoError> {
+	let shares = shares.as_ref();
+	let needed = threshold.max(2);
+	if shares.len() < needed {
+		return Err(QosCryptoError::NotEnoughShares {
+			got: shares.len(),
+			needed,
+		});
+	}
+	check_shares(shares)?;
+
+	let (basis, rest) = shares.split_at(needed);
+	for share in rest {
+		let identifier = share_identifier(share)
+			.ok_or(QosCryptoError::InvalidShareIdentifier)?;
+		if !ct_eq(&share_at(basis, identifier)?, share) {
+			return Err(QosCryptoError::InconsistentShares(identifier));
+		}
+	}
+
+	shares_reconstruct(basis)
+}
+
+/// Check that `shares` can be combined: there are at least two, all the same
+/// length and format with at least one byte after the identifier, and their
+/// identifiers are distinct and not 0.
+///
+/// Returns the decoded shares if they were made by [`shares_generate`], and
+/// `None` if they were split over GF(256).
+fn check_shares(
+	shares: &[Vec<u8>],
+) -> Result<Option<Vec<PedersenShare>>, QosCryptoError> {
+	if shares.len() < 2 {
+		return Err(QosCryptoError::NotEnoughShares {
+			got: shares.len(),
+			needed: 2,
+		});
+	}
+	let len = shares[0].len();
+	if len < 2 || shares.iter().any(|share| share.len() != len) {
+		return Err(QosCryptoError::InvalidShareLength);
+	}
+
+	let pedersen = shares[0][0] == PEDERSEN_SHARE_TAG;
+	if shares.iter().any(|share| (share[0] == PEDERSEN_SHARE_TAG) != pedersen) {
+		return Err(QosCryptoError::InvalidShareLength);
+	}
+
+	let mut seen = [false; 256];
+	for share in shares {
+		let identifier = share_identifier(share)
+			.expect("shares are at least two bytes long. qed.");
+		if identifier == 0 {
+			return Err(QosCryptoError::InvalidShareIdentifier);
+		}
+		if std::mem::replace(&mut seen[usize::from(identifier)], true) {
+			return Err(QosCryptoError::DuplicateShareIdentifier(identifier));
+		}
+	}
+
+	if !pedersen {
+		return Ok(None);
+	}
+	let decoded = shares
+		.iter()
+		.map(|share| PedersenShare::decode(share))
+		.collect::<Result<Vec<_>, _>>()?;
+	if decoded.iter().any(|share| share.secret_len != decoded[0].secret_len) {
+		return Err(QosCryptoError::InvalidShareLength);
+	}
+
+	Ok(Some(decoded))
+}
+
+/// Compute the share with the given `identifier` from enough `shares` of the
+/// same secret to reconstruct it.
+pub fn share_at<B: AsRef<[Vec<u8>]>>(
+	shares: B,
+	identifier: u8,
 ) -> Result<Vec<u8>, QosCryptoError> {
-	Gf256::combine_array(shares).map_err(QosCryptoError::Vsss)
+	if identifier == 0 {
+		return Err(QosCryptoError::InvalidShareIdentifier);
+	}
+
+	let shares = shares.as_ref();
+	if let Some(share) =
+		shares.iter().find(|share| share_identifier(share) == Some(identifier))
+	{
+		return Ok(share.clone());
+	}
+
+	let Some(decoded) = check_shares(shares)? else {
+		// Interpolating at `identifier` is the same as interpolating at 0
+		// after translating the identifiers by `identifier`, which is XOR in
+		// GF(256).
+		let translated: Vec<_> = shares
+			.iter()
+			.map(|share| {
+				let mut share = share.clone();
+				share[0] ^= identifier;
+				share
+			})
+			.collect();
+
+		let share = shares_reconstruct(translated)?;
+		return Ok([&[identifier], share.as_slice()].concat());
+	};
+
+	let x = Scalar::from(u64::from(identifier));
+	let values = chunk_points(&decoded, |(value, _)| *value);
+	let blindings = chunk_points(&decoded, |(_, blinding)| *blinding);
+	let share = PedersenShare {
+		identifier,
+		secret_len: decoded[0].secret_len,
+		chunks: values
+			.zip(blindings)
+			.map(|(values, blindings)| {
+				(interpolate(&values, x), interpolate(&blindings, x))
+			})
+			.collect(),
+	};
+
+	Ok(share.encode())
+}
+
+/// A share made by [`shares_generate`].
+struct PedersenShare {
+	identifier: u8,
+	secret_len: usize,
+	/// Value and blinding of each chunk of the secret at `identifier`.
+	chunks: Vec<(Scalar, Scalar)>,
+}
+
+impl PedersenShare {
+	fn decode(share: &[u8]) -> Result<Self, QosCryptoError> {
+		if share.len() < HEADER_LEN {
+			return Err(QosCryptoError::InvalidShareLength);
+		}
+		let (header, body) = share.split_at(HEADER_LEN);
+		if header[0] != PEDERSEN_SHARE_TAG {
+			return Err(QosCryptoError::Vsss(vsss_rs::Error::InvalidShare));
+		}
+		let identifier = header[1];
+		if identifier == 0 {
+			return Err(QosCryptoError::InvalidShareIdentifier);
+		}
+		let secret_len = u32::from_be_bytes(
+			header[2..].try_into().expect("header is 6 bytes. qed."),
+		) as usize;
+		if secret_len == 0
+			|| body.len() != secret_len.div_ceil(CHUNK_LEN) * 2 * SCALAR_LEN
+		{
+			return Err(QosCryptoError::InvalidShareLength);
+		}
+
+		let chunks = body
+			.chunks(2 * SCALAR_LEN)
+			.map(|chunk| {
+				let (value, blinding) = chunk.split_at(SCALAR_LEN);
+				Ok((decode_scalar(value)?, decode_scalar(blinding)?))
+			})
+			.collect::<Result<_, QosCryptoError>>()?;
+
+		Ok(Self { identifier, secret_len, chunks })
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let secret_len = u32::try_from(self.secret_len)
+			.expect("decoded from a u32. qed.")
+			.to_be_bytes();
+		let mut share =
+			[&[PEDERSEN_SHARE_TAG, self.identifier][..], &secret_len].concat();
+		for (value, blinding) in &self.chunks {
+			share.extend_from_slice(&value.to_repr());
+			share.extend_from_slice(&blinding.to_repr());
+		}
+
+		share
+	}
+}
+
+impl Drop for PedersenShare {
+	fn drop(&mut self) {
+		zeroize::Zeroize::zeroize(&mut self.chunks);
+	}
+}
+
+/// For each chunk of the secret, the points `(identifier, f(identifier))` of
+/// `shares`, where `f` picks the value or blinding polynomial.
+fn chunk_points<'a>(
+	shares: &'a [PedersenShare],
+	f: impl Fn(&(Scalar, Scalar)) -> Scalar + Copy + 'a,
+) -> impl Iterator<Item = Zeroizing<Vec<(Scalar, Scalar)>>> + 'a {
+	(0..shares[0].chunks.len()).map(move |chunk| {
+		Zeroizing::new(
+			shares
+				.iter()
+				.map(|share| {
+					(
+						Scalar::from(u64::from(share.identifier)),
+						f(&share.chunks[chunk]),
+					)
+				})
+				.collect(),
+		)
+	})
+}
+
+/// Lagrange interpolation of the polynomial through `points` at `x`. The
+/// points must have distinct `x` coordinates.
+fn interpolate(points: &[(Scalar, Scalar)], x: Scalar) -> Scalar {
+	points.iter().fold(Scalar::ZERO, |sum, (xi, yi)| {
+		let (numerator, denominator) = points
+			.iter()
+			.filter(|(xj, _)| xj != xi)
+			.fold((Scalar::ONE, Scalar::ONE), |(n, d), (xj, _)| {
+				(n * (x - xj), d * (xi - xj))
+			});
+		let basis = numerator
+			* denominator.invert().expect(
+				"identifiers are distinct, so the product is not 0. qed.",
+			);
+
+		sum + *yi * basis
+	})
+}
+
+/// A polynomial of degree `threshold - 1` with constant term `constant` and
+/// random other coefficients, lowest degree first.
+fn random_polynomial(
+	constant: Scalar,
+	threshold: usize,
+) -> Zeroizing<Vec<Scalar>> {
+	let mut coefficients = Zeroizing::new(vec![constant]);
+	coefficients.extend((1..threshold).map(|_| Scalar::random(OsRng)));
+	coefficients
+}
+
+/// Evaluate the polynomial with `coefficients`, lowest degree first, at `x`.
+fn evaluate(coefficients: &[Scalar], x: Scalar) -> Scalar {
+	coefficients.iter().rev().fold(Scalar::ZERO, |y, a| y * x + a)
+}
+
+/// The scalar with the big endian value of `chunk`, at most 31 bytes.
+fn chunk_scalar(chunk: &[u8]) -> Scalar {
+	let mut repr = Zeroizing::new(FieldBytes::default());
+	repr[SCALAR_LEN - chunk.len()..].copy_from_slice(chunk);
+	Scalar::from_repr(*repr)
+		.expect("31 bytes are less than the order of P256. qed.")
+}
+
+fn decode_scalar(bytes: &[u8]) -> Result<Scalar, QosCryptoError> {
+	Option::from(Scalar::from_repr(*FieldBytes::from_slice(bytes)))
+		.ok_or(QosCryptoError::Vsss(vsss_rs::Error::InvalidShareConversion))
+}
+
+fn decode_point(
+	commitment: &ShareCommitment,
+) -> Result<ProjectivePoint, QosCryptoError> {
+	Option::from(ProjectivePoint::from_bytes(commitment.into()))
+		.ok_or(QosCryptoError::InvalidShareCommitment)
+}
+
+/// The Pedersen commitment `value·G + blinding·H`.
+fn commit(value: &Scalar, blinding: &Scalar) -> ShareCommitment {
+	let point = ProjectivePoint::GENERATOR * value + *generator_h() * blinding;
+	let mut commitment = [0; 33];
+	commitment.copy_from_slice(&point.to_bytes());
+	commitment
+}
+
+/// The second generator of the commitments, hashed to the curve so that
+/// nobody knows its discrete log to `G`.
+fn generator_h() -> &'static ProjectivePoint {
+	static H: OnceLock<ProjectivePoint> = OnceLock::new();
+	H.get_or_init(|| {
+		NistP256::hash_from_bytes::<ExpandMsgXmd<Sha256>>(
+			&[b"H"],
+			GENERATOR_DST,
+		)
+		.expect("the domain separation tag is short enough. qed.")
+	})
 }
 
 #[cfg(test)]
 mod test {
+	use proptest::{collection::vec, prelude::*};
 	use rand::prelude::SliceRandom;
 
 	use super::*;
@@ -36,35 +481,134 @@ mod test {
 		let secret = b"this is a crazy secret";
 		let n = 6;
 		let k = 3;
-		let all_shares = shares_generate(secret, n, k).unwrap();
+		let (all_shares, _) = shares_generate(secret, n, k).unwrap();
 
 		// Reconstruct with all the shares
 		let shares = all_shares.clone();
 		let reconstructed = shares_reconstruct(shares).unwrap();
-		assert_eq!(secret.to_vec(), reconstructed);
+		assert_eq!(secret.to_vec(), *reconstructed);
 
 		// Reconstruct with enough shares
 		let shares = &all_shares[..k];
 		let reconstructed = shares_reconstruct(shares).unwrap();
-		assert_eq!(secret.to_vec(), reconstructed);
+		assert_eq!(secret.to_vec(), *reconstructed);
 
 		// Reconstruct with not enough shares
 		let shares = &all_shares[..(k - 1)];
 		let reconstructed = shares_reconstruct(shares).unwrap();
 		let old_reconstructed = shares_reconstruct(shares).unwrap();
-		assert!(secret.to_vec() != reconstructed);
-		assert!(secret.to_vec() != old_reconstructed);
+		assert!(secret.to_vec() != *reconstructed);
+		assert!(secret.to_vec() != *old_reconstructed);
 
 		// Reconstruct with enough shuffled shares
 		let mut shares = all_shares.clone()[..k].to_vec();
 		shares.shuffle(&mut rand::thread_rng());
 		let reconstructed = shares_reconstruct(&shares).unwrap();
-		assert_eq!(secret.to_vec(), reconstructed);
+		assert_eq!(secret.to_vec(), *reconstructed);
 
 		for combo in crate::n_choose_k::combinations(&all_shares, k) {
 			let reconstructed = shares_reconstruct(&combo).unwrap();
-			assert_eq!(secret.to_vec(), reconstructed);
+			assert_eq!(secret.to_vec(), *reconstructed);
+		}
+	}
+
+	#[test]
+	fn share_at_computes_missing_shares() {
+		let secret = b"this is a crazy secret";
+		let (all_shares, _) = shares_generate(secret, 5, 3).unwrap();
+
+		for combo in crate::n_choose_k::combinations(&all_shares, 3) {
+			for share in &all_shares {
+				let identifier = share_identifier(share).unwrap();
+				assert_eq!(&share_at(&combo, identifier).unwrap(), share);
+			}
 		}
+
+		// The computed share works like any other
+		let share = share_at(&all_shares[..3], 5).unwrap();
+		let shares = [all_shares[3].clone(), share];
+		assert_eq!(
+			*shares_reconstruct([&all_shares[..1], &shares[..]].concat())
+				.unwrap(),
+			secret.to_vec()
+		);
+
+		assert!(share_at(&all_shares, 0).is_err());
+	}
+
+	#[test]
+	fn share_verify_attributes_corrupted_shares() {
+		let secret = b"this is a crazy secret";
+		let (shares, commitments) = shares_generate(secret, 5, 3).unwrap();
+
+		for share in &shares {
+			assert!(share_verify(share, &commitments).is_ok());
+		}
+
+		let mut corrupted = shares[2].clone();
+		corrupted[HEADER_LEN + 4] ^= 1;
+		assert_eq!(
+			share_verify(&corrupted, &commitments),
+			Err(QosCryptoError::ShareCommitmentMismatch)
+		);
+
+		// A share of another secret
+		let (other_shares, _) = shares_generate(secret, 5, 3).unwrap();
+		assert_eq!(
+			share_verify(&other_shares[0], &commitments),
+			Err(QosCryptoError::ShareCommitmentMismatch)
+		);
+
+		// Shares computed from enough good shares verify
+		let share = share_at(&shares[1..4], 5).unwrap();
+		assert!(share_verify(&share, &commitments).is_ok());
+
+		// Any share of the committed polynomials verifies
+		let unissued = share_at(&shares[..3], 6).unwrap();
+		assert!(share_verify(&unissued, &commitments).is_ok());
+
+		assert!(share_verify(&[], &commitments).is_err());
+		assert_eq!(
+			share_verify(&shares[0], &commitments[..2]),
+			Err(QosCryptoError::ShareCommitmentMismatch)
+		);
+		let mut invalid = commitments.clone();
+		invalid[0] = [0xff; 33];
+		assert_eq!(
+			share_verify(&shares[0], &invalid),
+			Err(QosCryptoError::InvalidShareCommitment)
+		);
+
+		// Shares split over GF(256) have no commitments
+		let gf256_share =
+			qos_hex::decode("01661fc0cc265daa4e7bde354c281dcc23a80c590249")
+				.unwrap();
+		assert!(share_verify(&gf256_share, &commitments).is_err());
+	}
+
+	#[test]
+	fn commitments_cover_each_chunk_of_the_secret() {
+		let secret = [7; 100];
+		let (shares, commitments) = shares_generate(&secret, 4, 3).unwrap();
+		assert_eq!(shares[0].len(), HEADER_LEN + 4 * 2 * SCALAR_LEN);
+		assert_eq!(commitments.len(), 4 * 3);
+
+		// A share that is good in every chunk but the last
+		let (other, _) = shares_generate(&secret, 4, 3).unwrap();
+		let mut mixed = shares[1].clone();
+		let last_chunk = mixed.len() - 2 * SCALAR_LEN;
+		mixed[last_chunk..].copy_from_slice(&other[1][last_chunk..]);
+		assert_eq!(
+			share_verify(&mixed, &commitments),
+			Err(QosCryptoError::ShareCommitmentMismatch)
+		);
+		assert_eq!(
+			shares_reconstruct_checked(
+				[&shares[..1], &shares[2..], &[mixed]].concat(),
+				3
+			),
+			Err(QosCryptoError::InconsistentShares(2))
+		);
 	}
 
 	#[test]
@@ -112,8 +656,169 @@ mod test {
 
 		// Regardless of the combination we should get the same secret
 		let expected_secret = b"my cute little secret";
-		assert_eq!(reconstructed1, expected_secret);
-		assert_eq!(reconstructed2, expected_secret);
-		assert_eq!(reconstructed3, expected_secret);
+		assert_eq!(*reconstructed1, expected_secret);
+		assert_eq!(*reconstructed2, expected_secret);
+		assert_eq!(*reconstructed3, expected_secret);
+	}
+
+	#[test]
+	fn reconstruct_refuses_shares_that_cannot_be_combined() {
+		let (shares, _) = shares_generate(b"secret", 4, 2).unwrap();
+
+		assert_eq!(
+			shares_reconstruct(&shares[..1]),
+			Err(QosCryptoError::NotEnoughShares { got: 1, needed: 2 })
+		);
+
+		let mut short = shares[..2].to_vec();
+		short[1].pop();
+		assert_eq!(
+			shares_reconstruct(&short),
+			Err(QosCryptoError::InvalidShareLength)
+		);
+		assert_eq!(
+			shares_reconstruct(vec![vec![1], vec![2]]),
+			Err(QosCryptoError::InvalidShareLength)
+		);
+
+		let mut zero = shares[..2].to_vec();
+		zero[0][1] = 0;
+		assert_eq!(
+			shares_reconstruct(&zero),
+			Err(QosCryptoError::InvalidShareIdentifier)
+		);
+
+		let mut duplicate = shares[..3].to_vec();
+		duplicate[2][1] = duplicate[0][1];
+		assert_eq!(
+			shares_reconstruct(&duplicate),
+			Err(QosCryptoError::DuplicateShareIdentifier(duplicate[0][1]))
+		);
+
+		let mut longer = shares[..2].to_vec();
+		longer[1][5] += 1;
+		assert_eq!(
+			shares_reconstruct(&longer),
+			Err(QosCryptoError::InvalidShareLength)
+		);
+
+		// Shares split over GF(256) of the same length
+		let mut mixed = shares[..2].to_vec();
+		mixed[1][0] = 3;
+		assert_eq!(
+			shares_reconstruct(&mixed),
+			Err(QosCryptoError::InvalidShareLength)
+		);
+	}
+
+	#[test]
+	fn shares_generate_refuses_invalid_settings() {
+		for (secret, share_count, threshold, error) in [
+			(&b"secret"[..], 3, 1, vsss_rs::Error::SharingMinThreshold),
+			(b"secret", 2, 3, vsss_rs::Error::SharingLimitLessThanThreshold),
+			(b"secret", 256, 2, vsss_rs::Error::InvalidSizeRequest),
+			(b"", 3, 2, vsss_rs::Error::InvalidSecret),
+		] {
+			assert_eq!(
+				shares_generate(secret, share_count, threshold),
+				Err(QosCryptoError::Vsss(error))
+			);
+		}
+
+		let (shares, _) = shares_generate(b"secret", 255, 2).unwrap();
+		assert_eq!(share_identifier(&shares[254]), Some(255));
+	}
+
+	/// A secret, a share count and threshold for it, and an order to take
+	/// the shares in.
+	fn sharing() -> impl Strategy<Value = (Vec<u8>, usize, Vec<usize>)> {
+		(vec(any::<u8>(), 1..64), 2..=12usize).prop_flat_map(
+			|(secret, share_count)| {
+				(
+					Just(secret),
+					2..=share_count,
+					Just((0..share_count).collect::<Vec<_>>()).prop_shuffle(),
+				)
+			},
+		)
+	}
+
+	fn take(shares: &[Vec<u8>], order: &[usize]) -> Vec<Vec<u8>> {
+		order.iter().map(|i| shares[*i].clone()).collect()
+	}
+
+	proptest! {
+		#[test]
+		fn any_threshold_shares_reconstruct(
+			(secret, threshold, order) in sharing()
+		) {
+			let (shares, commitments) =
+				shares_generate(&secret, order.len(), threshold).unwrap();
+			let shuffled = take(&shares, &order);
+
+			prop_assert_eq!(
+				&*shares_reconstruct(&shuffled[..threshold]).unwrap(),
+				&secret
+			);
+			prop_assert_eq!(
+				&*shares_reconstruct_checked(&shuffled, threshold).unwrap(),
+				&secret
+			);
+			for share in &shuffled {
+				prop_assert!(share_verify(share, &commitments).is_ok());
+			}
+
+			prop_assert_eq!(
+				shares_reconstruct_checked(&shuffled[..threshold - 1], threshold),
+				Err(QosCryptoError::NotEnoughShares {
+					got: threshold - 1,
+					needed: threshold,
+				})
+			);
+		}
+
+		#[test]
+		fn checked_reconstruct_detects_corrupted_shares(
+			(secret, threshold, order) in sharing(),
+			corrupt in any::<prop::sample::Index>(),
+			flip in 1..=u8::MAX,
+		) {
+			prop_assume!(threshold < order.len());
+			let (shares, _) =
+				shares_generate(&secret, order.len(), threshold).unwrap();
+			let mut shuffled = take(&shares, &order[..=threshold]);
+
+			// Corrupt any byte after the header of any share
+			let share = corrupt.get_mut(&mut shuffled);
+			let byte = HEADER_LEN + corrupt.index(share.len() - HEADER_LEN);
+			share[byte] ^= flip;
+
+			prop_assert!(matches!(
+				shares_reconstruct_checked(&shuffled, threshold),
+				Err(QosCryptoError::InconsistentShares(_))
+			));
+		}
+
+		#[test]
+		fn shares_of_different_secrets_are_inconsistent(
+			(secret, threshold, order) in sharing(),
+		) {
+			prop_assume!(threshold < order.len());
+			let (shares, _) =
+				shares_generate(&secret, order.len(), threshold).unwrap();
+			let (other, _) =
+				shares_generate(&secret, order.len(), threshold).unwrap();
+			let mut mixed = take(&shares, &order[..=threshold]);
+			mixed[threshold].clone_from(&other[order[threshold]]);
+			// Short secrets can share a byte by chance
+			prop_assume!(mixed[threshold] != shares[order[threshold]]);
+
+			prop_assert_eq!(
+				shares_reconstruct_checked(&mixed, threshold),
+				Err(QosCryptoError::InconsistentShares(
+					share_identifier(&mixed[threshold]).unwrap()
+				))
+			);
+		}
 	}
 }
@@ -19,7 +19,8 @@
 //!
 //! With the serde feature enabled you can use [`crate::serde`] to serialize any
 //! `u8` array or `Vec<u8>` to hex and deserialize hex string to a `Vec<u8>` and
-//! a fixed selection of `u8` arrays.
+//! a fixed selection of `u8` arrays, and [`crate::serde_vec`] to do the same
+//! for lists of them.
 
 use std::{convert::Into, num::ParseIntError, string::FromUtf8Error};
 
@@ -288,6 +289,40 @@ pub mod serde {
 	}
 }
 
+/// Like [`crate::serde`], but for a list of byte strings, each hex encoded.
+#[cfg(feature = "serde")]
+pub mod serde_vec {
+	use serde::{de::Deserialize, ser::SerializeSeq, Deserializer, Serializer};
+
+	use super::{encode, FromHex};
+
+	pub fn serialize<T, S>(list: &[T], serializer: S) -> Result<S::Ok, S::Error>
+	where
+		T: AsRef<[u8]>,
+		S: Serializer,
+	{
+		let mut seq = serializer.serialize_seq(Some(list.len()))?;
+		for bytes in list {
+			seq.serialize_element(&encode(bytes.as_ref()))?;
+		}
+		seq.end()
+	}
+
+	pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+	where
+		D: Deserializer<'de>,
+		T: FromHex,
+	{
+		Vec::<String>::deserialize(deserializer)?
+			.iter()
+			.map(|hex| {
+				T::from_hex(hex)
+					.map_err(|e| serde::de::Error::custom(format!("{e:?}")))
+			})
+			.collect()
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
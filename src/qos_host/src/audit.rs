@@ -0,0 +1,7 @@
+//! Hash chained log of the messages the host proxied to the enclave, see
+//! [`qos_core::audit`].
+
+pub use qos_core::audit::AuditEntry;
+
+/// Default for [`crate::HostServer::with_audit_log_capacity`].
+pub const AUDIT_LOG_CAPACITY: usize = 10_000;
@@ -0,0 +1,176 @@
+//! Circuit breaker for the connection to the enclave.
+//!
+//! After enough consecutive failures the circuit opens and requests fail fast
+//! instead of each waiting out the client's retries. Once the cooldown has
+//! passed a single trial request is let through; its outcome closes or
+//! re-opens the circuit. Should the trial never report back, e.g. because the
+//! HTTP request was dropped, another one is let through after a further
+//! cooldown.
+
+use std::{
+	sync::{Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+
+/// Consecutive failures after which the circuit opens.
+pub const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+/// How long the circuit stays open before letting a trial request through.
+pub const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// State of a [`CircuitBreaker`].
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+	/// Requests are sent to the enclave.
+	Closed,
+	/// Requests fail fast without contacting the enclave.
+	Open,
+	/// The cooldown has passed; the next request decides whether the circuit
+	/// closes again.
+	HalfOpen,
+}
+
+/// Tracks failures talking to the enclave. See the module docs.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+	failure_threshold: u32,
+	cooldown: Duration,
+	inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+	consecutive_failures: u32,
+	opened_at: Option<Instant>,
+	trial_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+	/// Create a new, closed [`Self`] that opens after `failure_threshold`
+	/// consecutive failures and stays open for `cooldown`.
+	#[must_use]
+	pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+		Self { failure_threshold, cooldown, inner: Mutex::default() }
+	}
+
+	/// Create a new, closed [`Self`] configured like `self`.
+	#[must_use]
+	pub(crate) fn new_like(&self) -> Self {
+		Self::new(self.failure_threshold, self.cooldown)
+	}
+
+	/// Current state of the circuit.
+	#[must_use]
+	pub fn state(&self) -> CircuitState {
+		self.lock().state(self.cooldown)
+	}
+
+	/// Number of failures since the last successful request.
+	#[must_use]
+	pub fn consecutive_failures(&self) -> u32 {
+		self.lock().consecutive_failures
+	}
+
+	/// Check whether a request may be sent. If not, returns how long until
+	/// the circuit lets a trial request through.
+	pub(crate) fn try_acquire(&self) -> Result<(), Duration> {
+		let mut inner = self.lock();
+		match inner.state(self.cooldown) {
+			CircuitState::Closed => Ok(()),
+			CircuitState::Open => Err(self.remaining(inner.opened_at)),
+			CircuitState::HalfOpen
+				if inner
+					.trial_started_at
+					.is_some_and(|at| at.elapsed() < self.cooldown) =>
+			{
+				Err(self.remaining(inner.trial_started_at))
+			}
+			CircuitState::HalfOpen => {
+				inner.trial_started_at = Some(Instant::now());
+				Ok(())
+			}
+		}
+	}
+
+	/// Record the outcome of a request let through by [`Self::try_acquire`].
+	pub(crate) fn record(&self, success: bool) {
+		let mut inner = self.lock();
+		inner.trial_started_at = None;
+		if success {
+			inner.consecutive_failures = 0;
+			inner.opened_at = None;
+		} else {
+			inner.consecutive_failures =
+				inner.consecutive_failures.saturating_add(1);
+			if inner.consecutive_failures >= self.failure_threshold {
+				inner.opened_at = Some(Instant::now());
+			}
+		}
+	}
+
+	/// Time left of a cooldown that started at `since`.
+	fn remaining(&self, since: Option<Instant>) -> Duration {
+		since.map_or(self.cooldown, |at| {
+			self.cooldown.saturating_sub(at.elapsed())
+		})
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+		self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl Default for CircuitBreaker {
+	fn default() -> Self {
+		Self::new(CIRCUIT_FAILURE_THRESHOLD, CIRCUIT_COOLDOWN)
+	}
+}
+
+impl Inner {
+	fn state(&self, cooldown: Duration) -> CircuitState {
+		match self.opened_at {
+			None => CircuitState::Closed,
+			Some(at) if at.elapsed() < cooldown => CircuitState::Open,
+			Some(_) => CircuitState::HalfOpen,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn opens_after_threshold_and_recovers_after_cooldown() {
+		let circuit = CircuitBreaker::new(2, Duration::from_millis(50));
+
+		circuit.try_acquire().unwrap();
+		circuit.record(false);
+		assert_eq!(circuit.state(), CircuitState::Closed);
+		circuit.try_acquire().unwrap();
+		circuit.record(false);
+		assert_eq!(circuit.state(), CircuitState::Open);
+		assert!(circuit.try_acquire().is_err());
+
+		std::thread::sleep(Duration::from_millis(60));
+		assert_eq!(circuit.state(), CircuitState::HalfOpen);
+		// Only a single trial request is let through
+		circuit.try_acquire().unwrap();
+		assert!(circuit.try_acquire().is_err());
+		// ... unless it does not report back within the cooldown
+		std::thread::sleep(Duration::from_millis(60));
+		circuit.try_acquire().unwrap();
+
+		// A failed trial re-opens the circuit
+		circuit.record(false);
+		assert_eq!(circuit.state(), CircuitState::Open);
+
+		std::thread::sleep(Duration::from_millis(60));
+		circuit.try_acquire().unwrap();
+		circuit.record(true);
+		assert_eq!(circuit.state(), CircuitState::Closed);
+		assert_eq!(circuit.consecutive_failures(), 0);
+	}
+}
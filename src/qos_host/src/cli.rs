@@ -7,20 +7,34 @@ use std::{
 };
 
 use qos_core::{
-	cli::{CID, PORT, USOCK},
-	io::SocketAddress,
+	cli::{CID, PORT, USOCK, USOCK_GID, USOCK_MODE, USOCK_UID},
+	io::{SocketAddress, SocketPermissions, TimeVal, TimeValLike},
+	log,
 	parser::{GetParserForOptions, OptionsParser, Parser, Token},
 };
 
-use crate::HostServer;
+use crate::{ingress::IngressMap, tls::HostTls, HostServer};
 
 const HOST_IP: &str = "host-ip";
 const HOST_PORT: &str = "host-port";
 const ENDPOINT_BASE_PATH: &str = "endpoint-base-path";
 const VSOCK_TO_HOST: &str = "vsock-to-host";
+const TLS_CERT: &str = "tls-cert";
+const TLS_KEY: &str = "tls-key";
+const CLIENT_CA: &str = "client-ca";
+const MAX_MESSAGE_SIZE: &str = "max-message-size";
+const ENCLAVE_TIMEOUT: &str = "enclave-timeout";
+const NAMESPACE_USOCK: &str = "namespace-usock";
+const CONFIG: &str = "config";
+const CORS_ORIGIN: &str = "cors-origin";
+const APP_HTTP_PROXY: &str = "app-http-proxy";
+const APP_GRPC_PROXY: &str = "app-grpc-proxy";
+const INGRESS_MAP: &str = "ingress-map";
+const TRACE_IDS: &str = "trace-ids";
 
 struct HostParser;
 impl GetParserForOptions for HostParser {
+	#[allow(clippy::too_many_lines)]
 	fn parser() -> Parser {
 		Parser::new()
 			.token(
@@ -36,7 +50,7 @@ impl GetParserForOptions for HostParser {
 					.requires(CID),
 			)
 			.token(
-				Token::new(USOCK, "name of the socket file (ex: `dev.sock`) (only for unix sockets)")
+				Token::new(USOCK, "name of the socket file (ex: `dev.sock`, or `@dev` for an abstract socket) (only for unix sockets)")
 					.takes_value(true)
 					.forbids(vec!["port", "cid"])
 			)
@@ -60,6 +74,74 @@ impl GetParserForOptions for HostParser {
 					.required(false)
 					.forbids(vec![USOCK])
 			)
+			.token(
+				Token::new(MAX_MESSAGE_SIZE, "maximum size in bytes of a message posted to the message endpoint")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(USOCK_MODE, "octal mode the enclave unix socket must have, e.g. `660`; the host refuses to connect otherwise")
+					.takes_value(true)
+					.requires(USOCK)
+			)
+			.token(
+				Token::new(USOCK_UID, "user id that must own the enclave unix socket")
+					.takes_value(true)
+					.requires(USOCK_MODE)
+			)
+			.token(
+				Token::new(USOCK_GID, "group id that must own the enclave unix socket")
+					.takes_value(true)
+					.requires(USOCK_MODE)
+			)
+			.token(
+				Token::new(ENCLAVE_TIMEOUT, "seconds to wait on each send and receive to the enclave before responding with a timeout error")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(NAMESPACE_USOCK, "`<namespace>=<usock>` to also proxy to the enclave listening on `usock` under `ns/<namespace>/`, e.g. <BASE>/ns/<namespace>/message. Can be given multiple times")
+					.takes_value(true)
+					.allow_multiple(true)
+			)
+			.token(
+				Token::new(CORS_ORIGIN, "origin, e.g. `https://approve.example.com`, whose web pages may call the host's endpoints; `*` allows any origin. Can be given multiple times")
+					.takes_value(true)
+					.allow_multiple(true)
+			)
+			.token(
+				Token::new(APP_HTTP_PROXY, "whether to forward HTTP requests under <BASE>/app/ to a pivot that speaks HTTP over the app socket. Valid options are `true` or `false`")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(APP_GRPC_PROXY, "whether to bridge unary gRPC-Web calls under <BASE>/grpc/ to a pivot that serves gRPC methods over the app socket. Valid options are `true` or `false`")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(TRACE_IDS, "whether to assign each request a trace ID, returned in the `x-qos-trace-id` header, and send it to the enclave and the pivot. The enclave must understand traced requests. Valid options are `true` or `false`")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(INGRESS_MAP, "TOML file mapping routes under <BASE>/api/ to the borsh messages of a pivot, see `qos_host::ingress`")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(CONFIG, "TOML file with any of these options, keyed by their name, e.g. `host-port = 3000`; options given on the command line take precedence")
+					.takes_value(true)
+			)
+			.token(
+				Token::new(TLS_CERT, "PEM encoded certificate chain to serve TLS with")
+					.takes_value(true)
+					.requires(TLS_KEY)
+			)
+			.token(
+				Token::new(TLS_KEY, "PEM encoded private key for the TLS certificate")
+					.takes_value(true)
+					.requires(TLS_CERT)
+			)
+			.token(
+				Token::new(CLIENT_CA, "PEM encoded CA; only clients with a certificate issued by it may post to the message endpoint")
+					.takes_value(true)
+					.requires(TLS_CERT)
+			)
 	}
 }
 
@@ -71,6 +153,19 @@ pub struct HostOpts {
 
 impl HostOpts {
 	fn new(args: &mut Vec<String>) -> Self {
+		let config_path = args
+			.iter()
+			.position(|arg| *arg == format!("--{CONFIG}"))
+			.and_then(|i| args.get(i + 1))
+			.cloned();
+		if let Some(path) = config_path {
+			let config = std::fs::read_to_string(&path)
+				.expect("Could not read config file");
+			let config_args = config_args(&config, args);
+			// The first arg is the binary name
+			args.splice(1..1, config_args);
+		}
+
 		let parsed = OptionsParser::<HostParser>::parse(args)
 			.expect("Entered invalid CLI args");
 
@@ -141,6 +236,154 @@ impl HostOpts {
 		self.parsed.single(ENDPOINT_BASE_PATH).cloned()
 	}
 
+	/// Maximum message size in bytes, if specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the size is not a valid `usize`.
+	#[must_use]
+	pub fn max_message_size(&self) -> Option<usize> {
+		self.parsed.single(MAX_MESSAGE_SIZE).map(|size| {
+			size.parse().expect("Could not parse max-message-size to usize")
+		})
+	}
+
+	/// Timeout for each send and receive to the enclave, if specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the timeout is not a valid number of seconds.
+	#[must_use]
+	pub fn enclave_timeout(&self) -> Option<TimeVal> {
+		self.parsed.single(ENCLAVE_TIMEOUT).map(|secs| {
+			TimeVal::seconds(
+				secs.parse()
+					.expect("Could not parse enclave-timeout as seconds"),
+			)
+		})
+	}
+
+	/// Permissions the enclave unix socket must have, if specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the mode is not octal or an id is not a valid `u32`.
+	#[must_use]
+	pub fn socket_permissions(&self) -> Option<SocketPermissions> {
+		let mode = self.parsed.single(USOCK_MODE)?;
+		let parse_id = |opt| {
+			self.parsed.single(opt).map(|id| {
+				id.parse::<u32>().expect("Could not parse socket owner id")
+			})
+		};
+
+		Some(SocketPermissions {
+			mode: u32::from_str_radix(mode, 8)
+				.expect("Could not parse usock-mode as octal"),
+			uid: parse_id(USOCK_UID),
+			gid: parse_id(USOCK_GID),
+		})
+	}
+
+	/// Enclaves to proxy to per namespace, in addition to
+	/// [`Self::enclave_addr`].
+	///
+	/// # Panics
+	///
+	/// Panics if a value is not of the form `<namespace>=<usock>`.
+	#[must_use]
+	pub fn namespaces(&self) -> Vec<(String, SocketAddress)> {
+		self.parsed
+			.multiple(NAMESPACE_USOCK)
+			.unwrap_or_default()
+			.iter()
+			.map(|value| {
+				let (namespace, usock) = value
+					.split_once('=')
+					.filter(|(namespace, _)| !namespace.is_empty())
+					.expect("namespace-usock must be `<namespace>=<usock>`");
+				(namespace.to_string(), SocketAddress::new_unix(usock))
+			})
+			.collect()
+	}
+
+	/// Origins allowed to make cross origin requests to the host.
+	#[must_use]
+	pub fn cors_origins(&self) -> Vec<String> {
+		self.parsed.multiple(CORS_ORIGIN).unwrap_or_default().to_vec()
+	}
+
+	/// Whether to forward HTTP requests to the app.
+	///
+	/// # Panics
+	///
+	/// Panics if the value is not `true` or `false`.
+	#[must_use]
+	pub fn app_http_proxy(&self) -> bool {
+		self.parsed.single(APP_HTTP_PROXY).is_some_and(|proxy| {
+			proxy.parse().expect(
+				"could not parse `--app-http-proxy`. Valid args are true or false",
+			)
+		})
+	}
+
+	/// Whether to bridge gRPC-Web calls to the app.
+	///
+	/// # Panics
+	///
+	/// Panics if the value is not `true` or `false`.
+	#[must_use]
+	pub fn app_grpc_proxy(&self) -> bool {
+		self.parsed.single(APP_GRPC_PROXY).is_some_and(|proxy| {
+			proxy.parse().expect(
+				"could not parse `--app-grpc-proxy`. Valid args are true or false",
+			)
+		})
+	}
+
+	/// Whether to trace requests through the enclave and the app.
+	///
+	/// # Panics
+	///
+	/// Panics if the value is not `true` or `false`.
+	#[must_use]
+	pub fn trace_ids(&self) -> bool {
+		self.parsed.single(TRACE_IDS).is_some_and(|trace_ids| {
+			trace_ids.parse().expect(
+				"could not parse `--trace-ids`. Valid args are true or false",
+			)
+		})
+	}
+
+	/// Map for the app's JSON API, if one was specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the map cannot be read or is invalid.
+	#[must_use]
+	pub fn ingress_map(&self) -> Option<IngressMap> {
+		let path = self.parsed.single(INGRESS_MAP)?;
+		let map = IngressMap::from_file(path)
+			.unwrap_or_else(|e| panic!("Could not load ingress map: {e}"));
+
+		Some(map)
+	}
+
+	/// TLS configuration, if a certificate was specified.
+	///
+	/// # Panics
+	///
+	/// Panics if the certificate, key or client CA cannot be loaded.
+	#[must_use]
+	pub fn tls(&self) -> Option<HostTls> {
+		let cert = self.parsed.single(TLS_CERT)?;
+		let key = self.parsed.single(TLS_KEY).expect("required by tls-cert");
+		let tls = HostTls::load(cert, key, self.parsed.single(CLIENT_CA))
+			.expect("Could not load TLS configuration");
+
+		Some(tls)
+	}
+
 	#[cfg(feature = "vm")]
 	fn to_host_flag(&self) -> u8 {
 		let include = self
@@ -154,15 +397,55 @@ impl HostOpts {
 			.unwrap_or(false);
 
 		if include {
-			println!("Configuring vsock with VMADDR_FLAG_TO_HOST.");
+			log::info("host", "Configuring vsock with VMADDR_FLAG_TO_HOST.");
 			qos_core::io::VMADDR_FLAG_TO_HOST
 		} else {
-			println!("Configuring vsock with VMADDR_NO_FLAGS.");
+			log::info("host", "Configuring vsock with VMADDR_NO_FLAGS.");
 			qos_core::io::VMADDR_NO_FLAGS
 		}
 	}
 }
 
+/// Turn the options in the TOML `config` into command line arguments, leaving
+/// out the ones already given in `cli_args`.
+///
+/// # Panics
+///
+/// Panics if `config` is not valid TOML or an option is not a string, integer,
+/// boolean or an array of those.
+fn config_args(config: &str, cli_args: &[String]) -> Vec<String> {
+	let config: toml_edit::Document =
+		config.parse().expect("Could not parse config file as TOML");
+	let to_arg = |key: &str, value: &toml_edit::Value| match value {
+		toml_edit::Value::String(s) => s.value().clone(),
+		toml_edit::Value::Integer(i) => i.value().to_string(),
+		toml_edit::Value::Boolean(b) => b.value().to_string(),
+		_ => panic!("Invalid value for `{key}` in config file"),
+	};
+
+	let mut args = vec![];
+	for (key, item) in config.iter() {
+		let flag = format!("--{key}");
+		if cli_args.contains(&flag) {
+			continue;
+		}
+
+		let values = match item.as_value() {
+			Some(toml_edit::Value::Array(values)) => {
+				values.iter().map(|value| to_arg(key, value)).collect()
+			}
+			Some(value) => vec![to_arg(key, value)],
+			None => panic!("Invalid value for `{key}` in config file"),
+		};
+		for value in values {
+			args.push(flag.clone());
+			args.push(value);
+		}
+	}
+
+	args
+}
+
 /// Host server command line interface.
 pub struct CLI;
 impl CLI {
@@ -170,19 +453,63 @@ impl CLI {
 	pub async fn execute() {
 		let mut args: Vec<String> = env::args().collect();
 		let options = HostOpts::new(&mut args);
+		log::global().set_print_level(options.parsed.log_level());
 
 		if options.parsed.version() {
 			println!("version: {}", env!("CARGO_PKG_VERSION"));
 		} else if options.parsed.help() {
 			println!("{}", options.parsed.info());
 		} else {
-			HostServer::new(
+			let server = HostServer::new(
 				options.enclave_addr(),
 				options.host_addr(),
 				options.base_path(),
-			)
-			.serve()
-			.await;
+			);
+			let server = match options.tls() {
+				Some(tls) => server.with_tls(tls),
+				None => server,
+			};
+			let server = match options.max_message_size() {
+				Some(size) => server.with_max_message_size(size),
+				None => server,
+			};
+			let server = match options.enclave_timeout() {
+				Some(timeout) => server.with_enclave_timeout(timeout),
+				None => server,
+			};
+			let server = match options.socket_permissions() {
+				Some(permissions) => {
+					server.with_socket_permissions(permissions)
+				}
+				None => server,
+			};
+			let server = options.namespaces().into_iter().fold(
+				server,
+				|server, (namespace, enclave_addr)| {
+					server.with_namespace(namespace, enclave_addr)
+				},
+			);
+			let server = server.with_cors_origins(options.cors_origins());
+			let server = if options.app_http_proxy() {
+				server.with_app_http_proxy()
+			} else {
+				server
+			};
+			let server = if options.app_grpc_proxy() {
+				server.with_app_grpc_proxy()
+			} else {
+				server
+			};
+			let server = if options.trace_ids() {
+				server.with_trace_ids()
+			} else {
+				server
+			};
+			let server = match options.ingress_map() {
+				Some(ingress) => server.with_ingress_map(ingress),
+				None => server,
+			};
+			server.serve().await;
 		}
 	}
 }
@@ -258,6 +585,87 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn parse_enclave_timeout() {
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--cid",
+			"6",
+			"--port",
+			"3999",
+			"--host-ip",
+			"0.0.0.0",
+			"--host-port",
+			"3000",
+			"--enclave-timeout",
+			"30",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = HostOpts::new(&mut args);
+
+		assert_eq!(opts.enclave_timeout(), Some(TimeVal::seconds(30)));
+	}
+
+	#[test]
+	fn parse_namespaces() {
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--cid",
+			"6",
+			"--port",
+			"3999",
+			"--host-ip",
+			"0.0.0.0",
+			"--host-port",
+			"3000",
+			"--namespace-usock",
+			"alpha=./alpha.sock",
+			"--namespace-usock",
+			"beta=./beta.sock",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = HostOpts::new(&mut args);
+
+		assert_eq!(
+			opts.namespaces(),
+			vec![
+				("alpha".to_string(), SocketAddress::new_unix("./alpha.sock")),
+				("beta".to_string(), SocketAddress::new_unix("./beta.sock")),
+			]
+		);
+	}
+
+	#[test]
+	fn config_args_yield_to_cli_args() {
+		let config = r#"
+			cid = 6
+			port = "3999"
+			host-ip = "0.0.0.0"
+			host-port = 3000
+			vsock-to-host = true
+			namespace-usock = ["alpha=./alpha.sock", "beta=./beta.sock"]
+		"#;
+		let mut args: Vec<_> = vec!["binary", "--host-port", "4000"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let config_args = config_args(config, &args);
+		args.splice(1..1, config_args);
+		let opts = HostOpts::new(&mut args);
+
+		assert_eq!(*opts.parsed.single(CID).unwrap(), "6".to_string());
+		assert_eq!(*opts.parsed.single(HOST_PORT).unwrap(), "4000".to_string());
+		assert_eq!(
+			*opts.parsed.single(VSOCK_TO_HOST).unwrap(),
+			"true".to_string()
+		);
+		assert_eq!(opts.namespaces().len(), 2);
+	}
+
 	#[test]
 	#[should_panic = "Entered invalid CLI args: UnexpectedInput(\"--durp\")"]
 	fn panic_when_mistyped_cid() {
@@ -0,0 +1,86 @@
+//! CORS headers for the host's routes, so browser based tools, e.g. a web UI
+//! for reviewing and approving manifests, can call the host directly.
+
+use std::sync::Arc;
+
+use axum::{
+	body::Body,
+	extract::State,
+	http::{
+		header::{
+			ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+			ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+			ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+			ORIGIN, VARY,
+		},
+		HeaderValue, Method, Request, StatusCode,
+	},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+/// Methods the host's routes accept.
+const ALLOWED_METHODS: &str = "GET, POST";
+/// Seconds browsers may cache the answer to a preflight request.
+const MAX_AGE_SECS: &str = "600";
+
+/// Origins allowed to make cross origin requests.
+#[derive(Debug)]
+pub(crate) struct Cors {
+	origins: Vec<String>,
+}
+
+impl Cors {
+	/// Allow requests from `origins`. `*` allows any origin.
+	pub(crate) fn new(origins: Vec<String>) -> Self {
+		Self { origins }
+	}
+
+	fn allows(&self, origin: &HeaderValue) -> bool {
+		self.origins.iter().any(|allowed| allowed == "*" || origin == allowed)
+	}
+}
+
+/// Middleware answering preflight requests from allowed origins and adding
+/// CORS headers to the responses to them. Requests from other origins pass
+/// through untouched, so browsers block them.
+pub(crate) async fn layer(
+	State(cors): State<Arc<Cors>>,
+	request: Request<Body>,
+	next: Next<Body>,
+) -> Response {
+	let Some(origin) =
+		request.headers().get(ORIGIN).filter(|origin| cors.allows(origin))
+	else {
+		return next.run(request).await;
+	};
+	let origin = origin.clone();
+
+	let is_preflight = request.method() == Method::OPTIONS
+		&& request.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+	let mut response = if is_preflight {
+		let mut response = StatusCode::NO_CONTENT.into_response();
+		let headers = response.headers_mut();
+		headers.insert(
+			ACCESS_CONTROL_ALLOW_METHODS,
+			HeaderValue::from_static(ALLOWED_METHODS),
+		);
+		if let Some(requested) =
+			request.headers().get(ACCESS_CONTROL_REQUEST_HEADERS)
+		{
+			headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+		}
+		headers.insert(
+			ACCESS_CONTROL_MAX_AGE,
+			HeaderValue::from_static(MAX_AGE_SECS),
+		);
+		response
+	} else {
+		next.run(request).await
+	};
+
+	let headers = response.headers_mut();
+	headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+	headers.append(VARY, HeaderValue::from_static("origin"));
+	response
+}
@@ -0,0 +1,193 @@
+//! gRPC-Web framing for unary calls bridged to a pivot, see
+//! [`qos_core::protocol::app_grpc`].
+//!
+//! gRPC-Web carries gRPC over HTTP/1.1: the body is a sequence of frames,
+//! each a flags byte and a big endian `u32` length followed by the payload.
+//! Data frames have the flags `0x00`, and the last frame of a response has
+//! the flags `0x80` and holds the trailers, including `grpc-status`.
+
+use axum::http::{
+	header::{CONTENT_LENGTH, CONTENT_TYPE, HOST},
+	HeaderMap, HeaderName, HeaderValue,
+};
+use qos_core::protocol::app_grpc::{AppGrpcResponse, GrpcCode, GrpcStatus};
+
+use crate::http_proxy;
+
+/// Content type of gRPC-Web responses.
+pub(crate) const GRPC_WEB_CONTENT_TYPE: &str = "application/grpc-web+proto";
+
+/// Flags of a data frame.
+const DATA_FRAME: u8 = 0x00;
+/// Flags of a trailers frame.
+const TRAILERS_FRAME: u8 = 0x80;
+/// Bytes before the payload of each frame.
+const FRAME_HEADER_LEN: usize = 5;
+
+/// Whether `content_type` is a gRPC-Web content type this bridge supports.
+/// The text encoding, `application/grpc-web-text`, is not supported.
+pub(crate) fn is_grpc_web(content_type: &str) -> bool {
+	matches!(content_type, "application/grpc-web" | GRPC_WEB_CONTENT_TYPE)
+}
+
+/// Get the message of a unary call from a gRPC-Web request body, which must
+/// be a single uncompressed data frame.
+pub(crate) fn decode_request(body: &[u8]) -> Result<Vec<u8>, GrpcStatus> {
+	let invalid = |message: &str| {
+		Err(GrpcStatus::new(GrpcCode::InvalidArgument, message))
+	};
+
+	let Some((header, payload)) = body.split_at_checked(FRAME_HEADER_LEN)
+	else {
+		return invalid("request body is not a gRPC-Web frame");
+	};
+	match header[0] {
+		DATA_FRAME => {}
+		flags if flags & 0x01 == 0x01 => {
+			return Err(GrpcStatus::new(
+				GrpcCode::Unimplemented,
+				"compressed messages are not supported",
+			))
+		}
+		_ => return invalid("request body is not a data frame"),
+	}
+	let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+	if usize::try_from(len).ok() != Some(payload.len()) {
+		return invalid("request body must be exactly one data frame");
+	}
+
+	Ok(payload.to_vec())
+}
+
+/// Custom metadata of a call: its request headers, leaving out hop by hop
+/// headers, headers describing the HTTP request itself and reserved
+/// `grpc-` headers.
+pub(crate) fn request_metadata(headers: &HeaderMap) -> Vec<(String, String)> {
+	headers
+		.iter()
+		.filter(|(name, _)| {
+			http_proxy::forwarded(name)
+				&& ![CONTENT_TYPE, CONTENT_LENGTH, HOST].contains(name)
+				&& !name.as_str().starts_with("grpc-")
+		})
+		.filter_map(|(name, value)| {
+			Some((name.to_string(), value.to_str().ok()?.to_string()))
+		})
+		.collect()
+}
+
+/// Headers for the custom metadata of a response. Metadata that is not a
+/// valid header is left out.
+pub(crate) fn response_headers(response: &AppGrpcResponse) -> HeaderMap {
+	let mut headers = HeaderMap::new();
+	for (name, value) in &response.metadata {
+		if let (Ok(name), Ok(value)) = (
+			HeaderName::from_bytes(name.as_bytes()),
+			HeaderValue::from_str(value),
+		) {
+			headers.append(name, value);
+		}
+	}
+	headers
+}
+
+/// Encode the gRPC-Web response body for `response`: a data frame with the
+/// message, if there is one, and the trailers frame.
+pub(crate) fn encode_response(response: &AppGrpcResponse) -> Vec<u8> {
+	let mut body = vec![];
+	if let Some(message) = &response.message {
+		push_frame(&mut body, DATA_FRAME, message);
+	}
+	push_frame(&mut body, TRAILERS_FRAME, &trailers(&response.status));
+	body
+}
+
+fn trailers(status: &GrpcStatus) -> Vec<u8> {
+	let mut trailers = format!("grpc-status: {}\r\n", status.code as u8);
+	if !status.message.is_empty() {
+		trailers.push_str(&format!(
+			"grpc-message: {}\r\n",
+			percent_encode(&status.message)
+		));
+	}
+	trailers.into_bytes()
+}
+
+fn push_frame(body: &mut Vec<u8>, flags: u8, payload: &[u8]) {
+	let len = u32::try_from(payload.len())
+		.expect("frames are bounded by the max message size. qed.");
+	body.push(flags);
+	body.extend_from_slice(&len.to_be_bytes());
+	body.extend_from_slice(payload);
+}
+
+/// Percent encode `message` for the `grpc-message` trailer, which must be
+/// printable ASCII without `%`.
+fn percent_encode(message: &str) -> String {
+	message
+		.bytes()
+		.map(|byte| {
+			if (0x20..=0x7e).contains(&byte) && byte != b'%' {
+				char::from(byte).to_string()
+			} else {
+				format!("%{byte:02X}")
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn decodes_single_data_frames() {
+		assert_eq!(decode_request(b"\x00\x00\x00\x00\x02hi").unwrap(), b"hi");
+		assert_eq!(decode_request(b"\x00\x00\x00\x00\x00").unwrap(), b"");
+
+		assert_eq!(
+			decode_request(b"\x01\x00\x00\x00\x02hi").unwrap_err().code,
+			GrpcCode::Unimplemented
+		);
+		for body in [
+			&b"\x00\x00\x00"[..],
+			b"\x80\x00\x00\x00\x02hi",
+			b"\x00\x00\x00\x00\x03hi",
+			b"\x00\x00\x00\x00\x01hi",
+		] {
+			assert_eq!(
+				decode_request(body).unwrap_err().code,
+				GrpcCode::InvalidArgument
+			);
+		}
+	}
+
+	#[test]
+	fn encodes_messages_and_trailers() {
+		assert_eq!(
+			encode_response(&AppGrpcResponse::ok(b"hi".to_vec())),
+			b"\x00\x00\x00\x00\x02hi\x80\x00\x00\x00\x10grpc-status: 0\r\n"
+		);
+		assert_eq!(
+			encode_response(&AppGrpcResponse::error(GrpcStatus::new(
+				GrpcCode::NotFound,
+				"no 100% é"
+			))),
+			b"\x80\x00\x00\x00\x30grpc-status: 5\r\ngrpc-message: no 100%25 %C3%A9\r\n"
+		);
+	}
+
+	#[test]
+	fn forwards_custom_metadata() {
+		let mut headers = HeaderMap::new();
+		headers.insert(CONTENT_TYPE, HeaderValue::from_static("x"));
+		headers.insert("grpc-timeout", HeaderValue::from_static("1S"));
+		headers.insert("connection", HeaderValue::from_static("close"));
+		headers.insert("x-request-id", HeaderValue::from_static("7"));
+
+		assert_eq!(
+			request_metadata(&headers),
+			vec![("x-request-id".to_string(), "7".to_string())]
+		);
+	}
+}
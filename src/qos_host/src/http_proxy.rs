@@ -0,0 +1,152 @@
+//! Encoding of raw HTTP/1.1 messages exchanged with a pivot that speaks HTTP
+//! over the app socket. The host sends the encoded request as the data of a
+//! [`qos_core::protocol::msg::ProtocolMsg::ProxyRequest`] and expects an
+//! encoded response back.
+
+use axum::http::{
+	header::{
+		HeaderName, CONNECTION, CONTENT_LENGTH, PROXY_AUTHENTICATE,
+		PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+	},
+	HeaderMap, HeaderValue, Method, StatusCode,
+};
+
+/// Most headers a response from the pivot may have.
+const MAX_HEADERS: usize = 64;
+
+/// Headers that are not forwarded, because they only apply to a single
+/// connection or, like `Content-Length`, are set anew.
+const NOT_FORWARDED: [HeaderName; 8] = [
+	CONNECTION,
+	CONTENT_LENGTH,
+	PROXY_AUTHENTICATE,
+	PROXY_AUTHORIZATION,
+	TE,
+	TRAILER,
+	TRANSFER_ENCODING,
+	UPGRADE,
+];
+
+/// A response from the pivot.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HttpResponse {
+	pub(crate) status: StatusCode,
+	pub(crate) headers: HeaderMap,
+	pub(crate) body: Vec<u8>,
+}
+
+/// Encode a request for `path_and_query` as HTTP/1.1, leaving out hop by hop
+/// headers.
+pub(crate) fn encode_request(
+	method: &Method,
+	path_and_query: &str,
+	headers: &HeaderMap,
+	body: &[u8],
+) -> Vec<u8> {
+	let mut request =
+		format!("{method} {path_and_query} HTTP/1.1\r\n").into_bytes();
+	for (name, value) in headers.iter().filter(|(name, _)| forwarded(name)) {
+		request.extend_from_slice(name.as_str().as_bytes());
+		request.extend_from_slice(b": ");
+		request.extend_from_slice(value.as_bytes());
+		request.extend_from_slice(b"\r\n");
+	}
+	request.extend_from_slice(
+		format!("{CONTENT_LENGTH}: {}\r\n\r\n", body.len()).as_bytes(),
+	);
+	request.extend_from_slice(body);
+
+	request
+}
+
+/// Decode an HTTP/1.1 response, leaving out hop by hop headers. The body is
+/// everything after the head, cut to the `Content-Length` if there is one.
+pub(crate) fn decode_response(response: &[u8]) -> Result<HttpResponse, String> {
+	let mut parsed_headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+	let mut parsed = httparse::Response::new(&mut parsed_headers);
+	let head_len = match parsed.parse(response) {
+		Ok(httparse::Status::Complete(len)) => len,
+		Ok(httparse::Status::Partial) => {
+			return Err("incomplete response head".to_string())
+		}
+		Err(e) => return Err(format!("invalid response: {e}")),
+	};
+
+	let status = parsed
+		.code
+		.and_then(|code| StatusCode::from_u16(code).ok())
+		.ok_or_else(|| "invalid status code".to_string())?;
+
+	let mut headers = HeaderMap::new();
+	let mut content_length = None;
+	for header in parsed.headers.iter() {
+		let name = HeaderName::from_bytes(header.name.as_bytes())
+			.map_err(|e| format!("invalid header name: {e}"))?;
+		let value = HeaderValue::from_bytes(header.value)
+			.map_err(|e| format!("invalid header value: {e}"))?;
+		if name == CONTENT_LENGTH {
+			content_length = Some(
+				value
+					.to_str()
+					.ok()
+					.and_then(|len| len.parse::<usize>().ok())
+					.ok_or_else(|| "invalid content length".to_string())?,
+			);
+		}
+		if forwarded(&name) {
+			headers.append(name, value);
+		}
+	}
+
+	let body = &response[head_len..];
+	let body = match content_length {
+		Some(len) => body.get(..len).ok_or_else(|| {
+			"body shorter than its content length".to_string()
+		})?,
+		None => body,
+	};
+
+	Ok(HttpResponse { status, headers, body: body.to_vec() })
+}
+
+pub(crate) fn forwarded(name: &HeaderName) -> bool {
+	!NOT_FORWARDED.contains(name)
+		&& !name.as_str().eq_ignore_ascii_case("keep-alive")
+}
+
+#[cfg(test)]
+mod test {
+	use axum::http::header::CONTENT_TYPE;
+
+	use super::*;
+
+	#[test]
+	fn encodes_requests_without_hop_by_hop_headers() {
+		let mut headers = HeaderMap::new();
+		headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+		headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+
+		assert_eq!(
+			encode_request(&Method::POST, "/sign?key=1", &headers, b"hi"),
+			b"POST /sign?key=1 HTTP/1.1\r\ncontent-type: text/plain\r\ncontent-length: 2\r\n\r\nhi"
+		);
+	}
+
+	#[test]
+	fn decodes_responses() {
+		let response = decode_response(
+			b"HTTP/1.1 201 Created\r\nContent-Type: text/plain\r\nConnection: close\r\nContent-Length: 5\r\n\r\nhello trailing",
+		)
+		.unwrap();
+		assert_eq!(response.status, StatusCode::CREATED);
+		assert_eq!(response.headers.len(), 1);
+		assert_eq!(response.headers[CONTENT_TYPE], "text/plain");
+		assert_eq!(response.body, b"hello");
+
+		assert!(decode_response(b"HTTP/1.1 200 OK\r\n").is_err());
+		assert!(decode_response(
+			b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nshort"
+		)
+		.is_err());
+	}
+}
@@ -0,0 +1,617 @@
+//! Mapping from REST style HTTP routes to the borsh encoded messages of a
+//! pivot, so external callers get a JSON API while the app keeps its borsh
+//! protocol. See [`crate::HostServer::with_ingress_map`].
+//!
+//! The map describes the variants of the app's request and response enums
+//! in TOML:
+//!
+//! ```toml
+//! # `POST <BASE>/api/keys/:name/sign` with the body `{"payload": "<hex>"}` is
+//! # sent as variant 1 of the request enum,
+//! # `Sign { name: String, payload: Vec<u8> }`.
+//! [[route]]
+//! method = "POST"
+//! path = "/keys/:name/sign"
+//! variant = 1
+//! fields = [
+//!   { name = "name", type = "string" },
+//!   { name = "payload", type = "bytes" },
+//! ]
+//!
+//! # Variant 0 of the response enum, `Signature { signature: Vec<u8> }`, is
+//! # answered with a 200 and the body `{"signature": "<hex>"}`.
+//! [[response]]
+//! variant = 0
+//! status = 200
+//! fields = [{ name = "signature", type = "bytes" }]
+//! ```
+//!
+//! Fields are encoded in the order they are listed. Each one is taken from
+//! the path parameter of the same name if there is one, and from the JSON
+//! object in the request body otherwise. The types are `bool`, `u8`, `u16`,
+//! `u32`, `u64`, `i32`, `i64`, `string`, `bytes` (hex in JSON), `option<T>`
+//! (`null` or left out for `None`) and `vec<T>`.
+
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use axum::http::{Method, StatusCode};
+use serde_json::{Map, Value};
+
+/// Type of a field of a request or response variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldType {
+	Bool,
+	U8,
+	U16,
+	U32,
+	U64,
+	I32,
+	I64,
+	String,
+	Bytes,
+	Option(Box<FieldType>),
+	Vec(Box<FieldType>),
+}
+
+impl TryFrom<&str> for FieldType {
+	type Error = String;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		let generic = |prefix: &str| {
+			s.strip_prefix(prefix).and_then(|s| s.strip_suffix('>'))
+		};
+		if let Some(inner) = generic("option<") {
+			return Ok(Self::Option(Box::new(inner.trim().try_into()?)));
+		}
+		if let Some(inner) = generic("vec<") {
+			return Ok(Self::Vec(Box::new(inner.trim().try_into()?)));
+		}
+
+		match s {
+			"bool" => Ok(Self::Bool),
+			"u8" => Ok(Self::U8),
+			"u16" => Ok(Self::U16),
+			"u32" => Ok(Self::U32),
+			"u64" => Ok(Self::U64),
+			"i32" => Ok(Self::I32),
+			"i64" => Ok(Self::I64),
+			"string" => Ok(Self::String),
+			"bytes" => Ok(Self::Bytes),
+			_ => Err(format!("unknown type `{s}`")),
+		}
+	}
+}
+
+impl fmt::Display for FieldType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Bool => write!(f, "bool"),
+			Self::U8 => write!(f, "u8"),
+			Self::U16 => write!(f, "u16"),
+			Self::U32 => write!(f, "u32"),
+			Self::U64 => write!(f, "u64"),
+			Self::I32 => write!(f, "i32"),
+			Self::I64 => write!(f, "i64"),
+			Self::String => write!(f, "string"),
+			Self::Bytes => write!(f, "bytes"),
+			Self::Option(inner) => write!(f, "option<{inner}>"),
+			Self::Vec(inner) => write!(f, "vec<{inner}>"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+	name: String,
+	ty: FieldType,
+}
+
+/// A route and the request variant it is sent as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IngressRoute {
+	method: Method,
+	/// Segments of the path; `:name` segments are parameters.
+	segments: Vec<String>,
+	variant: u8,
+	fields: Vec<Field>,
+}
+
+impl IngressRoute {
+	/// Encode the request variant for this route, taking fields from
+	/// `params` and the JSON object in `body`.
+	pub(crate) fn encode_request(
+		&self,
+		params: &BTreeMap<String, String>,
+		body: &[u8],
+	) -> Result<Vec<u8>, String> {
+		let body = if body.is_empty() {
+			Map::new()
+		} else {
+			match serde_json::from_slice(body) {
+				Ok(Value::Object(body)) => body,
+				_ => return Err("body must be a JSON object".to_string()),
+			}
+		};
+
+		let mut encoded = vec![self.variant];
+		for Field { name, ty } in &self.fields {
+			let value = match params.get(name) {
+				Some(param) => param_value(ty, param),
+				None => Some(body.get(name).cloned().unwrap_or(Value::Null)),
+			};
+			value
+				.and_then(|value| encode_value(ty, &value, &mut encoded))
+				.ok_or_else(|| format!("`{name}` must be {ty}"))?;
+		}
+
+		Ok(encoded)
+	}
+}
+
+/// How a response variant is answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IngressResponse {
+	status: StatusCode,
+	fields: Vec<Field>,
+}
+
+/// Routes and responses of a pivot's JSON API. See the [module
+/// docs](self) for the format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngressMap {
+	routes: Vec<IngressRoute>,
+	responses: BTreeMap<u8, IngressResponse>,
+}
+
+impl IngressMap {
+	/// Read the map from the TOML file at `path`.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+		let path = path.as_ref();
+		let contents = fs::read_to_string(path)
+			.map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+
+		Self::from_toml(&contents)
+	}
+
+	/// Parse the map from TOML.
+	pub fn from_toml(contents: &str) -> Result<Self, String> {
+		let map: toml_edit::Document =
+			contents.parse().map_err(|e| format!("not valid TOML: {e}"))?;
+		if let Some((key, _)) =
+			map.iter().find(|(key, _)| !["route", "response"].contains(key))
+		{
+			return Err(format!("unknown key `{key}`"));
+		}
+
+		let mut routes = vec![];
+		for table in tables(&map, "route")? {
+			check_keys(table, &["method", "path", "variant", "fields"])?;
+			let method = table
+				.get("method")
+				.and_then(toml_edit::Item::as_str)
+				.and_then(|m| {
+					Method::from_bytes(m.to_ascii_uppercase().as_bytes()).ok()
+				})
+				.ok_or("`method` of a route must be an HTTP method")?;
+			let path = table
+				.get("path")
+				.and_then(toml_edit::Item::as_str)
+				.filter(|path| path.starts_with('/'))
+				.ok_or("`path` of a route must start with `/`")?;
+
+			routes.push(IngressRoute {
+				method,
+				segments: segments(path).map(String::from).collect(),
+				variant: variant(table)?,
+				fields: fields(table)?,
+			});
+		}
+
+		let mut responses = BTreeMap::new();
+		for table in tables(&map, "response")? {
+			check_keys(table, &["variant", "status", "fields"])?;
+			let variant = variant(table)?;
+			let status = table
+				.get("status")
+				.and_then(toml_edit::Item::as_integer)
+				.and_then(|status| u16::try_from(status).ok())
+				.and_then(|status| StatusCode::from_u16(status).ok())
+				.ok_or("`status` of a response must be an HTTP status")?;
+			let response = IngressResponse { status, fields: fields(table)? };
+			if responses.insert(variant, response).is_some() {
+				return Err(format!(
+					"response variant {variant} is mapped twice"
+				));
+			}
+		}
+
+		Ok(Self { routes, responses })
+	}
+
+	/// The first route matching `method` and `path`, with the values of its
+	/// path parameters.
+	pub(crate) fn find(
+		&self,
+		method: &Method,
+		path: &str,
+	) -> Option<(&IngressRoute, BTreeMap<String, String>)> {
+		let path: Vec<_> = segments(path).collect();
+		self.routes.iter().filter(|route| route.method == method).find_map(
+			|route| {
+				if route.segments.len() != path.len() {
+					return None;
+				}
+				let mut params = BTreeMap::new();
+				for (segment, value) in route.segments.iter().zip(&path) {
+					match segment.strip_prefix(':') {
+						Some(name) => {
+							params
+								.insert(name.to_string(), (*value).to_string());
+						}
+						None if segment == value => {}
+						None => return None,
+					}
+				}
+				Some((route, params))
+			},
+		)
+	}
+
+	/// Decode a response variant of the app into the status and JSON body to
+	/// answer with.
+	pub(crate) fn decode_response(
+		&self,
+		mut data: &[u8],
+	) -> Result<(StatusCode, Value), String> {
+		let [variant] = take_array(&mut data).ok_or("empty response")?;
+		let response = self.responses.get(&variant).ok_or_else(|| {
+			format!("response variant {variant} is not mapped")
+		})?;
+
+		let mut body = Map::new();
+		for Field { name, ty } in &response.fields {
+			let value = decode_value(ty, &mut data)
+				.ok_or_else(|| format!("`{name}` is not a valid {ty}"))?;
+			body.insert(name.clone(), value);
+		}
+		if !data.is_empty() {
+			return Err(format!("response variant {variant} is too long"));
+		}
+
+		Ok((response.status, Value::Object(body)))
+	}
+}
+
+fn tables<'a>(
+	map: &'a toml_edit::Document,
+	key: &str,
+) -> Result<Vec<&'a toml_edit::Table>, String> {
+	match map.get(key) {
+		None => Ok(vec![]),
+		Some(item) => item
+			.as_array_of_tables()
+			.map(|tables| tables.iter().collect())
+			.ok_or_else(|| format!("`{key}` must be an array of tables")),
+	}
+}
+
+fn check_keys(table: &toml_edit::Table, keys: &[&str]) -> Result<(), String> {
+	match table.iter().find(|(key, _)| !keys.contains(key)) {
+		Some((key, _)) => Err(format!("unknown key `{key}`")),
+		None => Ok(()),
+	}
+}
+
+fn variant(table: &toml_edit::Table) -> Result<u8, String> {
+	table
+		.get("variant")
+		.and_then(toml_edit::Item::as_integer)
+		.and_then(|variant| u8::try_from(variant).ok())
+		.ok_or_else(|| "`variant` must be an enum variant index".to_string())
+}
+
+fn fields(table: &toml_edit::Table) -> Result<Vec<Field>, String> {
+	let invalid = "`fields` must be an array of `{ name, type }` tables";
+	let Some(fields) = table.get("fields") else {
+		return Ok(vec![]);
+	};
+
+	fields
+		.as_array()
+		.ok_or(invalid)?
+		.iter()
+		.map(|field| {
+			let field = field.as_inline_table().ok_or(invalid)?;
+			let name = field
+				.get("name")
+				.and_then(toml_edit::Value::as_str)
+				.ok_or(invalid)?;
+			let ty = field
+				.get("type")
+				.and_then(toml_edit::Value::as_str)
+				.ok_or(invalid)?;
+			Ok(Field { name: name.to_string(), ty: ty.try_into()? })
+		})
+		.collect()
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+	path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// The JSON value for a path parameter, which is always a string.
+fn param_value(ty: &FieldType, param: &str) -> Option<Value> {
+	match ty {
+		FieldType::String | FieldType::Bytes => {
+			Some(Value::String(param.to_string()))
+		}
+		FieldType::Bool => param.parse::<bool>().ok().map(Value::from),
+		FieldType::U8 | FieldType::U16 | FieldType::U32 | FieldType::U64 => {
+			param.parse::<u64>().ok().map(Value::from)
+		}
+		FieldType::I32 | FieldType::I64 => {
+			param.parse::<i64>().ok().map(Value::from)
+		}
+		FieldType::Option(inner) => param_value(inner, param),
+		FieldType::Vec(_) => None,
+	}
+}
+
+fn encode_value(
+	ty: &FieldType,
+	value: &Value,
+	out: &mut Vec<u8>,
+) -> Option<()> {
+	match ty {
+		FieldType::Bool => out.push(u8::from(value.as_bool()?)),
+		FieldType::U8 => out.push(u8::try_from(value.as_u64()?).ok()?),
+		FieldType::U16 => out.extend_from_slice(
+			&u16::try_from(value.as_u64()?).ok()?.to_le_bytes(),
+		),
+		FieldType::U32 => out.extend_from_slice(
+			&u32::try_from(value.as_u64()?).ok()?.to_le_bytes(),
+		),
+		FieldType::U64 => out.extend_from_slice(&value.as_u64()?.to_le_bytes()),
+		FieldType::I32 => out.extend_from_slice(
+			&i32::try_from(value.as_i64()?).ok()?.to_le_bytes(),
+		),
+		FieldType::I64 => out.extend_from_slice(&value.as_i64()?.to_le_bytes()),
+		FieldType::String => {
+			let string = value.as_str()?;
+			push_len(out, string.len())?;
+			out.extend_from_slice(string.as_bytes());
+		}
+		FieldType::Bytes => {
+			let bytes = qos_hex::decode(value.as_str()?).ok()?;
+			push_len(out, bytes.len())?;
+			out.extend_from_slice(&bytes);
+		}
+		FieldType::Option(inner) => {
+			if value.is_null() {
+				out.push(0);
+			} else {
+				out.push(1);
+				encode_value(inner, value, out)?;
+			}
+		}
+		FieldType::Vec(inner) => {
+			let items = value.as_array()?;
+			push_len(out, items.len())?;
+			for item in items {
+				encode_value(inner, item, out)?;
+			}
+		}
+	}
+
+	Some(())
+}
+
+/// Push a borsh length prefix.
+fn push_len(out: &mut Vec<u8>, len: usize) -> Option<()> {
+	out.extend_from_slice(&u32::try_from(len).ok()?.to_le_bytes());
+	Some(())
+}
+
+fn decode_value(ty: &FieldType, data: &mut &[u8]) -> Option<Value> {
+	let value = match ty {
+		FieldType::Bool => match take_array::<1>(data)? {
+			[0] => Value::Bool(false),
+			[1] => Value::Bool(true),
+			_ => return None,
+		},
+		FieldType::U8 => u8::from_le_bytes(take_array(data)?).into(),
+		FieldType::U16 => u16::from_le_bytes(take_array(data)?).into(),
+		FieldType::U32 => u32::from_le_bytes(take_array(data)?).into(),
+		FieldType::U64 => u64::from_le_bytes(take_array(data)?).into(),
+		FieldType::I32 => i32::from_le_bytes(take_array(data)?).into(),
+		FieldType::I64 => i64::from_le_bytes(take_array(data)?).into(),
+		FieldType::String => {
+			let len = take_len(data)?;
+			String::from_utf8(take(data, len)?.to_vec()).ok()?.into()
+		}
+		FieldType::Bytes => {
+			let len = take_len(data)?;
+			qos_hex::encode(take(data, len)?).into()
+		}
+		FieldType::Option(inner) => match take_array::<1>(data)? {
+			[0] => Value::Null,
+			[1] => decode_value(inner, data)?,
+			_ => return None,
+		},
+		FieldType::Vec(inner) => {
+			let len = take_len(data)?;
+			// Every item takes at least a byte, so this ends with the data
+			(0..len)
+				.map(|_| decode_value(inner, data))
+				.collect::<Option<Vec<_>>>()?
+				.into()
+		}
+	};
+
+	Some(value)
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+	let (taken, rest) = data.split_at_checked(len)?;
+	*data = rest;
+	Some(taken)
+}
+
+fn take_array<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+	take(data, N)?.try_into().ok()
+}
+
+fn take_len(data: &mut &[u8]) -> Option<usize> {
+	usize::try_from(u32::from_le_bytes(take_array(data)?)).ok()
+}
+
+#[cfg(test)]
+mod test {
+	use borsh::{BorshDeserialize, BorshSerialize};
+
+	use super::*;
+
+	#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+	enum AppRequest {
+		Health,
+		Sign { name: String, payload: Vec<u8>, nonce: Option<u64> },
+	}
+
+	#[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+	enum AppResponse {
+		Signature { signature: Vec<u8>, tags: Vec<String> },
+		Error { code: u16 },
+	}
+
+	const MAP: &str = r#"
+[[route]]
+method = "get"
+path = "/health"
+variant = 0
+
+[[route]]
+method = "POST"
+path = "/keys/:name/sign"
+variant = 1
+fields = [
+  { name = "name", type = "string" },
+  { name = "payload", type = "bytes" },
+  { name = "nonce", type = "option<u64>" },
+]
+
+[[response]]
+variant = 0
+status = 200
+fields = [
+  { name = "signature", type = "bytes" },
+  { name = "tags", type = "vec<string>" },
+]
+
+[[response]]
+variant = 1
+status = 409
+fields = [{ name = "code", type = "u16" }]
+"#;
+
+	#[test]
+	fn requests_are_encoded_as_the_app_enum() {
+		let map = IngressMap::from_toml(MAP).unwrap();
+
+		let (route, params) = map.find(&Method::GET, "/health").unwrap();
+		assert_eq!(
+			route.encode_request(&params, b"").unwrap(),
+			borsh::to_vec(&AppRequest::Health).unwrap()
+		);
+
+		assert!(map.find(&Method::POST, "/keys/sign").is_none());
+		assert!(map.find(&Method::GET, "/keys/k1/sign").is_none());
+		let (route, params) = map.find(&Method::POST, "/keys/k1/sign").unwrap();
+		assert_eq!(
+			route.encode_request(&params, br#"{"payload": "ab01"}"#).unwrap(),
+			borsh::to_vec(&AppRequest::Sign {
+				name: "k1".to_string(),
+				payload: vec![0xab, 0x01],
+				nonce: None,
+			})
+			.unwrap()
+		);
+		assert_eq!(
+			route
+				.encode_request(&params, br#"{"payload": "", "nonce": 7}"#)
+				.unwrap(),
+			borsh::to_vec(&AppRequest::Sign {
+				name: "k1".to_string(),
+				payload: vec![],
+				nonce: Some(7),
+			})
+			.unwrap()
+		);
+
+		for (body, error) in [
+			(&b"[]"[..], "body must be a JSON object"),
+			(b"{}", "`payload` must be bytes"),
+			(br#"{"payload": "zz"}"#, "`payload` must be bytes"),
+			(br#"{"payload": "", "nonce": -1}"#, "`nonce` must be option<u64>"),
+		] {
+			assert_eq!(
+				route.encode_request(&params, body),
+				Err(error.to_string())
+			);
+		}
+	}
+
+	#[test]
+	fn responses_are_decoded_from_the_app_enum() {
+		let map = IngressMap::from_toml(MAP).unwrap();
+
+		let response = borsh::to_vec(&AppResponse::Signature {
+			signature: vec![1, 2],
+			tags: vec!["a".to_string()],
+		})
+		.unwrap();
+		assert_eq!(
+			map.decode_response(&response).unwrap(),
+			(
+				StatusCode::OK,
+				serde_json::json!({ "signature": "0102", "tags": ["a"] })
+			)
+		);
+		let response = borsh::to_vec(&AppResponse::Error { code: 3 }).unwrap();
+		assert_eq!(
+			map.decode_response(&response).unwrap(),
+			(StatusCode::CONFLICT, serde_json::json!({ "code": 3 }))
+		);
+
+		assert!(map.decode_response(&[]).is_err());
+		assert!(map.decode_response(&[2]).is_err());
+		assert!(map.decode_response(&[1, 3]).is_err());
+		assert!(map.decode_response(&[1, 3, 0, 0]).is_err());
+	}
+
+	#[test]
+	fn invalid_maps_are_errors() {
+		for (map, error) in [
+			("routes = []", "unknown key `routes`"),
+			(
+				"[[route]]\nmethod = \"GET\"\npath = \"x\"\nvariant = 0",
+				"`path` of a route must start with `/`",
+			),
+			(
+				"[[route]]\nmethod = \"GET\"\npath = \"/\"\nvariant = 256",
+				"`variant` must be an enum variant index",
+			),
+			(
+				"[[response]]\nvariant = 0\nstatus = 200\n\
+				fields = [{ name = \"a\", type = \"u128\" }]",
+				"unknown type `u128`",
+			),
+			(
+				"[[response]]\nvariant = 0\nstatus = 200\n\
+				[[response]]\nvariant = 0\nstatus = 404",
+				"response variant 0 is mapped twice",
+			),
+		] {
+			assert_eq!(IngressMap::from_toml(map), Err(error.to_string()));
+		}
+	}
+}
@@ -15,56 +15,254 @@
 #![forbid(unsafe_code)]
 #![deny(clippy::all)]
 #![warn(missing_docs, clippy::pedantic)]
-#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+	collections::{BTreeMap, HashMap},
+	net::SocketAddr,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use axum::{
-	body::Bytes,
-	extract::{DefaultBodyLimit, State},
-	http::StatusCode,
+	body::{Body, Full},
+	extract::{
+		rejection::PathRejection, DefaultBodyLimit, Extension,
+		FromRequestParts, OriginalUri, Path, Query, RawBody, State,
+	},
+	http::{
+		header::{
+			CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER,
+			SEC_WEBSOCKET_ACCEPT, UPGRADE,
+		},
+		request::Parts,
+		HeaderMap, HeaderValue, Request, StatusCode,
+	},
+	middleware,
 	response::{Html, IntoResponse, Response},
-	routing::{get, post},
+	routing::{any, get, post},
 	Json, Router,
 };
 use borsh::BorshDeserialize;
+use hyper::body::HttpBody;
 use qos_core::{
-	client::Client,
-	io::{SocketAddress, TimeVal, TimeValLike},
+	audit::AuditLog,
+	client::{Client, ClientError},
+	io::{
+		Backoff, IOError, SocketAddress, SocketPermissions, TimeVal,
+		TimeValLike,
+	},
+	log::{self, LogBatch, MAX_LOGS_PER_REQUEST},
 	protocol::{
-		msg::ProtocolMsg, services::boot::ManifestEnvelope, Hash256,
-		ProtocolError, ProtocolPhase, ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
+		app_grpc::{AppGrpcRequest, AppGrpcResponse, GrpcCode, GrpcStatus},
+		app_health::AppHealth,
+		msg::ProtocolMsg,
+		services::boot::ManifestEnvelope,
+		status::EnclaveStatus,
+		trace::TraceId,
+		Hash256, ProtocolError, ProtocolPhase,
+		ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
 	},
+	time::{SystemClock, TimeSource},
 };
+use sha2::{Digest, Sha256};
 
+pub mod audit;
+pub mod circuit;
 pub mod cli;
+mod cors;
+mod grpc_web;
+mod http_proxy;
+pub mod ingress;
+mod spool;
+pub mod tls;
+mod trace;
+mod ws;
+
+use audit::{AuditEntry, AUDIT_LOG_CAPACITY};
+use circuit::{CircuitBreaker, CircuitState};
+use cors::Cors;
+use ingress::IngressMap;
+use spool::{Spool, SpoolError};
+use tls::{ClientCertificate, HostTls};
+use ws::{Message, WebSocket, WsError};
 
 const MEGABYTE: usize = 1024 * 1024;
-const MAX_ENCODED_MSG_LEN: usize = 256 * MEGABYTE;
+/// Default for [`HostServer::with_max_message_size`].
+pub const MAX_ENCODED_MSG_LEN: usize = 256 * MEGABYTE;
 const QOS_SOCKET_CLIENT_TIMEOUT_SECS: i64 =
 	ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS + 2;
+/// Default for [`HostServer::with_enclave_backoff`]. Keeps retrying to connect
+/// to the enclave for a few seconds, e.g. while it is booting the pivot.
+pub const ENCLAVE_CLIENT_BACKOFF: Backoff = Backoff {
+	initial: Duration::from_millis(50),
+	max: Duration::from_millis(500),
+	max_elapsed: Duration::from_secs(3),
+};
 
 /// Simple error that implements [`IntoResponse`] so it can
 /// be returned from handlers as an http response (and not get silently
 /// dropped).
-struct Error(String);
+enum Error {
+	/// Responds with a 500.
+	Internal(String),
+	/// The enclave circuit is open. Responds with a 503 asking the client to
+	/// retry after the given time.
+	Unavailable(Duration),
+}
+
+impl Error {
+	/// Error for a failed `request` to the enclave.
+	fn enclave(request: &str, err: EnclaveError) -> Self {
+		match err {
+			EnclaveError::CircuitOpen(retry_after) => {
+				Self::Unavailable(retry_after)
+			}
+			EnclaveError::Client(e) => Self::Internal(format!(
+				"error while trying to send {request} socket request to enclave: {e:?}"
+			)),
+		}
+	}
+}
 
 impl IntoResponse for Error {
 	fn into_response(self) -> Response {
-		let body = JsonError { error: self.0 };
-		eprintln!("qos_host error: {body:?}");
-
-		// In the future we may want to change `Error` into an enum
-		// indicating what status code to use. For now it will always be
-		// an internal error since we don't need to express other error types.
-		(StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
+		match self {
+			Self::Internal(error) => {
+				let body = JsonError { error };
+				log::error("host", format_args!("qos_host error: {body:?}"));
+				(StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
+			}
+			Self::Unavailable(retry_after) => {
+				let body = JsonError { error: ENCLAVE_UNAVAILABLE.to_string() };
+				log::error("host", format_args!("qos_host error: {body:?}"));
+				(
+					StatusCode::SERVICE_UNAVAILABLE,
+					[(RETRY_AFTER, retry_after_secs(retry_after))],
+					Json(body),
+				)
+					.into_response()
+			}
+		}
 	}
 }
 
+const ENCLAVE_UNAVAILABLE: &str =
+	"enclave unavailable after repeated failures, retry later";
+
+/// Value for the `Retry-After` header: `retry_after` in whole seconds, rounded
+/// up and at least 1.
+fn retry_after_secs(retry_after: Duration) -> String {
+	let secs =
+		retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+	secs.max(1).to_string()
+}
+
+/// Reasons a request to the enclave can fail.
+enum EnclaveError {
+	/// The circuit is open; retry after the given time.
+	CircuitOpen(Duration),
+	/// Talking to the enclave failed.
+	Client(ClientError),
+}
+
 /// Resource shared across tasks in the [`HostServer`].
 #[derive(Debug)]
 struct QosHostState {
-	enclave_client: Client,
+	enclave: Arc<EnclaveConnection>,
+	namespaces: HashMap<String, Arc<EnclaveConnection>>,
+	require_client_cert: bool,
+	max_message_size: usize,
+	audit: AuditLog,
+	ingress: IngressMap,
+}
+
+/// Client and circuit breaker for a single enclave.
+#[derive(Debug)]
+struct EnclaveConnection {
+	client: Client,
+	circuit: Arc<CircuitBreaker>,
+}
+
+impl EnclaveConnection {
+	/// Send `request` to the enclave, unless the circuit is open, and return
+	/// its response. The outcome is recorded in the circuit.
+	async fn send(&self, request: Vec<u8>) -> Result<Vec<u8>, EnclaveError> {
+		self.circuit.try_acquire().map_err(EnclaveError::CircuitOpen)?;
+
+		let client = self.client.clone();
+		let result = tokio::task::spawn_blocking(move || client.send(&request))
+			.await
+			.expect("sending a request to the enclave panicked. qed.");
+		self.circuit.record(result.is_ok());
+
+		result.map_err(EnclaveError::Client)
+	}
+}
+
+/// [`EnclaveConnection`] for a single request to the host. If tracing is
+/// enabled, what is sent to the enclave is wrapped in a
+/// [`ProtocolMsg::TracedRequest`] with the trace ID of the request.
+struct Enclave {
+	connection: Arc<EnclaveConnection>,
+	trace_id: Option<TraceId>,
+}
+
+impl Enclave {
+	/// Like [`EnclaveConnection::send`], but tracing `request`.
+	async fn send(&self, request: Vec<u8>) -> Result<Vec<u8>, EnclaveError> {
+		let request = match &self.trace_id {
+			Some(trace_id) => [trace_id.wrap_prefix(), request].concat(),
+			None => request,
+		};
+		self.connection.send(request).await
+	}
+
+	/// Bytes to send before an encoded request to trace it, empty if tracing
+	/// is disabled. See [`TraceId::wrap_prefix`].
+	fn trace_prefix(&self) -> Vec<u8> {
+		self.trace_id.as_ref().map(TraceId::wrap_prefix).unwrap_or_default()
+	}
+}
+
+/// The enclave a request is for: the enclave serving `{namespace}` on routes
+/// under `/ns/{namespace}`, the default enclave on all others.
+struct TargetEnclave(Enclave);
+
+#[axum::async_trait]
+impl FromRequestParts<Arc<QosHostState>> for TargetEnclave {
+	type Rejection = Response;
+
+	async fn from_request_parts(
+		parts: &mut Parts,
+		state: &Arc<QosHostState>,
+	) -> Result<Self, Self::Rejection> {
+		let params = match Path::<HashMap<String, String>>::from_request_parts(
+			parts, state,
+		)
+		.await
+		{
+			Ok(Path(params)) => params,
+			Err(PathRejection::MissingPathParams(_)) => HashMap::new(),
+			Err(e) => return Err(e.into_response()),
+		};
+
+		let connection = match params.get(NAMESPACE) {
+			None => state.enclave.clone(),
+			Some(namespace) => {
+				state.namespaces.get(namespace).cloned().ok_or_else(|| {
+					(
+						StatusCode::NOT_FOUND,
+						Html(format!("unknown namespace: {namespace}")),
+					)
+						.into_response()
+				})?
+			}
+		};
+		let trace_id = parts.extensions.get::<TraceId>().cloned();
+
+		Ok(Self(Enclave { connection, trace_id }))
+	}
 }
 
 /// HTTP server for the host of the enclave; proxies requests to the enclave.
@@ -72,12 +270,39 @@ pub struct HostServer {
 	enclave_addr: SocketAddress,
 	addr: SocketAddr,
 	base_path: Option<String>,
+	enclave_timeout: TimeVal,
+	tls: Option<HostTls>,
+	max_message_size: usize,
+	socket_permissions: Option<SocketPermissions>,
+	enclave_backoff: Backoff,
+	circuit: Arc<CircuitBreaker>,
+	namespaces: BTreeMap<String, SocketAddress>,
+	cors_origins: Vec<String>,
+	app_http_proxy: bool,
+	app_grpc_proxy: bool,
+	ingress: Option<IngressMap>,
+	audit_log_capacity: usize,
+	time: Arc<dyn TimeSource>,
+	trace_ids: bool,
 }
 
 const HOST_HEALTH: &str = "/host-health";
 const ENCLAVE_HEALTH: &str = "/enclave-health";
+const DEEP_HEALTH: &str = "/health/deep";
 const MESSAGE: &str = "/message";
 const ENCLAVE_INFO: &str = "/enclave-info";
+const ENCLAVE_STATUS: &str = "/enclave-status";
+const LOGS: &str = "/logs";
+const APP_WS: &str = "/app/ws";
+const AUDIT_LOG: &str = "/audit-log";
+const APP_HTTP: &str = "/app/*path";
+const APP_GRPC: &str = "/grpc/*path";
+const APP_API: &str = "/api/*path";
+/// Path parameter with the path to request from the app on [`APP_HTTP`],
+/// [`APP_GRPC`] and [`APP_API`].
+const APP_PATH: &str = "path";
+/// Path parameter selecting the enclave on namespaced routes.
+const NAMESPACE: &str = "namespace";
 
 /// Response body to the `/enclave-info` endpoint.
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -89,6 +314,64 @@ pub struct EnclaveInfo {
 	pub manifest_envelope: Option<ManifestEnvelope>,
 }
 
+/// Response body to the `/host-health` endpoint.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostHealth {
+	/// State of the circuit breaker guarding the enclave.
+	pub enclave_circuit: CircuitState,
+	/// Failed requests to the enclave since the last successful one.
+	pub consecutive_failures: u32,
+}
+
+/// Overall health reported by the `/health/deep` endpoint.
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthState {
+	/// The host is up and the enclave responded in a phase where it can
+	/// serve requests.
+	Healthy,
+	/// The host is up and the enclave responded, but it is not provisioned
+	/// yet or failed unrecoverably.
+	EnclaveNotReady,
+	/// The host is up but the enclave did not respond.
+	EnclaveUnreachable,
+	/// The enclave is ready, but the pivot reported that it is not, or did
+	/// not answer its health probe.
+	AppNotReady,
+}
+
+/// Response body to the `/health/deep` endpoint.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepHealth {
+	/// Overall health.
+	pub state: HealthState,
+	/// Phase the enclave reported, if it responded.
+	pub phase: Option<ProtocolPhase>,
+	/// Milliseconds the round trip to the enclave took, if it responded.
+	pub round_trip_ms: Option<u64>,
+	/// Health the pivot reported, if the enclave is provisioned and the
+	/// pivot speaks the health protocol.
+	pub app: Option<AppHealth>,
+	/// State of the circuit breaker guarding the enclave.
+	pub enclave_circuit: CircuitState,
+	/// Why the enclave is unreachable, or why the pivot did not answer its
+	/// health probe.
+	pub error: Option<String>,
+}
+
+/// Query of the `/logs` endpoint.
+#[derive(Debug, Default, serde::Deserialize)]
+struct LogsQuery {
+	/// Sequence number of the first entry to read, 0 if not set.
+	since: Option<u64>,
+	/// Most entries to read, [`MAX_LOGS_PER_REQUEST`] if not set.
+	max: Option<u32>,
+}
+
 /// Vitals we just use for logging right now to avoid logging the entire
 /// manifest.
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -120,7 +403,158 @@ impl HostServer {
 		addr: SocketAddr,
 		base_path: Option<String>,
 	) -> Self {
-		Self { enclave_addr, addr, base_path }
+		Self {
+			enclave_addr,
+			addr,
+			base_path,
+			enclave_timeout: TimeVal::seconds(QOS_SOCKET_CLIENT_TIMEOUT_SECS),
+			tls: None,
+			max_message_size: MAX_ENCODED_MSG_LEN,
+			socket_permissions: None,
+			enclave_backoff: ENCLAVE_CLIENT_BACKOFF,
+			circuit: Arc::default(),
+			namespaces: BTreeMap::new(),
+			cors_origins: Vec::new(),
+			app_http_proxy: false,
+			app_grpc_proxy: false,
+			ingress: None,
+			audit_log_capacity: AUDIT_LOG_CAPACITY,
+			time: Arc::new(SystemClock),
+			trace_ids: false,
+		}
+	}
+
+	/// Use `timeout` for each send and receive when talking to the enclave.
+	#[must_use]
+	pub fn with_enclave_timeout(mut self, timeout: TimeVal) -> Self {
+		self.enclave_timeout = timeout;
+		self
+	}
+
+	/// Serve over TLS. See [`HostTls::load`] for requiring client
+	/// certificates on the message route.
+	#[must_use]
+	pub fn with_tls(mut self, tls: HostTls) -> Self {
+		self.tls = Some(tls);
+		self
+	}
+
+	/// Reject messages larger than `max_message_size` bytes instead of
+	/// [`MAX_ENCODED_MSG_LEN`]. Large messages are buffered in a temporary
+	/// file rather than in memory, so this bounds the disk space used per
+	/// request.
+	#[must_use]
+	pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+		self.max_message_size = max_message_size;
+		self
+	}
+
+	/// Refuse to talk to the enclave unless its unix socket file has exactly
+	/// `permissions`, so messages are not sent to a socket some other process
+	/// could have bound.
+	#[must_use]
+	pub fn with_socket_permissions(
+		mut self,
+		permissions: SocketPermissions,
+	) -> Self {
+		self.socket_permissions = Some(permissions);
+		self
+	}
+
+	/// Retry connecting to the enclave with `backoff` instead of
+	/// [`ENCLAVE_CLIENT_BACKOFF`].
+	#[must_use]
+	pub fn with_enclave_backoff(mut self, backoff: Backoff) -> Self {
+		self.enclave_backoff = backoff;
+		self
+	}
+
+	/// Fail fast according to `circuit` instead of a default
+	/// [`CircuitBreaker`] when requests to the enclave keep failing.
+	#[must_use]
+	pub fn with_circuit_breaker(mut self, circuit: CircuitBreaker) -> Self {
+		self.circuit = Arc::new(circuit);
+		self
+	}
+
+	/// Also proxy to the enclave at `enclave_addr` under
+	/// `/ns/{namespace}`, e.g. `/qos/ns/{namespace}/message`. All routes are
+	/// available per namespace; the enclave given to [`Self::new`] remains
+	/// the one served on the routes without a namespace.
+	#[must_use]
+	pub fn with_namespace(
+		mut self,
+		namespace: String,
+		enclave_addr: SocketAddress,
+	) -> Self {
+		self.namespaces.insert(namespace, enclave_addr);
+		self
+	}
+
+	/// Let web pages served from `origins` call the host's routes, e.g. a UI
+	/// for reviewing manifests. `*` allows any origin.
+	#[must_use]
+	pub fn with_cors_origins(mut self, origins: Vec<String>) -> Self {
+		self.cors_origins = origins;
+		self
+	}
+
+	/// Forward HTTP requests under `/app/` to a pivot that speaks HTTP over
+	/// the app socket, e.g. `/qos/app/sign` as a request for `/sign`. Each
+	/// request is sent to the app encoded as HTTP/1.1 in a
+	/// [`ProtocolMsg::ProxyRequest`], and the app responds with an HTTP/1.1
+	/// encoded response.
+	#[must_use]
+	pub fn with_app_http_proxy(mut self) -> Self {
+		self.app_http_proxy = true;
+		self
+	}
+
+	/// Bridge unary gRPC-Web calls under `/grpc/` to a pivot that serves
+	/// gRPC methods over the app socket, e.g. `/qos/grpc/echo.Echo/Say` as a
+	/// call to `/echo.Echo/Say`. Each call is sent to the app as an
+	/// [`AppGrpcRequest`] in a [`ProtocolMsg::ProxyRequest`], and the app
+	/// responds with an [`AppGrpcResponse`].
+	#[must_use]
+	pub fn with_app_grpc_proxy(mut self) -> Self {
+		self.app_grpc_proxy = true;
+		self
+	}
+
+	/// Serve a JSON API under `/api/` for a pivot that speaks borsh over the
+	/// app socket, e.g. `/qos/api/keys/k1/sign`, translating requests and
+	/// responses with `ingress`. Each request is sent to the app as the
+	/// encoded request variant of its route in a
+	/// [`ProtocolMsg::ProxyRequest`], see [`ingress`].
+	#[must_use]
+	pub fn with_ingress_map(mut self, ingress: IngressMap) -> Self {
+		self.ingress = Some(ingress);
+		self
+	}
+
+	/// Keep the latest `capacity` entries of the audit log, instead of
+	/// [`AUDIT_LOG_CAPACITY`], for export under `/audit-log`.
+	#[must_use]
+	pub fn with_audit_log_capacity(mut self, capacity: usize) -> Self {
+		self.audit_log_capacity = capacity;
+		self
+	}
+
+	/// Timestamp audit log entries with `time` instead of the system clock.
+	#[must_use]
+	pub fn with_time_source(mut self, time: Arc<dyn TimeSource>) -> Self {
+		self.time = time;
+		self
+	}
+
+	/// Assign each request a trace ID and send it to the enclave and the
+	/// pivot along with the request, see [`qos_core::protocol::trace`]. The
+	/// enclave must run a version of QOS that understands
+	/// [`ProtocolMsg::TracedRequest`].
+	#[must_use]
+	pub fn with_trace_ids(mut self) -> Self {
+		self.trace_ids = true;
+		self
 	}
 
 	fn path(&self, endpoint: &str) -> String {
@@ -131,6 +565,23 @@ impl HostServer {
 		}
 	}
 
+	/// Connection to the enclave at `enclave_addr`, failing fast according
+	/// to `circuit`.
+	fn connect(
+		&self,
+		enclave_addr: &SocketAddress,
+		circuit: Arc<CircuitBreaker>,
+	) -> Arc<EnclaveConnection> {
+		let client = Client::new(enclave_addr.clone(), self.enclave_timeout)
+			.with_backoff(self.enclave_backoff);
+		let client = match self.socket_permissions {
+			Some(permissions) => client.with_socket_permissions(permissions),
+			None => client,
+		};
+
+		Arc::new(EnclaveConnection { client, circuit })
+	}
+
 	/// Start the server, running indefinitely.
 	///
 	/// # Panics
@@ -138,52 +589,176 @@ impl HostServer {
 	/// Panics if there is an issue starting the server.
 	// pub async fn serve(&self) -> Result<(), String> {
 	pub async fn serve(&self) {
+		let namespaces = self
+			.namespaces
+			.iter()
+			.map(|(namespace, enclave_addr)| {
+				let circuit = Arc::new(self.circuit.new_like());
+				(namespace.clone(), self.connect(enclave_addr, circuit))
+			})
+			.collect();
 		let state = Arc::new(QosHostState {
-			enclave_client: Client::new(
-				self.enclave_addr.clone(),
-				TimeVal::seconds(QOS_SOCKET_CLIENT_TIMEOUT_SECS),
-			),
+			enclave: self.connect(&self.enclave_addr, self.circuit.clone()),
+			namespaces,
+			require_client_cert: self
+				.tls
+				.as_ref()
+				.is_some_and(|tls| tls.require_client_cert),
+			max_message_size: self.max_message_size,
+			audit: AuditLog::new(self.audit_log_capacity, self.time.clone()),
+			ingress: self.ingress.clone().unwrap_or_default(),
 		});
 
+		let routes = Router::new()
+			.route(HOST_HEALTH, get(Self::host_health))
+			.route(ENCLAVE_HEALTH, get(Self::enclave_health))
+			.route(DEEP_HEALTH, get(Self::deep_health))
+			.route(MESSAGE, post(Self::message))
+			.route(ENCLAVE_INFO, get(Self::enclave_info))
+			.route(ENCLAVE_STATUS, get(Self::enclave_status))
+			.route(LOGS, get(Self::logs))
+			.route(APP_WS, get(Self::app_ws));
+		let routes = if self.app_http_proxy {
+			routes.route(APP_HTTP, any(Self::app_http))
+		} else {
+			routes
+		};
+		let routes = if self.app_grpc_proxy {
+			routes.route(APP_GRPC, post(Self::app_grpc))
+		} else {
+			routes
+		};
+		let routes = if self.ingress.is_some() {
+			routes.route(APP_API, any(Self::app_api))
+		} else {
+			routes
+		};
 		let app = Router::new()
-			.route(&self.path(HOST_HEALTH), get(Self::host_health))
-			.route(&self.path(ENCLAVE_HEALTH), get(Self::enclave_health))
-			.route(&self.path(MESSAGE), post(Self::message))
-			.route(&self.path(ENCLAVE_INFO), get(Self::enclave_info))
-			.layer(DefaultBodyLimit::disable())
-			.with_state(state);
+			.nest(&self.path(""), routes.clone())
+			.nest(&self.path(&format!("/ns/:{NAMESPACE}")), routes)
+			.route(&self.path(AUDIT_LOG), get(Self::audit_log));
+		let app = if self.trace_ids {
+			app.layer(middleware::from_fn(trace::layer))
+		} else {
+			app
+		};
+		let app = if self.cors_origins.is_empty() {
+			app
+		} else {
+			let cors = Arc::new(Cors::new(self.cors_origins.clone()));
+			app.layer(middleware::from_fn_with_state(cors, cors::layer))
+		};
+		let app = app.layer(DefaultBodyLimit::disable()).with_state(state);
 
-		println!("HostServer listening on {}", self.addr);
+		log::info(
+			"host",
+			format_args!("HostServer listening on {}", self.addr),
+		);
 
-		axum::Server::bind(&self.addr)
-			.serve(app.into_make_service())
-			.await
-			.unwrap();
+		if let Some(tls) = self.tls.as_ref() {
+			Self::serve_tls(self.addr, tls, app).await;
+		} else {
+			axum::Server::bind(&self.addr)
+				.serve(app.into_make_service())
+				.await
+				.unwrap();
+		}
+	}
+
+	/// Accept TLS connections indefinitely, serving each with `app`.
+	async fn serve_tls(addr: SocketAddr, tls: &HostTls, app: Router) {
+		let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+		let acceptor = tokio_rustls::TlsAcceptor::from(tls.config.clone());
+
+		loop {
+			let tcp = match listener.accept().await {
+				Ok((tcp, _)) => tcp,
+				Err(e) => {
+					log::error(
+						"host",
+						format_args!("Error accepting TCP connection: {e:?}"),
+					);
+					continue;
+				}
+			};
+			let acceptor = acceptor.clone();
+			let app = app.clone();
+
+			tokio::spawn(async move {
+				let stream = match acceptor.accept(tcp).await {
+					Ok(stream) => stream,
+					Err(e) => {
+						log::error(
+							"host",
+							format_args!("Error during TLS handshake: {e:?}"),
+						);
+						return;
+					}
+				};
+
+				// The verifier only lets certificates through that chain to
+				// the client CA.
+				let app = if stream.get_ref().1.peer_certificates().is_some() {
+					app.layer(Extension(ClientCertificate))
+				} else {
+					app
+				};
+
+				if let Err(e) = hyper::server::conn::Http::new()
+					.serve_connection(stream, app)
+					.with_upgrades()
+					.await
+				{
+					log::error(
+						"host",
+						format_args!("Error serving TLS connection: {e:?}"),
+					);
+				}
+			});
+		}
 	}
 
 	/// Health route handler.
 	#[allow(clippy::unused_async)]
-	async fn host_health(_: State<Arc<QosHostState>>) -> impl IntoResponse {
-		println!("Host health...");
-		Html("Ok!")
+	async fn host_health(
+		TargetEnclave(enclave): TargetEnclave,
+	) -> Json<HostHealth> {
+		log::debug("host", "Host health...");
+		Json(HostHealth {
+			enclave_circuit: enclave.connection.circuit.state(),
+			consecutive_failures: enclave
+				.connection
+				.circuit
+				.consecutive_failures(),
+		})
 	}
 
 	/// Health route handler.
-	#[allow(clippy::unused_async)]
-	async fn enclave_health(
-		State(state): State<Arc<QosHostState>>,
-	) -> impl IntoResponse {
-		println!("Enclave health...");
+	async fn enclave_health(TargetEnclave(enclave): TargetEnclave) -> Response {
+		log::debug("host", "Enclave health...");
 
 		let encoded_request = borsh::to_vec(&ProtocolMsg::StatusRequest)
 			.expect("ProtocolMsg can always serialize. qed.");
-		let encoded_response = match state.enclave_client.send(&encoded_request)
-		{
+		let encoded_response = match enclave.send(encoded_request).await {
 			Ok(encoded_response) => encoded_response,
-			Err(e) => {
+			Err(EnclaveError::CircuitOpen(retry_after)) => {
+				log::warn("host", ENCLAVE_UNAVAILABLE);
+				return (
+					StatusCode::SERVICE_UNAVAILABLE,
+					[(RETRY_AFTER, retry_after_secs(retry_after))],
+					Html(ENCLAVE_UNAVAILABLE.to_string()),
+				)
+					.into_response();
+			}
+			Err(EnclaveError::Client(e)) => {
 				let msg = format!("Error while trying to send socket request to enclave: {e:?}");
-				eprintln!("{msg}");
-				return (StatusCode::INTERNAL_SERVER_ERROR, Html(msg));
+				log::error("host", &msg);
+				let status = if is_timeout(&e) {
+					StatusCode::GATEWAY_TIMEOUT
+				} else {
+					StatusCode::INTERNAL_SERVER_ERROR
+				};
+				return (status, Html(msg)).into_response();
 			}
 		};
 
@@ -191,76 +766,155 @@ impl HostServer {
 			Ok(r) => r,
 			Err(e) => {
 				let msg = format!("Error deserializing response from enclave, make sure qos_host version match qos_core: {e}");
-				eprintln!("{msg}");
-				return (StatusCode::INTERNAL_SERVER_ERROR, Html(msg));
+				log::error("host", &msg);
+				return (StatusCode::INTERNAL_SERVER_ERROR, Html(msg))
+					.into_response();
 			}
 		};
 
 		match response {
 			ProtocolMsg::StatusResponse(phase) => {
 				let inner = format!("{phase:?}");
-				let status = match phase {
-					ProtocolPhase::UnrecoverableError
-					| ProtocolPhase::WaitingForBootInstruction
-					| ProtocolPhase::WaitingForQuorumShards
-					| ProtocolPhase::WaitingForForwardedKey => StatusCode::SERVICE_UNAVAILABLE,
-					ProtocolPhase::QuorumKeyProvisioned
-					| ProtocolPhase::GenesisBooted => StatusCode::OK,
+				let status = if is_ready(phase) {
+					StatusCode::OK
+				} else {
+					StatusCode::SERVICE_UNAVAILABLE
 				};
 
-				(status, Html(inner))
+				(status, Html(inner)).into_response()
 			}
 			other => {
 				let msg = format!("Unexpected response: Expected a ProtocolMsg::StatusResponse, but got: {other:?}");
-				eprintln!("{msg}");
-				(StatusCode::INTERNAL_SERVER_ERROR, Html(msg))
+				log::error("host", &msg);
+				(StatusCode::INTERNAL_SERVER_ERROR, Html(msg)).into_response()
 			}
 		}
 	}
 
-	#[allow(clippy::unused_async)]
+	/// Deep health route handler. Round trips a status request to the enclave
+	/// to tell apart an unreachable enclave from one that is not ready yet,
+	/// then probes the health of the pivot once the enclave is provisioned.
+	async fn deep_health(
+		TargetEnclave(enclave): TargetEnclave,
+	) -> (StatusCode, Json<DeepHealth>) {
+		log::debug("host", "Deep health...");
+
+		let encoded_request = borsh::to_vec(&ProtocolMsg::StatusRequest)
+			.expect("ProtocolMsg can always serialize. qed.");
+		let start = Instant::now();
+		let result = enclave.send(encoded_request).await;
+		let round_trip_ms =
+			u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+		let phase = match result {
+			Ok(encoded_response) => {
+				match ProtocolMsg::try_from_slice(&encoded_response) {
+					Ok(ProtocolMsg::StatusResponse(phase)) => Ok(phase),
+					Ok(other) => Err(format!("unexpected response: expected a ProtocolMsg::StatusResponse, but got: {other:?}")),
+					Err(e) => Err(format!("error deserializing status response from enclave, make sure qos_host version match qos_core: {e}")),
+				}
+			}
+			Err(EnclaveError::CircuitOpen(_)) => {
+				Err(ENCLAVE_UNAVAILABLE.to_string())
+			}
+			Err(EnclaveError::Client(e)) => Err(format!(
+				"error while trying to send status socket request to enclave: {e:?}"
+			)),
+		};
+
+		let health = match phase {
+			Ok(phase) if phase == ProtocolPhase::QuorumKeyProvisioned => {
+				let (state, app, error) = match probe_app_health(&enclave).await
+				{
+					Ok(Some(app)) if !app.is_ready() => {
+						(HealthState::AppNotReady, Some(app), None)
+					}
+					Ok(app) => (HealthState::Healthy, app, None),
+					Err(error) => {
+						log::warn("host", &error);
+						(HealthState::AppNotReady, None, Some(error))
+					}
+				};
+				DeepHealth {
+					state,
+					phase: Some(phase),
+					round_trip_ms: Some(round_trip_ms),
+					app,
+					enclave_circuit: enclave.connection.circuit.state(),
+					error,
+				}
+			}
+			Ok(phase) => DeepHealth {
+				state: if is_ready(phase) {
+					HealthState::Healthy
+				} else {
+					HealthState::EnclaveNotReady
+				},
+				phase: Some(phase),
+				round_trip_ms: Some(round_trip_ms),
+				app: None,
+				enclave_circuit: enclave.connection.circuit.state(),
+				error: None,
+			},
+			Err(error) => {
+				log::warn("host", &error);
+				DeepHealth {
+					state: HealthState::EnclaveUnreachable,
+					phase: None,
+					round_trip_ms: None,
+					app: None,
+					enclave_circuit: enclave.connection.circuit.state(),
+					error: Some(error),
+				}
+			}
+		};
+		let status = if health.state == HealthState::Healthy {
+			StatusCode::OK
+		} else {
+			StatusCode::SERVICE_UNAVAILABLE
+		};
+
+		(status, Json(health))
+	}
+
 	async fn enclave_info(
-		State(state): State<Arc<QosHostState>>,
+		TargetEnclave(enclave): TargetEnclave,
 	) -> Result<Json<EnclaveInfo>, Error> {
-		println!("Enclave info...");
+		log::debug("host", "Enclave info...");
 
 		let enc_status_req = borsh::to_vec(&ProtocolMsg::StatusRequest)
 			.expect("ProtocolMsg can always serialize. qed.");
-		let enc_status_resp = state.enclave_client.send(&enc_status_req)
-			.map_err(|e|
-				Error(format!("error deserializing status response from enclave, make sure qos_host version match qos_core: {e:?}"))
-			)?;
+		let enc_status_resp = enclave
+			.send(enc_status_req)
+			.await
+			.map_err(|e| Error::enclave("status", e))?;
 
 		let status_resp = match ProtocolMsg::try_from_slice(&enc_status_resp) {
 			Ok(status_resp) => status_resp,
 			Err(e) => {
-				return Err(Error(format!("error deserializing status response from enclave, make sure qos_host version match qos_core: {e:?}")));
+				return Err(Error::Internal(format!("error deserializing status response from enclave, make sure qos_host version match qos_core: {e:?}")));
 			}
 		};
 		let phase = match status_resp {
 			ProtocolMsg::StatusResponse(phase) => phase,
 			other => {
-				return Err(Error(format!("unexpected response: expected a ProtocolMsg::StatusResponse, but got: {other:?}")));
+				return Err(Error::Internal(format!("unexpected response: expected a ProtocolMsg::StatusResponse, but got: {other:?}")));
 			}
 		};
 
 		let enc_manifest_envelope_req =
 			borsh::to_vec(&ProtocolMsg::ManifestEnvelopeRequest)
 				.expect("ProtocolMsg can always serialize. qed.");
-		let enc_manifest_envelope_resp = state
-			.enclave_client
-			.send(&enc_manifest_envelope_req)
-			.map_err(|e| {
-				Error(format!(
-					"error while trying to send manifest envelope socket request to enclave: {e:?}"
-				))
-			})?;
+		let enc_manifest_envelope_resp = enclave
+			.send(enc_manifest_envelope_req)
+			.await
+			.map_err(|e| Error::enclave("manifest envelope", e))?;
 
 		let manifest_envelope_resp = ProtocolMsg::try_from_slice(
 			&enc_manifest_envelope_resp,
 		)
 		.map_err(|e|
-			Error(format!("error deserializing manifest envelope response from enclave, make sure qos_host version match qos_core: {e}"))
+			Error::Internal(format!("error deserializing manifest envelope response from enclave, make sure qos_host version match qos_core: {e}"))
 		)?;
 
 		let manifest_envelope = match manifest_envelope_resp {
@@ -269,7 +923,7 @@ impl HostServer {
 			}
 			other => {
 				return Err(
-					Error(format!("unexpected response: expected a ProtocolMsg::ManifestEnvelopeResponse, but got: {other:?}"))
+					Error::Internal(format!("unexpected response: expected a ProtocolMsg::ManifestEnvelopeResponse, but got: {other:?}"))
 				);
 			}
 		};
@@ -287,44 +941,768 @@ impl HostServer {
 		} else {
 			serde_json::to_string(&phase).expect("always valid json. qed.")
 		};
-		println!("{vitals_log}");
+		log::debug("host", &vitals_log);
 
 		let info = EnclaveInfo { phase, manifest_envelope };
 
 		Ok(Json(info))
 	}
 
+	/// Enclave status route handler.
+	async fn enclave_status(
+		TargetEnclave(enclave): TargetEnclave,
+	) -> Result<Json<EnclaveStatus>, Error> {
+		log::debug("host", "Enclave status...");
+
+		let encoded_request = borsh::to_vec(&ProtocolMsg::EnclaveStatusRequest)
+			.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = enclave
+			.send(encoded_request)
+			.await
+			.map_err(|e| Error::enclave("enclave status", e))?;
+
+		match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::EnclaveStatusResponse(status)) => Ok(Json(*status)),
+			Ok(other) => Err(Error::Internal(format!("unexpected response: expected a ProtocolMsg::EnclaveStatusResponse, but got: {other:?}"))),
+			Err(e) => Err(Error::Internal(format!("error deserializing enclave status response from enclave, make sure qos_host version match qos_core: {e}"))),
+		}
+	}
+
+	/// Logs route handler. Pulls entries from the log of the enclave, see
+	/// [`qos_core::log`]. Poll with `since` set to the `nextSeq` of the last
+	/// response to only get new entries.
+	async fn logs(
+		TargetEnclave(enclave): TargetEnclave,
+		Query(query): Query<LogsQuery>,
+	) -> Result<Json<LogBatch>, Error> {
+		let encoded_request = borsh::to_vec(&ProtocolMsg::LogsRequest {
+			since: query.since.unwrap_or(0),
+			max: query.max.unwrap_or(MAX_LOGS_PER_REQUEST),
+		})
+		.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = enclave
+			.send(encoded_request)
+			.await
+			.map_err(|e| Error::enclave("logs", e))?;
+
+		match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::LogsResponse(batch)) => Ok(Json(batch)),
+			Ok(other) => Err(Error::Internal(format!("unexpected response: expected a ProtocolMsg::LogsResponse, but got: {other:?}"))),
+			Err(e) => Err(Error::Internal(format!("error deserializing logs response from enclave, make sure qos_host version match qos_core: {e}"))),
+		}
+	}
+
 	/// Message route handler.
 	#[allow(clippy::unused_async)]
 	async fn message(
 		State(state): State<Arc<QosHostState>>,
-		encoded_request: Bytes,
-	) -> impl IntoResponse {
-		if encoded_request.len() > MAX_ENCODED_MSG_LEN {
+		TargetEnclave(enclave): TargetEnclave,
+		client_certificate: Option<Extension<ClientCertificate>>,
+		OriginalUri(uri): OriginalUri,
+		headers: HeaderMap,
+		RawBody(mut body): RawBody,
+	) -> Response {
+		if state.require_client_cert && client_certificate.is_none() {
 			return (
+				StatusCode::FORBIDDEN,
+				borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
+					ProtocolError::ClientCertificateRequired,
+				))
+				.expect("ProtocolMsg can always serialize. qed."),
+			)
+				.into_response();
+		}
+
+		// Fail fast when the client tells us upfront the body is too large
+		let content_length = headers
+			.get(CONTENT_LENGTH)
+			.and_then(|len| len.to_str().ok())
+			.and_then(|len| len.parse::<usize>().ok());
+		if content_length.is_some_and(|len| len > state.max_message_size) {
+			return Self::forward_error_response(ForwardError::Oversize);
+		}
+
+		let spool = match Self::receive_message(&state, &mut body).await {
+			Ok(spool) => spool,
+			Err(e) => return Self::forward_error_response(e),
+		};
+		let payload_hash = spool.hash();
+		let response = match Self::forward_message(&enclave, spool).await {
+			Ok(encoded_response) => {
+				(StatusCode::OK, encoded_response).into_response()
+			}
+			Err(e) => Self::forward_error_response(e),
+		};
+		state.audit.record(
+			uri.path(),
+			payload_hash,
+			response.status().as_u16(),
+		);
+
+		response
+	}
+
+	/// Response for a message that could not be forwarded because of `err`.
+	fn forward_error_response(err: ForwardError) -> Response {
+		match err {
+			ForwardError::Oversize => (
 				StatusCode::BAD_REQUEST,
 				borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
 					ProtocolError::OversizeMsg,
 				))
 				.expect("ProtocolMsg can always serialize. qed."),
-			);
-		}
+			)
+				.into_response(),
+			ForwardError::Body(e) => {
+				log::error(
+					"host",
+					format_args!("Error while reading message body: {e:?}"),
+				);
 
-		match state.enclave_client.send(&encoded_request) {
-			Ok(encoded_response) => (StatusCode::OK, encoded_response),
-			Err(e) => {
+				(
+					StatusCode::BAD_REQUEST,
+					borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
+						ProtocolError::InvalidMsg,
+					))
+					.expect("ProtocolMsg can always serialize. qed."),
+				)
+					.into_response()
+			}
+			ForwardError::Spool(e) => {
+				log::error(
+					"host",
+					format_args!("Error while buffering message body: {e:?}"),
+				);
+
+				(
+					StatusCode::INTERNAL_SERVER_ERROR,
+					borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
+						ProtocolError::IOError,
+					))
+					.expect("ProtocolMsg can always serialize. qed."),
+				)
+					.into_response()
+			}
+			ForwardError::Enclave(e) => {
 				let msg =
 					format!("Error while trying to send request over socket to enclave: {e:?}");
 				eprint!("{msg}");
 
+				let (status, error) = if is_timeout(&e) {
+					(
+						StatusCode::GATEWAY_TIMEOUT,
+						ProtocolError::EnclaveClientTimeout,
+					)
+				} else {
+					(
+						StatusCode::INTERNAL_SERVER_ERROR,
+						ProtocolError::EnclaveClient,
+					)
+				};
+				(
+					status,
+					borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(error))
+						.expect("ProtocolMsg can always serialize. qed."),
+				)
+					.into_response()
+			}
+			ForwardError::CircuitOpen(retry_after) => {
+				log::warn("host", ENCLAVE_UNAVAILABLE);
+
 				(
-					StatusCode::INTERNAL_SERVER_ERROR,
+					StatusCode::SERVICE_UNAVAILABLE,
+					[(RETRY_AFTER, retry_after_secs(retry_after))],
 					borsh::to_vec(&ProtocolMsg::ProtocolErrorResponse(
-						ProtocolError::EnclaveClient,
+						ProtocolError::EnclaveUnavailable,
 					))
 					.expect("ProtocolMsg can always serialize. qed."),
 				)
+					.into_response()
+			}
+		}
+	}
+
+	/// Receive all of `body`. Only talking to the enclave once the body is
+	/// complete means a slow client cannot hold up the enclave.
+	async fn receive_message(
+		state: &QosHostState,
+		body: &mut Body,
+	) -> Result<Spool, ForwardError> {
+		let mut spool = Spool::default();
+		while let Some(data) = body.data().await {
+			let data = data.map_err(ForwardError::Body)?;
+			if spool.len() + data.len() > state.max_message_size {
+				return Err(ForwardError::Oversize);
+			}
+			spool.push(&data).await.map_err(ForwardError::Spool)?;
+		}
+
+		Ok(spool)
+	}
+
+	/// Send the received message in `spool` to the enclave and return its
+	/// response.
+	async fn forward_message(
+		enclave: &Enclave,
+		spool: Spool,
+	) -> Result<Vec<u8>, ForwardError> {
+		// Only claim the circuit once the body is complete, so a slow client
+		// cannot hold up a trial request.
+		enclave
+			.connection
+			.circuit
+			.try_acquire()
+			.map_err(ForwardError::CircuitOpen)?;
+		let client = enclave.connection.client.clone();
+		let prefix = enclave.trace_prefix();
+		let result = tokio::task::spawn_blocking(move || {
+			spool.forward(&client, &prefix)
+		})
+		.await
+		.expect("forwarding a message panicked. qed.");
+		enclave
+			.connection
+			.circuit
+			.record(!matches!(result, Err(SpoolError::Enclave(_))));
+
+		result.map_err(|e| match e {
+			SpoolError::File(e) => ForwardError::Spool(e),
+			SpoolError::Enclave(e) => ForwardError::Enclave(e),
+		})
+	}
+
+	/// App websocket route handler. Upgrades the connection, then sends each
+	/// binary message to the secure app as a [`ProtocolMsg::ProxyRequest`]
+	/// and answers with the data of its [`ProtocolMsg::ProxyResponse`].
+	#[allow(clippy::unused_async)]
+	async fn app_ws(
+		State(state): State<Arc<QosHostState>>,
+		TargetEnclave(enclave): TargetEnclave,
+		client_certificate: Option<Extension<ClientCertificate>>,
+		OriginalUri(uri): OriginalUri,
+		request: Request<Body>,
+	) -> Response {
+		if state.require_client_cert && client_certificate.is_none() {
+			return (
+				StatusCode::FORBIDDEN,
+				Html("client certificate required".to_string()),
+			)
+				.into_response();
+		}
+
+		let Some(accept) = ws::accept(request.headers()) else {
+			return (
+				StatusCode::BAD_REQUEST,
+				Html("expected a WebSocket upgrade request".to_string()),
+			)
+				.into_response();
+		};
+
+		tokio::spawn(async move {
+			match hyper::upgrade::on(request).await {
+				Ok(upgraded) => {
+					let mut socket =
+						WebSocket::new(upgraded, state.max_message_size);
+					let (code, reason) =
+						Self::bridge(&state, &enclave, uri.path(), &mut socket)
+							.await;
+					// The client may be gone already
+					let _ = socket.close(code, &reason).await;
+				}
+				Err(e) => log::error(
+					"host",
+					format_args!("Error upgrading to a WebSocket: {e:?}"),
+				),
 			}
+		});
+
+		(
+			StatusCode::SWITCHING_PROTOCOLS,
+			[
+				(UPGRADE, "websocket".to_string()),
+				(CONNECTION, "upgrade".to_string()),
+				(SEC_WEBSOCKET_ACCEPT, accept),
+			],
+		)
+			.into_response()
+	}
+
+	/// App HTTP route handler. See [`HostServer::with_app_http_proxy`].
+	async fn app_http(
+		State(state): State<Arc<QosHostState>>,
+		TargetEnclave(enclave): TargetEnclave,
+		client_certificate: Option<Extension<ClientCertificate>>,
+		OriginalUri(uri): OriginalUri,
+		Path(params): Path<HashMap<String, String>>,
+		request: Request<Body>,
+	) -> Response {
+		if state.require_client_cert && client_certificate.is_none() {
+			return (
+				StatusCode::FORBIDDEN,
+				Html("client certificate required".to_string()),
+			)
+				.into_response();
+		}
+
+		let (parts, mut body) = request.into_parts();
+		let path = params.get(APP_PATH).map_or("", |path| path.as_str());
+		let path_and_query = match parts.uri.query() {
+			Some(query) => format!("/{}?{query}", path.trim_start_matches('/')),
+			None => format!("/{}", path.trim_start_matches('/')),
+		};
+
+		let data = match read_body(&mut body, state.max_message_size).await {
+			Ok(data) => data,
+			Err(status) => return status.into_response(),
+		};
+
+		let data = http_proxy::encode_request(
+			&parts.method,
+			&path_and_query,
+			&parts.headers,
+			&data,
+		);
+		let payload_hash = Sha256::digest(&data).into();
+		let response = Self::forward_app_http(&enclave, data).await;
+		state.audit.record(
+			uri.path(),
+			payload_hash,
+			response.status().as_u16(),
+		);
+
+		response
+	}
+
+	/// App gRPC route handler. See [`HostServer::with_app_grpc_proxy`].
+	async fn app_grpc(
+		State(state): State<Arc<QosHostState>>,
+		TargetEnclave(enclave): TargetEnclave,
+		client_certificate: Option<Extension<ClientCertificate>>,
+		OriginalUri(uri): OriginalUri,
+		Path(params): Path<HashMap<String, String>>,
+		request: Request<Body>,
+	) -> Response {
+		if state.require_client_cert && client_certificate.is_none() {
+			return (
+				StatusCode::FORBIDDEN,
+				Html("client certificate required".to_string()),
+			)
+				.into_response();
+		}
+
+		let (parts, mut body) = request.into_parts();
+		if !parts
+			.headers
+			.get(CONTENT_TYPE)
+			.and_then(|content_type| content_type.to_str().ok())
+			.is_some_and(grpc_web::is_grpc_web)
+		{
+			return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
 		}
+		let path = params.get(APP_PATH).map_or("", |path| path.as_str());
+
+		let data = match read_body(&mut body, state.max_message_size).await {
+			Ok(data) => data,
+			Err(status) => return status.into_response(),
+		};
+
+		let payload_hash = Sha256::digest(&data).into();
+		let app_response = match grpc_web::decode_request(&data) {
+			Ok(message) => {
+				let request = AppGrpcRequest {
+					path: format!("/{}", path.trim_start_matches('/')),
+					metadata: grpc_web::request_metadata(&parts.headers),
+					message,
+				};
+				Self::forward_app_grpc(&enclave, &request).await
+			}
+			Err(status) => AppGrpcResponse::error(status),
+		};
+		// gRPC reports errors in the trailers, so record the gRPC status
+		state.audit.record(
+			uri.path(),
+			payload_hash,
+			u16::from(app_response.status.code as u8),
+		);
+
+		let mut response = Response::new(axum::body::boxed(Full::from(
+			grpc_web::encode_response(&app_response),
+		)));
+		*response.headers_mut() = grpc_web::response_headers(&app_response);
+		response.headers_mut().insert(
+			CONTENT_TYPE,
+			HeaderValue::from_static(grpc_web::GRPC_WEB_CONTENT_TYPE),
+		);
+		response
 	}
+
+	/// App API route handler. See [`HostServer::with_ingress_map`].
+	async fn app_api(
+		State(state): State<Arc<QosHostState>>,
+		TargetEnclave(enclave): TargetEnclave,
+		client_certificate: Option<Extension<ClientCertificate>>,
+		OriginalUri(uri): OriginalUri,
+		Path(params): Path<HashMap<String, String>>,
+		request: Request<Body>,
+	) -> Response {
+		if state.require_client_cert && client_certificate.is_none() {
+			return (
+				StatusCode::FORBIDDEN,
+				Html("client certificate required".to_string()),
+			)
+				.into_response();
+		}
+
+		let (parts, mut body) = request.into_parts();
+		let path = params.get(APP_PATH).map_or("", |path| path.as_str());
+		let Some((route, path_params)) =
+			state.ingress.find(&parts.method, path)
+		else {
+			let error = format!("no API route for {} {path}", parts.method);
+			return (StatusCode::NOT_FOUND, Json(JsonError { error }))
+				.into_response();
+		};
+
+		let data = match read_body(&mut body, state.max_message_size).await {
+			Ok(data) => data,
+			Err(status) => return status.into_response(),
+		};
+
+		let payload_hash = Sha256::digest(&data).into();
+		let response = match route.encode_request(&path_params, &data) {
+			Ok(data) => {
+				Self::forward_app_api(&state.ingress, &enclave, data).await
+			}
+			Err(error) => (StatusCode::BAD_REQUEST, Json(JsonError { error }))
+				.into_response(),
+		};
+		state.audit.record(
+			uri.path(),
+			payload_hash,
+			response.status().as_u16(),
+		);
+
+		response
+	}
+
+	/// Send the encoded request variant in `data` to the secure app and
+	/// answer with its response, translated by `ingress`.
+	async fn forward_app_api(
+		ingress: &IngressMap,
+		enclave: &Enclave,
+		data: Vec<u8>,
+	) -> Response {
+		let request = borsh::to_vec(&ProtocolMsg::ProxyRequest { data })
+			.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = match enclave.send(request).await {
+			Ok(encoded_response) => encoded_response,
+			Err(e) => return Error::enclave("app", e).into_response(),
+		};
+
+		let data = match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::ProxyResponse { data }) => data,
+			Ok(other) => return Error::Internal(format!("unexpected response: expected a ProtocolMsg::ProxyResponse, but got: {other:?}")).into_response(),
+			Err(e) => return Error::Internal(format!("error deserializing app response from enclave, make sure qos_host version match qos_core: {e}")).into_response(),
+		};
+
+		match ingress.decode_response(&data) {
+			Ok((status, body)) => (status, Json(body)).into_response(),
+			Err(e) => {
+				let error = format!("error decoding response from app: {e}");
+				log::error("host", format_args!("qos_host error: {error}"));
+				(StatusCode::BAD_GATEWAY, Json(JsonError { error }))
+					.into_response()
+			}
+		}
+	}
+
+	/// Send `request` to the secure app and decode its response. Failures to
+	/// reach the app are reported as gRPC statuses.
+	async fn forward_app_grpc(
+		enclave: &Enclave,
+		request: &AppGrpcRequest,
+	) -> AppGrpcResponse {
+		let internal = |message: String| {
+			log::error("host", &message);
+			AppGrpcResponse::error(GrpcStatus::new(
+				GrpcCode::Internal,
+				&message,
+			))
+		};
+
+		let data = borsh::to_vec(request)
+			.expect("AppGrpcRequest can always serialize. qed.");
+		let request = borsh::to_vec(&ProtocolMsg::ProxyRequest { data })
+			.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = match enclave.send(request).await {
+			Ok(encoded_response) => encoded_response,
+			Err(EnclaveError::CircuitOpen(_)) => {
+				return AppGrpcResponse::error(GrpcStatus::new(
+					GrpcCode::Unavailable,
+					ENCLAVE_UNAVAILABLE,
+				))
+			}
+			Err(EnclaveError::Client(e)) => {
+				return internal(format!(
+					"error while trying to send app socket request to enclave: {e:?}"
+				))
+			}
+		};
+
+		match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::ProxyResponse { data }) => {
+				borsh::from_slice(&data).unwrap_or_else(|e| {
+					internal(format!("error decoding gRPC response from app: {e}"))
+				})
+			}
+			Ok(ProtocolMsg::ProtocolErrorResponse(e)) => {
+				AppGrpcResponse::error(GrpcStatus::new(
+					GrpcCode::Unavailable,
+					&format!("the enclave could not reach the app: {e:?}"),
+				))
+			}
+			Ok(other) => internal(format!("unexpected response: expected a ProtocolMsg::ProxyResponse, but got: {other:?}")),
+			Err(e) => internal(format!("error deserializing app response from enclave, make sure qos_host version match qos_core: {e}")),
+		}
+	}
+
+	/// Send the HTTP/1.1 encoded request in `data` to the secure app and
+	/// decode its response.
+	async fn forward_app_http(enclave: &Enclave, data: Vec<u8>) -> Response {
+		let request = borsh::to_vec(&ProtocolMsg::ProxyRequest { data })
+			.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = match enclave.send(request).await {
+			Ok(encoded_response) => encoded_response,
+			Err(e) => return Error::enclave("app", e).into_response(),
+		};
+
+		let data = match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::ProxyResponse { data }) => data,
+			Ok(other) => return Error::Internal(format!("unexpected response: expected a ProtocolMsg::ProxyResponse, but got: {other:?}")).into_response(),
+			Err(e) => return Error::Internal(format!("error deserializing app response from enclave, make sure qos_host version match qos_core: {e}")).into_response(),
+		};
+		let app_response = match http_proxy::decode_response(&data) {
+			Ok(app_response) => app_response,
+			Err(e) => {
+				return Error::Internal(format!(
+					"error decoding HTTP response from app: {e}"
+				))
+				.into_response()
+			}
+		};
+
+		let mut response =
+			Response::new(axum::body::boxed(Full::from(app_response.body)));
+		*response.status_mut() = app_response.status;
+		*response.headers_mut() = app_response.headers;
+		response
+	}
+
+	/// Bridge messages between `socket` and the secure app until either side
+	/// fails or the client closes the connection, recording each message
+	/// under `route` in the audit log. Returns the code and reason to close
+	/// the connection with.
+	async fn bridge<S>(
+		state: &QosHostState,
+		enclave: &Enclave,
+		route: &str,
+		socket: &mut WebSocket<S>,
+	) -> (u16, String)
+	where
+		S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+	{
+		loop {
+			let data = match socket.recv().await {
+				Ok(Message::Binary(data)) => data,
+				Ok(Message::Text(_)) => {
+					return (
+						ws::CLOSE_UNSUPPORTED_DATA,
+						"only binary messages are supported".to_string(),
+					)
+				}
+				Ok(Message::Close) => return (ws::CLOSE_NORMAL, String::new()),
+				Err(WsError::Protocol(reason)) => {
+					return (ws::CLOSE_PROTOCOL_ERROR, reason.to_string())
+				}
+				Err(WsError::TooBig) => {
+					return (
+						ws::CLOSE_MESSAGE_TOO_BIG,
+						"message too big".to_string(),
+					)
+				}
+				Err(WsError::Io(e)) => {
+					return (ws::CLOSE_INTERNAL_ERROR, format!("{e:?}"))
+				}
+			};
+
+			let payload_hash = Sha256::digest(&data).into();
+			let result = Self::forward_app_message(enclave, data).await;
+			let status = match &result {
+				Ok(_) => StatusCode::OK,
+				Err((ws::CLOSE_TRY_AGAIN_LATER, _)) => {
+					StatusCode::SERVICE_UNAVAILABLE
+				}
+				Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+			};
+			state.audit.record(route, payload_hash, status.as_u16());
+
+			match result {
+				Ok(data) => {
+					if let Err(e) = socket.send_binary(&data).await {
+						return (ws::CLOSE_INTERNAL_ERROR, format!("{e:?}"));
+					}
+				}
+				Err(close) => return close,
+			}
+		}
+	}
+
+	/// Send a websocket message to the secure app and return the data of its
+	/// response, or the code and reason to close the connection with.
+	async fn forward_app_message(
+		enclave: &Enclave,
+		data: Vec<u8>,
+	) -> Result<Vec<u8>, (u16, String)> {
+		let request = borsh::to_vec(&ProtocolMsg::ProxyRequest { data })
+			.expect("ProtocolMsg can always serialize. qed.");
+		let encoded_response = match enclave.send(request).await {
+			Ok(encoded_response) => encoded_response,
+			Err(EnclaveError::CircuitOpen(_)) => {
+				return Err((
+					ws::CLOSE_TRY_AGAIN_LATER,
+					ENCLAVE_UNAVAILABLE.to_string(),
+				))
+			}
+			Err(EnclaveError::Client(e)) => {
+				log::error("host", format_args!("Error while trying to send request over socket to enclave: {e:?}"));
+				return Err((
+					ws::CLOSE_INTERNAL_ERROR,
+					"error while trying to send request to enclave".to_string(),
+				));
+			}
+		};
+
+		match ProtocolMsg::try_from_slice(&encoded_response) {
+			Ok(ProtocolMsg::ProxyResponse { data }) => Ok(data),
+			Ok(ProtocolMsg::ProtocolErrorResponse(e)) => {
+				Err((ws::CLOSE_INTERNAL_ERROR, format!("{e:?}")))
+			}
+			Ok(other) => {
+				log::error("host", format_args!("Unexpected response: Expected a ProtocolMsg::ProxyResponse, but got: {other:?}"));
+				Err((
+					ws::CLOSE_INTERNAL_ERROR,
+					"unexpected response from enclave".to_string(),
+				))
+			}
+			Err(e) => {
+				log::error("host", format_args!("Error deserializing response from enclave, make sure qos_host version match qos_core: {e}"));
+				Err((
+					ws::CLOSE_INTERNAL_ERROR,
+					"error deserializing response from enclave".to_string(),
+				))
+			}
+		}
+	}
+
+	/// Audit log route handler. Returns the entries kept in memory, oldest
+	/// first; see [`AuditEntry::verify_chain`] for checking them.
+	#[allow(clippy::unused_async)]
+	async fn audit_log(
+		State(state): State<Arc<QosHostState>>,
+	) -> Json<Vec<AuditEntry>> {
+		Json(state.audit.entries())
+	}
+}
+
+/// Read `body` in full, failing with the status to respond with if it cannot
+/// be read or is larger than `max_size`.
+async fn read_body(
+	body: &mut Body,
+	max_size: usize,
+) -> Result<Vec<u8>, StatusCode> {
+	let mut data = vec![];
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk.map_err(|e| {
+			log::error(
+				"host",
+				format_args!("Error while reading request body: {e:?}"),
+			);
+			StatusCode::BAD_REQUEST
+		})?;
+		if data.len() + chunk.len() > max_size {
+			return Err(StatusCode::PAYLOAD_TOO_LARGE);
+		}
+		data.extend_from_slice(&chunk);
+	}
+
+	Ok(data)
+}
+
+/// Probe the health of the pivot through the enclave. `None` if the pivot
+/// does not speak the health protocol, in which case answering at all is the
+/// best sign of health there is.
+async fn probe_app_health(
+	enclave: &Enclave,
+) -> Result<Option<AppHealth>, String> {
+	let encoded_request = borsh::to_vec(&ProtocolMsg::AppHealthRequest)
+		.expect("ProtocolMsg can always serialize. qed.");
+	let encoded_response = match enclave.send(encoded_request).await {
+		Ok(encoded_response) => encoded_response,
+		Err(EnclaveError::CircuitOpen(_)) => {
+			return Err(ENCLAVE_UNAVAILABLE.to_string())
+		}
+		Err(EnclaveError::Client(e)) => {
+			return Err(format!(
+				"error while trying to send app health socket request to enclave: {e:?}"
+			))
+		}
+	};
+
+	match ProtocolMsg::try_from_slice(&encoded_response) {
+		Ok(ProtocolMsg::AppHealthResponse(app)) => Ok(Some(app)),
+		Ok(ProtocolMsg::ProtocolErrorResponse(
+			ProtocolError::InvalidAppHealthResponse,
+		)) => Ok(None),
+		Ok(ProtocolMsg::ProtocolErrorResponse(e)) => {
+			Err(format!("error while probing app health: {e:?}"))
+		}
+		Ok(other) => Err(format!("unexpected response: expected a ProtocolMsg::AppHealthResponse, but got: {other:?}")),
+		Err(e) => Err(format!("error deserializing app health response from enclave, make sure qos_host version match qos_core: {e}")),
+	}
+}
+
+/// Whether an enclave in `phase` can serve requests.
+fn is_ready(phase: ProtocolPhase) -> bool {
+	match phase {
+		ProtocolPhase::UnrecoverableError
+		| ProtocolPhase::WaitingForBootInstruction
+		| ProtocolPhase::WaitingForQuorumShards
+		| ProtocolPhase::WaitingForForwardedKey => false,
+		ProtocolPhase::QuorumKeyProvisioned | ProtocolPhase::GenesisBooted => {
+			true
+		}
+	}
+}
+
+/// Whether `err` means the enclave did not accept the request or respond in
+/// time.
+fn is_timeout(err: &ClientError) -> bool {
+	matches!(
+		err,
+		ClientError::IOError(IOError::RecvTimeout | IOError::SendTimeout)
+	)
+}
+
+/// Reasons forwarding a message to the enclave can fail.
+enum ForwardError {
+	/// The message is larger than the configured limit.
+	Oversize,
+	/// Reading the request body failed.
+	Body(hyper::Error),
+	/// Buffering the request body failed.
+	Spool(std::io::Error),
+	/// Talking to the enclave failed.
+	Enclave(ClientError),
+	/// The circuit is open; retry after the given time.
+	CircuitOpen(Duration),
 }
@@ -0,0 +1,208 @@
+//! Buffer for message bodies, so a body is only forwarded to the enclave once
+//! it has been received in full. Otherwise a slow client would hold the
+//! enclave's connection, and with it the enclave, for as long as it likes.
+
+use std::{
+	fs::{File, OpenOptions},
+	io::{Read, Seek, Write},
+	os::unix::fs::OpenOptionsExt,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use qos_core::{
+	client::{Client, ClientError},
+	protocol::Hash256,
+};
+use sha2::{Digest, Sha256};
+
+/// Bodies up to this size are kept in memory. Larger ones are spooled to a
+/// temporary file, bounding the memory used per request.
+pub(crate) const SPOOL_THRESHOLD: usize = 4 * 1024 * 1024;
+/// Spooled bodies are written to their file and forwarded to the enclave in
+/// chunks of this size.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A message body, held in memory until it outgrows [`SPOOL_THRESHOLD`] and in
+/// an unlinked temporary file after that.
+#[derive(Default)]
+pub(crate) struct Spool {
+	/// Data not written to `file` yet.
+	buf: Vec<u8>,
+	file: Option<File>,
+	len: usize,
+	hasher: Sha256,
+}
+
+/// Reasons forwarding a spooled body to the enclave can fail.
+#[derive(Debug)]
+pub(crate) enum SpoolError {
+	/// Reading or writing the temporary file failed.
+	File(std::io::Error),
+	/// Talking to the enclave failed.
+	Enclave(ClientError),
+}
+
+impl Spool {
+	/// Number of bytes pushed so far.
+	pub(crate) fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Sha256 of the data pushed so far.
+	pub(crate) fn hash(&self) -> Hash256 {
+		self.hasher.clone().finalize().into()
+	}
+
+	/// Append `data` to the body.
+	pub(crate) async fn push(&mut self, data: &[u8]) -> std::io::Result<()> {
+		self.buf.extend_from_slice(data);
+		self.len += data.len();
+		self.hasher.update(data);
+
+		let limit =
+			if self.file.is_some() { CHUNK_SIZE } else { SPOOL_THRESHOLD };
+		if self.buf.len() > limit {
+			self.flush().await?;
+		}
+
+		Ok(())
+	}
+
+	/// Move the buffered data to the file, off the async runtime.
+	async fn flush(&mut self) -> std::io::Result<()> {
+		let buf = std::mem::take(&mut self.buf);
+		let file = self.file.take();
+		let file = tokio::task::spawn_blocking(move || {
+			let mut file = match file {
+				Some(file) => file,
+				None => spool_file()?,
+			};
+			file.write_all(&buf)?;
+			Ok::<_, std::io::Error>(file)
+		})
+		.await
+		.map_err(std::io::Error::other)??;
+		self.file = Some(file);
+
+		Ok(())
+	}
+
+	/// Send `prefix` followed by the body to the enclave with `client` and
+	/// return its response. Blocks, so call it from a thread where blocking
+	/// is allowed.
+	pub(crate) fn forward(
+		self,
+		client: &Client,
+		prefix: &[u8],
+	) -> Result<Vec<u8>, SpoolError> {
+		let Some(mut file) = self.file else {
+			return if prefix.is_empty() {
+				client.send(&self.buf)
+			} else {
+				client.send(&[prefix, &self.buf].concat())
+			}
+			.map_err(SpoolError::Enclave);
+		};
+		file.rewind().map_err(SpoolError::File)?;
+
+		let mut request =
+			client.stream_request().map_err(SpoolError::Enclave)?;
+		if !prefix.is_empty() {
+			request.send_chunk(prefix).map_err(SpoolError::Enclave)?;
+		}
+		let mut chunk = vec![0; CHUNK_SIZE];
+		let mut remaining = self.len - self.buf.len();
+		while remaining > 0 {
+			let size = remaining.min(CHUNK_SIZE);
+			file.read_exact(&mut chunk[..size]).map_err(SpoolError::File)?;
+			request.send_chunk(&chunk[..size]).map_err(SpoolError::Enclave)?;
+			remaining -= size;
+		}
+
+		request.finish(&self.buf).map_err(SpoolError::Enclave)
+	}
+}
+
+/// Create a temporary file only this process can access. It is unlinked right
+/// away, so it disappears with the last handle, even if the host crashes.
+fn spool_file() -> std::io::Result<File> {
+	static NEXT: AtomicU64 = AtomicU64::new(0);
+
+	let path = std::env::temp_dir().join(format!(
+		"qos_host-{}-{}.spool",
+		std::process::id(),
+		NEXT.fetch_add(1, Ordering::Relaxed)
+	));
+	let file = OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create_new(true)
+		.mode(0o600)
+		.open(&path)?;
+	std::fs::remove_file(&path)?;
+
+	Ok(file)
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::{
+		io::{SocketAddress, TimeVal, TimeValLike},
+		server::{RequestProcessor, SocketServer},
+	};
+
+	use super::*;
+
+	struct EchoProcessor;
+	impl RequestProcessor for EchoProcessor {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[tokio::test]
+	async fn forwards_spooled_bodies() {
+		let path = "./forwards_spooled_bodies.sock";
+		let server = SocketServer::spawn(
+			vec![SocketAddress::new_unix(path)],
+			EchoProcessor,
+		)
+		.unwrap();
+		let client =
+			Client::new(SocketAddress::new_unix(path), TimeVal::seconds(5));
+
+		let small: Vec<u8> = (0..=255).cycle().take(1000).collect();
+		let mut spool = Spool::default();
+		spool.push(&small).await.unwrap();
+		assert!(spool.file.is_none());
+		let forward_client = client.clone();
+		let response = tokio::task::spawn_blocking(move || {
+			spool.forward(&forward_client, &[])
+		})
+		.await
+		.unwrap()
+		.unwrap();
+		assert_eq!(response, small);
+
+		// Spans the threshold and several chunks, and is not chunk aligned
+		let large: Vec<u8> =
+			(0..=250).cycle().take(2 * SPOOL_THRESHOLD + 7).collect();
+		let mut spool = Spool::default();
+		for data in large.chunks(100_000) {
+			spool.push(data).await.unwrap();
+		}
+		assert!(spool.file.is_some());
+		assert_eq!(spool.len(), large.len());
+		assert_eq!(spool.hash(), <Hash256>::from(Sha256::digest(&large)));
+		// A prefix is sent ahead of the spooled chunks
+		let response = tokio::task::spawn_blocking(move || {
+			spool.forward(&client, b"prefix")
+		})
+		.await
+		.unwrap()
+		.unwrap();
+		assert_eq!(response, [&b"prefix"[..], &large].concat());
+
+		server.shutdown();
+	}
+}
@@ -0,0 +1,113 @@
+//! TLS for the [`crate::HostServer`], optionally authenticating clients with
+//! certificates issued by a configured CA.
+//!
+//! Client certificates are requested on every connection but not required to
+//! complete the handshake, so load balancers can keep probing health routes.
+//! Routes that must only be reachable by authenticated clients check for the
+//! [`ClientCertificate`] extension instead.
+
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use rustls::{
+	pki_types::CertificateDer,
+	server::{VerifierBuilderError, WebPkiClientVerifier},
+	RootCertStore, ServerConfig,
+};
+
+/// Errors loading the host TLS configuration.
+#[derive(Debug)]
+pub enum TlsError {
+	/// Failed to read a PEM file.
+	Io(std::io::Error),
+	/// The PEM file did not contain a certificate.
+	NoCertificates,
+	/// The PEM file did not contain a private key.
+	NoPrivateKey,
+	/// The client CA could not be used to verify client certificates.
+	ClientVerifier(VerifierBuilderError),
+	/// [`rustls::Error`] wrapper.
+	Rustls(rustls::Error),
+}
+
+impl From<std::io::Error> for TlsError {
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+impl From<VerifierBuilderError> for TlsError {
+	fn from(err: VerifierBuilderError) -> Self {
+		Self::ClientVerifier(err)
+	}
+}
+
+impl From<rustls::Error> for TlsError {
+	fn from(err: rustls::Error) -> Self {
+		Self::Rustls(err)
+	}
+}
+
+/// Marker inserted into the extensions of every request received over a
+/// connection where the client presented a certificate that chains to the
+/// configured client CA.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCertificate;
+
+/// TLS configuration for the [`crate::HostServer`].
+#[derive(Debug, Clone)]
+pub struct HostTls {
+	pub(crate) config: Arc<ServerConfig>,
+	pub(crate) require_client_cert: bool,
+}
+
+impl HostTls {
+	/// Load the server certificate chain and key from PEM files. If
+	/// `client_ca_path` is given, the message route only accepts requests
+	/// from clients with a certificate issued by that CA.
+	pub fn load(
+		cert_path: impl AsRef<Path>,
+		key_path: impl AsRef<Path>,
+		client_ca_path: Option<impl AsRef<Path>>,
+	) -> Result<Self, TlsError> {
+		let certs = read_certs(cert_path)?;
+		let key = rustls_pemfile::private_key(&mut reader(key_path)?)?
+			.ok_or(TlsError::NoPrivateKey)?;
+
+		let builder = ServerConfig::builder();
+		let (builder, require_client_cert) = match client_ca_path {
+			Some(client_ca_path) => {
+				let mut roots = RootCertStore::empty();
+				for cert in read_certs(client_ca_path)? {
+					roots.add(cert)?;
+				}
+				let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+					.allow_unauthenticated()
+					.build()?;
+
+				(builder.with_client_cert_verifier(verifier), true)
+			}
+			None => (builder.with_no_client_auth(), false),
+		};
+
+		Ok(Self {
+			config: Arc::new(builder.with_single_cert(certs, key)?),
+			require_client_cert,
+		})
+	}
+}
+
+fn reader(path: impl AsRef<Path>) -> Result<BufReader<File>, TlsError> {
+	Ok(BufReader::new(File::open(path)?))
+}
+
+fn read_certs(
+	path: impl AsRef<Path>,
+) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+	let certs = rustls_pemfile::certs(&mut reader(path)?)
+		.collect::<Result<Vec<_>, _>>()?;
+	if certs.is_empty() {
+		return Err(TlsError::NoCertificates);
+	}
+
+	Ok(certs)
+}
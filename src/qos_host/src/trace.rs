@@ -0,0 +1,49 @@
+//! Trace IDs for the host's routes, see [`qos_core::protocol::trace`].
+
+use axum::{
+	body::Body,
+	http::{HeaderValue, Request},
+	middleware::Next,
+	response::Response,
+};
+use qos_core::{
+	log,
+	protocol::trace::{TraceId, TRACE_ID_HEADER},
+};
+
+/// Middleware assigning each request a [`TraceId`]: the one in its
+/// [`TRACE_ID_HEADER`] if valid, a random one otherwise. The trace ID is set
+/// as the header of the request, so proxied requests forward it to the pivot,
+/// added to its extensions for [`crate::TargetEnclave`], and returned in the
+/// header of the response. Server errors are logged with the trace ID.
+pub(crate) async fn layer(
+	mut request: Request<Body>,
+	next: Next<Body>,
+) -> Response {
+	let trace_id = request
+		.headers()
+		.get(TRACE_ID_HEADER)
+		.and_then(|id| id.to_str().ok())
+		.and_then(|id| TraceId::parse(id).ok())
+		.unwrap_or_else(TraceId::random);
+	let header = HeaderValue::from_str(trace_id.as_str())
+		.expect("trace IDs are valid header values. qed.");
+	let method = request.method().clone();
+	let path = request.uri().path().to_string();
+
+	request.headers_mut().insert(TRACE_ID_HEADER, header.clone());
+	request.extensions_mut().insert(trace_id.clone());
+	let mut response = next.run(request).await;
+
+	if response.status().is_server_error() {
+		log::error(
+			"host",
+			format_args!(
+				"trace_id={trace_id} {method} {path} failed with {}",
+				response.status()
+			),
+		);
+	}
+	response.headers_mut().insert(TRACE_ID_HEADER, header);
+	response
+}
@@ -0,0 +1,339 @@
+//! Server side of the websocket protocol ([RFC 6455]), just enough to exchange
+//! binary messages with a client over an upgraded HTTP connection.
+//!
+//! [RFC 6455]: https://www.rfc-editor.org/rfc/rfc6455
+
+use axum::http::{
+	header::{CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE},
+	HeaderMap,
+};
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Appended to the client's key to compute the `Sec-WebSocket-Accept` header.
+const ACCEPT_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Close frames carry a 2 byte code and at most this many bytes of reason.
+const MAX_CLOSE_REASON_LEN: usize = 123;
+
+/// The connection is closed after the client asked to.
+pub(crate) const CLOSE_NORMAL: u16 = 1000;
+/// The client violated the protocol.
+pub(crate) const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// The client sent a kind of message that is not supported.
+pub(crate) const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+/// The client sent a message larger than allowed.
+pub(crate) const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+/// Handling a message failed.
+pub(crate) const CLOSE_INTERNAL_ERROR: u16 = 1011;
+/// The server can not handle messages right now.
+pub(crate) const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opcode {
+	Continuation = 0x0,
+	Text = 0x1,
+	Binary = 0x2,
+	Close = 0x8,
+	Ping = 0x9,
+	Pong = 0xA,
+}
+
+impl Opcode {
+	fn parse(byte: u8) -> Option<Self> {
+		match byte {
+			0x0 => Some(Self::Continuation),
+			0x1 => Some(Self::Text),
+			0x2 => Some(Self::Binary),
+			0x8 => Some(Self::Close),
+			0x9 => Some(Self::Ping),
+			0xA => Some(Self::Pong),
+			_ => None,
+		}
+	}
+
+	fn is_control(self) -> bool {
+		matches!(self, Self::Close | Self::Ping | Self::Pong)
+	}
+}
+
+/// A complete message received from the client.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Message {
+	/// A text message.
+	Text(Vec<u8>),
+	/// A binary message.
+	Binary(Vec<u8>),
+	/// The client is closing the connection.
+	Close,
+}
+
+/// Reasons receiving a message can fail.
+#[derive(Debug)]
+pub(crate) enum WsError {
+	/// Reading from or writing to the connection failed.
+	Io(std::io::Error),
+	/// The client violated the protocol.
+	Protocol(&'static str),
+	/// The message is larger than the configured limit.
+	TooBig,
+}
+
+impl From<std::io::Error> for WsError {
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+/// If `headers` belong to a valid websocket upgrade request, the value of
+/// the `Sec-WebSocket-Accept` header to respond with.
+pub(crate) fn accept(headers: &HeaderMap) -> Option<String> {
+	let has_token = |name, token: &str| {
+		headers.get_all(name).iter().any(|value| {
+			value.to_str().is_ok_and(|value| {
+				value.split(',').any(|v| v.trim().eq_ignore_ascii_case(token))
+			})
+		})
+	};
+	if !has_token(UPGRADE, "websocket")
+		|| !has_token(CONNECTION, "upgrade")
+		|| headers.get(SEC_WEBSOCKET_VERSION)? != "13"
+	{
+		return None;
+	}
+
+	Some(accept_key(headers.get(SEC_WEBSOCKET_KEY)?.as_bytes()))
+}
+
+fn accept_key(key: &[u8]) -> String {
+	let mut hasher = Sha1::new();
+	hasher.update(key);
+	hasher.update(ACCEPT_GUID);
+	base64::engine::general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// A websocket connection to a client.
+pub(crate) struct WebSocket<S> {
+	stream: S,
+	max_message_size: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WebSocket<S> {
+	/// Speak the websocket protocol over `stream`, the upgraded connection, rejecting
+	/// messages larger than `max_message_size` bytes.
+	pub(crate) fn new(stream: S, max_message_size: usize) -> Self {
+		Self { stream, max_message_size }
+	}
+
+	/// Receive the next message, reassembling fragmented messages and
+	/// answering pings along the way.
+	pub(crate) async fn recv(&mut self) -> Result<Message, WsError> {
+		let mut message: Option<(Opcode, Vec<u8>)> = None;
+		loop {
+			let buffered = message.as_ref().map_or(0, |(_, data)| data.len());
+			let (fin, opcode, payload) = self.read_frame(buffered).await?;
+			match opcode {
+				Opcode::Ping => {
+					self.send(Opcode::Pong, &payload).await?;
+					continue;
+				}
+				Opcode::Pong => continue,
+				Opcode::Close => return Ok(Message::Close),
+				Opcode::Text | Opcode::Binary if message.is_none() => {
+					message = Some((opcode, payload));
+				}
+				Opcode::Text | Opcode::Binary => {
+					return Err(WsError::Protocol("expected a continuation"))
+				}
+				Opcode::Continuation => match message.as_mut() {
+					Some((_, data)) => data.extend_from_slice(&payload),
+					None => {
+						return Err(WsError::Protocol(
+							"unexpected continuation",
+						))
+					}
+				},
+			}
+
+			if fin {
+				return Ok(match message.take() {
+					Some((Opcode::Text, data)) => Message::Text(data),
+					Some((_, data)) => Message::Binary(data),
+					None => unreachable!("a data frame was just received"),
+				});
+			}
+		}
+	}
+
+	/// Send `data` as a single binary message.
+	pub(crate) async fn send_binary(
+		&mut self,
+		data: &[u8],
+	) -> std::io::Result<()> {
+		self.send(Opcode::Binary, data).await
+	}
+
+	/// Send a close frame with `code` and `reason`, which is cut short if it
+	/// does not fit the frame.
+	pub(crate) async fn close(
+		&mut self,
+		code: u16,
+		reason: &str,
+	) -> std::io::Result<()> {
+		let mut end = reason.len().min(MAX_CLOSE_REASON_LEN);
+		while !reason.is_char_boundary(end) {
+			end -= 1;
+		}
+
+		let mut payload = code.to_be_bytes().to_vec();
+		payload.extend_from_slice(&reason.as_bytes()[..end]);
+		self.send(Opcode::Close, &payload).await?;
+		self.stream.shutdown().await
+	}
+
+	/// Read a single frame, given `buffered` bytes of the current message
+	/// were received already.
+	async fn read_frame(
+		&mut self,
+		buffered: usize,
+	) -> Result<(bool, Opcode, Vec<u8>), WsError> {
+		let mut head = [0; 2];
+		self.stream.read_exact(&mut head).await?;
+
+		let fin = head[0] & 0x80 != 0;
+		if head[0] & 0x70 != 0 {
+			return Err(WsError::Protocol("reserved bits set"));
+		}
+		let opcode = Opcode::parse(head[0] & 0x0F)
+			.ok_or(WsError::Protocol("unknown opcode"))?;
+		if head[1] & 0x80 == 0 {
+			return Err(WsError::Protocol("client frames must be masked"));
+		}
+
+		let len = match head[1] & 0x7F {
+			126 => u64::from(self.stream.read_u16().await?),
+			127 => self.stream.read_u64().await?,
+			len => u64::from(len),
+		};
+		if opcode.is_control() && (!fin || len > 125) {
+			return Err(WsError::Protocol("invalid control frame"));
+		}
+		let len = usize::try_from(len).map_err(|_| WsError::TooBig)?;
+		if buffered.saturating_add(len) > self.max_message_size {
+			return Err(WsError::TooBig);
+		}
+
+		let mut mask = [0; 4];
+		self.stream.read_exact(&mut mask).await?;
+		let mut payload = vec![0; len];
+		self.stream.read_exact(&mut payload).await?;
+		for (byte, mask) in payload.iter_mut().zip(mask.iter().cycle()) {
+			*byte ^= mask;
+		}
+
+		Ok((fin, opcode, payload))
+	}
+
+	/// Send `payload` as a single, unmasked frame.
+	async fn send(
+		&mut self,
+		opcode: Opcode,
+		payload: &[u8],
+	) -> std::io::Result<()> {
+		let mut frame = vec![0x80 | opcode as u8];
+		match u16::try_from(payload.len()) {
+			Ok(len @ 0..=125) => {
+				frame.extend_from_slice(&len.to_be_bytes()[1..]);
+			}
+			Ok(len) => {
+				frame.push(126);
+				frame.extend_from_slice(&len.to_be_bytes());
+			}
+			Err(_) => {
+				frame.push(127);
+				frame.extend_from_slice(
+					&u64::try_from(payload.len())
+						.expect("usize fits into u64. qed.")
+						.to_be_bytes(),
+				);
+			}
+		}
+		frame.extend_from_slice(payload);
+
+		self.stream.write_all(&frame).await?;
+		self.stream.flush().await
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Encode a masked frame, as a client would send it.
+	fn client_frame(fin: bool, opcode: Opcode, payload: &[u8]) -> Vec<u8> {
+		let mask = [0x12, 0x34, 0x56, 0x78];
+		let mut frame = vec![u8::from(fin) << 7 | opcode as u8];
+		let len =
+			u16::try_from(payload.len()).expect("test payloads are small");
+		if len <= 125 {
+			frame.push(0x80 | len.to_be_bytes()[1]);
+		} else {
+			frame.push(0x80 | 126);
+			frame.extend_from_slice(&len.to_be_bytes());
+		}
+		frame.extend_from_slice(&mask);
+		frame.extend(
+			payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m),
+		);
+		frame
+	}
+
+	#[test]
+	fn computes_accept_key() {
+		// Example from RFC 6455, section 1.3
+		assert_eq!(
+			accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="),
+			"s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+		);
+	}
+
+	#[tokio::test]
+	async fn receives_fragmented_messages_and_answers_pings() {
+		let (mut client, server) = tokio::io::duplex(4096);
+		let mut socket = WebSocket::new(server, 300);
+
+		let large = vec![7; 200];
+		let mut frames = client_frame(false, Opcode::Binary, b"hello ");
+		frames.extend(client_frame(true, Opcode::Ping, b"ping"));
+		frames.extend(client_frame(true, Opcode::Continuation, b"world"));
+		frames.extend(client_frame(true, Opcode::Binary, &large));
+		frames.extend(client_frame(true, Opcode::Text, &[0; 301]));
+		client.write_all(&frames).await.unwrap();
+
+		assert_eq!(
+			socket.recv().await.unwrap(),
+			Message::Binary(b"hello world".to_vec())
+		);
+		assert_eq!(socket.recv().await.unwrap(), Message::Binary(large));
+		assert!(matches!(socket.recv().await, Err(WsError::TooBig)));
+
+		let mut pong = [0; 6];
+		client.read_exact(&mut pong).await.unwrap();
+		assert_eq!(pong, [0x8A, 4, b'p', b'i', b'n', b'g']);
+
+		socket.send_binary(b"hi").await.unwrap();
+		socket.close(CLOSE_NORMAL, "").await.unwrap();
+		let mut frames = vec![];
+		client.read_to_end(&mut frames).await.unwrap();
+		assert_eq!(frames, [0x82, 2, b'h', b'i', 0x88, 2, 0x03, 0xE8]);
+	}
+
+	#[tokio::test]
+	async fn rejects_unmasked_frames() {
+		let (mut client, server) = tokio::io::duplex(64);
+		let mut socket = WebSocket::new(server, 64);
+
+		client.write_all(&[0x82, 2, b'h', b'i']).await.unwrap();
+		assert!(matches!(socket.recv().await, Err(WsError::Protocol(_))));
+	}
+}
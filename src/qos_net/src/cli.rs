@@ -1,14 +1,19 @@
 //! CLI for running a host proxy to provide remote connections.
 
-use std::env;
+use std::{env, fs};
 
+use borsh::BorshDeserialize;
 use qos_core::{
 	io::SocketAddress,
 	parser::{GetParserForOptions, OptionsParser, Parser, Token},
+	protocol::services::boot::Manifest,
 	server::SocketServer,
 };
 
-use crate::proxy::Proxy;
+use crate::{
+	egress::EgressPolicy,
+	proxy::{Proxy, DEFAULT_MAX_CONNECTION_SIZE},
+};
 
 /// "cid"
 pub const CID: &str = "cid";
@@ -16,6 +21,17 @@ pub const CID: &str = "cid";
 pub const PORT: &str = "port";
 /// "usock"
 pub const USOCK: &str = "usock";
+/// "manifest-file"
+pub const MANIFEST_FILE: &str = "manifest-file";
+/// "max-connections"
+pub const MAX_CONNECTIONS: &str = "max-connections";
+/// "max-connections-per-destination"
+pub const MAX_CONNECTIONS_PER_DESTINATION: &str =
+	"max-connections-per-destination";
+/// "bandwidth-limit"
+pub const BANDWIDTH_LIMIT: &str = "bandwidth-limit";
+/// "destination-bandwidth-limit"
+pub const DESTINATION_BANDWIDTH_LIMIT: &str = "destination-bandwidth-limit";
 
 /// CLI options for starting up the proxy.
 #[derive(Default, Clone, Debug, PartialEq)]
@@ -53,6 +69,53 @@ impl ProxyOpts {
 			_ => panic!("Invalid socket opts"),
 		}
 	}
+
+	/// Get the egress allow-list of the manifest given by `--manifest-file`,
+	/// if any.
+	///
+	/// # Panics
+	///
+	/// Panics if the file can't be read or is not a borsh encoded manifest.
+	fn egress(&self) -> Option<EgressPolicy> {
+		self.parsed.single(MANIFEST_FILE).map(|path| {
+			let bytes = fs::read(path).expect("Failed to read manifest file");
+			let manifest = Manifest::try_from_slice(&bytes)
+				.expect("File did not have a valid manifest");
+			EgressPolicy::from(&manifest)
+		})
+	}
+
+	/// Parse the value of the numeric option `name`, if given.
+	///
+	/// # Panics
+	///
+	/// Panics if the value is not a valid number.
+	fn number<T: std::str::FromStr>(&self, name: &str) -> Option<T> {
+		self.parsed.single(name).map(|value| {
+			value.parse().unwrap_or_else(|_| panic!("Invalid --{name}"))
+		})
+	}
+
+	/// Create the proxy with the egress policy and quotas from the options.
+	fn proxy(&self) -> Proxy {
+		let mut proxy = Proxy::new_with_max_connections(
+			self.number(MAX_CONNECTIONS).unwrap_or(DEFAULT_MAX_CONNECTION_SIZE),
+		);
+		if let Some(policy) = self.egress() {
+			proxy = proxy.with_egress(policy);
+		}
+		if let Some(max) = self.number(MAX_CONNECTIONS_PER_DESTINATION) {
+			proxy = proxy.with_max_connections_per_destination(max);
+		}
+		if let Some(rate) = self.number(BANDWIDTH_LIMIT) {
+			proxy = proxy.with_bandwidth_limit(rate);
+		}
+		if let Some(rate) = self.number(DESTINATION_BANDWIDTH_LIMIT) {
+			proxy = proxy.with_destination_bandwidth_limit(rate);
+		}
+
+		proxy
+	}
 }
 
 /// Proxy CLI.
@@ -68,7 +131,7 @@ impl CLI {
 		} else if opts.parsed.help() {
 			println!("{}", opts.parsed.info());
 		} else {
-			SocketServer::listen(opts.addr(), Proxy::new()).unwrap();
+			SocketServer::listen(opts.addr(), opts.proxy()).unwrap();
 		}
 	}
 }
@@ -98,11 +161,52 @@ impl GetParserForOptions for ProxyParser {
 					.takes_value(true)
 					.forbids(vec!["port", "cid"]),
 			)
+			.token(
+				Token::new(
+					MANIFEST_FILE,
+					"manifest whose egress allow-list the proxy should enforce. \
+					Connections to any endpoint are allowed without it.",
+				)
+				.takes_value(true),
+			)
+			.token(
+				Token::new(
+					MAX_CONNECTIONS,
+					"maximum number of connections open at once. Defaults to 512.",
+				)
+				.takes_value(true),
+			)
+			.token(
+				Token::new(
+					MAX_CONNECTIONS_PER_DESTINATION,
+					"maximum number of connections open at once to the same \
+					`host:port`.",
+				)
+				.takes_value(true),
+			)
+			.token(
+				Token::new(
+					BANDWIDTH_LIMIT,
+					"bytes per second all connections may read and write \
+					together.",
+				)
+				.takes_value(true),
+			)
+			.token(
+				Token::new(
+					DESTINATION_BANDWIDTH_LIMIT,
+					"bytes per second the connections to each `host:port` may \
+					read and write together.",
+				)
+				.takes_value(true),
+			)
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use qos_test_primitives::PathWrapper;
+
 	use super::*;
 
 	#[test]
@@ -127,6 +231,68 @@ mod test {
 		assert_eq!(opts.addr(), SocketAddress::new_unix("./test.sock"));
 	}
 
+	#[test]
+	fn egress_comes_from_the_manifest_file() {
+		let path: PathWrapper = "./egress_manifest_file.manifest".into();
+		let mut manifest = Manifest::default();
+		manifest.pivot.egress =
+			vec!["api.turnkey.com:443".to_string().try_into().unwrap()];
+		fs::write(&*path, borsh::to_vec(&manifest).unwrap()).unwrap();
+
+		let mut args: Vec<_> =
+			vec!["binary", "--usock", "./test.sock", "--manifest-file", &*path]
+				.into_iter()
+				.map(String::from)
+				.collect();
+		let opts = ProxyOpts::new(&mut args);
+
+		assert_eq!(opts.egress(), Some(EgressPolicy::from(&manifest)));
+
+		let mut args: Vec<_> = vec!["binary", "--usock", "./test.sock"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		assert_eq!(ProxyOpts::new(&mut args).egress(), None);
+	}
+
+	#[test]
+	fn parse_quotas() {
+		let mut args: Vec<_> = vec![
+			"binary",
+			"--usock",
+			"./test.sock",
+			"--max-connections",
+			"8",
+			"--max-connections-per-destination",
+			"2",
+			"--bandwidth-limit",
+			"1000",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		let opts = ProxyOpts::new(&mut args);
+
+		assert_eq!(opts.number::<usize>(MAX_CONNECTIONS), Some(8));
+		assert_eq!(
+			opts.number::<usize>(MAX_CONNECTIONS_PER_DESTINATION),
+			Some(2)
+		);
+		assert_eq!(opts.number::<u64>(BANDWIDTH_LIMIT), Some(1000));
+		assert_eq!(opts.number::<u64>(DESTINATION_BANDWIDTH_LIMIT), None);
+	}
+
+	#[test]
+	#[should_panic = "Invalid --bandwidth-limit"]
+	fn panic_on_invalid_quota() {
+		let mut args: Vec<_> =
+			vec!["binary", "--usock", "./test.sock", "--bandwidth-limit", "1k"]
+				.into_iter()
+				.map(String::from)
+				.collect();
+		let _proxy = ProxyOpts::new(&mut args).proxy();
+	}
+
 	#[test]
 	#[should_panic = "Entered invalid CLI args: MutuallyExclusiveInput(\"cid\", \"usock\")"]
 	fn panic_on_too_many_opts() {
@@ -0,0 +1,172 @@
+//! Enclave side allow list for the connections a pivot opens through the
+//! proxy.
+//!
+//! The manifest lists the endpoints the pivot may connect to, see
+//! [`qos_core::protocol::services::boot::PivotConfig::egress`]. An
+//! [`EgressPolicy`] built from the pivot info refuses to open a
+//! [`ProxyStream`] to anything else, so quorum members know where the pivot
+//! can send data when they approve the manifest.
+//!
+//! Pivots that speak TLS should connect with
+//! [`crate::tls`], which also checks the keys the manifest pins.
+//!
+//! The host proxy can enforce the same allow-list, see
+//! [`crate::proxy::Proxy::with_egress`], but since the host is untrusted
+//! that only protects the host from a misbehaving pivot. The proxy also
+//! resolves hostnames, so connections must still authenticate the endpoint,
+//! e.g. with TLS.
+
+use qos_core::{
+	handles::{PivotInfo, PivotInfoHandle},
+	io::{SocketAddress, TimeVal},
+	protocol::{
+		services::boot::{EgressEndpoint, Manifest},
+		ProtocolError,
+	},
+};
+
+use crate::{error::QosNetError, proxy_stream::ProxyStream};
+
+/// Endpoints a pivot may open [`ProxyStream`]s to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressPolicy {
+	allowed: Vec<EgressEndpoint>,
+}
+
+impl EgressPolicy {
+	/// Create a new instance of [`Self`] allowing only `allowed`.
+	#[must_use]
+	pub fn new(allowed: Vec<EgressEndpoint>) -> Self {
+		Self { allowed }
+	}
+
+	/// The endpoints allowed by the manifest the enclave booted with, read
+	/// from the pivot info. See [`PivotInfoHandle::from_env`].
+	pub fn from_env() -> Result<Self, ProtocolError> {
+		let info = PivotInfoHandle::from_env().get_pivot_info()?;
+		Ok(Self::from(&info))
+	}
+
+	/// Check that the policy allows connecting to `host` on `port`.
+	pub fn check(&self, host: &str, port: u16) -> Result<(), QosNetError> {
+		if self.allowed.iter().any(|endpoint| endpoint.allows(host, port)) {
+			Ok(())
+		} else {
+			Err(QosNetError::EgressNotAllowed(format!("{host}:{port}")))
+		}
+	}
+
+	/// The keys pinned for TLS connections to `host` on `port`, from every
+	/// allowed endpoint matching it. Empty if none of them pin keys.
+	#[must_use]
+	pub fn tls_pins(&self, host: &str, port: u16) -> Vec<[u8; 32]> {
+		self.allowed
+			.iter()
+			.filter(|endpoint| endpoint.allows(host, port))
+			.flat_map(|endpoint| endpoint.tls_pins.iter().copied())
+			.collect()
+	}
+
+	/// Like [`ProxyStream::connect_by_name`], but refuse endpoints the policy
+	/// does not allow without contacting the proxy.
+	pub fn connect_by_name(
+		&self,
+		addr: &SocketAddress,
+		timeout: TimeVal,
+		hostname: String,
+		port: u16,
+		dns_resolvers: Vec<String>,
+		dns_port: u16,
+	) -> Result<ProxyStream, QosNetError> {
+		self.check(&hostname, port)?;
+		ProxyStream::connect_by_name(
+			addr,
+			timeout,
+			hostname,
+			port,
+			dns_resolvers,
+			dns_port,
+		)
+	}
+
+	/// Like [`ProxyStream::connect_by_ip`], but refuse endpoints the policy
+	/// does not allow without contacting the proxy.
+	pub fn connect_by_ip(
+		&self,
+		addr: &SocketAddress,
+		timeout: TimeVal,
+		ip: String,
+		port: u16,
+	) -> Result<ProxyStream, QosNetError> {
+		self.check(&ip, port)?;
+		ProxyStream::connect_by_ip(addr, timeout, ip, port)
+	}
+}
+
+impl From<&PivotInfo> for EgressPolicy {
+	fn from(info: &PivotInfo) -> Self {
+		Self::new(info.egress.clone())
+	}
+}
+
+impl From<&Manifest> for EgressPolicy {
+	fn from(manifest: &Manifest) -> Self {
+		Self::new(manifest.pivot.egress.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::io::TimeValLike;
+
+	use super::*;
+
+	#[test]
+	fn only_allowed_endpoints_are_connected_to() {
+		let policy = EgressPolicy::new(vec![
+			EgressEndpoint::try_from("api.example.com:443".to_string())
+				.unwrap(),
+			EgressEndpoint::try_from("10.0.0.1:8080".to_string()).unwrap(),
+		]);
+		assert_eq!(policy.check("api.example.com", 443), Ok(()));
+		assert_eq!(policy.check("10.0.0.1", 8080), Ok(()));
+		assert_eq!(
+			policy.check("api.example.com", 80),
+			Err(QosNetError::EgressNotAllowed(
+				"api.example.com:80".to_string()
+			))
+		);
+
+		// Refused before the proxy, which does not exist, is contacted
+		let addr = SocketAddress::new_unix("./no_egress_proxy.sock");
+		assert!(matches!(
+			policy.connect_by_name(
+				&addr,
+				TimeVal::seconds(1),
+				"evil.com".to_string(),
+				443,
+				vec!["8.8.8.8".to_string()],
+				53,
+			),
+			Err(QosNetError::EgressNotAllowed(_))
+		));
+		assert!(matches!(
+			policy.connect_by_ip(
+				&addr,
+				TimeVal::seconds(1),
+				"10.0.0.2".to_string(),
+				8080
+			),
+			Err(QosNetError::EgressNotAllowed(_))
+		));
+		assert!(matches!(
+			policy.connect_by_ip(
+				&addr,
+				TimeVal::seconds(1),
+				"10.0.0.1".to_string(),
+				8080
+			),
+			Err(QosNetError::QOSIOError(_))
+		));
+	}
+}
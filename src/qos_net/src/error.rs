@@ -33,6 +33,15 @@ pub enum QosNetError {
 	ReadOverflow(usize, usize),
 	/// Happens when too many connections are opened in the proxy
 	TooManyConnections(usize),
+	/// The manifest does not allow the pivot to connect to the endpoint,
+	/// written `host:port`.
+	EgressNotAllowed(String),
+	/// The TLS connection could not be set up, e.g. because the server
+	/// certificate is invalid or does not chain through a pinned key.
+	TlsError(String),
+	/// Happens when too many connections to the same destination, written
+	/// `host:port`, are opened in the proxy.
+	TooManyDestinationConnections(String),
 }
 
 impl From<std::io::Error> for QosNetError {
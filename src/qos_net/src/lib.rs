@@ -2,11 +2,16 @@
 //! opens TCP connection.
 //! It exposes a simple protocol for remote clients who
 //! connect to let them manipulate these connections (read/write/flush)
+//!
+//! Pivots only connect to the endpoints their manifest allows, see
+//! [`egress`], and the proxy can limit their connections and bandwidth, see
+//! [`quota`].
 
 #![deny(clippy::all, unsafe_code)]
 
 #[cfg(feature = "proxy")]
 pub mod cli;
+pub mod egress;
 pub mod error;
 #[cfg(feature = "proxy")]
 pub mod proxy;
@@ -14,3 +19,7 @@ pub mod proxy;
 pub mod proxy_connection;
 pub mod proxy_msg;
 pub mod proxy_stream;
+#[cfg(feature = "proxy")]
+pub mod quota;
+#[cfg(feature = "tls")]
+pub mod tls;
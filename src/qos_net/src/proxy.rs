@@ -1,13 +1,18 @@
 //! Protocol proxy for our remote QOS net proxy
-use std::io::{Read, Write};
+use std::{
+	io::{Read, Write},
+	sync::Arc,
+};
 
 use borsh::BorshDeserialize;
-use qos_core::server;
+use qos_core::{log, server};
 
 use crate::{
+	egress::EgressPolicy,
 	error::QosNetError,
 	proxy_connection::{self, ProxyConnection},
 	proxy_msg::ProxyMsg,
+	quota::{ProxyMetrics, Quotas},
 };
 
 const MEGABYTE: usize = 1024 * 1024;
@@ -19,6 +24,8 @@ pub const DEFAULT_MAX_CONNECTION_SIZE: usize = 512;
 pub struct Proxy {
 	connections: Vec<ProxyConnection>,
 	max_connections: usize,
+	egress: Option<EgressPolicy>,
+	quotas: Quotas,
 }
 
 impl Default for Proxy {
@@ -34,12 +41,93 @@ impl Proxy {
 		Self {
 			connections: vec![],
 			max_connections: DEFAULT_MAX_CONNECTION_SIZE,
+			egress: None,
+			quotas: Quotas::default(),
 		}
 	}
 
 	#[must_use]
 	pub fn new_with_max_connections(max_connections: usize) -> Self {
-		Self { connections: vec![], max_connections }
+		Self {
+			connections: vec![],
+			max_connections,
+			egress: None,
+			quotas: Quotas::default(),
+		}
+	}
+
+	/// Only open connections to the endpoints `policy` allows. Without a
+	/// policy the proxy connects anywhere.
+	///
+	/// Pivots check the manifest's allow-list themselves, see
+	/// [`EgressPolicy`]. Enforcing it here too keeps a compromised pivot from
+	/// using the host's network beyond what the quorum approved.
+	#[must_use]
+	pub fn with_egress(mut self, policy: EgressPolicy) -> Self {
+		self.egress = Some(policy);
+		self
+	}
+
+	/// Refuse more than `max` connections open at once to the same
+	/// destination.
+	#[must_use]
+	pub fn with_max_connections_per_destination(mut self, max: usize) -> Self {
+		self.quotas.set_max_connections_per_destination(max);
+		self
+	}
+
+	/// Limit all connections together to `rate` bytes per second, counting
+	/// both reads and writes.
+	///
+	/// # Panics
+	///
+	/// Panics if `rate` is 0.
+	#[must_use]
+	pub fn with_bandwidth_limit(mut self, rate: u64) -> Self {
+		self.quotas.set_bandwidth(rate);
+		self
+	}
+
+	/// Limit the connections to each destination to `rate` bytes per second,
+	/// counting both reads and writes.
+	///
+	/// # Panics
+	///
+	/// Panics if `rate` is 0.
+	#[must_use]
+	pub fn with_destination_bandwidth_limit(mut self, rate: u64) -> Self {
+		self.quotas.set_destination_bandwidth(rate);
+		self
+	}
+
+	/// Report connections and bandwidth per destination to `metrics`.
+	#[must_use]
+	pub fn with_metrics(mut self, metrics: Arc<dyn ProxyMetrics>) -> Self {
+		self.quotas.metrics = metrics;
+		self
+	}
+
+	/// Check that the egress policy and quotas allow a new connection to
+	/// `host` on `port`.
+	fn admit(&self, host: &str, port: u16) -> Result<(), QosNetError> {
+		let destination = format!("{host}:{port}");
+		let result = match &self.egress {
+			Some(policy) => policy.check(host, port),
+			None => Ok(()),
+		}
+		.and_then(|()| {
+			let open = self
+				.connections
+				.iter()
+				.filter(|c| c.destination == destination)
+				.count();
+			self.quotas.check_connect(&destination, open)
+		});
+		if let Err(e) = &result {
+			self.quotas.metrics.connection_refused(&destination, e);
+		}
+
+		result
 	}
 
 	fn save_connection(
@@ -50,10 +138,13 @@ impl Proxy {
 			Err(QosNetError::DuplicateConnectionId(connection.id))
 		} else {
 			if self.connections.len() >= self.max_connections {
-				return Err(QosNetError::TooManyConnections(
-					self.max_connections,
-				));
+				let e = QosNetError::TooManyConnections(self.max_connections);
+				self.quotas
+					.metrics
+					.connection_refused(&connection.destination, &e);
+				return Err(e);
 			}
+			self.quotas.metrics.connection_opened(&connection.destination);
 			self.connections.push(connection);
 			Ok(())
 		}
@@ -62,7 +153,16 @@ impl Proxy {
 	fn remove_connection(&mut self, id: u32) -> Result<(), QosNetError> {
 		match self.connections.iter().position(|c| c.id == id) {
 			Some(i) => {
-				self.connections.remove(i);
+				let connection = self.connections.remove(i);
+				let destination = &connection.destination;
+				self.quotas.metrics.connection_closed(destination);
+				if !self
+					.connections
+					.iter()
+					.any(|c| &c.destination == destination)
+				{
+					self.quotas.forget(destination);
+				}
 				Ok(())
 			}
 			None => Err(QosNetError::ConnectionIdNotFound(id)),
@@ -95,6 +195,14 @@ impl Proxy {
 		dns_resolvers: Vec<String>,
 		dns_port: u16,
 	) -> ProxyMsg {
+		if let Err(e) = self.admit(&hostname, port) {
+			log::warn(
+				"net",
+				format_args!("egress refused {hostname}:{port}: {e:?}"),
+			);
+			return ProxyMsg::ProxyError(e);
+		}
+
 		match proxy_connection::ProxyConnection::new_from_name(
 			hostname.clone(),
 			port,
@@ -127,6 +235,11 @@ impl Proxy {
 	/// Create a new connection, targeting an IP address directly.
 	/// address. The TCP connection is opened and saved in internal state.
 	pub fn connect_by_ip(&mut self, ip: String, port: u16) -> ProxyMsg {
+		if let Err(e) = self.admit(&ip, port) {
+			log::warn("net", format_args!("egress refused {ip}:{port}: {e:?}"));
+			return ProxyMsg::ProxyError(e);
+		}
+
 		match proxy_connection::ProxyConnection::new_from_ip(ip.clone(), port) {
 			Ok(conn) => {
 				let connection_id = conn.id;
@@ -149,8 +262,23 @@ impl Proxy {
 		}
 	}
 
-	/// Performs a Read on a connection
+	/// Destination of the connection with `id`, and the number of bytes, at
+	/// most `want`, the bandwidth limits allow to transfer on it now.
+	fn allowance(&mut self, id: u32, want: usize) -> Option<(String, usize)> {
+		let destination = self.get_connection(id)?.destination.clone();
+		let allowed = self.quotas.allowance(&destination, want);
+		Some((destination, allowed))
+	}
+
+	/// Performs a Read on a connection. Reads at most as many bytes as the
+	/// bandwidth limits allow.
 	pub fn read(&mut self, connection_id: u32, size: usize) -> ProxyMsg {
+		let Some((destination, size)) = self.allowance(connection_id, size)
+		else {
+			return ProxyMsg::ProxyError(QosNetError::ConnectionIdNotFound(
+				connection_id,
+			));
+		};
 		if let Some(conn) = self.get_connection(connection_id) {
 			let mut buf: Vec<u8> = vec![0; size];
 			match conn.read(&mut buf) {
@@ -170,6 +298,8 @@ impl Proxy {
 					}
 				}
 				Ok(size) => {
+					self.quotas.consume(&destination, size);
+					self.quotas.metrics.bytes_read(&destination, size);
 					ProxyMsg::ReadResponse { connection_id, data: buf, size }
 				}
 				Err(e) => match self.remove_connection(connection_id) {
@@ -184,11 +314,23 @@ impl Proxy {
 		}
 	}
 
-	/// Performs a Write on an existing connection
+	/// Performs a Write on an existing connection. Writes at most as many
+	/// bytes as the bandwidth limits allow.
 	pub fn write(&mut self, connection_id: u32, data: Vec<u8>) -> ProxyMsg {
+		let Some((destination, allowed)) =
+			self.allowance(connection_id, data.len())
+		else {
+			return ProxyMsg::ProxyError(QosNetError::ConnectionIdNotFound(
+				connection_id,
+			));
+		};
 		if let Some(conn) = self.get_connection(connection_id) {
-			match conn.write(&data) {
-				Ok(size) => ProxyMsg::WriteResponse { connection_id, size },
+			match conn.write(&data[..allowed]) {
+				Ok(size) => {
+					self.quotas.consume(&destination, size);
+					self.quotas.metrics.bytes_written(&destination, size);
+					ProxyMsg::WriteResponse { connection_id, size }
+				}
 				Err(e) => ProxyMsg::ProxyError(e.into()),
 			}
 		} else {
@@ -293,6 +435,7 @@ mod test {
 	use server::RequestProcessor;
 
 	use super::*;
+	use crate::quota::{DestinationCounters, ProxyCounters};
 
 	#[test]
 	fn simple_status_request() {
@@ -385,6 +528,84 @@ mod test {
 		));
 	}
 
+	#[test]
+	fn refuses_endpoints_the_policy_does_not_allow() {
+		let mut proxy =
+			Proxy::new().with_egress(EgressPolicy::new(vec!["1.1.1.1:53"
+				.to_string()
+				.try_into()
+				.unwrap()]));
+
+		assert_eq!(
+			proxy.connect_by_ip("8.8.8.8".to_string(), 53),
+			ProxyMsg::ProxyError(QosNetError::EgressNotAllowed(
+				"8.8.8.8:53".to_string()
+			))
+		);
+		assert_eq!(
+			proxy.connect_by_name(
+				"api.turnkey.com".to_string(),
+				443,
+				vec!["8.8.8.8".to_string()],
+				53
+			),
+			ProxyMsg::ProxyError(QosNetError::EgressNotAllowed(
+				"api.turnkey.com:443".to_string()
+			))
+		);
+		assert_eq!(proxy.num_connections(), 0);
+
+		assert!(matches!(
+			proxy.connect_by_ip("1.1.1.1".to_string(), 53),
+			ProxyMsg::ConnectResponse { connection_id: _, remote_ip: _ }
+		));
+	}
+
+	#[test]
+	fn enforces_quotas_and_reports_metrics() {
+		let counters = Arc::new(ProxyCounters::default());
+		let mut proxy = Proxy::new()
+			.with_max_connections_per_destination(1)
+			.with_bandwidth_limit(4)
+			.with_metrics(counters.clone());
+
+		let ProxyMsg::ConnectResponse { connection_id, remote_ip: _ } =
+			proxy.connect_by_ip("1.1.1.1".to_string(), 53)
+		else {
+			panic!("test failure: expected ConnectResponse")
+		};
+		assert_eq!(
+			proxy.connect_by_ip("1.1.1.1".to_string(), 53),
+			ProxyMsg::ProxyError(QosNetError::TooManyDestinationConnections(
+				"1.1.1.1:53".to_string()
+			))
+		);
+		// Other destinations have their own count
+		assert!(matches!(
+			proxy.connect_by_ip("8.8.8.8".to_string(), 53),
+			ProxyMsg::ConnectResponse { connection_id: _, remote_ip: _ }
+		));
+
+		// Writes are cut down to the bandwidth left
+		assert_eq!(
+			proxy.write(connection_id, vec![0; 10]),
+			ProxyMsg::WriteResponse { connection_id, size: 4 }
+		);
+		proxy.close(connection_id);
+
+		assert_eq!(
+			counters.snapshot()["1.1.1.1:53"],
+			DestinationCounters {
+				open_connections: 0,
+				connections: 1,
+				refused_connections: 1,
+				bytes_written: 4,
+				..Default::default()
+			}
+		);
+		assert_eq!(counters.snapshot()["8.8.8.8:53"].open_connections, 1);
+	}
+
 	#[test]
 	fn closes_connections() {
 		let mut proxy = Proxy::new_with_max_connections(2);
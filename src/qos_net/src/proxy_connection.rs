@@ -19,6 +19,8 @@ pub struct ProxyConnection {
 	pub id: u32,
 	/// IP address of the remote host
 	pub ip: String,
+	/// Destination the connection was requested for, written `host:port`
+	pub destination: String,
 	/// TCP stream object
 	tcp_stream: TcpStream,
 }
@@ -32,6 +34,7 @@ impl ProxyConnection {
 		dns_resolvers: Vec<String>,
 		dns_port: u16,
 	) -> Result<ProxyConnection, QosNetError> {
+		let destination = format!("{hostname}:{port}");
 		let ip = resolve_hostname(hostname, dns_resolvers, dns_port)?;
 
 		// Generate a new random u32 to get an ID. We'll use it to name our
@@ -44,6 +47,7 @@ impl ProxyConnection {
 		Ok(ProxyConnection {
 			id: connection_id,
 			ip: ip.to_string(),
+			destination,
 			tcp_stream,
 		})
 	}
@@ -63,7 +67,12 @@ impl ProxyConnection {
 		let tcp_addr = SocketAddr::new(ip_addr, port);
 		let tcp_stream = TcpStream::connect(tcp_addr)?;
 
-		Ok(ProxyConnection { id: connection_id, ip, tcp_stream })
+		Ok(ProxyConnection {
+			id: connection_id,
+			destination: format!("{ip}:{port}"),
+			ip,
+			tcp_stream,
+		})
 	}
 }
 
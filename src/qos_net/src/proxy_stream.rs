@@ -70,6 +70,7 @@ impl ProxyStream {
 						remote_hostname: Some(hostname),
 					})
 				}
+				ProxyMsg::ProxyError(e) => Err(e),
 				_ => Err(QosNetError::InvalidMsg),
 			},
 			Err(_) => Err(QosNetError::InvalidMsg),
@@ -108,6 +109,7 @@ impl ProxyStream {
 						remote_hostname: None,
 					})
 				}
+				ProxyMsg::ProxyError(e) => Err(e),
 				_ => Err(QosNetError::InvalidMsg),
 			},
 			Err(_) => Err(QosNetError::InvalidMsg),
@@ -308,12 +310,56 @@ mod test {
 
 	use chunked_transfer::Decoder;
 	use httparse::Response;
-	use qos_core::server::RequestProcessor;
+	use qos_core::{
+		io::TimeValLike,
+		server::{RequestProcessor, SocketServer},
+	};
+	use qos_test_primitives::PathWrapper;
 	use rustls::{RootCertStore, SupportedCipherSuite};
 	use serde_json::Value;
 
 	use super::*;
-	use crate::proxy::Proxy;
+	use crate::{egress::EgressPolicy, proxy::Proxy};
+
+	#[test]
+	fn returns_egress_refusals_to_the_enclave() {
+		let socket: PathWrapper =
+			"./returns_egress_refusals_to_the_enclave.sock".into();
+		let addr = SocketAddress::new_unix(&socket);
+		let proxy =
+			Proxy::new().with_egress(EgressPolicy::new(vec!["1.1.1.1:53"
+				.to_string()
+				.try_into()
+				.unwrap()]));
+		let server_addr = addr.clone();
+		std::thread::spawn(move || SocketServer::listen(server_addr, proxy));
+
+		let timeout = TimeVal::seconds(5);
+		assert_eq!(
+			ProxyStream::connect_by_ip(
+				&addr,
+				timeout,
+				"8.8.8.8".to_string(),
+				53
+			)
+			.err(),
+			Some(QosNetError::EgressNotAllowed("8.8.8.8:53".to_string()))
+		);
+		assert_eq!(
+			ProxyStream::connect_by_name(
+				&addr,
+				timeout,
+				"api.turnkey.com".to_string(),
+				443,
+				vec!["8.8.8.8".to_string()],
+				53
+			)
+			.err(),
+			Some(QosNetError::EgressNotAllowed(
+				"api.turnkey.com:443".to_string()
+			))
+		);
+	}
 
 	#[test]
 	fn can_fetch_and_parse_chunked_json_over_tls_with_local_stream() {
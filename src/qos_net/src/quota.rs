@@ -0,0 +1,374 @@
+//! Connection and bandwidth quotas of the proxy, so one misbehaving pivot
+//! can't saturate the network of the parent instance.
+//!
+//! A proxy serves a single enclave, so its limits apply to the enclave as a
+//! whole. Each limit can also be set per destination, the `host:port` a
+//! connection was requested for. Connections over a limit are refused with a
+//! [`QosNetError`], while reads and writes over a bandwidth limit are
+//! shortened, or wait until the limit allows at least one byte, so streams
+//! slow down instead of failing.
+//!
+//! Quotas report what they see to a [`ProxyMetrics`] implementation, such as
+//! [`ProxyCounters`].
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Arc, Mutex},
+	thread,
+	time::{Duration, Instant},
+};
+
+use crate::error::QosNetError;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Events emitted by the proxy for each destination, written `host:port`.
+/// All methods default to doing nothing, so implementors only need to
+/// override the events they care about.
+pub trait ProxyMetrics: Send + Sync {
+	/// A connection to `destination` was opened.
+	fn connection_opened(&self, _destination: &str) {}
+
+	/// A connection to `destination` was closed.
+	fn connection_closed(&self, _destination: &str) {}
+
+	/// A connection to `destination` was refused, by a quota or the egress
+	/// policy.
+	fn connection_refused(&self, _destination: &str, _error: &QosNetError) {}
+
+	/// `bytes` were written to `destination`.
+	fn bytes_written(&self, _destination: &str, _bytes: usize) {}
+
+	/// `bytes` were read from `destination`.
+	fn bytes_read(&self, _destination: &str, _bytes: usize) {}
+
+	/// A read or write to `destination` waited `wait` for bandwidth.
+	fn throttled(&self, _destination: &str, _wait: Duration) {}
+}
+
+/// [`ProxyMetrics`] that ignores all events.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopProxyMetrics;
+
+impl ProxyMetrics for NoopProxyMetrics {}
+
+/// Counters of a single destination, see [`ProxyCounters`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DestinationCounters {
+	/// Number of connections currently open.
+	pub open_connections: u64,
+	/// Number of connections opened.
+	pub connections: u64,
+	/// Number of connections refused.
+	pub refused_connections: u64,
+	/// Total bytes written.
+	pub bytes_written: u64,
+	/// Total bytes read.
+	pub bytes_read: u64,
+	/// Number of reads and writes that waited for bandwidth.
+	pub throttled: u64,
+	/// Total time spent waiting for bandwidth.
+	pub throttled_for: Duration,
+}
+
+/// [`ProxyMetrics`] keeping [`DestinationCounters`] per destination in
+/// memory.
+#[derive(Debug, Default)]
+pub struct ProxyCounters {
+	destinations: Mutex<BTreeMap<String, DestinationCounters>>,
+}
+
+impl ProxyCounters {
+	/// Take a snapshot of the counters of every destination seen so far.
+	#[must_use]
+	pub fn snapshot(&self) -> BTreeMap<String, DestinationCounters> {
+		self.destinations
+			.lock()
+			.expect("counters are never poisoned. qed.")
+			.clone()
+	}
+
+	fn update(
+		&self,
+		destination: &str,
+		update: impl FnOnce(&mut DestinationCounters),
+	) {
+		let mut destinations = self
+			.destinations
+			.lock()
+			.expect("counters are never poisoned. qed.");
+		update(destinations.entry(destination.to_string()).or_default());
+	}
+}
+
+impl ProxyMetrics for ProxyCounters {
+	fn connection_opened(&self, destination: &str) {
+		self.update(destination, |counters| {
+			counters.open_connections += 1;
+			counters.connections += 1;
+		});
+	}
+
+	fn connection_closed(&self, destination: &str) {
+		self.update(destination, |counters| {
+			counters.open_connections =
+				counters.open_connections.saturating_sub(1);
+		});
+	}
+
+	fn connection_refused(&self, destination: &str, _error: &QosNetError) {
+		self.update(destination, |counters| counters.refused_connections += 1);
+	}
+
+	fn bytes_written(&self, destination: &str, bytes: usize) {
+		self.update(destination, |counters| {
+			counters.bytes_written += bytes as u64;
+		});
+	}
+
+	fn bytes_read(&self, destination: &str, bytes: usize) {
+		self.update(destination, |counters| {
+			counters.bytes_read += bytes as u64;
+		});
+	}
+
+	fn throttled(&self, destination: &str, wait: Duration) {
+		self.update(destination, |counters| {
+			counters.throttled += 1;
+			counters.throttled_for += wait;
+		});
+	}
+}
+
+/// Token bucket allowing `rate` bytes per second, with bursts of up to one
+/// second worth of bytes.
+#[derive(Debug)]
+struct TokenBucket {
+	rate: u64,
+	available: u64,
+	refilled_at: Instant,
+}
+
+impl TokenBucket {
+	fn new(rate: u64) -> Self {
+		Self { rate, available: rate, refilled_at: Instant::now() }
+	}
+
+	fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.refilled_at);
+		let tokens =
+			elapsed.as_nanos() * u128::from(self.rate) / NANOS_PER_SECOND;
+		let tokens = u64::try_from(tokens).unwrap_or(u64::MAX);
+		if self.available.saturating_add(tokens) >= self.rate {
+			self.available = self.rate;
+			self.refilled_at = now;
+		} else if tokens > 0 {
+			self.available += tokens;
+			// Only advance by the time the tokens took, so fractions of a
+			// token are not lost between refills
+			let nanos =
+				u128::from(tokens) * NANOS_PER_SECOND / u128::from(self.rate);
+			self.refilled_at += Duration::from_nanos(
+				u64::try_from(nanos).expect("less than a second. qed."),
+			);
+		}
+	}
+
+	/// Time until the next token is available.
+	fn wait(&self, now: Instant) -> Duration {
+		let nanos = NANOS_PER_SECOND.div_ceil(u128::from(self.rate));
+		let next = self.refilled_at
+			+ Duration::from_nanos(
+				u64::try_from(nanos).expect("at most a second. qed."),
+			);
+		next.saturating_duration_since(now)
+	}
+
+	fn take(&mut self, tokens: u64) {
+		self.available = self.available.saturating_sub(tokens);
+	}
+}
+
+/// The quotas of a proxy and the state to enforce them.
+pub(crate) struct Quotas {
+	max_connections_per_destination: Option<usize>,
+	bandwidth: Option<TokenBucket>,
+	destination_bandwidth: Option<u64>,
+	destination_buckets: HashMap<String, TokenBucket>,
+	pub(crate) metrics: Arc<dyn ProxyMetrics>,
+}
+
+impl Default for Quotas {
+	fn default() -> Self {
+		Self {
+			max_connections_per_destination: None,
+			bandwidth: None,
+			destination_bandwidth: None,
+			destination_buckets: HashMap::new(),
+			metrics: Arc::new(NoopProxyMetrics),
+		}
+	}
+}
+
+impl Quotas {
+	pub(crate) fn set_max_connections_per_destination(&mut self, max: usize) {
+		self.max_connections_per_destination = Some(max);
+	}
+
+	/// Limit all connections together to `rate` bytes per second.
+	///
+	/// # Panics
+	///
+	/// Panics if `rate` is 0.
+	pub(crate) fn set_bandwidth(&mut self, rate: u64) {
+		assert!(rate > 0, "bandwidth must allow at least 1 byte per second");
+		self.bandwidth = Some(TokenBucket::new(rate));
+	}
+
+	/// Limit the connections to each destination to `rate` bytes per second.
+	///
+	/// # Panics
+	///
+	/// Panics if `rate` is 0.
+	pub(crate) fn set_destination_bandwidth(&mut self, rate: u64) {
+		assert!(rate > 0, "bandwidth must allow at least 1 byte per second");
+		self.destination_bandwidth = Some(rate);
+	}
+
+	/// Check that another connection to `destination` is allowed, given the
+	/// number of connections to it that are already `open`.
+	pub(crate) fn check_connect(
+		&self,
+		destination: &str,
+		open: usize,
+	) -> Result<(), QosNetError> {
+		match self.max_connections_per_destination {
+			Some(max) if open >= max => {
+				Err(QosNetError::TooManyDestinationConnections(
+					destination.to_string(),
+				))
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Number of bytes, at most `want`, that may be read from or written to
+	/// `destination` now. Waits until at least one byte is allowed, unless
+	/// `want` is 0. Call [`Self::consume`] with the bytes actually
+	/// transferred.
+	pub(crate) fn allowance(
+		&mut self,
+		destination: &str,
+		want: usize,
+	) -> usize {
+		if want == 0 {
+			return 0;
+		}
+		if let Some(rate) = self.destination_bandwidth {
+			self.destination_buckets
+				.entry(destination.to_string())
+				.or_insert_with(|| TokenBucket::new(rate));
+		}
+
+		loop {
+			let now = Instant::now();
+			let mut allowed = u64::try_from(want).unwrap_or(u64::MAX);
+			let mut wait = Duration::ZERO;
+			for bucket in self
+				.bandwidth
+				.iter_mut()
+				.chain(self.destination_buckets.get_mut(destination))
+			{
+				bucket.refill(now);
+				allowed = allowed.min(bucket.available);
+				if bucket.available == 0 {
+					wait = wait.max(bucket.wait(now));
+				}
+			}
+
+			if allowed > 0 {
+				return usize::try_from(allowed).expect("at most `want`. qed.");
+			}
+			self.metrics.throttled(destination, wait);
+			thread::sleep(wait);
+		}
+	}
+
+	/// Record `bytes` transferred to or from `destination`.
+	pub(crate) fn consume(&mut self, destination: &str, bytes: usize) {
+		let tokens = u64::try_from(bytes).unwrap_or(u64::MAX);
+		for bucket in self
+			.bandwidth
+			.iter_mut()
+			.chain(self.destination_buckets.get_mut(destination))
+		{
+			bucket.take(tokens);
+		}
+	}
+
+	/// Forget the bandwidth used by `destination`, once it has no open
+	/// connections left.
+	pub(crate) fn forget(&mut self, destination: &str) {
+		self.destination_buckets.remove(destination);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn token_buckets_refill_at_their_rate() {
+		let mut bucket = TokenBucket::new(1_000);
+		let start = bucket.refilled_at;
+		bucket.take(1_000);
+		assert_eq!(bucket.wait(start), Duration::from_millis(1));
+
+		bucket.refill(start + Duration::from_micros(2_500));
+		assert_eq!(bucket.available, 2);
+		// The half token left over is kept for the next refill
+		bucket.refill(start + Duration::from_micros(3_000));
+		assert_eq!(bucket.available, 3);
+
+		// Bursts are capped at one second worth of tokens
+		bucket.refill(start + Duration::from_secs(10));
+		assert_eq!(bucket.available, 1_000);
+	}
+
+	#[test]
+	fn allowance_is_the_smallest_left_in_any_limit() {
+		let mut quotas = Quotas::default();
+		quotas.set_bandwidth(100);
+		quotas.set_destination_bandwidth(10);
+		let counters = Arc::new(ProxyCounters::default());
+		quotas.metrics = counters.clone();
+
+		assert_eq!(quotas.allowance("a.com:443", 0), 0);
+		assert_eq!(quotas.allowance("a.com:443", 50), 10);
+		quotas.consume("a.com:443", 10);
+		assert_eq!(quotas.allowance("b.com:443", 50), 10);
+		quotas.consume("b.com:443", 10);
+
+		// Waits for the destination limit to refill
+		let start = Instant::now();
+		assert!(quotas.allowance("a.com:443", 50) >= 1);
+		assert!(start.elapsed() >= Duration::from_millis(90));
+		assert_eq!(counters.snapshot()["a.com:443"].throttled, 1);
+		assert!(!counters.snapshot().contains_key("b.com:443"));
+	}
+
+	#[test]
+	fn connections_per_destination_are_limited() {
+		let mut quotas = Quotas::default();
+		assert_eq!(quotas.check_connect("a.com:443", 1_000), Ok(()));
+
+		quotas.set_max_connections_per_destination(2);
+		assert_eq!(quotas.check_connect("a.com:443", 1), Ok(()));
+		assert_eq!(
+			quotas.check_connect("a.com:443", 2),
+			Err(QosNetError::TooManyDestinationConnections(
+				"a.com:443".to_string()
+			))
+		);
+	}
+}
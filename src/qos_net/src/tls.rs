@@ -0,0 +1,306 @@
+//! TLS client for pivots, terminating TLS inside the enclave over a
+//! [`ProxyStream`].
+//!
+//! The proxy on the host only ever sees ciphertext, and since it also
+//! resolves hostnames, the server certificate is what authenticates the
+//! endpoint. Certificates are verified against the Mozilla root store of
+//! `webpki-roots` and, when the manifest pins keys for the endpoint (see
+//! [`EgressEndpoint::tls_pins`]), the verified chain must also contain one
+//! of them:
+//!
+//! ```no_run
+//! use std::io::{Read, Write};
+//!
+//! use qos_core::io::{SocketAddress, TimeVal, TimeValLike};
+//! use qos_net::egress::EgressPolicy;
+//!
+//! let policy = EgressPolicy::from_env().unwrap();
+//! let mut tls = policy
+//!     .connect_tls(
+//!         &SocketAddress::new_unix("./qos_net.sock"),
+//!         TimeVal::seconds(5),
+//!         "api.example.com".to_string(),
+//!         443,
+//!         vec!["8.8.8.8".to_string()],
+//!         53,
+//!     )
+//!     .unwrap();
+//! tls.write_all(b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n").unwrap();
+//! let mut response = vec![];
+//! tls.read_to_end(&mut response).unwrap();
+//! ```
+//!
+//! [`EgressEndpoint::tls_pins`]: qos_core::protocol::services::boot::EgressEndpoint::tls_pins
+
+use std::sync::Arc;
+
+use qos_core::io::{SocketAddress, TimeVal};
+use rustls::{
+	client::{
+		danger::{
+			HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+		},
+		WebPkiServerVerifier,
+	},
+	crypto::{aws_lc_rs, WebPkiSupportedAlgorithms},
+	pki_types::{CertificateDer, ServerName, UnixTime},
+	CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct,
+	RootCertStore, SignatureScheme, StreamOwned,
+};
+use webpki::{EndEntityCert, KeyUsage, VerifiedPath};
+
+use crate::{
+	egress::EgressPolicy, error::QosNetError, proxy_stream::ProxyStream,
+};
+
+/// A TLS connection to a remote endpoint, through the proxy.
+pub type TlsStream = StreamOwned<ClientConnection, ProxyStream>;
+
+/// [`ServerCertVerifier`] doing the usual WebPKI checks and, if any keys are
+/// pinned, requiring one of them on the verified chain.
+#[derive(Debug)]
+pub struct PinnedServerVerifier {
+	inner: Arc<WebPkiServerVerifier>,
+	roots: Arc<RootCertStore>,
+	algorithms: WebPkiSupportedAlgorithms,
+	pins: Vec<[u8; 32]>,
+}
+
+impl PinnedServerVerifier {
+	/// Create a new instance of [`Self`] trusting `roots`. `pins` are SHA-256
+	/// hashes of DER encoded `SubjectPublicKeyInfo`s, see [`spki_sha256`];
+	/// when empty, no key is required.
+	pub fn new(
+		roots: RootCertStore,
+		pins: Vec<[u8; 32]>,
+	) -> Result<Self, QosNetError> {
+		let provider = Arc::new(aws_lc_rs::default_provider());
+		let roots = Arc::new(roots);
+		let inner = WebPkiServerVerifier::builder_with_provider(
+			roots.clone(),
+			provider.clone(),
+		)
+		.build()
+		.map_err(|e| QosNetError::TlsError(format!("{e:?}")))?;
+
+		Ok(Self {
+			inner,
+			roots,
+			algorithms: provider.signature_verification_algorithms,
+			pins,
+		})
+	}
+
+	fn path_is_pinned(&self, path: &VerifiedPath<'_>) -> bool {
+		let anchor = der_sequence(&path.anchor().subject_public_key_info);
+		std::iter::once(path.end_entity().subject_public_key_info().to_vec())
+			.chain(
+				path.intermediate_certificates()
+					.map(|cert| cert.subject_public_key_info().to_vec()),
+			)
+			.chain(std::iter::once(anchor))
+			.any(|spki| self.pins.contains(&qos_crypto::sha_256(&spki)))
+	}
+}
+
+impl ServerCertVerifier for PinnedServerVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &CertificateDer<'_>,
+		intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>,
+		ocsp_response: &[u8],
+		now: UnixTime,
+	) -> Result<ServerCertVerified, rustls::Error> {
+		let verified = self.inner.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			ocsp_response,
+			now,
+		)?;
+		if self.pins.is_empty() {
+			return Ok(verified);
+		}
+
+		// Build the chain again, only accepting paths through a pinned key,
+		// since the server may offer several valid chains.
+		let not_pinned = rustls::Error::InvalidCertificate(
+			CertificateError::ApplicationVerificationFailure,
+		);
+		let cert = EndEntityCert::try_from(end_entity)
+			.map_err(|_| not_pinned.clone())?;
+		let check_pins = |path: &VerifiedPath<'_>| {
+			if self.path_is_pinned(path) {
+				Ok(())
+			} else {
+				Err(webpki::Error::UnknownIssuer)
+			}
+		};
+		cert.verify_for_usage(
+			self.algorithms.all,
+			&self.roots.roots,
+			intermediates,
+			now,
+			KeyUsage::server_auth(),
+			None,
+			Some(&check_pins),
+		)
+		.map_err(|_| not_pinned)?;
+
+		Ok(verified)
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.inner.supported_verify_schemes()
+	}
+}
+
+/// SHA-256 hash of the DER encoded `SubjectPublicKeyInfo` of `cert`, the
+/// value to pin in the manifest.
+pub fn spki_sha256(cert: &CertificateDer<'_>) -> Result<[u8; 32], QosNetError> {
+	let cert = EndEntityCert::try_from(cert)
+		.map_err(|e| QosNetError::TlsError(format!("{e:?}")))?;
+	Ok(qos_crypto::sha_256(&cert.subject_public_key_info()))
+}
+
+/// Client configuration trusting the `webpki-roots` root store and
+/// requiring one of `pins`, if any, see [`PinnedServerVerifier`].
+pub fn client_config(pins: Vec<[u8; 32]>) -> Result<ClientConfig, QosNetError> {
+	let roots =
+		RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+	let verifier = PinnedServerVerifier::new(roots, pins)?;
+
+	Ok(ClientConfig::builder_with_provider(Arc::new(
+		aws_lc_rs::default_provider(),
+	))
+	.with_safe_default_protocol_versions()
+	.map_err(|e| QosNetError::TlsError(format!("{e:?}")))?
+	.dangerous()
+	.with_custom_certificate_verifier(Arc::new(verifier))
+	.with_no_client_auth())
+}
+
+impl EgressPolicy {
+	/// Like [`Self::connect_by_name`], but also complete a TLS handshake
+	/// with the endpoint, requiring the keys the manifest pins for it.
+	pub fn connect_tls(
+		&self,
+		addr: &SocketAddress,
+		timeout: TimeVal,
+		hostname: String,
+		port: u16,
+		dns_resolvers: Vec<String>,
+		dns_port: u16,
+	) -> Result<TlsStream, QosNetError> {
+		let tls_error = |e: rustls::Error| QosNetError::TlsError(e.to_string());
+
+		let config = client_config(self.tls_pins(&hostname, port))?;
+		let server_name = ServerName::try_from(hostname.clone())
+			.map_err(|e| QosNetError::TlsError(e.to_string()))?;
+		let stream = self.connect_by_name(
+			addr,
+			timeout,
+			hostname,
+			port,
+			dns_resolvers,
+			dns_port,
+		)?;
+		let conn = ClientConnection::new(Arc::new(config), server_name)
+			.map_err(tls_error)?;
+
+		let mut tls = StreamOwned::new(conn, stream);
+		while tls.conn.is_handshaking() {
+			tls.conn
+				.complete_io(&mut tls.sock)
+				.map_err(|e| QosNetError::TlsError(e.to_string()))?;
+		}
+		Ok(tls)
+	}
+}
+
+/// Wrap the contents of a `SubjectPublicKeyInfo`, as stored in trust
+/// anchors, back into a DER `SEQUENCE`.
+fn der_sequence(contents: &[u8]) -> Vec<u8> {
+	let mut der = vec![0x30];
+	if contents.len() < 0x80 {
+		der.push(u8::try_from(contents.len()).expect("checked above. qed."));
+	} else {
+		let len = contents.len().to_be_bytes();
+		let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+		der.push(0x80 | u8::try_from(len.len()).expect("at most 8. qed."));
+		der.extend_from_slice(len);
+	}
+	der.extend_from_slice(contents);
+	der
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::*;
+
+	const CA: &[u8] = include_bytes!("./static/tls/ca.der");
+	const SERVER: &[u8] = include_bytes!("./static/tls/server.der");
+
+	fn verify(pins: Vec<[u8; 32]>, name: &str) -> Result<(), rustls::Error> {
+		let mut roots = RootCertStore::empty();
+		roots.add(CertificateDer::from(CA)).unwrap();
+		let verifier = PinnedServerVerifier::new(roots, pins).unwrap();
+
+		// 2030-01-01, within the validity of the fixtures
+		let now =
+			UnixTime::since_unix_epoch(Duration::from_secs(1_893_456_000));
+		verifier
+			.verify_server_cert(
+				&CertificateDer::from(SERVER),
+				&[],
+				&ServerName::try_from(name.to_string()).unwrap(),
+				&[],
+				now,
+			)
+			.map(|_| ())
+	}
+
+	#[test]
+	fn pinned_keys_must_be_on_the_verified_chain() {
+		let server_pin = spki_sha256(&CertificateDer::from(SERVER)).unwrap();
+		let ca_pin = spki_sha256(&CertificateDer::from(CA)).unwrap();
+
+		assert_eq!(verify(vec![], "localhost"), Ok(()));
+		assert_eq!(verify(vec![server_pin], "localhost"), Ok(()));
+		assert_eq!(verify(vec![[0; 32], ca_pin], "localhost"), Ok(()));
+		assert_eq!(
+			verify(vec![[0; 32]], "localhost"),
+			Err(rustls::Error::InvalidCertificate(
+				CertificateError::ApplicationVerificationFailure
+			))
+		);
+		// Pins do not replace the usual checks
+		assert!(matches!(
+			verify(vec![server_pin], "example.com"),
+			Err(rustls::Error::InvalidCertificate(
+				CertificateError::NotValidForName
+			))
+		));
+	}
+}
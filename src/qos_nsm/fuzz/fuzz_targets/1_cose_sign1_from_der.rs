@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qos_nsm::nitro::{cose_sign1_from_der, unsafe_attestation_doc_from_der};
+
+// let the fuzzer come up with arbitrary COSE Sign1 structures, parsed without
+// checking their signature
+fuzz_target!(|data: &[u8]| {
+	if cose_sign1_from_der(data).is_ok() {
+		let _ = unsafe_attestation_doc_from_der(data);
+	}
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qos_nsm::{
+	mock::MOCK_SECONDS_SINCE_EPOCH,
+	nitro::{attestation_doc_from_der, aws_root_cert},
+};
+
+// let the fuzzer come up with arbitrary attestation documents, validated like
+// the enclave and the client do. Seeding the corpus with
+// `qos_nsm::mock::MOCK_NSM_ATTESTATION_DOCUMENT` gets it past the parsing and
+// into the certificate chain and signature checks.
+fuzz_target!(|data: &[u8]| {
+	let root_cert = aws_root_cert().expect("the root cert is valid");
+	let _ =
+		attestation_doc_from_der(data, &root_cert, MOCK_SECONDS_SINCE_EPOCH);
+});
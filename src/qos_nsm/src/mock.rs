@@ -1,11 +1,14 @@
 //! Mocks for external attest endpoints. Only for testing.
 
-use std::collections::BTreeSet;
+use std::{
+	collections::{BTreeMap, BTreeSet, VecDeque},
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
 
 use crate::{
 	nitro,
 	nsm::NsmProvider,
-	types::{NsmDigest, NsmRequest, NsmResponse},
+	types::{NsmDigest, NsmErrorCode, NsmRequest, NsmResponse},
 };
 
 /// DO NOT USE IN PRODUCTION - ONLY FOR TESTS.
@@ -92,3 +95,202 @@ impl NsmProvider for MockNsm {
 		}
 	}
 }
+
+/// A step of a [`ScriptedNsm`] script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NsmStep {
+	/// Answer the next request with this response, whatever the request is.
+	Respond(NsmResponse),
+	/// Answer `DescribePCR` requests from these PCRs, by index, from this
+	/// point in the script on. Does not answer a request itself.
+	SetPcrs(BTreeMap<u16, Vec<u8>>),
+}
+
+/// Mock Nitro Secure Module endpoint that answers from an ordered script of
+/// [`NsmStep`]s, to test how callers handle errors, failed attestations and
+/// PCRs that change. Requests made after the script runs out are answered
+/// like [`MockNsm`] does.
+///
+/// Clones share the same script, so a test can keep a clone to extend the
+/// script and inspect the requests after handing the NSM to the code under
+/// test.
+///
+/// ```
+/// use qos_nsm::{
+///     mock::ScriptedNsm,
+///     types::{NsmErrorCode, NsmRequest, NsmResponse},
+///     NsmProvider,
+/// };
+///
+/// let nsm = ScriptedNsm::new()
+///     .then_fail(NsmErrorCode::InternalError)
+///     .then_respond(NsmResponse::GetRandom { random: vec![1] });
+///
+/// assert_eq!(
+///     nsm.nsm_process_request(NsmRequest::GetRandom),
+///     NsmResponse::Error(NsmErrorCode::InternalError)
+/// );
+/// assert_eq!(
+///     nsm.nsm_process_request(NsmRequest::GetRandom),
+///     NsmResponse::GetRandom { random: vec![1] }
+/// );
+/// assert_eq!(nsm.requests().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedNsm {
+	inner: Arc<Mutex<Script>>,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+	steps: VecDeque<NsmStep>,
+	pcrs: BTreeMap<u16, Vec<u8>>,
+	requests: Vec<NsmRequest>,
+}
+
+impl ScriptedNsm {
+	/// Create a new instance of [`Self`] with an empty script.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add `step` to the end of the script.
+	#[must_use]
+	pub fn then(self, step: NsmStep) -> Self {
+		self.push(step);
+		self
+	}
+
+	/// Answer the next unscripted request with `response`.
+	#[must_use]
+	pub fn then_respond(self, response: NsmResponse) -> Self {
+		self.then(NsmStep::Respond(response))
+	}
+
+	/// Answer the next unscripted request with the error `code`.
+	#[must_use]
+	pub fn then_fail(self, code: NsmErrorCode) -> Self {
+		self.then_respond(NsmResponse::Error(code))
+	}
+
+	/// Switch to `pcrs` once the script gets here.
+	#[must_use]
+	pub fn then_pcrs(
+		self,
+		pcrs: impl IntoIterator<Item = (u16, Vec<u8>)>,
+	) -> Self {
+		self.then(NsmStep::SetPcrs(pcrs.into_iter().collect()))
+	}
+
+	/// Add `step` to the end of the script, shared by all clones.
+	pub fn push(&self, step: NsmStep) {
+		self.lock().steps.push_back(step);
+	}
+
+	/// The requests made so far, oldest first.
+	#[must_use]
+	pub fn requests(&self) -> Vec<NsmRequest> {
+		self.lock().requests.clone()
+	}
+
+	/// Number of steps of the script not reached yet.
+	#[must_use]
+	pub fn remaining(&self) -> usize {
+		self.lock().steps.len()
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Script> {
+		self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl NsmProvider for ScriptedNsm {
+	fn nsm_process_request(&self, request: NsmRequest) -> NsmResponse {
+		let mut script = self.lock();
+		script.requests.push(request.clone());
+
+		while let Some(step) = script.steps.pop_front() {
+			match step {
+				NsmStep::Respond(response) => return response,
+				NsmStep::SetPcrs(pcrs) => script.pcrs = pcrs,
+			}
+		}
+
+		match request {
+			NsmRequest::DescribePCR { index } => {
+				script.pcrs.get(&index).map_or_else(
+					|| MockNsm.nsm_process_request(request),
+					|data| NsmResponse::DescribePCR {
+						lock: false,
+						data: data.clone(),
+					},
+				)
+			}
+			request => MockNsm.nsm_process_request(request),
+		}
+	}
+
+	fn timestamp_ms(&self) -> Result<u64, nitro::AttestError> {
+		let request = NsmRequest::Attestation {
+			user_data: None,
+			nonce: None,
+			public_key: None,
+		};
+		if self.remaining() == 0 {
+			self.lock().requests.push(request);
+			return MockNsm.timestamp_ms();
+		}
+
+		// Read the time from the next scripted response, like the NSM does
+		match self.nsm_process_request(request) {
+			NsmResponse::Attestation { document } => {
+				Ok(nitro::unsafe_attestation_doc_from_der(&document)?.timestamp)
+			}
+			response => {
+				Err(nitro::AttestError::UnexpectedNsmResponse(response))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn scripted_nsm_follows_its_script_then_acts_like_the_mock() {
+		let nsm = ScriptedNsm::new()
+			.then_pcrs([(0, vec![1; 48])])
+			.then_fail(NsmErrorCode::InternalError)
+			.then_respond(NsmResponse::Attestation { document: vec![] })
+			.then_pcrs([(0, vec![2; 48])]);
+		let describe_pcr0 =
+			|| nsm.nsm_process_request(NsmRequest::DescribePCR { index: 0 });
+
+		assert_eq!(
+			describe_pcr0(),
+			NsmResponse::Error(NsmErrorCode::InternalError)
+		);
+		assert!(matches!(
+			nsm.timestamp_ms(),
+			Err(nitro::AttestError::InvalidCOSESign1Structure)
+		));
+		assert_eq!(nsm.remaining(), 1);
+		assert_eq!(
+			describe_pcr0(),
+			NsmResponse::DescribePCR { lock: false, data: vec![2; 48] }
+		);
+		assert_eq!(nsm.remaining(), 0);
+
+		// Steps pushed to a clone are shared
+		nsm.clone().push(NsmStep::SetPcrs(BTreeMap::new()));
+		assert_eq!(
+			describe_pcr0(),
+			MockNsm.nsm_process_request(NsmRequest::DescribePCR { index: 0 })
+		);
+		assert_eq!(nsm.timestamp_ms().unwrap(), MOCK_ATTESTATION_DOC_TIMESTAMP);
+		assert_eq!(nsm.requests().len(), 5);
+		assert_eq!(nsm.requests()[0], NsmRequest::DescribePCR { index: 0 });
+	}
+}
@@ -68,6 +68,9 @@ pub enum AttestError {
 	MissingPcr3,
 	/// The attestation doc has a different pcr3.
 	DifferentPcr3,
+	/// The root certificate PEM does not hash to the published AWS root
+	/// certificate checksum. Holds the hex encoded SHA256 of the PEM.
+	UnexpectedRootCert(String),
 }
 
 impl From<webpki::Error> for AttestError {
@@ -11,6 +11,7 @@ use p384::{
 	ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey},
 	PublicKey,
 };
+use qos_crypto::ct_eq;
 use serde_bytes::ByteBuf;
 
 mod error;
@@ -36,11 +37,35 @@ static AWS_NITRO_CERT_SIG_ALG: &[&webpki::SignatureAlgorithm] =
 /// For context and additional verification details, see
 /// <https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html/>.
 ///
-/// The `aws_root_cert.pem` contents hash as follows via SHA256:
-/// `6eb9688305e4bbca67f44b59c29a0661ae930f09b5945b5d1d9ae01125c8d6c0`.
+/// The `aws_root_cert.pem` contents hash to [`AWS_ROOT_CERT_PEM_SHA256`]. Use
+/// [`aws_root_cert`] to get the certificate with that checked.
 pub const AWS_ROOT_CERT_PEM: &[u8] =
 	std::include_bytes!("./static/aws_root_cert.pem");
 
+/// Hex encoded SHA256 of the contents of the PEM in the official AWS zip file
+/// (see [`AWS_ROOT_CERT_PEM`]).
+pub const AWS_ROOT_CERT_PEM_SHA256: &str =
+	"6eb9688305e4bbca67f44b59c29a0661ae930f09b5945b5d1d9ae01125c8d6c0";
+
+/// The DER encoded AWS Nitro root CA certificate, after checking that
+/// [`AWS_ROOT_CERT_PEM`] has not been substituted.
+pub fn aws_root_cert() -> Result<Vec<u8>, AttestError> {
+	aws_root_cert_from_pem(AWS_ROOT_CERT_PEM)
+}
+
+/// Extract a DER encoded certificate from `pem`, which must hash to
+/// [`AWS_ROOT_CERT_PEM_SHA256`].
+pub fn aws_root_cert_from_pem(pem: &[u8]) -> Result<Vec<u8>, AttestError> {
+	use sha2::Digest as _;
+
+	let digest = qos_hex::encode(&sha2::Sha256::digest(pem));
+	if digest != AWS_ROOT_CERT_PEM_SHA256 {
+		return Err(AttestError::UnexpectedRootCert(digest));
+	}
+
+	cert_from_pem(pem)
+}
+
 /// Extract a DER encoded certificate from bytes representing a PEM encoded
 /// certificate.
 pub fn cert_from_pem(pem: &[u8]) -> Result<Vec<u8>, AttestError> {
@@ -74,13 +99,13 @@ pub fn verify_attestation_doc_against_user_input(
 	pcr2: &[u8],
 	pcr3: &[u8],
 ) -> Result<(), AttestError> {
-	if user_data
-		!= attestation_doc
+	if !ct_eq(
+		user_data,
+		attestation_doc
 			.user_data
 			.as_ref()
-			.ok_or(AttestError::MissingUserData)?
-			.to_vec()
-	{
+			.ok_or(AttestError::MissingUserData)?,
+	) {
 		return Err(AttestError::DifferentUserData);
 	}
 
@@ -89,56 +114,49 @@ pub fn verify_attestation_doc_against_user_input(
 		return Err(AttestError::UnexpectedAttestationDocNonce);
 	}
 
-	if pcr0
-		!= attestation_doc
-			.pcrs
-			.get(&0)
-			.ok_or(AttestError::MissingPcr0)?
-			.clone()
-			.into_vec()
-	{
+	if !ct_eq(
+		pcr0,
+		attestation_doc.pcrs.get(&0).ok_or(AttestError::MissingPcr0)?,
+	) {
 		return Err(AttestError::DifferentPcr0);
 	}
 
 	// pcr1 matches
-	if pcr1
-		!= attestation_doc
-			.pcrs
-			.get(&1)
-			.ok_or(AttestError::MissingPcr1)?
-			.clone()
-			.into_vec()
-	{
+	if !ct_eq(
+		pcr1,
+		attestation_doc.pcrs.get(&1).ok_or(AttestError::MissingPcr1)?,
+	) {
 		return Err(AttestError::DifferentPcr1);
 	}
 
 	// pcr2 matches
-	if pcr2
-		!= attestation_doc
-			.pcrs
-			.get(&2)
-			.ok_or(AttestError::MissingPcr2)?
-			.clone()
-			.into_vec()
-	{
+	if !ct_eq(
+		pcr2,
+		attestation_doc.pcrs.get(&2).ok_or(AttestError::MissingPcr2)?,
+	) {
 		return Err(AttestError::DifferentPcr2);
 	}
 
 	// pcr3 matches
-	if pcr3
-		!= attestation_doc
-			.pcrs
-			.get(&3)
-			.ok_or(AttestError::MissingPcr3)?
-			.clone()
-			.into_vec()
-	{
+	if !ct_eq(
+		pcr3,
+		attestation_doc.pcrs.get(&3).ok_or(AttestError::MissingPcr3)?,
+	) {
 		return Err(AttestError::DifferentPcr3);
 	}
 
 	Ok(())
 }
 
+/// Parse the DER encoded COSE Sign1 structure an attestation document is
+/// delivered in, without checking its signature.
+pub fn cose_sign1_from_der(
+	cose_sign1_der: &[u8],
+) -> Result<CoseSign1, AttestError> {
+	CoseSign1::from_bytes(cose_sign1_der)
+		.map_err(|_| AttestError::InvalidCOSESign1Structure)
+}
+
 /// Extract the DER encoded `AttestationDoc` from the nitro secure module
 /// (nsm) provided COSE Sign1 structure.
 ///
@@ -153,9 +171,12 @@ pub fn verify_attestation_doc_against_user_input(
 pub fn unsafe_attestation_doc_from_der(
 	cose_sign1_der: &[u8],
 ) -> Result<AttestationDoc, AttestError> {
-	let cose_sign1 = CoseSign1::from_bytes(cose_sign1_der)
-		.map_err(|_| AttestError::InvalidCOSESign1Structure)?;
+	attestation_doc_from_cose_sign1(&cose_sign1_from_der(cose_sign1_der)?)
+}
 
+fn attestation_doc_from_cose_sign1(
+	cose_sign1: &CoseSign1,
+) -> Result<AttestationDoc, AttestError> {
 	let raw_attestation_doc = cose_sign1
 		.get_payload::<Sha2>(None)
 		.map_err(|_| AttestError::InvalidCOSESign1Structure)?;
@@ -185,9 +206,8 @@ pub fn attestation_doc_from_der(
 	root_cert: &[u8],
 	validation_time: u64, // seconds since unix epoch
 ) -> Result<AttestationDoc, AttestError> {
-	let attestation_doc = unsafe_attestation_doc_from_der(cose_sign1_der)?;
-	let cose_sign1 = CoseSign1::from_bytes(cose_sign1_der)
-		.map_err(|_| AttestError::InvalidCOSESign1Structure)?;
+	let cose_sign1 = cose_sign1_from_der(cose_sign1_der)?;
+	let attestation_doc = attestation_doc_from_cose_sign1(&cose_sign1)?;
 
 	syntactic_validation::module_id(&attestation_doc.module_id)?;
 	syntactic_validation::digest(attestation_doc.digest)?;
@@ -371,6 +391,23 @@ mod test {
 		(P384PrivateKey(private), P384PubKey(public))
 	}
 
+	#[test]
+	fn aws_root_cert_is_checked_against_the_published_checksum() {
+		assert_eq!(
+			aws_root_cert().unwrap(),
+			cert_from_pem(AWS_ROOT_CERT_PEM).unwrap()
+		);
+
+		// Still a valid PEM, but not the one AWS published
+		let mut substituted = AWS_ROOT_CERT_PEM.to_vec();
+		substituted.push(b'\n');
+		assert!(cert_from_pem(&substituted).is_ok());
+		assert!(matches!(
+			aws_root_cert_from_pem(&substituted),
+			Err(AttestError::UnexpectedRootCert(_))
+		));
+	}
+
 	#[test]
 	fn cose_sign1_ec384_validate() {
 		let (_, ec_public) = generate_p384();
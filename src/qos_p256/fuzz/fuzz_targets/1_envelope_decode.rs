@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qos_p256::encrypt::{Envelope, P256EncryptPair};
+
+// let the fuzzer come up with arbitrary encrypted envelopes, such as the
+// shares posted to an enclave
+fuzz_target!(|data: &[u8]| {
+	let Ok(envelope) = Envelope::from_bytes(data) else {
+		return;
+	};
+
+	// anything that decodes must encode with the current format version and
+	// decode again
+	let encoded = envelope.to_bytes().expect("decoded envelopes encode");
+	Envelope::from_bytes(&encoded).expect("encoded envelopes decode");
+
+	// a fresh key did not encrypt the envelope, so decrypting must fail
+	assert!(P256EncryptPair::generate().decrypt(data).is_err());
+});
@@ -1,7 +1,12 @@
 //! Abstractions for encryption.
 
+use std::io::{Read, Write};
+
 use aes_gcm::{
-	aead::{Aead, KeyInit, Payload},
+	aead::{
+		stream::{DecryptorBE32, EncryptorBE32},
+		Aead, KeyInit, Payload,
+	},
 	Aes256Gcm, Nonce,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -12,28 +17,159 @@ use p256::{
 };
 use rand_core::OsRng;
 use sha2::Sha512;
-use zeroize::ZeroizeOnDrop;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::{bytes_os_rng, P256Error, PUB_KEY_LEN_UNCOMPRESSED};
 
-const AES256_KEY_LEN: usize = 32;
+pub(crate) const AES256_KEY_LEN: usize = 32;
 const BITS_96_AS_BYTES: u8 = 12;
 const AES_GCM_256_HMAC_SHA512_TAG: &[u8] = b"qos_aes_gcm_256_hmac_sha512";
 const QOS_ENCRYPTION_HMAC_MESSAGE: &[u8] = b"qos_encryption_hmac_message";
+/// Length of the plaintext of each chunk of a stream made with
+/// [`P256EncryptPublic::encrypt_stream`].
+pub const STREAM_CHUNK_LEN: usize = 64 * 1024;
+const AES_GCM_TAG_LEN: usize = 16;
+/// The STREAM construction uses the last 5 bytes of the nonce for a counter
+/// and a last chunk flag.
+const STREAM_NONCE_PREFIX_LEN: usize = 7;
+
+/// Magic bytes at the start of a serialized [`Envelope`].
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"QOSE";
+/// Format version of the envelopes made by [`P256EncryptPublic::encrypt`].
+pub const ENVELOPE_VERSION: u8 = 1;
 
 type HmacSha512 = Hmac<Sha512>;
 
+/// Algorithm a message in an [`Envelope`] is encrypted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeAlgorithm {
+	/// ECDH with an ephemeral sender key, HMAC-SHA512 to derive the shared
+	/// key, and AES 256 GCM.
+	EcdhHmacSha512AesGcm256,
+}
+
+impl EnvelopeAlgorithm {
+	/// Identifier of the algorithm in a serialized [`Envelope`].
+	#[must_use]
+	pub fn id(self) -> u8 {
+		match self {
+			Self::EcdhHmacSha512AesGcm256 => 1,
+		}
+	}
+
+	/// The algorithm with the given identifier.
+	pub fn from_id(id: u8) -> Result<Self, P256Error> {
+		match id {
+			1 => Ok(Self::EcdhHmacSha512AesGcm256),
+			id => Err(P256Error::UnsupportedEnvelopeAlgorithm(id)),
+		}
+	}
+}
+
 /// Envelope for serializing an encrypted message with it's context.
-#[derive(BorshDeserialize, BorshSerialize, Debug)]
+///
+/// Serialized, it is [`ENVELOPE_MAGIC`], the format version and the
+/// algorithm identifier, followed by the borsh encoded nonce, sender public
+/// key and encrypted message. Envelopes made before the format was versioned
+/// are only the borsh encoded fields, and are still read as
+/// [`EnvelopeAlgorithm::EcdhHmacSha512AesGcm256`].
+#[derive(Debug)]
 pub struct Envelope {
+	/// Algorithm the message is encrypted with.
+	pub algorithm: EnvelopeAlgorithm,
 	/// Nonce used as an input to the cipher.
 	nonce: [u8; BITS_96_AS_BYTES as usize],
 	/// Public key as sec1 encoded point with no compression
 	pub ephemeral_sender_public: [u8; PUB_KEY_LEN_UNCOMPRESSED as usize],
-	/// The data encrypted with an AES 256 GCM cipher.
+	/// The data encrypted with the cipher of the algorithm.
+	encrypted_message: Vec<u8>,
+}
+
+/// The fields of an [`Envelope`] after its header, which is all there is to
+/// an envelope made before the format was versioned.
+#[derive(BorshDeserialize)]
+struct EnvelopeBody {
+	nonce: [u8; BITS_96_AS_BYTES as usize],
+	ephemeral_sender_public: [u8; PUB_KEY_LEN_UNCOMPRESSED as usize],
 	encrypted_message: Vec<u8>,
 }
 
+impl Envelope {
+	/// Serialize with the current format version.
+	pub fn to_bytes(&self) -> Result<Vec<u8>, P256Error> {
+		let mut bytes = ENVELOPE_MAGIC.to_vec();
+		bytes.extend([ENVELOPE_VERSION, self.algorithm.id()]);
+		// Same encoding as `EnvelopeBody`
+		borsh::to_writer(
+			&mut bytes,
+			&(
+				&self.nonce,
+				&self.ephemeral_sender_public,
+				&self.encrypted_message,
+			),
+		)
+		.map_err(|_| P256Error::FailedToSerializeEnvelope)?;
+
+		Ok(bytes)
+	}
+
+	/// Deserialize an envelope of any supported format version, including
+	/// envelopes made before the format was versioned.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, P256Error> {
+		let Some(rest) = bytes.strip_prefix(ENVELOPE_MAGIC.as_slice()) else {
+			return Self::from_body(
+				EnvelopeAlgorithm::EcdhHmacSha512AesGcm256,
+				bytes,
+			);
+		};
+
+		let versioned = match rest {
+			[ENVELOPE_VERSION, algorithm, body @ ..] => {
+				EnvelopeAlgorithm::from_id(*algorithm)
+					.and_then(|algorithm| Self::from_body(algorithm, body))
+			}
+			[version, ..] => {
+				Err(P256Error::UnsupportedEnvelopeVersion(*version))
+			}
+			[] => Err(P256Error::FailedToDeserializeEnvelope),
+		};
+		// The random nonce of an unversioned envelope can start with the
+		// magic bytes, so fall back to reading it as one.
+		versioned.or_else(|e| {
+			Self::from_body(EnvelopeAlgorithm::EcdhHmacSha512AesGcm256, bytes)
+				.map_err(|_| e)
+		})
+	}
+
+	fn from_body(
+		algorithm: EnvelopeAlgorithm,
+		body: &[u8],
+	) -> Result<Self, P256Error> {
+		let EnvelopeBody { nonce, ephemeral_sender_public, encrypted_message } =
+			EnvelopeBody::try_from_slice(body)
+				.map_err(|_| P256Error::FailedToDeserializeEnvelope)?;
+
+		Ok(Self {
+			algorithm,
+			nonce,
+			ephemeral_sender_public,
+			encrypted_message,
+		})
+	}
+}
+
+/// Header of a stream made with [`P256EncryptPublic::encrypt_stream`]. It is
+/// followed by the chunks of the stream, each the AES 256 GCM ciphertext of
+/// [`STREAM_CHUNK_LEN`] bytes of plaintext, except for the last one which may
+/// be shorter.
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+struct StreamHeader {
+	/// Public key as sec1 encoded point with no compression
+	ephemeral_sender_public: [u8; PUB_KEY_LEN_UNCOMPRESSED as usize],
+	/// Prefix of the nonce of every chunk.
+	nonce_prefix: [u8; STREAM_NONCE_PREFIX_LEN],
+}
+
 /// P256 key pair.
 #[derive(ZeroizeOnDrop)]
 #[cfg_attr(any(feature = "mock", test), derive(Clone, PartialEq, Eq))]
@@ -54,18 +190,73 @@ impl P256EncryptPair {
 		serialized_envelope: &[u8],
 	) -> Result<Vec<u8>, P256Error> {
 		let Envelope {
+			algorithm: EnvelopeAlgorithm::EcdhHmacSha512AesGcm256,
 			nonce,
 			ephemeral_sender_public: ephemeral_sender_public_bytes,
 			encrypted_message,
-		} = Envelope::try_from_slice(serialized_envelope)
-			.map_err(|_| P256Error::FailedToDeserializeEnvelope)?;
+		} = Envelope::from_bytes(serialized_envelope)?;
 
 		let nonce = Nonce::from_slice(&nonce);
+		let (cipher, aad) =
+			self.receiver_cipher(&ephemeral_sender_public_bytes)?;
+		let payload = Payload { aad: &aad, msg: &encrypted_message };
+
+		cipher
+			.decrypt(nonce, payload)
+			.map_err(|_| P256Error::AesGcm256DecryptError)
+	}
+
+	/// Decrypt a stream made with [`P256EncryptPublic::encrypt_stream`] from
+	/// `reader`, writing the plaintext to `writer` one chunk at a time.
+	///
+	/// Plaintext of the chunks before a bad one is already written when an
+	/// error is returned, so it must be discarded on error.
+	pub fn decrypt_stream<R: Read, W: Write>(
+		&self,
+		mut reader: R,
+		mut writer: W,
+	) -> Result<(), P256Error> {
+		let StreamHeader { ephemeral_sender_public, nonce_prefix } =
+			StreamHeader::deserialize_reader(&mut reader)
+				.map_err(|_| P256Error::FailedToDeserializeEnvelope)?;
+		let (cipher, aad) = self.receiver_cipher(&ephemeral_sender_public)?;
+		let mut decryptor =
+			DecryptorBE32::from_aead(cipher, nonce_prefix.as_slice().into());
+
+		let chunk_len = STREAM_CHUNK_LEN + AES_GCM_TAG_LEN;
+		let mut chunk = read_chunk(&mut reader, chunk_len)?;
+		loop {
+			let next = read_chunk(&mut reader, chunk_len)?;
+			let payload = Payload { aad: &aad, msg: &chunk };
+			if next.is_empty() {
+				let plaintext = decryptor
+					.decrypt_last(payload)
+					.map_err(|_| P256Error::AesGcm256DecryptError)?;
+				write_chunk(&mut writer, &plaintext)?;
+				break;
+			}
+
+			let plaintext = decryptor
+				.decrypt_next(payload)
+				.map_err(|_| P256Error::AesGcm256DecryptError)?;
+			write_chunk(&mut writer, &plaintext)?;
+			chunk = next;
+		}
+
+		writer.flush().map_err(|e| P256Error::IOError(e.to_string()))
+	}
+
+	/// The cipher and AAD of a message sent to this pair by the given
+	/// ephemeral sender.
+	fn receiver_cipher(
+		&self,
+		ephemeral_sender_public_bytes: &[u8],
+	) -> Result<(Aes256Gcm, Vec<u8>), P256Error> {
 		let ephemeral_sender_public =
-			PublicKey::from_sec1_bytes(&ephemeral_sender_public_bytes)
+			PublicKey::from_sec1_bytes(ephemeral_sender_public_bytes)
 				.map_err(|_| P256Error::FailedToDeserializePublicKey)?;
 
-		let sender_public_typed = SenderPublic(&ephemeral_sender_public_bytes);
+		let sender_public_typed = SenderPublic(ephemeral_sender_public_bytes);
 		let receiver_encoded_point =
 			self.private.public_key().to_encoded_point(false);
 		let receiver_public_typed =
@@ -84,11 +275,8 @@ impl P256EncryptPair {
 			&sender_public_typed,
 			&receiver_public_typed,
 		)?;
-		let payload = Payload { aad: &aad, msg: &encrypted_message };
 
-		cipher
-			.decrypt(nonce, payload)
-			.map_err(|_| P256Error::AesGcm256DecryptError)
+		Ok((cipher, aad))
 	}
 
 	/// Get the public key.
@@ -121,6 +309,81 @@ pub struct P256EncryptPublic {
 impl P256EncryptPublic {
 	/// Encrypt a message to this public key.
 	pub fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>, P256Error> {
+		let (cipher, ephemeral_sender_public, aad) = self.sender_cipher()?;
+
+		let nonce = {
+			let random_bytes =
+				crate::bytes_os_rng::<{ BITS_96_AS_BYTES as usize }>();
+			*Nonce::from_slice(&random_bytes)
+		};
+		let payload = Payload { aad: &aad, msg: message };
+
+		let encrypted_message = cipher
+			.encrypt(&nonce, payload)
+			.map_err(|_| P256Error::AesGcm256EncryptError)?;
+
+		let envelope = Envelope {
+			algorithm: EnvelopeAlgorithm::EcdhHmacSha512AesGcm256,
+			nonce: nonce.into(),
+			ephemeral_sender_public,
+			encrypted_message,
+		};
+
+		envelope.to_bytes()
+	}
+
+	/// Encrypt everything read from `reader` to this public key, writing the
+	/// ciphertext to `writer` one chunk at a time, so large payloads don't
+	/// have to be held in memory. Decrypt it with
+	/// [`P256EncryptPair::decrypt_stream`].
+	///
+	/// Uses the STREAM construction, so reordered, dropped or truncated
+	/// chunks fail decryption.
+	pub fn encrypt_stream<R: Read, W: Write>(
+		&self,
+		mut reader: R,
+		mut writer: W,
+	) -> Result<(), P256Error> {
+		let (cipher, ephemeral_sender_public, aad) = self.sender_cipher()?;
+		let nonce_prefix = bytes_os_rng::<STREAM_NONCE_PREFIX_LEN>();
+		borsh::to_writer(
+			&mut writer,
+			&StreamHeader { ephemeral_sender_public, nonce_prefix },
+		)
+		.map_err(|_| P256Error::FailedToSerializeEnvelope)?;
+		let mut encryptor =
+			EncryptorBE32::from_aead(cipher, nonce_prefix.as_slice().into());
+
+		let mut chunk = read_chunk(&mut reader, STREAM_CHUNK_LEN)?;
+		loop {
+			let next = read_chunk(&mut reader, STREAM_CHUNK_LEN)?;
+			let payload = Payload { aad: &aad, msg: &chunk };
+			if next.is_empty() {
+				let ciphertext = encryptor
+					.encrypt_last(payload)
+					.map_err(|_| P256Error::AesGcm256EncryptError)?;
+				write_chunk(&mut writer, &ciphertext)?;
+				break;
+			}
+
+			let ciphertext = encryptor
+				.encrypt_next(payload)
+				.map_err(|_| P256Error::AesGcm256EncryptError)?;
+			write_chunk(&mut writer, &ciphertext)?;
+			chunk = next;
+		}
+
+		writer.flush().map_err(|e| P256Error::IOError(e.to_string()))
+	}
+
+	/// A cipher for a message to this public key from a new ephemeral sender
+	/// key, with the ephemeral sender public key and the AAD.
+	fn sender_cipher(
+		&self,
+	) -> Result<
+		(Aes256Gcm, [u8; PUB_KEY_LEN_UNCOMPRESSED as usize], Vec<u8>),
+		P256Error,
+	> {
 		let ephemeral_sender_private = SecretKey::random(&mut OsRng);
 		let ephemeral_sender_public: [u8; PUB_KEY_LEN_UNCOMPRESSED as usize] =
 			ephemeral_sender_private
@@ -146,28 +409,12 @@ impl P256EncryptPublic {
 			&receiver_public_typed,
 		)?;
 
-		let nonce = {
-			let random_bytes =
-				crate::bytes_os_rng::<{ BITS_96_AS_BYTES as usize }>();
-			*Nonce::from_slice(&random_bytes)
-		};
-
 		let aad = create_additional_associated_data(
 			&sender_public_typed,
 			&receiver_public_typed,
 		)?;
-		let payload = Payload { aad: &aad, msg: message };
-
-		let encrypted_message = cipher
-			.encrypt(&nonce, payload)
-			.map_err(|_| P256Error::AesGcm256EncryptError)?;
-
-		let nonce = nonce.into();
-		let envelope =
-			Envelope { nonce, ephemeral_sender_public, encrypted_message };
 
-		borsh::to_vec(&envelope)
-			.map_err(|_| P256Error::FailedToSerializeEnvelope)
+		Ok((cipher, ephemeral_sender_public, aad))
 	}
 
 	/// Decrypt a message encoded to this pair's public key.
@@ -181,11 +428,11 @@ impl P256EncryptPublic {
 		shared_secret: &[u8],
 	) -> Result<Vec<u8>, P256Error> {
 		let Envelope {
+			algorithm: EnvelopeAlgorithm::EcdhHmacSha512AesGcm256,
 			nonce,
 			ephemeral_sender_public: ephemeral_sender_public_bytes,
 			encrypted_message,
-		} = Envelope::try_from_slice(serialized_envelope)
-			.map_err(|_| P256Error::FailedToDeserializeEnvelope)?;
+		} = Envelope::from_bytes(serialized_envelope)?;
 
 		let nonce = Nonce::from_slice(&nonce);
 
@@ -218,7 +465,14 @@ impl P256EncryptPublic {
 		sec1_encoded_point.to_bytes()
 	}
 
-	/// Deserialize from a SEC1 encoded point, not compressed.
+	/// Serialize to SEC1 encoded point, compressed.
+	#[must_use]
+	pub fn to_compressed_bytes(&self) -> Box<[u8]> {
+		let sec1_encoded_point = self.public.to_encoded_point(true);
+		sec1_encoded_point.to_bytes()
+	}
+
+	/// Deserialize from a SEC1 encoded point, compressed or not.
 	pub fn from_bytes(bytes: &[u8]) -> Result<Self, P256Error> {
 		Ok(Self {
 			public: PublicKey::from_sec1_bytes(bytes)
@@ -252,7 +506,7 @@ fn create_cipher(
 	ephemeral_sender_public: &SenderPublic,
 	receiver_public: &ReceiverPublic,
 ) -> Result<Aes256Gcm, P256Error> {
-	let shared_secret = match shared_secret {
+	let shared_secret = Zeroizing::new(match shared_secret {
 		PrivPubOrSharedSecret::PrivPub { private, public } => {
 			diffie_hellman(private.to_nonzero_scalar(), public.as_affine())
 				.raw_secret_bytes()
@@ -261,26 +515,31 @@ fn create_cipher(
 		PrivPubOrSharedSecret::SharedSecret { shared_secret } => {
 			shared_secret.to_vec()
 		}
-	};
+	});
 
 	// To help with entropy and add domain context, we do
 	// `sender_public||receiver_public||shared_secret` as the pre-image for the
 	// shared key.
-	let pre_image: Vec<u8> = ephemeral_sender_public
-		.0
-		.iter()
-		.chain(receiver_public.0)
-		.chain(shared_secret.iter())
-		.copied()
-		.collect();
+	let pre_image: Zeroizing<Vec<u8>> = Zeroizing::new(
+		ephemeral_sender_public
+			.0
+			.iter()
+			.chain(receiver_public.0)
+			.chain(shared_secret.iter())
+			.copied()
+			.collect(),
+	);
 
 	let mut mac = <HmacSha512 as KeyInit>::new_from_slice(&pre_image[..])
 		.expect("hmac can take a key of any size");
 	mac.update(QOS_ENCRYPTION_HMAC_MESSAGE);
-	let shared_key = mac.finalize().into_bytes();
+	let mut shared_key = mac.finalize().into_bytes();
 
-	Aes256Gcm::new_from_slice(&shared_key[..AES256_KEY_LEN])
-		.map_err(|_| P256Error::FailedToCreateAes256GcmCipher)
+	let cipher = Aes256Gcm::new_from_slice(&shared_key[..AES256_KEY_LEN])
+		.map_err(|_| P256Error::FailedToCreateAes256GcmCipher);
+	shared_key.as_mut_slice().zeroize();
+
+	cipher
 }
 
 /// Helper function to create the additional associated data (AAD). The data is
@@ -318,6 +577,24 @@ fn create_additional_associated_data(
 	Ok(aad)
 }
 
+/// Read up to `len` bytes, less only at the end of `reader`.
+fn read_chunk(
+	reader: &mut impl Read,
+	len: usize,
+) -> Result<Vec<u8>, P256Error> {
+	let mut chunk = Vec::with_capacity(len);
+	reader
+		.take(len as u64)
+		.read_to_end(&mut chunk)
+		.map_err(|e| P256Error::IOError(e.to_string()))?;
+
+	Ok(chunk)
+}
+
+fn write_chunk(writer: &mut impl Write, chunk: &[u8]) -> Result<(), P256Error> {
+	writer.write_all(chunk).map_err(|e| P256Error::IOError(e.to_string()))
+}
+
 /// Envelope for holding an encrypted message and some metadata needed to
 /// perform decryption.
 #[derive(Debug, BorshSerialize, BorshDeserialize)]
@@ -410,6 +687,7 @@ impl AesGcm256Secret {
 #[cfg(test)]
 mod test_asymmetric {
 	use super::*;
+	use crate::PUB_KEY_LEN_COMPRESSED;
 
 	#[test]
 	fn basic_encrypt_decrypt_works() {
@@ -451,11 +729,10 @@ mod test_asymmetric {
 
 		let serialized_envelope = alice_public.encrypt(plaintext).unwrap();
 
-		let mut envelope =
-			Envelope::try_from_slice(&serialized_envelope).unwrap();
+		let mut envelope = Envelope::from_bytes(&serialized_envelope).unwrap();
 
 		envelope.encrypted_message.push(0);
-		let tampered_envelope = borsh::to_vec(&envelope).unwrap();
+		let tampered_envelope = envelope.to_bytes().unwrap();
 
 		assert_eq!(
 			alice_pair.decrypt(&tampered_envelope).unwrap_err(),
@@ -472,8 +749,7 @@ mod test_asymmetric {
 
 		let serialized_envelope = alice_public.encrypt(plaintext).unwrap();
 
-		let mut envelope =
-			Envelope::try_from_slice(&serialized_envelope).unwrap();
+		let mut envelope = Envelope::from_bytes(&serialized_envelope).unwrap();
 
 		// Alter the first byte of the nonce.
 		if envelope.nonce[0] == 0 {
@@ -481,7 +757,7 @@ mod test_asymmetric {
 		} else {
 			envelope.nonce[0] = 0;
 		};
-		let tampered_envelope = borsh::to_vec(&envelope).unwrap();
+		let tampered_envelope = envelope.to_bytes().unwrap();
 
 		assert_eq!(
 			alice_pair.decrypt(&tampered_envelope).unwrap_err(),
@@ -498,8 +774,7 @@ mod test_asymmetric {
 
 		let serialized_envelope = alice_public.encrypt(plaintext).unwrap();
 
-		let mut envelope =
-			Envelope::try_from_slice(&serialized_envelope).unwrap();
+		let mut envelope = Envelope::from_bytes(&serialized_envelope).unwrap();
 
 		// Alter the first byte of the sender's public key.
 		if envelope.ephemeral_sender_public[0] == 0 {
@@ -507,7 +782,7 @@ mod test_asymmetric {
 		} else {
 			envelope.ephemeral_sender_public[0] = 0;
 		};
-		let tampered_envelope = borsh::to_vec(&envelope).unwrap();
+		let tampered_envelope = envelope.to_bytes().unwrap();
 
 		assert_eq!(
 			alice_pair.decrypt(&tampered_envelope).unwrap_err(),
@@ -523,8 +798,9 @@ mod test_asymmetric {
 		let plaintext = b"rust test message";
 
 		let mut serialized_envelope = alice_public.encrypt(plaintext).unwrap();
-		// Given borsh encoding, this should be a byte in the nonce. We insert a
-		// byte and shift everything after, making the nonce too long.
+		// Given the header and borsh encoding, this should be a byte in the
+		// nonce. We insert a byte and shift everything after, making the
+		// nonce too long.
 		serialized_envelope.insert(BITS_96_AS_BYTES as usize, 0xff);
 
 		assert_eq!(
@@ -533,6 +809,50 @@ mod test_asymmetric {
 		);
 	}
 
+	#[test]
+	fn unversioned_envelope_decrypts() {
+		let alice_pair = P256EncryptPair::generate();
+		let plaintext = b"rust test message";
+
+		let serialized_envelope =
+			alice_pair.public_key().encrypt(plaintext).unwrap();
+		assert_eq!(serialized_envelope[..4], ENVELOPE_MAGIC);
+		assert_eq!(serialized_envelope[4], ENVELOPE_VERSION);
+
+		// As made before the format was versioned
+		let unversioned = serialized_envelope[6..].to_vec();
+		assert_eq!(alice_pair.decrypt(&unversioned).unwrap(), plaintext);
+
+		// Including when the random nonce starts with the magic bytes
+		let mut envelope = Envelope::from_bytes(&unversioned).unwrap();
+		envelope.nonce[..4].copy_from_slice(&ENVELOPE_MAGIC);
+		let unversioned_magic = envelope.to_bytes().unwrap()[6..].to_vec();
+		let decoded = Envelope::from_bytes(&unversioned_magic).unwrap();
+		assert_eq!(decoded.nonce, envelope.nonce);
+		assert_eq!(decoded.encrypted_message, envelope.encrypted_message);
+	}
+
+	#[test]
+	fn unsupported_envelope_version_and_algorithm_errors() {
+		let alice_pair = P256EncryptPair::generate();
+		let serialized_envelope =
+			alice_pair.public_key().encrypt(b"rust test message").unwrap();
+
+		let mut future_version = serialized_envelope.clone();
+		future_version[4] = ENVELOPE_VERSION + 1;
+		assert_eq!(
+			alice_pair.decrypt(&future_version).unwrap_err(),
+			P256Error::UnsupportedEnvelopeVersion(ENVELOPE_VERSION + 1)
+		);
+
+		let mut future_algorithm = serialized_envelope;
+		future_algorithm[5] = 0xff;
+		assert_eq!(
+			alice_pair.decrypt(&future_algorithm).unwrap_err(),
+			P256Error::UnsupportedEnvelopeAlgorithm(0xff)
+		);
+	}
+
 	#[test]
 	fn public_key_roundtrip_bytes() {
 		let alice_pair = P256EncryptPair::generate();
@@ -551,6 +871,24 @@ mod test_asymmetric {
 		assert_eq!(decrypted, plaintext);
 	}
 
+	#[test]
+	fn public_key_roundtrip_compressed_bytes() {
+		let alice_pair = P256EncryptPair::generate();
+		let alice_public = alice_pair.public_key();
+
+		let compressed = alice_public.to_compressed_bytes();
+		assert_eq!(compressed.len(), PUB_KEY_LEN_COMPRESSED as usize);
+		let alice_public2 = P256EncryptPublic::from_bytes(&compressed).unwrap();
+		assert_eq!(alice_public2.to_bytes(), alice_public.to_bytes());
+
+		let plaintext = b"rust test message";
+		let serialized_envelope = alice_public2.encrypt(plaintext).unwrap();
+		assert_eq!(
+			alice_pair.decrypt(&serialized_envelope).unwrap(),
+			plaintext
+		);
+	}
+
 	#[test]
 	fn private_key_roundtrip_bytes() {
 		let pair = P256EncryptPair::generate();
@@ -561,6 +899,137 @@ mod test_asymmetric {
 
 		assert_eq!(raw_secret1, raw_secret2);
 	}
+
+	/// Borsh encoding of an envelope with every field set. If this changes,
+	/// existing envelopes can no longer be decrypted.
+	const GOLDEN_ENVELOPE: &str = concat!(
+		"514f534501010101010101010101010101010202020202020202020202020202",
+		"0202020202020202020202020202020202020202020202020202020202020202",
+		"020202020202020202020202020202020202020400000003030303",
+	);
+
+	#[test]
+	fn envelope_encoding_matches_the_golden_vector() {
+		let envelope = Envelope {
+			algorithm: EnvelopeAlgorithm::EcdhHmacSha512AesGcm256,
+			nonce: [1; BITS_96_AS_BYTES as usize],
+			ephemeral_sender_public: [2; PUB_KEY_LEN_UNCOMPRESSED as usize],
+			encrypted_message: vec![3; 4],
+		};
+		assert_eq!(
+			qos_hex::encode(&envelope.to_bytes().unwrap()),
+			GOLDEN_ENVELOPE
+		);
+
+		let decoded =
+			Envelope::from_bytes(&qos_hex::decode(GOLDEN_ENVELOPE).unwrap())
+				.unwrap();
+		assert_eq!(decoded.algorithm, envelope.algorithm);
+		assert_eq!(decoded.nonce, envelope.nonce);
+		assert_eq!(
+			decoded.ephemeral_sender_public,
+			envelope.ephemeral_sender_public
+		);
+		assert_eq!(decoded.encrypted_message, envelope.encrypted_message);
+	}
+}
+
+#[cfg(test)]
+mod test_stream {
+	use super::*;
+
+	const HEADER_LEN: usize =
+		PUB_KEY_LEN_UNCOMPRESSED as usize + STREAM_NONCE_PREFIX_LEN;
+	const CHUNK_LEN: usize = STREAM_CHUNK_LEN + AES_GCM_TAG_LEN;
+
+	fn encrypt(public: &P256EncryptPublic, plaintext: &[u8]) -> Vec<u8> {
+		let mut ciphertext = vec![];
+		public.encrypt_stream(plaintext, &mut ciphertext).unwrap();
+		ciphertext
+	}
+
+	fn decrypt(
+		pair: &P256EncryptPair,
+		ciphertext: &[u8],
+	) -> Result<Vec<u8>, P256Error> {
+		let mut plaintext = vec![];
+		pair.decrypt_stream(ciphertext, &mut plaintext).map(|()| plaintext)
+	}
+
+	#[test]
+	fn encrypt_decrypt_stream_round_trip() {
+		let pair = P256EncryptPair::generate();
+		let public = pair.public_key();
+
+		for len in [
+			0,
+			1,
+			STREAM_CHUNK_LEN - 1,
+			STREAM_CHUNK_LEN,
+			STREAM_CHUNK_LEN + 1,
+			2 * STREAM_CHUNK_LEN + 5,
+		] {
+			let plaintext: Vec<u8> = (0..=u8::MAX).cycle().take(len).collect();
+			let ciphertext = encrypt(&public, &plaintext);
+			let chunks = len.div_ceil(STREAM_CHUNK_LEN).max(1);
+			assert_eq!(
+				ciphertext.len(),
+				HEADER_LEN + len + chunks * AES_GCM_TAG_LEN
+			);
+
+			assert_eq!(decrypt(&pair, &ciphertext).unwrap(), plaintext);
+		}
+	}
+
+	#[test]
+	fn wrong_receiver_cannot_decrypt_stream() {
+		let public = P256EncryptPair::generate().public_key();
+		let ciphertext = encrypt(&public, b"rust test message");
+
+		assert_eq!(
+			decrypt(&P256EncryptPair::generate(), &ciphertext),
+			Err(P256Error::AesGcm256DecryptError)
+		);
+	}
+
+	#[test]
+	fn modified_streams_fail_decryption() {
+		let pair = P256EncryptPair::generate();
+		let plaintext = vec![7; 3 * STREAM_CHUNK_LEN + 10];
+		let ciphertext = encrypt(&pair.public_key(), &plaintext);
+		let chunk = |i: usize| {
+			let start = HEADER_LEN + i * CHUNK_LEN;
+			&ciphertext[start..(start + CHUNK_LEN).min(ciphertext.len())]
+		};
+
+		let mut tampered = ciphertext.clone();
+		tampered[HEADER_LEN + CHUNK_LEN + 3] ^= 1;
+
+		// Truncated at a chunk boundary
+		let truncated = &ciphertext[..HEADER_LEN + 2 * CHUNK_LEN];
+
+		let reordered =
+			[&ciphertext[..HEADER_LEN], chunk(1), chunk(0), chunk(2), chunk(3)]
+				.concat();
+
+		let dropped =
+			[&ciphertext[..HEADER_LEN], chunk(0), chunk(2), chunk(3)].concat();
+
+		for modified in [&tampered[..], truncated, &reordered, &dropped] {
+			assert_eq!(
+				decrypt(&pair, modified),
+				Err(P256Error::AesGcm256DecryptError)
+			);
+		}
+		assert_eq!(
+			decrypt(&pair, &ciphertext[..HEADER_LEN]),
+			Err(P256Error::AesGcm256DecryptError)
+		);
+		assert_eq!(
+			decrypt(&pair, &ciphertext[..10]),
+			Err(P256Error::FailedToDeserializeEnvelope)
+		);
+	}
 }
 
 #[cfg(test)]
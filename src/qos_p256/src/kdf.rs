@@ -0,0 +1,192 @@
+//! HKDF key derivation from a master seed.
+//!
+//! Every derived secret has a [`DerivationContext`]: the [`KeyPurpose`] it is for
+//! and, optionally, the namespace it belongs to. The label of the purpose is
+//! the HKDF salt and the namespace is the HKDF info, so secrets derived for
+//! different purposes or namespaces are independent.
+
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+
+use crate::{
+	P256Error, AES_GCM_256_PATH, P256_ENCRYPT_DERIVE_PATH,
+	P256_SIGN_DERIVE_PATH,
+};
+
+/// Prefix of the label of [`KeyPurpose::Application`] purposes, so they never
+/// collide with the purposes of this crate.
+const APPLICATION_LABEL_PREFIX: &[u8] = b"qos_app_";
+/// Prefix of the HKDF info of a namespaced context.
+const NAMESPACE_INFO_PREFIX: &[u8] = b"qos_namespace:";
+
+/// Hash function HKDF is instantiated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HkdfHash {
+	/// HKDF-SHA256.
+	Sha256,
+	/// HKDF-SHA512.
+	Sha512,
+}
+
+/// What a derived secret is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPurpose<'a> {
+	/// The encryption secret of a [`crate::P256Pair`].
+	P256Encrypt,
+	/// The signing secret of a [`crate::P256Pair`].
+	P256Sign,
+	/// The symmetric encryption secret of a [`crate::P256Pair`].
+	AesGcm256,
+	/// A purpose defined by an application, with its own label.
+	Application(&'a str),
+}
+
+impl KeyPurpose<'_> {
+	/// Label of the purpose, used as the HKDF salt.
+	#[must_use]
+	pub fn label(&self) -> Vec<u8> {
+		match self {
+			Self::P256Encrypt => P256_ENCRYPT_DERIVE_PATH.to_vec(),
+			Self::P256Sign => P256_SIGN_DERIVE_PATH.to_vec(),
+			Self::AesGcm256 => AES_GCM_256_PATH.to_vec(),
+			Self::Application(label) => {
+				[APPLICATION_LABEL_PREFIX, label.as_bytes()].concat()
+			}
+		}
+	}
+}
+
+/// Domain a secret is derived for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivationContext<'a> {
+	purpose: KeyPurpose<'a>,
+	namespace: Option<&'a str>,
+}
+
+impl<'a> DerivationContext<'a> {
+	/// Context for deriving a secret for `purpose`.
+	#[must_use]
+	pub fn new(purpose: KeyPurpose<'a>) -> Self {
+		Self { purpose, namespace: None }
+	}
+
+	/// Restrict the context to the given namespace.
+	#[must_use]
+	pub fn with_namespace(mut self, namespace: &'a str) -> Self {
+		self.namespace = Some(namespace);
+		self
+	}
+
+	fn info(&self) -> Vec<u8> {
+		self.namespace
+			.map(|namespace| {
+				[NAMESPACE_INFO_PREFIX, namespace.as_bytes()].concat()
+			})
+			.unwrap_or_default()
+	}
+}
+
+/// Derive an `N` byte secret for `context` from the input key material
+/// `ikm`, typically a master seed.
+pub fn derive<const N: usize>(
+	hash: HkdfHash,
+	ikm: &[u8],
+	context: &DerivationContext,
+) -> Result<[u8; N], P256Error> {
+	expand(hash, &context.purpose.label(), ikm, &context.info())
+}
+
+/// Run HKDF extract and expand.
+pub(crate) fn expand<const N: usize>(
+	hash: HkdfHash,
+	salt: &[u8],
+	ikm: &[u8],
+	info: &[u8],
+) -> Result<[u8; N], P256Error> {
+	let mut okm = [0u8; N];
+	match hash {
+		HkdfHash::Sha256 => {
+			Hkdf::<Sha256>::new(Some(salt), ikm).expand(info, &mut okm)
+		}
+		HkdfHash::Sha512 => {
+			Hkdf::<Sha512>::new(Some(salt), ikm).expand(info, &mut okm)
+		}
+	}
+	.map_err(|_| P256Error::HkdfExpansionFailed)?;
+
+	Ok(okm)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{derive_secret, MASTER_SEED_LEN};
+
+	#[test]
+	fn expand_matches_rfc5869() {
+		// Test case 1 of RFC 5869
+		let ikm = [0x0b; 22];
+		let salt: Vec<u8> = (0x00..=0x0c).collect();
+		let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+		let okm: [u8; 42] =
+			expand(HkdfHash::Sha256, &salt, &ikm, &info).unwrap();
+		assert_eq!(
+			qos_hex::encode(&okm),
+			"3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+			34007208d5b887185865"
+		);
+
+		assert!(expand::<{ 255 * 32 + 1 }>(
+			HkdfHash::Sha256,
+			&salt,
+			&ikm,
+			&info
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn derive_matches_the_derive_paths() {
+		let seed = [7; MASTER_SEED_LEN];
+
+		for (purpose, path) in [
+			(KeyPurpose::P256Encrypt, P256_ENCRYPT_DERIVE_PATH),
+			(KeyPurpose::P256Sign, P256_SIGN_DERIVE_PATH),
+			(KeyPurpose::AesGcm256, AES_GCM_256_PATH),
+		] {
+			assert_eq!(
+				derive(
+					HkdfHash::Sha512,
+					&seed,
+					&DerivationContext::new(purpose)
+				)
+				.unwrap(),
+				derive_secret(&seed, path).unwrap()
+			);
+		}
+	}
+
+	#[test]
+	fn contexts_derive_independent_secrets() {
+		let seed = [7; MASTER_SEED_LEN];
+		let contexts = [
+			DerivationContext::new(KeyPurpose::P256Sign),
+			DerivationContext::new(KeyPurpose::Application("qos_p256_sign")),
+			DerivationContext::new(KeyPurpose::Application("tls")),
+			DerivationContext::new(KeyPurpose::Application("tls"))
+				.with_namespace("alpha"),
+			DerivationContext::new(KeyPurpose::Application("tls"))
+				.with_namespace("beta"),
+		];
+
+		let mut secrets: Vec<[u8; 32]> = contexts
+			.iter()
+			.map(|context| derive(HkdfHash::Sha512, &seed, context).unwrap())
+			.collect();
+		secrets.push(derive(HkdfHash::Sha256, &seed, &contexts[0]).unwrap());
+		secrets.sort_unstable();
+		secrets.dedup();
+		assert_eq!(secrets.len(), contexts.len() + 1);
+	}
+}
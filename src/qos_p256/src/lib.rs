@@ -5,20 +5,23 @@
 #![warn(missing_docs, clippy::pedantic)]
 #![allow(clippy::missing_errors_doc)]
 
-use std::path::Path;
+use std::{
+	io::{Read, Write},
+	path::Path,
+};
 
 use encrypt::AesGcm256Secret;
-use hkdf::Hkdf;
 use rand_core::{OsRng, RngCore};
-use sha2::Sha512;
-use zeroize::ZeroizeOnDrop;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 use crate::{
 	encrypt::{P256EncryptPair, P256EncryptPublic},
+	kdf::{DerivationContext, HkdfHash, KeyPurpose},
 	sign::{P256SignPair, P256SignPublic},
 };
 
 const PUB_KEY_LEN_UNCOMPRESSED: u8 = 65;
+const PUB_KEY_LEN_COMPRESSED: u8 = 33;
 
 /// Master seed derive path for encryption secret
 pub const P256_ENCRYPT_DERIVE_PATH: &[u8] = b"qos_p256_encrypt";
@@ -30,8 +33,17 @@ pub const AES_GCM_256_PATH: &[u8] = b"qos_aes_gcm_encrypt";
 pub const P256_SECRET_LEN: usize = 32;
 /// Length of the master seed.
 pub const MASTER_SEED_LEN: usize = 32;
+/// Prefix of a master seed file encrypted with a passphrase.
+pub const ENCRYPTED_MASTER_SEED_PREFIX: &str = "qos-encrypted-master-seed:";
+/// PBKDF2-HMAC-SHA256 rounds to derive the key that encrypts a master seed
+/// from a passphrase.
+pub const PASSPHRASE_KDF_ROUNDS: u32 = 600_000;
+const PASSPHRASE_SALT_LEN: usize = 16;
 
 pub mod encrypt;
+pub mod kdf;
+pub mod mnemonic;
+pub mod self_test;
 pub mod sign;
 
 /// Errors for qos P256.
@@ -83,6 +95,48 @@ pub enum P256Error {
 	/// Failed to convert a len (usize) to a u8. This is an internal error and
 	/// the code has a bug.
 	CannotCoerceLenToU8,
+	/// The encrypted master seed could not be decrypted, most likely because
+	/// the passphrase is wrong.
+	WrongPassphrase,
+	/// The signature at `index` of a batch could not be verified.
+	FailedBatchSignatureVerification {
+		/// Index of the first signature of the batch that is not good.
+		index: usize,
+	},
+	/// The recovery phrase is not valid.
+	InvalidMnemonic(String),
+	/// The encryption envelope has a format version this version of the
+	/// crate does not know.
+	UnsupportedEnvelopeVersion(u8),
+	/// The encryption envelope is encrypted with an algorithm this version of
+	/// the crate does not know.
+	UnsupportedEnvelopeAlgorithm(u8),
+	/// The algorithm is not approved, and this build only offers approved
+	/// algorithms. See [`qos_crypto::algorithms`].
+	UnapprovedAlgorithm(qos_crypto::algorithms::Algorithm),
+}
+
+/// A master seed encrypted with a key derived from a passphrase.
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+struct PassphraseEnvelope {
+	/// PBKDF2-HMAC-SHA256 rounds used to derive the key.
+	rounds: u32,
+	/// Salt used to derive the key.
+	salt: [u8; PASSPHRASE_SALT_LEN],
+	/// Serialized [`encrypt::SymmetricEnvelope`] with the master seed.
+	encrypted_master_seed: Vec<u8>,
+}
+
+fn passphrase_secret(
+	passphrase: &[u8],
+	salt: &[u8],
+	rounds: u32,
+) -> Result<AesGcm256Secret, P256Error> {
+	let mut key = [0u8; encrypt::AES256_KEY_LEN];
+	pbkdf2::pbkdf2::<hmac::Hmac<sha2::Sha256>>(
+		passphrase, salt, rounds, &mut key,
+	);
+	AesGcm256Secret::from_bytes(key)
 }
 
 impl From<qos_hex::HexError> for P256Error {
@@ -96,17 +150,13 @@ impl From<qos_hex::HexError> for P256Error {
 	}
 }
 
-/// Helper function to derive a secret from a master seed.
+/// Helper function to derive a secret from a master seed with HKDF-SHA512,
+/// using `derive_path` as the salt. See [`kdf`] for typed derivation.
 pub fn derive_secret(
 	seed: &[u8; MASTER_SEED_LEN],
 	derive_path: &[u8],
 ) -> Result<[u8; P256_SECRET_LEN], P256Error> {
-	let hk = Hkdf::<Sha512>::new(Some(derive_path), seed);
-
-	let mut buf = [0u8; P256_SECRET_LEN];
-	hk.expand(&[], &mut buf).map_err(|_| P256Error::HkdfExpansionFailed)?;
-
-	Ok(buf)
+	kdf::expand(HkdfHash::Sha512, derive_path, seed, &[])
 }
 
 /// Helper function to generate a `N` length byte buffer.
@@ -132,22 +182,7 @@ pub struct P256Pair {
 impl P256Pair {
 	/// Generate a new private key using the OS randomness source.
 	pub fn generate() -> Result<Self, P256Error> {
-		let master_seed = bytes_os_rng::<MASTER_SEED_LEN>();
-
-		let p256_encrypt_secret =
-			derive_secret(&master_seed, P256_ENCRYPT_DERIVE_PATH)?;
-		let p256_sign_secret =
-			derive_secret(&master_seed, P256_SIGN_DERIVE_PATH)?;
-		let aes_gcm_secret = derive_secret(&master_seed, AES_GCM_256_PATH)?;
-
-		Ok(Self {
-			p256_encrypt_private: P256EncryptPair::from_bytes(
-				&p256_encrypt_secret,
-			)?,
-			sign_private: P256SignPair::from_bytes(&p256_sign_secret)?,
-			master_seed,
-			aes_gcm_256_secret: AesGcm256Secret::from_bytes(aes_gcm_secret)?,
-		})
+		Self::from_master_seed(&bytes_os_rng::<MASTER_SEED_LEN>())
 	}
 
 	/// Encrypt the given `msg` with the symmetric encryption secret.
@@ -174,6 +209,17 @@ impl P256Pair {
 		self.p256_encrypt_private.decrypt(serialized_envelope)
 	}
 
+	/// Decrypt a stream encrypted to this pair's public key with
+	/// [`P256Public::encrypt_stream`]. See
+	/// [`P256EncryptPair::decrypt_stream`].
+	pub fn decrypt_stream<R: Read, W: Write>(
+		&self,
+		reader: R,
+		writer: W,
+	) -> Result<(), P256Error> {
+		self.p256_encrypt_private.decrypt_stream(reader, writer)
+	}
+
 	/// Sign the message and return the raw signature.
 	pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, P256Error> {
 		self.sign_private.sign(message)
@@ -192,31 +238,61 @@ impl P256Pair {
 	pub fn from_master_seed(
 		master_seed: &[u8; MASTER_SEED_LEN],
 	) -> Result<Self, P256Error> {
-		let encrypt_secret =
-			derive_secret(master_seed, P256_ENCRYPT_DERIVE_PATH)?;
-		let sign_secret = derive_secret(master_seed, P256_SIGN_DERIVE_PATH)?;
-		let aes_gcm_256_encrypt = derive_secret(master_seed, AES_GCM_256_PATH)?;
+		let derive = |purpose| {
+			kdf::derive(
+				HkdfHash::Sha512,
+				master_seed,
+				&DerivationContext::new(purpose),
+			)
+			.map(Zeroizing::new)
+		};
+		let encrypt_secret = derive(KeyPurpose::P256Encrypt)?;
+		let sign_secret = derive(KeyPurpose::P256Sign)?;
+		let aes_gcm_256_encrypt = derive(KeyPurpose::AesGcm256)?;
 
 		Ok(Self {
-			p256_encrypt_private: P256EncryptPair::from_bytes(&encrypt_secret)?,
-			sign_private: P256SignPair::from_bytes(&sign_secret)?,
+			p256_encrypt_private: P256EncryptPair::from_bytes(
+				encrypt_secret.as_slice(),
+			)?,
+			sign_private: P256SignPair::from_bytes(sign_secret.as_slice())?,
 			master_seed: *master_seed,
 			aes_gcm_256_secret: AesGcm256Secret::from_bytes(
-				aes_gcm_256_encrypt,
+				*aes_gcm_256_encrypt,
 			)?,
 		})
 	}
 
+	/// Derive a secret for `context` from the master seed, for example a key
+	/// for an application in a namespace.
+	pub fn derive_key(
+		&self,
+		context: &DerivationContext,
+	) -> Result<Zeroizing<[u8; P256_SECRET_LEN]>, P256Error> {
+		kdf::derive(HkdfHash::Sha512, &self.master_seed, context)
+			.map(Zeroizing::new)
+	}
+
 	/// Get the raw master seed used to create this pair.
 	#[must_use]
 	pub fn to_master_seed(&self) -> &[u8; MASTER_SEED_LEN] {
 		&self.master_seed
 	}
 
+	/// Encode the master seed as a recovery phrase to write down. See
+	/// [`mnemonic`].
+	pub fn to_mnemonic(&self) -> Result<Zeroizing<String>, P256Error> {
+		mnemonic::to_mnemonic(&self.master_seed)
+	}
+
+	/// Create `Self` from a recovery phrase made with [`Self::to_mnemonic`].
+	pub fn from_mnemonic(phrase: &str) -> Result<Self, P256Error> {
+		Self::from_master_seed(&*mnemonic::from_mnemonic(phrase)?)
+	}
+
 	/// Convert to hex bytes.
 	#[must_use]
-	pub fn to_master_seed_hex(&self) -> Vec<u8> {
-		qos_hex::encode_to_vec(&self.master_seed)
+	pub fn to_master_seed_hex(&self) -> Zeroizing<Vec<u8>> {
+		Zeroizing::new(qos_hex::encode_to_vec(&self.master_seed))
 	}
 
 	/// Write the raw master seed to file as hex encoded.
@@ -224,7 +300,7 @@ impl P256Pair {
 		&self,
 		path: P,
 	) -> Result<(), P256Error> {
-		let hex_string = qos_hex::encode(&self.master_seed);
+		let hex_string = Zeroizing::new(qos_hex::encode(&self.master_seed));
 		std::fs::write(path, hex_string.as_bytes()).map_err(|e| {
 			P256Error::IOError(format!("failed to write master secret {e}"))
 		})
@@ -232,18 +308,86 @@ impl P256Pair {
 
 	/// Read the raw, hex encoded master from a file.
 	pub fn from_hex_file<P: AsRef<Path>>(path: P) -> Result<Self, P256Error> {
-		let hex_bytes = std::fs::read(path).map_err(|e| {
+		let hex_bytes = Zeroizing::new(std::fs::read(path).map_err(|e| {
 			P256Error::IOError(format!("failed to read master seed: {e}"))
-		})?;
+		})?);
 
-		let master_seed =
-			qos_hex::decode_from_vec(hex_bytes).map_err(P256Error::from)?;
-		let master_seed: [u8; MASTER_SEED_LEN] = master_seed
-			.try_into()
-			.map_err(|_| P256Error::MasterSeedInvalidLength)?;
+		let hex = std::str::from_utf8(&hex_bytes)
+			.map_err(|_| P256Error::MasterSeedInvalidUtf8)?;
+		let master_seed = Zeroizing::new(qos_hex::decode(hex.trim())?);
+		Self::from_master_seed_slice(&master_seed)
+	}
+
+	/// Encrypt the master seed with a key derived from `passphrase`.
+	///
+	/// Returns [`ENCRYPTED_MASTER_SEED_PREFIX`] followed by the hex encoded
+	/// envelope, suitable for writing to a file.
+	pub fn to_encrypted_master_seed_hex(
+		&self,
+		passphrase: &[u8],
+	) -> Result<Vec<u8>, P256Error> {
+		self.encrypt_master_seed(passphrase, PASSPHRASE_KDF_ROUNDS)
+	}
+
+	fn encrypt_master_seed(
+		&self,
+		passphrase: &[u8],
+		rounds: u32,
+	) -> Result<Vec<u8>, P256Error> {
+		let salt = bytes_os_rng::<PASSPHRASE_SALT_LEN>();
+		let secret = passphrase_secret(passphrase, &salt, rounds)?;
+		let envelope = PassphraseEnvelope {
+			rounds,
+			salt,
+			encrypted_master_seed: secret.encrypt(&self.master_seed)?,
+		};
+		let envelope = borsh::to_vec(&envelope)
+			.map_err(|_| P256Error::FailedToSerializeEnvelope)?;
+
+		let mut out = ENCRYPTED_MASTER_SEED_PREFIX.as_bytes().to_vec();
+		out.extend(qos_hex::encode_to_vec(&envelope));
+		Ok(out)
+	}
+
+	/// Create `Self` from the output of
+	/// [`Self::to_encrypted_master_seed_hex`].
+	pub fn from_encrypted_master_seed_hex(
+		encrypted: &[u8],
+		passphrase: &[u8],
+	) -> Result<Self, P256Error> {
+		let hex = encrypted
+			.trim_ascii()
+			.strip_prefix(ENCRYPTED_MASTER_SEED_PREFIX.as_bytes())
+			.ok_or(P256Error::FailedToDeserializeEnvelope)?;
+		let envelope = qos_hex::decode_from_vec(hex.to_vec())?;
+		let PassphraseEnvelope { rounds, salt, encrypted_master_seed } =
+			borsh::from_slice(&envelope)
+				.map_err(|_| P256Error::FailedToDeserializeEnvelope)?;
+
+		let master_seed = Zeroizing::new(
+			passphrase_secret(passphrase, &salt, rounds)?
+				.decrypt(&encrypted_master_seed)
+				.map_err(|_| P256Error::WrongPassphrase)?,
+		);
+		Self::from_master_seed_slice(&master_seed)
+	}
+
+	fn from_master_seed_slice(master_seed: &[u8]) -> Result<Self, P256Error> {
+		let master_seed: Zeroizing<[u8; MASTER_SEED_LEN]> = Zeroizing::new(
+			master_seed
+				.try_into()
+				.map_err(|_| P256Error::MasterSeedInvalidLength)?,
+		);
 		Self::from_master_seed(&master_seed)
 	}
 
+	/// Whether `contents` of a master seed file are encrypted with a
+	/// passphrase.
+	#[must_use]
+	pub fn is_encrypted_master_seed(contents: &[u8]) -> bool {
+		contents.starts_with(ENCRYPTED_MASTER_SEED_PREFIX.as_bytes())
+	}
+
 	/// Get a reference to the underlying signing key. Useful for interoperation
 	/// with other crypto abstractions.
 	#[must_use]
@@ -266,6 +410,16 @@ impl P256Public {
 		self.encrypt_public.encrypt(message)
 	}
 
+	/// Encrypt a stream to this public key, for payloads too large to hold in
+	/// memory. See [`P256EncryptPublic::encrypt_stream`].
+	pub fn encrypt_stream<R: Read, W: Write>(
+		&self,
+		reader: R,
+		writer: W,
+	) -> Result<(), P256Error> {
+		self.encrypt_public.encrypt_stream(reader, writer)
+	}
+
 	/// Verify a `signature` and `message` against this private key. Verifies
 	/// the SHA512 digest of the message.
 	///
@@ -278,6 +432,13 @@ impl P256Public {
 		self.sign_public.verify(message, signature)
 	}
 
+	/// Get the public key used to verify signatures, for example to verify it
+	/// as part of a batch with [`sign::verify_batch`].
+	#[must_use]
+	pub fn sign_public(&self) -> &P256SignPublic {
+		&self.sign_public
+	}
+
 	/// Serialize each public key as a SEC1 encoded point, not compressed.
 	/// Encodes as `encrypt_public||sign_public`.
 	#[must_use]
@@ -290,18 +451,40 @@ impl P256Public {
 			.collect()
 	}
 
-	/// Deserialize each public key from a SEC1 encoded point, not compressed.
-	/// Expects encoding as `encrypt_public||sign_public`.
+	/// Serialize each public key as a SEC1 encoded point, compressed, which
+	/// is about half the size of [`Self::to_bytes`]. Encodes as
+	/// `encrypt_public||sign_public`.
+	///
+	/// Note that the compressed and uncompressed encodings of the same key
+	/// are different bytes.
+	#[must_use]
+	pub fn to_compressed_bytes(&self) -> Vec<u8> {
+		self.encrypt_public
+			.to_compressed_bytes()
+			.iter()
+			.chain(self.sign_public.to_compressed_bytes().iter())
+			.copied()
+			.collect()
+	}
+
+	/// Deserialize each public key from a SEC1 encoded point, either both
+	/// compressed or both not compressed. Expects encoding as
+	/// `encrypt_public||sign_public`.
 	pub fn from_bytes(bytes: &[u8]) -> Result<Self, P256Error> {
-		if bytes.len() > PUB_KEY_LEN_UNCOMPRESSED as usize * 2 {
-			return Err(P256Error::EncodedPublicKeyTooLong);
-		}
-		if bytes.len() < PUB_KEY_LEN_UNCOMPRESSED as usize * 2 {
-			return Err(P256Error::EncodedPublicKeyTooShort);
-		}
+		let key_len = match bytes.len() {
+			len if len == PUB_KEY_LEN_UNCOMPRESSED as usize * 2 => {
+				PUB_KEY_LEN_UNCOMPRESSED
+			}
+			len if len == PUB_KEY_LEN_COMPRESSED as usize * 2 => {
+				PUB_KEY_LEN_COMPRESSED
+			}
+			len if len > PUB_KEY_LEN_UNCOMPRESSED as usize * 2 => {
+				return Err(P256Error::EncodedPublicKeyTooLong)
+			}
+			_ => return Err(P256Error::EncodedPublicKeyTooShort),
+		};
 
-		let (encrypt_bytes, sign_bytes) =
-			bytes.split_at(PUB_KEY_LEN_UNCOMPRESSED as usize);
+		let (encrypt_bytes, sign_bytes) = bytes.split_at(key_len as usize);
 
 		Ok(Self {
 			encrypt_public: P256EncryptPublic::from_bytes(encrypt_bytes)
@@ -351,6 +534,7 @@ impl P256Public {
 #[cfg(test)]
 mod test {
 	use qos_test_primitives::PathWrapper;
+	use zeroize::Zeroize;
 
 	use super::*;
 
@@ -443,6 +627,20 @@ mod test {
 		let message = b"a message to authenticate";
 		let signature = alice_pair.sign(message).unwrap();
 		assert!(alice_public2.verify(message, &signature).is_ok());
+
+		let compressed = alice_public.to_compressed_bytes();
+		assert_eq!(compressed.len(), PUB_KEY_LEN_COMPRESSED as usize * 2);
+		let alice_public3 = P256Public::from_bytes(&compressed).unwrap();
+		assert_eq!(alice_public3.to_bytes(), alice_public_bytes);
+
+		assert!(matches!(
+			P256Public::from_bytes(&alice_public_bytes[1..]),
+			Err(P256Error::EncodedPublicKeyTooShort)
+		));
+		assert!(matches!(
+			P256Public::from_bytes(&compressed[..65]),
+			Err(P256Error::EncodedPublicKeyTooShort)
+		));
 	}
 
 	#[test]
@@ -505,6 +703,52 @@ mod test {
 		assert!(alice_pair.public_key().verify(message, &signature).is_ok());
 	}
 
+	#[test]
+	fn secrets_are_wiped() {
+		fn zeroize_on_drop<T: ZeroizeOnDrop>() {}
+		zeroize_on_drop::<P256Pair>();
+		zeroize_on_drop::<P256EncryptPair>();
+		zeroize_on_drop::<P256SignPair>();
+		zeroize_on_drop::<AesGcm256Secret>();
+
+		let pair = P256Pair::generate().unwrap();
+		let mut key = pair
+			.derive_key(&DerivationContext::new(KeyPurpose::Application(
+				"test",
+			)))
+			.unwrap();
+		assert_ne!(*key, [0; P256_SECRET_LEN]);
+		key.zeroize();
+		assert_eq!(*key, [0; P256_SECRET_LEN]);
+
+		let mut hex = pair.to_master_seed_hex();
+		hex.zeroize();
+		assert!(hex.is_empty());
+	}
+
+	#[test]
+	fn encrypted_master_seed_round_trip() {
+		let pair = P256Pair::generate().unwrap();
+		// Few rounds to keep the test fast, they are stored with the seed
+		let encrypted = pair.encrypt_master_seed(b"hunter2", 1_000).unwrap();
+
+		assert!(P256Pair::is_encrypted_master_seed(&encrypted));
+		assert!(!P256Pair::is_encrypted_master_seed(
+			&pair.to_master_seed_hex()
+		));
+
+		let decrypted =
+			P256Pair::from_encrypted_master_seed_hex(&encrypted, b"hunter2")
+				.unwrap();
+		assert_eq!(decrypted.to_master_seed(), pair.to_master_seed());
+
+		assert_eq!(
+			P256Pair::from_encrypted_master_seed_hex(&encrypted, b"hunter3")
+				.err(),
+			Some(P256Error::WrongPassphrase)
+		);
+	}
+
 	mod aes_gcm_256 {
 		use super::*;
 
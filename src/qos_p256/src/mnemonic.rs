@@ -0,0 +1,130 @@
+//! Recovery phrases for P256 secrets, so they can be written down.
+//!
+//! A 32 byte secret is encoded as 24 words of the BIP-39 English word list,
+//! the last of which includes an 8 bit checksum of the secret.
+//!
+//! Recovery phrases are not an approved algorithm, so with the
+//! `approved-only` feature encoding and decoding them fails with
+//! [`P256Error::UnapprovedAlgorithm`].
+
+use bip39::{Language, Mnemonic};
+use qos_crypto::algorithms::{self, Algorithm};
+use zeroize::Zeroizing;
+
+use crate::{P256Error, P256_SECRET_LEN};
+
+/// Number of words of a recovery phrase.
+pub const MNEMONIC_WORD_COUNT: usize = 24;
+
+fn require_approved() -> Result<(), P256Error> {
+	algorithms::require_approved(Algorithm::Bip39Mnemonic)
+		.map_err(|_| P256Error::UnapprovedAlgorithm(Algorithm::Bip39Mnemonic))
+}
+
+/// Encode `secret` as a recovery phrase of [`MNEMONIC_WORD_COUNT`] words
+/// separated by spaces.
+///
+/// # Panics
+///
+/// Panics if BIP-39 rejects a 32 byte secret, which should never happen.
+pub fn to_mnemonic(
+	secret: &[u8; P256_SECRET_LEN],
+) -> Result<Zeroizing<String>, P256Error> {
+	require_approved()?;
+	let mnemonic = Mnemonic::from_entropy_in(Language::English, secret)
+		.expect("32 bytes is a valid BIP-39 entropy length. qed.");
+
+	Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Decode a recovery phrase made with [`to_mnemonic`]. Case and whitespace
+/// between words do not matter.
+pub fn from_mnemonic(
+	phrase: &str,
+) -> Result<Zeroizing<[u8; P256_SECRET_LEN]>, P256Error> {
+	require_approved()?;
+	let normalized = Zeroizing::new(
+		phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase(),
+	);
+	let mnemonic =
+		Mnemonic::parse_in_normalized(Language::English, &normalized)
+			.map_err(|e| P256Error::InvalidMnemonic(e.to_string()))?;
+	if mnemonic.word_count() != MNEMONIC_WORD_COUNT {
+		return Err(P256Error::InvalidMnemonic(format!(
+			"expected {MNEMONIC_WORD_COUNT} words, got {}",
+			mnemonic.word_count()
+		)));
+	}
+
+	let (entropy, len) = mnemonic.to_entropy_array();
+	let entropy = Zeroizing::new(entropy);
+	let mut secret = Zeroizing::new([0; P256_SECRET_LEN]);
+	secret.copy_from_slice(&entropy[..len]);
+
+	Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PHRASE: &str = "legal winner thank year wave sausage worth useful \
+		legal winner thank year wave sausage worth useful legal winner thank \
+		year wave sausage worth title";
+
+	#[test]
+	fn mnemonics_follow_the_approved_only_feature() {
+		let unapproved =
+			P256Error::UnapprovedAlgorithm(Algorithm::Bip39Mnemonic);
+		let secret = [0x7f; P256_SECRET_LEN];
+		if algorithms::APPROVED_ONLY {
+			assert_eq!(to_mnemonic(&secret).unwrap_err(), unapproved);
+			assert_eq!(from_mnemonic(PHRASE).unwrap_err(), unapproved);
+		} else {
+			assert!(to_mnemonic(&secret).is_ok());
+			assert!(from_mnemonic(PHRASE).is_ok());
+		}
+	}
+
+	#[cfg(not(feature = "approved-only"))]
+	#[test]
+	fn mnemonic_round_trip() {
+		// Test vector from BIP-39
+		let secret = [0x7f; P256_SECRET_LEN];
+		let phrase = to_mnemonic(&secret).unwrap();
+		assert_eq!(phrase.as_str(), PHRASE);
+		assert_eq!(*from_mnemonic(&phrase).unwrap(), secret);
+
+		// Written down by hand
+		let written = phrase.to_uppercase().replace(' ', "\n  ");
+		assert_eq!(*from_mnemonic(&written).unwrap(), secret);
+	}
+
+	#[cfg(not(feature = "approved-only"))]
+	#[test]
+	fn from_mnemonic_rejects_bad_phrases() {
+		let phrase = PHRASE;
+
+		// Bad checksum
+		let swapped = phrase.replacen("legal winner", "winner legal", 1);
+		assert!(matches!(
+			from_mnemonic(&swapped),
+			Err(P256Error::InvalidMnemonic(_))
+		));
+
+		let unknown_word = phrase.replacen("legal", "lethal", 1);
+		assert!(matches!(
+			from_mnemonic(&unknown_word),
+			Err(P256Error::InvalidMnemonic(_))
+		));
+
+		// A valid 12 word phrase
+		assert!(matches!(
+			from_mnemonic(
+				"legal winner thank year wave sausage worth useful legal \
+				winner thank yellow"
+			),
+			Err(P256Error::InvalidMnemonic(_))
+		));
+	}
+}
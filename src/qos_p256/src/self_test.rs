@@ -0,0 +1,118 @@
+//! Known-answer tests of the primitives underlying this crate, to check at
+//! runtime that they work on the machine they run on.
+//!
+//! Each test returns whether it passed. A test never panics, so a failure can
+//! be reported rather than taking down the caller.
+
+use aes_gcm::{
+	aead::{Aead, KeyInit, Payload},
+	Aes256Gcm, Nonce,
+};
+use p256::{ecdh::diffie_hellman, PublicKey, SecretKey};
+use qos_crypto::ct_eq;
+
+use crate::sign::P256SignPair;
+
+// Test case 16 of "The Galois/Counter Mode of Operation (GCM)", McGrew and
+// Viega.
+const AES_GCM_KEY: &str =
+	"feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308";
+const AES_GCM_NONCE: &str = "cafebabefacedbaddecaf888";
+const AES_GCM_AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+const AES_GCM_PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+const AES_GCM_CIPHERTEXT: &str = "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f66276fc6ece0f4e1768cddf8853bb2d551b";
+
+// Count 0 of the NIST CAVS ECC CDH primitive test vectors for P-256.
+const ECDH_PUBLIC: &str = "04700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac";
+const ECDH_SECRET: &str =
+	"7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534";
+const ECDH_SHARED_SECRET: &str =
+	"46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b";
+
+// RFC 6979, A.2.5: P-256 with SHA-256, message "sample".
+const ECDSA_SECRET: &str =
+	"c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+const ECDSA_MESSAGE: &[u8] = b"sample";
+const ECDSA_SIGNATURE: &str = "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8";
+
+/// Check AES-256-GCM encryption and decryption against a known answer.
+#[must_use]
+pub fn aes_gcm_known_answer() -> bool {
+	let (Some(key), Some(nonce), Some(aad), Some(plaintext), Some(ciphertext)) = (
+		hex(AES_GCM_KEY),
+		hex(AES_GCM_NONCE),
+		hex(AES_GCM_AAD),
+		hex(AES_GCM_PLAINTEXT),
+		hex(AES_GCM_CIPHERTEXT),
+	) else {
+		return false;
+	};
+	let Ok(cipher) = Aes256Gcm::new_from_slice(&key) else {
+		return false;
+	};
+	let nonce = Nonce::from_slice(&nonce);
+
+	let encrypts = cipher
+		.encrypt(nonce, Payload { msg: &plaintext, aad: &aad })
+		.is_ok_and(|actual| ct_eq(&actual, &ciphertext));
+	let decrypts = cipher
+		.decrypt(nonce, Payload { msg: &ciphertext, aad: &aad })
+		.is_ok_and(|actual| ct_eq(&actual, &plaintext));
+
+	encrypts && decrypts
+}
+
+/// Check ECDH over P256 against a known answer.
+#[must_use]
+pub fn ecdh_known_answer() -> bool {
+	let (Some(public), Some(secret), Some(shared_secret)) =
+		(hex(ECDH_PUBLIC), hex(ECDH_SECRET), hex(ECDH_SHARED_SECRET))
+	else {
+		return false;
+	};
+	let (Ok(public), Ok(secret)) = (
+		PublicKey::from_sec1_bytes(&public),
+		SecretKey::from_be_bytes(&secret),
+	) else {
+		return false;
+	};
+
+	let actual = diffie_hellman(secret.to_nonzero_scalar(), public.as_affine());
+	ct_eq(actual.raw_secret_bytes(), &shared_secret)
+}
+
+/// Check ECDSA over P256 with SHA256 signing and verification against a known
+/// answer.
+#[must_use]
+pub fn ecdsa_known_answer() -> bool {
+	let (Some(secret), Some(signature)) =
+		(hex(ECDSA_SECRET), hex(ECDSA_SIGNATURE))
+	else {
+		return false;
+	};
+	let Ok(pair) = P256SignPair::from_bytes(&secret) else {
+		return false;
+	};
+
+	let signs =
+		pair.sign(ECDSA_MESSAGE).is_ok_and(|actual| ct_eq(&actual, &signature));
+	let verifies = pair.public_key().verify(ECDSA_MESSAGE, &signature).is_ok();
+
+	signs && verifies
+}
+
+fn hex(s: &str) -> Option<Vec<u8>> {
+	qos_hex::decode(s).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn self_tests_pass() {
+		assert!(aes_gcm_known_answer());
+		assert!(ecdh_known_answer());
+		assert!(ecdsa_known_answer());
+	}
+}
@@ -1,4 +1,8 @@
 //! Abstractions for sign and signature verification
+//!
+//! Signatures are ECDSA over P256 with deterministic nonces (RFC6979), so
+//! signing the same message with the same key always gives the same
+//! signature.
 
 use p256::ecdsa::{
 	signature::{Signer, Verifier},
@@ -84,7 +88,14 @@ impl P256SignPublic {
 		sec1_encoded_point.to_bytes()
 	}
 
-	/// Deserialize from a SEC1 encoded point, not compressed.
+	/// Serialize to SEC1 encoded point, compressed.
+	#[must_use]
+	pub fn to_compressed_bytes(&self) -> Box<[u8]> {
+		let sec1_encoded_point = self.public.to_encoded_point(true);
+		sec1_encoded_point.to_bytes()
+	}
+
+	/// Deserialize from a SEC1 encoded point, compressed or not.
 	pub fn from_bytes(bytes: &[u8]) -> Result<Self, P256Error> {
 		Ok(Self {
 			public: VerifyingKey::from_sec1_bytes(bytes)
@@ -93,9 +104,29 @@ impl P256SignPublic {
 	}
 }
 
+/// Verify a batch of `(public key, message, signature)`, such as the
+/// approvals of a manifest.
+///
+/// Returns Ok if every signature is good, otherwise
+/// [`P256Error::FailedBatchSignatureVerification`] with the index of the
+/// first bad one.
+pub fn verify_batch<'a, I>(batch: I) -> Result<(), P256Error>
+where
+	I: IntoIterator<Item = (&'a P256SignPublic, &'a [u8], &'a [u8])>,
+{
+	batch.into_iter().enumerate().try_for_each(
+		|(index, (public, message, signature))| {
+			public.verify(message, signature).map_err(|_| {
+				P256Error::FailedBatchSignatureVerification { index }
+			})
+		},
+	)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::PUB_KEY_LEN_COMPRESSED;
 
 	#[test]
 	fn signatures_are_deterministic() {
@@ -145,6 +176,12 @@ mod tests {
 		let public = P256SignPublic::from_bytes(&bytes_public).unwrap();
 
 		assert!(public.verify(message, &signature).is_ok());
+
+		let compressed = pair.public_key().to_compressed_bytes();
+		assert_eq!(compressed.len(), PUB_KEY_LEN_COMPRESSED as usize);
+		let public = P256SignPublic::from_bytes(&compressed).unwrap();
+
+		assert!(public.verify(message, &signature).is_ok());
 	}
 
 	#[test]
@@ -157,4 +194,34 @@ mod tests {
 
 		assert_eq!(raw_secret1, raw_secret2);
 	}
+
+	#[test]
+	fn verify_batch_reports_first_bad_signature() {
+		let message = b"a manifest to approve";
+		let pairs: Vec<_> = (0..3).map(|_| P256SignPair::generate()).collect();
+		let publics: Vec<_> =
+			pairs.iter().map(P256SignPair::public_key).collect();
+		let mut signatures: Vec<_> =
+			pairs.iter().map(|pair| pair.sign(message).unwrap()).collect();
+
+		let verify = |signatures: &[Vec<u8>]| {
+			let batch: Vec<(&P256SignPublic, &[u8], &[u8])> = publics
+				.iter()
+				.zip(signatures)
+				.map(|(public, signature)| {
+					(public, message.as_slice(), signature.as_slice())
+				})
+				.collect();
+			verify_batch(batch)
+		};
+		assert!(verify(&signatures).is_ok());
+		assert!(verify_batch([]).is_ok());
+
+		signatures[1] = pairs[0].sign(message).unwrap();
+		signatures[2] = vec![0; 3];
+		assert_eq!(
+			verify(&signatures).unwrap_err(),
+			P256Error::FailedBatchSignatureVerification { index: 1 }
+		);
+	}
 }
@@ -0,0 +1,656 @@
+//! Quorum ceremonies simulated in process, see [`Ceremony`].
+
+use std::fmt;
+
+use qos_core::{
+	protocol::{
+		msg::ProtocolMsg,
+		services::{
+			boot::{
+				Approval, CryptoConfig, Manifest, ManifestEnvelope,
+				ManifestSet, Namespace, NitroConfig, PatchSet, PivotConfig,
+				QuorumMember, RestartPolicy, ShareSet,
+			},
+			genesis::{GenesisMemberOutput, GenesisOutput, GenesisSet},
+			provision::open_share,
+		},
+		ProtocolPhase, QosHash,
+	},
+	server::RequestProcessor,
+};
+use qos_crypto::{sha_256, sha_512, shamir};
+use qos_nsm::{
+	mock::{
+		MOCK_PCR0, MOCK_PCR1, MOCK_PCR2, MOCK_PCR3, MOCK_SECONDS_SINCE_EPOCH,
+	},
+	nitro::{attestation_doc_from_der, aws_root_cert},
+	types::NsmResponse,
+};
+use qos_p256::{P256Pair, P256Public, MASTER_SEED_LEN};
+
+use crate::LocalEnclave;
+
+/// A step of a [`Ceremony`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CeremonyStep {
+	/// Generating and sharding the quorum key.
+	Genesis,
+	/// Creating the manifest for the quorum key.
+	Manifest,
+	/// Approving the manifest by the manifest set.
+	Approval,
+	/// Booting the enclave with the approved manifest.
+	Boot,
+	/// Posting shares to the enclave until it has the quorum key.
+	Provision,
+}
+
+/// A [`Ceremony`] step failed, or one of its artifacts was invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CeremonyError {
+	/// The step that failed.
+	pub step: CeremonyStep,
+	/// Why it failed.
+	pub reason: String,
+}
+
+impl fmt::Display for CeremonyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:?} failed: {}", self.step, self.reason)
+	}
+}
+
+impl std::error::Error for CeremonyError {}
+
+/// A simulated member of the manifest set and share set.
+pub struct SimulatedMember {
+	/// Alias of the member.
+	pub alias: String,
+	/// Personal key of the member.
+	pub pair: P256Pair,
+}
+
+impl SimulatedMember {
+	/// The member as it appears in a manifest.
+	#[must_use]
+	pub fn quorum_member(&self) -> QuorumMember {
+		QuorumMember {
+			alias: self.alias.clone(),
+			pub_key: self.pair.public_key().to_bytes(),
+		}
+	}
+}
+
+/// The artifacts of a successful [`Ceremony`], and the enclave it booted.
+pub struct CeremonyOutput {
+	/// The members, in the order they appear in the manifest.
+	pub members: Vec<SimulatedMember>,
+	/// Output of the genesis ceremony.
+	pub genesis_output: GenesisOutput,
+	/// The manifest envelope of the provisioned enclave, including the share
+	/// set approvals.
+	pub manifest_envelope: ManifestEnvelope,
+	/// The provisioned enclave, serving the app given to [`Ceremony::run`].
+	pub enclave: LocalEnclave,
+}
+
+/// Every step of bringing up an enclave, run in process with `members`
+/// simulated members and a [`LocalEnclave`]: genesis, creating the manifest,
+/// `threshold` approvals from the manifest set, booting, and `threshold`
+/// members of the share set posting their shares. Each artifact is checked
+/// the way a member would check it before the next step uses it.
+///
+/// The attestation docs come from the mock NSM, so their certificate chain
+/// and PCRs are checked, but their user data does not match the artifacts.
+///
+/// ```
+/// use qos_core::protocol::{msg::ProtocolMsg, ProtocolPhase};
+/// use qos_test_primitives::Ceremony;
+///
+/// struct Echo;
+/// impl qos_core::server::RequestProcessor for Echo {
+///     fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+///         request
+///     }
+/// }
+///
+/// let output = Ceremony::new(3, 2).run(Echo).unwrap();
+/// assert_eq!(output.manifest_envelope.share_set_approvals.len(), 2);
+/// assert_eq!(
+///     output.enclave.request(&ProtocolMsg::StatusRequest).unwrap(),
+///     ProtocolMsg::StatusResponse(ProtocolPhase::QuorumKeyProvisioned)
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ceremony {
+	members: usize,
+	threshold: u32,
+	namespace: String,
+	pivot: Vec<u8>,
+}
+
+impl Ceremony {
+	/// Create a new instance of [`Self`] with `members` members, of which
+	/// `threshold` approve the manifest and post their shares. Shamir
+	/// sharing needs a `threshold` of at least 2.
+	#[must_use]
+	pub fn new(members: usize, threshold: u32) -> Self {
+		Self {
+			members,
+			threshold,
+			namespace: "simulated-ceremony".to_string(),
+			pivot: b"simulated pivot".to_vec(),
+		}
+	}
+
+	/// Name the namespace of the manifest `namespace`.
+	#[must_use]
+	pub fn with_namespace(mut self, namespace: &str) -> Self {
+		self.namespace = namespace.to_string();
+		self
+	}
+
+	/// Boot the enclave with `pivot` as the pivot binary. It is only
+	/// hashed into the manifest, the enclave serves the app given to
+	/// [`Self::run`] instead.
+	#[must_use]
+	pub fn with_pivot(mut self, pivot: Vec<u8>) -> Self {
+		self.pivot = pivot;
+		self
+	}
+
+	/// Run the ceremony, booting an enclave with `app` as its pivot.
+	pub fn run<A>(&self, app: A) -> Result<CeremonyOutput, CeremonyError>
+	where
+		A: RequestProcessor + Send + 'static,
+	{
+		let threshold = usize::try_from(self.threshold).unwrap_or(usize::MAX);
+		if threshold < 2 || threshold > self.members {
+			return Err(CeremonyError {
+				step: CeremonyStep::Genesis,
+				reason: format!(
+					"threshold {} must be between 2 and the {} members",
+					self.threshold, self.members
+				),
+			});
+		}
+
+		let members = (0..self.members)
+			.map(|i| {
+				Ok(SimulatedMember {
+					alias: format!("member{}", i + 1),
+					pair: P256Pair::generate()?,
+				})
+			})
+			.collect::<Result<Vec<_>, qos_p256::P256Error>>()
+			.map_err(fail(CeremonyStep::Genesis))?;
+
+		let genesis_output = self.genesis(&members)?;
+		let manifest = self.manifest(&members, &genesis_output)?;
+		let manifest_envelope = self.approve(&members, manifest)?;
+		let enclave = LocalEnclave::new(app);
+		self.boot(&enclave, &manifest_envelope)?;
+		let manifest_envelope =
+			self.provision(&enclave, &members, &genesis_output)?;
+
+		Ok(CeremonyOutput {
+			members,
+			genesis_output,
+			manifest_envelope,
+			enclave,
+		})
+	}
+
+	fn genesis(
+		&self,
+		members: &[SimulatedMember],
+	) -> Result<GenesisOutput, CeremonyError> {
+		let step = CeremonyStep::Genesis;
+		let enclave = LocalEnclave::new(Unused);
+		let dr_pair = P256Pair::generate().map_err(fail(step))?;
+
+		expect_phase(&enclave, step, ProtocolPhase::WaitingForBootInstruction)?;
+		let request = ProtocolMsg::BootGenesisRequest {
+			set: GenesisSet {
+				members: members
+					.iter()
+					.map(SimulatedMember::quorum_member)
+					.collect(),
+				threshold: self.threshold,
+			},
+			dr_key: Some(dr_pair.public_key().to_bytes()),
+		};
+		let ProtocolMsg::BootGenesisResponse { nsm_response, genesis_output } =
+			request_ok(&enclave, step, &request)?
+		else {
+			return Err(invalid(step, "unexpected response to genesis"));
+		};
+		check_attestation(step, &nsm_response, None)?;
+		expect_phase(&enclave, step, ProtocolPhase::GenesisBooted)?;
+
+		// Every member checks their own share
+		let output = *genesis_output;
+		if output.threshold != self.threshold
+			|| output.member_outputs.len() != members.len()
+		{
+			return Err(invalid(step, "genesis output does not match the set"));
+		}
+		let shares = members
+			.iter()
+			.zip(&output.member_outputs)
+			.map(|(member, member_output)| {
+				if member_output.share_set_member != member.quorum_member() {
+					return Err(invalid(
+						step,
+						"member outputs are out of order",
+					));
+				}
+				let share = member_share(member, &output)?;
+				if sha_512(&share) != member_output.share_hash {
+					return Err(invalid(step, "share hash mismatch"));
+				}
+				shamir::share_verify(&share, &output.share_commitments)
+					.map_err(fail(step))?;
+				Ok(share)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		// The shares, and the disaster recovery key, recover the quorum key
+		let threshold = self.threshold as usize;
+		let master_seed = if shares.len() > threshold {
+			shamir::shares_reconstruct_checked(&shares, threshold)
+		} else {
+			shamir::shares_reconstruct(&shares)
+		}
+		.map_err(fail(step))?;
+		let master_seed: [u8; MASTER_SEED_LEN] = master_seed
+			.as_slice()
+			.try_into()
+			.map_err(|_| invalid(step, "master seed has the wrong length"))?;
+		let quorum_pair =
+			P256Pair::from_master_seed(&master_seed).map_err(fail(step))?;
+		if quorum_pair.public_key().to_bytes() != output.quorum_key {
+			return Err(invalid(step, "shares do not recover the quorum key"));
+		}
+		if sha_512(qos_hex::encode(&master_seed).as_bytes())
+			!= output.quorum_key_hash
+		{
+			return Err(invalid(step, "quorum key hash mismatch"));
+		}
+		let dr_wrapped =
+			output.dr_key_wrapped_quorum_key.as_ref().ok_or_else(|| {
+				invalid(step, "missing the DR wrapped quorum key")
+			})?;
+		if dr_pair.decrypt(dr_wrapped).map_err(fail(step))? != master_seed {
+			return Err(invalid(
+				step,
+				"DR key does not recover the quorum key",
+			));
+		}
+
+		let test_message = quorum_pair
+			.decrypt(&output.test_message_ciphertext)
+			.map_err(fail(step))?;
+		if test_message != output.test_message {
+			return Err(invalid(step, "test message mismatch"));
+		}
+		quorum_pair
+			.public_key()
+			.verify(&output.test_message, &output.test_message_signature)
+			.map_err(fail(step))?;
+
+		Ok(output)
+	}
+
+	fn manifest(
+		&self,
+		members: &[SimulatedMember],
+		genesis_output: &GenesisOutput,
+	) -> Result<Manifest, CeremonyError> {
+		let step = CeremonyStep::Manifest;
+		let pcr = |hex: &str| qos_hex::decode(hex).map_err(fail(step));
+		let quorum_members: Vec<_> =
+			members.iter().map(SimulatedMember::quorum_member).collect();
+
+		let manifest = Manifest {
+			namespace: Namespace {
+				name: self.namespace.clone(),
+				nonce: 0,
+				quorum_key: genesis_output.quorum_key.clone(),
+			},
+			pivot: PivotConfig {
+				hash: sha_256(&self.pivot),
+				restart: RestartPolicy::Never,
+				args: vec![],
+				app_config: vec![],
+				egress: vec![],
+			},
+			manifest_set: ManifestSet {
+				threshold: self.threshold,
+				members: quorum_members.clone(),
+			},
+			share_set: ShareSet {
+				threshold: self.threshold,
+				members: quorum_members,
+				share_commitments: genesis_output.share_commitments.clone(),
+			},
+			enclave: NitroConfig {
+				pcr0: pcr(MOCK_PCR0)?,
+				pcr1: pcr(MOCK_PCR1)?,
+				pcr2: pcr(MOCK_PCR2)?,
+				pcr3: pcr(MOCK_PCR3)?,
+				aws_root_certificate: aws_root_cert().map_err(fail(step))?,
+				qos_commit: "simulated".to_string(),
+			},
+			patch_set: PatchSet::default(),
+			crypto: CryptoConfig::default(),
+		};
+
+		manifest.crypto.check().map_err(fail(step))?;
+		let decoded: Manifest =
+			borsh::from_slice(&borsh::to_vec(&manifest).map_err(fail(step))?)
+				.map_err(fail(step))?;
+		if decoded != manifest {
+			return Err(invalid(step, "manifest does not round trip"));
+		}
+
+		Ok(manifest)
+	}
+
+	fn approve(
+		&self,
+		members: &[SimulatedMember],
+		manifest: Manifest,
+	) -> Result<ManifestEnvelope, CeremonyError> {
+		let step = CeremonyStep::Approval;
+		let manifest_hash = manifest.qos_hash();
+
+		let manifest_set_approvals = members
+			.iter()
+			.take(self.threshold as usize)
+			.map(|member| {
+				let approval = approve(member, &manifest_hash, step)?;
+				P256Public::from_bytes(&approval.member.pub_key)
+					.and_then(|public| {
+						public.verify(&manifest_hash, &approval.signature)
+					})
+					.map_err(fail(step))?;
+				Ok(approval)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let manifest_envelope = ManifestEnvelope {
+			manifest,
+			manifest_set_approvals,
+			share_set_approvals: vec![],
+		};
+		manifest_envelope.check_approvals().map_err(fail(step))?;
+
+		Ok(manifest_envelope)
+	}
+
+	fn boot(
+		&self,
+		enclave: &LocalEnclave,
+		manifest_envelope: &ManifestEnvelope,
+	) -> Result<(), CeremonyError> {
+		let step = CeremonyStep::Boot;
+
+		expect_phase(enclave, step, ProtocolPhase::WaitingForBootInstruction)?;
+		let request = ProtocolMsg::BootStandardRequest {
+			manifest_envelope: Box::new(manifest_envelope.clone()),
+			pivot: self.pivot.clone(),
+		};
+		let ProtocolMsg::BootStandardResponse { nsm_response } =
+			request_ok(enclave, step, &request)?
+		else {
+			return Err(invalid(step, "unexpected response to boot"));
+		};
+		check_attestation(
+			step,
+			&nsm_response,
+			Some(&manifest_envelope.manifest.enclave),
+		)?;
+		expect_phase(enclave, step, ProtocolPhase::WaitingForQuorumShards)?;
+
+		if get_manifest_envelope(enclave, step)? != *manifest_envelope {
+			return Err(invalid(step, "enclave booted a different manifest"));
+		}
+
+		Ok(())
+	}
+
+	fn provision(
+		&self,
+		enclave: &LocalEnclave,
+		members: &[SimulatedMember],
+		genesis_output: &GenesisOutput,
+	) -> Result<ManifestEnvelope, CeremonyError> {
+		let step = CeremonyStep::Provision;
+		let manifest_envelope = get_manifest_envelope(enclave, step)?;
+		let manifest_hash = manifest_envelope.manifest.qos_hash();
+
+		let ProtocolMsg::MockEphemeralKeyResponse { ephemeral_public_key } =
+			request_ok(enclave, step, &ProtocolMsg::MockEphemeralKeyRequest)?
+		else {
+			return Err(invalid(step, "unexpected response to ephemeral key"));
+		};
+		let ephemeral_key = P256Public::from_bytes(&ephemeral_public_key)
+			.map_err(fail(step))?;
+
+		// The last members post their shares, so the share set approvals are
+		// not all from the members who approved the manifest
+		let threshold = self.threshold as usize;
+		let posting = &members[members.len() - threshold..];
+		for (i, member) in posting.iter().enumerate() {
+			let share = member
+				.pair
+				.decrypt(
+					&member_output(member, genesis_output)?
+						.encrypted_quorum_key_share,
+				)
+				.map_err(fail(step))?;
+			let request = ProtocolMsg::ProvisionRequest {
+				share: ephemeral_key.encrypt(&share).map_err(fail(step))?,
+				approval: approve(member, &manifest_hash, step)?,
+			};
+			let ProtocolMsg::ProvisionResponse { reconstructed } =
+				request_ok(enclave, step, &request)?
+			else {
+				return Err(invalid(step, "unexpected response to provision"));
+			};
+			if reconstructed != (i + 1 == threshold) {
+				return Err(invalid(
+					step,
+					&format!(
+						"reconstructed was {reconstructed} after {} shares",
+						i + 1
+					),
+				));
+			}
+		}
+		expect_phase(enclave, step, ProtocolPhase::QuorumKeyProvisioned)?;
+
+		let quorum_key =
+			enclave.handles().get_quorum_key().map_err(fail(step))?;
+		if quorum_key.public_key().to_bytes() != genesis_output.quorum_key {
+			return Err(invalid(step, "enclave has a different quorum key"));
+		}
+		let manifest_envelope = get_manifest_envelope(enclave, step)?;
+		if manifest_envelope.share_set_approvals.len() != threshold {
+			return Err(invalid(step, "share set approvals were not recorded"));
+		}
+
+		Ok(manifest_envelope)
+	}
+}
+
+/// App of the genesis enclave, which never pivots.
+struct Unused;
+
+impl RequestProcessor for Unused {
+	fn process(&mut self, _request: Vec<u8>) -> Vec<u8> {
+		vec![]
+	}
+}
+
+fn fail<E: fmt::Debug>(step: CeremonyStep) -> impl Fn(E) -> CeremonyError {
+	move |e| CeremonyError { step, reason: format!("{e:?}") }
+}
+
+fn invalid(step: CeremonyStep, reason: &str) -> CeremonyError {
+	CeremonyError { step, reason: reason.to_string() }
+}
+
+/// Send `request`, failing on errors and error responses.
+fn request_ok(
+	enclave: &LocalEnclave,
+	step: CeremonyStep,
+	request: &ProtocolMsg,
+) -> Result<ProtocolMsg, CeremonyError> {
+	match enclave.request(request).map_err(fail(step))? {
+		ProtocolMsg::ProtocolErrorResponse(e) => Err(fail(step)(e)),
+		response => Ok(response),
+	}
+}
+
+fn expect_phase(
+	enclave: &LocalEnclave,
+	step: CeremonyStep,
+	expected: ProtocolPhase,
+) -> Result<(), CeremonyError> {
+	match request_ok(enclave, step, &ProtocolMsg::StatusRequest)? {
+		ProtocolMsg::StatusResponse(phase) if phase == expected => Ok(()),
+		response => Err(invalid(
+			step,
+			&format!("expected phase {expected:?}, got {response:?}"),
+		)),
+	}
+}
+
+fn get_manifest_envelope(
+	enclave: &LocalEnclave,
+	step: CeremonyStep,
+) -> Result<ManifestEnvelope, CeremonyError> {
+	match request_ok(enclave, step, &ProtocolMsg::ManifestEnvelopeRequest)? {
+		ProtocolMsg::ManifestEnvelopeResponse { manifest_envelope } => {
+			manifest_envelope.ok_or_else(|| {
+				invalid(step, "enclave has no manifest envelope")
+			})
+		}
+		_ => Err(invalid(step, "unexpected response to manifest envelope")),
+	}
+}
+
+/// Check the attestation doc in `nsm_response` against the AWS root
+/// certificate and, if given, the PCRs of `enclave`.
+fn check_attestation(
+	step: CeremonyStep,
+	nsm_response: &NsmResponse,
+	enclave: Option<&NitroConfig>,
+) -> Result<(), CeremonyError> {
+	let NsmResponse::Attestation { document } = nsm_response else {
+		return Err(invalid(step, "expected an attestation doc"));
+	};
+	let root_cert = aws_root_cert().map_err(fail(step))?;
+	let doc = attestation_doc_from_der(
+		document,
+		&root_cert,
+		MOCK_SECONDS_SINCE_EPOCH,
+	)
+	.map_err(fail(step))?;
+
+	if let Some(enclave) = enclave {
+		let pcrs = [&enclave.pcr0, &enclave.pcr1, &enclave.pcr2, &enclave.pcr3];
+		for (index, expected) in pcrs.into_iter().enumerate() {
+			if doc.pcrs.get(&index).map(|pcr| pcr.as_slice())
+				!= Some(expected.as_slice())
+			{
+				return Err(invalid(step, &format!("PCR{index} mismatch")));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn member_output<'a>(
+	member: &SimulatedMember,
+	genesis_output: &'a GenesisOutput,
+) -> Result<&'a GenesisMemberOutput, CeremonyError> {
+	genesis_output
+		.member_outputs
+		.iter()
+		.find(|output| output.share_set_member == member.quorum_member())
+		.ok_or_else(|| invalid(CeremonyStep::Provision, "member has no share"))
+}
+
+/// Decrypt and open the share of `member`, as after genesis.
+fn member_share(
+	member: &SimulatedMember,
+	genesis_output: &GenesisOutput,
+) -> Result<Vec<u8>, CeremonyError> {
+	let step = CeremonyStep::Genesis;
+	let output = member_output(member, genesis_output)?;
+	let share = member
+		.pair
+		.decrypt(&output.encrypted_quorum_key_share)
+		.map_err(fail(step))?;
+	open_share(&share, &genesis_output.quorum_key, genesis_output.threshold)
+		.map_err(fail(step))
+}
+
+fn approve(
+	member: &SimulatedMember,
+	manifest_hash: &[u8],
+	step: CeremonyStep,
+) -> Result<Approval, CeremonyError> {
+	Ok(Approval {
+		signature: member.pair.sign(manifest_hash).map_err(fail(step))?,
+		member: member.quorum_member(),
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	struct Echo;
+	impl RequestProcessor for Echo {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[test]
+	fn ceremonies_provision_enclaves_serving_the_app() {
+		let output =
+			Ceremony::new(4, 3).with_namespace("test").run(Echo).unwrap();
+
+		assert_eq!(output.members.len(), 4);
+		assert_eq!(output.manifest_envelope.manifest.namespace.name, "test");
+		assert_eq!(output.manifest_envelope.manifest_set_approvals.len(), 3);
+		assert_eq!(output.manifest_envelope.share_set_approvals.len(), 3);
+		assert_eq!(
+			output
+				.enclave
+				.request(&ProtocolMsg::ProxyRequest { data: b"hi".to_vec() })
+				.unwrap(),
+			ProtocolMsg::ProxyResponse { data: b"hi".to_vec() }
+		);
+	}
+
+	#[test]
+	fn every_member_can_be_needed() {
+		let output = Ceremony::new(2, 2).run(Echo).unwrap();
+		assert_eq!(output.genesis_output.threshold, 2);
+	}
+
+	#[test]
+	fn invalid_thresholds_are_refused() {
+		for threshold in [1, 3] {
+			let error = Ceremony::new(2, threshold).run(Echo).err().unwrap();
+			assert_eq!(error.step, CeremonyStep::Genesis);
+		}
+	}
+}
@@ -0,0 +1,223 @@
+//! Child processes for tests, see [`ChildWrapper`].
+
+use std::{
+	io::{self, BufRead, BufReader, Read},
+	net::TcpStream,
+	os::unix::net::UnixStream,
+	path::Path,
+	process::{Child, Command, Stdio},
+	sync::{Arc, Mutex, PoisonError},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// How long [`ChildWrapper::wait_until_ready`] waits.
+pub const READY_TIMEOUT: Duration = Duration::from_secs(90);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Something to wait for, see [`ChildWrapper::wait_until_ready`].
+#[derive(Debug, Clone, Copy)]
+pub enum Ready<'a> {
+	/// A TCP server is accepting connections on this port of localhost.
+	PortBound(u16),
+	/// A unix socket server is accepting connections at this path.
+	SocketBound(&'a str),
+	/// A file exists at this path.
+	FileExists(&'a str),
+	/// A line the child wrote to its captured stdout or stderr contains this
+	/// text.
+	LogLine(&'a str),
+}
+
+/// Wrapper type for [`std::process::Child`] that kills the process on drop.
+///
+/// The stdout and stderr of the child are captured if they were piped when it
+/// was spawned, as [`Self::spawn_captured`] does.
+#[derive(Debug)]
+pub struct ChildWrapper {
+	child: Child,
+	stdout: Captured,
+	stderr: Captured,
+}
+
+impl From<Child> for ChildWrapper {
+	fn from(mut child: Child) -> Self {
+		let stdout = Captured::default();
+		let stderr = Captured::default();
+		if let Some(out) = child.stdout.take() {
+			stdout.capture(out);
+		}
+		if let Some(err) = child.stderr.take() {
+			stderr.capture(err);
+		}
+
+		Self { child, stdout, stderr }
+	}
+}
+
+impl Drop for ChildWrapper {
+	fn drop(&mut self) {
+		// Kill the process and explicitly ignore the result
+		drop(self.child.kill());
+	}
+}
+
+impl ChildWrapper {
+	/// Spawn `command` with its stdout and stderr captured.
+	pub fn spawn_captured(command: &mut Command) -> io::Result<Self> {
+		command
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.map(Into::into)
+	}
+
+	/// Everything the child wrote to its stdout so far, if captured.
+	#[must_use]
+	pub fn stdout(&self) -> String {
+		self.stdout.contents()
+	}
+
+	/// Everything the child wrote to its stderr so far, if captured.
+	#[must_use]
+	pub fn stderr(&self) -> String {
+		self.stderr.contents()
+	}
+
+	/// Wait until `ready`, for at most [`READY_TIMEOUT`]. Use this instead of
+	/// sleeping for a fixed time, which flakes when the machine is loaded.
+	///
+	/// # Panics
+	///
+	/// Panics if the child exits or the timeout passes first. The panic
+	/// message includes the captured output of the child.
+	pub fn wait_until_ready(&mut self, ready: Ready<'_>) {
+		self.wait_until_ready_within(ready, READY_TIMEOUT);
+	}
+
+	/// Like [`Self::wait_until_ready`], but waits at most `timeout`.
+	///
+	/// # Panics
+	///
+	/// Panics if the child exits or the timeout passes first.
+	pub fn wait_until_ready_within(
+		&mut self,
+		ready: Ready<'_>,
+		timeout: Duration,
+	) {
+		let start = Instant::now();
+		loop {
+			if self.is_ready(ready) {
+				return;
+			}
+
+			let reason = match self.child.try_wait() {
+				Ok(Some(status)) => format!("exited with {status}"),
+				Err(e) => format!("could not be waited on: {e}"),
+				Ok(None) if start.elapsed() >= timeout => {
+					format!("was not ready after {}s", timeout.as_secs())
+				}
+				Ok(None) => {
+					thread::sleep(READY_POLL_INTERVAL);
+					continue;
+				}
+			};
+			panic!(
+				"Child {} waiting for {ready:?}\nstdout:\n{}\nstderr:\n{}",
+				reason,
+				self.stdout(),
+				self.stderr()
+			);
+		}
+	}
+
+	fn is_ready(&self, ready: Ready<'_>) -> bool {
+		match ready {
+			Ready::PortBound(port) => {
+				TcpStream::connect(("127.0.0.1", port)).is_ok()
+			}
+			Ready::SocketBound(path) => UnixStream::connect(path).is_ok(),
+			Ready::FileExists(path) => Path::new(path).exists(),
+			Ready::LogLine(text) => [&self.stdout, &self.stderr]
+				.iter()
+				.any(|captured| captured.has_line_containing(text)),
+		}
+	}
+}
+
+/// Output of a child, read on a background thread until the child closes it.
+#[derive(Debug, Clone, Default)]
+struct Captured(Arc<Mutex<String>>);
+
+impl Captured {
+	fn capture(&self, output: impl Read + Send + 'static) {
+		let captured = self.clone();
+		thread::spawn(move || {
+			let mut output = BufReader::new(output);
+			let mut line = Vec::new();
+			while output.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+				captured
+					.0
+					.lock()
+					.unwrap_or_else(PoisonError::into_inner)
+					.push_str(&String::from_utf8_lossy(&line));
+				line.clear();
+			}
+		});
+	}
+
+	fn contents(&self) -> String {
+		self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+	}
+
+	fn has_line_containing(&self, text: &str) -> bool {
+		self.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.lines()
+			.any(|line| line.contains(text))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn sh(script: &str) -> ChildWrapper {
+		ChildWrapper::spawn_captured(Command::new("sh").args(["-c", script]))
+			.unwrap()
+	}
+
+	#[test]
+	fn waits_for_log_lines_and_files() {
+		let file = std::env::temp_dir()
+			.join(format!("qos_child_wrapper_{}", std::process::id()));
+		let file = file.to_str().unwrap();
+		let mut child = sh(&format!(
+			"echo starting; sleep 0.2; echo ready >&2; touch {file}; sleep 10"
+		));
+
+		child.wait_until_ready(Ready::LogLine("ready"));
+		child.wait_until_ready(Ready::FileExists(file));
+		assert_eq!(child.stdout(), "starting\n");
+		assert_eq!(child.stderr(), "ready\n");
+		std::fs::remove_file(file).unwrap();
+	}
+
+	#[test]
+	#[should_panic(expected = "exited with exit status: 3")]
+	fn panics_with_the_output_if_the_child_exits() {
+		let mut child = sh("echo oops; exit 3");
+		child.wait_until_ready(Ready::LogLine("never"));
+	}
+
+	#[test]
+	#[should_panic(expected = "was not ready after 0s")]
+	fn panics_after_the_timeout() {
+		let mut child = sh("sleep 10");
+		child.wait_until_ready_within(
+			Ready::PortBound(1),
+			Duration::from_millis(100),
+		);
+	}
+}
@@ -9,29 +9,26 @@ use std::{
 
 use rand::prelude::*;
 
+mod child;
+pub use child::{ChildWrapper, Ready, READY_TIMEOUT};
+
+#[cfg(feature = "enclave")]
+mod ceremony;
+#[cfg(feature = "enclave")]
+pub use ceremony::{
+	Ceremony, CeremonyError, CeremonyOutput, CeremonyStep, SimulatedMember,
+};
+#[cfg(feature = "enclave")]
+mod local_enclave;
+#[cfg(feature = "enclave")]
+pub use local_enclave::LocalEnclave;
+
 const MAX_PORT_BIND_WAIT_TIME: Duration = Duration::from_secs(90);
 const PORT_BIND_WAIT_TIME_INCREMENT: Duration = Duration::from_millis(500);
 const POST_BIND_SLEEP: Duration = Duration::from_millis(500);
 const SERVER_PORT_RANGE: Range<u16> = 10000..60000;
 const MAX_PORT_SEARCH_ATTEMPTS: u16 = 50;
 
-/// Wrapper type for [`std::process::Child`] that kills the process on drop.
-#[derive(Debug)]
-pub struct ChildWrapper(std::process::Child);
-
-impl From<std::process::Child> for ChildWrapper {
-	fn from(child: std::process::Child) -> Self {
-		Self(child)
-	}
-}
-
-impl Drop for ChildWrapper {
-	fn drop(&mut self) {
-		// Kill the process and explicitly ignore the result
-		drop(self.0.kill());
-	}
-}
-
 #[derive(Debug)]
 enum Internal<'a> {
 	String(String),
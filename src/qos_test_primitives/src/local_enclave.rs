@@ -0,0 +1,250 @@
+//! In process enclave, see [`LocalEnclave`].
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use borsh::BorshDeserialize;
+use qos_core::{
+	client::{Client, ClientError},
+	executor::ExecutorProcessor,
+	handles::Handles,
+	io::{memory, SocketAddress, TimeVal, TimeValLike},
+	protocol::{
+		msg::ProtocolMsg, services::boot::ManifestEnvelope, Processor,
+		ProtocolPhase, ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS,
+	},
+	server::{RequestProcessor, SocketServer},
+};
+use qos_nsm::mock::MockNsm;
+use qos_p256::P256Pair;
+
+use crate::PathWrapper;
+
+/// Distinguishes the state directories of enclaves in the same process.
+static ENCLAVE_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// A QOS enclave running in the test process: the protocol executor and the
+/// executor the pivot talks to, both with a mock NSM, and the pivot itself
+/// as any [`RequestProcessor`]. Each runs on a background thread and is
+/// reached over in memory connections, so tests need no child processes,
+/// sockets or sleeps.
+///
+/// The enclave state, such as the quorum key and manifest, is kept in a
+/// temporary directory that is removed when the enclave is dropped.
+///
+/// ```
+/// use qos_core::protocol::{msg::ProtocolMsg, ProtocolPhase};
+/// use qos_test_primitives::LocalEnclave;
+///
+/// struct Echo;
+/// impl qos_core::server::RequestProcessor for Echo {
+///     fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+///         request
+///     }
+/// }
+///
+/// let enclave = LocalEnclave::new(Echo);
+/// assert_eq!(
+///     enclave.request(&ProtocolMsg::StatusRequest).unwrap(),
+///     ProtocolMsg::StatusResponse(ProtocolPhase::WaitingForBootInstruction)
+/// );
+/// ```
+pub struct LocalEnclave {
+	client: Client,
+	executor_client: Client,
+	handles: Handles,
+	_state_dir: PathWrapper<'static>,
+}
+
+impl LocalEnclave {
+	/// Start an enclave waiting for its boot instruction, with `app` as its
+	/// pivot.
+	///
+	/// # Panics
+	///
+	/// If the temporary state directory cannot be created.
+	pub fn new<A>(app: A) -> Self
+	where
+		A: RequestProcessor + Send + 'static,
+	{
+		let (state_dir, handles) = state();
+		Self::start(app, state_dir, handles, None)
+	}
+
+	/// Start an enclave that is already provisioned with a fresh quorum key
+	/// and booted with `manifest_envelope`, with `app` as its pivot.
+	///
+	/// # Panics
+	///
+	/// If the enclave state cannot be written to the temporary directory.
+	pub fn provisioned<A>(app: A, manifest_envelope: &ManifestEnvelope) -> Self
+	where
+		A: RequestProcessor + Send + 'static,
+	{
+		let (state_dir, handles) = state();
+		handles
+			.put_quorum_key(
+				&P256Pair::generate().expect("Failed to generate quorum key"),
+			)
+			.expect("Failed to put the quorum key");
+		handles
+			.put_manifest_envelope(manifest_envelope)
+			.expect("Failed to put the manifest envelope");
+		handles
+			.put_pivot_info(&manifest_envelope.manifest)
+			.expect("Failed to put the pivot info");
+		handles
+			.put_app_config(&manifest_envelope.manifest)
+			.expect("Failed to put the app config");
+
+		Self::start(
+			app,
+			state_dir,
+			handles,
+			Some(ProtocolPhase::QuorumKeyProvisioned),
+		)
+	}
+
+	fn start<A>(
+		app: A,
+		state_dir: PathWrapper<'static>,
+		handles: Handles,
+		phase: Option<ProtocolPhase>,
+	) -> Self
+	where
+		A: RequestProcessor + Send + 'static,
+	{
+		let timeout = TimeVal::seconds(ENCLAVE_APP_SOCKET_CLIENT_TIMEOUT_SECS);
+		let (app_listener, app_connector) = memory::listener();
+		let (enclave_listener, enclave_connector) = memory::listener();
+		let (executor_listener, executor_connector) = memory::listener();
+
+		let processor = Processor::new(
+			Box::new(MockNsm),
+			handles.clone(),
+			// Unused, requests reach the app through `app_connector`
+			SocketAddress::new_unix(&format!("{}/app.sock", &*state_dir)),
+			phase,
+		)
+		.with_app_client(Client::in_memory(app_connector, timeout));
+		let executor =
+			ExecutorProcessor::new(Box::new(MockNsm), handles.clone());
+
+		// Each server returns once the last connector to it is dropped: the
+		// enclave and executor when `Self` is, and then the app with the
+		// enclave.
+		std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&app_listener, app);
+		});
+		std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&enclave_listener, processor);
+		});
+		std::thread::spawn(move || {
+			SocketServer::listen_in_memory(&executor_listener, executor);
+		});
+
+		Self {
+			client: Client::in_memory(enclave_connector, timeout),
+			executor_client: Client::in_memory(executor_connector, timeout),
+			handles,
+			_state_dir: state_dir,
+		}
+	}
+
+	/// Send `request` to the enclave, like the host does, and decode the
+	/// response.
+	pub fn request(
+		&self,
+		request: &ProtocolMsg,
+	) -> Result<ProtocolMsg, ClientError> {
+		let response = self.client.send(&borsh::to_vec(request)?)?;
+		Ok(ProtocolMsg::try_from_slice(&response)?)
+	}
+
+	/// Client for sending raw requests to the enclave, in place of the host.
+	#[must_use]
+	pub fn client(&self) -> &Client {
+		&self.client
+	}
+
+	/// Client for sending [`qos_core::executor::ExecutorMsg`]s to the
+	/// executor, in place of the pivot.
+	#[must_use]
+	pub fn executor_client(&self) -> &Client {
+		&self.executor_client
+	}
+
+	/// Handles to the state of the enclave.
+	#[must_use]
+	pub fn handles(&self) -> &Handles {
+		&self.handles
+	}
+}
+
+/// Create a temporary state directory and handles to the files in it.
+fn state() -> (PathWrapper<'static>, Handles) {
+	let state_dir = std::env::temp_dir().join(format!(
+		"qos_local_enclave_{}_{}",
+		std::process::id(),
+		ENCLAVE_COUNT.fetch_add(1, Ordering::SeqCst)
+	));
+	std::fs::create_dir_all(&state_dir)
+		.expect("Failed to create the enclave state directory");
+
+	let path = |file: &str| state_dir.join(file).display().to_string();
+	let handles = Handles::new(
+		path("qos.ephemeral.key"),
+		path("qos.quorum.key"),
+		path("qos.manifest"),
+		path("qos.pivot.bin"),
+		path("qos.pivot.info"),
+	);
+
+	(state_dir.display().to_string().into(), handles)
+}
+
+#[cfg(test)]
+mod test {
+	use qos_core::executor::ExecutorMsg;
+
+	use super::*;
+
+	struct Echo;
+	impl RequestProcessor for Echo {
+		fn process(&mut self, request: Vec<u8>) -> Vec<u8> {
+			request
+		}
+	}
+
+	#[test]
+	fn provisioned_enclaves_serve_the_pivot_and_executor() {
+		let enclave =
+			LocalEnclave::provisioned(Echo, &ManifestEnvelope::default());
+
+		assert_eq!(
+			enclave
+				.request(&ProtocolMsg::ProxyRequest { data: b"hi".to_vec() })
+				.unwrap(),
+			ProtocolMsg::ProxyResponse { data: b"hi".to_vec() }
+		);
+		assert!(enclave.handles().quorum_key_exists());
+
+		let request =
+			borsh::to_vec(&ExecutorMsg::AppSecretRequest { name: "a".into() })
+				.unwrap();
+		let response = enclave.executor_client().send(&request).unwrap();
+		assert!(matches!(
+			ExecutorMsg::try_from_slice(&response).unwrap(),
+			ExecutorMsg::AppSecretResponse { .. }
+		));
+	}
+
+	#[test]
+	fn state_is_removed_on_drop() {
+		let enclave = LocalEnclave::new(Echo);
+		let state_dir = enclave._state_dir.to_string();
+		assert!(std::path::Path::new(&state_dir).exists());
+
+		drop(enclave);
+		assert!(!std::path::Path::new(&state_dir).exists());
+	}
+}